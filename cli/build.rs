@@ -53,13 +53,55 @@ fn main() {
   .expect("Bundle compilation failed");
   assert!(bundle_path.exists());
 
+  // Embedders add runtime layers of their own to the snapshot with the
+  // JS and TS files listed in DENO_SNAPSHOT_EXTENSIONS, which run after the
+  // runtime's modules are instantiated, in order, and before its bootstrap.
+  // Their ops are only registered once the isolates are created, so they
+  // look up the ids with `Deno.core.ops()` when they're called.
+  println!("cargo:rerun-if-env-changed=DENO_SNAPSHOT_EXTENSIONS");
+  let extension_paths: Vec<PathBuf> = env::var_os("DENO_SNAPSHOT_EXTENSIONS")
+    .map(|paths| env::split_paths(&paths).collect())
+    .unwrap_or_default();
+  if !extension_paths.is_empty() {
+    // A rerun-if-changed replaces Cargo's default of rerunning when any file
+    // of the package changes, so the sources of the runtime are listed too.
+    // Only the extensions themselves are watched, not what they import.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", c.join("js").display());
+    for path in extern_crate_modules.values() {
+      println!("cargo:rerun-if-changed={}", path);
+    }
+    for path in &extension_paths {
+      println!("cargo:rerun-if-changed={}", path.display());
+    }
+  }
+  let extensions: Vec<deno_typescript::Extension> = extension_paths
+    .into_iter()
+    .enumerate()
+    .map(|(i, path)| {
+      if path.extension().map_or(false, |ext| ext == "ts") {
+        let bundle_path = o.join(format!("CLI_SNAPSHOT_EXTENSION_{}.js", i));
+        let main_module_name = deno_typescript::compile_bundle(
+          &bundle_path,
+          vec![path],
+          Some(extern_crate_modules.clone()),
+        )
+        .expect("Bundle compilation failed");
+        deno_typescript::Extension::Bundle(bundle_path, main_module_name)
+      } else {
+        deno_typescript::Extension::Script(path)
+      }
+    })
+    .collect();
+
   let mut runtime_isolate = CoreIsolate::new(StartupData::None, true);
 
-  deno_typescript::mksnapshot_bundle(
+  deno_typescript::mksnapshot_bundle_with_extensions(
     &mut runtime_isolate,
     &snapshot_path,
     &bundle_path,
     &main_module_name,
+    &extensions,
   )
   .expect("Failed to create snapshot");
 
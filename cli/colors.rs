@@ -1,8 +1,15 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! All colored output of the CLI goes through this module, so that it is
+//! turned off in one place: when `NO_COLOR` is set, when `--no-color` is
+//! given, or when stdout or stderr isn't a terminal.
+
 use regex::Regex;
 use std::env;
 use std::fmt;
 use std::io::Write;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use termcolor::Color::{Ansi256, Black, Magenta, Red, White};
 use termcolor::{Ansi, ColorSpec, WriteColor};
 
@@ -18,15 +25,34 @@ lazy_static! {
         static ref NO_COLOR: bool = {
                 env::var_os("NO_COLOR").is_some()
         };
+        static ref IS_TTY: bool = {
+                atty::is(atty::Stream::Stdout) && atty::is(atty::Stream::Stderr)
+        };
 }
 
+static NO_COLOR_FLAG: AtomicBool = AtomicBool::new(false);
+
 /// Helper function to strip ansi codes.
 pub fn strip_ansi_codes(s: &str) -> std::borrow::Cow<str> {
   STRIP_ANSI_RE.replace_all(s, "")
 }
 
+/// Turns colors off for the rest of the process, for `--no-color`.
+pub fn disable() {
+  NO_COLOR_FLAG.store(true, Ordering::SeqCst);
+}
+
+/// Whether the user asked for no colors, with `NO_COLOR` or `--no-color`.
+///
+/// This is what `Deno.noColor` reflects; unlike `use_color()` it doesn't
+/// depend on where the output goes, which scripts can check on their own.
+pub fn no_color() -> bool {
+  *NO_COLOR || NO_COLOR_FLAG.load(Ordering::SeqCst)
+}
+
+/// Whether the output of the CLI itself should be colored.
 pub fn use_color() -> bool {
-  !(*NO_COLOR)
+  !no_color() && *IS_TTY
 }
 
 #[cfg(windows)]
@@ -0,0 +1,359 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Discovery and parsing of the `deno.json` project config file.
+//!
+//! The file is looked up in the current directory and its ancestors before
+//! any subcommand runs. Its settings only fill in what wasn't given on the
+//! command line, so flags always take precedence:
+//!
+//!   {
+//!     "compilerOptions": { "strict": true },
+//!     "importMap": "./import_map.json",
+//!     "lint": { "include": ["src/"], "exclude": ["src/gen/"],
+//!               "rules": { "exclude": ["no-explicit-any"] } },
+//!     "fmt": { "include": ["src/"], "exclude": ["src/gen/"] },
+//!     "tasks": { "start": "deno run --allow-net server.ts" }
+//!   }
+
+use crate::flags::DenoSubcommand;
+use crate::flags::Flags;
+use crate::op_error::OpError;
+use deno_core::ErrBox;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+pub const CONFIG_FILE_NAME: &str = "deno.json";
+
+/// Paths a tool operates on, relative to the directory of the config file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+  pub include: Vec<String>,
+  pub exclude: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintRulesConfig {
+  pub exclude: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+  pub include: Vec<String>,
+  pub exclude: Vec<String>,
+  pub rules: LintRulesConfig,
+}
+
+impl LintConfig {
+  pub fn files(&self) -> FilesConfig {
+    FilesConfig {
+      include: self.include.clone(),
+      exclude: self.exclude.clone(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ConfigFile {
+  /// Absolute path of the file itself.
+  #[serde(skip)]
+  pub path: PathBuf,
+  pub compiler_options: Option<serde_json::Value>,
+  pub import_map: Option<String>,
+  pub lint: LintConfig,
+  pub fmt: FilesConfig,
+  /// Commands run by `deno task`, by name.
+  pub tasks: BTreeMap<String, String>,
+}
+
+impl ConfigFile {
+  /// Reads and parses the config file at `path`.
+  pub fn read(path: &Path) -> Result<Self, ErrBox> {
+    let path = path.canonicalize().map_err(|e| {
+      OpError::other(format!(
+        "Unable to read config file \"{}\": {}",
+        path.display(),
+        e
+      ))
+    })?;
+    let text = fs::read_to_string(&path).map_err(|e| {
+      OpError::other(format!(
+        "Unable to read config file \"{}\": {}",
+        path.display(),
+        e
+      ))
+    })?;
+    Self::parse(&text, path)
+  }
+
+  fn parse(text: &str, path: PathBuf) -> Result<Self, ErrBox> {
+    let parse_error = |msg: String| -> ErrBox {
+      OpError::other(format!(
+        "Unable to parse config file \"{}\": {}",
+        path.display(),
+        msg
+      ))
+      .into()
+    };
+    // Like `tsconfig.json`, the file may contain comments.
+    let text = crate::jsonc::strip_json_comments(text).map_err(parse_error)?;
+    let mut config: Self =
+      serde_json::from_str(&text).map_err(|e| parse_error(e.to_string()))?;
+    if let Some(options) = &config.compiler_options {
+      if !options.is_object() {
+        return Err(
+          OpError::other(format!(
+            "\"compilerOptions\" in \"{}\" must be an object",
+            path.display()
+          ))
+          .into(),
+        );
+      }
+    }
+    config.path = path;
+    Ok(config)
+  }
+
+  /// Finds the nearest `deno.json` in `start` or one of its ancestors.
+  ///
+  /// The search stops at the first file found: when it can't be read or
+  /// parsed, its error is returned rather than falling back to the files of
+  /// the directories above.
+  pub fn discover(start: &Path) -> Result<Option<Self>, ErrBox> {
+    let maybe_path = start
+      .ancestors()
+      .map(|dir| dir.join(CONFIG_FILE_NAME))
+      .find(|candidate| candidate.is_file());
+    match maybe_path {
+      Some(path) => {
+        debug!("Project config file: {}", path.display());
+        Self::read(&path).map(Some)
+      }
+      None => Ok(None),
+    }
+  }
+
+  /// Directory that relative paths in the file are resolved against.
+  pub fn dir(&self) -> &Path {
+    self.path.parent().unwrap()
+  }
+
+  pub fn resolve_path(&self, path: &str) -> PathBuf {
+    self.dir().join(path)
+  }
+
+  /// Fills in the flags that weren't passed on the command line.
+  ///
+  /// The file itself is handed to the compiler as its config; like with a
+  /// `tsconfig.json`, only `compilerOptions` is read from it.
+  pub fn apply_to_flags(&self, flags: &mut Flags) {
+    if flags.config_path.is_none() && self.compiler_options.is_some() {
+      flags.config_path = Some(self.path.to_string_lossy().to_string());
+    }
+    if flags.import_map_path.is_none() {
+      if let Some(import_map) = &self.import_map {
+        flags.import_map_path =
+          Some(self.resolve_path(import_map).to_string_lossy().to_string());
+      }
+    }
+  }
+
+  /// Expands `files` into the supported files it covers.
+  ///
+  /// Without `include` the whole directory of the config file is walked.
+  pub fn collect_files<F>(&self, files: &FilesConfig, filter: F) -> Vec<PathBuf>
+  where
+    F: Fn(&Path) -> bool,
+  {
+    let include = if files.include.is_empty() {
+      vec![self.dir().to_path_buf()]
+    } else {
+      files.include.iter().map(|p| self.resolve_path(p)).collect()
+    };
+    let exclude: Vec<PathBuf> =
+      files.exclude.iter().map(|p| self.resolve_path(p)).collect();
+
+    let mut target_files = vec![];
+    for path in include {
+      if path.is_dir() {
+        target_files.extend(crate::fs::files_in_subtree(path, &filter));
+      } else if path.is_file() {
+        target_files.push(path);
+      }
+    }
+    target_files.retain(|p| !exclude.iter().any(|e| p.starts_with(e)));
+    target_files.sort();
+    target_files.dedup();
+    target_files
+  }
+}
+
+/// Whether `subcommand` reads the project config file. The others run
+/// without looking it up, so that a broken file doesn't get in their way.
+pub fn is_used_by(subcommand: &DenoSubcommand) -> bool {
+  use DenoSubcommand::*;
+  match subcommand {
+    Bench { .. }
+    | Bundle { .. }
+    | Cache { .. }
+    | Doc { .. }
+    | Eval { .. }
+    | Fmt { .. }
+    | Info { .. }
+    | Lint { .. }
+    | Repl { .. }
+    | Run { .. }
+    | Task { .. }
+    | Test { .. }
+    | Xeval { .. } => true,
+    Completions { .. }
+    | Coverage { .. }
+    | Help
+    | Install { .. }
+    | Uninstall { .. }
+    | Types
+    | Upgrade { .. } => false,
+  }
+}
+
+/// Looks up the project config file for the current directory and applies it
+/// to `flags`.
+pub fn resolve(flags: &mut Flags) -> Result<Option<ConfigFile>, ErrBox> {
+  let cwd = std::env::current_dir()?;
+  let maybe_config_file = ConfigFile::discover(&cwd)?;
+  if let Some(config_file) = &maybe_config_file {
+    config_file.apply_to_flags(flags);
+  }
+  Ok(maybe_config_file)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(text: &str) -> Result<ConfigFile, ErrBox> {
+    ConfigFile::parse(text, PathBuf::from("/project/deno.json"))
+  }
+
+  #[test]
+  fn parse_full() {
+    let config = parse(
+      r#"{
+        // Comments are allowed.
+        "compilerOptions": { "strict": true },
+        "importMap": "./import_map.json",
+        "lint": {
+          "include": ["src/"],
+          "rules": { "exclude": ["no-var"] }
+        },
+        "fmt": { "exclude": ["src/gen/"] },
+        "tasks": { "start": "deno run main.ts" }
+      }"#,
+    )
+    .unwrap();
+    assert_eq!(config.path, PathBuf::from("/project/deno.json"));
+    assert_eq!(
+      config.compiler_options,
+      Some(serde_json::json!({ "strict": true }))
+    );
+    assert_eq!(config.import_map, Some("./import_map.json".to_string()));
+    assert_eq!(config.lint.include, vec!["src/".to_string()]);
+    assert_eq!(config.lint.rules.exclude, vec!["no-var".to_string()]);
+    assert_eq!(config.fmt.exclude, vec!["src/gen/".to_string()]);
+    assert!(config.fmt.include.is_empty());
+    assert_eq!(
+      config.tasks.get("start"),
+      Some(&"deno run main.ts".to_string())
+    );
+  }
+
+  #[test]
+  fn parse_errors() {
+    let err = parse("{").unwrap_err().to_string();
+    assert!(err.contains("/project/deno.json"), "{}", err);
+    assert!(parse(r#"{ "compilerOptions": [] }"#).is_err());
+    assert!(parse(r#"{ "fmt": { "files": [] } }"#).is_err());
+  }
+
+  #[test]
+  fn flags_take_precedence() {
+    let config =
+      parse(r#"{ "compilerOptions": {}, "importMap": "import_map.json" }"#)
+        .unwrap();
+
+    let mut flags = Flags::default();
+    config.apply_to_flags(&mut flags);
+    assert_eq!(flags.config_path, Some("/project/deno.json".to_string()));
+    assert_eq!(
+      flags.import_map_path,
+      Some(
+        PathBuf::from("/project/import_map.json")
+          .to_string_lossy()
+          .to_string()
+      )
+    );
+
+    let mut flags = Flags {
+      config_path: Some("tsconfig.json".to_string()),
+      import_map_path: Some("other.json".to_string()),
+      ..Flags::default()
+    };
+    config.apply_to_flags(&mut flags);
+    assert_eq!(flags.config_path, Some("tsconfig.json".to_string()));
+    assert_eq!(flags.import_map_path, Some("other.json".to_string()));
+  }
+
+  #[test]
+  fn discover_in_ancestors() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let nested = temp_dir.path().join("a").join("b");
+    std::fs::create_dir_all(&nested).unwrap();
+    assert!(ConfigFile::discover(&nested).unwrap().is_none());
+
+    std::fs::write(
+      temp_dir.path().join(CONFIG_FILE_NAME),
+      r#"{ "fmt": { "include": ["a/"], "exclude": ["a/b/"] } }"#,
+    )
+    .unwrap();
+    std::fs::write(temp_dir.path().join("a").join("x.ts"), "").unwrap();
+    std::fs::write(nested.join("y.ts"), "").unwrap();
+    std::fs::write(temp_dir.path().join("z.ts"), "").unwrap();
+
+    let config = ConfigFile::discover(&nested).unwrap().unwrap();
+    assert_eq!(
+      config.dir(),
+      temp_dir.path().canonicalize().unwrap().as_path()
+    );
+    let files = config.collect_files(&config.fmt, |_| true);
+    assert_eq!(files, vec![config.dir().join("a").join("x.ts")]);
+  }
+
+  #[test]
+  fn discover_stops_at_nearest() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let nested = temp_dir.path().join("a");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(temp_dir.path().join(CONFIG_FILE_NAME), "{}").unwrap();
+    std::fs::write(nested.join(CONFIG_FILE_NAME), "{ broken").unwrap();
+
+    // The broken file is reported, not skipped for the one above it.
+    let err = ConfigFile::discover(&nested).unwrap_err().to_string();
+    let path = nested.canonicalize().unwrap().join(CONFIG_FILE_NAME);
+    assert!(err.contains(&path.display().to_string()), "{}", err);
+  }
+
+  #[test]
+  fn only_some_subcommands_use_it() {
+    assert!(is_used_by(&DenoSubcommand::Run {
+      script: "main.ts".to_string()
+    }));
+    assert!(!is_used_by(&DenoSubcommand::Types));
+    assert!(!is_used_by(&DenoSubcommand::Help));
+  }
+}
@@ -0,0 +1,408 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Collects the coverage of the modules run by `deno test --coverage` with
+//! V8's precise coverage, and reports it for `deno coverage`.
+//!
+//! The coverage is written as raw V8 script coverages, along with the
+//! JavaScript source V8 ran and its source map, so that reporting doesn't
+//! depend on what the cache holds by then.
+
+use crate::colors;
+use crate::inspector::DenoInspector;
+use crate::inspector::InProcessSession;
+use crate::source_maps::SourceMapGetter;
+use crate::test_runner;
+use deno_core::ErrBox;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use sourcemap::SourceMap;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use url::Url;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageRange {
+  /// Offsets are in UTF-16 code units, as JavaScript strings.
+  pub start_offset: usize,
+  pub end_offset: usize,
+  pub count: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionCoverage {
+  pub function_name: String,
+  /// The first range covers the whole function, the others are the blocks
+  /// in it whose count differs.
+  pub ranges: Vec<CoverageRange>,
+  pub is_block_coverage: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptCoverage {
+  pub script_id: String,
+  pub url: String,
+  pub functions: Vec<FunctionCoverage>,
+  #[serde(default)]
+  pub source: String,
+  #[serde(default)]
+  pub source_map: Option<String>,
+}
+
+/// Drives the `Profiler` domain of an in-process inspector session.
+pub struct CoverageCollector {
+  session: Box<InProcessSession>,
+}
+
+impl CoverageCollector {
+  /// The collector must be dropped before the inspector.
+  pub fn new(inspector: &mut DenoInspector) -> Self {
+    Self {
+      session: InProcessSession::new(inspector),
+    }
+  }
+
+  pub fn start(&mut self) -> Result<(), ErrBox> {
+    // The debugger gives the sources of the scripts.
+    self.session.post("Debugger.enable", json!({}))?;
+    self.session.post("Profiler.enable", json!({}))?;
+    self.session.post(
+      "Profiler.startPreciseCoverage",
+      json!({ "callCount": true, "detailed": true }),
+    )?;
+    Ok(())
+  }
+
+  /// Takes the coverage of the modules run so far, leaving out the internal
+  /// scripts of deno and the module at `exclude`.
+  pub fn take(
+    &mut self,
+    source_map_getter: &impl SourceMapGetter,
+    exclude: &str,
+  ) -> Result<Vec<ScriptCoverage>, ErrBox> {
+    let mut result = self
+      .session
+      .post("Profiler.takePreciseCoverage", json!({}))?;
+    let scripts: Vec<ScriptCoverage> =
+      serde_json::from_value(result["result"].take())?;
+    let mut collected = vec![];
+    for mut script in scripts {
+      let is_module = ["file:", "http:", "https:"]
+        .iter()
+        .any(|scheme| script.url.starts_with(scheme));
+      if !is_module || script.url == exclude {
+        continue;
+      }
+      let source = self.session.post(
+        "Debugger.getScriptSource",
+        json!({ "scriptId": script.script_id }),
+      )?;
+      script.source = source["scriptSource"].as_str().unwrap_or("").into();
+      script.source_map = source_map_getter
+        .get_source_map(&script.url)
+        .map(|map| String::from_utf8_lossy(&map).into_owned());
+      collected.push(script);
+    }
+    Ok(collected)
+  }
+}
+
+/// Writes the coverage into a new file of `dir`, since several processes may
+/// write into it at the same time.
+pub fn write(dir: &Path, scripts: &[ScriptCoverage]) -> Result<(), ErrBox> {
+  fs::create_dir_all(dir)?;
+  let path = dir.join(format!("{}.json", Uuid::new_v4()));
+  fs::write(path, serde_json::to_vec(scripts)?)?;
+  Ok(())
+}
+
+/// The coverage of a module, by the lines of its original source (1-based).
+#[derive(Default)]
+struct ModuleCoverage {
+  lines: BTreeMap<usize, u64>,
+  /// The call counts of the named functions, by line and name.
+  functions: BTreeMap<(usize, String), u64>,
+}
+
+// Returns the count of the innermost range containing `offset`.
+fn count_at(functions: &[FunctionCoverage], offset: usize) -> Option<u64> {
+  functions
+    .iter()
+    .flat_map(|function| function.ranges.iter())
+    .filter(|range| range.start_offset <= offset && offset < range.end_offset)
+    .min_by_key(|range| range.end_offset - range.start_offset)
+    .map(|range| range.count)
+}
+
+fn module_coverage(script: &ScriptCoverage) -> ModuleCoverage {
+  let source_map = script
+    .source_map
+    .as_ref()
+    .and_then(|map| SourceMap::from_slice(map.as_bytes()).ok());
+  // Maps a line and a column of the source V8 ran to a line of the original.
+  let original_line = |line: usize, column: usize| match &source_map {
+    Some(source_map) => source_map
+      .lookup_token(line as u32, column as u32)
+      .filter(|token| token.get_dst_line() == line as u32)
+      .map(|token| token.get_src_line() as usize + 1),
+    None => Some(line + 1),
+  };
+
+  let mut coverage = ModuleCoverage::default();
+  let mut offset = 0;
+  // The offsets of the starts of the lines, to locate the functions.
+  let mut line_offsets = vec![];
+  for (line, text) in script.source.split('\n').enumerate() {
+    line_offsets.push(offset);
+    let indent = text.len() - text.trim_start().len();
+    let code = text.trim();
+    let code_offset = offset + text[..indent].encode_utf16().count();
+    offset += text.encode_utf16().count() + 1;
+    if code.is_empty() || code.starts_with("//") {
+      continue;
+    }
+    let count = match count_at(&script.functions, code_offset) {
+      Some(count) => count,
+      None => continue,
+    };
+    if let Some(line) = original_line(line, indent) {
+      let line_count = coverage.lines.entry(line).or_insert(0);
+      *line_count = (*line_count).max(count);
+    }
+  }
+
+  for function in &script.functions {
+    let range = match function.ranges.first() {
+      Some(range) if !function.function_name.is_empty() => range,
+      _ => continue,
+    };
+    let line = match line_offsets.binary_search(&range.start_offset) {
+      Ok(line) => line,
+      Err(line) => line - 1,
+    };
+    let column = range.start_offset - line_offsets[line];
+    if let Some(line) = original_line(line, column) {
+      let key = (line, function.function_name.clone());
+      *coverage.functions.entry(key).or_insert(0) += range.count;
+    }
+  }
+
+  coverage
+}
+
+// Only the local modules that aren't tests are reported.
+fn is_reported(url: &str) -> bool {
+  match Url::parse(url).ok().and_then(|url| url.to_file_path().ok()) {
+    Some(path) => !test_runner::is_supported(&path),
+    None => false,
+  }
+}
+
+/// Reads the coverage collected into `dir` by `deno test --coverage`,
+/// merging the coverages of the modules run by several tests processes.
+fn read_coverage(
+  dir: &Path,
+) -> Result<BTreeMap<String, ModuleCoverage>, ErrBox> {
+  let mut modules: BTreeMap<String, ModuleCoverage> = BTreeMap::new();
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.extension().map_or(true, |ext| ext != "json") {
+      continue;
+    }
+    let scripts: Vec<ScriptCoverage> =
+      serde_json::from_slice(&fs::read(&path)?)?;
+    for script in scripts.iter().filter(|s| is_reported(&s.url)) {
+      let coverage = module_coverage(script);
+      let merged = modules.entry(script.url.clone()).or_default();
+      for (line, count) in coverage.lines {
+        *merged.lines.entry(line).or_insert(0) += count;
+      }
+      for (function, count) in coverage.functions {
+        *merged.functions.entry(function).or_insert(0) += count;
+      }
+    }
+  }
+  Ok(modules)
+}
+
+// Formats the uncovered lines as ranges, such as `3-4, 9`.
+fn format_uncovered(coverage: &ModuleCoverage) -> String {
+  let mut ranges: Vec<(usize, usize)> = vec![];
+  let mut previous = None;
+  for (&line, &count) in &coverage.lines {
+    if count > 0 {
+      previous = Some(line);
+      continue;
+    }
+    match ranges.last_mut() {
+      Some(range) if previous == Some(range.1) => range.1 = line,
+      _ => ranges.push((line, line)),
+    }
+    previous = Some(line);
+  }
+  ranges
+    .iter()
+    .map(|&(start, end)| {
+      if start == end {
+        start.to_string()
+      } else {
+        format!("{}-{}", start, end)
+      }
+    })
+    .collect::<Vec<String>>()
+    .join(", ")
+}
+
+fn text_report(modules: &BTreeMap<String, ModuleCoverage>) -> String {
+  let mut report = String::new();
+  for (url, coverage) in modules {
+    let found = coverage.lines.len();
+    let hit = coverage.lines.values().filter(|&&count| count > 0).count();
+    let percent = if found == 0 {
+      100.0
+    } else {
+      hit as f64 * 100.0 / found as f64
+    };
+    let ratio = format!("{:.2}% ({}/{})", percent, hit, found);
+    let ratio = if percent >= 90.0 {
+      colors::green(ratio).to_string()
+    } else if percent >= 50.0 {
+      colors::yellow(ratio).to_string()
+    } else {
+      colors::red(ratio).to_string()
+    };
+    report.push_str(&format!("cover {} ... {}\n", url, ratio));
+    if hit < found {
+      let uncovered = format_uncovered(coverage);
+      report.push_str(&format!("  uncovered lines: {}\n", uncovered));
+    }
+  }
+  report
+}
+
+fn lcov_report(modules: &BTreeMap<String, ModuleCoverage>) -> String {
+  let mut report = String::new();
+  for (url, coverage) in modules {
+    let path = Url::parse(url).unwrap().to_file_path().unwrap();
+    report.push_str(&format!("SF:{}\n", path.display()));
+    for (line, name) in coverage.functions.keys() {
+      report.push_str(&format!("FN:{},{}\n", line, name));
+    }
+    for ((_, name), count) in &coverage.functions {
+      report.push_str(&format!("FNDA:{},{}\n", count, name));
+    }
+    let functions_hit = coverage
+      .functions
+      .values()
+      .filter(|&&count| count > 0)
+      .count();
+    report.push_str(&format!("FNF:{}\n", coverage.functions.len()));
+    report.push_str(&format!("FNH:{}\n", functions_hit));
+    for (line, count) in &coverage.lines {
+      report.push_str(&format!("DA:{},{}\n", line, count));
+    }
+    let lines_hit = coverage.lines.values().filter(|&&count| count > 0).count();
+    report.push_str(&format!("LF:{}\n", coverage.lines.len()));
+    report.push_str(&format!("LH:{}\n", lines_hit));
+    report.push_str("end_of_record\n");
+  }
+  report
+}
+
+pub fn report(dir: &Path, lcov: bool) -> Result<(), ErrBox> {
+  let modules = read_coverage(dir)?;
+  if lcov {
+    print!("{}", lcov_report(&modules));
+  } else {
+    print!("{}", text_report(&modules));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn range(
+    start_offset: usize,
+    end_offset: usize,
+    count: u64,
+  ) -> CoverageRange {
+    CoverageRange {
+      start_offset,
+      end_offset,
+      count,
+    }
+  }
+
+  fn script(source: &str, functions: Vec<FunctionCoverage>) -> ScriptCoverage {
+    ScriptCoverage {
+      script_id: "1".to_string(),
+      url: "file:///a.js".to_string(),
+      functions,
+      source: source.to_string(),
+      source_map: None,
+    }
+  }
+
+  #[test]
+  fn innermost_range_count() {
+    let source = "function f(x) {\n  if (x) {\n    return 1;\n  }\n}\nf(0);\n";
+    let functions = vec![
+      FunctionCoverage {
+        function_name: "".to_string(),
+        ranges: vec![range(0, source.len(), 1)],
+        is_block_coverage: true,
+      },
+      FunctionCoverage {
+        function_name: "f".to_string(),
+        ranges: vec![range(0, 47, 1), range(25, 44, 0)],
+        is_block_coverage: true,
+      },
+    ];
+    let coverage = module_coverage(&script(source, functions));
+    let lines: Vec<(usize, u64)> = coverage.lines.into_iter().collect();
+    assert_eq!(lines, vec![(1, 1), (2, 1), (3, 0), (4, 0), (5, 1), (6, 1)]);
+    let functions: Vec<_> = coverage.functions.into_iter().collect();
+    assert_eq!(functions, vec![((1, "f".to_string()), 1)]);
+  }
+
+  #[test]
+  fn uncovered_ranges() {
+    let mut coverage = ModuleCoverage::default();
+    for (line, count) in &[(1, 1), (3, 0), (4, 0), (5, 2), (9, 0)] {
+      coverage.lines.insert(*line, *count);
+    }
+    assert_eq!(format_uncovered(&coverage), "3-4, 9");
+  }
+
+  #[test]
+  fn lcov() {
+    let mut coverage = ModuleCoverage::default();
+    coverage.lines.insert(1, 1);
+    coverage.lines.insert(2, 0);
+    coverage.functions.insert((1, "f".to_string()), 1);
+    let mut modules = BTreeMap::new();
+    let url =
+      Url::from_file_path(std::env::current_dir().unwrap().join("a.ts"))
+        .unwrap()
+        .to_string();
+    modules.insert(url, coverage);
+    let report = lcov_report(&modules);
+    assert!(report.contains("FN:1,f\nFNDA:1,f\nFNF:1\nFNH:1\n"));
+    assert!(report.contains("DA:1,1\nDA:2,0\nLF:2\nLH:1\nend_of_record\n"));
+  }
+
+  #[test]
+  fn reported_modules() {
+    let cwd = std::env::current_dir().unwrap();
+    let url = |name: &str| Url::from_file_path(cwd.join(name)).unwrap();
+    assert!(is_reported(url("mod.ts").as_str()));
+    assert!(!is_reported(url("mod_test.ts").as_str()));
+    assert!(!is_reported("https://deno.land/std/mod.ts"));
+  }
+}
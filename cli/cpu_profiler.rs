@@ -0,0 +1,193 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Records the CPU profile of the main worker for `--prof`, with V8's
+//! sampling profiler, and writes it in the `.cpuprofile` format DevTools
+//! loads. The locations of the JS frames are mapped back to the sources they
+//! were compiled from.
+
+use crate::inspector::DenoInspector;
+use crate::inspector::InProcessSession;
+use crate::source_maps::SourceMapGetter;
+use deno_core::ErrBox;
+use serde_json::Value;
+use sourcemap::SourceMap;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Drives the `Profiler` domain of an in-process inspector session.
+pub struct CpuProfiler {
+  session: Box<InProcessSession>,
+}
+
+impl CpuProfiler {
+  /// The profiler must be dropped before the inspector.
+  pub fn new(inspector: &mut DenoInspector) -> Self {
+    Self {
+      session: InProcessSession::new(inspector),
+    }
+  }
+
+  pub fn start(&mut self) -> Result<(), ErrBox> {
+    self.session.post("Profiler.enable", json!({}))?;
+    self.session.post("Profiler.start", json!({}))?;
+    Ok(())
+  }
+
+  /// Stops profiling and returns the profile, source mapped.
+  pub fn stop(
+    &mut self,
+    source_map_getter: &impl SourceMapGetter,
+  ) -> Result<Value, ErrBox> {
+    let mut result = self.session.post("Profiler.stop", json!({}))?;
+    let mut profile = result["profile"].take();
+    apply_source_maps(&mut profile, |url| {
+      let map = source_map_getter.get_source_map(url)?;
+      SourceMap::from_slice(&map).ok()
+    });
+    Ok(profile)
+  }
+}
+
+/// Maps the call frames and the line ticks of the nodes of `profile` to the
+/// original sources of the scripts `get_source_map` has maps for.
+fn apply_source_maps(
+  profile: &mut Value,
+  mut get_source_map: impl FnMut(&str) -> Option<SourceMap>,
+) {
+  let mut source_maps: HashMap<String, Option<SourceMap>> = HashMap::new();
+  let nodes = match profile["nodes"].as_array_mut() {
+    Some(nodes) => nodes,
+    None => return,
+  };
+  for node in nodes {
+    let url = node["callFrame"]["url"].as_str().unwrap_or_default();
+    if url.is_empty() {
+      continue;
+    }
+    let source_map = source_maps
+      .entry(url.to_string())
+      .or_insert_with(|| get_source_map(url));
+    let source_map = match source_map {
+      Some(source_map) => source_map,
+      None => continue,
+    };
+
+    // Call frame locations are 0-based.
+    let call_frame = &mut node["callFrame"];
+    let line = call_frame["lineNumber"].as_i64().unwrap_or(-1);
+    let column = call_frame["columnNumber"].as_i64().unwrap_or(-1);
+    if line >= 0 && column >= 0 {
+      if let Some(token) = source_map.lookup_token(line as u32, column as u32) {
+        call_frame["lineNumber"] = json!(token.get_src_line());
+        call_frame["columnNumber"] = json!(token.get_src_col());
+      }
+    }
+
+    // Line ticks are 1-based, and are dropped when their line of the
+    // compiled source has no counterpart in the original.
+    if let Some(position_ticks) = node["positionTicks"].as_array() {
+      let mut ticks_by_line: BTreeMap<u32, u64> = BTreeMap::new();
+      for position_tick in position_ticks {
+        let line = position_tick["line"].as_u64().unwrap_or(0) as u32;
+        let ticks = position_tick["ticks"].as_u64().unwrap_or(0);
+        if line == 0 {
+          continue;
+        }
+        let original_line = source_map
+          .lookup_token(line - 1, u32::MAX)
+          .filter(|token| token.get_dst_line() == line - 1)
+          .map(|token| token.get_src_line() + 1);
+        if let Some(original_line) = original_line {
+          *ticks_by_line.entry(original_line).or_insert(0) += ticks;
+        }
+      }
+      node["positionTicks"] = ticks_by_line
+        .into_iter()
+        .map(|(line, ticks)| json!({ "line": line, "ticks": ticks }))
+        .collect();
+    }
+  }
+}
+
+pub fn write(path: &Path, profile: &Value) -> Result<(), ErrBox> {
+  if let Some(dir) = path.parent() {
+    if !dir.as_os_str().is_empty() {
+      fs::create_dir_all(dir)?;
+    }
+  }
+  fs::write(path, serde_json::to_vec(profile)?)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Maps line 1 of the compiled source to line 3 of the original.
+  const SOURCE_MAP: &str = r#"{
+    "version": 3,
+    "sources": ["file:///a.ts"],
+    "names": [],
+    "mappings": ";AAEA,IAAI"
+  }"#;
+
+  #[test]
+  fn source_mapped_profile() {
+    let mut profile = json!({
+      "nodes": [
+        {
+          "id": 1,
+          "callFrame": {
+            "functionName": "(root)",
+            "url": "",
+            "lineNumber": -1,
+            "columnNumber": -1
+          }
+        },
+        {
+          "id": 2,
+          "callFrame": {
+            "functionName": "f",
+            "url": "file:///a.ts",
+            "lineNumber": 1,
+            "columnNumber": 4
+          },
+          "positionTicks": [
+            { "line": 1, "ticks": 2 },
+            { "line": 2, "ticks": 5 }
+          ]
+        },
+        {
+          "id": 3,
+          "callFrame": {
+            "functionName": "g",
+            "url": "file:///b.js",
+            "lineNumber": 7,
+            "columnNumber": 2
+          }
+        }
+      ]
+    });
+    let mut requested = vec![];
+    apply_source_maps(&mut profile, |url| {
+      requested.push(url.to_string());
+      if url == "file:///a.ts" {
+        SourceMap::from_slice(SOURCE_MAP.as_bytes()).ok()
+      } else {
+        None
+      }
+    });
+    assert_eq!(requested, vec!["file:///a.ts", "file:///b.js"]);
+
+    let f = &profile["nodes"][1];
+    assert_eq!(f["callFrame"]["lineNumber"], 2);
+    assert_eq!(f["callFrame"]["columnNumber"], 4);
+    // Line 1 was an empty line of the compiled source.
+    assert_eq!(f["positionTicks"], json!([{ "line": 3, "ticks": 5 }]));
+
+    let g = &profile["nodes"][2];
+    assert_eq!(g["callFrame"]["lineNumber"], 7);
+    assert_eq!(g["callFrame"]["columnNumber"], 2);
+  }
+}
@@ -0,0 +1,243 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Writes a report to `$DENO_DIR/crash_reports/` when Deno panics, with what
+//! a bug report needs: the version, the flags, the main module, the number
+//! of pending ops and the backtrace of the panic.
+//!
+//! The panic hook can't reach the worker, so the main worker registers what
+//! the report needs here when it's created. Its state is only visible from
+//! the thread the worker runs on.
+//!
+//! Reports are meant to be attached to bug reports, so the values of the
+//! download headers and the arguments of the script are redacted from them.
+
+use crate::deno_dir::DenoDir;
+use crate::flags::Flags;
+use crate::state::State;
+use crate::state::StateInner;
+use crate::version;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::panic::PanicInfo;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::rc::Weak;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+struct Context {
+  args: Vec<String>,
+  flags: String,
+  main_module: String,
+}
+
+const REDACTED: &str = "<redacted>";
+
+/// Keeps the scope and the name of a download header, but not its value.
+fn redact_header(header: &str) -> String {
+  match header.find(':') {
+    Some(index) => format!("{}: {}", &header[..index], REDACTED),
+    None => REDACTED.to_string(),
+  }
+}
+
+fn redact_flags(flags: &Flags) -> Flags {
+  let mut flags = flags.clone();
+  flags.download_headers = flags
+    .download_headers
+    .iter()
+    .map(|h| redact_header(h))
+    .collect();
+  flags.argv = flags.argv.iter().map(|_| REDACTED.to_string()).collect();
+  flags
+}
+
+/// Redacts the command line the same way as the flags. The arguments of the
+/// script always come last, so as many arguments as `flags.argv` holds are
+/// redacted from the end.
+fn redact_args(args: Vec<String>, flags: &Flags) -> Vec<String> {
+  let script_args_start = args.len().saturating_sub(flags.argv.len());
+  let mut redacted = Vec::with_capacity(args.len());
+  let mut header_value = false;
+  for (index, arg) in args.into_iter().enumerate() {
+    if index >= script_args_start {
+      redacted.push(REDACTED.to_string());
+    } else if header_value {
+      header_value = false;
+      redacted.push(redact_header(&arg));
+    } else if arg == "--download-header" {
+      header_value = true;
+      redacted.push(arg);
+    } else if arg.starts_with("--download-header=") {
+      let value = &arg["--download-header=".len()..];
+      redacted.push(format!("--download-header={}", redact_header(value)));
+    } else {
+      redacted.push(arg);
+    }
+  }
+  redacted
+}
+
+lazy_static! {
+  static ref CONTEXT: Mutex<Option<Context>> = Mutex::new(None);
+}
+
+thread_local! {
+  static MAIN_STATE: RefCell<Weak<RefCell<StateInner>>> =
+    RefCell::new(Weak::new());
+}
+
+/// Records the flags and the main module of the main worker, and the state
+/// its pending ops are counted from.
+pub fn set_main_worker_state(state: &State) {
+  let state_ = state.borrow();
+  let flags = &state_.global_state.flags;
+  let context = Context {
+    args: redact_args(env::args().collect(), flags),
+    flags: format!("{:?}", redact_flags(flags)),
+    main_module: state_.main_module.to_string(),
+  };
+  if let Ok(mut guard) = CONTEXT.lock() {
+    *guard = Some(context);
+  }
+  MAIN_STATE.with(|main_state| {
+    *main_state.borrow_mut() = Rc::downgrade(&*state);
+  });
+}
+
+/// The command line of the main worker with its secrets redacted, as it goes
+/// into the report. `None` until the main worker has registered it.
+pub fn redacted_args() -> Option<Vec<String>> {
+  let guard = CONTEXT.try_lock().ok()?;
+  guard.as_ref().map(|context| context.args.clone())
+}
+
+/// The counts of the ops of the main worker which haven't completed, if the
+/// panic happened on its thread while its state could be borrowed.
+fn pending_ops() -> Value {
+  MAIN_STATE
+    .try_with(|main_state| {
+      let state = main_state.try_borrow().ok()?.upgrade()?;
+      let state = state.try_borrow().ok()?;
+      let metrics = &state.metrics;
+      Some(json!({
+        "async": metrics.ops_dispatched_async - metrics.ops_completed_async,
+        "asyncUnref": metrics.ops_dispatched_async_unref
+          - metrics.ops_completed_async_unref,
+      }))
+    })
+    .ok()
+    .flatten()
+    .unwrap_or(Value::Null)
+}
+
+fn report(panic_info: &PanicInfo) -> Value {
+  // Without the flags, the arguments can't be told apart from the ones of
+  // the script, so none are reported.
+  let (args, flags, main_module) = match CONTEXT.try_lock() {
+    Ok(guard) => match guard.as_ref() {
+      Some(context) => (
+        json!(context.args),
+        json!(context.flags),
+        json!(context.main_module),
+      ),
+      None => (Value::Null, Value::Null, Value::Null),
+    },
+    Err(_) => (Value::Null, Value::Null, Value::Null),
+  };
+  let message = if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
+    s.to_string()
+  } else if let Some(s) = panic_info.payload().downcast_ref::<String>() {
+    s.clone()
+  } else {
+    "Box<Any>".to_string()
+  };
+  json!({
+    "version": version::DENO,
+    "v8": version::v8(),
+    "typescript": version::TYPESCRIPT,
+    "platform": format!("{} {}", env::consts::OS, env::consts::ARCH),
+    "args": args,
+    "flags": flags,
+    "mainModule": main_module,
+    "thread": std::thread::current().name().unwrap_or("<unnamed>"),
+    "message": message,
+    "location": panic_info.location().map(|l| l.to_string()),
+    "pendingOps": pending_ops(),
+    "backtrace": format!("{:?}", backtrace::Backtrace::new()),
+  })
+}
+
+/// Writes the report of the panic and returns its path.
+pub fn write(panic_info: &PanicInfo) -> Option<PathBuf> {
+  let custom_root = env::var("DENO_DIR").map(String::into).ok();
+  let deno_dir = DenoDir::new(custom_root).ok()?;
+  let dir = deno_dir.root.join("crash_reports");
+  fs::create_dir_all(&dir).ok()?;
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis())
+    .unwrap_or(0);
+  let path = dir.join(format!("{}-{}.json", timestamp, std::process::id()));
+  let report = serde_json::to_vec_pretty(&report(panic_info)).ok()?;
+  fs::write(&path, report).ok()?;
+  Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn pending_ops_of_main_worker() {
+    assert_eq!(pending_ops(), Value::Null);
+
+    let state = State::mock("./hello.js");
+    set_main_worker_state(&state);
+    state.borrow_mut().metrics.op_dispatched_async(0, 0);
+    assert_eq!(pending_ops(), json!({ "async": 1, "asyncUnref": 0 }));
+
+    // The state is only visible from the thread of the worker.
+    let other_thread = std::thread::spawn(pending_ops).join().unwrap();
+    assert_eq!(other_thread, Value::Null);
+
+    drop(state);
+    assert_eq!(pending_ops(), Value::Null);
+  }
+
+  #[test]
+  fn redacts_secrets() {
+    let args: Vec<String> = vec![
+      "deno",
+      "run",
+      "--download-header",
+      "deno.land=X-Org-Token: secret",
+      "--download-header=localhost=Authorization: Bearer secret",
+      "script.ts",
+      "--token",
+      "secret",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    let flags = crate::flags::flags_from_vec(args.clone());
+    assert_eq!(
+      redact_args(args, &flags),
+      vec![
+        "deno",
+        "run",
+        "--download-header",
+        "deno.land=X-Org-Token: <redacted>",
+        "--download-header=localhost=Authorization: <redacted>",
+        "script.ts",
+        "<redacted>",
+        "<redacted>",
+      ]
+    );
+    let flags = format!("{:?}", redact_flags(&flags));
+    assert!(flags.contains("X-Org-Token: <redacted>"));
+    assert!(!flags.contains("secret"));
+  }
+}
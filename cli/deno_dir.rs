@@ -14,16 +14,6 @@ pub struct DenoDir {
 
 impl DenoDir {
   pub fn new(maybe_custom_root: Option<PathBuf>) -> std::io::Result<Self> {
-    // Only setup once.
-    let home_dir = dirs::home_dir().expect("Could not get home directory.");
-    let fallback = home_dir.join(".deno");
-    // We use the OS cache dir because all files deno writes are cache files
-    // Once that changes we need to start using different roots if DENO_DIR
-    // is not set, and keep a single one if it is.
-    let default = dirs::cache_dir()
-      .map(|d| d.join("deno"))
-      .unwrap_or(fallback);
-
     let root: PathBuf = if let Some(root) = maybe_custom_root {
       if root.is_absolute() {
         root
@@ -31,7 +21,19 @@ impl DenoDir {
         std::env::current_dir()?.join(root)
       }
     } else {
-      default
+      // We use the OS cache dir because all files deno writes are cache files
+      // Once that changes we need to start using different roots if DENO_DIR
+      // is not set, and keep a single one if it is.
+      dirs::cache_dir()
+        .map(|d| d.join("deno"))
+        .or_else(|| dirs::home_dir().map(|d| d.join(".deno")))
+        .ok_or_else(|| {
+          std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Could not get home directory. Set DENO_DIR to choose where Deno \
+             caches files.",
+          )
+        })?
     };
     assert!(root.is_absolute());
     let gen_path = root.join("gen");
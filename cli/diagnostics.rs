@@ -9,6 +9,7 @@ use crate::colors;
 use crate::fmt_errors::format_stack;
 use serde::Deserialize;
 use serde::Deserializer;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
 
@@ -18,19 +19,76 @@ pub struct Diagnostic {
   pub items: Vec<DiagnosticItem>,
 }
 
+impl Diagnostic {
+  /// Groups the items that were reported for the same reason, ordered by
+  /// file and position. Items without a file come last.
+  fn grouped_items(&self) -> Vec<DiagnosticGroup> {
+    let mut items: Vec<&DiagnosticItem> = self.items.iter().collect();
+    items.sort_by_key(|item| {
+      (
+        item.script_resource_name.is_none(),
+        item.script_resource_name.clone(),
+        item.line_number,
+        item.start_column,
+      )
+    });
+
+    let mut groups: Vec<DiagnosticGroup> = vec![];
+    for item in items {
+      match groups.iter_mut().find(|g| g.item.has_same_reason(item)) {
+        Some(group) => {
+          match group.locations.iter_mut().find(|(l, _)| *l == item) {
+            Some((_, count)) => *count += 1,
+            None => group.locations.push((item, 1)),
+          }
+        }
+        None => groups.push(DiagnosticGroup {
+          item,
+          locations: vec![(item, 1)],
+        }),
+      }
+    }
+    groups
+  }
+}
+
+/// Diagnostics with the same code and message, which are shown once with all
+/// of their locations.
+struct DiagnosticGroup<'a> {
+  item: &'a DiagnosticItem,
+  /// Items at each location, with how many times each one was reported.
+  locations: Vec<(&'a DiagnosticItem, usize)>,
+}
+
 impl fmt::Display for Diagnostic {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    let mut i = 0;
-    for item in &self.items {
+    for (i, group) in self.grouped_items().iter().enumerate() {
       if i > 0 {
         write!(f, "\n\n")?;
       }
-      write!(f, "{}", item.to_string())?;
-      i += 1;
+      let frames: Vec<String> = group
+        .locations
+        .iter()
+        .map(|(item, count)| match count {
+          1 => item.format_frame(),
+          _ => format!("{} ({} times)", item.format_frame(), count),
+        })
+        .collect();
+      write!(f, "{}", group.item.format_with_frames(&frames))?;
     }
 
-    if i > 1 {
-      write!(f, "\n\nFound {} errors.", i)?;
+    let count = self.items.len();
+    if count > 1 {
+      let files: HashSet<&String> = self
+        .items
+        .iter()
+        .filter_map(|item| item.script_resource_name.as_ref())
+        .collect();
+      if files.len() > 1 {
+        write!(f, "\n\nFound {} errors in {} files.", count, files.len())?;
+      } else {
+        write!(f, "\n\nFound {} errors.", count)?;
+      }
     }
 
     Ok(())
@@ -174,38 +232,50 @@ fn format_maybe_related_information(
   s
 }
 
+impl DiagnosticItem {
+  /// Whether `other` is the same diagnostic, possibly at another location.
+  fn has_same_reason(&self, other: &DiagnosticItem) -> bool {
+    self.code == other.code
+      && self.category == other.category
+      && self.message == other.message
+      && self.message_chain == other.message_chain
+      && self.related_information == other.related_information
+  }
+
+  fn format_frame(&self) -> String {
+    // Formatter expects 1-based line and column numbers, but ours are 0-based.
+    format_maybe_frame(
+      self.script_resource_name.clone(),
+      self.line_number.map(|n| n + 1),
+      self.start_column.map(|n| n + 1),
+    )
+  }
+
+  fn format_with_frames(&self, frames: &[String]) -> String {
+    let mut s = format_stack(
+      match self.category {
+        DiagnosticCategory::Error => true,
+        _ => false,
+      },
+      format!(
+        "{}: {}",
+        format_category_and_code(&self.category, self.code),
+        format_message(&self.message_chain, &self.message, 0)
+      ),
+      self.source_line.clone(),
+      self.start_column,
+      self.end_column,
+      frames,
+      0,
+    );
+    s.push_str(&format_maybe_related_information(&self.related_information));
+    s
+  }
+}
+
 impl fmt::Display for DiagnosticItem {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(
-      f,
-      "{}",
-      format_stack(
-        match self.category {
-          DiagnosticCategory::Error => true,
-          _ => false,
-        },
-        format!(
-          "{}: {}",
-          format_category_and_code(&self.category, self.code),
-          format_message(&self.message_chain, &self.message, 0)
-        ),
-        self.source_line.clone(),
-        self.start_column,
-        self.end_column,
-        // Formatter expects 1-based line and column numbers, but ours are 0-based.
-        &[format_maybe_frame(
-          self.script_resource_name.clone(),
-          self.line_number.map(|n| n + 1),
-          self.start_column.map(|n| n + 1)
-        )],
-        0
-      )
-    )?;
-    write!(
-      f,
-      "{}",
-      format_maybe_related_information(&self.related_information),
-    )
+    write!(f, "{}", self.format_with_frames(&[self.format_frame()]))
   }
 }
 
@@ -441,7 +511,20 @@ mod tests {
   #[test]
   fn diagnostic_to_string2() {
     let d = diagnostic2();
-    let expected = "TS2322 [ERROR]: Example 1\n  values: o => [\n  ~~~~~~\n    at deno/tests/complex_diagnostics.ts:19:3\n\nTS2000 [ERROR]: Example 2\n  values: undefined,\n  ~~~~~~\n    at /foo/bar.ts:129:3\n\nFound 2 errors.";
+    let expected = "TS2000 [ERROR]: Example 2\n  values: undefined,\n  ~~~~~~\n    at /foo/bar.ts:129:3\n\nTS2322 [ERROR]: Example 1\n  values: o => [\n  ~~~~~~\n    at deno/tests/complex_diagnostics.ts:19:3\n\nFound 2 errors in 2 files.";
+    assert_eq!(expected, strip_ansi_codes(&d.to_string()));
+  }
+
+  #[test]
+  fn diagnostic_to_string_grouped() {
+    let mut d = diagnostic2();
+    let mut item = d.items[1].clone();
+    item.message = "Example 1".to_string();
+    item.code = 2322;
+    d.items.push(item.clone());
+    d.items.push(item);
+    d.items.push(d.items[0].clone());
+    let expected = "TS2000 [ERROR]: Example 2\n  values: undefined,\n  ~~~~~~\n    at /foo/bar.ts:129:3\n\nTS2322 [ERROR]: Example 1\n  values: undefined,\n  ~~~~~~\n    at /foo/bar.ts:129:3 (2 times)\n    at deno/tests/complex_diagnostics.ts:19:3 (2 times)\n\nFound 5 errors in 2 files.";
     assert_eq!(expected, strip_ansi_codes(&d.to_string()));
   }
 
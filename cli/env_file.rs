@@ -0,0 +1,182 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Loading of dotenv-style files given with `--env-file`.
+//!
+//!   # Comments and empty lines are ignored.
+//!   PORT=8000
+//!   export GREETING="Hello\nWorld"
+//!   RAW='no $escapes\n here' # trailing comments are allowed
+
+use crate::op_error::OpError;
+use deno_core::ErrBox;
+use std::env;
+use std::fs;
+
+/// Parses the contents of an env file into its variables, in order.
+///
+/// Errors carry the 1-based number of the offending line.
+pub fn parse(text: &str) -> Result<Vec<(String, String)>, (usize, String)> {
+  let mut vars = vec![];
+  for (index, line) in text.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let line = if line.starts_with("export ") {
+      &line["export ".len()..]
+    } else {
+      line
+    };
+    let var = parse_line(line).map_err(|msg| (index + 1, msg))?;
+    vars.push(var);
+  }
+  Ok(vars)
+}
+
+fn parse_line(line: &str) -> Result<(String, String), String> {
+  let eq = match line.find('=') {
+    Some(eq) => eq,
+    None => return Err("expected KEY=VALUE".to_string()),
+  };
+  let key = line[..eq].trim();
+  if !is_valid_key(key) {
+    return Err(format!("invalid variable name \"{}\"", key));
+  }
+  let raw_value = line[eq + 1..].trim_start();
+
+  let (value, rest) = match raw_value.chars().next() {
+    Some('"') => parse_double_quoted(&raw_value[1..])?,
+    Some('\'') => match raw_value[1..].find('\'') {
+      Some(end) => (raw_value[1..end + 1].to_string(), &raw_value[end + 2..]),
+      None => return Err("unterminated quoted value".to_string()),
+    },
+    _ => {
+      // An unquoted value ends at a comment that follows whitespace.
+      let end = raw_value
+        .char_indices()
+        .find(|&(i, c)| {
+          c == '#' && raw_value[..i].ends_with(char::is_whitespace)
+        })
+        .map(|(i, _)| i)
+        .unwrap_or_else(|| raw_value.len());
+      (raw_value[..end].trim_end().to_string(), "")
+    }
+  };
+
+  let rest = rest.trim_start();
+  if !rest.is_empty() && !rest.starts_with('#') {
+    return Err(format!("unexpected characters after value: {}", rest));
+  }
+  Ok((key.to_string(), value))
+}
+
+/// Parses the inside of a double quoted value, returning the value and what
+/// follows the closing quote.
+fn parse_double_quoted(text: &str) -> Result<(String, &str), String> {
+  let mut value = String::new();
+  let mut chars = text.char_indices();
+  while let Some((i, c)) = chars.next() {
+    match c {
+      '"' => return Ok((value, &text[i + 1..])),
+      '\\' => match chars.next() {
+        Some((_, 'n')) => value.push('\n'),
+        Some((_, 'r')) => value.push('\r'),
+        Some((_, 't')) => value.push('\t'),
+        Some((_, c @ '"')) | Some((_, c @ '\\')) => value.push(c),
+        Some((_, c)) => {
+          value.push('\\');
+          value.push(c);
+        }
+        None => break,
+      },
+      c => value.push(c),
+    }
+  }
+  Err("unterminated quoted value".to_string())
+}
+
+fn is_valid_key(key: &str) -> bool {
+  let mut chars = key.chars();
+  match chars.next() {
+    Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+    _ => return false,
+  }
+  chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Loads the env file at `path` into the environment of this process.
+///
+/// Variables that are already set are left as they are, so that the
+/// environment of the shell can override the file.
+pub fn load(path: &str) -> Result<(), ErrBox> {
+  let text = fs::read_to_string(path).map_err(|e| {
+    OpError::other(format!("Unable to read env file \"{}\": {}", path, e))
+  })?;
+  let vars = parse(&text).map_err(|(line, msg)| {
+    OpError::other(format!(
+      "Unable to parse env file \"{}\" at line {}: {}",
+      path, line, msg
+    ))
+  })?;
+  for (key, value) in vars {
+    if env::var_os(&key).is_none() {
+      env::set_var(key, value);
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn vars(text: &str) -> Vec<(String, String)> {
+    parse(text).unwrap()
+  }
+
+  fn var(key: &str, value: &str) -> (String, String) {
+    (key.to_string(), value.to_string())
+  }
+
+  #[test]
+  fn parse_values() {
+    assert_eq!(
+      vars(
+        "# comment\n\nA=1\nexport B = two words \nC=x#y # comment\nD=\nE=a=b"
+      ),
+      vec![
+        var("A", "1"),
+        var("B", "two words"),
+        var("C", "x#y"),
+        var("D", ""),
+        var("E", "a=b"),
+      ]
+    );
+  }
+
+  #[test]
+  fn parse_quoted_values() {
+    assert_eq!(
+      vars(
+        "A=\"a\\nb \\\"c\\\" # d\" # comment\nB='$x\\n'\nC=\"\\q\"\r\nD=\"\""
+      ),
+      vec![
+        var("A", "a\nb \"c\" # d"),
+        var("B", "$x\\n"),
+        var("C", "\\q"),
+        var("D", ""),
+      ]
+    );
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert_eq!(parse("A=1\nB").unwrap_err().0, 2);
+    assert!(parse("1A=1").is_err());
+    assert!(parse("A B=1").is_err());
+    assert!(parse("=1").is_err());
+    assert!(parse("A=\"1").is_err());
+    assert!(parse("A='1").is_err());
+    assert!(parse("A=\"1\" 2").is_err());
+  }
+}
@@ -0,0 +1,579 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+/// HTTP cache for the responses of `fetch()`, enabled with `--fetch-cache`.
+/// It lives in `$DENO_DIR/fetch`, apart from the module cache, and follows
+/// RFC 7234 (https://tools.ietf.org/html/rfc7234) closely enough for scripts
+/// that request the same resources over and over: `max-age` responses are
+/// served from disk while they are fresh, stale ones are revalidated with
+/// their `ETag` or `Last-Modified`, and `no-store` is never written.
+/// Requests that carry credentials are never cached, and entries that have
+/// been stale for a week are pruned once a day.
+///
+/// Every URL has an index listing the request headers its response varies
+/// on, and one entry per combination of values of those headers.
+use crate::checksum;
+use crate::fs as deno_fs;
+use crate::http_cache::url_to_filename;
+use deno_core::ErrBox;
+use serde::Serialize;
+use serde_derive::Deserialize;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use url::Url;
+use walkdir::WalkDir;
+
+pub type Headers = Vec<(String, String)>;
+
+/// How often the cache looks for entries to prune, in seconds.
+const PRUNE_INTERVAL: u64 = 24 * 60 * 60;
+/// How long a stale entry is kept after it was stored or last revalidated,
+/// in seconds.
+const PRUNE_AGE: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Clone)]
+pub struct FetchCache {
+  pub location: PathBuf,
+}
+
+/// The headers of a cached response. The body is stored next to it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+  pub url: String,
+  pub status: u16,
+  pub status_text: String,
+  pub headers: Headers,
+  /// When the response was stored or last revalidated, in seconds since the
+  /// unix epoch.
+  pub stored_at: u64,
+}
+
+#[derive(Default, Debug, PartialEq)]
+struct CacheControl {
+  no_store: bool,
+  no_cache: bool,
+  max_age: Option<u64>,
+}
+
+/// The `Vary` header names of a URL, lowercased.
+#[derive(Serialize, Deserialize)]
+struct VaryIndex {
+  vary: Vec<String>,
+}
+
+fn get_header<'a>(
+  headers: &'a [(String, String)],
+  name: &str,
+) -> Option<&'a str> {
+  headers
+    .iter()
+    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+    .map(|(_, value)| value.as_str())
+}
+
+fn cache_control(headers: &[(String, String)]) -> CacheControl {
+  let mut cache_control = CacheControl::default();
+  let directives = headers
+    .iter()
+    .filter(|(key, _)| key.eq_ignore_ascii_case("cache-control"))
+    .flat_map(|(_, value)| value.split(','));
+  for directive in directives {
+    let mut parts = directive.trim().splitn(2, '=');
+    let name = parts.next().unwrap().to_ascii_lowercase();
+    let value = parts.next().map(|v| v.trim_matches('"'));
+    match name.as_str() {
+      "no-store" => cache_control.no_store = true,
+      "no-cache" => cache_control.no_cache = true,
+      "max-age" => cache_control.max_age = value.and_then(|v| v.parse().ok()),
+      _ => {}
+    }
+  }
+  cache_control
+}
+
+fn vary(headers: &[(String, String)]) -> Vec<String> {
+  headers
+    .iter()
+    .filter(|(key, _)| key.eq_ignore_ascii_case("vary"))
+    .flat_map(|(_, value)| value.split(','))
+    .map(|name| name.trim().to_ascii_lowercase())
+    .filter(|name| !name.is_empty())
+    .collect()
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap()
+    .as_secs()
+}
+
+impl CachedResponse {
+  pub fn new(
+    url: &Url,
+    status: u16,
+    status_text: &str,
+    headers: Headers,
+  ) -> Self {
+    Self {
+      url: url.to_string(),
+      status,
+      status_text: status_text.to_string(),
+      headers,
+      stored_at: now(),
+    }
+  }
+
+  /// Whether the response can be used without asking the server.
+  pub fn is_fresh(&self) -> bool {
+    let cache_control = cache_control(&self.headers);
+    match cache_control.max_age {
+      Some(max_age) if !cache_control.no_cache => {
+        now().saturating_sub(self.stored_at) < max_age
+      }
+      _ => false,
+    }
+  }
+
+  /// The headers that turn a request for this response into a conditional
+  /// one.
+  pub fn revalidation_headers(&self) -> Headers {
+    let mut headers = Vec::new();
+    if let Some(etag) = get_header(&self.headers, "etag") {
+      headers.push(("if-none-match".to_string(), etag.to_string()));
+    }
+    if let Some(last_modified) = get_header(&self.headers, "last-modified") {
+      headers
+        .push(("if-modified-since".to_string(), last_modified.to_string()));
+    }
+    headers
+  }
+
+  /// Updates the response with the headers of a `304 Not Modified` that
+  /// revalidated it.
+  pub fn revalidate(&mut self, headers: &[(String, String)]) {
+    self.headers.retain(|(key, _)| {
+      !headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case(key))
+    });
+    self.headers.extend(headers.iter().cloned());
+    self.stored_at = now();
+  }
+
+  /// Whether the response may be stored: it must be a `200 OK`, must not be
+  /// `no-store`, set cookies or vary on everything, and must either be fresh
+  /// for a while or carry a validator.
+  pub fn is_storable(&self) -> bool {
+    let cache_control = cache_control(&self.headers);
+    self.status == 200
+      && !cache_control.no_store
+      && get_header(&self.headers, "set-cookie").is_none()
+      && !vary(&self.headers).iter().any(|name| name == "*")
+      && (cache_control.max_age.is_some()
+        || get_header(&self.headers, "etag").is_some()
+        || get_header(&self.headers, "last-modified").is_some())
+  }
+}
+
+/// Whether a request may be answered from the cache. Only plain `GET`s are,
+/// and requests that carry credentials, ask for `no-store` or are
+/// conditional already always go to the server.
+pub fn is_cacheable_request(
+  method: &str,
+  has_body: bool,
+  headers: &[(String, String)],
+) -> bool {
+  method.eq_ignore_ascii_case("GET")
+    && !has_body
+    && !cache_control(headers).no_store
+    && get_header(headers, "authorization").is_none()
+    && get_header(headers, "cookie").is_none()
+    && get_header(headers, "if-none-match").is_none()
+    && get_header(headers, "if-modified-since").is_none()
+}
+
+impl FetchCache {
+  /// Returns a new instance.
+  ///
+  /// `location` must be an absolute path.
+  pub fn new(location: &Path) -> Self {
+    assert!(location.is_absolute());
+    Self {
+      location: location.to_owned(),
+    }
+  }
+
+  fn index_filename(&self, url: &Url) -> PathBuf {
+    self
+      .location
+      .join(url_to_filename(url))
+      .with_extension("vary.json")
+  }
+
+  /// Ex: $DENO_DIR/fetch/https/deno.land/c885b7dcf1d6936e33a9cc3a2d74ec79bab5d733d3701c85a029b7f7ec9fbed4_0ab6c1bb
+  fn entry_filename(
+    &self,
+    url: &Url,
+    vary: &[String],
+    request_headers: &[(String, String)],
+  ) -> PathBuf {
+    let mut filename = self.location.join(url_to_filename(url));
+    if !vary.is_empty() {
+      let values: Vec<String> = vary
+        .iter()
+        .map(|name| {
+          format!(
+            "{}:{}",
+            name,
+            get_header(request_headers, name).unwrap_or("")
+          )
+        })
+        .collect();
+      let values = values.join("\n");
+      let hash = checksum::gen(vec![values.as_bytes()]);
+      let name = format!(
+        "{}_{}",
+        filename.file_name().unwrap().to_string_lossy(),
+        &hash[..8]
+      );
+      filename.set_file_name(name);
+    }
+    filename
+  }
+
+  fn metadata_filename(entry_filename: &Path) -> PathBuf {
+    entry_filename.with_extension("metadata.json")
+  }
+
+  /// Looks up the response that was stored for the same URL and the same
+  /// values of the headers it varies on.
+  pub fn get(
+    &self,
+    url: &Url,
+    request_headers: &[(String, String)],
+  ) -> Result<(CachedResponse, File), ErrBox> {
+    let index = fs::read_to_string(self.index_filename(url))?;
+    let index: VaryIndex = serde_json::from_str(&index)?;
+    let entry_filename = self.entry_filename(url, &index.vary, request_headers);
+    let metadata =
+      fs::read_to_string(Self::metadata_filename(&entry_filename))?;
+    let response: CachedResponse = serde_json::from_str(&metadata)?;
+    let file = File::open(entry_filename)?;
+    Ok((response, file))
+  }
+
+  /// Stores a response, or only its headers when `body` is `None`, and
+  /// returns its body.
+  pub fn set(
+    &self,
+    url: &Url,
+    request_headers: &[(String, String)],
+    response: &CachedResponse,
+    body: Option<&[u8]>,
+  ) -> Result<File, ErrBox> {
+    let vary = vary(&response.headers);
+    let entry_filename = self.entry_filename(url, &vary, request_headers);
+    let parent = entry_filename
+      .parent()
+      .expect("Cache filename should have a parent dir");
+    fs::create_dir_all(parent).map_err(|e| {
+      io::Error::new(
+        e.kind(),
+        format!(
+          "Could not create fetch cache location: {:?}\nCheck the permission of the directory.",
+          parent
+        ),
+      )
+    })?;
+    if let Some(body) = body {
+      deno_fs::write_file(&entry_filename, body, 0o666)?;
+    }
+    let metadata = serde_json::to_string_pretty(response)?;
+    deno_fs::write_file(
+      &Self::metadata_filename(&entry_filename),
+      metadata,
+      0o666,
+    )?;
+    let index = serde_json::to_string(&VaryIndex { vary })?;
+    deno_fs::write_file(&self.index_filename(url), index, 0o666)?;
+    Ok(File::open(entry_filename)?)
+  }
+
+  /// Like `get`, but reads the cache on the blocking thread pool.
+  pub async fn get_async(
+    &self,
+    url: Url,
+    request_headers: Headers,
+  ) -> Result<(CachedResponse, File), ErrBox> {
+    let cache = self.clone();
+    tokio::task::spawn_blocking(move || cache.get(&url, &request_headers))
+      .await
+      .unwrap()
+  }
+
+  /// Like `set`, but writes the cache on the blocking thread pool.
+  pub async fn set_async(
+    &self,
+    url: Url,
+    request_headers: Headers,
+    response: CachedResponse,
+    body: Option<Vec<u8>>,
+  ) -> Result<File, ErrBox> {
+    let cache = self.clone();
+    tokio::task::spawn_blocking(move || {
+      cache.set(&url, &request_headers, &response, body.as_deref())
+    })
+    .await
+    .unwrap()
+  }
+
+  fn prune_marker_filename(&self) -> PathBuf {
+    self.location.join("last_prune")
+  }
+
+  /// Prunes the cache unless it was already pruned in the last day.
+  pub fn prune_if_due(&self) -> Result<(), ErrBox> {
+    if !self.location.is_dir() {
+      return Ok(());
+    }
+    let marker = self.prune_marker_filename();
+    let last_prune = fs::read_to_string(&marker)
+      .ok()
+      .and_then(|s| s.trim().parse::<u64>().ok());
+    if let Some(last_prune) = last_prune {
+      if now().saturating_sub(last_prune) < PRUNE_INTERVAL {
+        return Ok(());
+      }
+    }
+    self.prune(now().saturating_sub(PRUNE_AGE))?;
+    deno_fs::write_file(&marker, now().to_string(), 0o666)?;
+    Ok(())
+  }
+
+  /// Removes the entries that are stale and were stored or last revalidated
+  /// before `stored_before`, in seconds since the unix epoch.
+  fn prune(&self, stored_before: u64) -> Result<(), ErrBox> {
+    let metadata_files = WalkDir::new(&self.location)
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|entry| {
+        entry.file_type().is_file()
+          && entry
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".metadata.json")
+      });
+    for entry in metadata_files {
+      let metadata_filename = entry.path();
+      let response: CachedResponse = match fs::read_to_string(metadata_filename)
+        .ok()
+        .and_then(|metadata| serde_json::from_str(&metadata).ok())
+      {
+        Some(response) => response,
+        None => continue,
+      };
+      if response.is_fresh() || response.stored_at >= stored_before {
+        continue;
+      }
+      // The body goes first, so that an interrupted prune never leaves
+      // headers without their body.
+      let name = entry.file_name().to_string_lossy();
+      let entry_filename = metadata_filename
+        .with_file_name(name.trim_end_matches(".metadata.json"));
+      match fs::remove_file(&entry_filename) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+      }
+      fs::remove_file(metadata_filename)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Read;
+  use tempfile::TempDir;
+
+  fn headers(headers: &[(&str, &str)]) -> Headers {
+    headers
+      .iter()
+      .map(|(k, v)| (k.to_string(), v.to_string()))
+      .collect()
+  }
+
+  fn response(response_headers: &[(&str, &str)]) -> CachedResponse {
+    CachedResponse::new(
+      &Url::parse("https://deno.land/x/data.json").unwrap(),
+      200,
+      "OK",
+      headers(response_headers),
+    )
+  }
+
+  #[test]
+  fn test_cache_control() {
+    assert_eq!(
+      cache_control(&headers(&[("Cache-Control", "public, max-age=60")])),
+      CacheControl {
+        no_store: false,
+        no_cache: false,
+        max_age: Some(60),
+      }
+    );
+    assert_eq!(
+      cache_control(&headers(&[
+        ("cache-control", "no-cache"),
+        ("cache-control", "No-Store")
+      ])),
+      CacheControl {
+        no_store: true,
+        no_cache: true,
+        max_age: None,
+      }
+    );
+  }
+
+  #[test]
+  fn test_is_fresh() {
+    let mut res = response(&[("cache-control", "max-age=60")]);
+    assert!(res.is_fresh());
+    res.stored_at -= 61;
+    assert!(!res.is_fresh());
+    assert!(!response(&[("cache-control", "max-age=60, no-cache")]).is_fresh());
+    assert!(!response(&[("etag", "\"abc\"")]).is_fresh());
+  }
+
+  #[test]
+  fn test_is_storable() {
+    assert!(response(&[("cache-control", "max-age=60")]).is_storable());
+    assert!(response(&[("etag", "\"abc\"")]).is_storable());
+    assert!(!response(&[]).is_storable());
+    assert!(
+      !response(&[("cache-control", "no-store"), ("etag", "\"abc\"")])
+        .is_storable()
+    );
+    assert!(!response(&[("vary", "*"), ("etag", "\"abc\"")]).is_storable());
+    assert!(
+      !response(&[("set-cookie", "id=1"), ("etag", "\"abc\"")]).is_storable()
+    );
+  }
+
+  #[test]
+  fn test_is_cacheable_request() {
+    assert!(is_cacheable_request("GET", false, &[]));
+    assert!(!is_cacheable_request("POST", false, &[]));
+    assert!(!is_cacheable_request("GET", true, &[]));
+    assert!(!is_cacheable_request(
+      "GET",
+      false,
+      &headers(&[("cache-control", "no-store")])
+    ));
+    assert!(!is_cacheable_request(
+      "GET",
+      false,
+      &headers(&[("if-none-match", "\"abc\"")])
+    ));
+    assert!(!is_cacheable_request(
+      "GET",
+      false,
+      &headers(&[("Authorization", "Bearer secret")])
+    ));
+    assert!(!is_cacheable_request(
+      "GET",
+      false,
+      &headers(&[("cookie", "id=1")])
+    ));
+  }
+
+  #[test]
+  fn test_revalidate() {
+    let mut res = response(&[
+      ("etag", "\"abc\""),
+      ("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+      ("content-type", "application/json"),
+    ]);
+    assert_eq!(
+      res.revalidation_headers(),
+      headers(&[
+        ("if-none-match", "\"abc\""),
+        ("if-modified-since", "Wed, 21 Oct 2015 07:28:00 GMT"),
+      ])
+    );
+    res.stored_at -= 100;
+    res.revalidate(&headers(&[("ETag", "\"def\"")]));
+    assert!(now() - res.stored_at < 100);
+    assert_eq!(get_header(&res.headers, "etag"), Some("\"def\""));
+    assert_eq!(
+      get_header(&res.headers, "content-type"),
+      Some("application/json")
+    );
+  }
+
+  #[test]
+  fn test_get_set() {
+    let dir = TempDir::new().unwrap();
+    let cache = FetchCache::new(&dir.path().join("fetch"));
+    let url = Url::parse("https://deno.land/x/data.json").unwrap();
+    assert!(cache.get(&url, &[]).is_err());
+
+    let res = response(&[("cache-control", "max-age=60")]);
+    cache.set(&url, &[], &res, Some(b"{}")).unwrap();
+    let (cached, mut file) = cache.get(&url, &[]).unwrap();
+    assert_eq!(cached, res);
+    let mut body = String::new();
+    file.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "{}");
+  }
+
+  #[test]
+  fn test_get_set_vary() {
+    let dir = TempDir::new().unwrap();
+    let cache = FetchCache::new(dir.path());
+    let url = Url::parse("https://deno.land/x/data.json").unwrap();
+    let en = headers(&[("Accept-Language", "en")]);
+    let fr = headers(&[("accept-language", "fr")]);
+
+    let res = response(&[("etag", "\"en\""), ("vary", "Accept-Language")]);
+    cache.set(&url, &en, &res, Some(b"hello")).unwrap();
+    let res = response(&[("etag", "\"fr\""), ("vary", "Accept-Language")]);
+    cache.set(&url, &fr, &res, Some(b"bonjour")).unwrap();
+
+    let (cached, _) = cache.get(&url, &en).unwrap();
+    assert_eq!(get_header(&cached.headers, "etag"), Some("\"en\""));
+    let (cached, _) = cache.get(&url, &fr).unwrap();
+    assert_eq!(get_header(&cached.headers, "etag"), Some("\"fr\""));
+    assert!(cache.get(&url, &[]).is_err());
+  }
+
+  #[test]
+  fn test_prune() {
+    let dir = TempDir::new().unwrap();
+    let cache = FetchCache::new(dir.path());
+    let stale_url = Url::parse("https://deno.land/x/stale.json").unwrap();
+    let fresh_url = Url::parse("https://deno.land/x/fresh.json").unwrap();
+
+    let mut res = response(&[("etag", "\"abc\"")]);
+    res.stored_at -= PRUNE_AGE + 1;
+    cache.set(&stale_url, &[], &res, Some(b"{}")).unwrap();
+    let res = response(&[("cache-control", "max-age=60")]);
+    cache.set(&fresh_url, &[], &res, Some(b"{}")).unwrap();
+
+    cache.prune_if_due().unwrap();
+    assert!(cache.get(&stale_url, &[]).is_err());
+    assert!(cache.get(&fresh_url, &[]).is_ok());
+
+    // Pruned a moment ago, so the next call leaves the cache alone.
+    let mut res = response(&[("etag", "\"abc\"")]);
+    res.stored_at -= PRUNE_AGE + 1;
+    cache.set(&stale_url, &[], &res, Some(b"{}")).unwrap();
+    cache.prune_if_due().unwrap();
+    assert!(cache.get(&stale_url, &[]).is_ok());
+  }
+}
@@ -2,11 +2,16 @@
 use crate::colors;
 use crate::http_cache::HttpCache;
 use crate::http_util;
-use crate::http_util::create_http_client;
+use crate::http_util::DownloadHeaders;
 use crate::http_util::FetchOnceResult;
+use crate::log_category;
 use crate::msg;
+use crate::npm;
+use crate::npm::NpmResolver;
+use crate::op_error::ModuleNotFoundError;
 use crate::op_error::OpError;
 use crate::permissions::Permissions;
+use crate::source_maps;
 use deno_core::ErrBox;
 use deno_core::ModuleSpecifier;
 use futures::future::FutureExt;
@@ -59,7 +64,7 @@ impl SourceFileCache {
   }
 }
 
-const SUPPORTED_URL_SCHEMES: [&str; 3] = ["http", "https", "file"];
+const SUPPORTED_URL_SCHEMES: [&str; 4] = ["http", "https", "file", "npm"];
 
 #[derive(Clone)]
 pub struct SourceFileFetcher {
@@ -68,20 +73,31 @@ pub struct SourceFileFetcher {
   use_disk_cache: bool,
   no_remote: bool,
   cached_only: bool,
+  download_retries: u32,
+  download_headers: DownloadHeaders,
   http_client: reqwest::Client,
   // This field is public only to expose it's location
   pub http_cache: HttpCache,
+  pub npm_resolver: NpmResolver,
 }
 
 impl SourceFileFetcher {
   pub fn new(
     http_cache: HttpCache,
+    npm_cache_location: &Path,
     use_disk_cache: bool,
     cache_blacklist: Vec<String>,
     no_remote: bool,
     cached_only: bool,
-    ca_file: Option<String>,
+    http_client: reqwest::Client,
+    download_retries: u32,
+    download_headers: DownloadHeaders,
   ) -> Result<Self, ErrBox> {
+    let npm_resolver = NpmResolver::new(
+      npm_cache_location,
+      npm::registry_url(),
+      http_client.clone(),
+    );
     let file_fetcher = Self {
       http_cache,
       source_file_cache: SourceFileCache::default(),
@@ -89,7 +105,10 @@ impl SourceFileFetcher {
       use_disk_cache,
       no_remote,
       cached_only,
-      http_client: create_http_client(ca_file)?,
+      download_retries,
+      download_headers,
+      http_client,
+      npm_resolver,
     };
 
     Ok(file_fetcher)
@@ -147,6 +166,18 @@ impl SourceFileFetcher {
     self.source_file_cache.set(specifier.to_string(), file);
   }
 
+  /// Returns the paths of the local files fetched so far. Files of npm
+  /// packages are left out.
+  pub fn fetched_local_files(&self) -> Vec<PathBuf> {
+    let cache = self.source_file_cache.0.lock().unwrap();
+    cache
+      .values()
+      .filter(|file| file.url.scheme() == "file")
+      .filter(|file| !self.npm_resolver.is_package_file(&file.filename))
+      .map(|file| file.filename.clone())
+      .collect()
+  }
+
   pub async fn fetch_source_file(
     &self,
     specifier: &ModuleSpecifier,
@@ -154,7 +185,11 @@ impl SourceFileFetcher {
     permissions: Permissions,
   ) -> Result<SourceFile, ErrBox> {
     let module_url = specifier.as_url().to_owned();
-    debug!("fetch_source_file specifier: {} ", &module_url);
+    debug!(
+      target: log_category::CACHE,
+      "fetch_source_file specifier: {} ",
+      &module_url
+    );
 
     // Check if this file was already fetched and can be retrieved from in-process cache.
     let maybe_cached_file = self.source_file_cache.get(specifier.to_string());
@@ -185,6 +220,10 @@ impl SourceFileFetcher {
         // Cache in-process for subsequent access.
         source_file_cache.set(specifier_.to_string(), file.clone());
 
+        if let "http" | "https" = module_url.scheme() {
+          self.fetch_declared_source_map(&file, &permissions).await;
+        }
+
         Ok(file)
       }
       Err(err) => {
@@ -209,13 +248,13 @@ impl SourceFileFetcher {
             r#"Cannot find module "{}"{} in cache, --cached-only is specified"#,
             module_url, referrer_suffix
           );
-          OpError::not_found(msg).into()
+          ModuleNotFoundError::new(msg).into()
         } else if is_not_found {
           let msg = format!(
             r#"Cannot resolve module "{}"{}"#,
             module_url, referrer_suffix
           );
-          OpError::not_found(msg).into()
+          ModuleNotFoundError::new(msg).into()
         } else {
           err
         };
@@ -224,6 +263,45 @@ impl SourceFileFetcher {
     }
   }
 
+  /// Downloads the source map a remote module refers to, if any, so that it
+  /// is in the cache when errors thrown by the module are source mapped.
+  /// Failing to fetch it only means that the generated code is shown.
+  async fn fetch_declared_source_map(
+    &self,
+    file: &SourceFile,
+    permissions: &Permissions,
+  ) {
+    let map_url = match source_maps::find_source_mapping_url(&file.source_code)
+    {
+      Some(map_url) if !map_url.starts_with("data:") => map_url,
+      _ => return,
+    };
+    let map_url = match file.url.join(&map_url) {
+      Ok(map_url) if map_url.scheme() != "file" => map_url,
+      _ => return,
+    };
+    let result = self
+      .get_source_file(
+        &map_url,
+        self.use_disk_cache,
+        self.no_remote,
+        self.cached_only,
+        permissions,
+      )
+      .await;
+    match result {
+      Ok(map_file) => {
+        self.source_file_cache.set(map_url.to_string(), map_file);
+      }
+      Err(err) => debug!(
+        target: log_category::CACHE,
+        "Unable to fetch source map {}: {}",
+        map_url,
+        err
+      ),
+    }
+  }
+
   fn get_source_file_from_local_cache(
     &self,
     module_url: &Url,
@@ -238,6 +316,15 @@ impl SourceFileFetcher {
       return self.fetch_local_file(&module_url, permissions).map(Some);
     }
 
+    if url_scheme == "npm" {
+      return match self.npm_resolver.resolve_cached(&module_url)? {
+        Some(path) => self
+          .fetch_local_file(&Url::from_file_path(path).unwrap(), permissions)
+          .map(Some),
+        None => Ok(None),
+      };
+    }
+
     self.fetch_cached_remote_source(&module_url)
   }
 
@@ -281,6 +368,17 @@ impl SourceFileFetcher {
       return Err(e.into());
     }
 
+    // npm packages are unpacked into DENO_DIR and served from there; the
+    // resulting file URL acts like the target of a redirect.
+    if url_scheme == "npm" {
+      let path = self
+        .npm_resolver
+        .resolve(module_url, use_disk_cache, cached_only, permissions)
+        .await?;
+      return self
+        .fetch_local_file(&Url::from_file_path(path).unwrap(), permissions);
+    }
+
     // Fetch remote file and cache on-disk for subsequent access
     self
       .fetch_remote_source(
@@ -305,7 +403,10 @@ impl SourceFileFetcher {
       ))
     })?;
 
-    permissions.check_read(&filepath)?;
+    self
+      .npm_resolver
+      .module_permissions(permissions)
+      .check_read(&filepath)?;
     let source_code = match fs::read(filepath.clone()) {
       Ok(c) => c,
       Err(e) => return Err(e.into()),
@@ -412,6 +513,27 @@ impl SourceFileFetcher {
     cached_only: bool,
     redirect_limit: i64,
     permissions: &Permissions,
+  ) -> Pin<Box<dyn Future<Output = Result<SourceFile, ErrBox>>>> {
+    self.fetch_remote_source_with_headers(
+      module_url,
+      use_disk_cache,
+      cached_only,
+      redirect_limit,
+      permissions,
+      self.download_headers.clone(),
+    )
+  }
+
+  /// Like `fetch_remote_source`, but only sends the given download headers,
+  /// which are dropped once a redirect leaves the origin of the first URL.
+  fn fetch_remote_source_with_headers(
+    &self,
+    module_url: &Url,
+    use_disk_cache: bool,
+    cached_only: bool,
+    redirect_limit: i64,
+    permissions: &Permissions,
+    download_headers: DownloadHeaders,
   ) -> Pin<Box<dyn Future<Output = Result<SourceFile, ErrBox>>>> {
     if redirect_limit < 0 {
       let e = OpError::http("too many redirects".to_string());
@@ -469,9 +591,17 @@ impl SourceFileFetcher {
     };
     let permissions = permissions.clone();
     let http_client = self.http_client.clone();
+    let download_retries = self.download_retries;
     // Single pass fetch, either yields code or yields redirect.
     let f = async move {
-      match http_util::fetch_once(http_client, &module_url, module_etag).await?
+      match http_util::fetch_once_with_retries(
+        http_client,
+        &module_url,
+        module_etag,
+        &download_headers.for_url(&module_url),
+        download_retries,
+      )
+      .await?
       {
         FetchOnceResult::NotModified => {
           let source_file =
@@ -483,14 +613,23 @@ impl SourceFileFetcher {
           // If redirects, update module_name and filename for next looped call.
           dir.http_cache.set(&module_url, headers, &[])?;
 
+          // Don't leak the headers meant for this origin to another one.
+          let download_headers =
+            if new_module_url.origin() == module_url.origin() {
+              download_headers
+            } else {
+              DownloadHeaders::default()
+            };
+
           // Recurse
           dir
-            .fetch_remote_source(
+            .fetch_remote_source_with_headers(
               &new_module_url,
               use_disk_cache,
               cached_only,
               redirect_limit - 1,
               &permissions,
+              download_headers,
             )
             .await
         }
@@ -690,16 +829,21 @@ pub struct SourceCodeHeaders {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::http_util::create_http_client;
+  use crate::http_util::PoolOptions;
   use tempfile::TempDir;
 
   fn setup_file_fetcher(dir_path: &Path) -> SourceFileFetcher {
     SourceFileFetcher::new(
       HttpCache::new(&dir_path.to_path_buf().join("deps")),
+      &dir_path.join("npm"),
       true,
       vec![],
       false,
       false,
-      None,
+      create_http_client(None, &PoolOptions::default()).unwrap(),
+      0,
+      DownloadHeaders::default(),
     )
     .expect("setup fail")
   }
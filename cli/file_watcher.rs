@@ -0,0 +1,186 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Restarts a program whenever one of its local source files changes.
+//!
+//! Used by `deno run --watch`.
+
+use crate::colors;
+use crate::op_error::OpError;
+use deno_core::v8;
+use deno_core::ErrBox;
+use futures::future::select;
+use futures::future::Either;
+use futures::Future;
+use notify::event::Event as NotifyEvent;
+use notify::event::EventKind;
+use notify::Error as NotifyError;
+use notify::RecommendedWatcher;
+use notify::RecursiveMode;
+use notify::Watcher;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Editors often write a file several times when saving it, so events
+/// arriving within this interval of the first one are folded into it.
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(200);
+
+pub type WatchedFuture = Pin<Box<dyn Future<Output = Result<(), ErrBox>>>>;
+
+/// Watches a set of files for modifications.
+pub struct FileWatcher {
+  #[allow(unused)]
+  watcher: RecommendedWatcher,
+  receiver: mpsc::Receiver<Result<NotifyEvent, NotifyError>>,
+  /// Isolate to terminate on the first change, see `terminate_on_change`.
+  isolate_handle: Arc<Mutex<Option<v8::IsolateHandle>>>,
+  terminated: Arc<AtomicBool>,
+}
+
+/// Accesses, and kinds the backend can't classify, aren't changes.
+fn is_change(kind: &EventKind) -> bool {
+  match kind {
+    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => true,
+    _ => false,
+  }
+}
+
+impl FileWatcher {
+  /// Starts watching `paths`. Paths that don't exist are skipped.
+  pub fn new(paths: &[PathBuf]) -> Result<Self, ErrBox> {
+    let (sender, receiver) = mpsc::channel(16);
+    let sender = Mutex::new(sender);
+    let isolate_handle = Arc::new(Mutex::new(None));
+    let terminated = Arc::new(AtomicBool::new(false));
+    let isolate_handle_ = isolate_handle.clone();
+    let terminated_ = terminated.clone();
+    let mut watcher: RecommendedWatcher =
+      Watcher::new_immediate(move |res: Result<NotifyEvent, NotifyError>| {
+        // This runs on the watcher's own thread, so it's called even while
+        // the program keeps the main thread busy.
+        if let Ok(event) = &res {
+          if is_change(&event.kind) {
+            let handle: Option<v8::IsolateHandle> =
+              isolate_handle_.lock().unwrap().take();
+            if let Some(handle) = handle {
+              terminated_.store(true, Ordering::SeqCst);
+              handle.terminate_execution();
+            }
+          }
+        }
+        let mut sender = sender.lock().unwrap();
+        // The receiver is gone once the watcher is dropped.
+        let _ = sender.try_send(res);
+      })
+      .map_err(ErrBox::from)?;
+    for path in paths.iter().filter(|p| p.exists()) {
+      watcher
+        .watch(path, RecursiveMode::NonRecursive)
+        .map_err(ErrBox::from)?;
+    }
+    Ok(Self {
+      watcher,
+      receiver,
+      isolate_handle,
+      terminated,
+    })
+  }
+
+  /// Terminates the JavaScript running in the isolate of `isolate_handle` as
+  /// soon as one of the watched files changes.
+  pub fn terminate_on_change(&self, isolate_handle: v8::IsolateHandle) {
+    *self.isolate_handle.lock().unwrap() = Some(isolate_handle);
+  }
+
+  /// Resolves once one of the watched files is created, modified or removed.
+  pub async fn changed(&mut self) -> Result<(), ErrBox> {
+    loop {
+      match self.receiver.recv().await {
+        Some(Ok(event)) if is_change(&event.kind) => break,
+        Some(Ok(_)) => continue,
+        Some(Err(err)) => return Err(ErrBox::from(err)),
+        None => {
+          return Err(
+            OpError::other("File watcher stopped unexpectedly".to_string())
+              .into(),
+          )
+        }
+      }
+    }
+    tokio::time::delay_for(DEBOUNCE_INTERVAL).await;
+    while let Ok(event) = self.receiver.try_recv() {
+      event.map_err(ErrBox::from)?;
+    }
+    Ok(())
+  }
+}
+
+/// Runs `fut` until it completes or one of `paths` changes, whichever
+/// happens first. If the program exits on its own, with or without an error,
+/// this keeps waiting for a change so that the caller can restart it.
+///
+/// A change terminates the JavaScript running in the isolate of
+/// `isolate_handle`, so that a busy program stops too. `fut` is then
+/// dropped, which tears down that isolate, before this returns.
+pub async fn run_until_change(
+  paths: &[PathBuf],
+  fut: WatchedFuture,
+  isolate_handle: v8::IsolateHandle,
+) -> Result<(), ErrBox> {
+  let mut watcher = FileWatcher::new(paths)?;
+  watcher.terminate_on_change(isolate_handle);
+  let terminated = watcher.terminated.clone();
+  let finished = {
+    let changed = watcher.changed();
+    futures::pin_mut!(changed);
+    match select(fut, changed).await {
+      Either::Left((result, changed)) => {
+        // A terminated program finishes with an error before the change
+        // reaches this thread; it's a change all the same.
+        if terminated.load(Ordering::SeqCst) {
+          changed.await?;
+          None
+        } else {
+          Some(result)
+        }
+      }
+      Either::Right((changed, _)) => {
+        changed?;
+        None
+      }
+    }
+  };
+
+  if let Some(result) = finished {
+    if let Err(err) = result {
+      eprintln!("{}: {}", colors::red_bold("error".to_string()), err);
+    }
+    info!(
+      "{} Process finished. Restarting on file change...",
+      colors::green("Watcher".to_string())
+    );
+    watcher.changed().await?;
+  }
+
+  info!(
+    "{} File change detected! Restarting!",
+    colors::green("Watcher".to_string())
+  );
+  Ok(())
+}
+
+/// Resolves once one of `paths` changes. Used when the program can't be
+/// started at all, e.g. because of a syntax error in one of its modules.
+pub async fn wait_for_change(paths: &[PathBuf]) -> Result<(), ErrBox> {
+  FileWatcher::new(paths)?.changed().await?;
+  info!(
+    "{} File change detected! Restarting!",
+    colors::green("Watcher".to_string())
+  );
+  Ok(())
+}
@@ -13,8 +13,22 @@ macro_rules! svec {
     ($($x:expr),*) => (vec![$($x.to_string()),*]);
 }
 
+/// The format `deno test` reports the results in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TestReporter {
+  Pretty,
+  Tap,
+  Junit,
+  Json,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum DenoSubcommand {
+  Bench {
+    allow_none: bool,
+    include: Option<Vec<String>>,
+    filter: Option<String>,
+  },
   Bundle {
     source_file: String,
     out_file: Option<PathBuf>,
@@ -22,6 +36,10 @@ pub enum DenoSubcommand {
   Completions {
     buf: Box<[u8]>,
   },
+  Coverage {
+    dir: PathBuf,
+    lcov: bool,
+  },
   Doc {
     json: bool,
     source_file: Option<String>,
@@ -37,6 +55,9 @@ pub enum DenoSubcommand {
   Fmt {
     check: bool,
     files: Vec<String>,
+    /// Extensions besides JavaScript and TypeScript to format when walking
+    /// directories and matching globs, like `json` and `md`.
+    ext: Vec<String>,
   },
   Help,
   Info {
@@ -49,28 +70,54 @@ pub enum DenoSubcommand {
     root: Option<PathBuf>,
     force: bool,
   },
-  Repl,
+  Lint {
+    files: Vec<String>,
+    json: bool,
+  },
+  Uninstall {
+    name: String,
+    root: Option<PathBuf>,
+  },
+  Repl {
+    eval: Option<String>,
+    eval_files: Vec<String>,
+  },
   Run {
     script: String,
   },
+  Task {
+    task: Option<String>,
+    args: Vec<String>,
+  },
   Test {
     fail_fast: bool,
     quiet: bool,
     allow_none: bool,
     include: Option<Vec<String>>,
     filter: Option<String>,
+    jobs: usize,
+    reporter: TestReporter,
   },
   Types,
   Upgrade {
     dry_run: bool,
     force: bool,
     version: Option<String>,
+    allow_unverified: bool,
+  },
+  Xeval {
+    code: String,
+    delimiter: Option<String>,
+    replvar: Option<String>,
   },
 }
 
 impl Default for DenoSubcommand {
   fn default() -> DenoSubcommand {
-    DenoSubcommand::Repl
+    DenoSubcommand::Repl {
+      eval: None,
+      eval_files: vec![],
+    }
   }
 }
 
@@ -92,21 +139,43 @@ pub struct Flags {
   pub ca_file: Option<String>,
   pub cached_only: bool,
   pub config_path: Option<String>,
+  pub coverage_dir: Option<PathBuf>,
+  pub download_headers: Vec<String>,
+  pub download_retries: Option<u32>,
+  pub env_file: Option<String>,
+  pub fetch_cache: bool,
+  /// The time `Date.now()` starts at with `--frozen-time`, in milliseconds
+  /// since the epoch.
+  pub frozen_time: Option<u64>,
+  pub http_pool_idle_timeout: Option<u64>,
+  pub http_pool_max_idle_per_host: Option<usize>,
   pub import_map_path: Option<String>,
   pub inspect: Option<SocketAddr>,
   pub inspect_brk: Option<SocketAddr>,
+  pub json_errors: bool,
   pub lock: Option<String>,
   pub lock_write: bool,
+  /// The categories of debug output enabled by `--log`.
+  pub log_categories: Vec<String>,
   pub log_level: Option<Level>,
   pub net_whitelist: Vec<String>,
+  pub no_check: bool,
+  pub no_color: bool,
   pub no_prompts: bool,
   pub no_remote: bool,
+  pub op_stack_traces: bool,
+  /// Where to write the CPU profile of the program, with `--prof`.
+  pub prof: Option<PathBuf>,
   pub read_whitelist: Vec<PathBuf>,
   pub reload: bool,
   pub seed: Option<u64>,
+  pub strict_resources: bool,
+  /// Where to write the trace of the ops of the program, with `--trace-ops`.
+  pub trace_ops: Option<PathBuf>,
   pub unstable: bool,
   pub v8_flags: Option<Vec<String>>,
   pub version: bool,
+  pub watch: bool,
   pub write_whitelist: Vec<PathBuf>,
 }
 
@@ -172,11 +241,19 @@ impl Flags {
   }
 }
 
+/// Used by `deno test --coverage` and `deno coverage` when no directory is
+/// given.
+const DEFAULT_COVERAGE_DIR: &str = "coverage";
+
 static ENV_VARIABLES_HELP: &str = "ENVIRONMENT VARIABLES:
     DENO_DIR             Set deno's base directory (defaults to $HOME/.deno)
     DENO_INSTALL_ROOT    Set deno install's output directory
                          (defaults to $HOME/.deno/bin)
+    DENO_REPL_HISTORY    Set the REPL history file, or disable the history
+                         when empty (defaults to $DENO_DIR/deno_history.txt)
     NO_COLOR             Set to disable color
+    NPM_CONFIG_REGISTRY  Registry used for npm: specifiers
+                         (defaults to https://registry.npmjs.org)
     HTTP_PROXY           Proxy address for HTTP requests
                          (module downloads, fetch)
     HTTPS_PROXY          Same but for HTTPS";
@@ -224,17 +301,30 @@ pub fn flags_from_vec_safe(args: Vec<String>) -> clap::Result<Flags> {
 
   if matches.is_present("log-level") {
     flags.log_level = match matches.value_of("log-level").unwrap() {
-      "debug" => Some(Level::Debug),
+      "error" => Some(Level::Error),
+      "warn" => Some(Level::Warn),
       "info" => Some(Level::Info),
+      "debug" => Some(Level::Debug),
       _ => unreachable!(),
     };
   }
   if matches.is_present("quiet") {
     flags.log_level = Some(Level::Error);
   }
+  if let Some(categories) = matches.values_of("log") {
+    flags.log_categories = categories.map(String::from).collect();
+  }
+  if matches.is_present("json-errors") {
+    flags.json_errors = true;
+  }
+  if matches.is_present("no-color") {
+    flags.no_color = true;
+  }
 
   if let Some(m) = matches.subcommand_matches("run") {
     run_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("bench") {
+    bench_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("fmt") {
     fmt_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("types") {
@@ -251,14 +341,24 @@ pub fn flags_from_vec_safe(args: Vec<String>) -> clap::Result<Flags> {
     bundle_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("install") {
     install_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("lint") {
+    lint_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("uninstall") {
+    uninstall_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("completions") {
     completions_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("coverage") {
+    coverage_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("task") {
+    task_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("test") {
     test_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("upgrade") {
     upgrade_parse(&mut flags, m);
   } else if let Some(m) = matches.subcommand_matches("doc") {
     doc_parse(&mut flags, m);
+  } else if let Some(m) = matches.subcommand_matches("xeval") {
+    xeval_parse(&mut flags, m);
   } else {
     repl_parse(&mut flags, &matches);
   }
@@ -284,8 +384,35 @@ fn clap_root<'a, 'b>() -> App<'a, 'b> {
         .short("L")
         .long("log-level")
         .help("Set log level")
+        .long_help(
+          "Set log level
+Diagnostic messages are written to stderr and are filtered by their level:
+errors, warnings, informational messages (such as downloads and
+compilations) and debug output. Defaults to info.",
+        )
+        .takes_value(true)
+        .possible_values(&["error", "warn", "info", "debug"])
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("log")
+        .long("log")
         .takes_value(true)
-        .possible_values(&["debug", "info"])
+        .use_delimiter(true)
+        .require_equals(true)
+        .value_name("CATEGORIES")
+        .possible_values(crate::log_category::ALL)
+        .help("Enable the debug output of some categories")
+        .long_help(
+          "Enable the debug output of some categories
+Shows the debug messages of the given categories without the debug output of
+everything else:
+  ops    the ops, with their arguments
+  net    HTTP requests and sockets
+  cache  the module cache, the lock file and the cache of fetch()
+
+  deno run --log=net,cache https://deno.land/std/examples/welcome.ts",
+        )
         .global(true),
     )
     .arg(
@@ -300,19 +427,48 @@ If the flag is set, restrict these messages to errors.",
         )
         .global(true),
     )
+    .arg(
+      Arg::with_name("json-errors")
+        .long("json-errors")
+        .help("Print fatal errors as JSON")
+        .long_help(
+          "Print fatal errors as JSON
+Instead of colored text, a fatal error is written to stderr as a single line
+of JSON with its kind, message, code frame and source mapped stack, so that
+editors and CI systems can parse it.",
+        )
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("no-color")
+        .long("no-color")
+        .help("Disable colored output")
+        .long_help(
+          "Disable colored output
+Has the same effect as setting the NO_COLOR environment variable. Colors are
+also disabled when stdout or stderr is not a terminal.",
+        )
+        .global(true),
+    )
+    .subcommand(bench_subcommand())
     .subcommand(bundle_subcommand())
     .subcommand(completions_subcommand())
+    .subcommand(coverage_subcommand())
     .subcommand(eval_subcommand())
     .subcommand(cache_subcommand())
     .subcommand(fmt_subcommand())
     .subcommand(info_subcommand())
     .subcommand(install_subcommand())
+    .subcommand(lint_subcommand())
     .subcommand(repl_subcommand())
     .subcommand(run_subcommand())
+    .subcommand(task_subcommand())
     .subcommand(test_subcommand())
     .subcommand(types_subcommand())
+    .subcommand(uninstall_subcommand())
     .subcommand(upgrade_subcommand())
     .subcommand(doc_subcommand())
+    .subcommand(xeval_subcommand())
     .long_about(DENO_HELP)
     .after_help(ENV_VARIABLES_HELP)
 }
@@ -327,15 +483,23 @@ fn fmt_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
     Some(f) => f.map(String::from).collect(),
     None => vec![],
   };
+  let ext = match matches.values_of("ext") {
+    Some(e) => e.map(String::from).collect(),
+    None => vec![],
+  };
   flags.subcommand = DenoSubcommand::Fmt {
     check: matches.is_present("check"),
     files,
+    ext,
   }
 }
 
 fn install_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   permission_args_parse(flags, matches);
   ca_file_arg_parse(flags, matches);
+  download_retries_arg_parse(flags, matches);
+  download_header_arg_parse(flags, matches);
+  http_pool_args_parse(flags, matches);
   unstable_arg_parse(flags, matches);
 
   let root = if matches.is_present("root") {
@@ -365,8 +529,39 @@ fn install_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   };
 }
 
+fn lint_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  let files = match matches.values_of("files") {
+    Some(f) => f.map(String::from).collect(),
+    None => vec![],
+  };
+  flags.subcommand = DenoSubcommand::Lint {
+    files,
+    json: matches.is_present("json"),
+  }
+}
+
+fn task_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  let args = match matches.values_of("task_args") {
+    Some(args) => args.map(String::from).collect(),
+    None => vec![],
+  };
+  flags.subcommand = DenoSubcommand::Task {
+    task: matches.value_of("task").map(String::from),
+    args,
+  }
+}
+
+fn uninstall_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  let root = matches.value_of("root").map(PathBuf::from);
+  let name = matches.value_of("name").unwrap().to_string();
+  flags.subcommand = DenoSubcommand::Uninstall { name, root };
+}
+
 fn bundle_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   ca_file_arg_parse(flags, matches);
+  download_retries_arg_parse(flags, matches);
+  download_header_arg_parse(flags, matches);
+  http_pool_args_parse(flags, matches);
   config_arg_parse(flags, matches);
   importmap_arg_parse(flags, matches);
   unstable_arg_parse(flags, matches);
@@ -404,9 +599,16 @@ fn completions_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
 fn repl_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   v8_flags_arg_parse(flags, matches);
   ca_file_arg_parse(flags, matches);
+  download_retries_arg_parse(flags, matches);
+  download_header_arg_parse(flags, matches);
+  http_pool_args_parse(flags, matches);
   inspect_arg_parse(flags, matches);
   unstable_arg_parse(flags, matches);
-  flags.subcommand = DenoSubcommand::Repl;
+  let eval = matches.value_of("eval").map(ToOwned::to_owned);
+  let eval_files = matches
+    .values_of("eval-file")
+    .map_or_else(Vec::new, |files| files.map(String::from).collect());
+  flags.subcommand = DenoSubcommand::Repl { eval, eval_files };
   flags.allow_net = true;
   flags.allow_env = true;
   flags.allow_run = true;
@@ -419,15 +621,13 @@ fn repl_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
 fn eval_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   v8_flags_arg_parse(flags, matches);
   ca_file_arg_parse(flags, matches);
+  download_retries_arg_parse(flags, matches);
+  download_header_arg_parse(flags, matches);
+  http_pool_args_parse(flags, matches);
   inspect_arg_parse(flags, matches);
   unstable_arg_parse(flags, matches);
-  flags.allow_net = true;
-  flags.allow_env = true;
-  flags.allow_run = true;
-  flags.allow_read = true;
-  flags.allow_write = true;
-  flags.allow_plugin = true;
-  flags.allow_hrtime = true;
+  implicit_permission_args_parse(flags, matches);
+  script_args_parse(flags, matches);
   let code = matches.value_of("code").unwrap().to_string();
   let as_typescript = matches.is_present("ts");
   flags.subcommand = DenoSubcommand::Eval {
@@ -436,8 +636,45 @@ fn eval_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   }
 }
 
+fn xeval_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  v8_flags_arg_parse(flags, matches);
+  ca_file_arg_parse(flags, matches);
+  download_retries_arg_parse(flags, matches);
+  download_header_arg_parse(flags, matches);
+  http_pool_args_parse(flags, matches);
+  unstable_arg_parse(flags, matches);
+  implicit_permission_args_parse(flags, matches);
+  flags.subcommand = DenoSubcommand::Xeval {
+    code: matches.value_of("code").unwrap().to_string(),
+    delimiter: matches.value_of("delim").map(String::from),
+    replvar: matches.value_of("replvar").map(String::from),
+  }
+}
+
+/// Commands evaluating code from the command line have implicit access to
+/// all permissions, unless permission flags are given.
+fn implicit_permission_args_parse(
+  flags: &mut Flags,
+  matches: &clap::ArgMatches,
+) {
+  if PERMISSION_ARGS.iter().any(|arg| matches.is_present(arg)) {
+    permission_args_parse(flags, matches);
+  } else {
+    flags.allow_net = true;
+    flags.allow_env = true;
+    flags.allow_run = true;
+    flags.allow_read = true;
+    flags.allow_write = true;
+    flags.allow_plugin = true;
+    flags.allow_hrtime = true;
+  }
+}
+
 fn info_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   ca_file_arg_parse(flags, matches);
+  download_retries_arg_parse(flags, matches);
+  download_header_arg_parse(flags, matches);
+  http_pool_args_parse(flags, matches);
   unstable_arg_parse(flags, matches);
 
   flags.subcommand = DenoSubcommand::Info {
@@ -450,8 +687,13 @@ fn cache_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   lock_args_parse(flags, matches);
   importmap_arg_parse(flags, matches);
   config_arg_parse(flags, matches);
+  env_file_arg_parse(flags, matches);
+  no_check_arg_parse(flags, matches);
   no_remote_arg_parse(flags, matches);
   ca_file_arg_parse(flags, matches);
+  download_retries_arg_parse(flags, matches);
+  download_header_arg_parse(flags, matches);
+  http_pool_args_parse(flags, matches);
   unstable_arg_parse(flags, matches);
   let files = matches
     .values_of("file")
@@ -477,10 +719,20 @@ fn run_test_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   lock_args_parse(flags, matches);
   importmap_arg_parse(flags, matches);
   config_arg_parse(flags, matches);
+  no_check_arg_parse(flags, matches);
   v8_flags_arg_parse(flags, matches);
   no_remote_arg_parse(flags, matches);
   permission_args_parse(flags, matches);
   ca_file_arg_parse(flags, matches);
+  download_retries_arg_parse(flags, matches);
+  download_header_arg_parse(flags, matches);
+  http_pool_args_parse(flags, matches);
+  fetch_cache_arg_parse(flags, matches);
+  op_stack_traces_arg_parse(flags, matches);
+  strict_resources_arg_parse(flags, matches);
+  frozen_time_arg_parse(flags, matches);
+  prof_arg_parse(flags, matches);
+  trace_ops_arg_parse(flags, matches);
   inspect_arg_parse(flags, matches);
   unstable_arg_parse(flags, matches);
 
@@ -508,6 +760,7 @@ fn run_test_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
 
 fn run_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   run_test_args_parse(flags, matches);
+  watch_arg_parse(flags, matches);
 
   let mut script: Vec<String> = matches
     .values_of("script_arg")
@@ -526,11 +779,25 @@ fn run_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
 
 fn test_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   run_test_args_parse(flags, matches);
+  script_args_parse(flags, matches);
 
   let failfast = matches.is_present("failfast");
   let allow_none = matches.is_present("allow_none");
   let quiet = matches.is_present("quiet");
   let filter = matches.value_of("filter").map(String::from);
+  let jobs = matches
+    .value_of("jobs")
+    .map_or(1, |jobs| jobs.parse::<usize>().unwrap());
+  let reporter = match matches.value_of("reporter") {
+    Some("tap") => TestReporter::Tap,
+    Some("junit") => TestReporter::Junit,
+    Some("json") => TestReporter::Json,
+    _ => TestReporter::Pretty,
+  };
+  if matches.is_present("coverage") {
+    let dir = matches.value_of("coverage").unwrap_or(DEFAULT_COVERAGE_DIR);
+    flags.coverage_dir = Some(PathBuf::from(dir));
+  }
   let include = if matches.is_present("files") {
     let files: Vec<String> = matches
       .values_of("files")
@@ -548,6 +815,34 @@ fn test_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
     include,
     filter,
     allow_none,
+    jobs,
+    reporter,
+  };
+}
+
+fn bench_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  run_test_args_parse(flags, matches);
+  script_args_parse(flags, matches);
+
+  let allow_none = matches.is_present("allow_none");
+  let filter = matches.value_of("filter").map(String::from);
+  let include = matches
+    .values_of("files")
+    .map(|files| files.map(String::from).collect());
+
+  flags.subcommand = DenoSubcommand::Bench {
+    allow_none,
+    include,
+    filter,
+  };
+}
+
+fn coverage_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  unstable_arg_parse(flags, matches);
+  let dir = matches.value_of("dir").unwrap_or(DEFAULT_COVERAGE_DIR);
+  flags.subcommand = DenoSubcommand::Coverage {
+    dir: PathBuf::from(dir),
+    lcov: matches.is_present("lcov"),
   };
 }
 
@@ -555,10 +850,12 @@ fn upgrade_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   let dry_run = matches.is_present("dry-run");
   let force = matches.is_present("force");
   let version = matches.value_of("version").map(|s| s.to_string());
+  let allow_unverified = matches.is_present("allow-unverified");
   flags.subcommand = DenoSubcommand::Upgrade {
     dry_run,
     force,
     version,
+    allow_unverified,
   };
 }
 
@@ -584,7 +881,11 @@ fn types_subcommand<'a, 'b>() -> App<'a, 'b> {
       "Print runtime TypeScript declarations.
   deno types > lib.deno.d.ts
 
-The declaration file could be saved and used for typing information.",
+Include the declarations of unstable APIs:
+  deno types --unstable > lib.deno.d.ts
+
+The declaration file could be saved and used for typing information. Its
+first line names the Deno and TypeScript versions it was generated for.",
     )
 }
 
@@ -592,11 +893,17 @@ fn fmt_subcommand<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("fmt")
     .about("Format source files")
     .long_about(
-      "Auto-format JavaScript/TypeScript source code.
+      "Auto-format JavaScript/TypeScript source code, JSON files and code blocks
+in Markdown files.
   deno fmt
   deno fmt myfile1.ts myfile2.ts
+  deno fmt \"src/**/*.ts\" tsconfig.json
   deno fmt --check
 
+JSON and Markdown files are formatted when given by name. Directories and
+globs only cover JavaScript and TypeScript unless told otherwise:
+  deno fmt --ext=json,md
+
 Format stdin and write to stdout:
   cat file.ts | deno fmt -
 
@@ -612,6 +919,16 @@ Ignore formatting a file by adding an ignore comment at the top of the file:
         .help("Check if the source files are formatted.")
         .takes_value(false),
     )
+    .arg(
+      Arg::with_name("ext")
+        .long("ext")
+        .takes_value(true)
+        .use_delimiter(true)
+        .require_equals(true)
+        .value_name("EXTENSIONS")
+        .possible_values(&["json", "md"])
+        .help("Also format JSON or Markdown files in directories and globs"),
+    )
     .arg(
       Arg::with_name("files")
         .takes_value(true)
@@ -623,8 +940,34 @@ Ignore formatting a file by adding an ignore comment at the top of the file:
 fn repl_subcommand<'a, 'b>() -> App<'a, 'b> {
   inspect_args(SubCommand::with_name("repl"))
     .about("Read Eval Print Loop")
+    .long_about(
+      "Read Eval Print Loop.
+
+Files and code given with --eval-file and --eval are evaluated as scripts
+before the first prompt, e.g. to define helpers for the session:
+  deno repl --eval-file=helpers.js --eval \"const db = connect()\"",
+    )
+    .arg(
+      Arg::with_name("eval-file")
+        .long("eval-file")
+        .value_name("FILE")
+        .help("Evaluate the given file when the REPL starts")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1),
+    )
+    .arg(
+      Arg::with_name("eval")
+        .long("eval")
+        .value_name("CODE")
+        .help("Evaluate the given code when the REPL starts")
+        .takes_value(true),
+    )
     .arg(v8_flags_arg())
     .arg(ca_file_arg())
+    .arg(download_retries_arg())
+    .arg(download_header_arg())
+    .args(&http_pool_args())
     .arg(unstable_arg())
 }
 
@@ -656,6 +999,9 @@ fn install_subcommand<'a, 'b>() -> App<'a, 'b> {
             .help("Forcefully overwrite existing installation")
             .takes_value(false))
         .arg(ca_file_arg())
+        .arg(download_retries_arg())
+        .arg(download_header_arg())
+        .args(&http_pool_args())
         .arg(unstable_arg())
         .about("Install script as an executable")
         .long_about(
@@ -684,6 +1030,96 @@ The installation root is determined, in order of precedence:
 These must be added to the path manually if required.")
 }
 
+fn lint_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("lint")
+    .about("Lint source files")
+    .long_about(
+      "Lint JavaScript/TypeScript source code.
+  deno lint
+  deno lint myfile1.ts myfile2.js
+  deno lint \"src/**/*.ts\"
+
+Print the diagnostics as JSON:
+  deno lint --json
+
+The checked rules are eqeqeq, no-debugger, no-empty, no-explicit-any,
+no-unused-vars and no-var.
+
+Ignore diagnostics on the next line by preceding it with an ignore comment
+and, optionally, the rules to ignore:
+  // deno-lint-ignore no-explicit-any
+
+Ignore linting a file by adding an ignore comment at the top of the file:
+  // deno-lint-ignore-file",
+    )
+    .arg(
+      Arg::with_name("json")
+        .long("json")
+        .help("Output lint diagnostics in JSON format")
+        .takes_value(false),
+    )
+    .arg(
+      Arg::with_name("files")
+        .takes_value(true)
+        .multiple(true)
+        .required(false),
+    )
+}
+
+fn task_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("task")
+    .setting(AppSettings::TrailingVarArg)
+    .about("Run a task defined in the configuration file")
+    .long_about(
+      "Run a task defined in the \"tasks\" field of the deno.json configuration
+file. Tasks run in the directory of that file.
+  deno task start
+
+List the available tasks:
+  deno task
+
+Extra arguments are appended to the task's command:
+  deno task test --filter \"parser\"
+
+Tasks are run by a small built-in shell that works the same on every platform.
+It supports quotes, $VAR and ${VAR} expansion, NAME=value assignments, pipes
+(|) and the &&, || and ; connectors. `deno` refers to the running executable.",
+    )
+    .arg(
+      Arg::with_name("task")
+        .help("Name of the task")
+        .required(false),
+    )
+    .arg(
+      Arg::with_name("task_args")
+        .multiple(true)
+        .required(false)
+        .requires("task"),
+    )
+}
+
+fn uninstall_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("uninstall")
+    .arg(Arg::with_name("name").required(true).takes_value(true))
+    .arg(
+      Arg::with_name("root")
+        .long("root")
+        .help("Installation root")
+        .takes_value(true)
+        .multiple(false),
+    )
+    .about("Uninstall a script previously installed with deno install")
+    .long_about(
+      "Removes an executable previously installed with 'deno install'.
+  deno uninstall file_server
+
+To remove an executable from a custom installation root, use --root:
+  deno uninstall --root /usr/local file_server
+
+The installation root is determined the same way as for 'deno install'.",
+    )
+}
+
 fn bundle_subcommand<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("bundle")
     .arg(
@@ -693,6 +1129,9 @@ fn bundle_subcommand<'a, 'b>() -> App<'a, 'b> {
     )
     .arg(Arg::with_name("out_file").takes_value(true).required(false))
     .arg(ca_file_arg())
+    .arg(download_retries_arg())
+    .arg(download_header_arg())
+    .args(&http_pool_args())
     .arg(importmap_arg())
     .arg(unstable_arg())
     .arg(config_arg())
@@ -718,13 +1157,23 @@ fn completions_subcommand<'a, 'b>() -> App<'a, 'b> {
     .long_about(
       "Output shell completion script to standard output.
   deno completions bash > /usr/local/etc/bash_completion.d/deno.bash
-  source /usr/local/etc/bash_completion.d/deno.bash",
+  source /usr/local/etc/bash_completion.d/deno.bash
+
+  deno completions zsh > ~/.zsh/_deno
+  deno completions fish > ~/.config/fish/completions/deno.fish
+  deno completions powershell >> $profile
+
+The script is generated from the flags of the running binary, so it has to
+be regenerated after upgrading deno.",
     )
 }
 
 fn eval_subcommand<'a, 'b>() -> App<'a, 'b> {
-  inspect_args(SubCommand::with_name("eval"))
+  permission_args(inspect_args(SubCommand::with_name("eval")))
     .arg(ca_file_arg())
+    .arg(download_retries_arg())
+    .arg(download_header_arg())
+    .args(&http_pool_args())
     .arg(unstable_arg())
     .about("Eval script")
     .long_about(
@@ -734,7 +1183,12 @@ fn eval_subcommand<'a, 'b>() -> App<'a, 'b> {
 To evaluate as TypeScript:
   deno eval -T \"const v: string = 'hello'; console.log(v)\"
 
-This command has implicit access to all permissions (--allow-all).",
+This command has implicit access to all permissions (--allow-all), unless
+permission flags are given:
+  deno eval --allow-net=deno.land \"fetch('https://deno.land')\"
+
+Arguments after '--' are passed to the code as Deno.args:
+  deno eval \"console.log(Deno.args)\" -- --foo bar",
     )
     .arg(
       Arg::with_name("ts")
@@ -745,7 +1199,55 @@ This command has implicit access to all permissions (--allow-all).",
         .multiple(false),
     )
     .arg(Arg::with_name("code").takes_value(true).required(true))
+    .arg(script_args_arg())
+    .arg(v8_flags_arg())
+}
+
+fn xeval_subcommand<'a, 'b>() -> App<'a, 'b> {
+  permission_args(SubCommand::with_name("xeval"))
+    .arg(ca_file_arg())
+    .arg(download_retries_arg())
+    .arg(download_header_arg())
+    .args(&http_pool_args())
+    .arg(unstable_arg())
     .arg(v8_flags_arg())
+    .about("Eval a script on every chunk of stdin")
+    .long_about(
+      "Evaluate a script for every line of stdin, bound to the variable $.
+  cat /etc/passwd | deno xeval \"a = $.split(':'); if (a) console.log(a[0])\"
+
+Split stdin on another delimiter:
+  echo 'a,b,c' | deno xeval -d , \"console.log($.trim())\"
+
+Bind chunks to another variable:
+  ls | deno xeval -I line \"console.log(line.toUpperCase())\"
+
+The script may use await; each chunk is fully processed before the next one
+is read. Like eval, this command has implicit access to all permissions
+(--allow-all), unless permission flags are given.",
+    )
+    .arg(
+      Arg::with_name("replvar")
+        .long("replvar")
+        .short("I")
+        .help("Set variable name to be used in eval, defaults to $")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("delim")
+        .long("delim")
+        .short("d")
+        .help("Set delimiter, defaults to newline")
+        .takes_value(true)
+        .validator(|val: String| {
+          if val.is_empty() {
+            Err("Delimiter can't be empty".to_string())
+          } else {
+            Ok(())
+          }
+        }),
+    )
+    .arg(Arg::with_name("code").takes_value(true).required(true))
 }
 
 fn info_subcommand<'a, 'b>() -> App<'a, 'b> {
@@ -773,6 +1275,9 @@ TypeScript compiler cache: Subdirectory containing TS compiler output.",
     )
     .arg(Arg::with_name("file").takes_value(true).required(false))
     .arg(ca_file_arg())
+    .arg(download_retries_arg())
+    .arg(download_header_arg())
+    .args(&http_pool_args())
     .arg(unstable_arg())
 }
 
@@ -784,6 +1289,8 @@ fn cache_subcommand<'a, 'b>() -> App<'a, 'b> {
     .arg(importmap_arg())
     .arg(unstable_arg())
     .arg(config_arg())
+    .arg(env_file_arg())
+    .arg(no_check_arg())
     .arg(no_remote_arg())
     .arg(
       Arg::with_name("file")
@@ -792,6 +1299,9 @@ fn cache_subcommand<'a, 'b>() -> App<'a, 'b> {
         .min_values(1),
     )
     .arg(ca_file_arg())
+    .arg(download_retries_arg())
+    .arg(download_header_arg())
+    .args(&http_pool_args())
     .about("Cache the dependencies")
     .long_about(
       "Cache and compile remote dependencies recursively.
@@ -814,7 +1324,9 @@ Defaults to latest.
 
 The version is downloaded from
 https://github.com/denoland/deno/releases
-and is used to replace the current executable.",
+and is used to replace the current executable. The download is verified
+against the SHA-256 checksum the release publishes for the archive. Releases
+without one are only installed with --allow-unverified.",
     )
     .arg(
       Arg::with_name("version")
@@ -833,6 +1345,11 @@ and is used to replace the current executable.",
         .short("f")
         .help("Replace current exe even if not out-of-date"),
     )
+    .arg(
+      Arg::with_name("allow-unverified")
+        .long("allow-unverified")
+        .help("Install a release that publishes no checksum of its archive"),
+    )
 }
 
 fn doc_subcommand<'a, 'b>() -> App<'a, 'b> {
@@ -873,11 +1390,21 @@ Show documentation for runtime built-ins:
         .help("Dot separated path to symbol.")
         .takes_value(true)
         .required(false)
-        .conflicts_with("json")
-        .conflicts_with("pretty"),
+        .conflicts_with("json"),
     )
 }
 
+const PERMISSION_ARGS: &[&str] = &[
+  "allow-read",
+  "allow-write",
+  "allow-net",
+  "allow-env",
+  "allow-run",
+  "allow-plugin",
+  "allow-hrtime",
+  "allow-all",
+];
+
 fn permission_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
   app
     .arg(
@@ -941,11 +1468,21 @@ fn run_test_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
     .arg(unstable_arg())
     .arg(reload_arg())
     .arg(config_arg())
+    .arg(no_check_arg())
     .arg(lock_arg())
     .arg(lock_write_arg())
     .arg(no_remote_arg())
     .arg(v8_flags_arg())
     .arg(ca_file_arg())
+    .arg(download_retries_arg())
+    .arg(download_header_arg())
+    .args(&http_pool_args())
+    .arg(fetch_cache_arg())
+    .arg(op_stack_traces_arg())
+    .arg(strict_resources_arg())
+    .arg(frozen_time_arg())
+    .arg(prof_arg())
+    .arg(trace_ops_arg())
     .arg(
       Arg::with_name("cached-only")
         .long("cached-only")
@@ -967,6 +1504,7 @@ fn run_test_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
 fn run_subcommand<'a, 'b>() -> App<'a, 'b> {
   run_test_args(SubCommand::with_name("run"))
     .setting(AppSettings::TrailingVarArg)
+    .arg(watch_arg())
     .arg(script_arg())
     .about("Run a program given a filename or url to the module")
     .long_about(
@@ -983,7 +1521,16 @@ Grant permission to read from disk and listen to network:
   deno run --allow-read --allow-net https://deno.land/std/http/file_server.ts
 
 Grant permission to read whitelisted files from disk:
-  deno run --allow-read=/etc https://deno.land/std/http/file_server.ts",
+  deno run --allow-read=/etc https://deno.land/std/http/file_server.ts
+
+Restart the program whenever one of its local files changes:
+  deno run --watch mod.ts
+
+Skip type checking to start faster:
+  deno run --no-check mod.ts
+
+Read the program from stdin:
+  echo \"console.log('hello')\" | deno run -",
     )
 }
 
@@ -1007,12 +1554,40 @@ fn test_subcommand<'a, 'b>() -> App<'a, 'b> {
         .takes_value(true)
         .help("A pattern to filter the tests to run by"),
     )
+    .arg(
+      Arg::with_name("coverage")
+        .long("coverage")
+        .value_name("DIR")
+        .takes_value(true)
+        .min_values(0)
+        .require_equals(true)
+        .help("Collect coverage into DIR (defaults to 'coverage')"),
+    )
+    .arg(
+      Arg::with_name("jobs")
+        .long("jobs")
+        .takes_value(true)
+        .value_name("N")
+        .help("Run the test modules in N processes")
+        .validator(|val: String| match val.parse::<usize>() {
+          Ok(jobs) if jobs > 0 => Ok(()),
+          _ => Err("Jobs should be a positive number".to_string()),
+        }),
+    )
+    .arg(
+      Arg::with_name("reporter")
+        .long("reporter")
+        .takes_value(true)
+        .possible_values(&["pretty", "tap", "junit", "json"])
+        .help("The format to report the results of the tests in"),
+    )
     .arg(
       Arg::with_name("files")
         .help("List of file names to run")
         .takes_value(true)
         .multiple(true),
     )
+    .arg(script_args_arg())
     .about("Run tests")
     .long_about(
       "Run tests using Deno's built-in test runner.
@@ -1023,10 +1598,108 @@ report results to standard output:
 
 Directory arguments are expanded to all contained files matching the glob
 {*_,*.,}test.{js,ts,jsx,tsx}:
-  deno test src/",
+  deno test src/
+
+Arguments after '--' are passed to the tests as Deno.args:
+  deno test src/ -- --update-snapshots
+
+The coverage of the tests can be collected, then reported by 'deno coverage'.
+Requires --unstable:
+  deno test --unstable --coverage=cov/ src/
+
+The test modules can be split across several processes, which run at the same
+time. The output of each test is then printed after its result:
+  deno test --jobs 4 src/
+
+The results can be reported as TAP, JUnit XML or JSON lines, for CI systems:
+  deno test --reporter=junit src/ > report.xml",
+    )
+}
+
+fn coverage_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("coverage")
+    .arg(unstable_arg())
+    .arg(
+      Arg::with_name("lcov")
+        .long("lcov")
+        .help("Output the report in the lcov format")
+        .takes_value(false),
+    )
+    .arg(
+      Arg::with_name("dir")
+        .help("Directory the coverage was collected into")
+        .takes_value(true),
+    )
+    .about("Report the coverage collected by 'deno test --coverage'")
+    .long_about(
+      "Report the line coverage of the modules run by 'deno test --coverage'.
+Requires --unstable.
+
+The coverage is mapped back to the original sources. Test modules and remote
+modules are left out:
+  deno coverage --unstable cov/
+
+The report can be written in the lcov format, for other tools to use:
+  deno coverage --unstable --lcov cov/ > cov.lcov",
+    )
+}
+
+fn bench_subcommand<'a, 'b>() -> App<'a, 'b> {
+  run_test_args(SubCommand::with_name("bench"))
+    .arg(
+      Arg::with_name("allow_none")
+        .long("allow-none")
+        .help("Don't return error code if no bench files are found")
+        .takes_value(false),
+    )
+    .arg(
+      Arg::with_name("filter")
+        .long("filter")
+        .takes_value(true)
+        .help("A pattern to filter the benchmarks to run by"),
+    )
+    .arg(
+      Arg::with_name("files")
+        .help("List of file names to run")
+        .takes_value(true)
+        .multiple(true),
+    )
+    .arg(script_args_arg())
+    .about("Run benchmarks")
+    .long_about(
+      "Run benchmarks using Deno's built-in bench runner. Requires --unstable.
+
+Evaluate the given modules, run all benchmarks declared with 'Deno.bench()'
+and report their statistics to standard output:
+  deno bench --unstable --allow-hrtime src/sort_bench.ts
+
+Each benchmark is warmed up, then run in samples of as many iterations as fit
+in about 10ms, for about a second. The mean, standard deviation, extremes and
+percentiles of the duration of one iteration are reported. Measuring requires
+the hrtime permission.
+
+Directory arguments are expanded to all contained files matching the glob
+{*_,*.,}bench.{js,ts,jsx,tsx}:
+  deno bench --unstable --allow-hrtime src/",
     )
 }
 
+/// Arguments following `--`, which are passed to the program as `Deno.args`
+/// by subcommands without a script argument.
+fn script_args_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("script_args")
+    .multiple(true)
+    .last(true)
+    .help("Arguments passed to the program as Deno.args")
+    .value_name("ARGS")
+}
+
+fn script_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if let Some(args) = matches.values_of("script_args") {
+    flags.argv.extend(args.map(String::from));
+  }
+}
+
 fn script_arg<'a, 'b>() -> Arg<'a, 'b> {
   Arg::with_name("script_arg")
     .multiple(true)
@@ -1062,18 +1735,178 @@ fn config_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
   flags.config_path = matches.value_of("config").map(ToOwned::to_owned);
 }
 
-fn ca_file_arg<'a, 'b>() -> Arg<'a, 'b> {
-  Arg::with_name("cert")
-    .long("cert")
+fn env_file_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("env-file")
+    .long("env-file")
     .value_name("FILE")
-    .help("Load certificate authority from PEM encoded file")
-    .takes_value(true)
-}
+    .help("Load environment variables from a .env file")
+    .long_help(
+      "Load environment variables from a .env file.
+
+Every line of the file is a KEY=VALUE pair. Empty lines and lines starting
+with # are ignored, and values may be quoted. Variables that are already set
+in the environment are left untouched.",
+    )
+    .takes_value(true)
+}
+
+fn env_file_arg_parse(flags: &mut Flags, matches: &ArgMatches) {
+  flags.env_file = matches.value_of("env-file").map(ToOwned::to_owned);
+}
+
+fn no_check_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("no-check")
+    .long("no-check")
+    .help("Skip type checking modules")
+    .long_help(
+      "Skip type checking modules.
+
+TypeScript modules are transpiled one by one, which is much faster than type
+checking the whole program. Only syntax errors are reported. Since every module
+is transpiled on its own, re-exported types have to use `export type`.",
+    )
+}
+
+fn no_check_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if matches.is_present("no-check") {
+    flags.no_check = true;
+  }
+}
+
+fn ca_file_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("cert")
+    .long("cert")
+    .value_name("FILE")
+    .help("Load certificate authority from PEM encoded file")
+    .takes_value(true)
+}
 
 fn ca_file_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   flags.ca_file = matches.value_of("cert").map(ToOwned::to_owned);
 }
 
+fn download_retries_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("download-retries")
+    .long("download-retries")
+    .value_name("NUMBER")
+    .help("Retry failed downloads of remote modules this many times")
+    .long_help(
+      "Retry downloads of remote modules that fail because of a timeout, a
+dropped connection or a server error this many times before giving up.
+Defaults to 2.",
+    )
+    .takes_value(true)
+    .validator(|val: String| match val.parse::<u32>() {
+      Ok(_) => Ok(()),
+      Err(_) => Err("Download retries should be a number".to_string()),
+    })
+}
+
+fn download_retries_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  flags.download_retries = matches
+    .value_of("download-retries")
+    .map(|val| val.parse().unwrap());
+}
+
+fn download_header_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("download-header")
+    .long("download-header")
+    .value_name("HEADER")
+    .help("Send this header with the downloads of remote modules from a host")
+    .long_help(
+      "Send this header, given as \"host=Name: value\", with the downloads of
+remote modules from that host over https. The host can include a port, or be
+an origin like \"http://localhost:4545\" to send the header over plain http.
+The header is not sent to other hosts, even when redirected to them. It can be
+repeated.
+Headers can also be given in the DENO_DOWNLOAD_HEADERS environment variable,
+one per line.",
+    )
+    .takes_value(true)
+    .multiple(true)
+    .number_of_values(1)
+    .validator(|val: String| match val.find('=') {
+      Some(index) if val[index..].contains(':') => Ok(()),
+      _ => Err("Header should be given as \"host=Name: value\"".to_string()),
+    })
+}
+
+fn download_header_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if let Some(headers) = matches.values_of("download-header") {
+    flags.download_headers = headers.map(String::from).collect();
+  }
+}
+
+fn http_pool_args<'a, 'b>() -> [Arg<'a, 'b>; 2] {
+  [
+    Arg::with_name("http-pool-idle-timeout")
+      .long("http-pool-idle-timeout")
+      .value_name("SECONDS")
+      .help("Close pooled HTTP connections after they are idle this long")
+      .takes_value(true)
+      .validator(|val: String| match val.parse::<u64>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Idle timeout should be a number".to_string()),
+      }),
+    Arg::with_name("http-pool-max-idle-per-host")
+      .long("http-pool-max-idle-per-host")
+      .value_name("NUMBER")
+      .help("Keep at most this many idle HTTP connections to each host")
+      .long_help(
+        "Keep at most this many idle HTTP connections to each host open for
+reuse. Module downloads and fetch() share one pool of connections, so
+repeated requests to the same host skip the TCP and TLS handshakes.",
+      )
+      .takes_value(true)
+      .validator(|val: String| match val.parse::<usize>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Max idle connections should be a number".to_string()),
+      }),
+  ]
+}
+
+fn http_pool_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  flags.http_pool_idle_timeout = matches
+    .value_of("http-pool-idle-timeout")
+    .map(|val| val.parse().unwrap());
+  flags.http_pool_max_idle_per_host = matches
+    .value_of("http-pool-max-idle-per-host")
+    .map(|val| val.parse().unwrap());
+}
+
+fn fetch_cache_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("fetch-cache")
+    .long("fetch-cache")
+    .help("Cache the responses of fetch() like a browser does")
+    .long_help(
+      "Store the responses of fetch() in DENO_DIR, apart from the module cache,
+as told by their Cache-Control, ETag and Last-Modified headers. Fresh responses
+are served from disk and stale ones are revalidated with the server.",
+    )
+}
+
+fn fetch_cache_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if matches.is_present("fetch-cache") {
+    flags.fetch_cache = true;
+  }
+}
+
+fn watch_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("watch")
+    .long("watch")
+    .help("Restart the program when one of its local files changes")
+    .long_help(
+      "Watch the local files of the module graph and restart the program
+whenever one of them is changed. Remote modules aren't watched.",
+    )
+}
+
+fn watch_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if matches.is_present("watch") {
+    flags.watch = true;
+  }
+}
+
 fn unstable_arg<'a, 'b>() -> Arg<'a, 'b> {
   Arg::with_name("unstable")
     .long("unstable")
@@ -1225,6 +2058,126 @@ fn no_remote_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   }
 }
 
+fn op_stack_traces_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("op-stack-traces")
+    .long("op-stack-traces")
+    .help("Add the stack that started an async op to the errors it throws")
+    .long_help(
+      "Capture the stack of the caller whenever an async op is started, and
+add it to the stack of the error thrown if the op fails. This makes errors
+from deep inside promise chains diagnosable, at the cost of capturing a stack
+for every async op.",
+    )
+}
+
+fn op_stack_traces_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if matches.is_present("op-stack-traces") {
+    flags.op_stack_traces = true;
+  }
+}
+
+fn strict_resources_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("strict-resources")
+    .long("strict-resources")
+    .help("Fail if resources are left open at exit")
+    .long_help(
+      "Record the stack of the code that created each resource, and fail at
+exit if resources other than stdin, stdout and stderr were never closed,
+listing each one with its type and the stack it was created from. This is
+meant for test runs, since a stack is captured for every op.",
+    )
+}
+
+fn strict_resources_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if matches.is_present("strict-resources") {
+    flags.strict_resources = true;
+  }
+}
+
+fn frozen_time_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("frozen-time")
+    .long("frozen-time")
+    .value_name("EPOCH_MS")
+    .takes_value(true)
+    .min_values(0)
+    .require_equals(true)
+    .help("Run on a virtual clock, advanced with Deno.advanceTime()")
+    .long_help(
+      "Drive Date.now(), performance.now() and the timers with a virtual clock
+that only moves when Deno.advanceTime() is called, so that code depending on
+time runs instantly and the same way every time. The clock starts at
+EPOCH_MS, in milliseconds since the Unix epoch, and defaults to the current
+time. Requires --unstable.
+  deno test --unstable --frozen-time=0 timers_test.ts",
+    )
+    .validator(|val: String| match val.parse::<u64>() {
+      Ok(_) => Ok(()),
+      Err(_) => Err("The frozen time should be a number".to_string()),
+    })
+}
+
+fn frozen_time_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if matches.is_present("frozen-time") {
+    let epoch_ms = match matches.value_of("frozen-time") {
+      Some(val) => val.parse::<u64>().unwrap(),
+      None => std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64,
+    };
+    flags.frozen_time = Some(epoch_ms);
+  }
+}
+
+fn prof_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("prof")
+    .long("prof")
+    .value_name("FILE")
+    .takes_value(true)
+    .require_equals(true)
+    .help("Write a CPU profile of the program to FILE")
+    .long_help(
+      "Profile the program with V8's sampling CPU profiler from its start,
+and write the profile to FILE once it is done, in the .cpuprofile format the
+Performance panel of Chrome DevTools loads. The locations of the functions
+are those of the TypeScript sources. The profile isn't written when the
+program exits with Deno.exit(). With deno test --jobs, each process writes
+its own profile, with its index before the extension of FILE. Requires
+--unstable.
+  deno run --unstable --prof=out.cpuprofile main.ts",
+    )
+}
+
+fn prof_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if let Some(path) = matches.value_of("prof") {
+    flags.prof = Some(PathBuf::from(path));
+  }
+}
+
+fn trace_ops_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("trace-ops")
+    .long("trace-ops")
+    .value_name("FILE")
+    .takes_value(true)
+    .require_equals(true)
+    .help("Write a timeline of the ops of the program to FILE")
+    .long_help(
+      "Record when each op is dispatched and completes, with its type and the
+size of its payload, and write them to FILE in the trace event format that
+chrome://tracing loads, which shows the ops that stall the event loop and the
+slow async ops. The ops of plugins and web workers aren't recorded. With
+deno test --jobs, each process writes its own timeline, with its index before
+the extension of FILE. Requires --unstable.
+  deno run --unstable --trace-ops=trace.json main.ts",
+    )
+}
+
+fn trace_ops_arg_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
+  if let Some(path) = matches.value_of("trace-ops") {
+    flags.trace_ops = Some(PathBuf::from(path));
+  }
+}
+
 fn permission_args_parse(flags: &mut Flags, matches: &clap::ArgMatches) {
   if let Some(read_wl) = matches.values_of("allow-read") {
     let read_whitelist: Vec<PathBuf> = read_wl.map(PathBuf::from).collect();
@@ -1353,11 +2306,23 @@ mod tests {
         subcommand: DenoSubcommand::Upgrade {
           force: true,
           dry_run: true,
-          version: None
+          version: None,
+          allow_unverified: false,
         },
         ..Flags::default()
       }
     );
+
+    let r = flags_from_vec_safe(svec!["deno", "upgrade", "--allow-unverified"]);
+    assert_eq!(
+      r.unwrap().subcommand,
+      DenoSubcommand::Upgrade {
+        force: false,
+        dry_run: false,
+        version: None,
+        allow_unverified: true,
+      }
+    );
   }
 
   #[test]
@@ -1384,6 +2349,128 @@ mod tests {
     );
   }
 
+  #[test]
+  fn run_op_stack_traces() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--op-stack-traces",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        op_stack_traces: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_strict_resources() {
+    let r = flags_from_vec_safe(svec!["deno", "test", "--strict-resources"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          quiet: false,
+          allow_none: false,
+          filter: None,
+          include: None,
+          jobs: 1,
+          reporter: TestReporter::Pretty,
+        },
+        strict_resources: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn run_watch() {
+    let r = flags_from_vec_safe(svec!["deno", "run", "--watch", "script.ts"]);
+    let flags = r.unwrap();
+    assert_eq!(
+      flags,
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        watch: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn env_file() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--env-file",
+      ".env",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        env_file: Some(".env".to_string()),
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn run_stdin() {
+    let r = flags_from_vec_safe(svec!["deno", "run", "-", "arg1"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "-".to_string(),
+        },
+        argv: svec!["arg1"],
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn no_check() {
+    let r =
+      flags_from_vec_safe(svec!["deno", "run", "--no-check", "script.ts"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        no_check: true,
+        ..Flags::default()
+      }
+    );
+
+    let r =
+      flags_from_vec_safe(svec!["deno", "cache", "--no-check", "script.ts"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Cache {
+          files: svec!["script.ts"],
+        },
+        no_check: true,
+        ..Flags::default()
+      }
+    );
+  }
+
   #[test]
   fn run_reload_allow_write() {
     let r = flags_from_vec_safe(svec![
@@ -1547,43 +2634,149 @@ mod tests {
   }
 
   #[test]
-  fn fmt() {
-    let r =
-      flags_from_vec_safe(svec!["deno", "fmt", "script_1.ts", "script_2.ts"]);
+  fn double_hyphen_before_script() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-read",
+      "--",
+      "script.ts",
+      "--allow-net",
+      "-L",
+      "debug"
+    ]);
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Fmt {
-          check: false,
-          files: vec!["script_1.ts".to_string(), "script_2.ts".to_string()]
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
         },
+        argv: svec!["--allow-net", "-L", "debug"],
+        allow_read: true,
         ..Flags::default()
       }
     );
+  }
 
-    let r = flags_from_vec_safe(svec!["deno", "fmt", "--check"]);
+  #[test]
+  fn eval_script_args() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "eval",
+      "console.log(Deno.args)",
+      "--",
+      "--allow-net",
+      "arg"
+    ]);
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Fmt {
-          check: true,
-          files: vec![],
+        subcommand: DenoSubcommand::Eval {
+          code: "console.log(Deno.args)".to_string(),
+          as_typescript: false,
         },
+        argv: svec!["--allow-net", "arg"],
+        allow_net: true,
+        allow_env: true,
+        allow_run: true,
+        allow_read: true,
+        allow_write: true,
+        allow_plugin: true,
+        allow_hrtime: true,
         ..Flags::default()
       }
     );
+  }
 
-    let r = flags_from_vec_safe(svec!["deno", "fmt"]);
+  #[test]
+  fn test_script_args() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "dir1/",
+      "dir2/",
+      "--",
+      "--failfast",
+      "value"
+    ]);
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Fmt {
-          check: false,
-          files: vec![],
-        },
-        ..Flags::default()
-      }
-    );
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          quiet: false,
+          allow_none: false,
+          filter: None,
+          include: Some(svec!["dir1/", "dir2/"]),
+          jobs: 1,
+          reporter: TestReporter::Pretty,
+        },
+        argv: svec!["--failfast", "value"],
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn fmt() {
+    let r =
+      flags_from_vec_safe(svec!["deno", "fmt", "script_1.ts", "script_2.ts"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Fmt {
+          check: false,
+          files: vec!["script_1.ts".to_string(), "script_2.ts".to_string()],
+          ext: vec![],
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "fmt", "--check"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Fmt {
+          check: true,
+          files: vec![],
+          ext: vec![],
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "fmt"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Fmt {
+          check: false,
+          files: vec![],
+          ext: vec![],
+        },
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn fmt_ext() {
+    let r = flags_from_vec_safe(svec!["deno", "fmt", "--ext=json,md", "src"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Fmt {
+          check: false,
+          files: svec!["src"],
+          ext: svec!["json", "md"],
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "fmt", "--ext=yaml"]);
+    assert!(r.is_err());
   }
 
   #[test]
@@ -1787,13 +2980,39 @@ mod tests {
     );
   }
 
+  #[test]
+  fn eval_with_permissions() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "eval",
+      "--allow-read",
+      "--allow-net=deno.land",
+      "42"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Eval {
+          code: "42".to_string(),
+          as_typescript: false,
+        },
+        allow_read: true,
+        net_whitelist: svec!["deno.land"],
+        ..Flags::default()
+      }
+    );
+  }
+
   #[test]
   fn repl() {
     let r = flags_from_vec_safe(svec!["deno"]);
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Repl,
+        subcommand: DenoSubcommand::Repl {
+          eval: None,
+          eval_files: vec![],
+        },
         allow_net: true,
         allow_env: true,
         allow_run: true,
@@ -1813,7 +3032,40 @@ mod tests {
       r.unwrap(),
       Flags {
         unstable: true,
-        subcommand: DenoSubcommand::Repl,
+        subcommand: DenoSubcommand::Repl {
+          eval: None,
+          eval_files: vec![],
+        },
+        allow_net: true,
+        allow_env: true,
+        allow_run: true,
+        allow_read: true,
+        allow_write: true,
+        allow_plugin: true,
+        allow_hrtime: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn repl_with_eval() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "repl",
+      "--eval-file=a.js",
+      "--eval-file",
+      "b.js",
+      "--eval",
+      "const x = 1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Repl {
+          eval: Some("const x = 1".to_string()),
+          eval_files: svec!["a.js", "b.js"],
+        },
         allow_net: true,
         allow_env: true,
         allow_run: true,
@@ -2171,210 +3423,477 @@ mod tests {
   }
 
   #[test]
-  fn log_level() {
+  fn lint() {
+    let r = flags_from_vec_safe(svec!["deno", "lint"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Lint {
+          files: vec![],
+          json: false,
+        },
+        ..Flags::default()
+      }
+    );
+
     let r = flags_from_vec_safe(svec![
       "deno",
-      "run",
-      "--log-level=debug",
-      "script.ts"
+      "lint",
+      "--json",
+      "script_1.ts",
+      "src/**/*.ts"
     ]);
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Run {
-          script: "script.ts".to_string(),
+        subcommand: DenoSubcommand::Lint {
+          files: svec!["script_1.ts", "src/**/*.ts"],
+          json: true,
         },
-        log_level: Some(Level::Debug),
         ..Flags::default()
       }
     );
   }
 
   #[test]
-  fn quiet() {
-    let r = flags_from_vec_safe(svec!["deno", "run", "-q", "script.ts"]);
+  fn uninstall() {
+    let r = flags_from_vec_safe(svec!["deno", "uninstall", "file_server"]);
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Run {
-          script: "script.ts".to_string(),
+        subcommand: DenoSubcommand::Uninstall {
+          name: "file_server".to_string(),
+          root: None,
         },
-        log_level: Some(Level::Error),
         ..Flags::default()
       }
     );
-  }
 
-  #[test]
-  fn completions() {
-    let r = flags_from_vec_safe(svec!["deno", "completions", "bash"]).unwrap();
-
-    match r.subcommand {
-      DenoSubcommand::Completions { buf } => assert!(!buf.is_empty()),
-      _ => unreachable!(),
-    }
-  }
-
-  /* TODO(ry) Fix this test
-  #[test]
-  fn test_flags_from_vec_33() {
-    let (flags, subcommand, argv) =
-      flags_from_vec_safe(svec!["deno", "script.ts", "--allow-read", "--allow-net"]);
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "uninstall",
+      "--root",
+      "/usr/local",
+      "file_server"
+    ]);
     assert_eq!(
-      flags,
+      r.unwrap(),
       Flags {
-        allow_net: true,
-        allow_read: true,
+        subcommand: DenoSubcommand::Uninstall {
+          name: "file_server".to_string(),
+          root: Some(PathBuf::from("/usr/local")),
+        },
         ..Flags::default()
       }
     );
-    assert_eq!(subcommand, DenoSubcommand::Run);
-    assert_eq!(argv, svec!["script.ts"]);
+  }
 
-    let (flags, subcommand, argv) = flags_from_vec_safe(svec![
+  #[test]
+  fn log_level() {
+    let r = flags_from_vec_safe(svec![
       "deno",
       "run",
-      "--allow-read",
-      "script.ts",
-      "--allow-net",
-      "-r",
-      "--help",
-      "--foo",
-      "bar"
+      "--log-level=debug",
+      "script.ts"
     ]);
     assert_eq!(
-      flags,
+      r.unwrap(),
       Flags {
-        allow_net: true,
-        allow_read: true,
-        reload: true,
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        log_level: Some(Level::Debug),
         ..Flags::default()
       }
     );
-    assert_eq!(subcommand, DenoSubcommand::Run);
-    assert_eq!(argv, svec!["deno", "script.ts", "--help", "--foo", "bar"]);
-
-    let (flags, subcommand, argv) =
-      flags_from_vec_safe(svec!["deno""script.ts", "foo", "bar"]);
-    assert_eq!(flags, Flags::default());
-    assert_eq!(subcommand, DenoSubcommand::Run);
-  assert_eq!(argv, svec!["script.ts", "foo", "bar"]);
 
-    let (flags, subcommand, argv) =
-      flags_from_vec_safe(svec!["deno""script.ts", "-"]);
-    assert_eq!(flags, Flags::default());
-    assert_eq!(subcommand, DenoSubcommand::Run);
-    assert_eq!(argv, svec!["script.ts", "-"]);
+    let r = flags_from_vec_safe(svec!["deno", "fmt", "-L", "warn"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Fmt {
+          check: false,
+          files: vec![],
+          ext: vec![],
+        },
+        log_level: Some(Level::Warn),
+        ..Flags::default()
+      }
+    );
 
-    let (flags, subcommand, argv) =
-      flags_from_vec_safe(svec!["deno""script.ts", "-", "foo", "bar"]);
-    assert_eq!(flags, Flags::default());
-    assert_eq!(subcommand, DenoSubcommand::Run);
-    assert_eq!(argv, svec!["script.ts", "-", "foo", "bar"]);
+    let r = flags_from_vec_safe(svec!["deno", "-L", "trace", "script.ts"]);
+    assert!(r.is_err());
   }
-  */
 
   #[test]
-  fn no_remote() {
+  fn log_categories() {
     let r =
-      flags_from_vec_safe(svec!["deno", "run", "--no-remote", "script.ts"]);
+      flags_from_vec_safe(svec!["deno", "run", "--log=net,cache", "script.ts"]);
     assert_eq!(
       r.unwrap(),
       Flags {
         subcommand: DenoSubcommand::Run {
           script: "script.ts".to_string(),
         },
-        no_remote: true,
+        log_categories: svec!["net", "cache"],
         ..Flags::default()
       }
     );
+
+    let r = flags_from_vec_safe(svec!["deno", "run", "--log=gc", "script.ts"]);
+    assert!(r.is_err());
   }
 
   #[test]
-  fn cached_only() {
-    let r =
-      flags_from_vec_safe(svec!["deno", "run", "--cached-only", "script.ts"]);
+  fn quiet() {
+    let r = flags_from_vec_safe(svec!["deno", "run", "-q", "script.ts"]);
     assert_eq!(
       r.unwrap(),
       Flags {
         subcommand: DenoSubcommand::Run {
           script: "script.ts".to_string(),
         },
-        cached_only: true,
+        log_level: Some(Level::Error),
         ..Flags::default()
       }
     );
   }
 
   #[test]
-  fn allow_net_whitelist_with_ports() {
-    let r = flags_from_vec_safe(svec![
-      "deno",
-      "run",
-      "--allow-net=deno.land,:8000,:4545",
-      "script.ts"
-    ]);
+  fn json_errors() {
+    let r =
+      flags_from_vec_safe(svec!["deno", "run", "--json-errors", "script.ts"]);
     assert_eq!(
       r.unwrap(),
       Flags {
         subcommand: DenoSubcommand::Run {
           script: "script.ts".to_string(),
         },
-        net_whitelist: svec![
-          "deno.land",
-          "0.0.0.0:8000",
-          "127.0.0.1:8000",
-          "localhost:8000",
-          "0.0.0.0:4545",
-          "127.0.0.1:4545",
-          "localhost:4545"
-        ],
+        json_errors: true,
         ..Flags::default()
       }
     );
   }
 
   #[test]
-  fn lock_write() {
-    let r = flags_from_vec_safe(svec![
-      "deno",
-      "run",
-      "--lock-write",
-      "--lock=lock.json",
-      "script.ts"
-    ]);
+  fn no_color() {
+    let r =
+      flags_from_vec_safe(svec!["deno", "--no-color", "run", "script.ts"]);
     assert_eq!(
       r.unwrap(),
       Flags {
         subcommand: DenoSubcommand::Run {
           script: "script.ts".to_string(),
         },
-        lock_write: true,
-        lock: Some("lock.json".to_string()),
+        no_color: true,
         ..Flags::default()
       }
     );
   }
 
   #[test]
-  fn test_with_allow_net() {
-    let r = flags_from_vec_safe(svec![
-      "deno",
-      "test",
-      "--allow-net",
-      "--allow-none",
-      "dir1/",
-      "dir2/"
-    ]);
+  fn xeval() {
+    let r = flags_from_vec_safe(svec!["deno", "xeval", "console.log($)"]);
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Test {
+        subcommand: DenoSubcommand::Xeval {
+          code: "console.log($)".to_string(),
+          delimiter: None,
+          replvar: None,
+        },
+        allow_net: true,
+        allow_env: true,
+        allow_run: true,
+        allow_read: true,
+        allow_write: true,
+        allow_plugin: true,
+        allow_hrtime: true,
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "xeval",
+      "--allow-read",
+      "-I",
+      "line",
+      "-d",
+      ",",
+      "console.log(line)"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Xeval {
+          code: "console.log(line)".to_string(),
+          delimiter: Some(",".to_string()),
+          replvar: Some("line".to_string()),
+        },
+        allow_read: true,
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "xeval", "-d", "", "1"]);
+    assert!(r.is_err());
+  }
+
+  const SUBCOMMANDS: &[&str] = &[
+    "bench",
+    "bundle",
+    "cache",
+    "completions",
+    "coverage",
+    "doc",
+    "eval",
+    "fmt",
+    "info",
+    "install",
+    "lint",
+    "repl",
+    "run",
+    "task",
+    "test",
+    "types",
+    "uninstall",
+    "upgrade",
+    "xeval",
+  ];
+
+  #[test]
+  fn completions() {
+    let r = flags_from_vec_safe(svec!["deno", "completions", "bash"]).unwrap();
+
+    match r.subcommand {
+      DenoSubcommand::Completions { buf } => {
+        let script = std::str::from_utf8(&buf).unwrap();
+        for subcommand in SUBCOMMANDS {
+          assert!(script.contains(subcommand), "missing {}", subcommand);
+        }
+      }
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn task() {
+    let r = flags_from_vec_safe(svec!["deno", "task"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Task {
+          task: None,
+          args: vec![],
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno", "task", "test", "--filter", "parser", "--", "-A"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Task {
+          task: Some("test".to_string()),
+          args: svec!["--filter", "parser", "--", "-A"],
+        },
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn completions_include_all_subcommands() {
+    for shell in &["bash", "fish", "zsh", "powershell"] {
+      let r = flags_from_vec_safe(svec!["deno", "completions", shell]).unwrap();
+      let script = match r.subcommand {
+        DenoSubcommand::Completions { buf } => {
+          String::from_utf8(buf.into_vec()).unwrap()
+        }
+        _ => unreachable!(),
+      };
+      for subcommand in SUBCOMMANDS {
+        assert!(
+          script.contains(subcommand),
+          "{} completions are missing {}",
+          shell,
+          subcommand
+        );
+      }
+      assert!(
+        script.contains("watch"),
+        "{} completions are missing --watch",
+        shell
+      );
+    }
+  }
+
+  /* TODO(ry) Fix this test
+  #[test]
+  fn test_flags_from_vec_33() {
+    let (flags, subcommand, argv) =
+      flags_from_vec_safe(svec!["deno", "script.ts", "--allow-read", "--allow-net"]);
+    assert_eq!(
+      flags,
+      Flags {
+        allow_net: true,
+        allow_read: true,
+        ..Flags::default()
+      }
+    );
+    assert_eq!(subcommand, DenoSubcommand::Run);
+    assert_eq!(argv, svec!["script.ts"]);
+
+    let (flags, subcommand, argv) = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-read",
+      "script.ts",
+      "--allow-net",
+      "-r",
+      "--help",
+      "--foo",
+      "bar"
+    ]);
+    assert_eq!(
+      flags,
+      Flags {
+        allow_net: true,
+        allow_read: true,
+        reload: true,
+        ..Flags::default()
+      }
+    );
+    assert_eq!(subcommand, DenoSubcommand::Run);
+    assert_eq!(argv, svec!["deno", "script.ts", "--help", "--foo", "bar"]);
+
+    let (flags, subcommand, argv) =
+      flags_from_vec_safe(svec!["deno""script.ts", "foo", "bar"]);
+    assert_eq!(flags, Flags::default());
+    assert_eq!(subcommand, DenoSubcommand::Run);
+  assert_eq!(argv, svec!["script.ts", "foo", "bar"]);
+
+    let (flags, subcommand, argv) =
+      flags_from_vec_safe(svec!["deno""script.ts", "-"]);
+    assert_eq!(flags, Flags::default());
+    assert_eq!(subcommand, DenoSubcommand::Run);
+    assert_eq!(argv, svec!["script.ts", "-"]);
+
+    let (flags, subcommand, argv) =
+      flags_from_vec_safe(svec!["deno""script.ts", "-", "foo", "bar"]);
+    assert_eq!(flags, Flags::default());
+    assert_eq!(subcommand, DenoSubcommand::Run);
+    assert_eq!(argv, svec!["script.ts", "-", "foo", "bar"]);
+  }
+  */
+
+  #[test]
+  fn no_remote() {
+    let r =
+      flags_from_vec_safe(svec!["deno", "run", "--no-remote", "script.ts"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        no_remote: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn cached_only() {
+    let r =
+      flags_from_vec_safe(svec!["deno", "run", "--cached-only", "script.ts"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        cached_only: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn allow_net_whitelist_with_ports() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--allow-net=deno.land,:8000,:4545",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        net_whitelist: svec![
+          "deno.land",
+          "0.0.0.0:8000",
+          "127.0.0.1:8000",
+          "localhost:8000",
+          "0.0.0.0:4545",
+          "127.0.0.1:4545",
+          "localhost:4545"
+        ],
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn lock_write() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--lock-write",
+      "--lock=lock.json",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        lock_write: true,
+        lock: Some("lock.json".to_string()),
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn test_with_allow_net() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--allow-net",
+      "--allow-none",
+      "dir1/",
+      "dir2/"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
           fail_fast: false,
           filter: None,
           allow_none: true,
           quiet: false,
           include: Some(svec!["dir1/", "dir2/"]),
+          jobs: 1,
+          reporter: TestReporter::Pretty,
         },
         allow_net: true,
         ..Flags::default()
@@ -2394,12 +3913,326 @@ mod tests {
           quiet: false,
           filter: Some("foo".to_string()),
           include: Some(svec!["dir1"]),
+          jobs: 1,
+          reporter: TestReporter::Pretty,
         },
         ..Flags::default()
       }
     );
   }
 
+  #[test]
+  fn test_jobs() {
+    let r =
+      flags_from_vec_safe(svec!["deno", "test", "--jobs", "4", "dir1", "dir2"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1", "dir2"]),
+          jobs: 4,
+          reporter: TestReporter::Pretty,
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "test", "--jobs", "0"]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn test_reporter() {
+    let r = flags_from_vec_safe(svec!["deno", "test", "--reporter=tap"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: None,
+          jobs: 1,
+          reporter: TestReporter::Tap,
+        },
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "test", "--reporter=xml"]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn test_coverage() {
+    let r = flags_from_vec_safe(svec!["deno", "test", "--coverage", "dir1"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["dir1"]),
+          jobs: 1,
+          reporter: TestReporter::Pretty,
+        },
+        coverage_dir: Some(PathBuf::from("coverage")),
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "test", "--coverage=cov"]);
+    assert_eq!(r.unwrap().coverage_dir, Some(PathBuf::from("cov")));
+  }
+
+  #[test]
+  fn frozen_time() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--unstable",
+      "--frozen-time=1000"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: None,
+          jobs: 1,
+          reporter: TestReporter::Pretty,
+        },
+        unstable: true,
+        frozen_time: Some(1000),
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "run", "--frozen-time", "a.js"]);
+    assert!(r.unwrap().frozen_time.unwrap() > 1_500_000_000_000);
+
+    let r =
+      flags_from_vec_safe(svec!["deno", "run", "--frozen-time=x", "a.js"]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn prof() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--unstable",
+      "--prof=out.cpuprofile",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        unstable: true,
+        prof: Some(PathBuf::from("out.cpuprofile")),
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "run", "--prof", "script.ts"]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn trace_ops() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--unstable",
+      "--trace-ops=trace.json",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        unstable: true,
+        trace_ops: Some(PathBuf::from("trace.json")),
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn coverage() {
+    let r =
+      flags_from_vec_safe(svec!["deno", "coverage", "--unstable", "--lcov"]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Coverage {
+          dir: PathBuf::from("coverage"),
+          lcov: true,
+        },
+        unstable: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn bench() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "bench",
+      "--unstable",
+      "--allow-hrtime",
+      "--filter=sort",
+      "dir1"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Bench {
+          allow_none: false,
+          filter: Some("sort".to_string()),
+          include: Some(svec!["dir1"]),
+        },
+        unstable: true,
+        allow_hrtime: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn run_with_download_retries() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--download-retries",
+      "5",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        download_retries: Some(5),
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--download-retries",
+      "many",
+      "script.ts"
+    ]);
+    assert!(r.is_err());
+  }
+
+  #[test]
+  fn run_with_fetch_cache() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--unstable",
+      "--fetch-cache",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        unstable: true,
+        fetch_cache: true,
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn run_with_http_pool() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--http-pool-idle-timeout",
+      "30",
+      "--http-pool-max-idle-per-host=4",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        http_pool_idle_timeout: Some(30),
+        http_pool_max_idle_per_host: Some(4),
+        ..Flags::default()
+      }
+    );
+  }
+
+  #[test]
+  fn run_with_download_headers() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--download-header",
+      "deno.land=X-Org-Token: secret",
+      "--download-header=localhost:4545=Accept-Language: en",
+      "script.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Run {
+          script: "script.ts".to_string(),
+        },
+        download_headers: svec![
+          "deno.land=X-Org-Token: secret",
+          "localhost:4545=Accept-Language: en"
+        ],
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--download-header",
+      "X-Org-Token",
+      "script.ts"
+    ]);
+    assert!(r.is_err());
+
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "run",
+      "--download-header",
+      "X-Org-Token: secret",
+      "script.ts"
+    ]);
+    assert!(r.is_err());
+  }
+
   #[test]
   fn run_with_cafile() {
     let r = flags_from_vec_safe(svec![
@@ -2576,7 +4409,10 @@ mod tests {
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Repl {},
+        subcommand: DenoSubcommand::Repl {
+          eval: None,
+          eval_files: vec![],
+        },
         ca_file: Some("example.crt".to_owned()),
         allow_read: true,
         allow_write: true,
@@ -2596,7 +4432,10 @@ mod tests {
     assert_eq!(
       r.unwrap(),
       Flags {
-        subcommand: DenoSubcommand::Repl {},
+        subcommand: DenoSubcommand::Repl {
+          eval: None,
+          eval_files: vec![],
+        },
         inspect: Some("127.0.0.1:9229".parse().unwrap()),
         allow_read: true,
         allow_write: true,
@@ -2686,4 +4525,36 @@ mod tests {
       }
     );
   }
+
+  #[test]
+  fn inspect_brk() {
+    let r = flags_from_vec_safe(svec![
+      "deno",
+      "test",
+      "--inspect-brk=0.0.0.0:9230",
+      "foo_test.ts"
+    ]);
+    assert_eq!(
+      r.unwrap(),
+      Flags {
+        subcommand: DenoSubcommand::Test {
+          fail_fast: false,
+          allow_none: false,
+          quiet: false,
+          filter: None,
+          include: Some(svec!["foo_test.ts"]),
+          jobs: 1,
+          reporter: TestReporter::Pretty,
+        },
+        inspect_brk: Some("0.0.0.0:9230".parse().unwrap()),
+        ..Flags::default()
+      }
+    );
+
+    let r = flags_from_vec_safe(svec!["deno", "run", "--inspect-brk", "a.js"]);
+    assert_eq!(
+      r.unwrap().inspect_brk,
+      Some("127.0.0.1:9229".parse().unwrap())
+    );
+  }
 }
@@ -3,11 +3,22 @@
 //! This module provides file formating utilities using
 //! [`dprint`](https://github.com/dsherret/dprint).
 //!
+//! JSON files are formatted by a small printer of our own, which keeps
+//! comments so it can be used on `tsconfig.json` files too. In Markdown
+//! files only the fenced code blocks of supported languages are formatted.
+//!
 //! At the moment it is only consumed using CLI but in
 //! the future it can be easily extended to provide
 //! the same functions as ops available in JS runtime.
 
+use crate::config_file::ConfigFile;
+use crate::fs::expand_paths;
 use crate::fs::files_in_subtree;
+use crate::fs::is_script;
+use crate::jsonc::json_error;
+use crate::jsonc::tokenize_json;
+use crate::jsonc::JsonToken;
+use crate::jsonc::JsonTokenKind;
 use crate::op_error::OpError;
 use deno_core::ErrBox;
 use dprint_plugin_typescript as dprint;
@@ -23,31 +34,33 @@ use std::sync::{Arc, Mutex};
 
 const BOM_CHAR: char = '\u{FEFF}';
 
-/// Format JavaScript/TypeScript files.
+/// Format JavaScript/TypeScript, JSON and Markdown files.
 ///
-/// First argument supports globs, and if it is `None`
-/// then the current directory is recursively walked.
-pub async fn format(args: Vec<String>, check: bool) -> Result<(), ErrBox> {
+/// Arguments support globs, and if there are none then the files selected
+/// by the `fmt` section of the project config file are formatted, or the
+/// current directory is recursively walked when there's no config file.
+/// Walks and globs only pick JSON and Markdown files when their extension is
+/// in `ext`, files given by name are formatted whatever their extension.
+pub async fn format(
+  args: Vec<String>,
+  check: bool,
+  ext: Vec<String>,
+  maybe_config_file: Option<ConfigFile>,
+) -> Result<(), ErrBox> {
   if args.len() == 1 && args[0] == "-" {
     return format_stdin(check);
   }
 
+  let filter = |path: &Path| is_supported(path, &ext);
   let mut target_files: Vec<PathBuf> = vec![];
 
-  if args.is_empty() {
-    target_files.extend(files_in_subtree(
-      std::env::current_dir().unwrap(),
-      is_supported,
-    ));
+  if let (true, Some(config_file)) = (args.is_empty(), &maybe_config_file) {
+    target_files.extend(config_file.collect_files(&config_file.fmt, filter));
+  } else if args.is_empty() {
+    target_files
+      .extend(files_in_subtree(std::env::current_dir().unwrap(), filter));
   } else {
-    for arg in args {
-      let p = PathBuf::from(arg);
-      if p.is_dir() {
-        target_files.extend(files_in_subtree(p, is_supported));
-      } else {
-        target_files.push(p);
-      };
-    }
+    target_files.extend(expand_paths(&args, filter)?);
   }
   let config = get_config();
   if check {
@@ -69,7 +82,7 @@ async fn check_source_files(
     let not_formatted_files_count = not_formatted_files_count.clone();
     move |file_path| {
       let file_text = read_file_contents(&file_path)?.text;
-      let r = formatter.format_text(&file_path, &file_text);
+      let r = format_file(&formatter, &file_path, &file_text);
       match r {
         Ok(formatted_text) => {
           if formatted_text != file_text {
@@ -115,7 +128,7 @@ async fn format_source_files(
     let formatted_files_count = formatted_files_count.clone();
     move |file_path| {
       let file_contents = read_file_contents(&file_path)?;
-      let r = formatter.format_text(&file_path, &file_contents.text);
+      let r = format_file(&formatter, &file_path, &file_contents.text);
       match r {
         Ok(formatted_text) => {
           if formatted_text != file_contents.text {
@@ -186,6 +199,307 @@ pub fn format_text(source: &str) -> Result<String, ErrBox> {
     .map_err(|e| OpError::other(e).into())
 }
 
+fn lowercase_ext(path: &Path) -> Option<String> {
+  path
+    .extension()
+    .and_then(|e| e.to_str())
+    .map(|e| e.to_lowercase())
+}
+
+/// Formats a file according to its extension.
+fn format_file(
+  formatter: &dprint::Formatter,
+  file_path: &Path,
+  file_text: &str,
+) -> Result<String, String> {
+  match lowercase_ext(file_path).as_deref() {
+    Some("json") => format_json(file_text),
+    Some("md") | Some("markdown") => Ok(format_markdown(formatter, file_text)),
+    _ => formatter.format_text(&file_path.to_path_buf(), file_text),
+  }
+}
+
+struct JsonPrinter<'a> {
+  text: &'a str,
+  tokens: Vec<JsonToken<'a>>,
+  pos: usize,
+  out: String,
+  /// Set after a line comment, the next token has to go on a new line.
+  needs_newline: bool,
+}
+
+impl<'a> JsonPrinter<'a> {
+  fn peek(&self) -> Option<&JsonTokenKind<'a>> {
+    self.tokens.get(self.pos).map(|t| &t.kind)
+  }
+
+  fn peek_past_comments(&self) -> Option<&JsonTokenKind<'a>> {
+    self.tokens[self.pos..]
+      .iter()
+      .map(|t| &t.kind)
+      .find(|k| match k {
+        JsonTokenKind::LineComment(_) | JsonTokenKind::BlockComment(_) => false,
+        _ => true,
+      })
+  }
+
+  fn unexpected(&self) -> String {
+    match self.tokens.get(self.pos) {
+      Some(token) => json_error(self.text, token.offset, "Unexpected token"),
+      None => "Unexpected end of JSON input".to_string(),
+    }
+  }
+
+  fn newline(&mut self, indent: usize, keep_blank_line: bool) {
+    if keep_blank_line {
+      self.out.push('\n');
+    }
+    self.out.push('\n');
+    self.out.push_str(&"  ".repeat(indent));
+    self.needs_newline = false;
+  }
+
+  fn space(&mut self, indent: usize) {
+    if self.needs_newline {
+      self.newline(indent, false);
+    } else {
+      self.out.push(' ');
+    }
+  }
+
+  /// Prints comments at the current position. Comments that started on their
+  /// own line in the source stay on their own line.
+  fn comments(&mut self, indent: usize) {
+    while let Some(token) = self.tokens.get(self.pos) {
+      let (text, is_line_comment) = match token.kind {
+        JsonTokenKind::LineComment(text) => (text, true),
+        JsonTokenKind::BlockComment(text) => (text, false),
+        _ => return,
+      };
+      if token.newlines_before > 0 && !self.out.is_empty() {
+        let keep_blank_line = token.newlines_before > 1;
+        self.newline(indent, keep_blank_line);
+      } else if !self.out.is_empty() {
+        self.space(indent);
+      }
+      self.out.push_str(text);
+      self.needs_newline = is_line_comment;
+      self.pos += 1;
+    }
+  }
+
+  fn value(&mut self, indent: usize) -> Result<(), String> {
+    match self.peek() {
+      Some(JsonTokenKind::Punct('{')) => self.container(indent, '}'),
+      Some(JsonTokenKind::Punct('[')) => self.container(indent, ']'),
+      Some(JsonTokenKind::Literal(literal)) => {
+        let literal = *literal;
+        self.out.push_str(literal);
+        self.pos += 1;
+        Ok(())
+      }
+      _ => Err(self.unexpected()),
+    }
+  }
+
+  /// Arrays of literals written on a single line are kept that way.
+  fn is_inline_array(&self) -> bool {
+    for token in &self.tokens[self.pos + 1..] {
+      match token.kind {
+        JsonTokenKind::Punct(']') => return token.newlines_before == 0,
+        JsonTokenKind::Punct(',') | JsonTokenKind::Literal(_)
+          if token.newlines_before == 0 => {}
+        _ => return false,
+      }
+    }
+    false
+  }
+
+  fn container(&mut self, indent: usize, close: char) -> Result<(), String> {
+    let is_object = close == '}';
+    let inline = !is_object && self.is_inline_array();
+    self.out.push(if is_object { '{' } else { '[' });
+    self.pos += 1;
+
+    let mut is_empty = true;
+    loop {
+      self.comments(indent + 1);
+      match self.peek() {
+        Some(JsonTokenKind::Punct(c)) if *c == close => break,
+        None => return Err(self.unexpected()),
+        _ => {}
+      }
+
+      if inline {
+        if !is_empty {
+          self.out.push(' ');
+        }
+      } else {
+        let keep_blank_line =
+          !is_empty && self.tokens[self.pos].newlines_before > 1;
+        self.newline(indent + 1, keep_blank_line);
+      }
+      is_empty = false;
+
+      if is_object {
+        match self.peek() {
+          Some(JsonTokenKind::Literal(key)) => {
+            let key = *key;
+            self.out.push_str(key);
+            self.pos += 1;
+          }
+          _ => return Err(self.unexpected()),
+        }
+        self.comments(indent + 1);
+        if self.peek() != Some(&JsonTokenKind::Punct(':')) {
+          return Err(self.unexpected());
+        }
+        self.out.push(':');
+        self.pos += 1;
+        self.comments(indent + 1);
+        self.space(indent + 1);
+      }
+
+      self.value(indent + 1)?;
+      self.comments(indent + 1);
+      match self.peek() {
+        Some(JsonTokenKind::Punct(',')) => {
+          self.pos += 1;
+          // Trailing commas are dropped.
+          if self.peek_past_comments() != Some(&JsonTokenKind::Punct(close)) {
+            self.out.push(',');
+          }
+        }
+        Some(JsonTokenKind::Punct(c)) if *c == close => {}
+        _ => return Err(self.unexpected()),
+      }
+    }
+
+    if !inline && (!is_empty || self.needs_newline) {
+      self.newline(indent, false);
+    }
+    self.out.push(close);
+    self.pos += 1;
+    Ok(())
+  }
+}
+
+/// Formats JSON (with comments) using two space indentation.
+fn format_json(text: &str) -> Result<String, String> {
+  let mut printer = JsonPrinter {
+    text,
+    tokens: tokenize_json(text)?,
+    pos: 0,
+    out: String::new(),
+    needs_newline: false,
+  };
+  printer.comments(0);
+  if printer.peek().is_some() {
+    if !printer.out.is_empty() {
+      printer.newline(0, false);
+    }
+    printer.value(0)?;
+    printer.comments(0);
+  }
+  if printer.pos != printer.tokens.len() {
+    return Err(printer.unexpected());
+  }
+  if !printer.out.is_empty() {
+    printer.out.push('\n');
+  }
+  Ok(printer.out)
+}
+
+/// Formats a code block found in a Markdown file, or returns `None` if the
+/// language is not supported.
+fn format_code_block(
+  formatter: &dprint::Formatter,
+  lang: &str,
+  code: &str,
+) -> Option<Result<String, String>> {
+  let file_name = match lang {
+    "ts" | "typescript" => "_tmp.ts",
+    "tsx" => "_tmp.tsx",
+    "js" | "javascript" | "mjs" => "_tmp.js",
+    "jsx" => "_tmp.jsx",
+    "json" | "jsonc" => return Some(format_json(code)),
+    _ => return None,
+  };
+  Some(formatter.format_text(&PathBuf::from(file_name), code))
+}
+
+/// Formats the fenced code blocks of a Markdown document, leaving the rest of
+/// the text untouched. Blocks that fail to parse are kept as they are.
+fn format_markdown(formatter: &dprint::Formatter, text: &str) -> String {
+  let mut out = String::new();
+  let mut lines = text.lines();
+  while let Some(line) = lines.next() {
+    out.push_str(line);
+    out.push('\n');
+
+    let indent = line.len() - line.trim_start().len();
+    let trimmed = line.trim_start();
+    let fence_char = match trimmed.chars().next() {
+      Some(c) if indent <= 3 && (c == '`' || c == '~') => c,
+      _ => continue,
+    };
+    let fence_len = trimmed.chars().take_while(|c| *c == fence_char).count();
+    if fence_len < 3 {
+      continue;
+    }
+    let info = trimmed[fence_len..].trim();
+    let lang = info.split_whitespace().next().unwrap_or("").to_lowercase();
+
+    let mut body = vec![];
+    let mut closing = None;
+    for line in &mut lines {
+      let trimmed = line.trim_start();
+      if trimmed.len() >= fence_len
+        && trimmed.chars().take_while(|c| *c == fence_char).count() >= fence_len
+        && trimmed.trim_start_matches(fence_char).trim().is_empty()
+      {
+        closing = Some(line);
+        break;
+      }
+      body.push(line);
+    }
+
+    let code: String = body
+      .iter()
+      .map(|l| {
+        format!("{}\n", l.get(indent..).unwrap_or_else(|| l.trim_start()))
+      })
+      .collect();
+    let formatted = match (closing, format_code_block(formatter, &lang, &code))
+    {
+      (Some(_), Some(Ok(formatted))) => Some(formatted),
+      _ => None,
+    };
+    match formatted {
+      Some(formatted) => {
+        for l in formatted.lines() {
+          if !l.is_empty() {
+            out.push_str(&line[..indent]);
+          }
+          out.push_str(l);
+          out.push('\n');
+        }
+      }
+      None => {
+        for l in body {
+          out.push_str(l);
+          out.push('\n');
+        }
+      }
+    }
+    if let Some(closing) = closing {
+      out.push_str(closing);
+      out.push('\n');
+    }
+  }
+  out
+}
+
 fn files_str(len: usize) -> &'static str {
   if len == 1 {
     "file"
@@ -194,15 +508,17 @@ fn files_str(len: usize) -> &'static str {
   }
 }
 
-fn is_supported(path: &Path) -> bool {
-  let lowercase_ext = path
-    .extension()
-    .and_then(|e| e.to_str())
-    .map(|e| e.to_lowercase());
-  if let Some(ext) = lowercase_ext {
-    ext == "ts" || ext == "tsx" || ext == "js" || ext == "jsx"
-  } else {
-    false
+/// Whether `path` is a JavaScript or TypeScript file, or has one of the
+/// `ext` extensions. `md` stands for `markdown` too.
+fn is_supported(path: &Path, ext: &[String]) -> bool {
+  if is_script(path) {
+    return true;
+  }
+  let enabled = |e: &str| ext.iter().any(|x| x == e);
+  match lowercase_ext(path).as_deref() {
+    Some("json") => enabled("json"),
+    Some("md") | Some("markdown") => enabled("md"),
+    _ => false,
   }
 }
 
@@ -289,23 +605,104 @@ where
 
 #[test]
 fn test_is_supported() {
-  assert!(!is_supported(Path::new("tests/subdir/redirects")));
-  assert!(!is_supported(Path::new("README.md")));
-  assert!(is_supported(Path::new("lib/typescript.d.ts")));
-  assert!(is_supported(Path::new("cli/tests/001_hello.js")));
-  assert!(is_supported(Path::new("cli/tests/002_hello.ts")));
-  assert!(is_supported(Path::new("foo.jsx")));
-  assert!(is_supported(Path::new("foo.tsx")));
-  assert!(is_supported(Path::new("foo.TS")));
-  assert!(is_supported(Path::new("foo.TSX")));
-  assert!(is_supported(Path::new("foo.JS")));
-  assert!(is_supported(Path::new("foo.JSX")));
+  let supported = |path: &str| is_supported(Path::new(path), &[]);
+  assert!(!supported("tests/subdir/redirects"));
+  assert!(!supported("LICENSE"));
+  assert!(!supported("README.md"));
+  assert!(!supported("tsconfig.json"));
+  assert!(supported("lib/typescript.d.ts"));
+  assert!(supported("cli/tests/001_hello.js"));
+  assert!(supported("cli/tests/002_hello.ts"));
+  assert!(supported("foo.jsx"));
+  assert!(supported("foo.tsx"));
+  assert!(supported("foo.TS"));
+  assert!(supported("foo.TSX"));
+  assert!(supported("foo.JS"));
+  assert!(supported("foo.JSX"));
+
+  let ext = vec!["json".to_string(), "md".to_string()];
+  assert!(is_supported(Path::new("README.md"), &ext));
+  assert!(is_supported(Path::new("notes.markdown"), &ext));
+  assert!(is_supported(Path::new("tsconfig.json"), &ext));
+  assert!(!is_supported(Path::new("LICENSE"), &ext));
 }
 
 #[tokio::test]
 async fn check_tests_dir() {
   // Because of cli/tests/error_syntax.js the following should fail but not
   // crash.
-  let r = format(vec!["./tests".to_string()], true).await;
+  let r = format(vec!["./tests".to_string()], true, vec![], None).await;
   assert!(r.is_err());
 }
+
+#[test]
+fn test_format_json() {
+  assert_eq!(format_json("").unwrap(), "");
+  assert_eq!(format_json("  {  }").unwrap(), "{}\n");
+  assert_eq!(format_json("[1,2,  3]").unwrap(), "[1, 2, 3]\n");
+  assert_eq!(
+    format_json(r#"{"a":1,"b":[true,null],"c":{"d":"e, f"},"g":[]}"#).unwrap(),
+    r#"{
+  "a": 1,
+  "b": [true, null],
+  "c": {
+    "d": "e, f"
+  },
+  "g": []
+}
+"#
+  );
+  assert_eq!(
+    format_json(
+      r#"// config
+{
+    "compilerOptions": { // options
+        "strict": true, /* inline */ "jsx": "react",
+
+
+        // multi-line
+        "lib": [
+            "dom",
+            "esnext",
+        ],
+    }
+}"#
+    )
+    .unwrap(),
+    r#"// config
+{
+  "compilerOptions": { // options
+    "strict": true, /* inline */
+    "jsx": "react",
+
+    // multi-line
+    "lib": [
+      "dom",
+      "esnext"
+    ]
+  }
+}
+"#
+  );
+  assert_eq!(
+    format_json("{\"a\": // why\n1}").unwrap(),
+    "{\n  \"a\": // why\n  1\n}\n"
+  );
+  assert!(format_json("{\"a\" 1}").is_err());
+  assert!(format_json("{\"a\": 1").is_err());
+  assert!(format_json("[1]]").is_err());
+  assert_eq!(
+    format_json("{\n  \"a\": \"b\n}").unwrap_err(),
+    "Unterminated string at 2:8"
+  );
+}
+
+#[test]
+fn test_format_markdown() {
+  let formatter = dprint::Formatter::new(get_config());
+  let text = "# Title\n\n```ts\nconst a = 1\n```\n\n- item\n\n  ```json\n  {\"a\":1}\n  ```\n\n```sh\ndeno  run\n```\n\n```js\nnot valid(\n```\n";
+  assert_eq!(
+    format_markdown(&formatter, text),
+    "# Title\n\n```ts\nconst a = 1;\n```\n\n- item\n\n  ```json\n  {\n    \"a\": 1\n  }\n  ```\n\n```sh\ndeno  run\n```\n\n```js\nnot valid(\n```\n"
+  );
+}
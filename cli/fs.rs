@@ -4,6 +4,7 @@ use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::{Component, Path, PathBuf};
 
+use crate::op_error::OpError;
 use deno_core::ErrBox;
 use walkdir::WalkDir;
 
@@ -130,6 +131,45 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_is_script() {
+    assert!(is_script(Path::new("cli/tests/002_hello.ts")));
+    assert!(is_script(Path::new("foo.JSX")));
+    assert!(!is_script(Path::new("README.md")));
+    assert!(!is_script(Path::new("LICENSE")));
+  }
+
+  #[test]
+  fn test_expand_paths() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    std::fs::create_dir_all(root.join("src").join("nested")).unwrap();
+    std::fs::write(root.join("src").join("a.ts"), "").unwrap();
+    std::fs::write(root.join("src").join("b.md"), "").unwrap();
+    std::fs::write(root.join("src").join("nested").join("c.js"), "").unwrap();
+    std::fs::write(root.join("d.ts"), "").unwrap();
+
+    let arg = |p: PathBuf| p.to_string_lossy().to_string();
+    let mut files =
+      expand_paths(&[arg(root.join("src")), arg(root.join("*.ts"))], is_script)
+        .unwrap();
+    files.sort();
+    assert_eq!(
+      files,
+      vec![
+        root.join("d.ts"),
+        root.join("src").join("a.ts"),
+        root.join("src").join("nested").join("c.js"),
+      ]
+    );
+    // Files given by name are kept whatever their extension.
+    assert_eq!(
+      expand_paths(&[arg(root.join("src").join("b.md"))], is_script).unwrap(),
+      vec![root.join("src").join("b.md")]
+    );
+    assert!(expand_paths(&["[".to_string()], is_script).is_err());
+  }
+
   // TODO: Get a good expected value here for Windows.
   #[cfg(not(windows))]
   #[test]
@@ -152,3 +192,52 @@ where
     .filter(|p| if p.is_dir() { false } else { filter(&p) })
     .collect()
 }
+
+/// Whether `path` has the extension of a JavaScript or TypeScript module.
+pub fn is_script(path: &Path) -> bool {
+  match path.extension().and_then(|e| e.to_str()) {
+    Some(ext) => {
+      let ext = ext.to_lowercase();
+      ext == "ts" || ext == "tsx" || ext == "js" || ext == "jsx"
+    }
+    None => false,
+  }
+}
+
+/// Expands the path arguments of a tool like `deno fmt` or `deno lint` into
+/// the files they cover. Globs are matched, and directories are walked for
+/// the files `filter` accepts. Files given by name are kept as they are.
+pub fn expand_paths<F>(
+  args: &[String],
+  filter: F,
+) -> Result<Vec<PathBuf>, ErrBox>
+where
+  F: Fn(&Path) -> bool,
+{
+  let mut target_files = vec![];
+  for arg in args {
+    for p in expand_glob(arg, &filter)? {
+      if p.is_dir() {
+        target_files.extend(files_in_subtree(p, &filter));
+      } else {
+        target_files.push(p);
+      }
+    }
+  }
+  Ok(target_files)
+}
+
+fn expand_glob<F>(arg: &str, filter: F) -> Result<Vec<PathBuf>, ErrBox>
+where
+  F: Fn(&Path) -> bool,
+{
+  if !arg.contains(|c| c == '*' || c == '?' || c == '[') {
+    return Ok(vec![PathBuf::from(arg)]);
+  }
+  let paths = glob::glob(arg)
+    .map_err(|e| OpError::other(format!("Invalid glob \"{}\": {}", arg, e)))?
+    .filter_map(Result::ok)
+    .filter(|p| p.is_dir() || filter(p))
+    .collect();
+  Ok(paths)
+}
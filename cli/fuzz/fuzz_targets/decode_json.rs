@@ -0,0 +1,7 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  deno::fuzzing::decode_json(data);
+});
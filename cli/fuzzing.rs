@@ -0,0 +1,134 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Entry points for the cargo-fuzz targets in `cli/fuzz`. Everything JS hands
+//! to ops can be made up by a compromised isolate, so none of these may ever
+//! panic, whatever the input.
+
+use crate::flags::Flags;
+use crate::global_state::GlobalState;
+use crate::ops;
+use crate::tokio_util;
+use crate::worker::MainWorker;
+use deno_core::Buf;
+use deno_core::ModuleSpecifier;
+use futures::future::poll_fn;
+use futures::future::FutureExt;
+use std::task::Poll;
+
+/// Ops that are skipped by `dispatch_op()`, because they end or block the
+/// fuzzing process itself rather than fail.
+const SKIPPED_OPS: &[&str] = &[
+  "op_exit",
+  "op_repl_start",
+  "op_repl_readline",
+  "op_request_permission",
+];
+
+/// Decodes `data` as the control buffer of a minimal op. Records that decode
+/// have to encode back to the same bytes.
+pub fn decode_minimal(data: &[u8]) {
+  if let Some(record) = ops::parse_min_record(data) {
+    let buf: Buf = record.into();
+    assert_eq!(&*buf, data);
+  }
+}
+
+/// Decodes `data` as the control buffer of a JSON op.
+pub fn decode_json(data: &[u8]) {
+  let _ = ops::parse_control(data);
+}
+
+/// Dispatches an op from a fresh worker without any permission. The first
+/// byte of `data` picks the op, the second one the number of zero copy
+/// buffers, each following byte the length of one of them, and the rest is
+/// the control buffer. The worker is then polled once, so that the response
+/// of the op is handled if it's ready.
+pub fn dispatch_op(data: &[u8]) {
+  let flags = Flags {
+    no_prompts: true,
+    ..Flags::default()
+  };
+  let global_state = GlobalState::new(flags).unwrap();
+  dispatch_op_with(data, global_state);
+}
+
+/// Like `dispatch_op()`, from a worker of `global_state`.
+fn dispatch_op_with(data: &[u8], global_state: GlobalState) {
+  if data.len() < 2 {
+    return;
+  }
+  let op = data[0];
+  let buf_count = (data[1] % 3) as usize;
+  if data.len() < 2 + buf_count {
+    return;
+  }
+  let buf_lens = &data[2..2 + buf_count];
+  let control = &data[2 + buf_count..];
+  // Stdin is closed first, since a sync read would block on it.
+  let source = format!(
+    "Deno.close(Deno.stdin.rid);
+const skipped = {:?};
+const ops = Object.entries(Deno.core.ops())
+  .filter(([name]) => !skipped.includes(name));
+const [, opId] = ops[{} % ops.length];
+const zeroCopy = {:?}.map((len) => new Uint8Array(len));
+Deno.core.dispatch(opId, new Uint8Array({:?}), ...zeroCopy);",
+    SKIPPED_OPS, op, buf_lens, control
+  );
+
+  let main_module =
+    ModuleSpecifier::resolve_url_or_path("./__fuzz__.js").unwrap();
+
+  let mut rt = tokio_util::create_basic_runtime();
+  rt.block_on(async move {
+    let mut worker = MainWorker::create(global_state, main_module).unwrap();
+    // Exceptions thrown by the op, or by the handling of its response, are
+    // fine. Only panics are bugs.
+    let _ = worker.execute(&source);
+    poll_fn(|cx| {
+      let _ = (&mut *worker).poll_unpin(cx);
+      Poll::Ready(())
+    })
+    .await;
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::deno_dir::DenoDir;
+  use crate::permissions::Permissions;
+
+  #[test]
+  fn decode() {
+    decode_minimal(&[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0]);
+    decode_minimal(&[0; 5]);
+    decode_json(b"{\"promiseId\":1}");
+    decode_json(b"{\"promiseId\":\"1\"}");
+    decode_json(&[0xff, 0xfe]);
+  }
+
+  #[test]
+  fn dispatch() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let dir = DenoDir::new(Some(temp_dir.path().to_path_buf())).unwrap();
+    let flags = Flags {
+      no_prompts: true,
+      ..Flags::default()
+    };
+    let permissions = Permissions::from_flags(&flags);
+    let global_state =
+      GlobalState::with_deno_dir(flags, permissions, dir).unwrap();
+
+    dispatch_op_with(&[], global_state.clone());
+    // A sync call of a few ops without arguments, then async ones with too
+    // many buffers. The fuzz target covers the others.
+    for op in &[0, 1, 7, 42, 255] {
+      dispatch_op_with(&[*op, 0, b'{', b'}'], global_state.clone());
+      dispatch_op_with(
+        &[*op, 2, 8, 8, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        global_state.clone(),
+      );
+    }
+  }
+}
@@ -1,8 +1,10 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use crate::deno_dir;
+use crate::fetch_cache::FetchCache;
 use crate::file_fetcher::SourceFileFetcher;
 use crate::flags;
 use crate::http_cache;
+use crate::http_util;
 use crate::import_map::ImportMap;
 use crate::lockfile::Lockfile;
 use crate::module_graph::ModuleGraphFile;
@@ -14,6 +16,7 @@ use crate::state::exit_unstable;
 use crate::tsc::CompiledModule;
 use crate::tsc::TargetLib;
 use crate::tsc::TsCompiler;
+use crate::wasm;
 use deno_core::ErrBox;
 use deno_core::ModuleSpecifier;
 use std::env;
@@ -21,6 +24,7 @@ use std::ops::Deref;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use tokio::sync::Mutex as AsyncMutex;
 
 /// Holds state of the program and can be accessed by V8 isolate.
@@ -33,10 +37,14 @@ pub struct GlobalState(Arc<GlobalStateInner>);
 pub struct GlobalStateInner {
   /// Flags parsed from `argv` contents.
   pub flags: flags::Flags,
-  /// Permissions parsed from `flags`.
+  /// Permissions parsed from `flags`, unless they were given.
   pub permissions: Permissions,
   pub dir: deno_dir::DenoDir,
+  /// HTTP client shared by module downloads and `fetch()`.
+  pub http_client: reqwest::Client,
   pub file_fetcher: SourceFileFetcher,
+  /// Cache of `fetch()` responses, if enabled with `--fetch-cache`.
+  pub fetch_cache: Option<FetchCache>,
   pub ts_compiler: TsCompiler,
   pub lockfile: Option<Mutex<Lockfile>>,
   pub compiler_starts: AtomicUsize,
@@ -53,18 +61,62 @@ impl Deref for GlobalState {
 
 impl GlobalState {
   pub fn new(flags: flags::Flags) -> Result<Self, ErrBox> {
+    let permissions = Permissions::from_flags(&flags);
+    Self::with_permissions(flags, permissions)
+  }
+
+  /// Like `new()`, with permissions other than those of the flags, like the
+  /// ones of a `PermissionPolicy` of an embedder.
+  pub fn with_permissions(
+    flags: flags::Flags,
+    permissions: Permissions,
+  ) -> Result<Self, ErrBox> {
     let custom_root = env::var("DENO_DIR").map(String::into).ok();
     let dir = deno_dir::DenoDir::new(custom_root)?;
+    Self::with_deno_dir(flags, permissions, dir)
+  }
+
+  /// Like `with_permissions()`, with a cache directory other than the one of
+  /// `$DENO_DIR`.
+  pub fn with_deno_dir(
+    flags: flags::Flags,
+    permissions: Permissions,
+    dir: deno_dir::DenoDir,
+  ) -> Result<Self, ErrBox> {
     let deps_cache_location = dir.root.join("deps");
     let http_cache = http_cache::HttpCache::new(&deps_cache_location);
+    let npm_cache_location = dir.root.join("npm");
+
+    let http_client = http_util::create_http_client(
+      flags.ca_file.clone(),
+      &http_util::PoolOptions {
+        idle_timeout: flags.http_pool_idle_timeout.map(Duration::from_secs),
+        max_idle_per_host: flags.http_pool_max_idle_per_host,
+      },
+    )?;
+
+    let env_download_headers = env::var("DENO_DOWNLOAD_HEADERS").ok();
+    let download_headers = http_util::parse_headers(
+      flags.download_headers.iter().map(String::as_str).chain(
+        env_download_headers
+          .iter()
+          .flat_map(|headers| headers.lines())
+          .filter(|line| !line.trim().is_empty()),
+      ),
+    )?;
 
     let file_fetcher = SourceFileFetcher::new(
       http_cache,
+      &npm_cache_location,
       !flags.reload,
       flags.cache_blacklist.clone(),
       flags.no_remote,
       flags.cached_only,
-      flags.ca_file.clone(),
+      http_client.clone(),
+      flags
+        .download_retries
+        .unwrap_or(http_util::DEFAULT_DOWNLOAD_RETRIES),
+      download_headers,
     )?;
 
     let ts_compiler = TsCompiler::new(
@@ -72,6 +124,7 @@ impl GlobalState {
       dir.gen_cache.clone(),
       !flags.reload,
       flags.config_path.clone(),
+      flags.no_check,
     )?;
 
     // Note: reads lazily from disk on first call to lockfile.check()
@@ -81,6 +134,20 @@ impl GlobalState {
       None
     };
 
+    let fetch_cache = if flags.fetch_cache {
+      if !flags.unstable {
+        exit_unstable("--fetch-cache")
+      }
+      let fetch_cache = FetchCache::new(&dir.root.join("fetch"));
+      let cache = fetch_cache.clone();
+      std::thread::spawn(move || {
+        let _ = cache.prune_if_due();
+      });
+      Some(fetch_cache)
+    } else {
+      None
+    };
+
     let maybe_import_map: Option<ImportMap> =
       match flags.import_map_path.as_ref() {
         None => None,
@@ -94,9 +161,11 @@ impl GlobalState {
 
     let inner = GlobalStateInner {
       dir,
-      permissions: Permissions::from_flags(&flags),
+      permissions,
       flags,
+      http_client,
       file_fetcher,
+      fetch_cache,
       ts_compiler,
       lockfile,
       maybe_import_map,
@@ -201,6 +270,11 @@ impl GlobalState {
 
     let compiled_module = if was_compiled {
       state1.ts_compiler.get_compiled_module(&out.url)?
+    } else if out.media_type == msg::MediaType::Wasm {
+      CompiledModule {
+        code: wasm::wrap(&out.source_code)?,
+        name: out.url.to_string(),
+      }
     } else {
       CompiledModule {
         code: String::from_utf8(out.source_code.clone())?,
@@ -0,0 +1,70 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Takes the heap snapshots requested by `Deno.writeHeapSnapshot()`, with
+//! the `HeapProfiler` domain of an in-process inspector session, and writes
+//! them in the `.heapsnapshot` format DevTools loads.
+
+use crate::inspector::DenoInspector;
+use crate::inspector::InProcessSession;
+use deno_core::ErrBox;
+use serde_json::Value;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+pub fn write(inspector: &mut DenoInspector, path: &Path) -> Result<(), ErrBox> {
+  let mut session = InProcessSession::new(inspector);
+  session.post("HeapProfiler.enable", json!({}))?;
+  // The snapshot is sent as a series of chunks of JSON before the response.
+  let (_, notifications) = session.post_collecting_notifications(
+    "HeapProfiler.takeHeapSnapshot",
+    json!({ "reportProgress": false }),
+  )?;
+  session.post("HeapProfiler.disable", json!({}))?;
+
+  let mut file = BufWriter::new(File::create(path)?);
+  write_chunks(&notifications, &mut file)?;
+  file.flush()?;
+  Ok(())
+}
+
+fn write_chunks(
+  notifications: &[Value],
+  writer: &mut impl Write,
+) -> Result<(), ErrBox> {
+  for notification in notifications {
+    if notification["method"] != "HeapProfiler.addHeapSnapshotChunk" {
+      continue;
+    }
+    if let Some(chunk) = notification["params"]["chunk"].as_str() {
+      writer.write_all(chunk.as_bytes())?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn chunks() {
+    let notifications = vec![
+      json!({
+        "method": "HeapProfiler.addHeapSnapshotChunk",
+        "params": { "chunk": "{\"snapshot\":" }
+      }),
+      json!({
+        "method": "HeapProfiler.reportHeapSnapshotProgress",
+        "params": { "done": 1, "total": 1 }
+      }),
+      json!({
+        "method": "HeapProfiler.addHeapSnapshotChunk",
+        "params": { "chunk": "{}}" }
+      }),
+    ];
+    let mut out = vec![];
+    write_chunks(&notifications, &mut out).unwrap();
+    assert_eq!(out, b"{\"snapshot\":{}}");
+  }
+}
@@ -1,9 +1,12 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use crate::log_category;
 use crate::version;
 use bytes::Bytes;
 use deno_core::ErrBox;
 use futures::future::FutureExt;
+use rand::Rng;
 use reqwest::header::HeaderMap;
+use reqwest::header::HeaderName;
 use reqwest::header::HeaderValue;
 use reqwest::header::IF_NONE_MATCH;
 use reqwest::header::LOCATION;
@@ -14,19 +17,42 @@ use reqwest::Response;
 use reqwest::StatusCode;
 use std::cmp::min;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::future::Future;
 use std::io;
 use std::io::Read;
 use std::pin::Pin;
+use std::sync::Mutex;
 use std::task::Context;
 use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::io::AsyncRead;
 use url::Url;
 
+/// Tuning of the connection pool of a client. Fields left to `None` keep the
+/// defaults of reqwest.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PoolOptions {
+  /// How long an idle connection is kept open for reuse.
+  pub idle_timeout: Option<Duration>,
+  /// How many idle connections are kept open for each host.
+  pub max_idle_per_host: Option<usize>,
+}
+
 /// Create new instance of async reqwest::Client. This client supports
-/// proxies and doesn't follow redirects.
-pub fn create_http_client(ca_file: Option<String>) -> Result<Client, ErrBox> {
+/// proxies and doesn't follow redirects. It sends `Accept-Encoding: gzip, br`
+/// and decodes compressed bodies as they are streamed, so consumers like
+/// `HttpBody` only ever see the decoded bytes.
+///
+/// The client is meant to be created once and shared, so that connections
+/// are kept alive across module downloads and `fetch()` calls.
+pub fn create_http_client(
+  ca_file: Option<String>,
+  pool: &PoolOptions,
+) -> Result<Client, ErrBox> {
   let mut headers = HeaderMap::new();
   headers.insert(
     USER_AGENT,
@@ -35,6 +61,8 @@ pub fn create_http_client(ca_file: Option<String>) -> Result<Client, ErrBox> {
   let mut builder = Client::builder()
     .redirect(Policy::none())
     .default_headers(headers)
+    .gzip(true)
+    .brotli(true)
     .use_rustls_tls();
 
   if let Some(ca_file) = ca_file {
@@ -44,6 +72,13 @@ pub fn create_http_client(ca_file: Option<String>) -> Result<Client, ErrBox> {
     builder = builder.add_root_certificate(cert);
   }
 
+  if let Some(idle_timeout) = pool.idle_timeout {
+    builder = builder.pool_idle_timeout(idle_timeout);
+  }
+  if let Some(max_idle_per_host) = pool.max_idle_per_host {
+    builder = builder.pool_max_idle_per_host(max_idle_per_host);
+  }
+
   builder.build().map_err(|_| {
     ErrBox::from(io::Error::new(
       io::ErrorKind::Other,
@@ -51,6 +86,106 @@ pub fn create_http_client(ca_file: Option<String>) -> Result<Client, ErrBox> {
     ))
   })
 }
+
+/// Headers to send with the downloads of remote modules. Each header is
+/// scoped to a host, so that a token for one registry is never sent to
+/// another one.
+#[derive(Clone, Debug, Default)]
+pub struct DownloadHeaders(Vec<(HeaderScope, HeaderName, HeaderValue)>);
+
+impl DownloadHeaders {
+  /// Returns the headers that should be sent with a request to `url`.
+  pub fn for_url(&self, url: &Url) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (scope, name, value) in &self.0 {
+      if scope.matches(url) {
+        map.append(name.clone(), value.clone());
+      }
+    }
+    map
+  }
+}
+
+/// The hosts a download header is sent to: either `host[:port]`, which
+/// matches that host over https (and any port when none is given), or an
+/// origin like `https://host[:port]`. Headers are only sent over plain http
+/// to an origin that spells out `http://`.
+#[derive(Clone, Debug, PartialEq)]
+struct HeaderScope {
+  scheme: Option<String>,
+  host: String,
+  port: Option<u16>,
+}
+
+impl HeaderScope {
+  fn parse(scope: &str) -> Option<Self> {
+    let (scheme, authority) = match scope.find("://") {
+      Some(index) => (Some(&scope[..index]), &scope[index + 3..]),
+      None => (None, scope),
+    };
+    if authority.is_empty() || authority.contains(&['/', '?', '#', '@'][..]) {
+      return None;
+    }
+    // A non-special scheme keeps an explicitly given default port, so that
+    // `localhost:80` only matches port 80.
+    let url =
+      Url::parse(&format!("{}://{}", scheme.unwrap_or("x-deno"), authority))
+        .ok()?;
+    let port = match scheme {
+      Some(_) => url.port_or_known_default(),
+      None => url.port(),
+    };
+    Some(HeaderScope {
+      scheme: scheme.map(str::to_lowercase),
+      host: url.host_str()?.to_lowercase(),
+      port,
+    })
+  }
+
+  fn matches(&self, url: &Url) -> bool {
+    if url.scheme() != self.scheme.as_deref().unwrap_or("https") {
+      return false;
+    }
+    if let Some(port) = self.port {
+      if url.port_or_known_default() != Some(port) {
+        return false;
+      }
+    }
+    url.host_str() == Some(self.host.as_str())
+  }
+}
+
+/// Parses headers given as `host=Name: value`, like `--download-header` and
+/// the lines of `DENO_DOWNLOAD_HEADERS` take them.
+pub fn parse_headers<'a>(
+  headers: impl IntoIterator<Item = &'a str>,
+) -> Result<DownloadHeaders, ErrBox> {
+  let mut parsed = Vec::new();
+  for header in headers {
+    let invalid = || {
+      ErrBox::from(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+          "Invalid header \"{}\", expected \"host=Name: value\"",
+          header
+        ),
+      ))
+    };
+    let mut parts = header.splitn(2, '=');
+    let scope = parts.next().unwrap().trim();
+    let header = parts.next().ok_or_else(invalid)?;
+    let scope = HeaderScope::parse(scope).ok_or_else(invalid)?;
+    let mut parts = header.splitn(2, ':');
+    let name = parts.next().unwrap().trim();
+    let value = parts.next().ok_or_else(invalid)?.trim();
+    let name =
+      HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+    let value = HeaderValue::from_str(value).map_err(|_| invalid())?;
+    parsed.push((scope, name, value));
+  }
+  Ok(DownloadHeaders(parsed))
+}
+
 /// Construct the next uri based on base uri and location header fragment
 /// See <https://tools.ietf.org/html/rfc3986#section-4.2>
 fn resolve_url_from_location(base_url: &Url, location: &str) -> Url {
@@ -99,17 +234,18 @@ pub fn fetch_once(
   client: Client,
   url: &Url,
   cached_etag: Option<String>,
+  headers: HeaderMap,
 ) -> impl Future<Output = Result<FetchOnceResult, ErrBox>> {
   let url = url.clone();
 
   let fut = async move {
-    let mut request = client.get(url.clone());
+    let mut request = client.get(url.clone()).headers(headers);
 
     if let Some(etag) = cached_etag {
       let if_none_match_val = HeaderValue::from_str(&etag).unwrap();
       request = request.header(IF_NONE_MATCH, if_none_match_val);
     }
-    let response = request.send().await?;
+    let mut response = request.send().await?;
 
     if response.status() == StatusCode::NOT_MODIFIED {
       return Ok(FetchOnceResult::NotModified);
@@ -119,7 +255,7 @@ pub fn fetch_once(
     let headers = response.headers();
 
     if let Some(warning) = headers.get("X-Deno-Warning") {
-      eprintln!(
+      warn!(
         "{} {}",
         crate::colors::yellow("Warning".to_string()),
         warning.to_str().unwrap()
@@ -145,7 +281,11 @@ pub fn fetch_once(
         .to_str()
         .unwrap();
 
-      debug!("Redirecting to {:?}...", &location_string);
+      debug!(
+        target: log_category::NET,
+        "Redirecting to {:?}...",
+        &location_string
+      );
       let new_url = resolve_url_from_location(&url, location_string);
       return Ok(FetchOnceResult::Redirect(new_url, headers_));
     }
@@ -155,12 +295,20 @@ pub fn fetch_once(
     {
       let err = io::Error::new(
         io::ErrorKind::Other,
-        format!("Import '{}' failed: {}", &url, response.status()),
+        StatusError {
+          url: url.clone(),
+          status: response.status(),
+        },
       );
       return Err(err.into());
     }
 
-    let body = response.bytes().await?.to_vec();
+    let mut progress = DownloadProgress::new(&url, response.content_length());
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+      body.extend_from_slice(&chunk);
+      progress.update(body.len() as u64);
+    }
 
     return Ok(FetchOnceResult::Code(body, headers_));
   };
@@ -168,6 +316,249 @@ pub fn fetch_once(
   fut.boxed()
 }
 
+/// The error of a response with a 4xx or 5xx status, wrapped in an
+/// `io::Error`.
+#[derive(Debug)]
+struct StatusError {
+  url: Url,
+  status: StatusCode,
+}
+
+impl fmt::Display for StatusError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Import '{}' failed: {}", self.url, self.status)
+  }
+}
+
+impl Error for StatusError {}
+
+/// How many times a download is retried by default.
+pub const DEFAULT_DOWNLOAD_RETRIES: u32 = 2;
+
+/// How long to wait before the first retry. Each retry waits about twice as
+/// long as the one before.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Like `fetch_once`, but tries again up to `retries` times when the fetch
+/// fails in a way that may go away on its own: timeouts, connections that
+/// are refused or reset, and server errors. The last error is returned once
+/// there are no retries left.
+pub async fn fetch_once_with_retries(
+  client: Client,
+  url: &Url,
+  cached_etag: Option<String>,
+  headers: &HeaderMap,
+  retries: u32,
+) -> Result<FetchOnceResult, ErrBox> {
+  let mut attempt = 0;
+  loop {
+    let result =
+      fetch_once(client.clone(), url, cached_etag.clone(), headers.clone())
+        .await;
+    match result {
+      Err(err) if attempt < retries && is_transient(&err) => {
+        let delay = retry_delay(attempt);
+        warn!(
+          "{} {}: {}, retrying in {}ms",
+          crate::colors::yellow("Download failed".to_string()),
+          url,
+          err,
+          delay.as_millis()
+        );
+        tokio::time::delay_for(delay).await;
+        attempt += 1;
+      }
+      result => return result,
+    }
+  }
+}
+
+/// Waits grow exponentially, with jitter so that the downloads of a module
+/// graph that failed together don't all retry at once.
+fn retry_delay(attempt: u32) -> Duration {
+  let max = RETRY_BASE_DELAY * 2u32.pow(min(attempt, 6));
+  let max_millis = max.as_millis() as u64;
+  let millis = rand::thread_rng().gen_range(max_millis / 2, max_millis + 1);
+  Duration::from_millis(millis)
+}
+
+/// Whether an error of `fetch_once` may go away by trying again.
+fn is_transient(err: &ErrBox) -> bool {
+  if let Some(err) = err.downcast_ref::<io::Error>() {
+    if let Some(status_error) =
+      err.get_ref().and_then(|e| e.downcast_ref::<StatusError>())
+    {
+      return status_error.status.is_server_error();
+    }
+    return is_transient_io(err);
+  }
+  if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+    if err.is_timeout() {
+      return true;
+    }
+    let mut source = err.source();
+    while let Some(err) = source {
+      if let Some(err) = err.downcast_ref::<io::Error>() {
+        return is_transient_io(err);
+      }
+      source = err.source();
+    }
+  }
+  false
+}
+
+fn is_transient_io(err: &io::Error) -> bool {
+  use io::ErrorKind::*;
+  matches!(
+    err.kind(),
+    ConnectionRefused
+      | ConnectionReset
+      | ConnectionAborted
+      | BrokenPipe
+      | TimedOut
+      | UnexpectedEof
+  )
+}
+
+pub fn format_progress(done: u64, total: Option<u64>) -> String {
+  const MIB: f64 = 1024.0 * 1024.0;
+  match total {
+    Some(total) if total > 0 => format!(
+      "{:.1} / {:.1} MiB ({}%)",
+      done as f64 / MIB,
+      total as f64 / MIB,
+      done * 100 / total
+    ),
+    _ => format!("{:.1} MiB", done as f64 / MIB),
+  }
+}
+
+/// How long downloads run before their progress is shown. Most modules are
+/// small and downloaded well before that.
+const PROGRESS_DELAY: Duration = Duration::from_secs(1);
+
+/// How often the progress of downloads is redrawn.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Transfer {
+  url: Url,
+  done: u64,
+  total: Option<u64>,
+}
+
+/// The single progress line of all the downloads in flight. Module graphs
+/// are downloaded concurrently, and one line per download would have them
+/// overwrite each other.
+#[derive(Default)]
+struct ProgressLine {
+  transfers: HashMap<usize, Transfer>,
+  next_id: usize,
+  started: Option<Instant>,
+  last_draw: Option<Instant>,
+  line_len: usize,
+}
+
+impl ProgressLine {
+  /// What is being downloaded and how much of it is done.
+  fn status(&self) -> String {
+    let done: u64 = self.transfers.values().map(|t| t.done).sum();
+    let total: Option<u64> = self.transfers.values().map(|t| t.total).sum();
+    let what = match self.transfers.values().next() {
+      Some(transfer) if self.transfers.len() == 1 => transfer.url.to_string(),
+      _ => format!("{} files", self.transfers.len()),
+    };
+    format!("{} {}", what, format_progress(done, total))
+  }
+
+  fn draw(&mut self) {
+    let now = Instant::now();
+    match self.started {
+      Some(started) if now.duration_since(started) >= PROGRESS_DELAY => {}
+      _ => return,
+    }
+    if let Some(last_draw) = self.last_draw {
+      if now.duration_since(last_draw) < PROGRESS_INTERVAL {
+        return;
+      }
+    }
+    self.last_draw = Some(now);
+    let line = format!(
+      "{} {}",
+      crate::colors::green("Download".to_string()),
+      self.status()
+    );
+    let line_len = crate::colors::strip_ansi_codes(&line).chars().count();
+    let padding = self.line_len.saturating_sub(line_len);
+    eprint!("\r{}{}", line, " ".repeat(padding));
+    self.line_len = line_len;
+  }
+
+  fn erase(&mut self) {
+    if self.last_draw.is_some() {
+      eprint!("\r{}\r", " ".repeat(self.line_len));
+    }
+    self.started = None;
+    self.last_draw = None;
+    self.line_len = 0;
+  }
+}
+
+lazy_static! {
+  static ref PROGRESS_LINE: Mutex<ProgressLine> =
+    Mutex::new(ProgressLine::default());
+}
+
+/// Adds a download to the progress line drawn on stderr once downloads have
+/// run for a while, so that slow ones don't look like a hang. The line
+/// sums up every download in flight, and is erased when the last one is
+/// over.
+struct DownloadProgress {
+  id: Option<usize>,
+}
+
+impl DownloadProgress {
+  fn new(url: &Url, total: Option<u64>) -> Self {
+    if !log_enabled!(log::Level::Info) || !atty::is(atty::Stream::Stderr) {
+      return Self { id: None };
+    }
+    let mut line = PROGRESS_LINE.lock().unwrap();
+    let id = line.next_id;
+    line.next_id += 1;
+    line.started.get_or_insert_with(Instant::now);
+    line.transfers.insert(
+      id,
+      Transfer {
+        url: url.clone(),
+        done: 0,
+        total,
+      },
+    );
+    Self { id: Some(id) }
+  }
+
+  fn update(&mut self, done: u64) {
+    if let Some(id) = self.id {
+      let mut line = PROGRESS_LINE.lock().unwrap();
+      if let Some(transfer) = line.transfers.get_mut(&id) {
+        transfer.done = done;
+      }
+      line.draw();
+    }
+  }
+}
+
+impl Drop for DownloadProgress {
+  fn drop(&mut self) {
+    if let Some(id) = self.id {
+      let mut line = PROGRESS_LINE.lock().unwrap();
+      line.transfers.remove(&id);
+      if line.transfers.is_empty() {
+        line.erase();
+      }
+    }
+  }
+}
+
 /// Wraps reqwest `Response` so that it can be exposed as an `AsyncRead` and integrated
 /// into resources more easily.
 pub struct HttpBody {
@@ -195,6 +586,7 @@ impl AsyncRead for HttpBody {
     let mut inner = self.get_mut();
     if let Some(chunk) = inner.chunk.take() {
       debug!(
+        target: log_category::NET,
         "HttpBody Fake Read buf {} chunk {} pos {}",
         buf.len(),
         chunk.len(),
@@ -220,9 +612,13 @@ impl AsyncRead for HttpBody {
     futures::pin_mut!(chunk_future);
 
     let result = match futures::ready!(chunk_future.poll(cx)) {
+      Err(e) if e.is_timeout() => {
+        Err(io::Error::new(io::ErrorKind::TimedOut, e))
+      }
       Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
       Ok(Some(chunk)) => {
         debug!(
+          target: log_category::NET,
           "HttpBody Real Read buf {} chunk {} pos {}",
           buf.len(),
           chunk.len(),
@@ -252,8 +648,8 @@ mod tests {
     // Relies on external http server. See tools/http_server.py
     let url =
       Url::parse("http://127.0.0.1:4545/cli/tests/fixture.json").unwrap();
-    let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let client = create_http_client(None, &PoolOptions::default()).unwrap();
+    let result = fetch_once(client, &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(headers.get("content-type").unwrap(), "application/json");
@@ -273,8 +669,8 @@ mod tests {
       "http://127.0.0.1:4545/cli/tests/053_import_compression/gziped",
     )
     .unwrap();
-    let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let client = create_http_client(None, &PoolOptions::default()).unwrap();
+    let result = fetch_once(client, &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('gzip')");
       assert_eq!(
@@ -293,8 +689,8 @@ mod tests {
   async fn test_fetch_with_etag() {
     let http_server_guard = crate::test_util::http_server();
     let url = Url::parse("http://127.0.0.1:4545/etag_script.ts").unwrap();
-    let client = create_http_client(None).unwrap();
-    let result = fetch_once(client.clone(), &url, None).await;
+    let client = create_http_client(None, &PoolOptions::default()).unwrap();
+    let result = fetch_once(client.clone(), &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('etag')");
@@ -307,8 +703,13 @@ mod tests {
       panic!();
     }
 
-    let res =
-      fetch_once(client, &url, Some("33a64df551425fcc55e".to_string())).await;
+    let res = fetch_once(
+      client,
+      &url,
+      Some("33a64df551425fcc55e".to_string()),
+      HeaderMap::new(),
+    )
+    .await;
     assert_eq!(res.unwrap(), FetchOnceResult::NotModified);
 
     drop(http_server_guard);
@@ -322,8 +723,8 @@ mod tests {
       "http://127.0.0.1:4545/cli/tests/053_import_compression/brotli",
     )
     .unwrap();
-    let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let client = create_http_client(None, &PoolOptions::default()).unwrap();
+    let result = fetch_once(client, &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('brotli');");
@@ -348,8 +749,8 @@ mod tests {
     // Dns resolver substitutes `127.0.0.1` with `localhost`
     let target_url =
       Url::parse("http://localhost:4545/cli/tests/fixture.json").unwrap();
-    let client = create_http_client(None).unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let client = create_http_client(None, &PoolOptions::default()).unwrap();
+    let result = fetch_once(client, &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Redirect(url, _)) = result {
       assert_eq!(url, target_url);
     } else {
@@ -358,6 +759,130 @@ mod tests {
     drop(http_server_guard);
   }
 
+  #[test]
+  fn test_is_transient() {
+    let status_error = |status| -> ErrBox {
+      io::Error::new(
+        io::ErrorKind::Other,
+        StatusError {
+          url: Url::parse("https://deno.land/x/mod.ts").unwrap(),
+          status,
+        },
+      )
+      .into()
+    };
+    let err = status_error(StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+      err.to_string(),
+      "Import 'https://deno.land/x/mod.ts' failed: 503 Service Unavailable"
+    );
+    assert!(is_transient(&err));
+    assert!(!is_transient(&status_error(StatusCode::NOT_FOUND)));
+    let reset: ErrBox = io::Error::from(io::ErrorKind::ConnectionReset).into();
+    assert!(is_transient(&reset));
+    let not_found: ErrBox = io::Error::from(io::ErrorKind::NotFound).into();
+    assert!(!is_transient(&not_found));
+  }
+
+  #[test]
+  fn test_retry_delay() {
+    for attempt in 0..10 {
+      let max = RETRY_BASE_DELAY * 2u32.pow(min(attempt, 6));
+      let delay = retry_delay(attempt);
+      assert!(delay >= max / 2);
+      assert!(delay <= max);
+    }
+  }
+
+  #[test]
+  fn test_format_progress() {
+    assert_eq!(
+      format_progress(512 * 1024, Some(2 * 1024 * 1024)),
+      "0.5 / 2.0 MiB (25%)"
+    );
+    assert_eq!(format_progress(3 * 1024 * 1024, None), "3.0 MiB");
+  }
+
+  #[test]
+  fn test_progress_line_status() {
+    const MIB: u64 = 1024 * 1024;
+    let mut line = ProgressLine::default();
+    let url = Url::parse("https://deno.land/x/a.ts").unwrap();
+    line.transfers.insert(
+      0,
+      Transfer {
+        url: url.clone(),
+        done: MIB,
+        total: Some(2 * MIB),
+      },
+    );
+    assert_eq!(
+      line.status(),
+      "https://deno.land/x/a.ts 1.0 / 2.0 MiB (50%)"
+    );
+    line.transfers.insert(
+      1,
+      Transfer {
+        url: url.clone(),
+        done: 0,
+        total: Some(2 * MIB),
+      },
+    );
+    assert_eq!(line.status(), "2 files 1.0 / 4.0 MiB (25%)");
+    line.transfers.insert(
+      2,
+      Transfer {
+        url,
+        done: MIB,
+        total: None,
+      },
+    );
+    assert_eq!(line.status(), "3 files 2.0 MiB");
+  }
+
+  #[test]
+  fn test_parse_headers() {
+    let headers = parse_headers(vec![
+      "deno.land=X-Org-Token: secret",
+      "deno.land=accept-language:en",
+      "DENO.LAND = X-Org-Token: a=b",
+      "localhost:4545=X-Org-Token: local",
+      "https://example.com=X-Org-Token: https",
+      "http://localhost:4546=X-Org-Token: http",
+    ])
+    .unwrap();
+    let tokens = |url: &str| -> Vec<String> {
+      headers
+        .for_url(&Url::parse(url).unwrap())
+        .get_all("x-org-token")
+        .iter()
+        .map(|v| v.to_str().unwrap().to_string())
+        .collect()
+    };
+    assert_eq!(tokens("https://deno.land/x/mod.ts"), vec!["secret", "a=b"]);
+    assert_eq!(
+      tokens("https://deno.land:8000/mod.ts"),
+      vec!["secret", "a=b"]
+    );
+    // Without a scheme in the scope, nothing is sent over plain http.
+    assert!(tokens("http://deno.land/mod.ts").is_empty());
+    assert!(tokens("https://sub.deno.land/mod.ts").is_empty());
+    assert_eq!(tokens("https://localhost:4545/mod.ts"), vec!["local"]);
+    assert!(tokens("http://localhost:4545/mod.ts").is_empty());
+    assert_eq!(tokens("http://localhost:4546/mod.ts"), vec!["http"]);
+    assert!(tokens("https://localhost:4546/mod.ts").is_empty());
+    assert_eq!(tokens("https://example.com/mod.ts"), vec!["https"]);
+    assert!(tokens("http://example.com/mod.ts").is_empty());
+    assert!(tokens("https://example.com:8443/mod.ts").is_empty());
+    let url = Url::parse("https://deno.land/").unwrap();
+    assert_eq!(headers.for_url(&url).get("Accept-Language").unwrap(), "en");
+    assert!(parse_headers(vec!["X-Org-Token: secret"]).is_err());
+    assert!(parse_headers(vec!["deno.land=X-Org-Token"]).is_err());
+    assert!(parse_headers(vec!["deno.land=Bad Name: value"]).is_err());
+    assert!(parse_headers(vec!["deno.land/x=X-Org-Token: a"]).is_err());
+    assert!(parse_headers(vec!["=X-Org-Token: a"]).is_err());
+  }
+
   #[test]
   fn test_resolve_url_from_location_full_1() {
     let url = "http://deno.land".parse::<Url>().unwrap();
@@ -403,14 +928,17 @@ mod tests {
     let url =
       Url::parse("https://localhost:5545/cli/tests/fixture.json").unwrap();
 
-    let client = create_http_client(Some(String::from(
-      crate::test_util::root_path()
-        .join("std/http/testdata/tls/RootCA.pem")
-        .to_str()
-        .unwrap(),
-    )))
+    let client = create_http_client(
+      Some(String::from(
+        crate::test_util::root_path()
+          .join("std/http/testdata/tls/RootCA.pem")
+          .to_str()
+          .unwrap(),
+      )),
+      &PoolOptions::default(),
+    )
     .unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(headers.get("content-type").unwrap(), "application/json");
@@ -430,14 +958,17 @@ mod tests {
       "https://localhost:5545/cli/tests/053_import_compression/gziped",
     )
     .unwrap();
-    let client = create_http_client(Some(String::from(
-      crate::test_util::root_path()
-        .join("std/http/testdata/tls/RootCA.pem")
-        .to_str()
-        .unwrap(),
-    )))
+    let client = create_http_client(
+      Some(String::from(
+        crate::test_util::root_path()
+          .join("std/http/testdata/tls/RootCA.pem")
+          .to_str()
+          .unwrap(),
+      )),
+      &PoolOptions::default(),
+    )
     .unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('gzip')");
       assert_eq!(
@@ -456,14 +987,17 @@ mod tests {
   async fn test_fetch_with_cafile_with_etag() {
     let http_server_guard = crate::test_util::http_server();
     let url = Url::parse("https://localhost:5545/etag_script.ts").unwrap();
-    let client = create_http_client(Some(String::from(
-      crate::test_util::root_path()
-        .join("std/http/testdata/tls/RootCA.pem")
-        .to_str()
-        .unwrap(),
-    )))
+    let client = create_http_client(
+      Some(String::from(
+        crate::test_util::root_path()
+          .join("std/http/testdata/tls/RootCA.pem")
+          .to_str()
+          .unwrap(),
+      )),
+      &PoolOptions::default(),
+    )
     .unwrap();
-    let result = fetch_once(client.clone(), &url, None).await;
+    let result = fetch_once(client.clone(), &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('etag')");
@@ -477,8 +1011,13 @@ mod tests {
       panic!();
     }
 
-    let res =
-      fetch_once(client, &url, Some("33a64df551425fcc55e".to_string())).await;
+    let res = fetch_once(
+      client,
+      &url,
+      Some("33a64df551425fcc55e".to_string()),
+      HeaderMap::new(),
+    )
+    .await;
     assert_eq!(res.unwrap(), FetchOnceResult::NotModified);
 
     drop(http_server_guard);
@@ -492,14 +1031,17 @@ mod tests {
       "https://localhost:5545/cli/tests/053_import_compression/brotli",
     )
     .unwrap();
-    let client = create_http_client(Some(String::from(
-      crate::test_util::root_path()
-        .join("std/http/testdata/tls/RootCA.pem")
-        .to_str()
-        .unwrap(),
-    )))
+    let client = create_http_client(
+      Some(String::from(
+        crate::test_util::root_path()
+          .join("std/http/testdata/tls/RootCA.pem")
+          .to_str()
+          .unwrap(),
+      )),
+      &PoolOptions::default(),
+    )
     .unwrap();
-    let result = fetch_once(client, &url, None).await;
+    let result = fetch_once(client, &url, None, HeaderMap::new()).await;
     if let Ok(FetchOnceResult::Code(body, headers)) = result {
       assert!(!body.is_empty());
       assert_eq!(String::from_utf8(body).unwrap(), "console.log('brotli');");
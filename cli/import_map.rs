@@ -48,11 +48,8 @@ pub struct ImportMap {
 impl ImportMap {
   pub fn load(file_path: &str) -> Result<Self, ErrBox> {
     let file_url = ModuleSpecifier::resolve_url_or_path(file_path)?.to_string();
-    let resolved_path = std::env::current_dir().unwrap().join(file_path);
-    debug!(
-      "Attempt to load import map: {}",
-      resolved_path.to_str().unwrap()
-    );
+    let resolved_path = std::env::current_dir()?.join(file_path);
+    debug!("Attempt to load import map: {}", resolved_path.display());
 
     // Load the contents of import map
     let json_string = fs::read_to_string(&resolved_path).map_err(|err| {
@@ -188,7 +185,7 @@ impl ImportMap {
 
       let url_string = url.to_string();
       if specifier_key.ends_with('/') && !url_string.ends_with('/') {
-        eprintln!(
+        warn!(
           "Invalid target address {:?} for package specifier {:?}.\
            Package address targets must end with \"/\".",
           url_string, specifier_key
@@ -291,7 +288,7 @@ impl ImportMap {
         match Url::parse(base_url).unwrap().join(scope_prefix) {
           Ok(url) => {
             if !SUPPORTED_FETCH_SCHEMES.contains(&url.scheme()) {
-              eprintln!(
+              warn!(
               "Invalid scope {:?}. Scope URLs must have a valid fetch scheme.",
               url.to_string()
             );
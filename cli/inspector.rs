@@ -6,6 +6,7 @@
 
 use core::convert::Infallible as Never; // Alias for the future `!` type.
 use deno_core::v8;
+use deno_core::ErrBox;
 use futures::channel::mpsc;
 use futures::channel::mpsc::UnboundedReceiver;
 use futures::channel::mpsc::UnboundedSender;
@@ -17,10 +18,12 @@ use futures::stream::FuturesUnordered;
 use futures::task;
 use futures::task::Context;
 use futures::task::Poll;
+use serde_json::Value;
 use std::cell::BorrowMutError;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::ffi::c_void;
+use std::io;
 use std::mem::replace;
 use std::mem::take;
 use std::mem::MaybeUninit;
@@ -302,8 +305,10 @@ pub struct DenoInspector {
   sessions: RefCell<InspectorSessions>,
   flags: RefCell<InspectorFlags>,
   waker: Arc<InspectorWaker>,
+  /// Drives the pauses instead of the debuggers, see `set_pause_handler()`.
+  pause_handler: RefCell<Option<Box<dyn FnMut()>>>,
   _canary_tx: oneshot::Sender<Never>,
-  pub debugger_url: String,
+  pub debugger_url: Option<String>,
 }
 
 impl Deref for DenoInspector {
@@ -325,6 +330,8 @@ impl Drop for DenoInspector {
     // Set the poll state to 'dropped' so it doesn't attempt to request an
     // interrupt from the isolate.
     self.waker.update(|w| w.poll_state = PollState::Dropped);
+    // The handler may own an in-process session.
+    self.pause_handler.borrow_mut().take();
     // V8 automatically deletes all sessions when an Inspector instance is
     // deleted, however InspectorSession also has a drop handler that cleans
     // up after itself. To avoid a double free, make sure the inspector is
@@ -345,6 +352,14 @@ impl v8::inspector::V8InspectorClientImpl for DenoInspector {
   fn run_message_loop_on_pause(&mut self, context_group_id: i32) {
     assert_eq!(context_group_id, DenoInspectorSession::CONTEXT_GROUP_ID);
     self.flags.borrow_mut().on_pause = true;
+    let handler = self.pause_handler.borrow_mut().take();
+    if let Some(mut handler) = handler {
+      while self.flags.borrow().on_pause {
+        handler();
+      }
+      self.pause_handler.borrow_mut().replace(handler);
+      return;
+    }
     let _ = self.poll_sessions(None);
   }
 
@@ -369,11 +384,13 @@ impl Future for DenoInspector {
 }
 
 impl DenoInspector {
-  const CONTEXT_GROUP_ID: i32 = 1;
+  pub const CONTEXT_GROUP_ID: i32 = 1;
 
+  /// Without a `host`, debuggers can't connect to the inspector, which can
+  /// only be used by in-process sessions such as the `CoverageCollector`.
   pub fn new(
     isolate: &mut deno_core::CoreIsolate,
-    host: SocketAddr,
+    host: Option<SocketAddr>,
   ) -> Box<Self> {
     let core_state_rc = deno_core::CoreIsolate::state(isolate);
     let core_state = core_state_rc.borrow();
@@ -385,13 +402,13 @@ impl DenoInspector {
       mpsc::unbounded::<WebSocketProxy>();
     let (canary_tx, canary_rx) = oneshot::channel::<Never>();
 
-    let info = InspectorInfo {
+    let info = host.map(|host| InspectorInfo {
       host,
       uuid: Uuid::new_v4(),
       thread_name: thread::current().name().map(|n| n.to_owned()),
       new_websocket_tx,
       canary_rx,
-    };
+    });
 
     // Create DenoInspector instance.
     let mut self_ = new_box_with(|self_ptr| {
@@ -410,8 +427,9 @@ impl DenoInspector {
         sessions,
         flags,
         waker,
+        pause_handler: RefCell::new(None),
         _canary_tx: canary_tx,
-        debugger_url: info.get_websocket_debugger_url(),
+        debugger_url: info.as_ref().map(|i| i.get_websocket_debugger_url()),
       }
     });
 
@@ -421,7 +439,9 @@ impl DenoInspector {
     self_.context_created(context, Self::CONTEXT_GROUP_ID, context_name);
 
     // Register this inspector with the server thread.
-    InspectorServer::register_inspector(info);
+    if let Some(info) = info {
+      InspectorServer::register_inspector(info);
+    }
 
     // Poll the session handler so we will get notified whenever there is
     // new_incoming debugger activity.
@@ -535,6 +555,13 @@ impl DenoInspector {
     }
   }
 
+  /// Makes `handler` drive the pauses of the isolate rather than the
+  /// debuggers, for a front end in the process like the debugger of the
+  /// REPL. It's called in a loop until a command it sends resumes execution.
+  pub fn set_pause_handler(&self, handler: Box<dyn FnMut()>) {
+    self.pause_handler.borrow_mut().replace(handler);
+  }
+
   /// This function blocks the thread until at least one inspector client has
   /// established a websocket connection and successfully completed the
   /// handshake. After that, it instructs V8 to pause at the next statement.
@@ -793,7 +820,131 @@ impl Future for DenoInspectorSession {
   }
 }
 
-fn new_box_with<T>(new_fn: impl FnOnce(*mut T) -> T) -> Box<T> {
+/// An inspector session driven from Rust rather than by a debugger, such as
+/// the ones collecting the coverage or the CPU profile of the main worker.
+pub struct InProcessSession {
+  v8_channel: v8::inspector::ChannelBase,
+  v8_session: v8::UniqueRef<v8::inspector::V8InspectorSession>,
+  responses: HashMap<i32, Value>,
+  /// The notifications received while they are being collected.
+  notifications: Option<Vec<Value>>,
+  /// The method whose notifications are kept until they're taken.
+  kept_method: Option<String>,
+  kept: Vec<Value>,
+  next_id: i32,
+}
+
+impl v8::inspector::ChannelImpl for InProcessSession {
+  fn base(&self) -> &v8::inspector::ChannelBase {
+    &self.v8_channel
+  }
+
+  fn base_mut(&mut self) -> &mut v8::inspector::ChannelBase {
+    &mut self.v8_channel
+  }
+
+  fn send_response(
+    &mut self,
+    call_id: i32,
+    message: v8::UniquePtr<v8::inspector::StringBuffer>,
+  ) {
+    let message = message.unwrap().string().to_string();
+    if let Ok(message) = serde_json::from_str(&message) {
+      self.responses.insert(call_id, message);
+    }
+  }
+
+  fn send_notification(
+    &mut self,
+    message: v8::UniquePtr<v8::inspector::StringBuffer>,
+  ) {
+    if self.notifications.is_none() && self.kept_method.is_none() {
+      return;
+    }
+    let message = message.unwrap().string().to_string();
+    let message: Value = match serde_json::from_str(&message) {
+      Ok(message) => message,
+      Err(_) => return,
+    };
+    if let Some(notifications) = self.notifications.as_mut() {
+      notifications.push(message);
+    } else if self.kept_method.as_deref() == message["method"].as_str() {
+      self.kept.push(message);
+    }
+  }
+
+  fn flush_protocol_notifications(&mut self) {}
+}
+
+impl InProcessSession {
+  /// The session must be dropped before the inspector.
+  pub fn new(inspector: &mut DenoInspector) -> Box<Self> {
+    new_box_with(move |self_ptr| {
+      let v8_channel = v8::inspector::ChannelBase::new::<Self>();
+      let v8_session = inspector.connect(
+        DenoInspector::CONTEXT_GROUP_ID,
+        unsafe { &mut *self_ptr },
+        v8::inspector::StringView::empty(),
+      );
+      Self {
+        v8_channel,
+        v8_session,
+        responses: HashMap::new(),
+        notifications: None,
+        kept_method: None,
+        kept: vec![],
+        next_id: 1,
+      }
+    })
+  }
+
+  /// Like `post()`, but also returns the notifications sent while `method`
+  /// was handled, such as the chunks of a heap snapshot.
+  pub fn post_collecting_notifications(
+    &mut self,
+    method: &str,
+    params: Value,
+  ) -> Result<(Value, Vec<Value>), ErrBox> {
+    self.notifications = Some(vec![]);
+    let result = self.post(method, params);
+    let notifications = self.notifications.take().unwrap_or_default();
+    Ok((result?, notifications))
+  }
+
+  /// Keeps the notifications of `method`, like `Debugger.paused`, until
+  /// they're taken with `take_notifications()`.
+  pub fn keep_notifications(&mut self, method: &str) {
+    self.kept_method = Some(method.to_string());
+  }
+
+  pub fn take_notifications(&mut self) -> Vec<Value> {
+    std::mem::take(&mut self.kept)
+  }
+
+  /// Calls `method` and returns its result. V8 handles the messages of the
+  /// protocol synchronously, so the response has been received once
+  /// dispatch_protocol_message() returns.
+  pub fn post(&mut self, method: &str, params: Value) -> Result<Value, ErrBox> {
+    let id = self.next_id;
+    self.next_id += 1;
+    let message = json!({ "id": id, "method": method, "params": params });
+    let message = message.to_string();
+    let message = v8::inspector::StringView::from(message.as_bytes());
+    self.v8_session.dispatch_protocol_message(message);
+
+    let mut response = self.responses.remove(&id).ok_or_else(|| {
+      let msg = format!("No response to {}", method);
+      io::Error::new(io::ErrorKind::Other, msg)
+    })?;
+    if let Some(error) = response.get("error") {
+      let msg = format!("{} failed: {}", method, error["message"]);
+      return Err(io::Error::new(io::ErrorKind::Other, msg).into());
+    }
+    Ok(response["result"].take())
+  }
+}
+
+pub fn new_box_with<T>(new_fn: impl FnOnce(*mut T) -> T) -> Box<T> {
   let b = Box::new(MaybeUninit::<T>::uninit());
   let p = Box::into_raw(b) as *mut T;
   unsafe { ptr::write(p, new_fn(p)) };
@@ -192,6 +192,18 @@ pub fn install(
     executable_args.push("--cert".to_string());
     executable_args.push(ca_file)
   }
+  if let Some(download_retries) = flags.download_retries {
+    executable_args.push("--download-retries".to_string());
+    executable_args.push(download_retries.to_string());
+  }
+  if let Some(idle_timeout) = flags.http_pool_idle_timeout {
+    executable_args.push("--http-pool-idle-timeout".to_string());
+    executable_args.push(idle_timeout.to_string());
+  }
+  if let Some(max_idle) = flags.http_pool_max_idle_per_host {
+    executable_args.push("--http-pool-max-idle-per-host".to_string());
+    executable_args.push(max_idle.to_string());
+  }
   if let Some(log_level) = flags.log_level {
     if log_level == Level::Error {
       executable_args.push("--quiet".to_string());
@@ -211,6 +223,10 @@ pub fn install(
     }
   }
 
+  if !flags.log_categories.is_empty() {
+    executable_args.push(format!("--log={}", flags.log_categories.join(",")));
+  }
+
   if flags.unstable {
     executable_args.push("--unstable".to_string());
   }
@@ -236,6 +252,48 @@ pub fn install(
   Ok(())
 }
 
+/// Marker written by `generate_executable_file`, used to avoid removing
+/// files that were not created by `deno install`.
+const GENERATED_MARKER: &str = "generated by deno install";
+
+pub fn uninstall(name: String, root: Option<PathBuf>) -> Result<(), Error> {
+  validate_name(&name)?;
+  let root = if let Some(root) = root {
+    root.canonicalize()?
+  } else {
+    get_installer_root()?
+  };
+  let installation_dir = root.join("bin");
+
+  let mut file_path = installation_dir.join(&name);
+  if cfg!(windows) {
+    file_path = file_path.with_extension("cmd");
+  }
+
+  if !file_path.is_file() {
+    return Err(Error::new(
+      ErrorKind::NotFound,
+      format!("No installation found for {}", name),
+    ));
+  }
+
+  let content = fs::read_to_string(&file_path).unwrap_or_default();
+  if !content.contains(GENERATED_MARKER) {
+    return Err(Error::new(
+      ErrorKind::Other,
+      format!(
+        "{} was not installed by deno install. Aborting.",
+        file_path.to_string_lossy()
+      ),
+    ));
+  }
+
+  fs::remove_file(&file_path)?;
+  println!("deleted {}", file_path.to_string_lossy());
+  println!("✅ Successfully uninstalled {}", name);
+  Ok(())
+}
+
 fn is_in_path(dir: &PathBuf) -> bool {
   if let Some(paths) = env::var_os("PATH") {
     for p in env::split_paths(&paths) {
@@ -550,6 +608,35 @@ mod tests {
     assert!(content.contains(r#""run" "--allow-read" "--allow-net" "--quiet" "http://localhost:4545/cli/tests/echo_server.ts" "--foobar""#));
   }
 
+  #[test]
+  fn install_with_http_pool_flags() {
+    let temp_dir = TempDir::new().expect("tempdir fail");
+    let bin_dir = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin_dir).unwrap();
+
+    install(
+      Flags {
+        http_pool_idle_timeout: Some(30),
+        http_pool_max_idle_per_host: Some(4),
+        ..Flags::default()
+      },
+      "http://localhost:4545/cli/tests/echo_server.ts",
+      vec![],
+      Some("echo_test".to_string()),
+      Some(temp_dir.path().to_path_buf()),
+      false,
+    )
+    .expect("Install failed");
+
+    let mut file_path = bin_dir.join("echo_test");
+    if cfg!(windows) {
+      file_path = file_path.with_extension("cmd");
+    }
+
+    let content = fs::read_to_string(file_path).unwrap();
+    assert!(content.contains(r#""run" "--http-pool-idle-timeout" "30" "--http-pool-max-idle-per-host" "4" "http://localhost:4545/cli/tests/echo_server.ts""#));
+  }
+
   #[test]
   fn install_local_module() {
     let temp_dir = TempDir::new().expect("tempdir fail");
@@ -633,4 +720,58 @@ mod tests {
     let file_content_2 = fs::read_to_string(&file_path).unwrap();
     assert!(file_content_2.contains("cat.ts"));
   }
+
+  #[test]
+  fn uninstall_basic() {
+    let temp_dir = TempDir::new().expect("tempdir fail");
+    let bin_dir = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin_dir).unwrap();
+
+    install(
+      Flags::default(),
+      "http://localhost:4545/cli/tests/echo_server.ts",
+      vec![],
+      Some("echo_test".to_string()),
+      Some(temp_dir.path().to_path_buf()),
+      false,
+    )
+    .expect("Install failed");
+
+    let mut file_path = bin_dir.join("echo_test");
+    if cfg!(windows) {
+      file_path = file_path.with_extension("cmd");
+    }
+    assert!(file_path.exists());
+
+    uninstall("echo_test".to_string(), Some(temp_dir.path().to_path_buf()))
+      .expect("Uninstall failed");
+    assert!(!file_path.exists());
+
+    let not_found =
+      uninstall("echo_test".to_string(), Some(temp_dir.path().to_path_buf()));
+    assert!(not_found
+      .unwrap_err()
+      .to_string()
+      .contains("No installation found"));
+  }
+
+  #[test]
+  fn uninstall_keeps_foreign_files() {
+    let temp_dir = TempDir::new().expect("tempdir fail");
+    let bin_dir = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin_dir).unwrap();
+    let mut file_path = bin_dir.join("deno");
+    if cfg!(windows) {
+      file_path = file_path.with_extension("cmd");
+    }
+    fs::write(&file_path, "not a shim").unwrap();
+
+    let result =
+      uninstall("deno".to_string(), Some(temp_dir.path().to_path_buf()));
+    assert!(result
+      .unwrap_err()
+      .to_string()
+      .contains("was not installed by deno install"));
+    assert!(file_path.exists());
+  }
 }
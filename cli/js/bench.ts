@@ -0,0 +1,90 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { exposeForTest } from "./internals.ts";
+import { BenchStats, benchBegin, benchStep } from "./ops/bench.ts";
+import { close } from "./ops/resources.ts";
+import { RunFn, RunTestsOptions, runSuite } from "./testing.ts";
+
+export { BenchStats } from "./ops/bench.ts";
+
+export interface BenchDefinition {
+  fn: () => void | Promise<void>;
+  name: string;
+  ignore?: boolean;
+}
+
+const BENCH_REGISTRY: BenchDefinition[] = [];
+
+export function bench(b: BenchDefinition): void;
+export function bench(name: string, fn: () => void | Promise<void>): void;
+export function bench(
+  b: string | BenchDefinition,
+  fn?: () => void | Promise<void>
+): void {
+  let benchDef: BenchDefinition;
+  if (typeof b === "string") {
+    if (!fn || typeof fn != "function") {
+      throw new TypeError("Missing benchmark function");
+    }
+    benchDef = { fn, name: b, ignore: false };
+  } else {
+    if (!b.fn) {
+      throw new TypeError("Missing benchmark function");
+    }
+    benchDef = { ignore: false, ...b };
+  }
+  if (!benchDef.name) {
+    throw new TypeError("The benchmark name can't be empty");
+  }
+  BENCH_REGISTRY.push(benchDef);
+}
+
+// Runs `fn` as many times as the ops ask, until they return the statistics.
+async function measure(fn: () => void | Promise<void>): Promise<BenchStats> {
+  const { rid, iterations: first } = benchBegin();
+  let iterations = first;
+  let done = false;
+  try {
+    while (true) {
+      for (let i = 0; i < iterations; i++) {
+        const result = fn();
+        if (result instanceof Promise) {
+          await result;
+        }
+      }
+      const step = benchStep(rid);
+      if (step.stats) {
+        // The op closes the benchmark once it is done.
+        done = true;
+        return step.stats;
+      }
+      iterations = step.iterations!;
+    }
+  } finally {
+    if (!done) {
+      close(rid);
+    }
+  }
+}
+
+type RunBenchmarksOptions = Pick<
+  RunTestsOptions,
+  "exitOnFail" | "filter" | "skip" | "reportToConsole" | "onMessage"
+>;
+
+// Benchmarks go through the runner and reporters of `deno test`, and are
+// measured rather than just run.
+function runBenchmarks(
+  options: RunBenchmarksOptions = {}
+): ReturnType<typeof runSuite> {
+  return runSuite(
+    BENCH_REGISTRY,
+    "bench",
+    async ({ fn }): ReturnType<RunFn> => ({
+      status: "measured",
+      stats: await measure(fn),
+    }),
+    options
+  );
+}
+
+exposeForTest("runBenchmarks", runBenchmarks);
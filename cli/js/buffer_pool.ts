@@ -0,0 +1,56 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+// A pool of the scratch buffers that I/O helpers like `Deno.copy()` read
+// into, so that each call doesn't allocate a new one. Only buffers of the
+// sizes in CHUNK_SIZES are kept; any other size is allocated as usual.
+
+const CHUNK_SIZES = [4 * 1024, 16 * 1024, 32 * 1024, 64 * 1024];
+// Buffers kept per size; more than that are left to the garbage collector.
+const MAX_FREE_BUFFERS = 8;
+
+const freeBuffers = new Map<number, Uint8Array[]>(
+  CHUNK_SIZES.map((size): [number, Uint8Array[]] => [size, []])
+);
+
+export interface BufferPoolStats {
+  bufferPoolHits: number;
+  bufferPoolMisses: number;
+  bufferPoolFree: number;
+}
+
+const stats: BufferPoolStats = {
+  bufferPoolHits: 0,
+  bufferPoolMisses: 0,
+  bufferPoolFree: 0,
+};
+
+/** Returns a buffer of `size` bytes that is only used by the caller until it
+ * is given back with `release()`. Its contents are unspecified. */
+export function checkout(size: number): Uint8Array {
+  const free = freeBuffers.get(size);
+  if (free === undefined) {
+    return new Uint8Array(size);
+  }
+  const buf = free.pop();
+  if (buf === undefined) {
+    stats.bufferPoolMisses++;
+    return new Uint8Array(size);
+  }
+  stats.bufferPoolHits++;
+  stats.bufferPoolFree--;
+  return buf;
+}
+
+/** Gives a buffer obtained with `checkout()` back to the pool. The caller must
+ * not use it, nor any view of it, afterwards. */
+export function release(buf: Uint8Array): void {
+  const free = freeBuffers.get(buf.byteLength);
+  if (free !== undefined && free.length < MAX_FREE_BUFFERS) {
+    free.push(buf);
+    stats.bufferPoolFree++;
+  }
+}
+
+export function bufferPoolStats(): BufferPoolStats {
+  return { ...stats };
+}
@@ -335,6 +335,15 @@ class Host implements ts.CompilerHost {
         delete options[key];
       }
     }
+    // Preserving JSX would emit `.jsx` files that V8 can't execute, so JSX is
+    // always transformed into factory calls (see `jsxFactory`).
+    if (
+      options.jsx === ts.JsxEmit.Preserve ||
+      options.jsx === ts.JsxEmit.ReactNative
+    ) {
+      ignoredOptions.push("jsx");
+      delete options.jsx;
+    }
     Object.assign(this.#options, options);
     return {
       ignoredOptions: ignoredOptions.length ? ignoredOptions : undefined,
@@ -1055,6 +1064,7 @@ interface CompilerRequestCompile {
   config?: string;
   unstable: boolean;
   bundle: boolean;
+  noCheck?: boolean;
   cwd: string;
   // key value is fully resolved URL
   sourceFileMap: Record<string, SourceFileMapEntry>;
@@ -1105,6 +1115,7 @@ function compile(request: CompilerRequestCompile): CompileResult {
     rootNames,
     target,
     unstable,
+    noCheck,
     cwd,
     sourceFileMap,
   } = request;
@@ -1147,7 +1158,14 @@ function compile(request: CompilerRequestCompile): CompileResult {
   buildSourceFileCache(sourceFileMap);
   // if there was a configuration and no diagnostics with it, we will continue
   // to generate the program and possibly emit it.
-  if (diagnostics.length === 0) {
+  if (diagnostics.length === 0 && noCheck && !bundle) {
+    assert(state.emitMap);
+    diagnostics = transpileSourceFileMap(
+      sourceFileMap,
+      host.getCompilationSettings(),
+      state.emitMap
+    );
+  } else if (diagnostics.length === 0) {
     const options = host.getCompilationSettings();
     const program = ts.createProgram({
       rootNames,
@@ -1196,6 +1214,43 @@ function compile(request: CompilerRequestCompile): CompileResult {
   return result;
 }
 
+/** Emits every TypeScript and JSX module of the graph on its own, without
+ * creating a program, so that only syntax errors are reported. Used by
+ * `--no-check`. */
+function transpileSourceFileMap(
+  sourceFileMap: Record<string, SourceFileMapEntry>,
+  options: ts.CompilerOptions,
+  emitMap: Record<string, EmittedSource>
+): ts.Diagnostic[] {
+  const compilerOptions = Object.assign({}, options, { outDir: undefined });
+  const diagnostics: ts.Diagnostic[] = [];
+  for (const { url, sourceCode, mediaType } of Object.values(sourceFileMap)) {
+    const isTsOrJsx =
+      mediaType === MediaType.TypeScript ||
+      mediaType === MediaType.TSX ||
+      mediaType === MediaType.JSX;
+    if (!isTsOrJsx || url.endsWith(".d.ts")) {
+      continue;
+    }
+    const result = ts.transpileModule(sourceCode, {
+      fileName: url,
+      compilerOptions,
+      reportDiagnostics: true,
+    });
+    if (result.diagnostics) {
+      diagnostics.push(...result.diagnostics);
+    }
+    emitMap[`${url}.js`] = { filename: url, contents: result.outputText };
+    if (result.sourceMapText) {
+      emitMap[`${url}.js.map`] = {
+        filename: url,
+        contents: result.sourceMapText,
+      };
+    }
+  }
+  return diagnostics.filter(({ code }) => !ignoredDiagnostics.includes(code));
+}
+
 function runtimeCompile(
   request: CompilerRequestRuntimeCompile
 ): RuntimeCompileResult | RuntimeBundleResult {
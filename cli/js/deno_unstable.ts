@@ -3,6 +3,10 @@
 // This module exports unstable Deno APIs.
 
 export { umask } from "./ops/fs/umask.ts";
+export { bench, BenchDefinition, BenchStats } from "./bench.ts";
+export { advanceTime } from "./fake_time.ts";
+export { interceptOp, OpInterceptor } from "./op_interceptors.ts";
+export { writeHeapSnapshot } from "./ops/heap_snapshot.ts";
 export { linkSync, link } from "./ops/fs/link.ts";
 export { symlinkSync, symlink } from "./ops/fs/symlink.ts";
 export { dir, loadavg, osRelease, hostname } from "./ops/os.ts";
@@ -10,12 +14,31 @@ export { openPlugin } from "./ops/plugins.ts";
 export { transpileOnly, compile, bundle } from "./compiler_api.ts";
 export { applySourceMap, formatDiagnostics } from "./ops/errors.ts";
 export { signal, signals, Signal, SignalStream } from "./signals.ts";
-export { setRaw } from "./ops/tty.ts";
+export { setRaw, consoleSize } from "./ops/tty.ts";
 export { utimeSync, utime } from "./ops/fs/utime.ts";
-export { ShutdownMode, shutdown } from "./net.ts";
+export {
+  ShutdownMode,
+  shutdown,
+  sendFile,
+  setIdleTimeouts,
+  IdleTimeouts,
+} from "./net.ts";
 export { listen, listenDatagram, connect } from "./net_unstable.ts";
 export { startTls } from "./tls.ts";
 export { kill } from "./ops/process.ts";
+export {
+  readv,
+  readvSync,
+  writev,
+  writevSync,
+  readTimeout,
+} from "./ops/io.ts";
+export {
+  dup,
+  sendResources,
+  receiveResources,
+  ReceivedResource,
+} from "./ops/resources.ts";
 export {
   permissions,
   PermissionName,
@@ -73,6 +73,128 @@ export function getErrorClass(kind: ErrorKind): { new (msg: string): Error } {
   }
 }
 
+export interface OpErrorInfo {
+  kind: ErrorKind;
+  message: string;
+  errno?: number | null;
+  cause?: string | null;
+  className?: string | null;
+}
+
+const customErrorClasses = new Map<string, { new (msg: string): Error }>();
+
+/** Registers the class of the errors that ops throw with the class name
+ * `className`, for errors that don't have an `ErrorKind` of their own. */
+export function registerErrorClass(
+  className: string,
+  errorClass: { new (msg: string): Error }
+): void {
+  if (customErrorClasses.has(className)) {
+    throw new TypeError(`Error class "${className}" is already registered`);
+  }
+  customErrorClasses.set(className, errorClass);
+}
+
+function createCustomError(className: string, message: string): Error {
+  const errorClass = customErrorClasses.get(className);
+  if (errorClass) {
+    return new errorClass(message);
+  }
+  const error = new Error(message);
+  error.name = className;
+  return error;
+}
+
+let opStackTraces = false;
+
+// @internal
+export function setOpStackTraces(enabled: boolean): void {
+  opStackTraces = enabled;
+}
+
+/** With `--op-stack-traces`, captures the stack of the code that called
+ * `dispatcher` to start an async op, so that it can be added to the error
+ * thrown if the op fails. */
+export function captureDispatchStack(
+  dispatcher: (...args: never[]) => unknown
+): Error | undefined {
+  if (!opStackTraces) {
+    return undefined;
+  }
+  const dispatchError = new Error();
+  Error.captureStackTrace(dispatchError, dispatcher);
+  return dispatchError;
+}
+
+type ErrorWithFrames = Error & {
+  __callSiteEvals: unknown[];
+  __formattedFrames: string[];
+};
+
+function appendDispatchStack(error: Error, dispatchError: Error): void {
+  // Reading `stack` runs prepareStackTrace(), which fills in the frames.
+  const stack = error.stack;
+  const dispatchStack = dispatchError.stack ?? "";
+  const frames = error as ErrorWithFrames;
+  const dispatchFrames = dispatchError as ErrorWithFrames;
+  const firstFrame = dispatchStack.indexOf("\n");
+  if (!frames.__formattedFrames || firstFrame < 0) {
+    return;
+  }
+  Object.defineProperties(error, {
+    __callSiteEvals: {
+      value: Object.freeze([
+        ...frames.__callSiteEvals,
+        ...dispatchFrames.__callSiteEvals,
+      ]),
+      configurable: true,
+    },
+    __formattedFrames: {
+      value: Object.freeze([
+        ...frames.__formattedFrames,
+        ...dispatchFrames.__formattedFrames,
+      ]),
+      configurable: true,
+    },
+    stack: {
+      value: stack + dispatchStack.slice(firstFrame),
+      configurable: true,
+      writable: true,
+    },
+  });
+}
+
+/** Creates the error thrown for an op that failed, with the details that
+ * came with it from Rust. `dispatchError` is the stack captured when the op
+ * was started, if any. */
+export function createOpError(
+  info: OpErrorInfo,
+  dispatchError?: Error
+): Error {
+  const error =
+    info.className != null
+      ? createCustomError(info.className, info.message)
+      : new (getErrorClass(info.kind))(info.message);
+  // Keep the stack starting at the op dispatch that failed.
+  Error.captureStackTrace(error, createOpError);
+  const details = error as Error & {
+    kind: string;
+    errno?: number;
+    cause?: string;
+  };
+  details.kind = info.className ?? ErrorKind[info.kind];
+  if (info.errno != null) {
+    details.errno = info.errno;
+  }
+  if (info.cause != null) {
+    details.cause = info.cause;
+  }
+  if (dispatchError) {
+    appendDispatchStack(error, dispatchError);
+  }
+  return error;
+}
+
 class NotFound extends Error {
   constructor(msg: string) {
     super(msg);
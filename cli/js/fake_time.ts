@@ -0,0 +1,63 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+// With `--frozen-time`, Rust runs the timers and `performance.now()` on a
+// virtual clock; `Date` is patched here to follow it too.
+import { now as opNow, stepFakeTime } from "./ops/timers.ts";
+import { queueDue, setTimeout } from "./web/timers.ts";
+
+// Milliseconds since the start time on the virtual clock, which are always
+// whole.
+function virtualNow(): number {
+  const res = opNow();
+  return res.seconds * 1e3 + res.subsecNanos / 1e6;
+}
+
+/** Makes `Date` start at `epochMs` and follow the virtual clock. */
+export function freezeTime(epochMs: number): void {
+  const RealDate = Date;
+  const now = (): number => epochMs + virtualNow();
+
+  function FakeDate(this: unknown, ...args: unknown[]): Date | string {
+    if (new.target === undefined) {
+      // Called as a function, `Date()` returns the current time as a string.
+      return new RealDate(now()).toString();
+    }
+    return Reflect.construct(
+      RealDate,
+      args.length === 0 ? [now()] : args,
+      new.target
+    );
+  }
+  // Dates keep the real prototype, so `instanceof` works both ways.
+  FakeDate.prototype = RealDate.prototype;
+  FakeDate.now = now;
+  FakeDate.parse = RealDate.parse;
+  FakeDate.UTC = RealDate.UTC;
+
+  Object.defineProperty(globalThis, "Date", {
+    value: FakeDate,
+    writable: true,
+    enumerable: false,
+    configurable: true,
+  });
+}
+
+// Resolves once the timers that are already due have fired. The zero delay
+// timer fires after them, as they were queued before it.
+function timersFired(): Promise<void> {
+  return new Promise((resolve): number => setTimeout(resolve, 0));
+}
+
+export async function advanceTime(ms: number): Promise<void> {
+  if (!(ms >= 0)) {
+    throw new TypeError("The time to advance by must be a positive number");
+  }
+  const target = virtualNow() + Math.floor(ms);
+  // The clock stops at each deadline on the way, and the timers due then fire
+  // before it moves on, so they all see the time they were due at.
+  let ids: number[] | null;
+  while ((ids = stepFakeTime(target)) !== null) {
+    queueDue(ids);
+    await timersFired();
+  }
+}
@@ -66,6 +66,7 @@ declare global {
   interface EvalErrorInfo {
     isNativeError: boolean;
     isCompileError: boolean;
+    isInterrupted?: boolean;
     // eslint-disable-next-line @typescript-eslint/no-explicit-any
     thrown: any;
   }
@@ -108,7 +109,8 @@ declare global {
 
     evalContext(
       code: string,
-      scriptName?: string
+      scriptName?: string,
+      interruptible?: boolean
     ): [unknown, EvalErrorInfo | null];
 
     formatError: (e: Error) => string;
@@ -127,6 +129,9 @@ declare global {
 
     decode(bytes: Uint8Array): string;
     encode(text: string): Uint8Array;
+
+    /** The functions registered with `CoreIsolate::register_fn()`. */
+    fns: Record<string, (...args: unknown[]) => unknown>;
   }
 
   // Only `var` variables show up in the `globalThis` type when doing a global
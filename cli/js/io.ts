@@ -3,6 +3,11 @@
 // Documentation liberally lifted from them too.
 // Thank you! We love Go!
 
+import { Buffer } from "./buffer.ts";
+import { checkout, release } from "./buffer_pool.ts";
+import { File, stdin, stdout, stderr } from "./files.ts";
+import { ConnImpl } from "./net.ts";
+
 const DEFAULT_BUFFER_SIZE = 32 * 1024;
 
 // Seek whence values.
@@ -49,6 +54,20 @@ export interface SeekerSync {
   seekSync(offset: number, whence: SeekMode): number;
 }
 
+// Whether `x` is one of the built-in readers or writers, which are done with
+// the chunks passed to them once the call resolves. Other ones may keep a
+// view of a pooled buffer after it has been handed to another caller.
+function isBuiltin(x: Reader | Writer): boolean {
+  return (
+    x instanceof Buffer ||
+    x instanceof File ||
+    x instanceof ConnImpl ||
+    x === stdin ||
+    x === stdout ||
+    x === stderr
+  );
+}
+
 export async function copy(
   src: Reader,
   dst: Writer,
@@ -58,18 +77,25 @@ export async function copy(
 ): Promise<number> {
   let n = 0;
   const bufSize = options?.bufSize ?? DEFAULT_BUFFER_SIZE;
-  const b = new Uint8Array(bufSize);
-  let gotEOF = false;
-  while (gotEOF === false) {
-    const result = await src.read(b);
-    if (result === null) {
-      gotEOF = true;
-    } else {
-      let nwritten = 0;
-      while (nwritten < result) {
-        nwritten += await dst.write(b.subarray(nwritten, result));
+  const pooled = isBuiltin(src) && isBuiltin(dst);
+  const b = pooled ? checkout(bufSize) : new Uint8Array(bufSize);
+  try {
+    let gotEOF = false;
+    while (gotEOF === false) {
+      const result = await src.read(b);
+      if (result === null) {
+        gotEOF = true;
+      } else {
+        let nwritten = 0;
+        while (nwritten < result) {
+          nwritten += await dst.write(b.subarray(nwritten, result));
+        }
+        n += nwritten;
       }
-      n += nwritten;
+    }
+  } finally {
+    if (pooled) {
+      release(b);
     }
   }
   return n;
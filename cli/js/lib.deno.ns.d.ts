@@ -26,10 +26,34 @@ declare namespace Deno {
     Busy: ErrorConstructor;
   };
 
+  /** Details that are set on the errors raised by Deno APIs, in addition to
+   * their message.
+   *
+   * ```ts
+   * try {
+   *   await Deno.connect({ port: 4000 });
+   * } catch (e) {
+   *   if ((e as Deno.ErrorDetails).kind === "ConnectionRefused") {
+   *     console.log("Nothing is listening on port 4000");
+   *   }
+   * }
+   * ```
+   */
+  export interface ErrorDetails {
+    /** Kind of the error, such as `"ConnectionRefused"`. Unlike `name`, it is
+     * also specific for the `TypeError`s and `Error`s raised by Deno APIs.
+     * Errors of a kind that is not built in use the name of their class. */
+    kind: string;
+    /** Error number reported by the operating system, if any. */
+    errno?: number;
+    /** Message of the error that caused this one, if any. */
+    cause?: string;
+  }
+
   /** The current process id of the runtime. */
   export const pid: number;
 
-  /** Reflects the `NO_COLOR` environment variable.
+  /** Reflects the `NO_COLOR` environment variable and the `--no-color` flag.
    *
    * See: https://no-color.org/ */
   export const noColor: boolean;
@@ -1666,6 +1690,12 @@ declare namespace Deno {
     bytesSentControl: number;
     bytesSentData: number;
     bytesReceived: number;
+    /** Number of times an I/O helper like `Deno.copy()` reused a buffer. */
+    bufferPoolHits: number;
+    /** Number of times an I/O helper had to allocate a buffer. */
+    bufferPoolMisses: number;
+    /** Number of buffers currently kept for reuse. */
+    bufferPoolFree: number;
   }
 
   /** Receive metrics from the privileged side of Deno. This is primarily used
@@ -1687,6 +1717,9 @@ declare namespace Deno {
    *      │    bytesSentControl     │   73   │
    *      │      bytesSentData      │   0    │
    *      │      bytesReceived      │  375   │
+   *      │     bufferPoolHits      │   0    │
+   *      │    bufferPoolMisses     │   0    │
+   *      │     bufferPoolFree      │   0    │
    *      └─────────────────────────┴────────┘
    */
   export function metrics(): Metrics;
@@ -1790,7 +1823,7 @@ declare namespace Deno {
       [key: string]: string;
     };
     stdout?: "inherit" | "piped" | "null" | number;
-    stderr?: "inherit" | "piped" | "null" | number;
+    stderr?: "inherit" | "piped" | "null" | "stdout" | number;
     stdin?: "inherit" | "piped" | "null" | number;
   }
 
@@ -1823,6 +1856,14 @@ declare namespace Deno {
    * `"null"` This stream will be ignored. This is the equivalent of attaching
    * the stream to `/dev/null`.
    *
+   * `opt.stderr` can also be set to `"stdout"`, for stderr to go wherever
+   * stdout goes. When stdout is piped, both are written to the same pipe and
+   * can be read from `Process.stdout`, in the order they were written.
+   *
+   * On Unix, sockets and the pipes of other subprocesses can be used for stdio
+   * too, as well as files. These are handed over to the subprocess and closed
+   * in this process.
+   *
    * Details of the spawned process are returned.
    *
    * Requires `allow-run` permission. */
@@ -882,12 +882,46 @@ interface RequestInit {
    * An AbortSignal to set request's signal.
    */
   signal?: AbortSignal | null;
+  /** **UNSTABLE**: new API, yet to be vetted. Requires `--unstable`.
+   *
+   * Number of milliseconds the whole fetch, redirects and reading the body
+   * included, may take before it fails with `Deno.errors.TimedOut`. */
+  timeout?: number;
+  /** **UNSTABLE**: new API, yet to be vetted. Requires `--unstable`.
+   *
+   * Called with the progress of the transfer: while the request body is
+   * sent, then while the response body is read, at most every 100
+   * milliseconds and once more at the end of each body.
+   *
+   * ```ts
+   * const res = await fetch("https://deno.land/x/big.tar.gz", {
+   *   onProgress({ direction, loaded, total }) {
+   *     console.log(direction, loaded, total ?? "?");
+   *   },
+   * });
+   * await res.arrayBuffer();
+   * ```
+   */
+  onProgress?: (progress: FetchProgress) => void;
   /**
    * Can only be null. Used to disassociate request from any Window.
    */
   window?: any;
 }
 
+/** **UNSTABLE**: new API, yet to be vetted.
+ *
+ * Progress of a fetch, passed to the `onProgress` callback of `RequestInit`.
+ */
+interface FetchProgress {
+  /** Whether the request body is being sent or the response body read. */
+  direction: "upload" | "download";
+  /** Number of bytes transferred so far. */
+  loaded: number;
+  /** Number of bytes to transfer, if the size of the body is known. */
+  total?: number;
+}
+
 /** This Fetch API interface represents a resource request. */
 interface Request extends Body {
   /**
@@ -1252,6 +1286,11 @@ declare class MessageEvent extends Event {
   readonly data: any;
   readonly origin: string;
   readonly lastEventId: string;
+  /** UNSTABLE: New API, yet to be vetted.
+   *
+   * The resources that were transferred with the message, with their rids
+   * here, in the order they were given to `postMessage()`. */
+  readonly resources: Array<{ rid: number; kind: string }>;
   constructor(type: string, eventInitDict?: MessageEventInit);
 }
 
@@ -1274,6 +1313,21 @@ declare class ErrorEvent extends Event {
 
 interface PostMessageOptions {
   transfer?: any[];
+  /** UNSTABLE: New API, yet to be vetted.
+   *
+   * The rids of resources to move along with the message, such as accepted
+   * connections, files and listeners. They're closed here and show up in the
+   * `resources` of the message event on the other side, so a listener in one
+   * thread can hand connections to workers. Requires `--unstable`.
+   *
+   * On Windows only files can be transferred.
+   *
+   * ```ts
+   * const conn = await listener.accept();
+   * worker.postMessage("conn", { resources: [conn.rid] });
+   * ```
+   */
+  resources?: number[];
 }
 
 declare class Worker extends EventTarget {
@@ -297,6 +297,8 @@ declare namespace Deno {
    *
    * Requires `allow-plugin` permission.
    *
+   * Throws if the plugin was built for an incompatible version of Deno.
+   *
    * The plugin system is not stable and will change in the future, hence the
    * lack of docs. For now take a look at the example
    * https://github.com/denoland/deno/tree/master/test_plugin
@@ -917,6 +919,119 @@ declare namespace Deno {
    */
   export function setRaw(rid: number, mode: boolean): void;
 
+  /** **UNSTABLE**: new API, yet to be vetted
+   *
+   * Gets the size of the terminal `rid` is, which can be stdin, stdout, stderr
+   * or a file opened on a terminal device. Throws if it isn't a terminal.
+   *
+   * ```ts
+   * const { columns, rows } = Deno.consoleSize(Deno.stdout.rid);
+   * ```
+   */
+  export function consoleSize(
+    rid: number
+  ): {
+    columns: number;
+    rows: number;
+  };
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Read from stdin (`rid`) into `buffer` like `Deno.read()`, but wait at most
+   * `timeout` milliseconds for data. Resolves to `0` if nothing arrived in
+   * time; with a `timeout` of `0` it only takes data that's already there.
+   * Data that arrives later isn't lost, the next read gets it. Resolves to
+   * `null` on EOF.
+   *
+   * Only stdin is supported.
+   *
+   * ```ts
+   * const buf = new Uint8Array(1024);
+   * const n = await Deno.readTimeout(Deno.stdin.rid, buf, 0);
+   * if (n === null) {
+   *   // EOF
+   * } else if (n > 0) {
+   *   handleInput(buf.subarray(0, n));
+   * }
+   * ```
+   */
+  export function readTimeout(
+    rid: number,
+    buffer: Uint8Array,
+    timeout: number
+  ): Promise<number | null>;
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Synchronously read from a resource ID (`rid`) into several buffers, filling
+   * them in order.
+   *
+   * Returns the total number of bytes read, or `null` on EOF. Like
+   * `Deno.readSync()`, it may return before all the buffers are full.
+   *
+   * ```ts
+   * const header = new Uint8Array(4);
+   * const body = new Uint8Array(100);
+   * const file = Deno.openSync("/foo/bar.txt");
+   * const bytesRead = Deno.readvSync(file.rid, [header, body]);
+   * ```
+   */
+  export function readvSync(rid: number, buffers: Uint8Array[]): number | null;
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Read from a resource ID (`rid`) into several buffers, filling them in
+   * order.
+   *
+   * Resolves to the total number of bytes read, or `null` on EOF. Like
+   * `Deno.read()`, it only waits until some data is available, so it may
+   * resolve before all the buffers are full.
+   *
+   * ```ts
+   * const header = new Uint8Array(4);
+   * const body = new Uint8Array(100);
+   * const file = await Deno.open("/foo/bar.txt");
+   * const bytesRead = await Deno.readv(file.rid, [header, body]);
+   * ```
+   */
+  export function readv(
+    rid: number,
+    buffers: Uint8Array[]
+  ): Promise<number | null>;
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Synchronously write the contents of several buffers to a resource ID
+   * (`rid`), in order.
+   *
+   * Returns the total number of bytes written. Like `Deno.writeSync()`, it may
+   * write less than all of the data.
+   *
+   * ```ts
+   * const encoder = new TextEncoder();
+   * const file = Deno.openSync("/foo/bar.txt", { write: true });
+   * Deno.writevSync(file.rid, [encoder.encode("Hello "), encoder.encode("world")]);
+   * ```
+   */
+  export function writevSync(rid: number, data: Uint8Array[]): number;
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Write the contents of several buffers to a resource ID (`rid`), in order,
+   * without concatenating them first. Small buffers are sent with a single
+   * write, so a message made of a header and a body doesn't take two.
+   *
+   * Resolves to the total number of bytes written. Like `Deno.write()`, it may
+   * write less than all of the data.
+   *
+   * ```ts
+   * const encoder = new TextEncoder();
+   * const conn = await Deno.connect({ port: 8080 });
+   * await Deno.writev(conn.rid, [encoder.encode("header"), body]);
+   * ```
+   */
+  export function writev(rid: number, data: Uint8Array[]): Promise<number>;
+
   /** **UNSTABLE**: needs investigation into high precision time.
    *
    * Synchronously changes the access (`atime`) and modification (`mtime`) times
@@ -977,6 +1092,105 @@ declare namespace Deno {
    */
   export function shutdown(rid: number, how: ShutdownMode): Promise<void>;
 
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Copy from the current position of the file `fileRid` to the connection
+   * `connRid`, until EOF or until `count` bytes were copied. The data is
+   * copied by the runtime and never goes through JavaScript. On Linux, files
+   * are sent to TCP and Unix connections with `sendfile(2)`; TLS connections
+   * and the other platforms copy the file through a buffer.
+   *
+   * Resolves to the number of bytes copied.
+   *
+   * ```ts
+   * const file = await Deno.open("./index.html");
+   * const { size } = await file.stat();
+   * await Deno.sendFile(file.rid, conn.rid, size);
+   * file.close();
+   * ```
+   */
+  export function sendFile(
+    fileRid: number,
+    connRid: number,
+    count?: number
+  ): Promise<number>;
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * How long reads and writes of a connection may wait, in milliseconds. */
+  export interface IdleTimeouts {
+    read?: number;
+    write?: number;
+  }
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Make reads and writes of the connection `rid` (TCP, TLS or Unix) that wait
+   * longer than `timeouts` reject with `Deno.errors.TimedOut`. A write that
+   * already wrote some bytes resolves with their number instead. A timeout
+   * that is left out or `0` is turned off. Reads and writes that are already
+   * pending aren't affected.
+   *
+   * ```ts
+   * const conn = await listener.accept();
+   * Deno.setIdleTimeouts(conn.rid, { read: 30000, write: 30000 });
+   * ```
+   */
+  export function setIdleTimeouts(rid: number, timeouts: IdleTimeouts): void;
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Duplicate the file descriptor or handle of the resource `rid` into a new
+   * resource, and return its rid. Both resources have to be closed. On
+   * Windows, only files can be duplicated.
+   *
+   * ```ts
+   * const file = Deno.openSync("./hello.txt");
+   * const rid = Deno.dup(file.rid);
+   * ```
+   */
+  export function dup(rid: number): number;
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * A resource received with `Deno.receiveResources()`. */
+  export interface ReceivedResource {
+    rid: number;
+    kind: "fsFile" | "tcpStream" | "tcpListener" | "unixStream";
+  }
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Send the resources `rids` over the Unix socket `rid` to another process,
+   * which gets them with `Deno.receiveResources()`. Files, TCP streams and
+   * listeners, and Unix streams can be sent, up to 16 at once. The resources
+   * stay open here and have to be closed as usual.
+   *
+   * Not supported on Windows.
+   *
+   * ```ts
+   * const listener = Deno.listen({ port: 8080 });
+   * await Deno.sendResources(conn.rid, [listener.rid]);
+   * listener.close();
+   * ```
+   */
+  export function sendResources(rid: number, rids: number[]): Promise<void>;
+
+  /** **UNSTABLE**: new API, yet to be vetted.
+   *
+   * Receive the resources sent by `Deno.sendResources()` over the Unix socket
+   * `rid`. Resolves to `null` when the socket was closed by the other side.
+   *
+   * Not supported on Windows.
+   *
+   * ```ts
+   * const resources = await Deno.receiveResources(conn.rid);
+   * ```
+   */
+  export function receiveResources(
+    rid: number
+  ): Promise<ReceivedResource[] | null>;
+
   /** **UNSTABLE**: new API, yet to be vetted.
    *
    * A generic transport listener for message-oriented protocols. */
@@ -1245,4 +1459,116 @@ declare namespace Deno {
    *  Requires `allow-env` permission.
    */
   export function hostname(): string;
+
+  /** **UNSTABLE**: New API, yet to be vetted.
+   *
+   * Statistics of a benchmark, measured by `Deno.bench()`. The durations are
+   * per iteration, in nanoseconds. */
+  export interface BenchStats {
+    /** Number of samples the statistics are computed on. */
+    samples: number;
+    /** Total number of times the benchmark function was run while being
+     * measured. */
+    iterations: number;
+    mean: number;
+    stddev: number;
+    min: number;
+    max: number;
+    p50: number;
+    p75: number;
+    p99: number;
+  }
+
+  /** **UNSTABLE**: New API, yet to be vetted. */
+  export interface BenchDefinition {
+    fn: () => void | Promise<void>;
+    name: string;
+    ignore?: boolean;
+  }
+
+  /** **UNSTABLE**: New API, yet to be vetted.
+   *
+   * Register a benchmark which will be run when `deno bench` is used on the
+   * command line and the containing module looks like a bench module. The
+   * function is run repeatedly: first to warm it up, then in samples of as
+   * many iterations as fit in about 10ms, for about a second. The mean,
+   * standard deviation and percentiles of the duration of one iteration are
+   * then reported. `fn` can be async.
+   *
+   * ```ts
+   * Deno.bench("JSON.parse", () => {
+   *   JSON.parse('{"hello":"world"}');
+   * });
+   * ```
+   *
+   * Requires `allow-hrtime` permission. */
+  export function bench(b: BenchDefinition): void;
+  export function bench(name: string, fn: () => void | Promise<void>): void;
+
+  /** **UNSTABLE**: New API, yet to be vetted.
+   *
+   * Moves the virtual clock of `--frozen-time` forward by `ms` milliseconds.
+   * The clock stops at each timer deadline on the way, and the promise
+   * resolves once every timer due by then has fired, with `Date.now()` and
+   * `performance.now()` reporting the time it was due at.
+   *
+   * ```ts
+   * let fired = false;
+   * setTimeout(() => (fired = true), 60000);
+   * await Deno.advanceTime(60000);
+   * console.log(fired); // true
+   * ```
+   *
+   * Throws without `--frozen-time`. */
+  export function advanceTime(ms: number): Promise<void>;
+
+  /** **UNSTABLE**: New API, yet to be vetted.
+   *
+   * Called instead of the handler of an intercepted op. `args` is what the op
+   * was sent: an object for the JSON ops, or the resource id for the minimal
+   * ones (like `op_read` and `op_write`), which must return a number. The
+   * returned value, or the value the returned promise resolves to for async
+   * ops, is the result of the op; an error thrown is its error. */
+  // eslint-disable-next-line @typescript-eslint/no-explicit-any
+  export type OpInterceptor = (args: any, ...zeroCopy: Uint8Array[]) => unknown;
+
+  /** **UNSTABLE**: New API, yet to be vetted.
+   *
+   * Makes `interceptor` handle the op `opName` instead of Rust, which allows
+   * tests to stub the file system or the network. It replaces the previous
+   * interceptor of the op, if any. Returns a function which stops the
+   * interception.
+   *
+   * ```ts
+   * const restore = Deno.interceptOp("op_open", () => {
+   *   throw new Deno.errors.NotFound("stubbed");
+   * });
+   * try {
+   *   await Deno.open("config.json"); // throws NotFound
+   * } finally {
+   *   restore();
+   * }
+   * ```
+   *
+   * The op names are internal, and may change between versions. */
+  export function interceptOp(
+    opName: string,
+    interceptor: OpInterceptor
+  ): () => void;
+
+  /** **UNSTABLE**: New API, yet to be vetted.
+   *
+   * Writes a snapshot of the V8 heap to `path`, in the `.heapsnapshot`
+   * format the memory tab of Chrome DevTools loads. Comparing the snapshots
+   * taken over time shows which objects are leaking.
+   *
+   * ```ts
+   * await Deno.writeHeapSnapshot("server.heapsnapshot");
+   * ```
+   *
+   * The snapshot is taken once the current task is done, and the whole
+   * program is paused while it's written.
+   *
+   * Requires `allow-write` permission. */
+  export function writeHeapSnapshot(path: string): Promise<void>;
 }
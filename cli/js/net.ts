@@ -5,7 +5,15 @@ import { read, write } from "./ops/io.ts";
 import { close } from "./ops/resources.ts";
 import * as netOps from "./ops/net.ts";
 import { Addr } from "./ops/net.ts";
-export { ShutdownMode, shutdown, NetAddr, UnixAddr } from "./ops/net.ts";
+export {
+  ShutdownMode,
+  shutdown,
+  sendFile,
+  setIdleTimeouts,
+  IdleTimeouts,
+  NetAddr,
+  UnixAddr,
+} from "./ops/net.ts";
 
 export interface DatagramConn extends AsyncIterable<[Uint8Array, Addr]> {
   receive(p?: Uint8Array): Promise<[Uint8Array, Addr]>;
@@ -0,0 +1,50 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+// Ops can be intercepted from JS so that tests can stub them. Both dispatchers
+// look an op up here before sending it to Rust; an intercepted op never
+// reaches its real handler.
+
+import { OPS_CACHE } from "./runtime.ts";
+
+// eslint-disable-next-line @typescript-eslint/no-explicit-any
+export type OpInterceptor = (args: any, ...zeroCopy: Uint8Array[]) => unknown;
+
+// Using an object without a prototype because `Map` was causing GC problems.
+const interceptors: { [opId: number]: OpInterceptor } = Object.create(null);
+
+export function interceptOp(
+  opName: string,
+  interceptor: OpInterceptor
+): () => void {
+  const opId = OPS_CACHE[opName];
+  if (opId == null) {
+    throw new TypeError(`Unknown op: ${opName}`);
+  }
+  if (typeof interceptor !== "function") {
+    throw new TypeError("The interceptor must be a function");
+  }
+  interceptors[opId] = interceptor;
+  return (): void => {
+    // Another interceptor may have replaced this one since.
+    if (interceptors[opId] === interceptor) {
+      delete interceptors[opId];
+    }
+  };
+}
+
+export function getInterceptor(opId: number): OpInterceptor | undefined {
+  return interceptors[opId];
+}
+
+// Runs the interceptor of a sync op, which has to return its result directly.
+export function interceptSync(
+  interceptor: OpInterceptor,
+  args: unknown,
+  zeroCopy: Uint8Array[]
+): unknown {
+  const res = interceptor(args, ...zeroCopy);
+  if (res instanceof Promise) {
+    throw new TypeError("The interceptor of a sync op can't return a promise");
+  }
+  return res;
+}
@@ -0,0 +1,33 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { sendSync } from "./dispatch_json.ts";
+
+/** Per iteration durations are in nanoseconds. */
+export interface BenchStats {
+  samples: number;
+  iterations: number;
+  mean: number;
+  stddev: number;
+  min: number;
+  max: number;
+  p50: number;
+  p75: number;
+  p99: number;
+}
+
+interface BenchStep {
+  iterations?: number;
+  stats?: BenchStats;
+}
+
+/** Starts measuring a benchmark. The benchmark function has to be run
+ * `iterations` times before calling `benchStep()`. */
+export function benchBegin(): { rid: number; iterations: number } {
+  return sendSync("op_bench_begin");
+}
+
+/** Ends the current sample of the benchmark. Returns the number of
+ * iterations of the next sample, or the statistics of the benchmark once it
+ * is done. */
+export function benchStep(rid: number): BenchStep {
+  return sendSync("op_bench_step", { rid });
+}
@@ -2,19 +2,22 @@
 import * as util from "../util.ts";
 import { core } from "../core.ts";
 import { OPS_CACHE } from "../runtime.ts";
-import { ErrorKind, getErrorClass } from "../errors.ts";
+import {
+  OpErrorInfo,
+  captureDispatchStack,
+  createOpError,
+} from "../errors.ts";
+import { exposeForTest } from "../internals.ts";
+import { recordResourceOrigin } from "../resource_origin.ts";
+import { opCompleted, opStarted } from "../pending_ops.ts";
+import { getInterceptor, interceptSync } from "../op_interceptors.ts";
 
 // eslint-disable-next-line @typescript-eslint/no-explicit-any
 type Ok = any;
 
-interface JsonError {
-  kind: ErrorKind;
-  message: string;
-}
-
 interface JsonResponse {
   ok?: Ok;
-  err?: JsonError;
+  err?: OpErrorInfo;
   promiseId?: number; // Only present in async messages.
 }
 
@@ -22,6 +25,8 @@ interface JsonResponse {
 const promiseTable: {
   [key: number]: util.Resolvable<JsonResponse>;
 } = Object.create(null);
+// The id of the op each pending promise is for.
+const promiseOpIds: { [key: number]: number } = Object.create(null);
 let _nextPromiseId = 1;
 
 function nextPromiseId(): number {
@@ -38,9 +43,9 @@ function encode(args: object): Uint8Array {
   return core.encode(s);
 }
 
-function unwrapResponse(res: JsonResponse): Ok {
+function unwrapResponse(res: JsonResponse, dispatchError?: Error): Ok {
   if (res.err != null) {
-    throw new (getErrorClass(res.err.kind))(res.err.message);
+    throw createOpError(res.err, dispatchError);
   }
   util.assert(res.ok != null);
   return res.ok;
@@ -53,6 +58,8 @@ export function asyncMsgFromRust(resUi8: Uint8Array): void {
   const promise = promiseTable[res.promiseId!];
   util.assert(promise != null);
   delete promiseTable[res.promiseId!];
+  opCompleted(promiseOpIds[res.promiseId!]);
+  delete promiseOpIds[res.promiseId!];
   promise.resolve(res);
 }
 
@@ -63,6 +70,11 @@ export function sendSync(
 ): Ok {
   const opId = OPS_CACHE[opName];
   util.log("sendSync", opName, opId);
+  const interceptor = getInterceptor(opId);
+  if (interceptor) {
+    return interceptSync(interceptor, args, zeroCopy);
+  }
+  recordResourceOrigin();
   const argsUi8 = encode(args);
   const resUi8 = core.dispatch(opId, argsUi8, ...zeroCopy);
   util.assert(resUi8 != null);
@@ -72,18 +84,20 @@ export function sendSync(
   return unwrapResponse(res);
 }
 
-export async function sendAsync(
+function dispatchAsync(
   opName: string,
-  args: object = {},
-  ...zeroCopy: Uint8Array[]
-): Promise<Ok> {
+  args: object,
+  zeroCopy: Uint8Array[],
+  signal?: AbortSignal
+): Promise<JsonResponse> {
   const opId = OPS_CACHE[opName];
   util.log("sendAsync", opName, opId);
   const promiseId = nextPromiseId();
   args = Object.assign(args, { promiseId });
-  const promise = util.createResolvable<Ok>();
+  const promise = util.createResolvable<JsonResponse>();
 
   const argsUi8 = encode(args);
+  recordResourceOrigin();
   const buf = core.dispatch(opId, argsUi8, ...zeroCopy);
   if (buf) {
     // Sync result.
@@ -92,8 +106,103 @@ export async function sendAsync(
   } else {
     // Async result.
     promiseTable[promiseId] = promise;
+    promiseOpIds[promiseId] = opId;
+    opStarted(opId);
+    if (signal) {
+      const onAbort = (): void => {
+        sendSync("op_cancel", { id: promiseId });
+      };
+      if (signal.aborted) {
+        onAbort();
+      } else {
+        signal.addEventListener("abort", onAbort);
+        promise.then((): void => signal.removeEventListener("abort", onAbort));
+      }
+    }
   }
 
-  const res = await promise;
-  return unwrapResponse(res);
+  return promise;
+}
+
+export async function sendAsync(
+  opName: string,
+  args: object = {},
+  ...zeroCopy: Uint8Array[]
+): Promise<Ok> {
+  const interceptor = getInterceptor(OPS_CACHE[opName]);
+  if (interceptor) {
+    return await interceptor(args, ...zeroCopy);
+  }
+  const dispatchError = captureDispatchStack(sendAsync);
+  const res = await dispatchAsync(opName, args, zeroCopy);
+  return unwrapResponse(res, dispatchError);
+}
+
+/** Like `sendAsync`, but the op is canceled when `signal` is aborted; it then
+ * fails with `Deno.errors.Interrupted`. */
+export async function sendAsyncCancelable(
+  signal: AbortSignal,
+  opName: string,
+  args: object = {},
+  ...zeroCopy: Uint8Array[]
+): Promise<Ok> {
+  const interceptor = getInterceptor(OPS_CACHE[opName]);
+  if (interceptor) {
+    return await interceptor(args, ...zeroCopy);
+  }
+  const dispatchError = captureDispatchStack(sendAsyncCancelable);
+  const res = await dispatchAsync(opName, args, zeroCopy, signal);
+  return unwrapResponse(res, dispatchError);
+}
+
+/** An op to run as part of a batch: its name and its arguments. */
+export type BatchOp = [string, object?];
+
+/** Outcome of one op of a batch; `err` is set if the op failed. */
+export interface BatchResult {
+  ok?: Ok;
+  err?: Error;
+}
+
+function encodeBatch(ops: BatchOp[]): object {
+  return {
+    ops: ops.map(([opName, args = {}]): [number, object] => {
+      const opId = OPS_CACHE[opName];
+      if (opId == null) {
+        throw new TypeError(`Unknown op: ${opName}`);
+      }
+      return [opId, args];
+    }),
+  };
 }
+
+function toBatchResult(
+  res: JsonResponse,
+  dispatchError?: Error
+): BatchResult {
+  if (res.err != null) {
+    return { err: createOpError(res.err, dispatchError) };
+  }
+  return { ok: res.ok };
+}
+
+/** Runs several sync JSON ops with a single dispatch. Unlike `sendSync`, a
+ * failing op doesn't throw; its error is returned in its place. */
+export function sendSyncBatch(ops: BatchOp[]): BatchResult[] {
+  const results: JsonResponse[] = sendSync("op_batch", encodeBatch(ops));
+  return results.map((res): BatchResult => toBatchResult(res));
+}
+
+/** Runs several async JSON ops with a single dispatch and resolves once all of
+ * them completed. */
+export async function sendAsyncBatch(ops: BatchOp[]): Promise<BatchResult[]> {
+  const dispatchError = captureDispatchStack(sendAsyncBatch);
+  const results: JsonResponse[] = await sendAsync(
+    "op_batch",
+    encodeBatch(ops)
+  );
+  return results.map((res): BatchResult => toBatchResult(res, dispatchError));
+}
+
+exposeForTest("sendSyncBatch", sendSyncBatch);
+exposeForTest("sendAsyncBatch", sendAsyncBatch);
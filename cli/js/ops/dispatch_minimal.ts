@@ -2,12 +2,22 @@
 import * as util from "../util.ts";
 import { core } from "../core.ts";
 import { TextDecoder } from "../web/text_encoding.ts";
-import { ErrorKind, errors, getErrorClass } from "../errors.ts";
+import {
+  ErrorKind,
+  OpErrorInfo,
+  captureDispatchStack,
+  createOpError,
+  errors,
+} from "../errors.ts";
+import { opCompleted, opStarted } from "../pending_ops.ts";
+import { getInterceptor, interceptSync } from "../op_interceptors.ts";
 
 // Using an object without a prototype because `Map` was causing GC problems.
 const promiseTableMin: {
   [key: number]: util.Resolvable<RecordMinimal>;
 } = Object.create(null);
+// The id of the op each pending promise is for.
+const promiseOpIdsMin: { [key: number]: number } = Object.create(null);
 
 // Note it's important that promiseId starts at 1 instead of 0, because sync
 // messages are indicated with promiseId 0. If we ever add wrap around logic for
@@ -24,10 +34,7 @@ export interface RecordMinimal {
   promiseId: number;
   arg: number;
   result: number;
-  err?: {
-    kind: ErrorKind;
-    message: string;
-  };
+  err?: OpErrorInfo;
 }
 
 export function recordFromBufMinimal(ui8: Uint8Array): RecordMinimal {
@@ -43,9 +50,11 @@ export function recordFromBufMinimal(ui8: Uint8Array): RecordMinimal {
   let err;
 
   if (arg < 0) {
+    // Error records have the errno, or 0, in a fourth header word.
     const kind = result as ErrorKind;
-    const message = decoder.decode(ui8.subarray(12));
-    err = { kind, message };
+    const errno = new Int32Array(ui8.buffer, ui8.byteOffset + 12, 1)[0];
+    const message = decoder.decode(ui8.subarray(16));
+    err = { kind, message, errno: errno === 0 ? null : errno };
   } else if (ui8.length != 12) {
     throw new errors.InvalidData("BadMessage");
   }
@@ -58,9 +67,9 @@ export function recordFromBufMinimal(ui8: Uint8Array): RecordMinimal {
   };
 }
 
-function unwrapResponse(res: RecordMinimal): number {
+function unwrapResponse(res: RecordMinimal, dispatchError?: Error): number {
   if (res.err != null) {
-    throw new (getErrorClass(res.err.kind))(res.err.message);
+    throw createOpError(res.err, dispatchError);
   }
   return res.result;
 }
@@ -79,20 +88,27 @@ export function asyncMsgFromRust(ui8: Uint8Array): void {
   const promise = promiseTableMin[promiseId];
   delete promiseTableMin[promiseId];
   util.assert(promise);
+  opCompleted(promiseOpIdsMin[promiseId]);
+  delete promiseOpIdsMin[promiseId];
   promise.resolve(record);
 }
 
 export async function sendAsyncMinimal(
   opId: number,
   arg: number,
-  zeroCopy: Uint8Array
+  ...zeroCopy: Uint8Array[]
 ): Promise<number> {
+  const interceptor = getInterceptor(opId);
+  if (interceptor) {
+    return (await interceptor(arg, ...zeroCopy)) as number;
+  }
+  const dispatchError = captureDispatchStack(sendAsyncMinimal);
   const promiseId = nextPromiseId(); // AKA cmdId
   scratch32[0] = promiseId;
   scratch32[1] = arg;
   scratch32[2] = 0; // result
   const promise = util.createResolvable<RecordMinimal>();
-  const buf = core.dispatch(opId, scratchBytes, zeroCopy);
+  const buf = core.dispatch(opId, scratchBytes, ...zeroCopy);
   if (buf) {
     const record = recordFromBufMinimal(buf);
     // Sync result.
@@ -100,20 +116,26 @@ export async function sendAsyncMinimal(
   } else {
     // Async result.
     promiseTableMin[promiseId] = promise;
+    promiseOpIdsMin[promiseId] = opId;
+    opStarted(opId);
   }
 
   const res = await promise;
-  return unwrapResponse(res);
+  return unwrapResponse(res, dispatchError);
 }
 
 export function sendSyncMinimal(
   opId: number,
   arg: number,
-  zeroCopy: Uint8Array
+  ...zeroCopy: Uint8Array[]
 ): number {
+  const interceptor = getInterceptor(opId);
+  if (interceptor) {
+    return interceptSync(interceptor, arg, zeroCopy) as number;
+  }
   scratch32[0] = 0; // promiseId 0 indicates sync
   scratch32[1] = arg;
-  const res = core.dispatch(opId, scratchBytes, zeroCopy)!;
+  const res = core.dispatch(opId, scratchBytes, ...zeroCopy)!;
   const resRecord = recordFromBufMinimal(res);
   return unwrapResponse(resRecord);
 }
@@ -1,11 +1,14 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
-import { sendAsync } from "./dispatch_json.ts";
+import { sendAsync, sendAsyncCancelable, sendSync } from "./dispatch_json.ts";
 
 interface FetchRequest {
   url: string;
   method: string | null;
   headers: Array<[string, string]>;
+  timeout?: number;
+  uploadProgressRid?: number;
+  progress: boolean;
 }
 
 export interface FetchResponse {
@@ -17,12 +20,30 @@ export interface FetchResponse {
 
 export function fetch(
   args: FetchRequest,
-  body: ArrayBufferView | undefined
+  body: ArrayBufferView | undefined,
+  signal?: AbortSignal
 ): Promise<FetchResponse> {
   let zeroCopy = undefined;
   if (body) {
     zeroCopy = new Uint8Array(body.buffer, body.byteOffset, body.byteLength);
   }
 
-  return sendAsync("op_fetch", args, ...(zeroCopy ? [zeroCopy] : []));
+  const zeroCopies = zeroCopy ? [zeroCopy] : [];
+  if (signal) {
+    return sendAsyncCancelable(signal, "op_fetch", args, ...zeroCopies);
+  }
+  return sendAsync("op_fetch", args, ...zeroCopies);
+}
+
+export interface UploadProgress {
+  loaded?: number;
+  done: boolean;
+}
+
+export function openUploadProgress(): number {
+  return sendSync("op_fetch_upload_progress_open");
+}
+
+export function pollUploadProgress(rid: number): Promise<UploadProgress> {
+  return sendAsync("op_fetch_upload_progress_poll", { rid });
 }
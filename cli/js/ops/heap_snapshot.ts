@@ -0,0 +1,6 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { sendAsync } from "./dispatch_json.ts";
+
+export async function writeHeapSnapshot(path: string): Promise<void> {
+  await sendAsync("op_heap_snapshot", { path });
+}
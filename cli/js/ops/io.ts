@@ -1,6 +1,7 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 import { sendAsyncMinimal, sendSyncMinimal } from "./dispatch_minimal.ts";
+import { sendAsync } from "./dispatch_json.ts";
 // TODO(bartlomieju): remove this import and maybe lazy-initialize
 // OPS_CACHE that belongs only to this module
 import { OPS_CACHE } from "../runtime.ts";
@@ -8,6 +9,8 @@ import { OPS_CACHE } from "../runtime.ts";
 // This is done because read/write are extremely performance sensitive.
 let OP_READ = -1;
 let OP_WRITE = -1;
+let OP_READV = -1;
+let OP_WRITEV = -1;
 
 export function readSync(rid: number, buffer: Uint8Array): number | null {
   if (buffer.length == 0) {
@@ -46,6 +49,24 @@ export async function read(
   }
 }
 
+export async function readTimeout(
+  rid: number,
+  buffer: Uint8Array,
+  timeout: number
+): Promise<number | null> {
+  if (buffer.length == 0) {
+    return 0;
+  }
+  const nread = await sendAsync("op_read_timeout", { rid, timeout }, buffer);
+  if (nread === null) {
+    return 0;
+  } else if (nread == 0) {
+    return null;
+  } else {
+    return nread;
+  }
+}
+
 export function writeSync(rid: number, data: Uint8Array): number {
   if (OP_WRITE < 0) {
     OP_WRITE = OPS_CACHE["op_write"];
@@ -69,3 +90,64 @@ export async function write(rid: number, data: Uint8Array): Promise<number> {
     return result;
   }
 }
+
+export function readvSync(rid: number, buffers: Uint8Array[]): number | null {
+  if (buffers.every((buffer) => buffer.length == 0)) {
+    return 0;
+  }
+  if (OP_READV < 0) {
+    OP_READV = OPS_CACHE["op_readv"];
+  }
+  const nread = sendSyncMinimal(OP_READV, rid, ...buffers);
+  if (nread < 0) {
+    throw new Error("read error");
+  } else if (nread == 0) {
+    return null;
+  } else {
+    return nread;
+  }
+}
+
+export async function readv(
+  rid: number,
+  buffers: Uint8Array[]
+): Promise<number | null> {
+  if (buffers.every((buffer) => buffer.length == 0)) {
+    return 0;
+  }
+  if (OP_READV < 0) {
+    OP_READV = OPS_CACHE["op_readv"];
+  }
+  const nread = await sendAsyncMinimal(OP_READV, rid, ...buffers);
+  if (nread < 0) {
+    throw new Error("read error");
+  } else if (nread == 0) {
+    return null;
+  } else {
+    return nread;
+  }
+}
+
+export function writevSync(rid: number, data: Uint8Array[]): number {
+  if (OP_WRITEV < 0) {
+    OP_WRITEV = OPS_CACHE["op_writev"];
+  }
+  const result = sendSyncMinimal(OP_WRITEV, rid, ...data);
+  if (result < 0) {
+    throw new Error("write error");
+  } else {
+    return result;
+  }
+}
+
+export async function writev(rid: number, data: Uint8Array[]): Promise<number> {
+  if (OP_WRITEV < 0) {
+    OP_WRITEV = OPS_CACHE["op_writev"];
+  }
+  const result = await sendAsyncMinimal(OP_WRITEV, rid, ...data);
+  if (result < 0) {
+    throw new Error("write error");
+  } else {
+    return result;
+  }
+}
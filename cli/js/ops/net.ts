@@ -27,6 +27,23 @@ export function shutdown(rid: number, how: ShutdownMode): Promise<void> {
   return Promise.resolve();
 }
 
+export function sendFile(
+  fileRid: number,
+  connRid: number,
+  count?: number
+): Promise<number> {
+  return sendAsync("op_send_file", { fileRid, connRid, count });
+}
+
+export interface IdleTimeouts {
+  read?: number;
+  write?: number;
+}
+
+export function setIdleTimeouts(rid: number, timeouts: IdleTimeouts): void {
+  sendSync("op_set_idle_timeouts", { rid, ...timeouts });
+}
+
 interface AcceptResponse {
   rid: number;
   localAddr: Addr;
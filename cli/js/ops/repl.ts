@@ -2,10 +2,40 @@
 
 import { sendSync, sendAsync } from "./dispatch_json.ts";
 
-export function startRepl(historyFile: string): number {
+export interface ReplStartResponse {
+  rid: number;
+  // Given with `--eval` and `--eval-file`.
+  eval: string | null;
+  evalFiles: string[];
+  // Whether `.break` is available, with `--inspect`.
+  debugger: boolean;
+}
+
+export function startRepl(historyFile: string): ReplStartResponse {
   return sendSync("op_repl_start", { historyFile });
 }
 
-export function readline(rid: number, prompt: string): Promise<string> {
+// Either the line that was read, or the expression before the cursor when
+// tab was pressed while reading it.
+export type ReadlineResult = { line: string } | { complete: string };
+
+export function readline(
+  rid: number,
+  prompt: string
+): Promise<ReadlineResult> {
   return sendAsync("op_repl_readline", { rid, prompt });
 }
+
+export function complete(rid: number, completions: string[]): void {
+  sendSync("op_repl_complete", { rid, completions });
+}
+
+// Returns a description of the breakpoint, or throws if the location isn't
+// valid.
+export function setBreakpoint(location: string): string {
+  return sendSync("op_repl_break", { location }).message;
+}
+
+export function isIncomplete(code: string): boolean {
+  return sendSync("op_repl_is_incomplete", { code });
+}
@@ -1,5 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-import { sendSync } from "./dispatch_json.ts";
+import { sendSync, sendAsync } from "./dispatch_json.ts";
 
 export interface ResourceMap {
   [rid: number]: string;
@@ -15,5 +15,32 @@ export function resources(): ResourceMap {
 }
 
 export function close(rid: number): void {
-  sendSync("op_close", { rid });
+  const { closing } = sendSync("op_close", { rid });
+  if (closing) {
+    // Sockets are shut down in order in the background, which keeps the
+    // event loop alive until they are. Errors don't matter by then.
+    sendAsync("op_finish_close", { rid }).catch((): void => {});
+  }
+}
+
+export function dup(rid: number): number {
+  return sendSync("op_dup", { rid });
+}
+
+export interface ReceivedResource {
+  rid: number;
+  kind: "fsFile" | "tcpStream" | "tcpListener" | "unixStream";
+}
+
+export async function sendResources(
+  rid: number,
+  rids: number[]
+): Promise<void> {
+  await sendAsync("op_send_resources", { rid, rids });
+}
+
+export function receiveResources(
+  rid: number
+): Promise<ReceivedResource[] | null> {
+  return sendAsync("op_receive_resources", { rid });
 }
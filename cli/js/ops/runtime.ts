@@ -1,15 +1,19 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
 import { sendSync } from "./dispatch_json.ts";
+import { BufferPoolStats, bufferPoolStats } from "../buffer_pool.ts";
 
 export interface Start {
   args: string[];
   cwd: string;
   debugFlag: boolean;
   denoVersion: string;
+  frozenTime: number | null;
   noColor: boolean;
+  opStackTraces: boolean;
   pid: number;
   repl: boolean;
+  strictResources: boolean;
   target: string;
   tsVersion: string;
   unstableFlag: boolean;
@@ -21,7 +25,7 @@ export function opStart(): Start {
   return sendSync("op_start");
 }
 
-export interface Metrics {
+export interface Metrics extends BufferPoolStats {
   opsDispatched: number;
   opsDispatchedSync: number;
   opsDispatchedAsync: number;
@@ -36,5 +40,5 @@ export interface Metrics {
 }
 
 export function metrics(): Metrics {
-  return sendSync("op_metrics");
+  return { ...sendSync("op_metrics"), ...bufferPoolStats() };
 }
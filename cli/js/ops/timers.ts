@@ -1,12 +1,42 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-import { sendSync, sendAsync } from "./dispatch_json.ts";
+import { sendAsync, sendSync } from "./dispatch_json.ts";
+import { sendSyncMinimal } from "./dispatch_minimal.ts";
+import { OPS_CACHE } from "../runtime.ts";
 
-export function stopGlobalTimer(): void {
-  sendSync("op_global_timer_stop");
+// Like `now()`, these are minimal ops since timers are often set and cleared
+// in hot code.
+let OP_TIMER_START = -1;
+let OP_TIMER_CANCEL = -1;
+const timerStartBuf = new Uint32Array(2);
+const timerStartBytes = new Uint8Array(timerStartBuf.buffer);
+
+export function startTimer(id: number, delay: number, repeat: boolean): void {
+  if (OP_TIMER_START < 0) {
+    OP_TIMER_START = OPS_CACHE["op_timer_start"];
+  }
+  timerStartBuf[0] = delay;
+  timerStartBuf[1] = repeat ? 1 : 0;
+  sendSyncMinimal(OP_TIMER_START, id, timerStartBytes);
+}
+
+export function cancelTimer(id: number): void {
+  if (OP_TIMER_CANCEL < 0) {
+    OP_TIMER_CANCEL = OPS_CACHE["op_timer_cancel"];
+  }
+  sendSyncMinimal(OP_TIMER_CANCEL, id);
 }
 
-export async function startGlobalTimer(timeout: number): Promise<void> {
-  await sendAsync("op_global_timer", { timeout });
+/** Resolves with the ids of the timers that are due, or with none when no
+ * timer is scheduled anymore. */
+export function pollTimers(): Promise<number[]> {
+  return sendAsync("op_timer_poll");
+}
+
+/** Moves the frozen clock to the next deadline up to `target`, in
+ * milliseconds since the start time, and returns the ids of the timers that
+ * are due then, or null once the clock reached `target`. */
+export function stepFakeTime(target: number): number[] | null {
+  return sendSync("op_fake_time_step", { target });
 }
 
 interface NowResponse {
@@ -14,6 +44,16 @@ interface NowResponse {
   subsecNanos: number;
 }
 
+// This is done because `performance.now()` is often called in hot loops; the
+// op writes its result into this buffer instead of sending JSON back.
+let OP_NOW = -1;
+const nowBuf = new Uint32Array(2);
+const nowBytes = new Uint8Array(nowBuf.buffer);
+
 export function now(): NowResponse {
-  return sendSync("op_now");
+  if (OP_NOW < 0) {
+    OP_NOW = OPS_CACHE["op_now"];
+  }
+  sendSyncMinimal(OP_NOW, 0, nowBytes);
+  return { seconds: nowBuf[0], subsecNanos: nowBuf[1] };
 }
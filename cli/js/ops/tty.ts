@@ -10,3 +10,7 @@ export function setRaw(rid: number, mode: boolean): void {
     mode,
   });
 }
+
+export function consoleSize(rid: number): { columns: number; rows: number } {
+  return sendSync("op_console_size", { rid });
+}
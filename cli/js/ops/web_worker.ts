@@ -2,8 +2,11 @@
 
 import { sendSync } from "./dispatch_json.ts";
 
-export function postMessage(data: Uint8Array): void {
-  sendSync("op_worker_post_message", {}, data);
+export function postMessage(
+  data: Uint8Array,
+  resources: number[] = []
+): void {
+  sendSync("op_worker_post_message", { resources }, data);
 }
 
 export function close(): void {
@@ -22,8 +22,12 @@ export function hostTerminateWorker(id: number): void {
   sendSync("op_host_terminate_worker", { id });
 }
 
-export function hostPostMessage(id: number, data: Uint8Array): void {
-  sendSync("op_host_post_message", { id }, data);
+export function hostPostMessage(
+  id: number,
+  data: Uint8Array,
+  resources: number[] = []
+): void {
+  sendSync("op_host_post_message", { id, resources }, data);
 }
 
 export function hostGetMessage(id: number): Promise<any> {
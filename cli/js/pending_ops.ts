@@ -0,0 +1,34 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+// Async ops that are still pending, counted by op id as they are dispatched
+// and as their responses come back, so that the test sanitizer can tell which
+// ops a test leaked.
+
+import { OPS_CACHE } from "./runtime.ts";
+
+// Using an object without a prototype because `Map` was causing GC problems.
+const pendingCounts: { [opId: number]: number } = Object.create(null);
+
+export function opStarted(opId: number): void {
+  pendingCounts[opId] = (pendingCounts[opId] ?? 0) + 1;
+}
+
+export function opCompleted(opId: number): void {
+  pendingCounts[opId]--;
+}
+
+export interface PendingOps {
+  [opName: string]: number;
+}
+
+/** Returns the number of pending async ops, by op name. */
+export function pendingOps(): PendingOps {
+  const pending: PendingOps = {};
+  for (const [opName, opId] of Object.entries(OPS_CACHE)) {
+    const count = pendingCounts[opId];
+    if (count) {
+      pending[opName] = count;
+    }
+  }
+  return pending;
+}
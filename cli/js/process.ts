@@ -14,7 +14,7 @@ export interface RunOptions {
   cwd?: string;
   env?: { [key: string]: string };
   stdout?: ProcessStdio | number;
-  stderr?: ProcessStdio | number;
+  stderr?: ProcessStdio | "stdout" | number;
   stdin?: ProcessStdio | number;
 }
 
@@ -3,8 +3,16 @@ import { exit } from "./ops/os.ts";
 import { core } from "./core.ts";
 import { version } from "./version.ts";
 import { stringifyArgs } from "./web/console.ts";
-import { startRepl, readline } from "./ops/repl.ts";
+import {
+  startRepl,
+  readline as readlineOrComplete,
+  complete,
+  isIncomplete,
+  setBreakpoint,
+} from "./ops/repl.ts";
 import { close } from "./ops/resources.ts";
+import { readTextFileSync } from "./read_text_file.ts";
+import { writeTextFileSync } from "./write_text_file.ts";
 
 function replLog(...args: unknown[]): void {
   core.print(stringifyArgs(args) + "\n");
@@ -24,12 +32,12 @@ const recoverableErrorMessages = [
   "Missing catch or finally after try", // try {}
   "missing ) after argument list", // console.log(1
   "Unterminated template literal", // `template
-  // TODO(kevinkassimo): need a parser to handling errors such as:
-  // "Missing } in template expression" // `${ or `${ a 123 }`
 ];
 
-function isRecoverableError(e: Error): boolean {
-  return recoverableErrorMessages.includes(e.message);
+// A syntax error is recoverable if the code so far looks like the beginning
+// of a longer input, e.g. because a bracket or a comment wasn't closed yet.
+function isRecoverableError(e: Error, code: string): boolean {
+  return recoverableErrorMessages.includes(e.message) || isIncomplete(code);
 }
 
 // Returns `true` if `close()` is called in REPL.
@@ -45,13 +53,26 @@ type Value = any;
 let lastEvalResult: Value = undefined;
 let lastThrownError: Value = undefined;
 
+// Inputs evaluated so far, written out by `.save`.
+const transcript: string[] = [];
+// Set by `.clear`, which ends the REPL loop of this isolate.
+let clearRequested = false;
+// With `--inspect`, execution can pause at breakpoints. The debugger reads its
+// own commands while it's paused.
+let debuggerEnabled = false;
+
 // Evaluate code.
 // Returns true if code is consumed (no error/irrecoverable error).
 // Returns false if error is recoverable
 function evaluate(code: string): boolean {
   // each evalContext is a separate function body, and we want strict mode to
   // work, so we should ensure that the code starts with "use strict"
-  const [result, errInfo] = core.evalContext(`"use strict";\n\n${code}`);
+  // Ctrl-C interrupts this script only, see `interrupt_on_ctrl_c()`.
+  const [result, errInfo] = core.evalContext(
+    `"use strict";\n\n${code}`,
+    undefined,
+    true
+  );
   if (!errInfo) {
     // when a function is eval'ed with just "use strict" sometimes the result
     // is "use strict" which should be discarded
@@ -62,27 +83,168 @@ function evaluate(code: string): boolean {
     if (!isCloseCalled()) {
       replLog(lastEvalResult);
     }
-  } else if (errInfo.isCompileError && isRecoverableError(errInfo.thrown)) {
+  } else if (errInfo.isInterrupted) {
+    replError("Interrupted");
+  } else if (
+    errInfo.isCompileError && isRecoverableError(errInfo.thrown, code)
+  ) {
     // Recoverable compiler error
     return false; // don't consume code.
   } else {
-    lastThrownError = errInfo.thrown;
-    if (errInfo.isNativeError) {
-      const formattedError = core.formatError(errInfo.thrown as Error);
-      replError(formattedError);
-    } else {
-      replError("Thrown:", errInfo.thrown);
+    reportError(errInfo);
+  }
+  transcript.push(code);
+  return true;
+}
+
+function reportError(errInfo: EvalErrorInfo): void {
+  lastThrownError = errInfo.thrown;
+  if (errInfo.isNativeError) {
+    const formattedError = core.formatError(errInfo.thrown as Error);
+    replError(formattedError);
+  } else {
+    replError("Thrown:", errInfo.thrown);
+  }
+}
+
+// Evaluates a script given with `--eval` or `--eval-file` before the first
+// prompt. Unlike inputs, it's not in strict mode and its result isn't printed.
+function preload(code: string, scriptName?: string): void {
+  const [, errInfo] = core.evalContext(code, scriptName);
+  if (errInfo) {
+    reportError(errInfo);
+  }
+}
+
+const replCommands: Array<[string, string]> = [
+  [".clear", "Start over with a fresh global scope"],
+  [".exit", "Exit the REPL"],
+  [".help", "Print this list of commands"],
+  [".load <file>", "Evaluate a file in this session"],
+  [".save <file>", "Save the inputs of this session to a file"],
+];
+
+const debuggerCommands: Array<[string, string]> = [
+  [".break <file>:<line>", "Pause when the line is reached"],
+  [".continue", "Resume execution, while paused"],
+  [".step", "Run to the next statement, while paused"],
+];
+
+// Runs a meta-command like `.load file.ts`. Returns `false` if `line` isn't
+// one, so that it's evaluated as code.
+function runReplCommand(
+  line: string,
+  quitRepl: (exitCode: number) => void
+): boolean {
+  const match = line.trim().match(/^\.([a-z]+)(?:\s+(.*))?$/);
+  if (!match) {
+    return false;
+  }
+  const [, command, arg = ""] = match;
+  try {
+    switch (command) {
+      case "help": {
+        const commands = debuggerEnabled
+          ? [...replCommands, ...debuggerCommands].sort()
+          : replCommands;
+        const width = debuggerEnabled ? 22 : 14;
+        for (const [name, help] of commands) {
+          replLog(`${name.padEnd(width)}${help}`);
+        }
+        break;
+      }
+      case "exit":
+        quitRepl(0);
+        break;
+      case "load":
+        if (!arg) {
+          replError("Usage: .load <file>");
+        } else if (!evaluate(readTextFileSync(arg))) {
+          replError(`Unexpected end of input in ${arg}`);
+        }
+        break;
+      case "save":
+        if (!arg) {
+          replError("Usage: .save <file>");
+        } else {
+          writeTextFileSync(arg, transcript.map((c) => `${c}\n`).join(""));
+          replLog(`Session saved to ${arg}`);
+        }
+        break;
+      case "clear":
+        clearRequested = true;
+        break;
+      case "break":
+        if (!debuggerEnabled) {
+          replError("The debugger of the REPL needs --inspect");
+        } else {
+          replLog(setBreakpoint(arg));
+        }
+        break;
+      case "continue":
+      case "step":
+        if (!debuggerEnabled) {
+          replError("The debugger of the REPL needs --inspect");
+        } else {
+          replError("Not paused");
+        }
+        break;
+      default:
+        replError(`Invalid REPL command .${command}, type .help for a list`);
     }
+  } catch (err) {
+    replError(core.formatError(err));
   }
   return true;
 }
 
+// Returns the names of the properties completing `expr`, like `readFile` for
+// `Deno.re`.
+function completions(expr: string): string[] {
+  const dot = expr.lastIndexOf(".");
+  const prefix = expr.slice(dot + 1);
+  let object: Value = globalThis;
+  if (dot !== -1) {
+    const [result, errInfo] = core.evalContext(expr.slice(0, dot));
+    if (errInfo || result == null) {
+      return [];
+    }
+    object = result;
+  }
+  const names = new Set<string>();
+  for (let o = Object(object); o != null; o = Object.getPrototypeOf(o)) {
+    for (const name of Object.getOwnPropertyNames(o)) {
+      if (name.startsWith(prefix) && /^[A-Za-z_$][\w$]*$/.test(name)) {
+        names.add(name);
+      }
+    }
+  }
+  return [...names].sort();
+}
+
+// Reads a line, answering the tab completion requests made while it's typed.
+async function readline(rid: number, prompt: string): Promise<string> {
+  while (true) {
+    const result = await readlineOrComplete(rid, prompt);
+    if ("line" in result) {
+      return result.line;
+    }
+    complete(rid, completions(result.complete));
+  }
+}
+
 // @internal
 export async function replLoop(): Promise<void> {
   const { console } = globalThis;
 
   const historyFile = "deno_history.txt";
-  const rid = startRepl(historyFile);
+  const {
+    rid,
+    eval: evalCode,
+    evalFiles,
+    debugger: withDebugger,
+  } = startRepl(historyFile);
+  debuggerEnabled = withDebugger;
 
   const quitRepl = (exitCode: number): void => {
     // Special handling in case user calls deno.close(3).
@@ -125,6 +287,20 @@ export async function replLoop(): Promise<void> {
   replLog(`Deno ${version.deno}`);
   replLog("exit using ctrl+d or close()");
 
+  for (const file of evalFiles) {
+    try {
+      preload(readTextFileSync(file), file);
+    } catch (err) {
+      replError(core.formatError(err));
+    }
+  }
+  if (evalCode !== null) {
+    preload(evalCode);
+  }
+
+  // Whether the last prompt was left with Ctrl-C.
+  let interrupted = false;
+
   while (true) {
     if (isCloseCalled()) {
       quitRepl(0);
@@ -134,12 +310,27 @@ export async function replLoop(): Promise<void> {
     // Top level read
     try {
       code = await readline(rid, "> ");
+      interrupted = false;
       if (code.trim() === "") {
         continue;
       }
+      if (runReplCommand(code, quitRepl)) {
+        if (clearRequested) {
+          // Once nothing is left to do, the event loop of this isolate ends
+          // and the REPL is started over in a new one.
+          close(rid);
+          return;
+        }
+        continue;
+      }
     } catch (err) {
       if (err.message === "EOF") {
         quitRepl(0);
+      } else if (err.message === "Interrupted" && !interrupted) {
+        // Only a second Ctrl-C in a row quits.
+        interrupted = true;
+        replLog("(To exit, press Ctrl+C again or Ctrl+D or type .exit)");
+        continue;
       } else {
         // If interrupted, don't print error.
         if (err.message !== "Interrupted") {
@@ -0,0 +1,32 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+// With `--strict-resources`, the stack of the code calling a JSON op is sent
+// to Rust right before the op. Rust records it for the resources the op adds,
+// so that the ones left open at exit can be reported along with where they
+// were created. Minimal ops (reads, writes and timers) never add resources.
+
+import { core } from "./core.ts";
+import { OPS_CACHE } from "./runtime.ts";
+
+let strictResources = false;
+
+// @internal
+export function setStrictResources(enabled: boolean): void {
+  strictResources = enabled;
+}
+
+export function recordResourceOrigin(): void {
+  if (!strictResources) {
+    return;
+  }
+  const error = new Error();
+  Error.captureStackTrace(error, recordResourceOrigin);
+  // Frames of the runtime itself only get in the way.
+  const origin = (error.stack ?? "")
+    .split("\n")
+    .slice(1)
+    .filter((line): boolean => !line.includes("$deno$"))
+    .join("\n");
+  const args = core.encode(JSON.stringify({ origin }));
+  core.dispatch(OPS_CACHE["op_set_resource_origin"], args);
+}
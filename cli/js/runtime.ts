@@ -6,8 +6,11 @@ import * as util from "./util.ts";
 import { setBuildInfo } from "./build.ts";
 import { setVersions } from "./version.ts";
 import { setPrepareStackTrace } from "./error_stack.ts";
+import { setOpStackTraces } from "./errors.ts";
+import { setStrictResources } from "./resource_origin.ts";
 import { Start, opStart } from "./ops/runtime.ts";
 import { handleTimerMacrotask } from "./web/timers.ts";
+import { freezeTime } from "./fake_time.ts";
 
 export let OPS_CACHE: { [name: string]: number };
 
@@ -15,6 +18,11 @@ function getAsyncHandler(opName: string): (msg: Uint8Array) => void {
   switch (opName) {
     case "op_write":
     case "op_read":
+    case "op_writev":
+    case "op_readv":
+    case "op_now":
+    case "op_timer_start":
+    case "op_timer_cancel":
       return dispatchMinimal.asyncMsgFromRust;
     default:
       return dispatchJson.asyncMsgFromRust;
@@ -41,5 +49,10 @@ export function start(source?: string): Start {
   setBuildInfo(s.target);
   util.setLogDebug(s.debugFlag, source);
   setPrepareStackTrace(Error);
+  setOpStackTraces(s.opStackTraces);
+  setStrictResources(s.strictResources);
+  if (s.frozenTime !== null) {
+    freezeTime(s.frozenTime);
+  }
   return s;
 }
@@ -26,6 +26,7 @@ import { replLoop } from "./repl.ts";
 import { setTimeout } from "./web/timers.ts";
 import * as runtime from "./runtime.ts";
 import { log, immutableDefine } from "./util.ts";
+import { wrapConsole } from "./web/console.ts";
 
 // TODO: factor out `Deno` global assignment to separate function
 // Add internal object to Deno object.
@@ -75,10 +76,12 @@ export function bootstrapMainRuntime(): void {
   (globalThis as any).bootstrap = undefined;
   log("bootstrapMainRuntime");
   hasBootstrapped = true;
+  const consoleFromV8 = globalThis.console;
   Object.defineProperties(globalThis, windowOrWorkerGlobalScopeMethods);
   Object.defineProperties(globalThis, windowOrWorkerGlobalScopeProperties);
   Object.defineProperties(globalThis, eventTargetProperties);
   Object.defineProperties(globalThis, mainRuntimeGlobalProperties);
+  wrapConsole(globalThis.console, consoleFromV8);
   setEventTargetData(globalThis);
   // Registers the handler for window.onload function.
   globalThis.addEventListener("load", (e) => {
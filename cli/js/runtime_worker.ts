@@ -20,10 +20,12 @@ import {
 import { unstableMethods, unstableProperties } from "./globals_unstable.ts";
 import * as denoNs from "./deno.ts";
 import * as denoUnstableNs from "./deno_unstable.ts";
+import { ReceivedResource } from "./ops/resources.ts";
 import * as webWorkerOps from "./ops/web_worker.ts";
 import { log, assert, immutableDefine } from "./util.ts";
 import { ErrorEventImpl as ErrorEvent } from "./web/error_event.ts";
 import { MessageEvent } from "./web/workers.ts";
+import { wrapConsole } from "./web/console.ts";
 import { TextEncoder } from "./web/text_encoding.ts";
 import * as runtime from "./runtime.ts";
 import { internalObject, internalSymbol } from "./internals.ts";
@@ -43,10 +45,13 @@ const encoder = new TextEncoder();
 export const onmessage: (e: { data: any }) => void = (): void => {};
 export const onerror: (e: { data: any }) => void = (): void => {};
 
-export function postMessage(data: any): void {
+export function postMessage(
+  data: any,
+  options?: { resources?: number[] }
+): void {
   const dataJson = JSON.stringify(data);
   const dataIntArray = encoder.encode(dataJson);
-  webWorkerOps.postMessage(dataIntArray);
+  webWorkerOps.postMessage(dataIntArray, options?.resources);
 }
 
 let isClosing = false;
@@ -61,10 +66,14 @@ export function close(): void {
   webWorkerOps.close();
 }
 
-export async function workerMessageRecvCallback(data: string): Promise<void> {
+export async function workerMessageRecvCallback(
+  data: string,
+  resources: ReceivedResource[]
+): Promise<void> {
   const msgEvent = new MessageEvent("message", {
     cancelable: false,
     data,
+    resources,
   });
 
   try {
@@ -132,11 +141,13 @@ export function bootstrapWorkerRuntime(
   (globalThis as any).bootstrap = undefined;
   log("bootstrapWorkerRuntime");
   hasBootstrapped = true;
+  const consoleFromV8 = globalThis.console;
   Object.defineProperties(globalThis, windowOrWorkerGlobalScopeMethods);
   Object.defineProperties(globalThis, windowOrWorkerGlobalScopeProperties);
   Object.defineProperties(globalThis, workerRuntimeGlobalProperties);
   Object.defineProperties(globalThis, eventTargetProperties);
   Object.defineProperties(globalThis, { name: readOnly(name) });
+  wrapConsole(globalThis.console, consoleFromV8);
   setEventTargetData(globalThis);
   const { unstableFlag, pid, noColor, args } = runtime.start(
     internalName ?? name
@@ -6,8 +6,10 @@ import { stdout } from "./files.ts";
 import { exposeForTest } from "./internals.ts";
 import { TextEncoder } from "./web/text_encoding.ts";
 import { metrics } from "./ops/runtime.ts";
-import { resources } from "./ops/resources.ts";
+import { ResourceMap, resources } from "./ops/resources.ts";
+import { PendingOps, pendingOps } from "./pending_ops.ts";
 import { assert } from "./util.ts";
+import { BenchStats } from "./ops/bench.ts";
 
 const RED_FAILED = red("FAILED");
 const GREEN_OK = green("ok");
@@ -25,6 +27,41 @@ function formatDuration(time = 0): string {
   return gray(italic(timeStr));
 }
 
+function formatNanos(ns: number): string {
+  if (ns < 1e3) {
+    return `${ns.toFixed(0)}ns`;
+  } else if (ns < 1e6) {
+    return `${(ns / 1e3).toFixed(2)}µs`;
+  } else if (ns < 1e9) {
+    return `${(ns / 1e6).toFixed(2)}ms`;
+  }
+  return `${(ns / 1e9).toFixed(2)}s`;
+}
+
+function formatStats(stats: BenchStats): string {
+  const details =
+    `± ${formatNanos(stats.stddev)}, ` +
+    `min ${formatNanos(stats.min)}, max ${formatNanos(stats.max)}, ` +
+    `p50 ${formatNanos(stats.p50)}, p75 ${formatNanos(stats.p75)}, ` +
+    `p99 ${formatNanos(stats.p99)}`;
+  const counts = `${stats.samples} samples, ${stats.iterations} iterations`;
+  return (
+    `${formatNanos(stats.mean)}/iter ${gray(`(${details})`)} ` +
+    gray(italic(`[${counts}]`))
+  );
+}
+
+// Lists the ops that have more pending calls in `post` than in `pre`.
+function formatLeakedOps(pre: PendingOps, post: PendingOps): string {
+  return Object.entries(post)
+    .filter(([opName, count]): boolean => count > (pre[opName] ?? 0))
+    .map(
+      ([opName, count]): string =>
+        `  - ${opName}: ${count - (pre[opName] ?? 0)} pending`
+    )
+    .join("\n");
+}
+
 // Wrap test function in additional assertion that makes sure
 // the test case does not leak async "ops" - ie. number of async
 // completed ops after the test is the same as number of dispatched
@@ -33,6 +70,7 @@ function formatDuration(time = 0): string {
 function assertOps(fn: () => void | Promise<void>): () => void | Promise<void> {
   return async function asyncOpSanitizer(): Promise<void> {
     const pre = metrics();
+    const prePending = pendingOps();
     await fn();
     // Defer until next event loop turn - that way timeouts and intervals
     // cleared can actually be removed from resource table, otherwise
@@ -43,8 +81,12 @@ function assertOps(fn: () => void | Promise<void>): () => void | Promise<void> {
     // that will be a pending async op before test starts.
     const dispatchedDiff = post.opsDispatchedAsync - pre.opsDispatchedAsync;
     const completedDiff = post.opsCompletedAsync - pre.opsCompletedAsync;
+    if (dispatchedDiff === completedDiff) {
+      return;
+    }
+    const leaked = formatLeakedOps(prePending, pendingOps());
     assert(
-      dispatchedDiff === completedDiff,
+      false,
       `Test case is leaking async ops.
 Before:
   - dispatched: ${pre.opsDispatchedAsync}
@@ -52,13 +94,30 @@ Before:
 After:
   - dispatched: ${post.opsDispatchedAsync}
   - completed: ${post.opsCompletedAsync}
-  
+${leaked ? `Pending ops started by the test:\n${leaked}\n` : ""}
 Make sure to await all promises returned from Deno APIs before 
 finishing test case.`
     );
   };
 }
 
+// Lists the resources of `post` that aren't in `pre`, and the other way
+// around.
+function formatResourceChanges(pre: ResourceMap, post: ResourceMap): string {
+  const lines: string[] = [];
+  for (const [rid, name] of Object.entries(post)) {
+    if (pre[+rid] !== name) {
+      lines.push(`  - opened: ${name} (rid ${rid})`);
+    }
+  }
+  for (const [rid, name] of Object.entries(pre)) {
+    if (post[+rid] !== name) {
+      lines.push(`  - closed: ${name} (rid ${rid})`);
+    }
+  }
+  return lines.join("\n");
+}
+
 // Wrap test function in additional assertion that makes sure
 // the test case does not "leak" resources - ie. resource table after
 // the test has exactly the same contents as before the test.
@@ -70,15 +129,13 @@ function assertResources(
     await fn();
     const post = resources();
 
-    const preStr = JSON.stringify(pre, null, 2);
-    const postStr = JSON.stringify(post, null, 2);
+    const changes = formatResourceChanges(pre, post);
     const msg = `Test case is leaking resources.
-Before: ${preStr}
-After: ${postStr}
+${changes}
 
 Make sure to close all open resource handles returned from Deno APIs before 
 finishing test case.`;
-    assert(preStr === postStr, msg);
+    assert(changes === "", msg);
   };
 }
 
@@ -146,9 +203,11 @@ interface TestMessage {
   };
   testEnd?: {
     name: string;
-    status: "passed" | "failed" | "ignored";
+    status: "passed" | "failed" | "ignored" | "measured";
     duration: number;
     error?: Error;
+    // Set when a benchmark is measured.
+    stats?: BenchStats;
   };
   end?: {
     filtered: number;
@@ -174,16 +233,24 @@ function log(msg: string, noNewLine = false): void {
   stdout.writeSync(encoder.encode(msg));
 }
 
-function reportToConsole(message: TestMessage): void {
+// What a suite runs: `deno test` and `deno bench` share the runner and the
+// reporters, and only differ by how each definition is run.
+export type SuiteKind = "test" | "bench";
+
+function reportToConsole(message: TestMessage, kind: SuiteKind = "test"): void {
   if (message.start != null) {
-    log(`running ${message.start.tests.length} tests`);
+    const noun = kind === "test" ? "tests" : "benchmarks";
+    log(`running ${message.start.tests.length} ${noun}`);
   } else if (message.testStart != null) {
     const { name } = message.testStart;
 
-    log(`test ${name} ... `, true);
+    log(`${kind} ${name} ... `, true);
     return;
   } else if (message.testEnd != null) {
     switch (message.testEnd.status) {
+      case "measured":
+        log(formatStats(message.testEnd.stats!));
+        break;
       case "passed":
         log(`${GREEN_OK} ${formatDuration(message.testEnd.duration)}`);
         break;
@@ -212,7 +279,7 @@ function reportToConsole(message: TestMessage): void {
       }
     }
     log(
-      `\ntest result: ${message.end.failed ? RED_FAILED : GREEN_OK}. ` +
+      `\n${kind} result: ${message.end.failed ? RED_FAILED : GREEN_OK}. ` +
         `${message.end.passed} passed; ${message.end.failed} failed; ` +
         `${message.end.ignored} ignored; ${message.end.measured} measured; ` +
         `${message.end.filtered} filtered out ` +
@@ -223,6 +290,36 @@ function reportToConsole(message: TestMessage): void {
 
 exposeForTest("reportToConsole", reportToConsole);
 
+// Used by the test modules run by `deno test --jobs`: the messages are written
+// on stdout, after a record separator so that the parent process can tell
+// them from the output of the tests, which it attributes to each test.
+function reportToJson(message: TestMessage): void {
+  let serializable: unknown = message;
+  if (message.start != null) {
+    const tests = message.start.tests.map(({ name }) => ({ name }));
+    serializable = { start: { tests } };
+  } else if (message.testStart != null) {
+    serializable = { testStart: { name: message.testStart.name } };
+  } else if (message.testEnd != null && message.testEnd.error != null) {
+    const error = stringifyArgs([message.testEnd.error]);
+    serializable = { testEnd: { ...message.testEnd, error } };
+  } else if (message.end != null) {
+    serializable = { end: { ...message.end, results: [] } };
+  }
+  log(`\x1e${JSON.stringify(serializable)}`);
+}
+
+// Runs a definition that doesn't get ignored, and returns what its end
+// message says when it doesn't throw.
+export type RunFn = (
+  def: TestDefinition
+) => Promise<Partial<TestMessage["testEnd"] & {}>>;
+
+async function runTest(test: TestDefinition): ReturnType<RunFn> {
+  await test.fn();
+  return { status: "passed" };
+}
+
 // TODO: already implements AsyncGenerator<RunTestsMessage>, but add as "implements to class"
 // TODO: implements PromiseLike<RunTestsEndResult>
 class TestApi {
@@ -238,7 +335,8 @@ class TestApi {
   constructor(
     public tests: TestDefinition[],
     public filterFn: (def: TestDefinition) => boolean,
-    public failFast: boolean
+    public failFast: boolean,
+    public run: RunFn = runTest
   ) {
     this.testsToRun = tests.filter(filterFn);
     this.stats.filtered = tests.length - this.testsToRun.length;
@@ -261,9 +359,12 @@ class TestApi {
       } else {
         const start = +new Date();
         try {
-          await test.fn();
-          endMessage.status = "passed";
-          this.stats.passed++;
+          Object.assign(endMessage, await this.run(test));
+          if (endMessage.status === "measured") {
+            this.stats.measured++;
+          } else {
+            this.stats.passed++;
+          }
         } catch (err) {
           endMessage.status = "failed";
           endMessage.error = err;
@@ -284,11 +385,11 @@ class TestApi {
   }
 }
 
-function createFilterFn(
+export function createFilterFn(
   filter: undefined | string | RegExp,
   skip: undefined | string | RegExp
-): (def: TestDefinition) => boolean {
-  return (def: TestDefinition): boolean => {
+): (def: { name: string }) => boolean {
+  return (def: { name: string }): boolean => {
     let passes = true;
 
     if (filter) {
@@ -311,27 +412,35 @@ function createFilterFn(
   };
 }
 
-interface RunTestsOptions {
+export interface RunTestsOptions {
   exitOnFail?: boolean;
   failFast?: boolean;
   filter?: string | RegExp;
   skip?: string | RegExp;
   disableLog?: boolean;
   reportToConsole?: boolean;
+  reportToJson?: boolean;
   onMessage?: (message: TestMessage) => void | Promise<void>;
 }
 
-async function runTests({
-  exitOnFail = true,
-  failFast = false,
-  filter = undefined,
-  skip = undefined,
-  disableLog = false,
-  reportToConsole: reportToConsole_ = true,
-  onMessage = undefined,
-}: RunTestsOptions = {}): Promise<TestMessage["end"] & {}> {
+// Runs the definitions of a suite that pass the filters, and reports them.
+export async function runSuite(
+  definitions: TestDefinition[],
+  kind: SuiteKind,
+  run: RunFn,
+  {
+    exitOnFail = true,
+    failFast = false,
+    filter = undefined,
+    skip = undefined,
+    disableLog = false,
+    reportToConsole: reportToConsole_ = true,
+    reportToJson: reportToJson_ = false,
+    onMessage = undefined,
+  }: RunTestsOptions = {}
+): Promise<TestMessage["end"] & {}> {
   const filterFn = createFilterFn(filter, skip);
-  const testApi = new TestApi(TEST_REGISTRY, filterFn, failFast);
+  const testApi = new TestApi(definitions, filterFn, failFast, run);
 
   const originalConsole = globalThis.console;
 
@@ -347,7 +456,10 @@ async function runTests({
       await onMessage(message);
     }
     if (reportToConsole_) {
-      reportToConsole(message);
+      reportToConsole(message, kind);
+    }
+    if (reportToJson_) {
+      reportToJson(message);
     }
     if (message.end != null) {
       endMsg = message.end;
@@ -365,4 +477,10 @@ async function runTests({
   return endMsg!;
 }
 
+function runTests(
+  options: RunTestsOptions = {}
+): Promise<TestMessage["end"] & {}> {
+  return runSuite(TEST_REGISTRY, "test", runTest, options);
+}
+
 exposeForTest("runTests", runTests);
@@ -974,6 +974,31 @@ export function inspect(
   }
 }
 
+/** Makes the methods of `consoleFromDeno` also call those of
+ * `consoleFromV8`, the console V8 puts on the global object. It only sends
+ * the messages to the inspector, so that they show up in DevTools, and does
+ * nothing when no inspector is attached. */
+export function wrapConsole(
+  consoleFromDeno: object,
+  consoleFromV8: object
+): void {
+  // eslint-disable-next-line @typescript-eslint/no-explicit-any
+  const fromDeno = consoleFromDeno as any;
+  // eslint-disable-next-line @typescript-eslint/no-explicit-any
+  const fromV8 = consoleFromV8 as any;
+  for (const key of Object.getOwnPropertyNames(fromV8)) {
+    const denoMethod = fromDeno[key];
+    const v8Method = fromV8[key];
+    if (typeof denoMethod !== "function" || typeof v8Method !== "function") {
+      continue;
+    }
+    fromDeno[key] = function (...args: unknown[]): unknown {
+      v8Method.apply(fromV8, args);
+      return denoMethod.apply(this, args);
+    };
+  }
+}
+
 // Expose these fields to internalObject for tests.
 exposeForTest("Console", Console);
 exposeForTest("stringifyArgs", stringifyArgs);
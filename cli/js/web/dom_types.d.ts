@@ -253,6 +253,12 @@ export interface Body {
   text(): Promise<string>;
 }
 
+export interface FetchProgress {
+  direction: "upload" | "download";
+  loaded: number;
+  total?: number;
+}
+
 export interface RequestInit {
   body?: BodyInit | null;
   cache?: RequestCache;
@@ -266,6 +272,8 @@ export interface RequestInit {
   referrer?: string;
   referrerPolicy?: ReferrerPolicy;
   signal?: AbortSignal | null;
+  timeout?: number;
+  onProgress?: (progress: FetchProgress) => void;
   window?: any;
 }
 
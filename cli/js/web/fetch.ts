@@ -4,13 +4,19 @@ import { isTypedArray } from "./util.ts";
 import * as domTypes from "./dom_types.d.ts";
 import { TextDecoder, TextEncoder } from "./text_encoding.ts";
 import { DenoBlob, bytesSymbol as blobBytesSymbol } from "./blob.ts";
-import { read } from "../ops/io.ts";
 import { close } from "../ops/resources.ts";
-import { fetch as opFetch, FetchResponse } from "../ops/fetch.ts";
+import {
+  fetch as opFetch,
+  FetchResponse,
+  openUploadProgress,
+  pollUploadProgress,
+} from "../ops/fetch.ts";
 import * as Body from "./body.ts";
 import { DomFileImpl } from "./dom_file.ts";
 import { getHeaderValueParams } from "./util.ts";
 import { ReadableStreamImpl } from "./streams/readable_stream.ts";
+import { readableStreamFromResource } from "./resource_stream.ts";
+import { DOMExceptionImpl } from "./dom_exception.ts";
 
 const NULL_BODY_STATUS = [101, 204, 205, 304];
 const REDIRECT_STATUS = [301, 302, 303, 307, 308];
@@ -166,11 +172,66 @@ export class Response extends Body.Body implements domTypes.Response {
   }
 }
 
+/** How often the download progress of a response body is reported. */
+const PROGRESS_INTERVAL = 100;
+
+/** Turns the reads of a response body into calls of `onProgress`, at most
+ * one every `PROGRESS_INTERVAL` milliseconds, and always one at the end. */
+function downloadProgress(
+  onProgress: (progress: domTypes.FetchProgress) => void,
+  total: number | undefined
+): (nread: number) => void {
+  let loaded = 0;
+  let lastReport = 0;
+  let done = false;
+  return (nread: number): void => {
+    if (done) {
+      return;
+    }
+    loaded += nread;
+    done = nread === 0 || loaded === total;
+    const now = Date.now();
+    if (done || now - lastReport >= PROGRESS_INTERVAL) {
+      lastReport = now;
+      onProgress({ direction: "download", loaded, total });
+    }
+  };
+}
+
+/** Reports the upload progress of a request body until it is sent, at most
+ * once every `PROGRESS_INTERVAL` milliseconds, and always at its end. */
+async function reportUploadProgress(
+  rid: number,
+  onProgress: (progress: domTypes.FetchProgress) => void,
+  total: number
+): Promise<void> {
+  let lastReport = 0;
+  try {
+    while (true) {
+      const { loaded, done } = await pollUploadProgress(rid);
+      if (done || loaded == null) {
+        return;
+      }
+      const now = Date.now();
+      if (loaded === total || now - lastReport >= PROGRESS_INTERVAL) {
+        lastReport = now;
+        onProgress({ direction: "upload", loaded, total });
+      }
+    }
+  } finally {
+    close(rid);
+  }
+}
+
 function sendFetchReq(
   url: string,
   method: string | null,
   headers: Headers | null,
-  body: ArrayBufferView | undefined
+  body: ArrayBufferView | undefined,
+  signal: AbortSignal | undefined,
+  timeout: number | undefined,
+  uploadProgressRid: number | undefined,
+  progress: boolean
 ): Promise<FetchResponse> {
   let headerArray: Array<[string, string]> = [];
   if (headers) {
@@ -181,9 +242,12 @@ function sendFetchReq(
     method,
     url,
     headers: headerArray,
+    timeout,
+    uploadProgressRid,
+    progress,
   };
 
-  return opFetch(args, body);
+  return opFetch(args, body, signal);
 }
 
 export async function fetch(
@@ -196,6 +260,10 @@ export async function fetch(
   let body: ArrayBufferView | undefined;
   let redirected = false;
   let remRedirectCount = 20; // TODO: use a better way to handle
+  const signal = init?.signal ?? undefined;
+  const onProgress = init?.onProgress;
+  // The timeout covers the whole fetch, so every redirect gets what is left.
+  const deadline = init?.timeout != null ? Date.now() + init.timeout : null;
 
   if (typeof input === "string" || input instanceof URL) {
     url = typeof input === "string" ? (input as string) : (input as URL).href;
@@ -293,7 +361,32 @@ export async function fetch(
   let responseBody;
   let responseInit: ResponseInit = {};
   while (remRedirectCount) {
-    const fetchResponse = await sendFetchReq(url, method, headers, body);
+    let fetchResponse: FetchResponse;
+    const uploadProgressRid =
+      onProgress && body ? openUploadProgress() : undefined;
+    try {
+      const request = sendFetchReq(
+        url,
+        method,
+        headers,
+        body,
+        signal,
+        deadline != null ? Math.max(deadline - Date.now(), 0) : undefined,
+        uploadProgressRid,
+        onProgress != null
+      );
+      if (uploadProgressRid != null) {
+        // The op has taken the progress sender by now, so the reports stop
+        // with the upload, whether the fetch succeeds or not.
+        reportUploadProgress(uploadProgressRid, onProgress!, body!.byteLength);
+      }
+      fetchResponse = await request;
+    } catch (err) {
+      if (signal?.aborted) {
+        throw new DOMExceptionImpl("The request was aborted.", "AbortError");
+      }
+      throw err;
+    }
 
     if (
       NULL_BODY_STATUS.includes(fetchResponse.status) ||
@@ -304,27 +397,17 @@ export async function fetch(
       close(fetchResponse.bodyRid);
       responseBody = null;
     } else {
-      responseBody = new ReadableStreamImpl({
-        async pull(controller: ReadableStreamDefaultController): Promise<void> {
-          try {
-            const b = new Uint8Array(1024 * 32);
-            const result = await read(fetchResponse.bodyRid, b);
-            if (result === null) {
-              controller.close();
-              return close(fetchResponse.bodyRid);
-            }
-
-            controller.enqueue(b.subarray(0, result));
-          } catch (e) {
-            controller.error(e);
-            controller.close();
-            close(fetchResponse.bodyRid);
-          }
-        },
-        cancel(): void {
-          // When reader.cancel() is called
-          close(fetchResponse.bodyRid);
-        },
+      let onRead: ((nread: number) => void) | undefined;
+      if (onProgress) {
+        const contentLength = fetchResponse.headers.find(
+          ([name]): boolean => name.toLowerCase() === "content-length"
+        );
+        const total = contentLength ? Number(contentLength[1]) : undefined;
+        onRead = downloadProgress(onProgress, total);
+      }
+      responseBody = readableStreamFromResource(fetchResponse.bodyRid, {
+        signal,
+        onRead,
       });
     }
 
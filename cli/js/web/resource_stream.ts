@@ -0,0 +1,154 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+// Streams over resources. Every kind of resource that can be read or written
+// (files, sockets, child process pipes, HTTP bodies) implements the same
+// poll based contract in Rust, so the streams here work for all of them and
+// apply backpressure the same way: a readable stream only reads from the
+// resource while its queue is below the high water mark, and a writable stream
+// reports a full queue through `desiredSize` and `ready` until the resource
+// caught up.
+
+import { read, write } from "../ops/io.ts";
+import { close } from "../ops/resources.ts";
+import { ReadableStreamImpl } from "./streams/readable_stream.ts";
+import { WritableStreamImpl } from "./streams/writable_stream.ts";
+import { DOMExceptionImpl } from "./dom_exception.ts";
+import { exposeForTest } from "../internals.ts";
+
+export interface ResourceStreamOptions {
+  /** Number of bytes the queue of the stream may hold before backpressure
+   * kicks in. Defaults to 64 KiB. */
+  highWaterMark?: number;
+  /** Size of the chunks a readable stream reads. Defaults to 32 KiB. */
+  chunkSize?: number;
+  /** Whether the resource is closed when the stream ends, fails or is
+   * canceled. Defaults to true. */
+  autoClose?: boolean;
+  /** Aborting the signal errors a readable stream with an `AbortError`, and
+   * closes the resource if `autoClose` is set. */
+  signal?: AbortSignal;
+  /** Called after every read of a readable stream with the number of bytes
+   * read, and with 0 once the end of the resource is reached. */
+  onRead?: (nread: number) => void;
+}
+
+const DEFAULT_HIGH_WATER_MARK = 64 * 1024;
+const DEFAULT_CHUNK_SIZE = 32 * 1024;
+
+function byteLengthStrategy(
+  highWaterMark: number
+): QueuingStrategy<Uint8Array> {
+  return {
+    highWaterMark,
+    size(chunk: Uint8Array): number {
+      return chunk.byteLength;
+    },
+  };
+}
+
+export function readableStreamFromResource(
+  rid: number,
+  {
+    highWaterMark = DEFAULT_HIGH_WATER_MARK,
+    chunkSize = DEFAULT_CHUNK_SIZE,
+    autoClose = true,
+    signal,
+    onRead,
+  }: ResourceStreamOptions = {}
+): ReadableStream<Uint8Array> {
+  let closed = false;
+  let onAbort: (() => void) | undefined;
+  const maybeClose = (): void => {
+    if (onAbort) {
+      signal!.removeEventListener("abort", onAbort);
+      onAbort = undefined;
+    }
+    if (autoClose && !closed) {
+      closed = true;
+      close(rid);
+    }
+  };
+  return new ReadableStreamImpl(
+    {
+      start(controller: ReadableStreamDefaultController<Uint8Array>): void {
+        if (!signal) {
+          return;
+        }
+        onAbort = (): void => {
+          controller.error(
+            new DOMExceptionImpl("The operation was aborted.", "AbortError")
+          );
+          maybeClose();
+        };
+        if (signal.aborted) {
+          onAbort();
+        } else {
+          signal.addEventListener("abort", onAbort);
+        }
+      },
+      async pull(
+        controller: ReadableStreamDefaultController<Uint8Array>
+      ): Promise<void> {
+        const chunk = new Uint8Array(chunkSize);
+        let nread;
+        try {
+          nread = await read(rid, chunk);
+        } catch (err) {
+          controller.error(err);
+          maybeClose();
+          return;
+        }
+        onRead?.(nread ?? 0);
+        if (nread === null) {
+          controller.close();
+          maybeClose();
+          return;
+        }
+        controller.enqueue(chunk.subarray(0, nread));
+      },
+      cancel(): void {
+        maybeClose();
+      },
+    },
+    byteLengthStrategy(highWaterMark)
+  );
+}
+
+export function writableStreamFromResource(
+  rid: number,
+  {
+    highWaterMark = DEFAULT_HIGH_WATER_MARK,
+    autoClose = true,
+  }: ResourceStreamOptions = {}
+): WritableStream<Uint8Array> {
+  const maybeClose = (): void => {
+    if (autoClose) {
+      close(rid);
+    }
+  };
+  return new WritableStreamImpl(
+    {
+      async write(chunk: Uint8Array): Promise<void> {
+        try {
+          let nwritten = 0;
+          while (nwritten < chunk.byteLength) {
+            nwritten += await write(rid, chunk.subarray(nwritten));
+          }
+        } catch (err) {
+          maybeClose();
+          throw err;
+        }
+      },
+      close(): void {
+        maybeClose();
+      },
+      abort(): void {
+        maybeClose();
+      },
+    },
+    byteLengthStrategy(highWaterMark)
+  );
+}
+
+exposeForTest("readableStreamFromResource", readableStreamFromResource);
+exposeForTest("writableStreamFromResource", writableStreamFromResource);
@@ -1,15 +1,16 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 import { assert } from "../util.ts";
-import { startGlobalTimer, stopGlobalTimer } from "../ops/timers.ts";
-import { RBTree } from "../rbtree.ts";
+import { cancelTimer, pollTimers, startTimer } from "../ops/timers.ts";
 
 const { console } = globalThis;
 
+// Timers are scheduled in Rust, which coalesces their wakeups and resolves a
+// single pending `pollTimers()` with the ids of the timers that are due. The
+// callbacks stay here, by id.
 interface Timer {
   id: number;
   callback: () => void;
   delay: number;
-  due: number;
   repeat: boolean;
   scheduled: boolean;
 }
@@ -17,19 +18,16 @@ interface Timer {
 // Timeout values > TIMEOUT_MAX are set to 1.
 const TIMEOUT_MAX = 2 ** 31 - 1;
 
-let globalTimeoutDue: number | null = null;
-
 let nextTimerId = 1;
 const idMap = new Map<number, Timer>();
-type DueNode = { due: number; timers: Timer[] };
-const dueTree = new RBTree<DueNode>((a, b) => a.due - b.due);
 
-function clearGlobalTimeout(): void {
-  globalTimeoutDue = null;
-  stopGlobalTimer();
-}
+// Number of timers scheduled in Rust, for which `pollTimers()` is pending.
+let scheduledCount = 0;
+// Number of times a timer was scheduled, to tell whether the result of a poll
+// predates one.
+let scheduleCount = 0;
+let polling = false;
 
-let pendingEvents = 0;
 const pendingFireTimers: Timer[] = [];
 
 /** Process and run a single ready timer macrotask.
@@ -47,113 +45,69 @@ export function handleTimerMacrotask(): boolean {
   return true;
 }
 
-async function setGlobalTimeout(due: number, now: number): Promise<void> {
-  // Since JS and Rust don't use the same clock, pass the time to rust as a
-  // relative time value. On the Rust side we'll turn that into an absolute
-  // value again.
-  const timeout = due - now;
-  assert(timeout >= 0);
-  // Send message to the backend.
-  globalTimeoutDue = due;
-  pendingEvents++;
-  // FIXME(bartlomieju): this is problematic, because `clearGlobalTimeout`
-  // is synchronous. That means that timer is cancelled, but this promise is still pending
-  // until next turn of event loop. This leads to "leaking of async ops" in tests;
-  // because `clearTimeout/clearInterval` might be the last statement in test function
-  // `opSanitizer` will immediately complain that there is pending op going on, unless
-  // some timeout/defer is put in place to allow promise resolution.
-  // Ideally `clearGlobalTimeout` doesn't return until this op is resolved, but
-  // I'm not if that's possible.
-  await startGlobalTimer(timeout);
-  pendingEvents--;
-  // eslint-disable-next-line @typescript-eslint/no-use-before-define
-  prepareReadyTimers();
-}
-
-function prepareReadyTimers(): void {
-  const now = Date.now();
-  // Bail out if we're not expecting the global timer to fire.
-  if (globalTimeoutDue === null || pendingEvents > 0) {
-    return;
-  }
-  // After firing the timers that are due now, this will hold the first timer
-  // list that hasn't fired yet.
-  let nextDueNode: DueNode | null;
-  while ((nextDueNode = dueTree.min()) !== null && nextDueNode.due <= now) {
-    dueTree.remove(nextDueNode);
-    // Fire all the timers in the list.
-    for (const timer of nextDueNode.timers) {
-      // With the list dropped, the timer is no longer scheduled.
+/** Queues the callbacks of the timers Rust found due, to be fired as
+ * macrotasks. */
+export function queueDue(ids: number[]): void {
+  for (const id of ids) {
+    const timer = idMap.get(id);
+    // The timer may have been cleared after Rust found it due.
+    if (timer === undefined || !timer.scheduled) {
+      continue;
+    }
+    if (!timer.repeat) {
       timer.scheduled = false;
-      // Place the callback to pending timers to fire.
-      pendingFireTimers.push(timer);
+      scheduledCount--;
+    } else if (pendingFireTimers.includes(timer)) {
+      // Intervals stay scheduled in Rust, which already set the next run,
+      // but they don't pile up when their callback couldn't run yet.
+      continue;
     }
+    // Place the callback to pending timers to fire.
+    pendingFireTimers.push(timer);
+  }
+}
+
+async function poll(): Promise<void> {
+  polling = true;
+  // Once the last timer is unscheduled, Rust resolves the pending poll with no
+  // ids, so no op is left behind. With --frozen-time it also does when no
+  // timer is due without the clock moving; scheduling the next timer polls
+  // again, and `Deno.advanceTime()` finds the others due itself.
+  while (scheduledCount > 0) {
+    const before = scheduleCount;
+    const ids = await pollTimers();
+    if (ids.length === 0 && scheduleCount === before) {
+      break;
+    }
+    queueDue(ids);
   }
-  setOrClearGlobalTimeout(nextDueNode && nextDueNode.due, now);
-}
-
-function setOrClearGlobalTimeout(due: number | null, now: number): void {
-  if (due == null) {
-    clearGlobalTimeout();
-  } else {
-    setGlobalTimeout(due, now);
-  }
+  polling = false;
 }
 
-function schedule(timer: Timer, now: number): void {
+function schedule(timer: Timer): void {
   assert(!timer.scheduled);
-  assert(now <= timer.due);
-  // Find or create the list of timers that will fire at point-in-time `due`.
-  const maybeNewDueNode = { due: timer.due, timers: [] };
-  let dueNode = dueTree.find(maybeNewDueNode);
-  if (dueNode === null) {
-    dueTree.insert(maybeNewDueNode);
-    dueNode = maybeNewDueNode;
-  }
-  // Append the newly scheduled timer to the list and mark it as scheduled.
-  dueNode!.timers.push(timer);
+  startTimer(timer.id, timer.delay, timer.repeat);
   timer.scheduled = true;
-  // If the new timer is scheduled to fire before any timer that existed before,
-  // update the global timeout to reflect this.
-  if (globalTimeoutDue === null || globalTimeoutDue > timer.due) {
-    setOrClearGlobalTimeout(timer.due, now);
+  scheduledCount++;
+  scheduleCount++;
+  if (!polling) {
+    poll();
   }
 }
 
 function unschedule(timer: Timer): void {
-  // Check if our timer is pending scheduling or pending firing.
-  // If either is true, they are not in tree, and their idMap entry
-  // will be deleted soon. Remove it from queue.
-  let index = -1;
-  if ((index = pendingFireTimers.indexOf(timer)) >= 0) {
-    pendingFireTimers.splice(index);
-    return;
+  // Check if our timer is pending firing. If it is, its idMap entry will be
+  // deleted soon. Remove it from queue.
+  const index = pendingFireTimers.indexOf(timer);
+  if (index >= 0) {
+    pendingFireTimers.splice(index, 1);
   }
-  // If timer is not in the 2 pending queues and is unscheduled,
-  // it is not in the tree.
   if (!timer.scheduled) {
     return;
   }
-  const searchKey = { due: timer.due, timers: [] };
-  // Find the list of timers that will fire at point-in-time `due`.
-  const list = dueTree.find(searchKey)!.timers;
-  if (list.length === 1) {
-    // Time timer is the only one in the list. Remove the entire list.
-    assert(list[0] === timer);
-    dueTree.remove(searchKey);
-    // If the unscheduled timer was 'next up', find when the next timer that
-    // still exists is due, and update the global alarm accordingly.
-    if (timer.due === globalTimeoutDue) {
-      const nextDueNode: DueNode | null = dueTree.min();
-      setOrClearGlobalTimeout(nextDueNode && nextDueNode.due, Date.now());
-    }
-  } else {
-    // Multiple timers that are due at the same point in time.
-    // Remove this timer from the list.
-    const index = list.indexOf(timer);
-    assert(index > -1);
-    list.splice(index, 1);
-  }
+  cancelTimer(timer.id);
+  timer.scheduled = false;
+  scheduledCount--;
 }
 
 function fire(timer: Timer): void {
@@ -162,16 +116,10 @@ function fire(timer: Timer): void {
   if (!idMap.has(timer.id)) {
     return;
   }
-  // Reschedule the timer if it is a repeating one, otherwise drop it.
+  // One-shot timers are dropped from the id-to-timer map. Repeating ones
+  // were already rescheduled by Rust.
   if (!timer.repeat) {
-    // One-shot timer: remove the timer from this id-to-timer map.
     idMap.delete(timer.id);
-  } else {
-    // Interval timer: compute when timer was supposed to fire next.
-    // However make sure to never schedule the next interval in the past.
-    const now = Date.now();
-    timer.due = Math.max(now, timer.due + timer.delay);
-    schedule(timer, now);
   }
   // Call the user callback. Intermediate assignment is to avoid leaking `this`
   // to it, while also keeping the stack trace neat when it shows up in there.
@@ -205,7 +153,6 @@ function setTimer(
   // In the browser, the delay value must be coercible to an integer between 0
   // and INT32_MAX. Any other value will cause the timer to fire immediately.
   // We emulate this behavior.
-  const now = Date.now();
   if (delay > TIMEOUT_MAX) {
     console.warn(
       `${delay} does not fit into` +
@@ -222,14 +169,13 @@ function setTimer(
     callback,
     args,
     delay,
-    due: now + delay,
     repeat,
     scheduled: false,
   };
   // Register the timer's existence in the id-to-timer map.
   idMap.set(timer.id, timer);
-  // Schedule the timer in the due table.
-  schedule(timer, now);
+  // Schedule the timer in Rust.
+  schedule(timer);
   return timer.id;
 }
 
@@ -6,6 +6,7 @@ import {
   hostPostMessage,
   hostGetMessage,
 } from "../ops/worker_host.ts";
+import { ReceivedResource } from "../ops/resources.ts";
 import { log } from "../util.ts";
 import { TextDecoder, TextEncoder } from "./text_encoding.ts";
 /*
@@ -22,12 +23,14 @@ export interface MessageEventInit extends EventInit {
   data?: any;
   origin?: string;
   lastEventId?: string;
+  resources?: ReceivedResource[];
 }
 
 export class MessageEvent extends Event {
   readonly data: any;
   readonly origin: string;
   readonly lastEventId: string;
+  readonly resources: ReceivedResource[];
 
   constructor(type: string, eventInitDict?: MessageEventInit) {
     super(type, {
@@ -39,6 +42,7 @@ export class MessageEvent extends Event {
     this.data = eventInitDict?.data ?? null;
     this.origin = eventInitDict?.origin ?? "";
     this.lastEventId = eventInitDict?.lastEventId ?? "";
+    this.resources = eventInitDict?.resources ?? [];
   }
 }
 
@@ -62,6 +66,7 @@ interface WorkerHostError {
 interface WorkerHostMessage {
   type: "terminalError" | "error" | "msg";
   data?: any;
+  resources?: ReceivedResource[];
   error?: WorkerHostError;
 }
 
@@ -131,7 +136,7 @@ export class WorkerImpl extends EventTarget implements Worker {
     this.#poll();
   }
 
-  #handleMessage = (msgData: any): void => {
+  #handleMessage = (msgData: any, resources: ReceivedResource[]): void => {
     let data;
     try {
       data = decodeMessage(new Uint8Array(msgData));
@@ -149,6 +154,7 @@ export class WorkerImpl extends EventTarget implements Worker {
     const msgEvent = new MessageEvent("message", {
       cancelable: false,
       data,
+      resources,
     });
 
     if (this.onmessage) {
@@ -201,7 +207,7 @@ export class WorkerImpl extends EventTarget implements Worker {
       }
 
       if (type === "msg") {
-        this.#handleMessage(event.data);
+        this.#handleMessage(event.data, event.resources!);
         continue;
       }
 
@@ -223,17 +229,22 @@ export class WorkerImpl extends EventTarget implements Worker {
   };
 
   postMessage(message: any, transferOrOptions?: any): void {
-    if (transferOrOptions) {
-      throw new Error(
-        "Not yet implemented: `transfer` and `options` are not supported."
-      );
+    if (
+      transferOrOptions &&
+      (Array.isArray(transferOrOptions) || transferOrOptions.transfer)
+    ) {
+      throw new Error("Not yet implemented: `transfer` is not supported.");
     }
 
     if (this.#terminated) {
       return;
     }
 
-    hostPostMessage(this.#id, encodeMessage(message));
+    hostPostMessage(
+      this.#id,
+      encodeMessage(message),
+      transferOrOptions?.resources
+    );
   }
 
   terminate(): void {
@@ -0,0 +1,224 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Serialization of fatal errors for `--json-errors`.
+//!
+//! Instead of the colored text meant for terminals, the error is written to
+//! stderr as a single line of JSON that tools can parse. The schema is stable;
+//! fields may be added but are never renamed or removed:
+//!
+//!   {
+//!     "kind": "JSError" | "Diagnostic" | "OpError" | "Other",
+//!     "name": "PermissionDenied",      // only for "OpError"
+//!     "message": "Uncaught Error: boom",
+//!     "codeFrame": {                   // null if the location is unknown
+//!       "fileName": "file:///main.ts",
+//!       "lineNumber": 1,
+//!       "startColumn": 7,
+//!       "endColumn": 8,
+//!       "sourceLine": "throw new Error(\"boom\");"
+//!     },
+//!     "stack": [{ "functionName": "foo", "fileName": "file:///main.ts",
+//!                 "lineNumber": 1, "columnNumber": 7, "isAsync": false }],
+//!     "diagnostics": [{ "code": 2322, "category": "error",
+//!                       "message": "...", "codeFrame": { ... } }]
+//!   }
+//!
+//! `stack` is only present for "JSError" and `diagnostics` only for
+//! "Diagnostic". Line and column numbers are 1-based, and locations are
+//! already mapped back to the original sources.
+
+use crate::colors;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticCategory;
+use crate::diagnostics::DiagnosticItem;
+use crate::fmt_errors::JSError;
+use crate::op_error::OpError;
+use deno_core::ErrBox;
+use serde_json::json;
+use serde_json::Value;
+
+pub fn to_json(err: &ErrBox) -> Value {
+  if let Some(js_error) = err.downcast_ref::<JSError>() {
+    js_error_to_json(js_error)
+  } else if let Some(js_error) = err.downcast_ref::<deno_core::JSError>() {
+    js_error_to_json(js_error)
+  } else if let Some(diagnostic) = err.downcast_ref::<Diagnostic>() {
+    diagnostic_to_json(diagnostic)
+  } else if let Some(op_error) = err.downcast_ref::<OpError>() {
+    json!({
+      "kind": "OpError",
+      "name": op_error
+        .class_name
+        .clone()
+        .unwrap_or_else(|| format!("{:?}", op_error.kind)),
+      "message": op_error.msg,
+      "codeFrame": null,
+    })
+  } else {
+    json!({
+      "kind": "Other",
+      "message": colors::strip_ansi_codes(&err.to_string()),
+      "codeFrame": null,
+    })
+  }
+}
+
+fn code_frame(
+  file_name: &Option<String>,
+  line_number: Option<i64>,
+  start_column: Option<i64>,
+  end_column: Option<i64>,
+  source_line: &Option<String>,
+) -> Value {
+  match (file_name, line_number, start_column) {
+    (Some(file_name), Some(line_number), Some(start_column)) => json!({
+      "fileName": file_name,
+      "lineNumber": line_number,
+      "startColumn": start_column + 1,
+      "endColumn": end_column.map(|c| c + 1),
+      "sourceLine": source_line,
+    }),
+    _ => Value::Null,
+  }
+}
+
+fn js_error_to_json(js_error: &deno_core::JSError) -> Value {
+  let stack: Vec<Value> = js_error
+    .frames
+    .iter()
+    .map(|frame| {
+      json!({
+        "functionName": frame.function_name,
+        "fileName": frame.file_name,
+        "lineNumber": frame.line_number,
+        "columnNumber": frame.column_number,
+        "isAsync": frame.is_async,
+      })
+    })
+    .collect();
+  json!({
+    "kind": "JSError",
+    "message": js_error.message,
+    "codeFrame": code_frame(
+      &js_error.script_resource_name,
+      js_error.line_number,
+      js_error.start_column,
+      js_error.end_column,
+      &js_error.source_line,
+    ),
+    "stack": stack,
+  })
+}
+
+fn diagnostic_item_to_json(item: &DiagnosticItem) -> Value {
+  let message = match &item.message_chain {
+    Some(chain) => chain.format_message(0).trim_end().to_string(),
+    None => item.message.clone(),
+  };
+  let category = match item.category {
+    DiagnosticCategory::Log => "log",
+    DiagnosticCategory::Debug => "debug",
+    DiagnosticCategory::Info => "info",
+    DiagnosticCategory::Error => "error",
+    DiagnosticCategory::Warning => "warning",
+    DiagnosticCategory::Suggestion => "suggestion",
+  };
+  json!({
+    "code": item.code,
+    "category": category,
+    "message": message,
+    // The compiler counts lines from 0.
+    "codeFrame": code_frame(
+      &item.script_resource_name,
+      item.line_number.map(|n| n + 1),
+      item.start_column,
+      item.end_column,
+      &item.source_line,
+    ),
+  })
+}
+
+fn diagnostic_to_json(diagnostic: &Diagnostic) -> Value {
+  let items: Vec<Value> = diagnostic
+    .items
+    .iter()
+    .map(diagnostic_item_to_json)
+    .collect();
+  json!({
+    "kind": "Diagnostic",
+    "message": items.first().map(|item| item["message"].clone()),
+    "codeFrame": items.first().map(|item| item["codeFrame"].clone()),
+    "diagnostics": items,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use deno_core::JSStackFrame;
+
+  #[test]
+  fn js_error_json() {
+    let err = ErrBox::from(deno_core::JSError {
+      message: "Uncaught Error: boom".to_string(),
+      name: Some("Error".to_string()),
+      source_line: Some("throw new Error(\"boom\");".to_string()),
+      script_resource_name: Some("file:///main.ts".to_string()),
+      line_number: Some(1),
+      start_column: Some(6),
+      end_column: Some(7),
+      frames: vec![JSStackFrame {
+        type_name: None,
+        function_name: None,
+        method_name: None,
+        file_name: Some("file:///main.ts".to_string()),
+        line_number: Some(1),
+        column_number: Some(7),
+        eval_origin: None,
+        is_top_level: Some(true),
+        is_eval: false,
+        is_native: false,
+        is_constructor: false,
+        is_async: false,
+        is_promise_all: false,
+        promise_index: None,
+      }],
+      formatted_frames: vec![],
+    });
+    assert_eq!(
+      to_json(&err),
+      json!({
+        "kind": "JSError",
+        "message": "Uncaught Error: boom",
+        "codeFrame": {
+          "fileName": "file:///main.ts",
+          "lineNumber": 1,
+          "startColumn": 7,
+          "endColumn": 8,
+          "sourceLine": "throw new Error(\"boom\");",
+        },
+        "stack": [{
+          "functionName": null,
+          "fileName": "file:///main.ts",
+          "lineNumber": 1,
+          "columnNumber": 7,
+          "isAsync": false,
+        }],
+      })
+    );
+  }
+
+  #[test]
+  fn op_error_json() {
+    let err = ErrBox::from(OpError::permission_denied("denied".to_string()));
+    assert_eq!(
+      to_json(&err),
+      json!({
+        "kind": "OpError",
+        "name": "PermissionDenied",
+        "message": "denied",
+        "codeFrame": null,
+      })
+    );
+  }
+}
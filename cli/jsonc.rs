@@ -0,0 +1,172 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! JSON with comments, as found in `tsconfig.json` and `deno.json` files.
+//!
+//! The tokenizer keeps comments and line breaks so that `deno fmt` can print
+//! them back, and `strip_json_comments` turns such a document into strict
+//! JSON for `serde_json`.
+
+#[derive(Debug, PartialEq)]
+pub enum JsonTokenKind<'a> {
+  Punct(char),
+  /// Strings, numbers and keywords.
+  Literal(&'a str),
+  LineComment(&'a str),
+  BlockComment(&'a str),
+}
+
+pub struct JsonToken<'a> {
+  pub kind: JsonTokenKind<'a>,
+  /// Byte offset of the token in the text.
+  pub offset: usize,
+  /// Line breaks between the previous token and this one.
+  pub newlines_before: usize,
+}
+
+/// Splits a JSON document into tokens, comments included.
+pub fn tokenize_json(text: &str) -> Result<Vec<JsonToken>, String> {
+  let bytes = text.as_bytes();
+  let mut tokens = vec![];
+  let mut newlines_before = 0;
+  let mut i = 0;
+  while i < bytes.len() {
+    let start = i;
+    let kind = match bytes[i] {
+      b'\n' => {
+        newlines_before += 1;
+        i += 1;
+        continue;
+      }
+      b' ' | b'\t' | b'\r' => {
+        i += 1;
+        continue;
+      }
+      b'{' | b'}' | b'[' | b']' | b':' | b',' => {
+        i += 1;
+        JsonTokenKind::Punct(bytes[start] as char)
+      }
+      b'"' => {
+        i += 1;
+        loop {
+          match bytes.get(i) {
+            Some(b'\\') => i += 2,
+            Some(b'"') => break,
+            Some(b'\n') | None => {
+              return Err(json_error(text, start, "Unterminated string"))
+            }
+            Some(_) => i += 1,
+          }
+        }
+        i += 1;
+        JsonTokenKind::Literal(&text[start..i])
+      }
+      b'/' if bytes.get(i + 1) == Some(&b'/') => {
+        while i < bytes.len() && bytes[i] != b'\n' {
+          i += 1;
+        }
+        JsonTokenKind::LineComment(text[start..i].trim_end())
+      }
+      b'/' if bytes.get(i + 1) == Some(&b'*') => {
+        match text[i + 2..].find("*/") {
+          Some(end) => i += end + 4,
+          None => return Err(json_error(text, start, "Unterminated comment")),
+        }
+        JsonTokenKind::BlockComment(&text[start..i])
+      }
+      _ => {
+        while i < bytes.len() && !b" \t\r\n{}[]:,\"/".contains(&bytes[i]) {
+          i += 1;
+        }
+        if i == start {
+          return Err(json_error(text, start, "Unexpected character"));
+        }
+        JsonTokenKind::Literal(&text[start..i])
+      }
+    };
+    tokens.push(JsonToken {
+      kind,
+      offset: start,
+      newlines_before,
+    });
+    newlines_before = 0;
+  }
+  Ok(tokens)
+}
+
+/// Formats `msg` with the line and column of `offset` in `text`.
+pub fn json_error(text: &str, offset: usize, msg: &str) -> String {
+  let before = &text[..offset];
+  let line = before.matches('\n').count() + 1;
+  let column = offset - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+  format!("{} at {}:{}", msg, line, column)
+}
+
+fn is_json_comment(kind: &JsonTokenKind) -> bool {
+  match kind {
+    JsonTokenKind::LineComment(_) | JsonTokenKind::BlockComment(_) => true,
+    _ => false,
+  }
+}
+
+/// Removes comments and trailing commas, which `tsconfig.json` files may
+/// contain, so that the document can be read by a strict JSON parser.
+///
+/// Line breaks are kept so that parse errors point at the right line.
+pub fn strip_json_comments(text: &str) -> Result<String, String> {
+  let tokens = tokenize_json(text)?;
+  let mut out = String::new();
+  for (i, token) in tokens.iter().enumerate() {
+    for _ in 0..token.newlines_before {
+      out.push('\n');
+    }
+    match token.kind {
+      JsonTokenKind::LineComment(_) => {}
+      JsonTokenKind::BlockComment(comment) => {
+        for _ in comment.matches('\n') {
+          out.push('\n');
+        }
+      }
+      JsonTokenKind::Punct(',') => {
+        let next = tokens[i + 1..]
+          .iter()
+          .map(|t| &t.kind)
+          .find(|kind| !is_json_comment(kind));
+        if next != Some(&JsonTokenKind::Punct('}'))
+          && next != Some(&JsonTokenKind::Punct(']'))
+        {
+          out.push(',');
+        }
+      }
+      JsonTokenKind::Punct(c) => out.push(c),
+      JsonTokenKind::Literal(literal) => out.push_str(literal),
+    }
+    out.push(' ');
+  }
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_strip_json_comments() {
+    let stripped = strip_json_comments(
+      r#"// config
+{
+  "compilerOptions": { /* inline */
+    "lib": ["dom", "esnext",], // trailing
+  },
+}"#,
+    )
+    .unwrap();
+    let value: serde_json::Value = serde_json::from_str(&stripped).unwrap();
+    assert_eq!(
+      value,
+      serde_json::json!({ "compilerOptions": { "lib": ["dom", "esnext"] } })
+    );
+    assert_eq!(stripped.matches('\n').count(), 5);
+    assert_eq!(strip_json_comments("\"a, // b\"").unwrap(), "\"a, // b\" ");
+    assert!(strip_json_comments("/* open").is_err());
+  }
+}
@@ -0,0 +1,1155 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+#![deny(warnings)]
+
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate log;
+extern crate futures;
+#[macro_use]
+extern crate serde_json;
+extern crate clap;
+extern crate deno_core;
+extern crate indexmap;
+#[cfg(unix)]
+extern crate nix;
+extern crate rand;
+extern crate regex;
+extern crate reqwest;
+extern crate serde;
+extern crate serde_derive;
+extern crate tokio;
+extern crate url;
+
+mod bench_runner;
+mod checksum;
+pub mod colors;
+mod config_file;
+mod coverage;
+mod cpu_profiler;
+mod crash_report;
+pub mod deno_dir;
+pub mod diagnostics;
+mod disk_cache;
+mod doc;
+mod env_file;
+mod fetch_cache;
+mod file_fetcher;
+mod file_watcher;
+pub mod flags;
+mod fmt;
+pub mod fmt_errors;
+mod fs;
+// cargo-fuzz builds with `--cfg fuzzing`; other builds leave the entry points
+// out.
+#[cfg(any(fuzzing, test))]
+pub mod fuzzing;
+pub mod global_state;
+mod heap_snapshot;
+pub mod http_cache;
+mod http_util;
+mod import_map;
+mod inspector;
+pub mod installer;
+mod js;
+mod json_errors;
+mod jsonc;
+mod lint;
+mod lockfile;
+mod log_category;
+mod metrics;
+mod module_graph;
+pub mod msg;
+mod npm;
+pub mod op_error;
+mod op_tracer;
+pub mod ops;
+pub mod permissions;
+mod repl;
+mod repl_debugger;
+pub mod resolve_addr;
+pub mod signal;
+pub mod source_maps;
+mod startup_data;
+pub mod state;
+mod swc_util;
+mod task_runner;
+mod test_reporter;
+mod test_runner;
+pub mod test_util;
+mod timer_wheel;
+mod tokio_util;
+mod tsc;
+mod upgrade;
+pub mod version;
+mod wasm;
+mod web_worker;
+pub mod worker;
+mod xeval;
+
+pub use dprint_plugin_typescript::swc_common;
+pub use dprint_plugin_typescript::swc_ecma_ast;
+pub use dprint_plugin_typescript::swc_ecma_parser;
+
+use crate::doc::parser::DocFileLoader;
+use crate::file_fetcher::SourceFile;
+use crate::file_fetcher::SourceFileFetcher;
+use crate::fs as deno_fs;
+use crate::global_state::GlobalState;
+use crate::module_graph::ModuleGraphLoader;
+use crate::msg::MediaType;
+use crate::op_error::ModuleNotFoundError;
+use crate::op_error::OpError;
+use crate::permissions::Permissions;
+use crate::state::exit_unstable;
+use crate::tsc::TargetLib;
+use crate::worker::MainWorker;
+use deno_core::v8_set_flags;
+use deno_core::ErrBox;
+use deno_core::EsIsolate;
+use deno_core::ModuleResolutionError;
+use deno_core::ModuleSpecifier;
+use flags::DenoSubcommand;
+use flags::Flags;
+use flags::TestReporter;
+use futures::future::FutureExt;
+use futures::Future;
+use log::Level;
+use log::Metadata;
+use log::Record;
+use std::env;
+use std::io::Read;
+use std::io::Write;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use upgrade::upgrade_command;
+use url::Url;
+
+static LOGGER: Logger = Logger;
+
+// TODO(ry) Switch to env_logger or other standard crate.
+struct Logger;
+
+impl log::Log for Logger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    log_category::enabled(metadata)
+  }
+
+  fn log(&self, record: &Record) {
+    if self.enabled(record.metadata()) {
+      let mut target = record.target().to_string();
+
+      if let Some(line_no) = record.line() {
+        target.push_str(":");
+        target.push_str(&line_no.to_string());
+      }
+
+      if record.level() <= Level::Info {
+        eprintln!("{}", record.args());
+      } else {
+        eprintln!("{} RS - {} - {}", record.level(), target, record.args());
+      }
+    }
+  }
+  fn flush(&self) {}
+}
+
+fn write_to_stdout_ignore_sigpipe(bytes: &[u8]) -> Result<(), std::io::Error> {
+  use std::io::ErrorKind;
+
+  match std::io::stdout().write_all(bytes) {
+    Ok(()) => Ok(()),
+    Err(e) => match e.kind() {
+      ErrorKind::BrokenPipe => Ok(()),
+      _ => Err(e),
+    },
+  }
+}
+
+fn write_lockfile(global_state: GlobalState) -> Result<(), std::io::Error> {
+  if global_state.flags.lock_write {
+    if let Some(ref lockfile) = global_state.lockfile {
+      let g = lockfile.lock().unwrap();
+      g.write()?;
+    } else {
+      eprintln!("--lock flag must be specified when using --lock-write");
+      std::process::exit(11);
+    }
+  }
+  Ok(())
+}
+
+fn print_cache_info(state: &GlobalState) {
+  println!(
+    "{} {:?}",
+    colors::bold("DENO_DIR location:".to_string()),
+    state.dir.root
+  );
+  println!(
+    "{} {:?}",
+    colors::bold("Remote modules cache:".to_string()),
+    state.file_fetcher.http_cache.location
+  );
+  println!(
+    "{} {:?}",
+    colors::bold("TypeScript compiler cache:".to_string()),
+    state.dir.gen_cache.location
+  );
+}
+
+// TODO(bartlomieju): this function de facto repeats
+// whole compilation stack. Can this be done better somehow?
+async fn print_file_info(
+  worker: &MainWorker,
+  module_specifier: ModuleSpecifier,
+) -> Result<(), ErrBox> {
+  let global_state = worker.state.borrow().global_state.clone();
+
+  let out = global_state
+    .file_fetcher
+    .fetch_source_file(&module_specifier, None, Permissions::allow_all())
+    .await?;
+
+  println!(
+    "{} {}",
+    colors::bold("local:".to_string()),
+    out.filename.to_str().unwrap()
+  );
+
+  println!(
+    "{} {}",
+    colors::bold("type:".to_string()),
+    msg::enum_name_media_type(out.media_type)
+  );
+
+  let module_specifier_ = module_specifier.clone();
+
+  global_state
+    .prepare_module_load(
+      module_specifier_.clone(),
+      None,
+      TargetLib::Main,
+      Permissions::allow_all(),
+      false,
+      global_state.maybe_import_map.clone(),
+    )
+    .await?;
+  global_state
+    .clone()
+    .fetch_compiled_module(module_specifier_, None)
+    .await?;
+
+  if out.media_type == msg::MediaType::TypeScript
+    || (out.media_type == msg::MediaType::JavaScript
+      && global_state.ts_compiler.compile_js)
+  {
+    let compiled_source_file = global_state
+      .ts_compiler
+      .get_compiled_source_file(&out.url)
+      .unwrap();
+
+    println!(
+      "{} {}",
+      colors::bold("compiled:".to_string()),
+      compiled_source_file.filename.to_str().unwrap(),
+    );
+  }
+
+  if let Ok(source_map) = global_state
+    .clone()
+    .ts_compiler
+    .get_source_map_file(&module_specifier)
+  {
+    println!(
+      "{} {}",
+      colors::bold("map:".to_string()),
+      source_map.filename.to_str().unwrap()
+    );
+  }
+
+  let es_state_rc = EsIsolate::state(&worker.isolate);
+  let es_state = es_state_rc.borrow();
+
+  if let Some(deps) = es_state.modules.deps(&module_specifier) {
+    println!("{}{}", colors::bold("deps:\n".to_string()), deps.name);
+    if let Some(ref depsdeps) = deps.deps {
+      for d in depsdeps {
+        println!("{}", d);
+      }
+    }
+  } else {
+    println!(
+      "{} cannot retrieve full dependency graph",
+      colors::bold("deps:".to_string()),
+    );
+  }
+
+  Ok(())
+}
+
+fn get_types(unstable: bool) -> String {
+  if unstable {
+    format!(
+      "{}\n{}\n{}\n{}",
+      crate::js::DENO_NS_LIB,
+      crate::js::SHARED_GLOBALS_LIB,
+      crate::js::WINDOW_LIB,
+      crate::js::UNSTABLE_NS_LIB,
+    )
+  } else {
+    format!(
+      "{}\n{}\n{}",
+      crate::js::DENO_NS_LIB,
+      crate::js::SHARED_GLOBALS_LIB,
+      crate::js::WINDOW_LIB,
+    )
+  }
+}
+
+async fn info_command(
+  flags: Flags,
+  file: Option<String>,
+) -> Result<(), ErrBox> {
+  let global_state = GlobalState::new(flags)?;
+  // If it was just "deno info" print location of caches and exit
+  if file.is_none() {
+    print_cache_info(&global_state);
+    return Ok(());
+  }
+
+  let main_module = ModuleSpecifier::resolve_url_or_path(&file.unwrap())?;
+  let mut worker = MainWorker::create(global_state, main_module.clone())?;
+  worker.preload_module(&main_module).await?;
+  print_file_info(&worker, main_module.clone()).await
+}
+
+async fn install_command(
+  flags: Flags,
+  module_url: String,
+  args: Vec<String>,
+  name: Option<String>,
+  root: Option<PathBuf>,
+  force: bool,
+) -> Result<(), ErrBox> {
+  // Firstly fetch and compile module, this step ensures that module exists.
+  let mut fetch_flags = flags.clone();
+  fetch_flags.reload = true;
+  let global_state = GlobalState::new(fetch_flags)?;
+  let main_module = ModuleSpecifier::resolve_url_or_path(&module_url)?;
+  let mut worker = MainWorker::create(global_state, main_module.clone())?;
+  worker.preload_module(&main_module).await?;
+  installer::install(flags, &module_url, args, name, root, force)
+    .map_err(ErrBox::from)
+}
+
+async fn cache_command(flags: Flags, files: Vec<String>) -> Result<(), ErrBox> {
+  let main_module =
+    ModuleSpecifier::resolve_url_or_path("./__$deno$fetch.ts").unwrap();
+  let global_state = GlobalState::new(flags)?;
+  let mut worker =
+    MainWorker::create(global_state.clone(), main_module.clone())?;
+
+  for file in files {
+    let specifier = ModuleSpecifier::resolve_url_or_path(&file)?;
+    worker.preload_module(&specifier).await.map(|_| ())?;
+  }
+
+  write_lockfile(global_state)?;
+
+  Ok(())
+}
+
+async fn eval_command(
+  flags: Flags,
+  code: String,
+  as_typescript: bool,
+) -> Result<(), ErrBox> {
+  // Force TypeScript compile.
+  let main_module =
+    ModuleSpecifier::resolve_url_or_path("./__$deno$eval.ts").unwrap();
+  let global_state = GlobalState::new(flags)?;
+  let mut worker = MainWorker::create(global_state, main_module.clone())?;
+  let main_module_url = main_module.as_url().to_owned();
+  // Create a dummy source file.
+  let source_file = SourceFile {
+    filename: main_module_url.to_file_path().unwrap(),
+    url: main_module_url,
+    types_url: None,
+    types_header: None,
+    media_type: if as_typescript {
+      MediaType::TypeScript
+    } else {
+      MediaType::JavaScript
+    },
+    source_code: code.clone().into_bytes(),
+  };
+  // Save our fake file into file fetcher cache
+  // to allow module access by TS compiler (e.g. op_fetch_source_files)
+  worker
+    .state
+    .borrow()
+    .global_state
+    .file_fetcher
+    .save_source_file_in_cache(&main_module, source_file);
+  debug!("main_module {}", &main_module);
+  worker.execute_module(&main_module).await?;
+  worker.execute("window.dispatchEvent(new Event('load'))")?;
+  (&mut *worker).await?;
+  worker.execute("window.dispatchEvent(new Event('unload'))")?;
+  Ok(())
+}
+
+async fn bundle_command(
+  flags: Flags,
+  source_file: String,
+  out_file: Option<PathBuf>,
+) -> Result<(), ErrBox> {
+  let mut module_specifier =
+    ModuleSpecifier::resolve_url_or_path(&source_file)?;
+  let url = module_specifier.as_url();
+
+  // TODO(bartlomieju): fix this hack in ModuleSpecifier
+  if url.scheme() == "file" {
+    let a = deno_fs::normalize_path(&url.to_file_path().unwrap());
+    let u = Url::from_file_path(a).unwrap();
+    module_specifier = ModuleSpecifier::from(u)
+  }
+
+  debug!(">>>>> bundle START");
+  let compiler_config = tsc::CompilerConfig::load(flags.config_path.clone())?;
+
+  let global_state = GlobalState::new(flags)?;
+
+  info!("Bundling {}", module_specifier.to_string());
+
+  let output = tsc::bundle(
+    &global_state,
+    compiler_config,
+    module_specifier,
+    global_state.maybe_import_map.clone(),
+    global_state.flags.unstable,
+  )
+  .await?;
+
+  debug!(">>>>> bundle END");
+
+  let output_string = fmt::format_text(&output)?;
+
+  if let Some(out_file_) = out_file.as_ref() {
+    info!("Emitting bundle to {:?}", out_file_);
+    let output_bytes = output_string.as_bytes();
+    let output_len = output_bytes.len();
+    deno_fs::write_file(out_file_, output_bytes, 0o666)?;
+    info!("{} emitted.", human_size(output_len as f64));
+  } else {
+    println!("{}", output_string);
+  }
+  Ok(())
+}
+
+fn human_size(bytse: f64) -> String {
+  let negative = if bytse.is_sign_positive() { "" } else { "-" };
+  let bytse = bytse.abs();
+  let units = ["Bytes", "KB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
+  if bytse < 1_f64 {
+    return format!("{}{} {}", negative, bytse, "Bytes");
+  }
+  let delimiter = 1024_f64;
+  let exponent = std::cmp::min(
+    (bytse.ln() / delimiter.ln()).floor() as i32,
+    (units.len() - 1) as i32,
+  );
+  let pretty_bytes = format!("{:.2}", bytse / delimiter.powi(exponent))
+    .parse::<f64>()
+    .unwrap()
+    * 1_f64;
+  let unit = units[exponent as usize];
+  format!("{}{} {}", negative, pretty_bytes, unit)
+}
+
+#[test]
+fn human_size_test() {
+  assert_eq!(human_size(16_f64), "16 Bytes");
+  assert_eq!(human_size((16 * 1024) as f64), "16 KB");
+  assert_eq!(human_size((16 * 1024 * 1024) as f64), "16 MB");
+  assert_eq!(human_size(16_f64 * 1024_f64.powf(3.0)), "16 GB");
+  assert_eq!(human_size(16_f64 * 1024_f64.powf(4.0)), "16 TB");
+  assert_eq!(human_size(16_f64 * 1024_f64.powf(5.0)), "16 PB");
+  assert_eq!(human_size(16_f64 * 1024_f64.powf(6.0)), "16 EB");
+  assert_eq!(human_size(16_f64 * 1024_f64.powf(7.0)), "16 ZB");
+  assert_eq!(human_size(16_f64 * 1024_f64.powf(8.0)), "16 YB");
+}
+
+async fn doc_command(
+  flags: Flags,
+  source_file: Option<String>,
+  json: bool,
+  maybe_filter: Option<String>,
+) -> Result<(), ErrBox> {
+  let global_state = GlobalState::new(flags.clone())?;
+  let source_file = source_file.unwrap_or_else(|| "--builtin".to_string());
+
+  impl DocFileLoader for SourceFileFetcher {
+    fn load_source_code(
+      &self,
+      specifier: &str,
+    ) -> Pin<Box<dyn Future<Output = Result<String, OpError>>>> {
+      let fetcher = self.clone();
+      let specifier = specifier.to_string();
+
+      async move {
+        let specifier = ModuleSpecifier::resolve_url_or_path(&specifier)?;
+        let source_file = fetcher
+          .fetch_source_file(&specifier, None, Permissions::allow_all())
+          .await?;
+        String::from_utf8(source_file.source_code)
+          .map_err(|_| OpError::other("failed to parse".to_string()))
+      }
+      .boxed_local()
+    }
+  }
+
+  let loader = Box::new(global_state.file_fetcher.clone());
+  let doc_parser = doc::DocParser::new(loader);
+
+  let parse_result = if source_file == "--builtin" {
+    doc_parser.parse_source("lib.deno.d.ts", get_types(flags.unstable).as_str())
+  } else {
+    let module_specifier = ModuleSpecifier::resolve_url_or_path(&source_file)?;
+    doc_parser
+      .parse_with_reexports(&module_specifier.to_string())
+      .await
+  };
+
+  let doc_nodes = match parse_result {
+    Ok(nodes) => nodes,
+    Err(e) => {
+      eprintln!("{}", e);
+      std::process::exit(1);
+    }
+  };
+
+  if json {
+    let writer = std::io::BufWriter::new(std::io::stdout());
+    serde_json::to_writer_pretty(writer, &doc_nodes).map_err(ErrBox::from)
+  } else {
+    let details = if let Some(filter) = maybe_filter {
+      let node = doc::find_node_by_name_recursively(doc_nodes, filter.clone());
+      if let Some(node) = node {
+        doc::printer::format_details(node)
+      } else {
+        eprintln!("Node {} was not found!", filter);
+        std::process::exit(1);
+      }
+    } else {
+      doc::printer::format(doc_nodes)
+    };
+
+    write_to_stdout_ignore_sigpipe(details.as_bytes()).map_err(ErrBox::from)
+  }
+}
+
+async fn run_repl(flags: Flags) -> Result<(), ErrBox> {
+  let main_module =
+    ModuleSpecifier::resolve_url_or_path("./__$deno$repl.ts").unwrap();
+  let global_state = GlobalState::new(flags)?;
+  let interrupt_target = Arc::new(Mutex::new(None));
+  repl::interrupt_on_ctrl_c(interrupt_target.clone());
+  // The REPL exits the process itself, so the event loop only finishes when
+  // `.clear` asks for a new isolate with a fresh global scope.
+  loop {
+    let mut worker =
+      MainWorker::create(global_state.clone(), main_module.clone())?;
+    *interrupt_target.lock().unwrap() = Some(worker.isolate.interrupt_handle());
+    (&mut *worker).await?;
+  }
+}
+
+/// Pseudo file name of a program piped into `deno run -`.
+const STDIN_MODULE: &str = "./$deno$stdin.ts";
+
+async fn run_command(flags: Flags, script: String) -> Result<(), ErrBox> {
+  if script == "-" {
+    return run_from_stdin(flags).await;
+  }
+  let main_module = ModuleSpecifier::resolve_url_or_path(&script).unwrap();
+  if flags.watch {
+    return run_with_watch(flags, main_module).await;
+  }
+  let global_state = GlobalState::new(flags)?;
+  run_main_module(global_state, main_module).await
+}
+
+async fn run_main_module(
+  global_state: GlobalState,
+  main_module: ModuleSpecifier,
+) -> Result<(), ErrBox> {
+  let worker = MainWorker::create(global_state.clone(), main_module.clone())?;
+  run_worker(global_state, worker, main_module).await
+}
+
+/// Runs `main_module` in `worker`, which was created for it.
+async fn run_worker(
+  global_state: GlobalState,
+  mut worker: MainWorker,
+  main_module: ModuleSpecifier,
+) -> Result<(), ErrBox> {
+  debug!("main_module {}", main_module);
+  // Declared after the worker, so that it's dropped before its inspector.
+  let mut cpu_profiler = start_cpu_profiler(&global_state.flags, &mut worker)?;
+  let result = async {
+    worker.execute_module(&main_module).await?;
+    write_lockfile(global_state.clone())?;
+    worker.execute("window.dispatchEvent(new Event('load'))")?;
+    (&mut *worker).await?;
+    worker.execute("window.dispatchEvent(new Event('unload'))")
+  }
+  .await;
+  // The profile is written even if the program failed.
+  write_cpu_profile(&global_state, cpu_profiler.as_mut())?;
+  result?;
+  if global_state.flags.strict_resources {
+    worker.check_leaked_resources()?;
+  }
+  Ok(())
+}
+
+/// Starts profiling `worker` if `--prof` was given.
+fn start_cpu_profiler(
+  flags: &Flags,
+  worker: &mut MainWorker,
+) -> Result<Option<cpu_profiler::CpuProfiler>, ErrBox> {
+  if flags.prof.is_none() {
+    return Ok(None);
+  }
+  let inspector = worker.inspector.as_mut().unwrap();
+  let mut cpu_profiler = cpu_profiler::CpuProfiler::new(inspector);
+  cpu_profiler.start()?;
+  Ok(Some(cpu_profiler))
+}
+
+fn write_cpu_profile(
+  global_state: &GlobalState,
+  cpu_profiler: Option<&mut cpu_profiler::CpuProfiler>,
+) -> Result<(), ErrBox> {
+  if let Some(cpu_profiler) = cpu_profiler {
+    let profile = cpu_profiler.stop(&global_state.ts_compiler)?;
+    let path = global_state.flags.prof.as_ref().unwrap();
+    cpu_profiler::write(&test_runner::shard_output_path(path), &profile)?;
+  }
+  Ok(())
+}
+
+/// Runs a program read from stdin.
+///
+/// The program is given a pseudo-URL in the current directory, so that it
+/// can import local modules by relative paths and shows up under a stable
+/// name in stack traces. Its compiled output isn't kept in the cache, since
+/// the next program piped in will have the same URL.
+async fn run_from_stdin(flags: Flags) -> Result<(), ErrBox> {
+  if flags.watch {
+    return Err(
+      OpError::other(
+        "--watch can't be used with a program read from stdin".to_string(),
+      )
+      .into(),
+    );
+  }
+  let mut source_code = Vec::new();
+  std::io::stdin().read_to_end(&mut source_code)?;
+
+  let main_module = ModuleSpecifier::resolve_url_or_path(STDIN_MODULE).unwrap();
+  let main_module_url = main_module.as_url().to_owned();
+  let global_state = GlobalState::new(flags)?;
+  let source_file = SourceFile {
+    filename: main_module_url.to_file_path().unwrap(),
+    url: main_module_url.clone(),
+    types_url: None,
+    types_header: None,
+    media_type: MediaType::TypeScript,
+    source_code,
+  };
+  global_state
+    .file_fetcher
+    .save_source_file_in_cache(&main_module, source_file);
+  global_state.ts_compiler.keep_in_memory(&main_module_url);
+  run_main_module(global_state, main_module).await
+}
+
+/// Runs `main_module` and restarts it whenever one of its local files
+/// changes.
+///
+/// Every run gets a new global state, so that the in-memory caches of the
+/// previous run don't hide the changed files.
+async fn run_with_watch(
+  flags: Flags,
+  main_module: ModuleSpecifier,
+) -> Result<(), ErrBox> {
+  loop {
+    let global_state = GlobalState::new(flags.clone())?;
+    let mut module_graph_loader = ModuleGraphLoader::new(
+      global_state.file_fetcher.clone(),
+      global_state.maybe_import_map.clone(),
+      global_state.permissions.clone(),
+      false,
+      false,
+    );
+    let result = module_graph_loader.add_to_graph(&main_module, None).await;
+    // Files that failed to parse are fetched too, so fixing them restarts
+    // the program.
+    let mut paths = global_state.file_fetcher.fetched_local_files();
+    if let Ok(path) = main_module.as_url().to_file_path() {
+      if !paths.contains(&path) {
+        paths.push(path);
+      }
+    }
+
+    match result {
+      Ok(()) => {
+        let worker =
+          MainWorker::create(global_state.clone(), main_module.clone())?;
+        let isolate_handle = worker.isolate.thread_safe_handle();
+        let fut =
+          run_worker(global_state, worker, main_module.clone()).boxed_local();
+        file_watcher::run_until_change(&paths, fut, isolate_handle).await?;
+      }
+      Err(err) => {
+        eprintln!("{}: {}", colors::red_bold("error".to_string()), err);
+        file_watcher::wait_for_change(&paths).await?;
+      }
+    }
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn test_command(
+  flags: Flags,
+  include: Option<Vec<String>>,
+  fail_fast: bool,
+  quiet: bool,
+  allow_none: bool,
+  filter: Option<String>,
+  jobs: usize,
+  reporter: TestReporter,
+) -> Result<(), ErrBox> {
+  let cwd = std::env::current_dir()?;
+  let include = include.unwrap_or_else(|| vec![".".to_string()]);
+  let test_modules = test_runner::prepare_test_modules_urls(include, &cwd)?;
+
+  if test_modules.is_empty() {
+    println!("No matching test modules found");
+    if !allow_none {
+      std::process::exit(1);
+    }
+    return Ok(());
+  }
+
+  if flags.coverage_dir.is_some() && !flags.unstable {
+    exit_unstable("--coverage");
+  }
+  let shard = test_runner::Shard::from_env();
+  // The JS test runner only reports in the pretty format; the other formats
+  // always get the results from shards, even if there is only one.
+  let parallel = jobs > 1 && test_modules.len() > 1;
+  if shard.is_none() && (parallel || reporter != TestReporter::Pretty) {
+    let module_count = test_modules.len();
+    let jobs = jobs.min(module_count);
+    let mut reporter = test_reporter::create(reporter, quiet);
+    return test_runner::run_in_parallel(
+      module_count,
+      jobs,
+      fail_fast,
+      &mut *reporter,
+    );
+  }
+  let (test_modules, test_file_name) = match shard {
+    Some(shard) => (shard.select(test_modules), shard.test_file_name()),
+    None => (test_modules, ".deno.test.ts".to_string()),
+  };
+
+  let test_file = test_runner::render_test_file(
+    test_modules,
+    fail_fast,
+    quiet,
+    filter,
+    shard.is_some(),
+  );
+  run_generated_module(flags, cwd.join(test_file_name), test_file).await
+}
+
+async fn bench_command(
+  flags: Flags,
+  include: Option<Vec<String>>,
+  allow_none: bool,
+  filter: Option<String>,
+) -> Result<(), ErrBox> {
+  if !flags.unstable {
+    exit_unstable("deno bench");
+  }
+  let cwd = std::env::current_dir()?;
+  let include = include.unwrap_or_else(|| vec![".".to_string()]);
+  let bench_modules = bench_runner::prepare_bench_modules_urls(include, &cwd)?;
+
+  if bench_modules.is_empty() {
+    println!("No matching bench modules found");
+    if !allow_none {
+      std::process::exit(1);
+    }
+    return Ok(());
+  }
+
+  let bench_file = bench_runner::render_bench_file(bench_modules, filter);
+  run_generated_module(flags, cwd.join(".deno.bench.ts"), bench_file).await
+}
+
+/// Runs `source_code` as if it was the module at `path`, which doesn't have
+/// to exist, like the test and bench runners do with the module importing
+/// the modules to run.
+async fn run_generated_module(
+  flags: Flags,
+  path: PathBuf,
+  source_code: String,
+) -> Result<(), ErrBox> {
+  let global_state = GlobalState::new(flags.clone())?;
+  let url = Url::from_file_path(&path).expect("Should be valid file url");
+  let main_module = ModuleSpecifier::resolve_url(&url.to_string()).unwrap();
+  let mut worker =
+    MainWorker::create(global_state.clone(), main_module.clone())?;
+  // Create a dummy source file.
+  let source_file = SourceFile {
+    filename: path,
+    url,
+    types_url: None,
+    types_header: None,
+    media_type: MediaType::TypeScript,
+    source_code: source_code.into_bytes(),
+  };
+  // Save our fake file into file fetcher cache
+  // to allow module access by TS compiler (e.g. op_fetch_source_files)
+  worker
+    .state
+    .borrow()
+    .global_state
+    .file_fetcher
+    .save_source_file_in_cache(&main_module, source_file);
+  // Declared after the worker, so that it's dropped before its inspector.
+  let mut coverage_collector = match &flags.coverage_dir {
+    Some(_) => {
+      let inspector = worker.inspector.as_mut().unwrap();
+      let mut coverage_collector = coverage::CoverageCollector::new(inspector);
+      coverage_collector.start()?;
+      Some(coverage_collector)
+    }
+    None => None,
+  };
+  let mut cpu_profiler = start_cpu_profiler(&flags, &mut worker)?;
+  let result = async {
+    worker.execute_module(&main_module).await?;
+    worker.execute("window.dispatchEvent(new Event('load'))")?;
+    (&mut *worker).await?;
+    worker.execute("window.dispatchEvent(new Event('unload'))")
+  }
+  .await;
+  // The profile and the coverage are written even if the program failed,
+  // e.g. because some of the tests it ran failed.
+  write_cpu_profile(&global_state, cpu_profiler.as_mut())?;
+  if let Some(coverage_collector) = coverage_collector.as_mut() {
+    let scripts = coverage_collector
+      .take(&global_state.ts_compiler, &main_module.to_string())?;
+    coverage::write(flags.coverage_dir.as_ref().unwrap(), &scripts)?;
+  }
+  result?;
+  if flags.strict_resources {
+    worker.check_leaked_resources()?;
+  }
+  Ok(())
+}
+
+// Exit codes of fatal errors, so that wrapper scripts can tell failures
+// apart. 10 and 11 are taken by lock file errors and 70 by unstable flags;
+// anything not listed here exits with 1.
+const EXIT_PERMISSION_DENIED: i32 = 20;
+const EXIT_MODULE_NOT_FOUND: i32 = 21;
+const EXIT_DIAGNOSTICS: i32 = 22;
+const EXIT_UNCAUGHT_ERROR: i32 = 23;
+// What shells report for a process killed by SIGPIPE.
+const EXIT_BROKEN_PIPE: i32 = 141;
+
+/// Whether `err` is an uncaught error for a write to stdout or stderr after
+/// the reading end of the pipe was closed, as in `deno run script.ts | head`.
+fn is_stdio_broken_pipe(err: &ErrBox) -> bool {
+  match err.downcast_ref::<fmt_errors::JSError>() {
+    Some(js_error) => {
+      js_error.message.starts_with("Uncaught BrokenPipe:")
+        && stdio_pipe_closed()
+    }
+    None => false,
+  }
+}
+
+#[cfg(unix)]
+fn stdio_pipe_closed() -> bool {
+  use nix::poll::poll;
+  use nix::poll::PollFd;
+  use nix::poll::PollFlags;
+  // Polling the writing end of a pipe without a reader reports an error.
+  let mut fds = [
+    PollFd::new(1, PollFlags::POLLOUT),
+    PollFd::new(2, PollFlags::POLLOUT),
+  ];
+  poll(&mut fds, 0).is_ok()
+    && fds.iter().any(|fd| {
+      fd.revents()
+        .map_or(false, |revents| revents.contains(PollFlags::POLLERR))
+    })
+}
+
+#[cfg(not(unix))]
+fn stdio_pipe_closed() -> bool {
+  false
+}
+
+fn exit_code(err: &ErrBox) -> i32 {
+  let js_error = err
+    .downcast_ref::<fmt_errors::JSError>()
+    .map(|js_error| &**js_error)
+    .or_else(|| err.downcast_ref::<deno_core::JSError>());
+  if let Some(js_error) = js_error {
+    // Permission errors thrown by ops reach the top level as uncaught
+    // exceptions of their own class.
+    match js_error.name.as_deref() {
+      Some("PermissionDenied") => EXIT_PERMISSION_DENIED,
+      _ => EXIT_UNCAUGHT_ERROR,
+    }
+  } else if err.downcast_ref::<diagnostics::Diagnostic>().is_some() {
+    EXIT_DIAGNOSTICS
+  } else if err.downcast_ref::<ModuleResolutionError>().is_some()
+    || err.downcast_ref::<ModuleNotFoundError>().is_some()
+  {
+    EXIT_MODULE_NOT_FOUND
+  } else if let Some(op_error) = err.downcast_ref::<OpError>() {
+    match op_error.kind {
+      op_error::ErrorKind::PermissionDenied => EXIT_PERMISSION_DENIED,
+      _ => 1,
+    }
+  } else {
+    1
+  }
+}
+
+/// Reports a fatal error on stderr, as JSON if `--json-errors` was given.
+fn exit_with_error(json_errors: bool, err: ErrBox) -> ! {
+  // Nobody is left to read the error, so exit quietly like with SIGPIPE.
+  if is_stdio_broken_pipe(&err) {
+    std::process::exit(EXIT_BROKEN_PIPE);
+  }
+  if json_errors {
+    eprintln!("{}", json_errors::to_json(&err));
+  } else {
+    eprintln!("{}: {}", colors::red_bold("error".to_string()), err);
+  }
+  std::process::exit(exit_code(&err));
+}
+
+/// Prefixes the report of a panic with what is needed to reproduce it.
+fn setup_panic_hook() {
+  let default_hook = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |panic_info| {
+    eprintln!("\n============================================================");
+    eprintln!("Deno has panicked. This is a bug in Deno. Please report this");
+    eprintln!("at https://github.com/denoland/deno/issues/new.");
+    eprintln!("If you can reliably reproduce this panic, include the");
+    eprintln!("reproduction steps and re-run with the RUST_BACKTRACE=1 env");
+    eprintln!("var set and include the backtrace in your report.");
+    eprintln!();
+    eprintln!("Platform: {} {}", env::consts::OS, env::consts::ARCH);
+    eprintln!(
+      "Version: {} (v8 {}, typescript {})",
+      version::DENO,
+      version::v8(),
+      version::TYPESCRIPT
+    );
+    // Secrets on the command line aren't meant to end up in bug reports.
+    if let Some(args) = crash_report::redacted_args() {
+      eprintln!("Args: {:?}", args);
+    }
+    eprintln!();
+    if let Some(path) = crash_report::write(panic_info) {
+      eprintln!("A crash report was written to {}", path.display());
+      eprintln!("Please attach it to your report.");
+      eprintln!();
+    }
+    default_hook(panic_info);
+  }));
+}
+
+pub fn main() {
+  #[cfg(windows)]
+  colors::enable_ansi(); // For Windows 10
+
+  setup_panic_hook();
+
+  log::set_logger(&LOGGER).unwrap();
+  let args: Vec<String> = env::args().collect();
+  let mut flags = flags::flags_from_vec(args);
+
+  if flags.no_color {
+    colors::disable();
+  }
+
+  if let Some(ref v8_flags) = flags.v8_flags {
+    let mut v8_flags_ = v8_flags.clone();
+    v8_flags_.insert(0, "UNUSED_BUT_NECESSARY_ARG0".to_string());
+    v8_set_flags(v8_flags_);
+  }
+
+  let log_level = match flags.log_level {
+    Some(level) => level,
+    None => Level::Info, // Default log level
+  };
+  log_category::init(log_level, &flags.log_categories);
+
+  // Loaded before anything else so that its settings reach the compiler.
+  let maybe_config_file = if config_file::is_used_by(&flags.subcommand) {
+    match config_file::resolve(&mut flags) {
+      Ok(maybe_config_file) => maybe_config_file,
+      Err(err) => exit_with_error(flags.json_errors, err),
+    }
+  } else {
+    None
+  };
+
+  if let Some(env_file) = &flags.env_file {
+    if let Err(err) = env_file::load(env_file) {
+      exit_with_error(flags.json_errors, err);
+    }
+  }
+
+  let json_errors = flags.json_errors;
+
+  if flags.frozen_time.is_some() && !flags.unstable {
+    exit_unstable("--frozen-time");
+  }
+  if flags.prof.is_some() && !flags.unstable {
+    exit_unstable("--prof");
+  }
+  if flags.trace_ops.is_some() && !flags.unstable {
+    exit_unstable("--trace-ops");
+  }
+
+  let fut = match flags.clone().subcommand {
+    DenoSubcommand::Bench {
+      allow_none,
+      include,
+      filter,
+    } => bench_command(flags, include, allow_none, filter).boxed_local(),
+    DenoSubcommand::Coverage { dir, lcov } => {
+      if !flags.unstable {
+        exit_unstable("deno coverage");
+      }
+      async move { coverage::report(&dir, lcov) }.boxed_local()
+    }
+    DenoSubcommand::Bundle {
+      source_file,
+      out_file,
+    } => bundle_command(flags, source_file, out_file).boxed_local(),
+    DenoSubcommand::Doc {
+      source_file,
+      json,
+      filter,
+    } => doc_command(flags, source_file, json, filter).boxed_local(),
+    DenoSubcommand::Eval {
+      code,
+      as_typescript,
+    } => eval_command(flags, code, as_typescript).boxed_local(),
+    DenoSubcommand::Cache { files } => {
+      cache_command(flags, files).boxed_local()
+    }
+    DenoSubcommand::Fmt { check, files, ext } => {
+      fmt::format(files, check, ext, maybe_config_file).boxed_local()
+    }
+    DenoSubcommand::Info { file } => info_command(flags, file).boxed_local(),
+    DenoSubcommand::Install {
+      module_url,
+      args,
+      name,
+      root,
+      force,
+    } => {
+      install_command(flags, module_url, args, name, root, force).boxed_local()
+    }
+    DenoSubcommand::Lint { files, json } => {
+      lint::lint_files(files, json, maybe_config_file).boxed_local()
+    }
+    DenoSubcommand::Uninstall { name, root } => {
+      async move { installer::uninstall(name, root).map_err(ErrBox::from) }
+        .boxed_local()
+    }
+    DenoSubcommand::Repl { .. } => run_repl(flags).boxed_local(),
+    DenoSubcommand::Run { script } => run_command(flags, script).boxed_local(),
+    DenoSubcommand::Task { task, args } => {
+      task_runner::run_task(maybe_config_file, task, args).boxed_local()
+    }
+    DenoSubcommand::Test {
+      fail_fast,
+      quiet,
+      include,
+      allow_none,
+      filter,
+      jobs,
+      reporter,
+    } => test_command(
+      flags, include, fail_fast, quiet, allow_none, filter, jobs, reporter,
+    )
+    .boxed_local(),
+    DenoSubcommand::Completions { buf } => {
+      if let Err(e) = write_to_stdout_ignore_sigpipe(&buf) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+      }
+      return;
+    }
+    DenoSubcommand::Types => {
+      // Tooling outside of deno can't tell which binary the declarations
+      // came from, so they're prefixed by the versions they describe.
+      let unstable = if flags.unstable {
+        ", including unstable APIs"
+      } else {
+        ""
+      };
+      let types = format!(
+        "// Type declarations for Deno {} (TypeScript {}){}\n{}",
+        version::DENO,
+        version::TYPESCRIPT,
+        unstable,
+        get_types(flags.unstable)
+      );
+      if let Err(e) = write_to_stdout_ignore_sigpipe(types.as_bytes()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+      }
+      return;
+    }
+    DenoSubcommand::Upgrade {
+      force,
+      dry_run,
+      version,
+      allow_unverified,
+    } => {
+      upgrade_command(dry_run, force, version, allow_unverified).boxed_local()
+    }
+    DenoSubcommand::Xeval {
+      code,
+      delimiter,
+      replvar,
+    } => xeval::xeval_command(flags, code, delimiter, replvar).boxed_local(),
+    _ => unreachable!(),
+  };
+
+  let result = tokio_util::run_basic(fut);
+  if let Err(err) = result {
+    exit_with_error(json_errors, err);
+  }
+}
@@ -0,0 +1,586 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! This module provides a small linter for JavaScript and TypeScript files.
+//!
+//! Files are parsed with swc and checked by a single AST visitor that
+//! implements the core rule set. Diagnostics are printed in the same frame
+//! format as runtime errors or, with `--json`, as a JSON document.
+//!
+//! A diagnostic can be suppressed by a comment on the line above it:
+//!
+//!   // deno-lint-ignore no-explicit-any
+//!
+//! Listing no rule names ignores every rule for that line, and
+//! `// deno-lint-ignore-file` at the top of a file skips the whole file.
+
+use crate::colors;
+use crate::config_file::ConfigFile;
+use crate::file_fetcher::map_file_extension;
+use crate::fmt_errors::format_stack;
+use crate::fs::expand_paths;
+use crate::fs::files_in_subtree;
+use crate::fs::is_script;
+use crate::msg::MediaType;
+use crate::op_error::OpError;
+use crate::swc_common::Span;
+use crate::swc_ecma_ast;
+use crate::swc_util::AstParser;
+use deno_core::ErrBox;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use swc_ecma_visit::Node;
+use swc_ecma_visit::Visit;
+
+const IGNORE_DIRECTIVE: &str = "deno-lint-ignore";
+const IGNORE_FILE_DIRECTIVE: &str = "deno-lint-ignore-file";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintDiagnostic {
+  pub filename: String,
+  /// 1-based line number.
+  pub line: usize,
+  /// 0-based column of the first offending character.
+  pub col: usize,
+  /// 0-based column just past the offending range on `line`.
+  #[serde(skip)]
+  pub end_col: usize,
+  pub code: String,
+  pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LintError {
+  filename: String,
+  message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LintReport<'a> {
+  diagnostics: &'a [LintDiagnostic],
+  errors: &'a [LintError],
+}
+
+/// Lint JavaScript/TypeScript files.
+///
+/// Arguments support globs, and if there are none then the files selected
+/// by the `lint` section of the project config file are linted, or the
+/// current directory is recursively walked when there's no config file.
+/// Rules excluded in the config file are skipped either way.
+pub async fn lint_files(
+  args: Vec<String>,
+  json: bool,
+  maybe_config_file: Option<ConfigFile>,
+) -> Result<(), ErrBox> {
+  let mut target_files: Vec<PathBuf> = vec![];
+  if let (true, Some(config_file)) = (args.is_empty(), &maybe_config_file) {
+    target_files
+      .extend(config_file.collect_files(&config_file.lint.files(), is_script));
+  } else if args.is_empty() {
+    target_files.extend(files_in_subtree(
+      std::env::current_dir().unwrap(),
+      is_script,
+    ));
+  } else {
+    target_files.extend(expand_paths(&args, is_script)?);
+  }
+  target_files.sort();
+
+  let excluded_rules: HashSet<String> = match &maybe_config_file {
+    Some(config_file) => {
+      config_file.lint.rules.exclude.iter().cloned().collect()
+    }
+    None => HashSet::new(),
+  };
+  let mut diagnostics = vec![];
+  let mut errors = vec![];
+  let mut sources = HashMap::new();
+  for file_path in target_files {
+    let filename = file_path.to_string_lossy().to_string();
+    let source = match fs::read_to_string(&file_path) {
+      Ok(source) => source,
+      Err(e) => {
+        errors.push(LintError {
+          filename,
+          message: e.to_string(),
+        });
+        continue;
+      }
+    };
+    match lint_source(&filename, map_file_extension(&file_path), &source) {
+      Ok(file_diagnostics) => diagnostics.extend(
+        file_diagnostics
+          .into_iter()
+          .filter(|d| !excluded_rules.contains(&d.code)),
+      ),
+      Err(message) => errors.push(LintError { filename, message }),
+    }
+    sources.insert(file_path.to_string_lossy().to_string(), source);
+  }
+
+  if json {
+    let report = LintReport {
+      diagnostics: &diagnostics,
+      errors: &errors,
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+  } else {
+    for d in diagnostics.iter() {
+      let source_line = sources
+        .get(&d.filename)
+        .and_then(|s| s.lines().nth(d.line - 1))
+        .map(String::from);
+      eprintln!("{}\n", format_diagnostic(d, source_line));
+    }
+    for e in errors.iter() {
+      eprintln!(
+        "{} {}: {}",
+        colors::red_bold("error".to_string()),
+        e.filename,
+        e.message
+      );
+    }
+  }
+
+  let problems = diagnostics.len() + errors.len();
+  if problems > 0 {
+    return Err(
+      OpError::other(format!(
+        "Found {} {}",
+        problems,
+        if problems == 1 { "problem" } else { "problems" }
+      ))
+      .into(),
+    );
+  }
+  Ok(())
+}
+
+fn format_diagnostic(
+  d: &LintDiagnostic,
+  source_line: Option<String>,
+) -> String {
+  let message_line = format!(
+    "({}) {}",
+    colors::gray(d.code.to_string()),
+    colors::bold(d.message.to_string())
+  );
+  let location = format!("{}:{}:{}", d.filename, d.line, d.col + 1);
+  let (start_column, end_column) = if source_line.is_some() {
+    (Some(d.col as i64), Some(d.end_col as i64))
+  } else {
+    (None, None)
+  };
+  format_stack(
+    true,
+    message_line,
+    source_line,
+    start_column,
+    end_column,
+    &[location],
+    0,
+  )
+}
+
+/// Lints a single module, returning its diagnostics ordered by position.
+///
+/// Syntax errors are returned as `Err` with the parser's message.
+pub fn lint_source(
+  filename: &str,
+  media_type: MediaType,
+  source: &str,
+) -> Result<Vec<LintDiagnostic>, String> {
+  if source
+    .lines()
+    .take_while(|l| l.trim().is_empty() || l.trim_start().starts_with("//"))
+    .any(|l| is_directive(l, IGNORE_FILE_DIRECTIVE).is_some())
+  {
+    return Ok(vec![]);
+  }
+
+  let parser = AstParser::new();
+  parser.parse_module(filename, media_type, source, |parse_result| {
+    let module = parse_result.map_err(|e| e.to_string())?;
+    let mut visitor = LintVisitor::new(&parser);
+    visitor.visit_module(&module, &module);
+    visitor.check_unused_vars();
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut diagnostics: Vec<LintDiagnostic> = visitor
+      .problems
+      .into_iter()
+      .map(|(code, message, span)| {
+        let lo = parser.get_span_location(span);
+        let hi = parser.source_map.lookup_char_pos(span.hi());
+        let line_len = lines
+          .get(lo.line - 1)
+          .map(|l| l.chars().count())
+          .unwrap_or(0);
+        let end_col = if hi.line == lo.line {
+          hi.col.0
+        } else {
+          line_len
+        };
+        LintDiagnostic {
+          filename: filename.to_string(),
+          line: lo.line,
+          col: lo.col.0,
+          end_col: end_col.min(line_len).max(lo.col.0 + 1),
+          code: code.to_string(),
+          message,
+        }
+      })
+      .filter(|d| !is_ignored(&lines, d))
+      .collect();
+    diagnostics.sort_by_key(|d| (d.line, d.col));
+    Ok(diagnostics)
+  })
+}
+
+/// Returns the text following `directive` if `line` is a line comment
+/// starting with it.
+fn is_directive<'a>(line: &'a str, directive: &str) -> Option<&'a str> {
+  let line = line.trim_start();
+  if !line.starts_with("//") {
+    return None;
+  }
+  let comment = line[2..].trim_start();
+  if !comment.starts_with(directive) {
+    return None;
+  }
+  let rest = &comment[directive.len()..];
+  if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+    Some(rest)
+  } else {
+    None
+  }
+}
+
+fn is_ignored(lines: &[&str], d: &LintDiagnostic) -> bool {
+  if d.line < 2 {
+    return false;
+  }
+  match lines
+    .get(d.line - 2)
+    .and_then(|l| is_directive(l, IGNORE_DIRECTIVE))
+  {
+    Some(codes) => {
+      let mut codes = codes.split_whitespace().peekable();
+      codes.peek().is_none() || codes.any(|c| c == d.code)
+    }
+    None => false,
+  }
+}
+
+struct LintVisitor<'a> {
+  parser: &'a AstParser,
+  problems: Vec<(&'static str, String, Span)>,
+  /// Bodies of functions, which are allowed to be empty.
+  function_bodies: HashSet<Span>,
+  declared: Vec<(String, Span)>,
+  references: HashSet<String>,
+}
+
+impl<'a> LintVisitor<'a> {
+  fn new(parser: &'a AstParser) -> Self {
+    Self {
+      parser,
+      problems: vec![],
+      function_bodies: HashSet::new(),
+      declared: vec![],
+      references: HashSet::new(),
+    }
+  }
+
+  fn report(&mut self, code: &'static str, message: String, span: Span) {
+    self.problems.push((code, message, span));
+  }
+
+  /// Variables are matched to their uses by name only, so a name that is
+  /// read anywhere in the module counts as used everywhere.
+  fn check_unused_vars(&mut self) {
+    let declared = std::mem::replace(&mut self.declared, vec![]);
+    for (name, span) in declared {
+      if !name.starts_with('_') && !self.references.contains(&name) {
+        self.report(
+          "no-unused-vars",
+          format!("\"{}\" is declared but never used", name),
+          span,
+        );
+      }
+    }
+  }
+}
+
+impl<'a> Visit for LintVisitor<'a> {
+  fn visit_ts_keyword_type(
+    &mut self,
+    keyword_type: &swc_ecma_ast::TsKeywordType,
+    _parent: &dyn Node,
+  ) {
+    use swc_ecma_ast::TsKeywordTypeKind::TsAnyKeyword;
+    if keyword_type.kind == TsAnyKeyword {
+      self.report(
+        "no-explicit-any",
+        "`any` type is not allowed".to_string(),
+        keyword_type.span,
+      );
+    }
+  }
+
+  fn visit_debugger_stmt(
+    &mut self,
+    debugger_stmt: &swc_ecma_ast::DebuggerStmt,
+    _parent: &dyn Node,
+  ) {
+    self.report(
+      "no-debugger",
+      "`debugger` statement is not allowed".to_string(),
+      debugger_stmt.span,
+    );
+  }
+
+  fn visit_var_decl(
+    &mut self,
+    var_decl: &swc_ecma_ast::VarDecl,
+    parent: &dyn Node,
+  ) {
+    if var_decl.kind == swc_ecma_ast::VarDeclKind::Var {
+      self.report(
+        "no-var",
+        "`var` keyword is not allowed".to_string(),
+        var_decl.span,
+      );
+    }
+    if var_decl.declare {
+      return;
+    }
+    swc_ecma_visit::visit_var_decl(self, var_decl, parent);
+  }
+
+  fn visit_var_declarator(
+    &mut self,
+    declarator: &swc_ecma_ast::VarDeclarator,
+    parent: &dyn Node,
+  ) {
+    match &declarator.name {
+      swc_ecma_ast::Pat::Ident(ident) => {
+        self.declared.push((ident.sym.to_string(), ident.span));
+        if let Some(type_ann) = &ident.type_ann {
+          self.visit_ts_type_ann(type_ann, declarator);
+        }
+        if let Some(init) = &declarator.init {
+          self.visit_expr(init, declarator);
+        }
+      }
+      _ => swc_ecma_visit::visit_var_declarator(self, declarator, parent),
+    }
+  }
+
+  fn visit_export_decl(
+    &mut self,
+    export_decl: &swc_ecma_ast::ExportDecl,
+    parent: &dyn Node,
+  ) {
+    if let swc_ecma_ast::Decl::Var(var_decl) = &export_decl.decl {
+      for declarator in var_decl.decls.iter() {
+        if let swc_ecma_ast::Pat::Ident(ident) = &declarator.name {
+          self.references.insert(ident.sym.to_string());
+        }
+      }
+    }
+    swc_ecma_visit::visit_export_decl(self, export_decl, parent);
+  }
+
+  fn visit_ident(&mut self, ident: &swc_ecma_ast::Ident, _parent: &dyn Node) {
+    self.references.insert(ident.sym.to_string());
+  }
+
+  fn visit_member_expr(
+    &mut self,
+    member_expr: &swc_ecma_ast::MemberExpr,
+    _parent: &dyn Node,
+  ) {
+    self.visit_expr_or_super(&member_expr.obj, member_expr);
+    // `a.b` doesn't read a variable named `b`.
+    if member_expr.computed {
+      self.visit_expr(&member_expr.prop, member_expr);
+    }
+  }
+
+  fn visit_prop_name(
+    &mut self,
+    prop_name: &swc_ecma_ast::PropName,
+    parent: &dyn Node,
+  ) {
+    if let swc_ecma_ast::PropName::Computed(computed) = prop_name {
+      self.visit_computed_prop_name(computed, parent);
+    }
+  }
+
+  fn visit_function(
+    &mut self,
+    function: &swc_ecma_ast::Function,
+    parent: &dyn Node,
+  ) {
+    if let Some(body) = &function.body {
+      self.function_bodies.insert(body.span);
+    }
+    swc_ecma_visit::visit_function(self, function, parent);
+  }
+
+  fn visit_arrow_expr(
+    &mut self,
+    arrow_expr: &swc_ecma_ast::ArrowExpr,
+    parent: &dyn Node,
+  ) {
+    if let swc_ecma_ast::BlockStmtOrExpr::BlockStmt(body) = &arrow_expr.body {
+      self.function_bodies.insert(body.span);
+    }
+    swc_ecma_visit::visit_arrow_expr(self, arrow_expr, parent);
+  }
+
+  fn visit_constructor(
+    &mut self,
+    constructor: &swc_ecma_ast::Constructor,
+    parent: &dyn Node,
+  ) {
+    if let Some(body) = &constructor.body {
+      self.function_bodies.insert(body.span);
+    }
+    swc_ecma_visit::visit_constructor(self, constructor, parent);
+  }
+
+  fn visit_block_stmt(
+    &mut self,
+    block_stmt: &swc_ecma_ast::BlockStmt,
+    parent: &dyn Node,
+  ) {
+    if block_stmt.stmts.is_empty()
+      && !self.function_bodies.contains(&block_stmt.span)
+    {
+      // A comment inside the block documents that it's empty on purpose.
+      let has_comment = self
+        .parser
+        .source_map
+        .span_to_snippet(block_stmt.span)
+        .map(|s| s.contains("//") || s.contains("/*"))
+        .unwrap_or(false);
+      if !has_comment {
+        self.report(
+          "no-empty",
+          "Empty block statement".to_string(),
+          block_stmt.span,
+        );
+      }
+    }
+    swc_ecma_visit::visit_block_stmt(self, block_stmt, parent);
+  }
+
+  fn visit_bin_expr(
+    &mut self,
+    bin_expr: &swc_ecma_ast::BinExpr,
+    parent: &dyn Node,
+  ) {
+    use swc_ecma_ast::BinaryOp;
+    let operators = match bin_expr.op {
+      BinaryOp::EqEq => Some(("===", "==")),
+      BinaryOp::NotEq => Some(("!==", "!=")),
+      _ => None,
+    };
+    if let Some((expected, actual)) = operators {
+      self.report(
+        "eqeqeq",
+        format!("Expected '{}' and instead saw '{}'", expected, actual),
+        bin_expr.span,
+      );
+    }
+    swc_ecma_visit::visit_bin_expr(self, bin_expr, parent);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lint(source: &str) -> Vec<(String, usize, usize)> {
+    lint_source("test.ts", MediaType::TypeScript, source)
+      .unwrap()
+      .into_iter()
+      .map(|d| (d.code, d.line, d.col))
+      .collect()
+  }
+
+  fn codes(source: &str) -> Vec<String> {
+    lint(source).into_iter().map(|(code, _, _)| code).collect()
+  }
+
+  #[test]
+  fn core_rules() {
+    let source = r#"var a = 1;
+let b: any = a;
+if (b == 2) {
+}
+debugger;
+"#;
+    assert_eq!(
+      lint(source),
+      vec![
+        ("no-var".to_string(), 1, 0),
+        ("no-explicit-any".to_string(), 2, 7),
+        ("eqeqeq".to_string(), 3, 4),
+        ("no-empty".to_string(), 3, 12),
+        ("no-debugger".to_string(), 5, 0),
+      ]
+    );
+  }
+
+  #[test]
+  fn no_unused_vars() {
+    let source = r#"const used = 1;
+const unused = 2;
+const _ignored = 3;
+export const exported = 4;
+const obj = { unused2: used };
+console.log(obj.unused2);
+"#;
+    assert_eq!(lint(source), vec![("no-unused-vars".to_string(), 2, 6)]);
+  }
+
+  #[test]
+  fn no_empty_allows_functions_and_comments() {
+    let source = r#"function noop() {}
+const f = () => {};
+try {
+  noop();
+  f();
+} catch (_e) {
+  // ignored
+}
+"#;
+    assert!(codes(source).is_empty());
+  }
+
+  #[test]
+  fn ignore_directives() {
+    let source = r#"// deno-lint-ignore no-explicit-any
+export const a: any = 1;
+// deno-lint-ignore
+export var b = 2;
+// deno-lint-ignore no-var
+export const c: any = 3;
+"#;
+    assert_eq!(codes(source), vec!["no-explicit-any".to_string()]);
+    assert!(codes("// deno-lint-ignore-file\nvar a = 1;\n").is_empty());
+  }
+
+  #[test]
+  fn syntax_error() {
+    assert!(lint_source("test.ts", MediaType::TypeScript, "const = ;").is_err());
+  }
+}
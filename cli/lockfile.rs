@@ -1,3 +1,4 @@
+use crate::log_category;
 use serde_json::json;
 pub use serde_json::Value;
 use std::collections::HashMap;
@@ -29,12 +30,12 @@ impl Lockfile {
       .open(&self.filename)?;
     use std::io::Write;
     f.write_all(s.as_bytes())?;
-    debug!("lockfile write {}", self.filename);
+    debug!(target: log_category::CACHE, "lockfile write {}", self.filename);
     Ok(())
   }
 
   pub fn read(&mut self) -> Result<()> {
-    debug!("lockfile read {}", self.filename);
+    debug!(target: log_category::CACHE, "lockfile read {}", self.filename);
     let s = std::fs::read_to_string(&self.filename)?;
     self.map = serde_json::from_str(&s)?;
     self.need_read = false;
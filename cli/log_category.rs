@@ -0,0 +1,106 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Categories of debug output, which `--log` enables without the debug
+//! output of the rest of the program. The records of a category use it as
+//! their target:
+//!
+//! ```ignore
+//! debug!(target: log_category::NET, "Redirecting to {}", url);
+//! ```
+
+use log::Level;
+use log::LevelFilter;
+use log::Metadata;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+/// The ops, with their arguments.
+pub const OPS: &str = "ops";
+/// HTTP requests and sockets.
+pub const NET: &str = "net";
+/// The module cache, the lock file and the cache of `fetch()`.
+pub const CACHE: &str = "cache";
+
+pub const ALL: &[&str] = &[OPS, NET, CACHE];
+
+/// The level set by `--log-level`, as a `LevelFilter`.
+static LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+/// The categories enabled by `--log`, one bit per entry of `ALL`.
+static ENABLED: AtomicUsize = AtomicUsize::new(0);
+
+pub fn init(level: Level, categories: &[String]) {
+  let enabled = enabled_mask(categories);
+  LEVEL.store(level.to_level_filter() as usize, Ordering::Relaxed);
+  ENABLED.store(enabled, Ordering::Relaxed);
+  log::set_max_level(max_level(level.to_level_filter(), enabled));
+}
+
+/// The bits of `ENABLED` for `categories`. Unknown categories are ignored.
+fn enabled_mask(categories: &[String]) -> usize {
+  let mut enabled = 0;
+  for category in categories {
+    if let Some(index) = ALL.iter().position(|c| c == category) {
+      enabled |= 1 << index;
+    }
+  }
+  enabled
+}
+
+/// The macros skip the records above the max level before they reach the
+/// logger, so the debug records have to go through when any category is
+/// enabled.
+fn max_level(level: LevelFilter, enabled: usize) -> LevelFilter {
+  if enabled == 0 {
+    level
+  } else {
+    level.max(LevelFilter::Debug)
+  }
+}
+
+/// Whether a record passes `--log-level`, or is the debug output of a
+/// category that `--log` enabled.
+pub fn enabled(metadata: &Metadata) -> bool {
+  let level = LEVEL.load(Ordering::Relaxed);
+  is_record_enabled(metadata, level, ENABLED.load(Ordering::Relaxed))
+}
+
+fn is_record_enabled(
+  metadata: &Metadata,
+  level: usize,
+  enabled: usize,
+) -> bool {
+  if metadata.level() as usize <= level {
+    return true;
+  }
+  metadata.level() <= Level::Debug
+    && ALL
+      .iter()
+      .position(|c| *c == metadata.target())
+      .map_or(false, |index| enabled & (1 << index) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn categories() {
+    let enabled = enabled_mask(&["net".to_string(), "unknown".to_string()]);
+    assert_eq!(enabled, enabled_mask(&[NET.to_string()]));
+    assert_eq!(max_level(LevelFilter::Info, enabled), LevelFilter::Debug);
+    assert_eq!(max_level(LevelFilter::Trace, enabled), LevelFilter::Trace);
+    assert_eq!(max_level(LevelFilter::Info, 0), LevelFilter::Info);
+
+    let info = LevelFilter::Info as usize;
+    let metadata =
+      |level, target| Metadata::builder().level(level).target(target).build();
+    let is_enabled = |level, target| {
+      is_record_enabled(&metadata(level, target), info, enabled)
+    };
+    assert!(is_enabled(Level::Info, "deno::worker"));
+    assert!(!is_enabled(Level::Debug, "deno::worker"));
+    assert!(is_enabled(Level::Debug, NET));
+    assert!(!is_enabled(Level::Trace, NET));
+    assert!(!is_enabled(Level::Debug, CACHE));
+    assert!(!is_record_enabled(&metadata(Level::Debug, NET), info, 0));
+  }
+}
@@ -11,6 +11,7 @@ use crate::permissions::Permissions;
 use crate::swc_util::analyze_dependencies_and_references;
 use crate::swc_util::TsReferenceKind;
 use crate::tsc::AVAILABLE_LIBS;
+use crate::wasm;
 use deno_core::ErrBox;
 use deno_core::ModuleSpecifier;
 use futures::stream::FuturesUnordered;
@@ -402,9 +403,20 @@ impl ModuleGraphLoader {
     }
 
     let module_specifier = ModuleSpecifier::from(source_file.url.clone());
-    let source_code = String::from_utf8(source_file.source_code)?;
+    // WebAssembly modules are represented in the graph by their JS wrapper,
+    // so that modules named in the import section are fetched like any other
+    // dependency and the compiler sees the exported bindings.
+    let (source_code, analyzed_media_type) =
+      if source_file.media_type == MediaType::Wasm {
+        (wasm::wrap(&source_file.source_code)?, MediaType::JavaScript)
+      } else {
+        (
+          String::from_utf8(source_file.source_code)?,
+          source_file.media_type,
+        )
+      };
 
-    if SUPPORTED_MEDIA_TYPES.contains(&source_file.media_type) {
+    if SUPPORTED_MEDIA_TYPES.contains(&analyzed_media_type) {
       if let Some(types_specifier) = source_file.types_header {
         let type_header = ReferenceDescriptor {
           specifier: types_specifier.to_string(),
@@ -422,7 +434,7 @@ impl ModuleGraphLoader {
 
       let (import_descs, ref_descs) = analyze_dependencies_and_references(
         &module_specifier.to_string(),
-        source_file.media_type,
+        analyzed_media_type,
         &source_code,
         self.analyze_dynamic_imports,
       )?;
@@ -439,7 +451,7 @@ impl ModuleGraphLoader {
         let resolved_specifier = if let Some(resolved) = maybe_resolved {
           resolved
         } else {
-          ModuleSpecifier::resolve_import(
+          self.file_fetcher.npm_resolver.resolve_import(
             &import_desc.specifier,
             &module_specifier.to_string(),
           )?
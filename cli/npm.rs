@@ -0,0 +1,1349 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Support for `npm:` module specifiers.
+//!
+//! `npm:<name>[@<range>][/<path>]` is resolved by picking the highest version
+//! in the registry that satisfies the range, unpacking the package tarball
+//! into `$DENO_DIR/npm/<registry>/<name>@<version>` and applying node's
+//! package entry point rules inside of it. Bare specifiers used by files of
+//! the package are mapped to the dependencies declared in its package.json,
+//! and node built-in modules are served by their std/node ports.
+//!
+//! Packages are executed as ES modules; CommonJS-only packages are not
+//! supported.
+use crate::colors;
+use crate::op_error::ModuleNotFoundError;
+use crate::op_error::OpError;
+use crate::permissions::Permissions;
+use deno_core::ErrBox;
+use deno_core::ModuleSpecifier;
+use flate2::read::GzDecoder;
+use reqwest::header::ACCEPT;
+use reqwest::header::LOCATION;
+use reqwest::Client;
+use ring::digest;
+use semver::Version;
+use semver::VersionReq;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fmt::Write;
+use std::fs;
+use std::io::Read;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str;
+use std::sync::Arc;
+use std::sync::Mutex;
+use url::Url;
+
+pub const DEFAULT_REGISTRY_URL: &str = "https://registry.npmjs.org/";
+const STD_NODE_URL: &str = "https://deno.land/std@0.56.0/node/";
+const NODE_BUILTINS: &[&str] = &[
+  "events",
+  "fs",
+  "module",
+  "os",
+  "path",
+  "process",
+  "querystring",
+  "timers",
+  "url",
+  "util",
+];
+/// Conditions honored in the "exports" field of package.json.
+const EXPORT_CONDITIONS: &[&str] = &["deno", "import", "module", "default"];
+const DEPENDENCY_FIELDS: &[&str] =
+  &["dependencies", "peerDependencies", "optionalDependencies"];
+const PACKUMENT_ACCEPT: &str =
+  "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8";
+
+/// Returns the registry configured with `NPM_CONFIG_REGISTRY`, falling back
+/// to the public npm registry.
+pub fn registry_url() -> Url {
+  env::var("NPM_CONFIG_REGISTRY")
+    .ok()
+    .and_then(|s| {
+      if s.ends_with('/') {
+        Url::parse(&s).ok()
+      } else {
+        Url::parse(&format!("{}/", s)).ok()
+      }
+    })
+    .unwrap_or_else(|| Url::parse(DEFAULT_REGISTRY_URL).unwrap())
+}
+
+/// Package requirement described by an `npm:` specifier.
+#[derive(Debug, PartialEq)]
+pub struct NpmPackageReq {
+  pub name: String,
+  pub version_req: Option<String>,
+  pub sub_path: Option<String>,
+}
+
+impl NpmPackageReq {
+  pub fn from_url(url: &Url) -> Result<Self, ErrBox> {
+    Self::parse(url.path())
+  }
+
+  /// Parses `<name>[@<range>][/<path>]`, where name might be scoped.
+  pub fn parse(text: &str) -> Result<Self, ErrBox> {
+    let invalid = || -> ErrBox {
+      OpError::uri_error(format!("Invalid npm specifier \"npm:{}\"", text))
+        .into()
+    };
+
+    let name_start = if text.starts_with('@') {
+      text.find('/').ok_or_else(invalid)? + 1
+    } else {
+      0
+    };
+    let end = text[name_start..]
+      .find('/')
+      .map(|i| i + name_start)
+      .unwrap_or_else(|| text.len());
+    let name_and_req = &text[..end];
+    let (name, version_req) = match name_and_req[name_start..].find('@') {
+      Some(i) => (
+        &name_and_req[..name_start + i],
+        Some(&name_and_req[name_start + i + 1..]),
+      ),
+      None => (name_and_req, None),
+    };
+
+    if !is_valid_package_name(name) {
+      return Err(invalid());
+    }
+
+    let version_req = version_req
+      .map(str::trim)
+      .filter(|r| !r.is_empty())
+      .map(String::from);
+    let sub_path = Some(text[end..].trim_start_matches('/'))
+      .filter(|p| !p.is_empty())
+      .map(String::from);
+
+    Ok(Self {
+      name: name.to_string(),
+      version_req,
+      sub_path,
+    })
+  }
+}
+
+impl fmt::Display for NpmPackageReq {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.version_req {
+      Some(req) => write!(f, "{}@{}", self.name, req),
+      None => write!(f, "{}", self.name),
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct Packument {
+  #[serde(rename = "dist-tags", default)]
+  dist_tags: HashMap<String, String>,
+  #[serde(default)]
+  versions: HashMap<String, PackageVersionInfo>,
+}
+
+#[derive(Deserialize)]
+struct PackageVersionInfo {
+  dist: PackageDist,
+}
+
+#[derive(Deserialize)]
+struct PackageDist {
+  tarball: String,
+  shasum: Option<String>,
+  integrity: Option<String>,
+}
+
+/// Returns true if `name` is a valid, possibly scoped, npm package name,
+/// which makes it safe to use as a directory of the cache.
+fn is_valid_package_name(name: &str) -> bool {
+  let is_valid_part = |part: &str| {
+    !part.is_empty()
+      && !part.starts_with('.')
+      && part.chars().all(|c| {
+        c.is_ascii_alphanumeric()
+          || c == '-'
+          || c == '.'
+          || c == '_'
+          || c == '~'
+      })
+  };
+  if name.starts_with('@') {
+    let mut parts = name[1..].splitn(2, '/');
+    match (parts.next(), parts.next()) {
+      (Some(scope), Some(name)) => is_valid_part(scope) && is_valid_part(name),
+      _ => false,
+    }
+  } else {
+    is_valid_part(name)
+  }
+}
+
+/// Converts one npm range (no `||`) into a `VersionReq`.
+///
+/// npm separates comparators with spaces and allows hyphen ranges, while
+/// `semver` only understands comma separated comparators.
+fn parse_range(range: &str) -> Result<VersionReq, ErrBox> {
+  let tokens: Vec<&str> = range.split_whitespace().collect();
+  let mut comparators = vec![];
+  let mut i = 0;
+  while i < tokens.len() {
+    let token = tokens[i];
+    if i + 2 < tokens.len() && tokens[i + 1] == "-" {
+      comparators.push(format!(">={}", tokens[i].trim_start_matches('v')));
+      comparators.push(format!("<={}", tokens[i + 2].trim_start_matches('v')));
+      i += 3;
+    } else if i + 1 < tokens.len() && token.chars().all(|c| "<>=~^".contains(c))
+    {
+      comparators.push(format!("{}{}", token, tokens[i + 1]));
+      i += 2;
+    } else {
+      comparators.push(token.to_string());
+      i += 1;
+    }
+  }
+
+  let comparators: Vec<String> = comparators
+    .into_iter()
+    .map(|c| {
+      let start = c.find(|ch: char| !"<>=~^".contains(ch)).unwrap_or(0);
+      let (op, version) = (&c[..start], c[start..].trim_start_matches('v'));
+      // Unlike `semver`, npm reads "1.2.3" as exactly that version and
+      // "1.2" as "1.2.x".
+      let is_wildcard =
+        version.contains(|ch| ch == 'x' || ch == 'X' || ch == '*');
+      let core = version.split(|ch| ch == '-' || ch == '+').next().unwrap();
+      let op = match (op, core.split('.').count()) {
+        ("", 3) if !is_wildcard => "=",
+        ("", 2) if !is_wildcard => "~",
+        (op, _) => op,
+      };
+      format!("{}{}", op, version)
+    })
+    .collect();
+  let req = if comparators.is_empty() {
+    "*".to_string()
+  } else {
+    comparators.join(", ")
+  };
+
+  VersionReq::parse(&req).map_err(|e| {
+    OpError::type_error(format!(
+      "Invalid npm version requirement \"{}\": {}",
+      range.trim(),
+      e
+    ))
+    .into()
+  })
+}
+
+/// Picks the version a requirement resolves to.
+///
+/// Dist-tags (including the implicit "latest") take precedence over ranges.
+/// Otherwise the highest version satisfying any of the `||` separated
+/// ranges is returned.
+pub fn select_version(
+  versions: &[String],
+  dist_tags: &HashMap<String, String>,
+  version_req: Option<&str>,
+) -> Result<Option<String>, ErrBox> {
+  let version_req = version_req.unwrap_or("latest");
+  if let Some(version) = dist_tags.get(version_req) {
+    return Ok(Some(version.clone()));
+  }
+  let version_req = if version_req == "latest" {
+    "*"
+  } else {
+    version_req
+  };
+  // `semver` lets a bare wildcard match pre-releases, npm doesn't.
+  let is_any = ["*", "x", "X"].contains(&version_req.trim());
+
+  let mut ranges = vec![];
+  for range in version_req.split("||") {
+    ranges.push(parse_range(range)?);
+  }
+
+  let best = versions
+    .iter()
+    .filter_map(|v| Version::parse(v).ok().map(|parsed| (parsed, v)))
+    .filter(|(parsed, _)| !(is_any && parsed.is_prerelease()))
+    .filter(|(parsed, _)| ranges.iter().any(|r| r.matches(parsed)))
+    .max_by(|(a, _), (b, _)| a.cmp(b))
+    .map(|(_, v)| v.clone());
+
+  Ok(best)
+}
+
+fn node_builtin_url(specifier: &str) -> Option<String> {
+  let name = if specifier.starts_with("node:") {
+    &specifier[5..]
+  } else {
+    specifier
+  };
+  if NODE_BUILTINS.contains(&name) {
+    Some(format!("{}{}.ts", STD_NODE_URL, name))
+  } else {
+    None
+  }
+}
+
+fn is_bare_specifier(specifier: &str) -> bool {
+  !specifier.starts_with("./")
+    && !specifier.starts_with("../")
+    && !specifier.starts_with('/')
+    && Url::parse(specifier).is_err()
+}
+
+fn read_manifest(package_dir: &Path) -> Result<Value, ErrBox> {
+  let text = fs::read_to_string(package_dir.join("package.json"))?;
+  Ok(serde_json::from_str(&text)?)
+}
+
+/// Joins a relative path from package.json onto the package directory,
+/// without letting it escape the package.
+fn join_package_path(package_dir: &Path, target: &str) -> PathBuf {
+  let mut path = package_dir.to_path_buf();
+  let mut depth = 0;
+  for component in Path::new(target).components() {
+    match component {
+      Component::Normal(part) => {
+        path.push(part);
+        depth += 1;
+      }
+      Component::ParentDir if depth > 0 => {
+        path.pop();
+        depth -= 1;
+      }
+      _ => {}
+    }
+  }
+  path
+}
+
+fn resolve_conditions(target: &Value) -> Option<String> {
+  match target {
+    Value::String(s) => Some(s.clone()),
+    Value::Array(items) => items.iter().find_map(resolve_conditions),
+    Value::Object(map) => map
+      .iter()
+      .filter(|(k, _)| EXPORT_CONDITIONS.contains(&k.as_str()))
+      .find_map(|(_, v)| resolve_conditions(v)),
+    _ => None,
+  }
+}
+
+fn resolve_exports(exports: &Value, sub_path: Option<&str>) -> Option<String> {
+  let key = match sub_path {
+    Some(sub_path) => format!("./{}", sub_path),
+    None => ".".to_string(),
+  };
+
+  let map = match exports {
+    Value::Object(map) if map.keys().any(|k| k.starts_with('.')) => map,
+    // "exports": "./index.js" or a conditions object for the main entry.
+    _ if sub_path.is_none() => return resolve_conditions(exports),
+    _ => return None,
+  };
+
+  if let Some(target) = map.get(&key) {
+    return resolve_conditions(target);
+  }
+  for (pattern, target) in map.iter() {
+    if let Some(star) = pattern.find('*') {
+      let (prefix, suffix) = (&pattern[..star], &pattern[star + 1..]);
+      if key.len() >= prefix.len() + suffix.len()
+        && key.starts_with(prefix)
+        && key.ends_with(suffix)
+      {
+        let matched = &key[prefix.len()..key.len() - suffix.len()];
+        return resolve_conditions(target).map(|t| t.replace('*', matched));
+      }
+    } else if pattern.ends_with('/') && key.starts_with(pattern.as_str()) {
+      return resolve_conditions(target)
+        .map(|t| format!("{}{}", t, &key[pattern.len()..]));
+    }
+  }
+  None
+}
+
+fn resolve_file(path: PathBuf) -> Result<PathBuf, ErrBox> {
+  if path.is_file() {
+    return Ok(path);
+  }
+  for ext in &["js", "mjs", "json"] {
+    let mut candidate = path.clone().into_os_string();
+    candidate.push(".");
+    candidate.push(ext);
+    let candidate = PathBuf::from(candidate);
+    if candidate.is_file() {
+      return Ok(candidate);
+    }
+  }
+  for index in &["index.js", "index.mjs", "index.json"] {
+    let candidate = path.join(index);
+    if candidate.is_file() {
+      return Ok(candidate);
+    }
+  }
+  Err(
+    ModuleNotFoundError::new(format!(
+      "Cannot find module \"{}\"",
+      path.display()
+    ))
+    .into(),
+  )
+}
+
+/// Finds the file a package (or a path inside of it) resolves to, following
+/// the "exports", "module" and "main" fields of its package.json.
+pub fn resolve_package_entry(
+  package_dir: &Path,
+  sub_path: Option<&str>,
+) -> Result<PathBuf, ErrBox> {
+  let manifest = read_manifest(package_dir)?;
+  let target = if let Some(exports) = manifest.get("exports") {
+    match resolve_exports(exports, sub_path) {
+      Some(target) => target,
+      None => {
+        let name = manifest.get("name").and_then(Value::as_str).unwrap_or("");
+        let sub_path = sub_path.map(|p| format!("./{}", p));
+        return Err(
+          ModuleNotFoundError::new(format!(
+            "Package subpath \"{}\" is not exported for ES modules by \"{}\"",
+            sub_path.as_deref().unwrap_or("."),
+            name
+          ))
+          .into(),
+        );
+      }
+    }
+  } else if let Some(sub_path) = sub_path {
+    sub_path.to_string()
+  } else {
+    ["module", "main"]
+      .iter()
+      .find_map(|field| manifest.get(*field).and_then(Value::as_str))
+      .unwrap_or("index.js")
+      .to_string()
+  };
+
+  resolve_file(join_package_path(package_dir, &target))
+}
+
+struct TarEntry<'a> {
+  path: String,
+  data: &'a [u8],
+}
+
+fn malformed_tarball() -> ErrBox {
+  OpError::other("Malformed npm package tarball".to_string()).into()
+}
+
+fn c_str(bytes: &[u8]) -> String {
+  let end = bytes
+    .iter()
+    .position(|b| *b == 0)
+    .unwrap_or_else(|| bytes.len());
+  String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn parse_octal(bytes: &[u8]) -> Result<usize, ErrBox> {
+  let text = str::from_utf8(bytes)
+    .map_err(|_| malformed_tarball())?
+    .trim_matches(|c| c == '\0' || c == ' ');
+  if text.is_empty() {
+    return Ok(0);
+  }
+  usize::from_str_radix(text, 8).map_err(|_| malformed_tarball())
+}
+
+/// Extracts the "path" record of a pax extended header.
+fn pax_path(data: &[u8]) -> Option<String> {
+  let mut rest = str::from_utf8(data).ok()?;
+  while !rest.is_empty() {
+    let space = rest.find(' ')?;
+    let len: usize = rest[..space].parse().ok()?;
+    let record = rest.get(space + 1..len)?;
+    rest = rest.get(len..)?;
+    if record.starts_with("path=") {
+      return Some(record[5..].trim_end_matches('\n').to_string());
+    }
+  }
+  None
+}
+
+/// Lists the regular files of a ustar archive, including pax and GNU long
+/// names. Other entry kinds are skipped.
+fn read_tar(tar: &[u8]) -> Result<Vec<TarEntry>, ErrBox> {
+  let mut entries = vec![];
+  let mut long_name: Option<String> = None;
+  let mut pos = 0;
+  while pos + 512 <= tar.len() {
+    let header = &tar[pos..pos + 512];
+    if header.iter().all(|b| *b == 0) {
+      break;
+    }
+    let size = parse_octal(&header[124..136])?;
+    let data_start = pos + 512;
+    let data = tar
+      .get(data_start..data_start + size)
+      .ok_or_else(malformed_tarball)?;
+    pos = data_start + (size + 511) / 512 * 512;
+
+    match header[156] {
+      b'x' => {
+        long_name = pax_path(data);
+        continue;
+      }
+      b'L' => {
+        long_name = Some(c_str(data));
+        continue;
+      }
+      b'0' | b'\0' => {}
+      b'g' => continue,
+      _ => {
+        long_name = None;
+        continue;
+      }
+    }
+
+    let path = match long_name.take() {
+      Some(path) => path,
+      None => {
+        let name = c_str(&header[0..100]);
+        let prefix = if &header[257..262] == b"ustar" {
+          c_str(&header[345..500])
+        } else {
+          "".to_string()
+        };
+        if prefix.is_empty() {
+          name
+        } else {
+          format!("{}/{}", prefix, name)
+        }
+      }
+    };
+    entries.push(TarEntry { path, data });
+  }
+  Ok(entries)
+}
+
+/// Strips the top level directory npm tarballs are rooted in (usually
+/// "package/") and rejects paths that would escape the destination.
+fn tar_entry_path(path: &str) -> Option<PathBuf> {
+  let mut stripped = false;
+  let mut out = PathBuf::new();
+  for component in Path::new(path).components() {
+    match component {
+      Component::Normal(part) if stripped => out.push(part),
+      Component::Normal(_) => stripped = true,
+      Component::CurDir => {}
+      _ => return None,
+    }
+  }
+  if out.as_os_str().is_empty() {
+    None
+  } else {
+    Some(out)
+  }
+}
+
+fn extract_tarball(data: &[u8], dest: &Path) -> Result<(), ErrBox> {
+  let mut tar = vec![];
+  GzDecoder::new(data)
+    .read_to_end(&mut tar)
+    .map_err(|_| malformed_tarball())?;
+
+  // Unpack next to the destination first, so an interrupted download never
+  // leaves a partial package behind.
+  let parent = dest.parent().unwrap();
+  fs::create_dir_all(parent)?;
+  let tmp_dir = parent.join(format!(".{}", uuid::Uuid::new_v4()));
+  for entry in read_tar(&tar)? {
+    if let Some(path) = tar_entry_path(&entry.path) {
+      let path = tmp_dir.join(path);
+      fs::create_dir_all(path.parent().unwrap())?;
+      fs::write(path, entry.data)?;
+    }
+  }
+
+  if dest.exists() {
+    fs::remove_dir_all(dest)?;
+  }
+  if let Err(err) = fs::rename(&tmp_dir, dest) {
+    fs::remove_dir_all(&tmp_dir)?;
+    // Another load of the same package might have won the race.
+    if !dest.is_dir() {
+      return Err(err.into());
+    }
+  }
+  Ok(())
+}
+
+fn check_integrity(
+  req: &NpmPackageReq,
+  dist: &PackageDist,
+  data: &[u8],
+) -> Result<(), ErrBox> {
+  let valid = if let Some(integrity) = &dist.integrity {
+    integrity.split_whitespace().any(|hash| {
+      let dash = match hash.find('-') {
+        Some(dash) => dash,
+        None => return false,
+      };
+      let algorithm = match &hash[..dash] {
+        "sha512" => &digest::SHA512,
+        "sha384" => &digest::SHA384,
+        "sha256" => &digest::SHA256,
+        "sha1" => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+        _ => return false,
+      };
+      base64::encode(digest::digest(algorithm, data).as_ref())
+        == hash[dash + 1..]
+    })
+  } else if let Some(shasum) = &dist.shasum {
+    let digest = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, data);
+    let mut hex = String::new();
+    for byte in digest.as_ref() {
+      write!(&mut hex, "{:02x}", byte).unwrap();
+    }
+    &hex == shasum
+  } else {
+    return Err(
+      OpError::other(format!(
+        "npm package \"{}\" has no integrity checksum",
+        req
+      ))
+      .into(),
+    );
+  };
+
+  if valid {
+    Ok(())
+  } else {
+    Err(
+      OpError::other(format!(
+        "Integrity check failed for npm package \"{}\"",
+        req
+      ))
+      .into(),
+    )
+  }
+}
+
+/// Resolves `npm:` specifiers to files of packages cached in DENO_DIR.
+#[derive(Clone)]
+pub struct NpmResolver {
+  root: PathBuf,
+  registry_url: Url,
+  http_client: Client,
+  /// Versions already picked in this process, keyed by requirement.
+  resolved: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl NpmResolver {
+  pub fn new(
+    cache_location: &Path,
+    registry_url: Url,
+    http_client: Client,
+  ) -> Self {
+    let host = registry_url.host_str().unwrap_or("registry");
+    let host_dir = match registry_url.port() {
+      Some(port) => format!("{}_PORT{}", host, port),
+      None => host.to_string(),
+    };
+    Self {
+      root: cache_location.join(host_dir),
+      registry_url,
+      http_client,
+      resolved: Arc::new(Mutex::new(HashMap::new())),
+    }
+  }
+
+  /// Returns true if given path belongs to a package in the npm cache.
+  pub fn is_package_file(&self, path: &Path) -> bool {
+    path.starts_with(&self.root)
+  }
+
+  /// The permissions modules are loaded with: `permissions`, plus reading the
+  /// npm cache, whose packages were allowed by their `npm:` imports.
+  pub fn module_permissions(&self, permissions: &Permissions) -> Permissions {
+    let mut permissions = permissions.clone();
+    permissions.allow_read_path(&self.root);
+    permissions
+  }
+
+  pub fn registry_url(&self) -> &Url {
+    &self.registry_url
+  }
+
+  /// Returns the cache directory of a version of a package. Both come from
+  /// the registry, so they're checked not to point outside of the cache.
+  fn package_dir(&self, name: &str, version: &str) -> Result<PathBuf, ErrBox> {
+    if !is_valid_package_name(name) {
+      return Err(
+        OpError::other(format!("Invalid npm package name \"{}\"", name)).into(),
+      );
+    }
+    if Version::parse(version).is_err()
+      || !version
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+')
+    {
+      return Err(
+        OpError::other(format!(
+          "Invalid version \"{}\" of npm package \"{}\"",
+          version, name
+        ))
+        .into(),
+      );
+    }
+    Ok(self.root.join(format!("{}@{}", name, version)))
+  }
+
+  /// Returns the directory of the package containing the referrer.
+  fn package_dir_of(&self, referrer: &str) -> Option<PathBuf> {
+    let path = Url::parse(referrer).ok()?.to_file_path().ok()?;
+    let mut components = path.strip_prefix(&self.root).ok()?.components();
+    let first = components.next()?.as_os_str();
+    if first.to_str()?.starts_with('@') {
+      Some(self.root.join(first).join(components.next()?.as_os_str()))
+    } else {
+      Some(self.root.join(first))
+    }
+  }
+
+  /// Resolves an import, mapping bare specifiers used inside of npm packages
+  /// to the package's dependencies and node built-ins to std/node.
+  pub fn resolve_import(
+    &self,
+    specifier: &str,
+    referrer: &str,
+  ) -> Result<ModuleSpecifier, ErrBox> {
+    if specifier.starts_with("node:") {
+      if let Some(url) = node_builtin_url(specifier) {
+        return Ok(ModuleSpecifier::resolve_url(&url)?);
+      }
+    }
+    if is_bare_specifier(specifier) {
+      if let Some(package_dir) = self.package_dir_of(referrer) {
+        return self.resolve_dependency(specifier, &package_dir);
+      }
+    }
+    Ok(ModuleSpecifier::resolve_import(specifier, referrer)?)
+  }
+
+  fn resolve_dependency(
+    &self,
+    specifier: &str,
+    package_dir: &Path,
+  ) -> Result<ModuleSpecifier, ErrBox> {
+    if let Some(url) = node_builtin_url(specifier) {
+      return Ok(ModuleSpecifier::resolve_url(&url)?);
+    }
+
+    let req = NpmPackageReq::parse(specifier)?;
+    let manifest = read_manifest(package_dir)?;
+    let package_name =
+      manifest.get("name").and_then(Value::as_str).unwrap_or("");
+    let range = DEPENDENCY_FIELDS
+      .iter()
+      .find_map(|field| manifest.get(*field)?.get(&req.name)?.as_str());
+    let target = match range {
+      Some(range) if range.starts_with("npm:") => range[4..].to_string(),
+      Some(range) if !range.contains(':') && !range.contains('/') => {
+        format!("{}@{}", req.name, range)
+      }
+      Some(range) => {
+        return Err(
+          OpError::other(format!(
+            "Unsupported dependency \"{}\": \"{}\" of npm package \"{}\"",
+            req.name, range, package_name
+          ))
+          .into(),
+        )
+      }
+      None => {
+        return Err(
+          ModuleNotFoundError::new(format!(
+            "Cannot resolve \"{}\" from npm package \"{}\", it is not listed in its dependencies",
+            specifier, package_name
+          ))
+          .into(),
+        )
+      }
+    };
+
+    let mut url = format!("npm:{}", target);
+    if let Some(sub_path) = req.sub_path {
+      url.push('/');
+      url.push_str(&sub_path);
+    }
+    Ok(ModuleSpecifier::resolve_url(&url)?)
+  }
+
+  fn find_cached_version(&self, req: &NpmPackageReq) -> Option<String> {
+    let key = req.to_string();
+    if let Some(version) = self.resolved.lock().unwrap().get(&key) {
+      return Some(version.clone());
+    }
+
+    let prefix = format!("{}@", req.name);
+    let dir = self.root.join(&req.name);
+    let dir = dir.parent()?;
+    let file_prefix = prefix.rsplit('/').next().unwrap();
+    let versions: Vec<String> = fs::read_dir(dir)
+      .ok()?
+      .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+      .filter(|name| name.starts_with(file_prefix))
+      .map(|name| name[file_prefix.len()..].to_string())
+      .filter(|v| match self.package_dir(&req.name, v) {
+        Ok(dir) => dir.join("package.json").is_file(),
+        Err(_) => false,
+      })
+      .collect();
+    let version =
+      select_version(&versions, &HashMap::new(), req.version_req.as_deref())
+        .ok()
+        .flatten()?;
+
+    self.resolved.lock().unwrap().insert(key, version.clone());
+    Some(version)
+  }
+
+  async fn fetch_bytes(
+    &self,
+    url: &Url,
+    accept: Option<&str>,
+  ) -> Result<Vec<u8>, ErrBox> {
+    let mut url = url.clone();
+    // The shared client doesn't follow redirects.
+    for _ in 0..10 {
+      let mut request = self.http_client.get(url.clone());
+      if let Some(accept) = accept {
+        request = request.header(ACCEPT, accept);
+      }
+      let response = request.send().await?;
+      if response.status().is_redirection() {
+        let location = response
+          .headers()
+          .get(LOCATION)
+          .and_then(|l| l.to_str().ok())
+          .ok_or_else(|| {
+            OpError::http(format!("Redirect from '{}' has no location", url))
+          })?;
+        url = url.join(location)?;
+        continue;
+      }
+      if !response.status().is_success() {
+        return Err(
+          OpError::http(format!(
+            "Import '{}' failed: {}",
+            url,
+            response.status()
+          ))
+          .into(),
+        );
+      }
+      return Ok(response.bytes().await?.to_vec());
+    }
+    Err(OpError::http(format!("Too many redirects for '{}'", url)).into())
+  }
+
+  async fn fetch_packument(&self, name: &str) -> Result<Packument, ErrBox> {
+    let url = self.registry_url.join(&name.replace('/', "%2f"))?;
+    let body = self.fetch_bytes(&url, Some(PACKUMENT_ACCEPT)).await?;
+    Ok(serde_json::from_slice(&body)?)
+  }
+
+  /// Makes sure a version of the package satisfying `req` is in the cache
+  /// and returns its directory.
+  async fn ensure_package(
+    &self,
+    req: &NpmPackageReq,
+    use_disk_cache: bool,
+    cached_only: bool,
+    permissions: &Permissions,
+  ) -> Result<PathBuf, ErrBox> {
+    let maybe_version =
+      self.resolved.lock().unwrap().get(&req.to_string()).cloned();
+    if let Some(version) = maybe_version {
+      return self.package_dir(&req.name, &version);
+    }
+    if use_disk_cache || cached_only {
+      if let Some(version) = self.find_cached_version(req) {
+        return self.package_dir(&req.name, &version);
+      }
+    }
+    if cached_only {
+      return Err(
+        ModuleNotFoundError::new(format!(
+          "Cannot find npm package \"{}\" in cache, --cached-only is specified",
+          req
+        ))
+        .into(),
+      );
+    }
+
+    permissions.check_net_url(&self.registry_url)?;
+    let packument = self.fetch_packument(&req.name).await?;
+    let versions: Vec<String> = packument.versions.keys().cloned().collect();
+    let version = select_version(
+      &versions,
+      &packument.dist_tags,
+      req.version_req.as_deref(),
+    )?
+    .ok_or_else(|| {
+      ModuleNotFoundError::new(format!(
+        "Could not find a version of npm package \"{}\" matching \"{}\"",
+        req.name,
+        req.version_req.as_deref().unwrap_or("latest")
+      ))
+    })?;
+
+    let package_dir = self.package_dir(&req.name, &version)?;
+    let is_cached = package_dir.join("package.json").is_file();
+    if !is_cached || !use_disk_cache {
+      // A dist-tag may point to a version the registry doesn't have.
+      let dist = &packument
+        .versions
+        .get(&version)
+        .ok_or_else(|| {
+          ModuleNotFoundError::new(format!(
+            "npm package \"{}\" has no version \"{}\"",
+            req.name, version
+          ))
+        })?
+        .dist;
+      let tarball_url = Url::parse(&dist.tarball)?;
+      permissions.check_net_url(&tarball_url)?;
+      info!("{} {}", colors::green("Download".to_string()), tarball_url);
+      let data = self.fetch_bytes(&tarball_url, None).await?;
+      check_integrity(req, dist, &data)?;
+      extract_tarball(&data, &package_dir)?;
+    }
+
+    self
+      .resolved
+      .lock()
+      .unwrap()
+      .insert(req.to_string(), version);
+    Ok(package_dir)
+  }
+
+  /// Resolves an `npm:` URL to a file, downloading the package if needed.
+  pub async fn resolve(
+    &self,
+    module_url: &Url,
+    use_disk_cache: bool,
+    cached_only: bool,
+    permissions: &Permissions,
+  ) -> Result<PathBuf, ErrBox> {
+    let req = NpmPackageReq::from_url(module_url)?;
+    let package_dir = self
+      .ensure_package(&req, use_disk_cache, cached_only, permissions)
+      .await?;
+    resolve_package_entry(&package_dir, req.sub_path.as_deref())
+  }
+
+  /// Like `resolve`, but only consults the cache.
+  pub fn resolve_cached(
+    &self,
+    module_url: &Url,
+  ) -> Result<Option<PathBuf>, ErrBox> {
+    let req = NpmPackageReq::from_url(module_url)?;
+    match self.find_cached_version(&req) {
+      Some(version) => resolve_package_entry(
+        &self.package_dir(&req.name, &version)?,
+        req.sub_path.as_deref(),
+      )
+      .map(Some),
+      None => Ok(None),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn req(
+    name: &str,
+    version_req: Option<&str>,
+    sub_path: Option<&str>,
+  ) -> NpmPackageReq {
+    NpmPackageReq {
+      name: name.to_string(),
+      version_req: version_req.map(String::from),
+      sub_path: sub_path.map(String::from),
+    }
+  }
+
+  #[test]
+  fn parse_package_req() {
+    assert_eq!(
+      NpmPackageReq::parse("chalk").unwrap(),
+      req("chalk", None, None)
+    );
+    assert_eq!(
+      NpmPackageReq::parse("chalk@^4.0.0").unwrap(),
+      req("chalk", Some("^4.0.0"), None)
+    );
+    assert_eq!(
+      NpmPackageReq::parse("preact@10/hooks").unwrap(),
+      req("preact", Some("10"), Some("hooks"))
+    );
+    assert_eq!(
+      NpmPackageReq::parse("@std/esm@>=1.0.0 <2/lib/a.js").unwrap(),
+      req("@std/esm", Some(">=1.0.0 <2"), Some("lib/a.js"))
+    );
+    assert_eq!(
+      NpmPackageReq::parse("@scope/pkg/sub").unwrap(),
+      req("@scope/pkg", None, Some("sub"))
+    );
+    assert!(NpmPackageReq::parse("@scope").is_err());
+    assert!(NpmPackageReq::parse("@scope/").is_err());
+    assert!(NpmPackageReq::parse("").is_err());
+    assert!(NpmPackageReq::parse("..").is_err());
+    assert!(NpmPackageReq::parse("@../pkg").is_err());
+    assert!(NpmPackageReq::parse("a\\b").is_err());
+  }
+
+  #[test]
+  fn package_dirs() {
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let registry_url = Url::parse("http://localhost:4545/npm/").unwrap();
+    let resolver =
+      NpmResolver::new(temp_dir.path(), registry_url, Client::new());
+    assert!(resolver
+      .package_dir("@scope/pkg", "1.0.0-beta.1+b2")
+      .is_ok());
+    assert!(resolver.package_dir("pkg", "../../x").is_err());
+    assert!(resolver.package_dir("pkg", "1.0.0/../../x").is_err());
+    assert!(resolver.package_dir("pkg", "latest").is_err());
+    assert!(resolver.package_dir("..", "1.0.0").is_err());
+    assert!(resolver.package_dir("@scope/../x", "1.0.0").is_err());
+  }
+
+  #[test]
+  fn integrity_is_required() {
+    let req = req("pkg", None, None);
+    let data = b"tarball";
+    let dist = |shasum: Option<&str>, integrity: Option<&str>| PackageDist {
+      tarball: "https://example.com/pkg.tgz".to_string(),
+      shasum: shasum.map(String::from),
+      integrity: integrity.map(String::from),
+    };
+    let sha512 = base64::encode(digest::digest(&digest::SHA512, data).as_ref());
+    let integrity = format!("sha512-{}", sha512);
+    assert!(check_integrity(&req, &dist(None, Some(&integrity)), data).is_ok());
+    assert!(check_integrity(&req, &dist(None, Some("sha512-x")), data).is_err());
+    assert!(check_integrity(&req, &dist(Some("00"), None), data).is_err());
+    assert!(check_integrity(&req, &dist(None, None), data).is_err());
+  }
+
+  #[test]
+  fn select_versions() {
+    let versions: Vec<String> = vec![
+      "1.0.0",
+      "1.2.3",
+      "1.10.0",
+      "2.0.0-beta.1",
+      "2.1.0",
+      "3.0.0",
+      "4.0.0-rc.1",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    let mut tags = HashMap::new();
+    tags.insert("latest".to_string(), "2.1.0".to_string());
+    tags.insert("next".to_string(), "2.0.0-beta.1".to_string());
+
+    let select =
+      |req: Option<&str>| select_version(&versions, &tags, req).unwrap();
+    assert_eq!(select(None), Some("2.1.0".to_string()));
+    assert_eq!(select(Some("next")), Some("2.0.0-beta.1".to_string()));
+    assert_eq!(select(Some("^1.2.0")), Some("1.10.0".to_string()));
+    assert_eq!(select(Some("~1.2.0")), Some("1.2.3".to_string()));
+    assert_eq!(select(Some(">=1.0.0 <1.5")), Some("1.2.3".to_string()));
+    assert_eq!(select(Some("1.0.0 - 1.9.0")), Some("1.2.3".to_string()));
+    assert_eq!(select(Some("^1.0.0 || ^3.0.0")), Some("3.0.0".to_string()));
+    assert_eq!(select(Some("1.0.0")), Some("1.0.0".to_string()));
+    assert_eq!(select(Some("v1.0.0")), Some("1.0.0".to_string()));
+    assert_eq!(select(Some("1.2")), Some("1.2.3".to_string()));
+    assert_eq!(select(Some("1.x")), Some("1.10.0".to_string()));
+    assert_eq!(
+      select(Some("2.0.0-beta.1")),
+      Some("2.0.0-beta.1".to_string())
+    );
+    assert_eq!(select(Some("^4.0.0")), None);
+    assert!(select_version(&versions, &tags, Some("not a range")).is_err());
+
+    // Without dist-tags "latest" is the highest stable version.
+    assert_eq!(
+      select_version(&versions, &HashMap::new(), None).unwrap(),
+      Some("3.0.0".to_string())
+    );
+  }
+
+  fn tar_header(name: &str, size: usize, typeflag: u8) -> Vec<u8> {
+    let mut header = vec![0u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    let size = format!("{:011o}", size);
+    header[124..135].copy_from_slice(size.as_bytes());
+    header[156] = typeflag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header
+  }
+
+  fn tar_entry(tar: &mut Vec<u8>, name: &str, data: &[u8], typeflag: u8) {
+    tar.extend(tar_header(name, data.len(), typeflag));
+    tar.extend(data);
+    let padding = (512 - data.len() % 512) % 512;
+    tar.extend(vec![0u8; padding]);
+  }
+
+  #[test]
+  fn read_tar_entries() {
+    let long_name = format!("package/{}.js", "a".repeat(120));
+    let pax = format!("{} path={}\n", long_name.len() + 10, long_name);
+    assert_eq!(pax.len(), long_name.len() + 10);
+
+    let mut tar = vec![];
+    tar_entry(&mut tar, "package/", b"", b'5');
+    tar_entry(&mut tar, "package/package.json", b"{}", b'0');
+    tar_entry(&mut tar, "PaxHeader", pax.as_bytes(), b'x');
+    tar_entry(&mut tar, "package/truncated.js", b"long", b'0');
+    tar.extend(vec![0u8; 1024]);
+
+    let entries = read_tar(&tar).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].path, "package/package.json");
+    assert_eq!(entries[0].data, b"{}");
+    assert_eq!(entries[1].path, long_name);
+    assert_eq!(entries[1].data, b"long");
+
+    assert!(read_tar(&tar[..1024]).is_err());
+  }
+
+  #[test]
+  fn tar_entry_paths() {
+    assert_eq!(
+      tar_entry_path("package/lib/index.js"),
+      Some(PathBuf::from("lib/index.js"))
+    );
+    assert_eq!(
+      tar_entry_path("./node/index.js"),
+      Some(PathBuf::from("index.js"))
+    );
+    assert_eq!(tar_entry_path("package/"), None);
+    assert_eq!(tar_entry_path("package/../../etc/passwd"), None);
+    assert_eq!(tar_entry_path("/etc/passwd"), None);
+  }
+
+  fn write_package(dir: &Path, manifest: &str, files: &[&str]) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join("package.json"), manifest).unwrap();
+    for file in files {
+      let path = dir.join(file);
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, "export default 1;").unwrap();
+    }
+  }
+
+  #[test]
+  fn resolve_entries() {
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let dir = temp_dir.path();
+
+    let main = dir.join("main");
+    write_package(&main, r#"{ "main": "lib/main" }"#, &["lib/main.js"]);
+    assert_eq!(
+      resolve_package_entry(&main, None).unwrap(),
+      main.join("lib/main.js")
+    );
+
+    let module = dir.join("module");
+    write_package(
+      &module,
+      r#"{ "main": "cjs.js", "module": "./esm/index.mjs" }"#,
+      &["cjs.js", "esm/index.mjs", "utils/index.js"],
+    );
+    assert_eq!(
+      resolve_package_entry(&module, None).unwrap(),
+      module.join("esm/index.mjs")
+    );
+    assert_eq!(
+      resolve_package_entry(&module, Some("utils")).unwrap(),
+      module.join("utils/index.js")
+    );
+
+    let exports = dir.join("exports");
+    write_package(
+      &exports,
+      r#"{
+        "name": "exports",
+        "main": "index.cjs",
+        "exports": {
+          ".": { "require": "./index.cjs", "import": "./index.mjs" },
+          "./hooks": "./hooks/index.js",
+          "./features/*": "./src/features/*.js"
+        }
+      }"#,
+      &[
+        "index.cjs",
+        "index.mjs",
+        "hooks/index.js",
+        "src/features/a.js",
+      ],
+    );
+    assert_eq!(
+      resolve_package_entry(&exports, None).unwrap(),
+      exports.join("index.mjs")
+    );
+    assert_eq!(
+      resolve_package_entry(&exports, Some("hooks")).unwrap(),
+      exports.join("hooks/index.js")
+    );
+    assert_eq!(
+      resolve_package_entry(&exports, Some("features/a")).unwrap(),
+      exports.join("src/features/a.js")
+    );
+    assert!(resolve_package_entry(&exports, Some("index.cjs")).is_err());
+
+    let cjs_only = dir.join("cjs_only");
+    write_package(
+      &cjs_only,
+      r#"{ "exports": { "require": "./index.js" } }"#,
+      &["index.js"],
+    );
+    assert!(resolve_package_entry(&cjs_only, None).is_err());
+  }
+
+  #[test]
+  fn resolve_imports_inside_packages() {
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let registry_url = Url::parse(DEFAULT_REGISTRY_URL).unwrap();
+    let resolver =
+      NpmResolver::new(temp_dir.path(), registry_url, Client::new());
+    let package_dir = resolver.package_dir("@scope/pkg", "1.0.0").unwrap();
+    write_package(
+      &package_dir,
+      r#"{
+        "name": "@scope/pkg",
+        "dependencies": { "dep": "^2.0.0", "alias": "npm:real@1", "git": "user/repo" }
+      }"#,
+      &["index.js"],
+    );
+    let referrer = Url::from_file_path(package_dir.join("index.js"))
+      .unwrap()
+      .to_string();
+
+    let resolve = |specifier: &str| {
+      resolver
+        .resolve_import(specifier, &referrer)
+        .map(|s| s.to_string())
+    };
+    assert_eq!(resolve("dep").unwrap(), "npm:dep@^2.0.0");
+    assert_eq!(resolve("dep/sub.js").unwrap(), "npm:dep@^2.0.0/sub.js");
+    assert_eq!(resolve("alias").unwrap(), "npm:real@1");
+    assert_eq!(
+      resolve("path").unwrap(),
+      "https://deno.land/std@0.56.0/node/path.ts"
+    );
+    assert_eq!(
+      resolve("node:events").unwrap(),
+      "https://deno.land/std@0.56.0/node/events.ts"
+    );
+    assert_eq!(
+      resolve("./lib.js").unwrap(),
+      Url::from_file_path(package_dir.join("lib.js"))
+        .unwrap()
+        .to_string()
+    );
+    assert!(resolve("git").is_err());
+    assert!(resolve("missing").is_err());
+
+    // Bare specifiers outside of npm packages are left alone.
+    assert!(resolver
+      .resolve_import("dep", "file:///project/main.ts")
+      .is_err());
+    assert_eq!(
+      resolver
+        .resolve_import("node:fs", "file:///project/main.ts")
+        .unwrap()
+        .to_string(),
+      "https://deno.land/std@0.56.0/node/fs.ts"
+    );
+  }
+
+  #[test]
+  fn cached_versions() {
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let registry_url = Url::parse("http://localhost:4545/npm/").unwrap();
+    let resolver =
+      NpmResolver::new(temp_dir.path(), registry_url, Client::new());
+    assert!(resolver
+      .package_dir("dep", "1.0.0")
+      .unwrap()
+      .starts_with(temp_dir.path().join("localhost_PORT4545")));
+    for version in &["1.0.0", "1.5.0", "2.0.0"] {
+      let dir = resolver.package_dir("dep", version).unwrap();
+      write_package(&dir, "{}", &["index.js"]);
+    }
+    // Not a complete package.
+    fs::create_dir_all(resolver.package_dir("dep", "1.9.0").unwrap()).unwrap();
+    let dir = resolver.package_dir("dep-extra", "1.8.0").unwrap();
+    write_package(&dir, "{}", &[]);
+
+    assert_eq!(
+      resolver.find_cached_version(&req("dep", Some("^1.0.0"), None)),
+      Some("1.5.0".to_string())
+    );
+    assert_eq!(
+      resolver.find_cached_version(&req("dep", None, None)),
+      Some("2.0.0".to_string())
+    );
+    assert_eq!(
+      resolver.find_cached_version(&req("dep", Some("^3.0.0"), None)),
+      None
+    );
+
+    let url = Url::parse("npm:dep@1").unwrap();
+    assert_eq!(
+      resolver.resolve_cached(&url).unwrap(),
+      Some(
+        resolver
+          .package_dir("dep", "1.5.0")
+          .unwrap()
+          .join("index.js")
+      )
+    );
+  }
+
+  #[test]
+  fn module_permissions() {
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let registry_url = Url::parse("http://localhost:4545/npm/").unwrap();
+    let resolver =
+      NpmResolver::new(temp_dir.path(), registry_url, Client::new());
+    let permissions = resolver.module_permissions(&Permissions::default());
+    let package_dir = resolver.package_dir("dep", "1.0.0").unwrap();
+    let package_file = package_dir.join("index.js");
+    assert!(permissions.check_read(&package_file).is_ok());
+    // Paths only looking like they're in the cache aren't allowed.
+    let outside = package_dir.join("../../../a.js");
+    assert!(permissions.check_read(&outside).is_err());
+    assert!(permissions.check_read(temp_dir.path()).is_err());
+  }
+}
@@ -8,13 +8,22 @@
 //! - OpError: these are errors that happen during ops, which are passed
 //!   back into the runtime, where an exception object is created and thrown.
 //!   OpErrors have an integer code associated with them - access this via the
-//!   `kind` field.
+//!   `kind` field. When they come from the OS they also keep its `errno`, and
+//!   the message of the error that caused them is kept in `cause`, so that
+//!   both can be set on the exception thrown in JavaScript. Errors that don't
+//!   fit any kind, like a deno_core::CustomError, keep their `class_name`
+//!   instead; JavaScript looks it up in the classes given to
+//!   `registerErrorClass()` in js/errors.ts.
+//! - ModuleNotFoundError: a module that couldn't be loaded because it doesn't
+//!   exist. It's thrown in JavaScript as a NotFound OpError, but is kept apart
+//!   so that the CLI can exit with a code of its own.
 //! - Diagnostic: these are errors that originate in TypeScript's compiler.
 //!   They're similar to JSError, in that they have line numbers.
 //!   But Diagnostics are compile-time type errors, whereas JSErrors are runtime
 //!   exceptions.
 
 use crate::import_map::ImportMapError;
+use deno_core::CustomError;
 use deno_core::ErrBox;
 use deno_core::ModuleResolutionError;
 use rustyline::error::ReadlineError;
@@ -53,15 +62,42 @@ pub enum ErrorKind {
   Busy = 23,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct OpError {
   pub kind: ErrorKind,
   pub msg: String,
+  /// Error number reported by the OS, if any.
+  pub errno: Option<i32>,
+  /// Message of the underlying error, if any.
+  pub cause: Option<String>,
+  /// Name of the JavaScript error class to throw, for custom errors.
+  pub class_name: Option<String>,
 }
 
 impl OpError {
   fn new(kind: ErrorKind, msg: String) -> Self {
-    Self { kind, msg }
+    Self {
+      kind,
+      msg,
+      errno: None,
+      cause: None,
+      class_name: None,
+    }
+  }
+
+  /// Keeps the errno and the message of the root cause of `error`.
+  fn with_cause(mut self, error: &dyn Error) -> Self {
+    let mut source = error.source();
+    while let Some(err) = source {
+      if let Some(io_error) = err.downcast_ref::<io::Error>() {
+        self.errno = self.errno.or_else(|| io_error.raw_os_error());
+      }
+      if err.source().is_none() {
+        self.cause = Some(err.to_string());
+      }
+      source = err.source();
+    }
+    self
   }
 
   pub fn not_found(msg: String) -> Self {
@@ -76,6 +112,16 @@ impl OpError {
     Self::new(ErrorKind::Other, msg)
   }
 
+  /// An error thrown in JavaScript as an instance of the class registered as
+  /// `class_name`, or as an `Error` with that name if there is none.
+  ///
+  /// The class name is only sent back by JSON ops.
+  pub fn custom(class_name: &str, msg: String) -> Self {
+    let mut op_error = Self::other(msg);
+    op_error.class_name = Some(class_name.to_string());
+    op_error
+  }
+
   pub fn type_error(msg: String) -> Self {
     Self::new(ErrorKind::TypeError, msg)
   }
@@ -105,6 +151,24 @@ impl OpError {
     Self::new(ErrorKind::InvalidData, "invalid utf8".to_string())
   }
 
+  pub fn unstable(api_name: &str) -> OpError {
+    Self::other(format!(
+      "Unstable API '{}'. The --unstable flag must be provided.",
+      api_name
+    ))
+  }
+
+  /// The error of an async op that was canceled from JavaScript.
+  pub fn canceled() -> OpError {
+    Self::new(ErrorKind::Interrupted, "operation canceled".to_string())
+  }
+
+  /// The error of a read or write that waited longer than the idle timeout
+  /// of its resource.
+  pub fn timed_out() -> OpError {
+    Self::new(ErrorKind::TimedOut, "operation timed out".to_string())
+  }
+
   pub fn resource_unavailable() -> OpError {
     Self::new(
       ErrorKind::Busy,
@@ -121,6 +185,31 @@ impl fmt::Display for OpError {
   }
 }
 
+#[derive(Debug)]
+pub struct ModuleNotFoundError {
+  pub msg: String,
+}
+
+impl ModuleNotFoundError {
+  pub fn new(msg: String) -> Self {
+    Self { msg }
+  }
+}
+
+impl Error for ModuleNotFoundError {}
+
+impl fmt::Display for ModuleNotFoundError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(self.msg.as_str())
+  }
+}
+
+impl From<&ModuleNotFoundError> for OpError {
+  fn from(error: &ModuleNotFoundError) -> Self {
+    Self::not_found(error.msg.clone())
+  }
+}
+
 impl From<ImportMapError> for OpError {
   fn from(error: ImportMapError) -> Self {
     OpError::from(&error)
@@ -129,10 +218,19 @@ impl From<ImportMapError> for OpError {
 
 impl From<&ImportMapError> for OpError {
   fn from(error: &ImportMapError) -> Self {
-    Self {
-      kind: ErrorKind::Other,
-      msg: error.to_string(),
-    }
+    Self::new(ErrorKind::Other, error.to_string())
+  }
+}
+
+impl From<CustomError> for OpError {
+  fn from(error: CustomError) -> Self {
+    OpError::from(&error)
+  }
+}
+
+impl From<&CustomError> for OpError {
+  fn from(error: &CustomError) -> Self {
+    Self::custom(&error.class_name, error.message.clone())
   }
 }
 
@@ -144,10 +242,7 @@ impl From<ModuleResolutionError> for OpError {
 
 impl From<&ModuleResolutionError> for OpError {
   fn from(error: &ModuleResolutionError) -> Self {
-    Self {
-      kind: ErrorKind::URIError,
-      msg: error.to_string(),
-    }
+    Self::new(ErrorKind::URIError, error.to_string())
   }
 }
 
@@ -165,10 +260,7 @@ impl From<&VarError> for OpError {
       NotUnicode(..) => ErrorKind::InvalidData,
     };
 
-    Self {
-      kind,
-      msg: error.to_string(),
-    }
+    Self::new(kind, error.to_string())
   }
 }
 
@@ -205,10 +297,9 @@ impl From<&io::Error> for OpError {
       _ => unreachable!(),
     };
 
-    Self {
-      kind,
-      msg: error.to_string(),
-    }
+    let mut op_error = Self::new(kind, error.to_string()).with_cause(error);
+    op_error.errno = error.raw_os_error();
+    op_error
   }
 }
 
@@ -220,10 +311,7 @@ impl From<url::ParseError> for OpError {
 
 impl From<&url::ParseError> for OpError {
   fn from(error: &url::ParseError) -> Self {
-    Self {
-      kind: ErrorKind::URIError,
-      msg: error.to_string(),
-    }
+    Self::new(ErrorKind::URIError, error.to_string())
   }
 }
 impl From<reqwest::Error> for OpError {
@@ -234,6 +322,9 @@ impl From<reqwest::Error> for OpError {
 
 impl From<&reqwest::Error> for OpError {
   fn from(error: &reqwest::Error) -> Self {
+    if error.is_timeout() {
+      return OpError::timed_out();
+    }
     match error.source() {
       Some(err_ref) => None
         .or_else(|| {
@@ -251,14 +342,10 @@ impl From<&reqwest::Error> for OpError {
             .downcast_ref::<serde_json::error::Error>()
             .map(|e| e.into())
         })
-        .unwrap_or_else(|| Self {
-          kind: ErrorKind::Http,
-          msg: error.to_string(),
+        .unwrap_or_else(|| {
+          Self::new(ErrorKind::Http, error.to_string()).with_cause(error)
         }),
-      None => Self {
-        kind: ErrorKind::Http,
-        msg: error.to_string(),
-      },
+      None => Self::new(ErrorKind::Http, error.to_string()),
     }
   }
 }
@@ -281,10 +368,7 @@ impl From<&ReadlineError> for OpError {
       _ => unimplemented!(),
     };
 
-    Self {
-      kind,
-      msg: error.to_string(),
-    }
+    Self::new(kind, error.to_string())
   }
 }
 
@@ -304,10 +388,7 @@ impl From<&serde_json::error::Error> for OpError {
       Category::Eof => ErrorKind::UnexpectedEof,
     };
 
-    Self {
-      kind,
-      msg: error.to_string(),
-    }
+    Self::new(kind, error.to_string())
   }
 }
 
@@ -316,20 +397,34 @@ impl From<nix::Error> for OpError {
   fn from(error: nix::Error) -> Self {
     use nix::errno::Errno::*;
     let kind = match error {
-      nix::Error::Sys(EPERM) => ErrorKind::PermissionDenied,
+      nix::Error::Sys(EPERM) | nix::Error::Sys(EACCES) => {
+        ErrorKind::PermissionDenied
+      }
       nix::Error::Sys(EINVAL) => ErrorKind::TypeError,
-      nix::Error::Sys(ENOENT) => ErrorKind::NotFound,
-      nix::Error::Sys(UnknownErrno) => unreachable!(),
-      nix::Error::Sys(_) => unreachable!(),
+      nix::Error::Sys(ENOENT) | nix::Error::Sys(ESRCH) => ErrorKind::NotFound,
+      nix::Error::Sys(EEXIST) => ErrorKind::AlreadyExists,
+      nix::Error::Sys(EPIPE) => ErrorKind::BrokenPipe,
+      nix::Error::Sys(ECONNREFUSED) => ErrorKind::ConnectionRefused,
+      nix::Error::Sys(ECONNRESET) => ErrorKind::ConnectionReset,
+      nix::Error::Sys(ECONNABORTED) => ErrorKind::ConnectionAborted,
+      nix::Error::Sys(ENOTCONN) => ErrorKind::NotConnected,
+      nix::Error::Sys(EADDRINUSE) => ErrorKind::AddrInUse,
+      nix::Error::Sys(EADDRNOTAVAIL) => ErrorKind::AddrNotAvailable,
+      nix::Error::Sys(ETIMEDOUT) => ErrorKind::TimedOut,
+      nix::Error::Sys(EINTR) => ErrorKind::Interrupted,
+      nix::Error::Sys(EBUSY) | nix::Error::Sys(ETXTBSY) => ErrorKind::Busy,
+      nix::Error::Sys(EBADF) => ErrorKind::BadResource,
+      // There is no kind for these; like io::Error, they only keep the errno.
+      // ENOTDIR, EISDIR, EMFILE, ENOSPC and the like all end up here.
+      nix::Error::Sys(_) => ErrorKind::Other,
       nix::Error::InvalidPath => ErrorKind::TypeError,
       nix::Error::InvalidUtf8 => ErrorKind::InvalidData,
-      nix::Error::UnsupportedOperation => unreachable!(),
+      nix::Error::UnsupportedOperation => ErrorKind::Other,
     };
 
-    Self {
-      kind,
-      msg: error.to_string(),
-    }
+    let mut op_error = Self::new(kind, error.to_string());
+    op_error.errno = error.as_errno().map(|errno| errno as i32);
+    op_error
   }
 }
 
@@ -350,10 +445,7 @@ impl From<&dlopen::Error> for OpError {
       NullSymbol => ErrorKind::Other,
     };
 
-    Self {
-      kind,
-      msg: error.to_string(),
-    }
+    Self::new(kind, error.to_string())
   }
 }
 
@@ -374,10 +466,7 @@ impl From<&notify::Error> for OpError {
       InvalidConfig(_) => ErrorKind::InvalidData,
     };
 
-    Self {
-      kind,
-      msg: error.to_string(),
-    }
+    Self::new(kind, error.to_string())
   }
 }
 
@@ -394,10 +483,12 @@ impl From<ErrBox> for OpError {
     }
 
     None
+      .or_else(|| error.downcast_ref::<OpError>().map(|e| e.clone()))
+      .or_else(|| error.downcast_ref::<CustomError>().map(|e| e.into()))
       .or_else(|| {
         error
-          .downcast_ref::<OpError>()
-          .map(|e| OpError::new(e.kind, e.msg.to_string()))
+          .downcast_ref::<ModuleNotFoundError>()
+          .map(|e| e.into())
       })
       .or_else(|| error.downcast_ref::<reqwest::Error>().map(|e| e.into()))
       .or_else(|| error.downcast_ref::<ImportMapError>().map(|e| e.into()))
@@ -449,6 +540,16 @@ mod tests {
     assert_eq!(err.to_string(), "foo");
   }
 
+  #[test]
+  fn test_unstable_error() {
+    let err = OpError::unstable("Deno.umask");
+    assert_eq!(err.kind, ErrorKind::Other);
+    assert_eq!(
+      err.to_string(),
+      "Unstable API 'Deno.umask'. The --unstable flag must be provided."
+    );
+  }
+
   #[test]
   fn test_io_error() {
     let err = OpError::from(io_error());
@@ -456,6 +557,52 @@ mod tests {
     assert_eq!(err.to_string(), "entity not found");
   }
 
+  #[test]
+  fn test_io_error_errno() {
+    let err = OpError::from(io::Error::from_raw_os_error(2));
+    assert_eq!(err.errno, Some(2));
+    assert_eq!(err.cause, None);
+
+    let err = OpError::from(io_error());
+    assert_eq!(err.errno, None);
+  }
+
+  #[test]
+  fn test_error_cause() {
+    #[derive(Debug)]
+    struct Wrapper(io::Error);
+
+    impl fmt::Display for Wrapper {
+      fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wrapped: {}", self.0)
+      }
+    }
+
+    impl Error for Wrapper {
+      fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+      }
+    }
+
+    let root = io::Error::from_raw_os_error(111);
+    let root_msg = root.to_string();
+    let err = OpError::other("failed".to_string())
+      .with_cause(&Wrapper(Wrapper(root)) as &dyn Error);
+    assert_eq!(err.errno, Some(111));
+    assert_eq!(err.cause, Some(root_msg));
+  }
+
+  #[test]
+  fn test_custom_error() {
+    let err = OpError::from(ErrBox::from(CustomError::new(
+      "EmbedderError",
+      "custom".to_string(),
+    )));
+    assert_eq!(err.kind, ErrorKind::Other);
+    assert_eq!(err.class_name, Some("EmbedderError".to_string()));
+    assert_eq!(err.to_string(), "custom");
+  }
+
   #[test]
   fn test_url_error() {
     let err = OpError::from(url_error());
@@ -463,7 +610,23 @@ mod tests {
     assert_eq!(err.to_string(), "empty host");
   }
 
-  // TODO find a way to easily test tokio errors and unix errors
+  // TODO find a way to easily test tokio errors
+
+  #[cfg(unix)]
+  #[test]
+  fn test_nix_error() {
+    use nix::errno::Errno;
+    let kind = |errno| OpError::from(nix::Error::Sys(errno)).kind;
+    assert_eq!(kind(Errno::EACCES), ErrorKind::PermissionDenied);
+    assert_eq!(kind(Errno::EEXIST), ErrorKind::AlreadyExists);
+    assert_eq!(kind(Errno::EPIPE), ErrorKind::BrokenPipe);
+    assert_eq!(kind(Errno::ECONNRESET), ErrorKind::ConnectionReset);
+    assert_eq!(kind(Errno::ESRCH), ErrorKind::NotFound);
+    assert_eq!(kind(Errno::ENOTDIR), ErrorKind::Other);
+
+    let err = OpError::from(nix::Error::Sys(Errno::EISDIR));
+    assert_eq!(err.errno, Some(Errno::EISDIR as i32));
+  }
 
   #[test]
   fn test_import_map_error() {
@@ -472,6 +635,14 @@ mod tests {
     assert_eq!(err.to_string(), "an import map error");
   }
 
+  #[test]
+  fn test_module_not_found_error() {
+    let err = ErrBox::from(ModuleNotFoundError::new("no module".to_string()));
+    let err = OpError::from(err);
+    assert_eq!(err.kind, ErrorKind::NotFound);
+    assert_eq!(err.to_string(), "no module");
+  }
+
   #[test]
   fn test_bad_resource() {
     let err = OpError::bad_resource("Resource has been closed".to_string());
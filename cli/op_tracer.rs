@@ -0,0 +1,242 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Records the ops of the main worker for `--trace-ops`, in the trace event
+//! format of chrome://tracing. The synchronous part of the dispatch of each
+//! op is a complete event, so that the ops that stall the event loop stand
+//! out, and each async op is also an async event from its dispatch to its
+//! completion.
+//!
+//! The events are written as they happen. The closing `]` of the array is
+//! optional in this format, so the trace of a program that crashed still
+//! loads.
+
+use deno_core::CoreIsolate;
+use deno_core::CoreIsolateState;
+use deno_core::ErrBox;
+use deno_core::Op;
+use deno_core::ZeroCopyBuf;
+use futures::future::FutureExt;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+
+pub struct OpTracer {
+  writer: Box<dyn Write>,
+  start: Instant,
+  pid: u32,
+  /// The id of the next async op, which pairs its begin and end events.
+  next_id: u64,
+  has_events: bool,
+  finished: bool,
+}
+
+impl OpTracer {
+  pub fn create(path: &Path) -> Result<Self, ErrBox> {
+    if let Some(dir) = path.parent() {
+      if !dir.as_os_str().is_empty() {
+        std::fs::create_dir_all(dir)?;
+      }
+    }
+    let file = File::create(path)?;
+    Ok(Self::new(Box::new(BufWriter::new(file))))
+  }
+
+  fn new(writer: Box<dyn Write>) -> Self {
+    Self {
+      writer,
+      start: Instant::now(),
+      pid: std::process::id(),
+      next_id: 0,
+      has_events: false,
+      finished: false,
+    }
+  }
+
+  /// The time since the tracer was created, in microseconds.
+  fn now(&self) -> u64 {
+    self.start.elapsed().as_micros() as u64
+  }
+
+  fn write_event(&mut self, mut event: Value) -> io::Result<()> {
+    event["pid"] = json!(self.pid);
+    event["tid"] = json!(0);
+    let separator = if self.has_events { ",\n" } else { "[\n" };
+    self.has_events = true;
+    self.writer.write_all(separator.as_bytes())?;
+    serde_json::to_writer(&mut self.writer, &event)?;
+    Ok(())
+  }
+
+  /// Closes the array of events. Called when the worker is dropped, and by
+  /// `Deno.exit()`, which doesn't drop it.
+  pub fn finish(&mut self) -> io::Result<()> {
+    if self.finished {
+      return Ok(());
+    }
+    self.finished = true;
+    let end = if self.has_events { "\n]\n" } else { "[]\n" };
+    self.writer.write_all(end.as_bytes())?;
+    self.writer.flush()
+  }
+}
+
+impl Drop for OpTracer {
+  fn drop(&mut self) {
+    let _ = self.finish();
+  }
+}
+
+/// Makes `tracer` record the ops registered so far in `isolate`.
+pub fn install(isolate: &mut CoreIsolate, tracer: Rc<RefCell<OpTracer>>) {
+  let state_rc = CoreIsolate::state(isolate);
+  let mut state = state_rc.borrow_mut();
+  state.op_registry.wrap_dispatchers(|name, dispatcher| {
+    let name = name.to_string();
+    let tracer = tracer.clone();
+    Rc::new(
+      move |isolate_state: &mut CoreIsolateState,
+            control: &[u8],
+            zero_copy: &mut [ZeroCopyBuf]|
+            -> Op {
+        let args = json!({
+          "bytesSentControl": control.len(),
+          "bytesSentZeroCopy":
+            zero_copy.iter().map(|b| b.len()).sum::<usize>(),
+        });
+        let ts = tracer.borrow().now();
+        let op = dispatcher(isolate_state, control, zero_copy);
+        trace_op(&tracer, &name, ts, args, op)
+      },
+    )
+  });
+}
+
+fn trace_op(
+  tracer: &Rc<RefCell<OpTracer>>,
+  name: &str,
+  ts: u64,
+  mut args: Value,
+  op: Op,
+) -> Op {
+  let mut t = tracer.borrow_mut();
+  let dur = t.now() - ts;
+  let (cat, fut, unref) = match op {
+    Op::Sync(buf) => {
+      args["bytesReceived"] = json!(buf.len());
+      let _ = t.write_event(json!({
+        "name": name,
+        "cat": "sync",
+        "ph": "X",
+        "ts": ts,
+        "dur": dur,
+        "args": args,
+      }));
+      return Op::Sync(buf);
+    }
+    Op::Async(fut) => ("async", fut, false),
+    Op::AsyncUnref(fut) => ("async_unref", fut, true),
+  };
+
+  let id = t.next_id;
+  t.next_id += 1;
+  let _ = t.write_event(json!({
+    "name": name,
+    "cat": cat,
+    "ph": "X",
+    "ts": ts,
+    "dur": dur,
+    "args": args,
+  }));
+  let _ = t.write_event(json!({
+    "name": name,
+    "cat": cat,
+    "ph": "b",
+    "id": id,
+    "ts": ts,
+  }));
+  drop(t);
+
+  let tracer = tracer.clone();
+  let name = name.to_string();
+  let fut = fut.map(move |buf| {
+    let mut t = tracer.borrow_mut();
+    let ts = t.now();
+    let _ = t.write_event(json!({
+      "name": name,
+      "cat": cat,
+      "ph": "e",
+      "id": id,
+      "ts": ts,
+      "args": { "bytesReceived": buf.len() },
+    }));
+    buf
+  });
+  if unref {
+    Op::AsyncUnref(fut.boxed_local())
+  } else {
+    Op::Async(fut.boxed_local())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Arc;
+  use std::sync::Mutex;
+
+  /// A writer whose output outlives the tracer.
+  #[derive(Clone, Default)]
+  struct Output(Arc<Mutex<Vec<u8>>>);
+
+  impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn trace_events() {
+    let output = Output::default();
+    let tracer = Rc::new(RefCell::new(OpTracer::new(Box::new(output.clone()))));
+
+    let op =
+      trace_op(&tracer, "op_sync", 0, json!({}), Op::Sync(Box::new([1])));
+    assert!(matches!(op, Op::Sync(_)));
+    let fut = futures::future::ready(vec![1, 2].into_boxed_slice());
+    let op = trace_op(
+      &tracer,
+      "op_async",
+      0,
+      json!({}),
+      Op::Async(fut.boxed_local()),
+    );
+    match op {
+      Op::Async(fut) => assert_eq!(futures::executor::block_on(fut).len(), 2),
+      _ => unreachable!(),
+    }
+    drop(tracer);
+
+    let output = output.0.lock().unwrap();
+    let events: Value = serde_json::from_slice(&output).unwrap();
+    let events = events.as_array().unwrap();
+    assert_eq!(events.len(), 4);
+    assert_eq!(events[0]["name"], "op_sync");
+    assert_eq!(events[0]["ph"], "X");
+    assert_eq!(events[0]["args"]["bytesReceived"], 1);
+    assert_eq!(events[1]["cat"], "async");
+    assert_eq!(events[1]["ph"], "X");
+    assert_eq!(events[2]["ph"], "b");
+    assert_eq!(events[3]["ph"], "e");
+    assert_eq!(events[3]["id"], events[2]["id"]);
+    assert_eq!(events[3]["args"]["bytesReceived"], 2);
+  }
+}
@@ -0,0 +1,111 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Connects to js/ops/dispatch_json.ts sendSyncBatch and sendAsyncBatch.
+//!
+//! `op_batch` runs a list of JSON ops in a single dispatch so that code doing
+//! many small operations (like stat-ing a thousand files) crosses the JS/Rust
+//! boundary once instead of once per op. The results are sent back together,
+//! in the order of the ops, each as the `{ ok }` or `{ err }` record the op
+//! would have responded with on its own.
+//!
+//! Only JSON ops can be batched, and sub ops can't take zero copy buffers.
+use super::dispatch_json::{json_err, Deserialize, JsonOp, Value};
+use crate::op_error::OpError;
+use crate::state::State;
+use deno_core::Buf;
+use deno_core::CoreIsolate;
+use deno_core::CoreIsolateState;
+use deno_core::Op;
+use deno_core::OpId;
+use deno_core::ZeroCopyBuf;
+use futures::future::FutureExt;
+
+pub fn init(i: &mut CoreIsolate, s: &State) {
+  i.register_op("op_batch", s.stateful_json_op2(op_batch));
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchArgs {
+  promise_id: Option<u64>,
+  ops: Vec<(OpId, Value)>,
+}
+
+/// Turns the response of a sub op back into its `{ ok }` or `{ err }` record.
+fn decode_response(buf: &[u8]) -> Value {
+  let mut value: Value = match serde_json::from_slice(buf) {
+    Ok(value) => value,
+    Err(e) => return json!({ "err": json_err(OpError::from(e)) }),
+  };
+  if let Some(obj) = value.as_object_mut() {
+    obj.remove("promiseId");
+  }
+  value
+}
+
+fn op_batch(
+  isolate_state: &mut CoreIsolateState,
+  _state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  let args: BatchArgs = serde_json::from_value(args)?;
+  let is_sync = args.promise_id.is_none();
+
+  // Look every op up first so that a bad batch doesn't run halfway.
+  let mut dispatchers = Vec::with_capacity(args.ops.len());
+  for (op_id, op_args) in args.ops {
+    let dispatcher = isolate_state.op_registry.get(op_id).ok_or_else(|| {
+      OpError::type_error(format!("Unknown op id: {}", op_id))
+    })?;
+    let mut op_args = match op_args {
+      Value::Object(op_args) => op_args,
+      _ => {
+        return Err(OpError::type_error(
+          "Arguments of batched ops must be objects".to_string(),
+        ))
+      }
+    };
+    // Sub ops respond asynchronously exactly when the batch does. Their
+    // responses aren't routed by promise id, so they all get 0, which JS never
+    // hands out; canceling the batch cancels them too.
+    op_args.remove("promiseId");
+    if args.promise_id.is_some() {
+      op_args.insert("promiseId".to_string(), json!(0));
+    }
+    let control = serde_json::to_vec(&op_args).unwrap();
+    dispatchers.push((dispatcher, control));
+  }
+
+  let mut responses = Vec::with_capacity(dispatchers.len());
+  for (dispatcher, control) in dispatchers {
+    let op = dispatcher(isolate_state, &control, &mut []);
+    responses.push(op);
+  }
+
+  if is_sync {
+    let results = responses
+      .into_iter()
+      .map(|op| match op {
+        Op::Sync(buf) => decode_response(&buf),
+        // JSON ops only go async when given a promise id, so this is an op
+        // of another kind.
+        Op::Async(_) | Op::AsyncUnref(_) => json!({
+          "err": json_err(OpError::type_error(
+            "Only JSON ops can be batched".to_string()
+          ))
+        }),
+      })
+      .collect();
+    return Ok(JsonOp::Sync(Value::Array(results)));
+  }
+
+  let futures = responses.into_iter().map(|op| match op {
+    Op::Sync(buf) => futures::future::ready(buf).boxed_local(),
+    Op::Async(fut) | Op::AsyncUnref(fut) => fut,
+  });
+  let fut = futures::future::join_all(futures).map(|bufs: Vec<Buf>| {
+    let results = bufs.iter().map(|buf| decode_response(buf)).collect();
+    Ok(Value::Array(results))
+  });
+  Ok(JsonOp::Async(fut.boxed_local()))
+}
@@ -0,0 +1,245 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Ops backing `Deno.bench()`. The JS side only runs the benchmark function
+//! the number of times it is told to; the timing, the warmup and the choice
+//! of the number of iterations per sample all happen here, so that the
+//! measures don't depend on the precision of `performance.now()`.
+use super::dispatch_json::{Deserialize, JsonOp, Value};
+use crate::op_error::OpError;
+use crate::state::State;
+use deno_core::CoreIsolate;
+use deno_core::CoreIsolateState;
+use deno_core::ZeroCopyBuf;
+use serde::Serialize;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The benchmark is run for at least this long before being measured, which
+/// gives the JIT time to optimize it and gives an estimate of its duration.
+const WARMUP_TIME: Duration = Duration::from_millis(100);
+/// The targeted duration of each sample. Fast benchmarks are run many times
+/// per sample so that the cost of the ops doesn't show in the results.
+const SAMPLE_TIME: Duration = Duration::from_millis(10);
+/// Samples are collected until this time has been spent measuring...
+const MEASURE_TIME: Duration = Duration::from_secs(1);
+/// ...but there are never less than `MIN_SAMPLES` or more than `MAX_SAMPLES`.
+const MIN_SAMPLES: usize = 5;
+const MAX_SAMPLES: usize = 100;
+
+pub fn init(i: &mut CoreIsolate, s: &State) {
+  i.register_op("op_bench_begin", s.stateful_json_op2(op_bench_begin));
+  i.register_op("op_bench_step", s.stateful_json_op2(op_bench_step));
+}
+
+struct BenchResource {
+  /// The number of times the benchmark is run in the current sample.
+  iterations: u64,
+  /// When the current sample started.
+  started: Instant,
+  /// The time spent and the iterations run while warming up, or `None` once
+  /// the benchmark is being measured.
+  warmup: Option<(Duration, u64)>,
+  measured: Duration,
+  /// The duration of one iteration in each sample, in nanoseconds.
+  samples: Vec<f64>,
+}
+
+impl BenchResource {
+  fn new() -> Self {
+    Self {
+      iterations: 1,
+      started: Instant::now(),
+      warmup: Some((Duration::default(), 0)),
+      measured: Duration::default(),
+      samples: vec![],
+    }
+  }
+
+  /// Ends the current sample, which took `elapsed`. Returns the number of
+  /// iterations of the next sample, or `None` if the benchmark is done.
+  fn step(&mut self, elapsed: Duration) -> Option<u64> {
+    if let Some((time, iterations)) = self.warmup.as_mut() {
+      *time += elapsed;
+      *iterations += self.iterations;
+      if *time < WARMUP_TIME {
+        self.iterations = (self.iterations * 2).min(1 << 20);
+      } else {
+        let per_iteration = time.as_nanos() as f64 / *iterations as f64;
+        let per_sample = SAMPLE_TIME.as_nanos() as f64 / per_iteration;
+        self.iterations = (per_sample as u64).max(1);
+        self.warmup = None;
+      }
+      return Some(self.iterations);
+    }
+
+    self.measured += elapsed;
+    self
+      .samples
+      .push(elapsed.as_nanos() as f64 / self.iterations as f64);
+    let enough =
+      self.measured >= MEASURE_TIME && self.samples.len() >= MIN_SAMPLES;
+    if enough || self.samples.len() >= MAX_SAMPLES {
+      None
+    } else {
+      Some(self.iterations)
+    }
+  }
+}
+
+/// The statistics of a benchmark. All durations are per iteration, in
+/// nanoseconds.
+#[derive(Debug, PartialEq, Serialize)]
+struct BenchStats {
+  samples: usize,
+  iterations: u64,
+  mean: f64,
+  stddev: f64,
+  min: f64,
+  max: f64,
+  p50: f64,
+  p75: f64,
+  p99: f64,
+}
+
+impl BenchStats {
+  fn new(mut samples: Vec<f64>, iterations: u64) -> Self {
+    assert!(!samples.is_empty());
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let len = samples.len();
+    let mean = samples.iter().sum::<f64>() / len as f64;
+    let variance = if len > 1 {
+      samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (len - 1) as f64
+    } else {
+      0.0
+    };
+    // Nearest-rank percentiles.
+    let percentile = |p: f64| {
+      let rank = (p / 100.0 * len as f64).ceil() as usize;
+      samples[rank.max(1) - 1]
+    };
+    Self {
+      samples: len,
+      iterations: iterations * len as u64,
+      mean,
+      stddev: variance.sqrt(),
+      min: samples[0],
+      max: samples[len - 1],
+      p50: percentile(50.0),
+      p75: percentile(75.0),
+      p99: percentile(99.0),
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct BenchArgs {
+  rid: u32,
+}
+
+// Starts a benchmark. Its first sample, of a single iteration, starts when
+// this op returns.
+fn op_bench_begin(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  _args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_hrtime()?;
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  let bench = BenchResource::new();
+  let iterations = bench.iterations;
+  let rid = resource_table.add("bench", Box::new(bench));
+  Ok(JsonOp::Sync(
+    json!({ "rid": rid, "iterations": iterations }),
+  ))
+}
+
+// Ends the current sample and starts the next one, or closes the benchmark
+// and returns its statistics once enough samples have been collected.
+fn op_bench_step(
+  isolate_state: &mut CoreIsolateState,
+  _state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  let elapsed_at = Instant::now();
+  let BenchArgs { rid } = serde_json::from_value(args)?;
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  let bench = resource_table
+    .get_mut::<BenchResource>(rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+  let elapsed = elapsed_at - bench.started;
+  match bench.step(elapsed) {
+    Some(iterations) => {
+      bench.started = Instant::now();
+      Ok(JsonOp::Sync(json!({ "iterations": iterations })))
+    }
+    None => {
+      let samples = std::mem::replace(&mut bench.samples, vec![]);
+      let stats = BenchStats::new(samples, bench.iterations);
+      resource_table.close(rid);
+      Ok(JsonOp::Sync(json!({ "stats": stats })))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bench_stats() {
+    let stats = BenchStats::new(vec![4.0, 2.0, 3.0, 1.0, 5.0], 10);
+    assert_eq!(stats.samples, 5);
+    assert_eq!(stats.iterations, 50);
+    assert_eq!(stats.mean, 3.0);
+    assert!((stats.stddev - 2.5f64.sqrt()).abs() < 1e-9);
+    assert_eq!(stats.min, 1.0);
+    assert_eq!(stats.max, 5.0);
+    assert_eq!(stats.p50, 3.0);
+    assert_eq!(stats.p75, 4.0);
+    assert_eq!(stats.p99, 5.0);
+  }
+
+  #[test]
+  fn bench_stats_single_sample() {
+    let stats = BenchStats::new(vec![7.0], 1);
+    assert_eq!(stats.stddev, 0.0);
+    assert_eq!(stats.p50, 7.0);
+    assert_eq!(stats.p99, 7.0);
+  }
+
+  #[test]
+  fn bench_warmup() {
+    let mut bench = BenchResource::new();
+    // 1ms per iteration: the iterations double until the warmup is over.
+    for i in 1..7 {
+      let elapsed = Duration::from_millis(bench.iterations);
+      assert_eq!(bench.step(elapsed), Some(1 << i));
+    }
+    assert_eq!(bench.step(Duration::from_millis(64)), Some(10));
+    assert!(bench.warmup.is_none());
+  }
+
+  #[test]
+  fn bench_samples() {
+    let mut bench = BenchResource::new();
+    assert_eq!(bench.step(Duration::from_millis(200)), Some(1));
+    // Slow benchmarks still get the minimum number of samples.
+    for _ in 1..MIN_SAMPLES {
+      assert_eq!(bench.step(Duration::from_millis(300)), Some(1));
+    }
+    assert_eq!(bench.step(Duration::from_millis(300)), None);
+    assert_eq!(bench.samples.len(), MIN_SAMPLES);
+
+    // Fast ones stop at the maximum number of samples.
+    let mut bench = BenchResource::new();
+    while bench.warmup.is_some() {
+      bench.step(Duration::from_micros(100 * bench.iterations));
+    }
+    assert_eq!(bench.iterations, 100);
+    for _ in 1..MAX_SAMPLES {
+      assert_eq!(bench.step(Duration::from_millis(1)), Some(100));
+    }
+    assert_eq!(bench.step(Duration::from_millis(1)), None);
+  }
+}
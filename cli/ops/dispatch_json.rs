@@ -1,4 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use super::eager::poll_eagerly;
 use crate::op_error::OpError;
 use deno_core::Buf;
 use deno_core::CoreIsolateState;
@@ -23,10 +24,13 @@ pub enum JsonOp {
   AsyncUnref(AsyncJsonOp),
 }
 
-fn json_err(err: OpError) -> Value {
+pub fn json_err(err: OpError) -> Value {
   json!({
     "message": err.msg,
     "kind": err.kind as u32,
+    "errno": err.errno,
+    "cause": err.cause,
+    "className": err.class_name,
   })
 }
 
@@ -44,6 +48,15 @@ struct AsyncArgs {
   promise_id: Option<u64>,
 }
 
+/// Decodes the control buffer of a JSON op into the promise id, which is only
+/// set for async calls, and the arguments of the op.
+pub fn parse_control(control: &[u8]) -> Result<(Option<u64>, Value), OpError> {
+  let AsyncArgs { promise_id } = serde_json::from_slice(control)?;
+  // The control buffer was already parsed once, so this can't fail.
+  let args = serde_json::from_slice(control)?;
+  Ok((promise_id, args))
+}
+
 pub fn json_op<D>(
   d: D,
 ) -> impl Fn(&mut CoreIsolateState, &[u8], &mut [ZeroCopyBuf]) -> Op
@@ -57,35 +70,39 @@ where
   move |isolate_state: &mut CoreIsolateState,
         control: &[u8],
         zero_copy: &mut [ZeroCopyBuf]| {
-    let async_args: AsyncArgs = match serde_json::from_slice(control) {
-      Ok(args) => args,
-      Err(e) => {
-        let buf = serialize_result(None, Err(OpError::from(e)));
-        return Op::Sync(buf);
-      }
+    let (promise_id, args) = match parse_control(control) {
+      Ok(parsed) => parsed,
+      Err(err) => return Op::Sync(serialize_result(None, Err(err))),
     };
-    let promise_id = async_args.promise_id;
     let is_sync = promise_id.is_none();
 
-    let result = serde_json::from_slice(control)
-      .map_err(OpError::from)
-      .and_then(|args| d(isolate_state, args, zero_copy));
+    // JS only calls sync ops with `sendSync()` and async ones with
+    // `sendAsync()`, but the control buffer can't be trusted to say so.
+    let result = match d(isolate_state, args, zero_copy) {
+      Ok(JsonOp::Sync(_)) if !is_sync => Err(OpError::type_error(
+        "Sync op called with a promise id".to_string(),
+      )),
+      Ok(JsonOp::Async(_)) | Ok(JsonOp::AsyncUnref(_)) if is_sync => Err(
+        OpError::type_error("Async op called without a promise id".to_string()),
+      ),
+      result => result,
+    };
 
     // Convert to Op
     match result {
       Ok(JsonOp::Sync(sync_value)) => {
-        assert!(promise_id.is_none());
         Op::Sync(serialize_result(promise_id, Ok(sync_value)))
       }
-      Ok(JsonOp::Async(fut)) => {
-        assert!(promise_id.is_some());
-        let fut2 = fut.then(move |result| {
-          futures::future::ready(serialize_result(promise_id, result))
-        });
-        Op::Async(fut2.boxed_local())
-      }
+      Ok(JsonOp::Async(fut)) => match poll_eagerly(fut) {
+        Ok(result) => Op::Sync(serialize_result(promise_id, result)),
+        Err(fut) => {
+          let fut2 = fut.then(move |result| {
+            futures::future::ready(serialize_result(promise_id, result))
+          });
+          Op::Async(fut2.boxed_local())
+        }
+      },
       Ok(JsonOp::AsyncUnref(fut)) => {
-        assert!(promise_id.is_some());
         let fut2 = fut.then(move |result| {
           futures::future::ready(serialize_result(promise_id, result))
         });
@@ -114,3 +131,19 @@ where
     Ok(JsonOp::Async(fut.boxed_local()))
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_control() {
+    let (promise_id, args) =
+      parse_control(b"{\"promiseId\":3,\"rid\":1}").unwrap();
+    assert_eq!(promise_id, Some(3));
+    assert_eq!(args["rid"], 1);
+    assert_eq!(parse_control(b"{}").unwrap().0, None);
+    assert!(parse_control(b"{\"promiseId\":-1}").is_err());
+    assert!(parse_control(b"").is_err());
+  }
+}
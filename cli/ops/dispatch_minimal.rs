@@ -4,6 +4,7 @@
 //! alternative to flatbuffers using a very simple list of int32s to lay out
 //! messages. The first i32 is used to determine if a message a flatbuffer
 //! message or a "minimal" message.
+use super::eager::poll_eagerly;
 use crate::op_error::OpError;
 use byteorder::{LittleEndian, WriteBytesExt};
 use deno_core::Buf;
@@ -29,10 +30,11 @@ pub struct Record {
 
 impl Into<Buf> for Record {
   fn into(self) -> Buf {
-    let vec = vec![self.promise_id, self.arg, self.result];
-    let buf32 = vec.into_boxed_slice();
-    let ptr = Box::into_raw(buf32) as *mut [u8; 3 * 4];
-    unsafe { Box::from_raw(ptr) }
+    let mut buf = Vec::with_capacity(3 * 4);
+    for n in &[self.promise_id, self.arg, self.result] {
+      buf.extend_from_slice(&n.to_ne_bytes());
+    }
+    buf.into_boxed_slice()
   }
 }
 
@@ -40,12 +42,27 @@ pub struct ErrorRecord {
   pub promise_id: i32,
   pub arg: i32,
   pub error_code: i32,
+  /// Error number reported by the OS, or 0.
+  pub errno: i32,
   pub error_message: Vec<u8>,
 }
 
+impl ErrorRecord {
+  fn new(promise_id: i32, err: OpError) -> Self {
+    Self {
+      promise_id,
+      arg: -1,
+      error_code: err.kind as i32,
+      errno: err.errno.unwrap_or(0),
+      error_message: err.msg.into_bytes(),
+    }
+  }
+}
+
 impl Into<Buf> for ErrorRecord {
   fn into(self) -> Buf {
-    let v32: Vec<i32> = vec![self.promise_id, self.arg, self.error_code];
+    let v32: Vec<i32> =
+      vec![self.promise_id, self.arg, self.error_code, self.errno];
     let mut v8: Vec<u8> = Vec::new();
     for n in v32 {
       v8.write_i32::<LittleEndian>(n).unwrap();
@@ -61,37 +78,35 @@ impl Into<Buf> for ErrorRecord {
 #[test]
 fn test_error_record() {
   let expected = vec![
-    1, 0, 0, 0, 255, 255, 255, 255, 10, 0, 0, 0, 69, 114, 114, 111, 114, 32,
-    32, 32,
+    1, 0, 0, 0, 255, 255, 255, 255, 10, 0, 0, 0, 2, 0, 0, 0, 69, 114, 114, 111,
+    114, 32, 32, 32,
   ];
   let err_record = ErrorRecord {
     promise_id: 1,
     arg: -1,
     error_code: 10,
+    errno: 2,
     error_message: "Error".to_string().as_bytes().to_owned(),
   };
   let buf: Buf = err_record.into();
   assert_eq!(buf, expected.into_boxed_slice());
 }
 
+/// Decodes the three i32 of a minimal record, in native byte order. The
+/// control buffer comes straight from JS, so it isn't assumed to be aligned.
 pub fn parse_min_record(bytes: &[u8]) -> Option<Record> {
-  if bytes.len() % std::mem::size_of::<i32>() != 0 {
+  if bytes.len() != 3 * 4 {
     return None;
   }
-  let p = bytes.as_ptr();
-  #[allow(clippy::cast_ptr_alignment)]
-  let p32 = p as *const i32;
-  let s = unsafe { std::slice::from_raw_parts(p32, bytes.len() / 4) };
-
-  if s.len() != 3 {
-    return None;
-  }
-  let ptr = s.as_ptr();
-  let ints = unsafe { std::slice::from_raw_parts(ptr, 3) };
+  let int_at = |i: usize| {
+    let mut int = [0u8; 4];
+    int.copy_from_slice(&bytes[i * 4..(i + 1) * 4]);
+    i32::from_ne_bytes(int)
+  };
   Some(Record {
-    promise_id: ints[0],
-    arg: ints[1],
-    result: ints[2],
+    promise_id: int_at(0),
+    arg: int_at(1),
+    result: int_at(2),
   })
 }
 
@@ -112,6 +127,24 @@ fn test_parse_min_record() {
 
   let buf = vec![5];
   assert_eq!(parse_min_record(&buf), None);
+
+  // Records don't have to be aligned.
+  let buf = vec![0, 1, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0];
+  assert_eq!(parse_min_record(&buf[1..]).map(|r| r.arg), Some(3));
+
+  let buf = vec![0; 16];
+  assert_eq!(parse_min_record(&buf), None);
+}
+
+#[test]
+fn test_min_record_round_trip() {
+  let record = Record {
+    promise_id: 7,
+    arg: -1,
+    result: i32::MIN,
+  };
+  let buf: Buf = record.into();
+  assert_eq!(parse_min_record(&buf), Some(record));
 }
 
 pub fn minimal_op<D>(
@@ -123,59 +156,33 @@ where
   move |isolate_state: &mut CoreIsolateState,
         control: &[u8],
         zero_copy: &mut [ZeroCopyBuf]| {
-    let mut record = match parse_min_record(control) {
+    let record = match parse_min_record(control) {
       Some(r) => r,
       None => {
         let e = OpError::type_error("Unparsable control buffer".to_string());
-        let error_record = ErrorRecord {
-          promise_id: 0,
-          arg: -1,
-          error_code: e.kind as i32,
-          error_message: e.msg.as_bytes().to_owned(),
-        };
-        return Op::Sync(error_record.into());
+        return Op::Sync(ErrorRecord::new(0, e).into());
       }
     };
     let is_sync = record.promise_id == 0;
     let rid = record.arg;
     let min_op = d(isolate_state, is_sync, rid, zero_copy);
 
-    match min_op {
-      MinimalOp::Sync(sync_result) => Op::Sync(match sync_result {
+    let into_buf = move |result: Result<i32, OpError>| -> Buf {
+      match result {
         Ok(r) => {
+          let mut record = record;
           record.result = r;
           record.into()
         }
-        Err(err) => {
-          let error_record = ErrorRecord {
-            promise_id: record.promise_id,
-            arg: -1,
-            error_code: err.kind as i32,
-            error_message: err.msg.as_bytes().to_owned(),
-          };
-          error_record.into()
-        }
-      }),
-      MinimalOp::Async(min_fut) => {
-        let fut = async move {
-          match min_fut.await {
-            Ok(r) => {
-              record.result = r;
-              record.into()
-            }
-            Err(err) => {
-              let error_record = ErrorRecord {
-                promise_id: record.promise_id,
-                arg: -1,
-                error_code: err.kind as i32,
-                error_message: err.msg.as_bytes().to_owned(),
-              };
-              error_record.into()
-            }
-          }
-        };
-        Op::Async(fut.boxed_local())
+        Err(err) => ErrorRecord::new(record.promise_id, err).into(),
       }
+    };
+    match min_op {
+      MinimalOp::Sync(sync_result) => Op::Sync(into_buf(sync_result)),
+      MinimalOp::Async(min_fut) => match poll_eagerly(min_fut) {
+        Ok(result) => Op::Sync(into_buf(result)),
+        Err(min_fut) => Op::Async(min_fut.map(into_buf).boxed_local()),
+      },
     }
   }
 }
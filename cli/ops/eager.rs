@@ -0,0 +1,53 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Eager polling of async ops.
+//!
+//! The future of an async op is normally first polled on the next turn of the
+//! event loop. Many of them, like a read from a socket that already has data
+//! or an accept with a connection waiting, are ready right away, and would pay
+//! a full event loop wakeup for nothing. The dispatchers therefore poll the
+//! future once as soon as the op is dispatched, and respond synchronously if
+//! it's done; JS handles a synchronous response to an async op like any other.
+//!
+//! A future that isn't ready is queued as usual, and polled again with the
+//! isolate's waker before the event loop waits, so the noop waker it was
+//! first polled with is never relied upon.
+//!
+//! This is the same on every platform. On Windows, where sockets are driven by
+//! IOCP, poll_read starts an overlapped read and returns pending if none of
+//! its data was received yet; the completion then makes the socket readable,
+//! and the queued future picks the data up without a second dispatch.
+use futures::task::noop_waker_ref;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+/// Polls `fut` once, returning its output if it's ready, otherwise the future
+/// itself.
+pub fn poll_eagerly<T>(
+  mut fut: Pin<Box<dyn Future<Output = T>>>,
+) -> Result<T, Pin<Box<dyn Future<Output = T>>>> {
+  let mut cx = Context::from_waker(noop_waker_ref());
+  match fut.as_mut().poll(&mut cx) {
+    Poll::Ready(output) => Ok(output),
+    Poll::Pending => Err(fut),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use futures::future::FutureExt;
+
+  #[test]
+  fn ready_future() {
+    let fut = futures::future::ready(42).boxed_local();
+    assert_eq!(poll_eagerly(fut).ok(), Some(42));
+  }
+
+  #[test]
+  fn pending_future() {
+    let fut = futures::future::pending::<()>().boxed_local();
+    assert!(poll_eagerly(fut).is_err());
+  }
+}
@@ -3,11 +3,9 @@ use super::dispatch_json::{Deserialize, JsonOp, Value};
 use crate::diagnostics::Diagnostic;
 use crate::op_error::OpError;
 use crate::source_maps::get_orig_position;
-use crate::source_maps::CachedMaps;
 use crate::state::State;
 use deno_core::CoreIsolate;
 use deno_core::ZeroCopyBuf;
-use std::collections::HashMap;
 
 pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op(
@@ -35,14 +33,18 @@ fn op_apply_source_map(
 ) -> Result<JsonOp, OpError> {
   let args: ApplySourceMap = serde_json::from_value(args)?;
 
-  let mut mappings_map: CachedMaps = HashMap::new();
+  // Stack traces are mapped one frame at a time, so parsed source maps are
+  // kept on the compiler instead of being re-read from disk for every frame.
+  let global_state = state.borrow().global_state.clone();
+  let ts_compiler = &global_state.ts_compiler;
+  let mut mappings_map = ts_compiler.source_maps.lock().unwrap();
   let (orig_file_name, orig_line_number, orig_column_number) =
     get_orig_position(
       args.file_name,
       args.line_number.into(),
       args.column_number.into(),
       &mut mappings_map,
-      &state.borrow().global_state.ts_compiler,
+      ts_compiler,
     );
 
   Ok(JsonOp::Sync(json!({
@@ -0,0 +1,524 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Duplication of resources and, on Unix, passing them to other processes
+//! over Unix sockets (SCM_RIGHTS).
+//!
+//! Each message that carries resources is a single byte along with the file
+//! descriptors, so a socket used for this shouldn't carry other data at the
+//! same time. Received descriptors are turned back into resources of the
+//! kind they are: files, TCP streams and listeners, and Unix streams.
+use super::dispatch_json::{Deserialize, JsonOp, Value};
+use crate::op_error::OpError;
+use crate::state::State;
+use deno_core::CoreIsolate;
+use deno_core::CoreIsolateState;
+use deno_core::ResourceTable;
+use deno_core::ZeroCopyBuf;
+
+#[cfg(not(unix))]
+use super::io::std_file_resource;
+
+use super::io::{FileMetadata, StreamResource, StreamResourceHolder};
+#[cfg(unix)]
+use futures::future::FutureExt;
+#[cfg(unix)]
+use nix::errno::Errno;
+#[cfg(unix)]
+use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+#[cfg(unix)]
+use nix::poll::{poll, PollFd, PollFlags};
+#[cfg(unix)]
+use nix::sys::socket::{
+  getpeername, getsockname, getsockopt, recvmsg, sendmsg, sockopt,
+  ControlMessage, ControlMessageOwned, MsgFlags, SockAddr, SockType,
+};
+#[cfg(unix)]
+use nix::sys::uio::IoVec;
+#[cfg(unix)]
+use std::cell::RefCell;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+#[cfg(unix)]
+use std::rc::Rc;
+
+pub fn init(i: &mut CoreIsolate, s: &State) {
+  i.register_op("op_dup", s.stateful_json_op2(op_dup));
+  #[cfg(unix)]
+  {
+    i.register_op("op_send_resources", s.stateful_json_op2(op_send_resources));
+    i.register_op(
+      "op_receive_resources",
+      s.stateful_json_op2(op_receive_resources),
+    );
+  }
+}
+
+#[derive(Deserialize)]
+struct DupArgs {
+  rid: i32,
+}
+
+/// Duplicates the file descriptor or handle of a resource into a new
+/// resource. Closing either one leaves the other open.
+fn op_dup(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.dup")?;
+  let args: DupArgs = serde_json::from_value(args)?;
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  let rid = dup(&mut resource_table, args.rid as u32)?;
+  Ok(JsonOp::Sync(json!(rid)))
+}
+
+fn dup(resource_table: &mut ResourceTable, rid: u32) -> Result<u32, OpError> {
+  let file = clone_file(resource_table, rid)?;
+  let (rid, _kind) = add_file(resource_table, file)?;
+  Ok(rid)
+}
+
+/// Returns a new file descriptor or handle for the resource `rid`.
+#[cfg(unix)]
+fn clone_file(
+  resource_table: &ResourceTable,
+  rid: u32,
+) -> Result<std::fs::File, OpError> {
+  let fd = raw_fd(resource_table, rid)?;
+  let fd = fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(0))?;
+  Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+}
+
+#[cfg(not(unix))]
+fn clone_file(
+  resource_table: &mut ResourceTable,
+  rid: u32,
+) -> Result<std::fs::File, OpError> {
+  std_file_resource(resource_table, rid, move |r| match r {
+    Ok(std_file) => std_file.try_clone().map_err(OpError::from),
+    Err(_) => Err(OpError::type_error(
+      "Only files can be duplicated or transferred on Windows".to_string(),
+    )),
+  })
+}
+
+/// Adds the resource that `file` really is to the resource table and returns
+/// its rid and kind.
+#[cfg(unix)]
+fn add_file(
+  resource_table: &mut ResourceTable,
+  file: std::fs::File,
+) -> Result<(u32, &'static str), OpError> {
+  add_fd(resource_table, file.into_raw_fd())
+}
+
+#[cfg(not(unix))]
+fn add_file(
+  resource_table: &mut ResourceTable,
+  file: std::fs::File,
+) -> Result<(u32, &'static str), OpError> {
+  let tokio_file = tokio::fs::File::from_std(file);
+  let rid = resource_table.add(
+    "fsFile",
+    Box::new(StreamResourceHolder::new(StreamResource::FsFile(Some((
+      tokio_file,
+      FileMetadata::default(),
+    ))))),
+  );
+  Ok((rid, "fsFile"))
+}
+
+/// A resource taken out of the resource table of one isolate, to be added to
+/// the table of another one, possibly on another thread.
+pub struct TransferredResource(std::fs::File);
+
+/// Duplicates the resources `rids` of `resource_table` so that they can be
+/// transferred, in that order. The originals stay in the table until the
+/// transfer went through and they're closed with `close_resources`, so that
+/// a failed transfer leaves them usable.
+pub fn clone_resources(
+  resource_table: &mut ResourceTable,
+  rids: &[u32],
+) -> Result<Vec<TransferredResource>, OpError> {
+  let mut resources = Vec::with_capacity(rids.len());
+  for (i, &rid) in rids.iter().enumerate() {
+    if rids[..i].contains(&rid) {
+      return Err(OpError::type_error(format!(
+        "Resource {} is transferred more than once",
+        rid
+      )));
+    }
+    resources.push(TransferredResource(clone_file(resource_table, rid)?));
+  }
+  Ok(resources)
+}
+
+/// Closes the originals of resources that were transferred.
+pub fn close_resources(resource_table: &mut ResourceTable, rids: &[u32]) {
+  for &rid in rids {
+    resource_table.close(rid);
+  }
+}
+
+/// Adds transferred resources to `resource_table` and returns their rids and
+/// kinds, in order. If one can't be added, none is.
+pub fn add_resources(
+  resource_table: &mut ResourceTable,
+  resources: Vec<TransferredResource>,
+) -> Result<Vec<Value>, OpError> {
+  let mut rids = Vec::with_capacity(resources.len());
+  let mut result = Ok(());
+  for TransferredResource(file) in resources {
+    if result.is_err() {
+      // Drop the rest so that they're closed.
+      continue;
+    }
+    match add_file(resource_table, file) {
+      Ok(resource) => rids.push(resource),
+      Err(err) => result = Err(err),
+    }
+  }
+  if let Err(err) = result {
+    for (rid, _kind) in rids {
+      resource_table.close(rid);
+    }
+    return Err(err);
+  }
+  Ok(
+    rids
+      .into_iter()
+      .map(|(rid, kind)| json!({ "rid": rid, "kind": kind }))
+      .collect(),
+  )
+}
+
+/// Returns the file descriptor of a resource that can be duplicated or sent.
+#[cfg(unix)]
+pub fn raw_fd(
+  resource_table: &ResourceTable,
+  rid: u32,
+) -> Result<RawFd, OpError> {
+  if let Some(fd) = super::net::tcp_listener_raw_fd(resource_table, rid) {
+    return Ok(fd);
+  }
+  let resource_holder = resource_table
+    .get::<StreamResourceHolder>(rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+  match &resource_holder.resource {
+    StreamResource::FsFile(Some((f, _))) => Ok(f.as_raw_fd()),
+    StreamResource::TcpStream(Some(stream)) => Ok(stream.as_raw_fd()),
+    StreamResource::UnixStream(stream) => Ok(stream.as_raw_fd()),
+    _ => Err(OpError::type_error(
+      "Resource can't be duplicated or sent".to_string(),
+    )),
+  }
+}
+
+/// Adds the resource that `fd` is to the resource table, taking ownership of
+/// `fd`, and returns its rid and kind.
+#[cfg(unix)]
+fn add_fd(
+  resource_table: &mut ResourceTable,
+  fd: RawFd,
+) -> Result<(u32, &'static str), OpError> {
+  // Owned by a File from here on, so that it's closed if anything fails.
+  let file = unsafe { std::fs::File::from_raw_fd(fd) };
+  let stat = nix::sys::stat::fstat(fd)?;
+  let file_type = stat.st_mode & libc::S_IFMT;
+  if file_type != libc::S_IFSOCK {
+    if file_type == libc::S_IFDIR {
+      return Err(OpError::type_error(
+        "Directories can't be used as resources".to_string(),
+      ));
+    }
+    let tokio_file = tokio::fs::File::from_std(file);
+    let rid = resource_table.add(
+      "fsFile",
+      Box::new(StreamResourceHolder::new(StreamResource::FsFile(Some((
+        tokio_file,
+        FileMetadata::default(),
+      ))))),
+    );
+    return Ok((rid, "fsFile"));
+  }
+
+  if getsockopt(fd, sockopt::SockType)? != SockType::Stream {
+    return Err(OpError::type_error(
+      "Only stream sockets can be used as resources".to_string(),
+    ));
+  }
+  // Listening sockets are the stream sockets that have no peer.
+  let listening = getpeername(fd).is_err();
+  match (getsockname(fd)?, listening) {
+    (SockAddr::Inet(_), true) => {
+      let listener =
+        unsafe { std::net::TcpListener::from_raw_fd(file.into_raw_fd()) };
+      listener.set_nonblocking(true)?;
+      let (rid, _local_addr) =
+        super::net::add_tcp_listener(resource_table, listener)?;
+      Ok((rid, "tcpListener"))
+    }
+    (SockAddr::Inet(_), false) => {
+      let stream =
+        unsafe { std::net::TcpStream::from_raw_fd(file.into_raw_fd()) };
+      stream.set_nonblocking(true)?;
+      let stream = tokio::net::TcpStream::from_std(stream)?;
+      let rid = resource_table.add(
+        "tcpStream",
+        Box::new(StreamResourceHolder::new(StreamResource::TcpStream(Some(
+          stream,
+        )))),
+      );
+      Ok((rid, "tcpStream"))
+    }
+    (SockAddr::Unix(_), false) => {
+      let stream = unsafe {
+        std::os::unix::net::UnixStream::from_raw_fd(file.into_raw_fd())
+      };
+      stream.set_nonblocking(true)?;
+      let stream = tokio::net::UnixStream::from_std(stream)?;
+      let rid = resource_table.add(
+        "unixStream",
+        Box::new(StreamResourceHolder::new(StreamResource::UnixStream(
+          stream,
+        ))),
+      );
+      Ok((rid, "unixStream"))
+    }
+    _ => Err(OpError::type_error(
+      "Unsupported kind of socket".to_string(),
+    )),
+  }
+}
+
+/// The most resources that one message can carry.
+#[cfg(unix)]
+const MAX_RESOURCES_PER_MESSAGE: usize = 16;
+
+/// How long a blocking wait for a socket lasts before checking whether the
+/// socket was closed in the meantime.
+#[cfg(unix)]
+const WAIT_READY_TIMEOUT_MS: libc::c_int = 100;
+
+#[cfg(unix)]
+fn unix_stream_fd(
+  resource_table: &ResourceTable,
+  rid: u32,
+) -> Result<RawFd, OpError> {
+  let resource_holder = resource_table
+    .get::<StreamResourceHolder>(rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+  match &resource_holder.resource {
+    StreamResource::UnixStream(stream) => Ok(stream.as_raw_fd()),
+    _ => Err(OpError::type_error(
+      "Resources can only be passed over Unix sockets".to_string(),
+    )),
+  }
+}
+
+/// Waits until the Unix socket `rid` is ready for `flags`, or for a while.
+///
+/// tokio doesn't let us wait for the readiness of its sockets without reading
+/// or writing, so a duplicate of the socket is polled on the blocking pool.
+#[cfg(unix)]
+async fn wait_ready(
+  resource_table: &Rc<RefCell<ResourceTable>>,
+  rid: u32,
+  flags: PollFlags,
+) -> Result<(), OpError> {
+  let fd = unix_stream_fd(&resource_table.borrow(), rid)?;
+  let fd = fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(0))?;
+  tokio::task::spawn_blocking(move || {
+    let mut poll_fds = [PollFd::new(fd, flags)];
+    let result = poll(&mut poll_fds, WAIT_READY_TIMEOUT_MS);
+    let _ = nix::unistd::close(fd);
+    result.map(|_| ())
+  })
+  .await
+  .unwrap()?;
+  Ok(())
+}
+
+#[cfg(unix)]
+#[derive(Deserialize)]
+struct SendResourcesArgs {
+  rid: i32,
+  rids: Vec<u32>,
+}
+
+#[cfg(unix)]
+fn op_send_resources(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.sendResources")?;
+  let args: SendResourcesArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
+  if args.rids.is_empty() || args.rids.len() > MAX_RESOURCES_PER_MESSAGE {
+    return Err(OpError::type_error(format!(
+      "Between 1 and {} resources can be sent at once",
+      MAX_RESOURCES_PER_MESSAGE
+    )));
+  }
+  let resource_table = isolate_state.resource_table.clone();
+
+  // The resources are sent as they are now, even if they are closed before
+  // the message goes out.
+  let mut fds = Vec::with_capacity(args.rids.len());
+  {
+    let resource_table = resource_table.borrow();
+    unix_stream_fd(&resource_table, rid)?;
+    for &rid in &args.rids {
+      let fd = raw_fd(&resource_table, rid).and_then(|fd| {
+        fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(0)).map_err(OpError::from)
+      });
+      match fd {
+        Ok(fd) => fds.push(fd),
+        Err(err) => {
+          close_all(&fds);
+          return Err(err);
+        }
+      }
+    }
+  }
+
+  let op = async move {
+    let result = loop {
+      let fd = match unix_stream_fd(&resource_table.borrow(), rid) {
+        Ok(fd) => fd,
+        Err(err) => break Err(err),
+      };
+      let iov = [IoVec::from_slice(&[0u8])];
+      let cmsgs = [ControlMessage::ScmRights(&fds)];
+      match sendmsg(fd, &iov, &cmsgs, MsgFlags::empty(), None) {
+        Ok(_) => break Ok(()),
+        Err(nix::Error::Sys(Errno::EAGAIN)) => {
+          if let Err(err) =
+            wait_ready(&resource_table, rid, PollFlags::POLLOUT).await
+          {
+            break Err(err);
+          }
+        }
+        Err(err) => break Err(OpError::from(err)),
+      }
+    };
+    // The receiving side has its own descriptors now.
+    close_all(&fds);
+    result?;
+    Ok(json!({}))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+#[cfg(unix)]
+fn close_all(fds: &[RawFd]) {
+  for &fd in fds {
+    let _ = nix::unistd::close(fd);
+  }
+}
+
+/// Received descriptors are close-on-exec from the start where the platform
+/// supports it; elsewhere they're marked right after `recvmsg`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+const RECV_FLAGS: MsgFlags = MsgFlags::MSG_CMSG_CLOEXEC;
+#[cfg(all(unix, not(any(target_os = "android", target_os = "linux"))))]
+const RECV_FLAGS: MsgFlags = MsgFlags::empty();
+
+/// Receives one message of resources. Returns `None` at EOF.
+///
+/// Fails with `EMSGSIZE` if the message carried more descriptors than fit
+/// in the buffer; the ones that did arrive are closed.
+#[cfg(unix)]
+fn receive_fds(fd: RawFd) -> nix::Result<Option<Vec<RawFd>>> {
+  let mut buf = [0u8; 1];
+  let iov = [IoVec::from_mut_slice(&mut buf)];
+  let mut cmsg_buffer = nix::cmsg_space!([RawFd; MAX_RESOURCES_PER_MESSAGE]);
+  let msg = recvmsg(fd, &iov, Some(&mut cmsg_buffer), RECV_FLAGS)?;
+  let mut fds = vec![];
+  for cmsg in msg.cmsgs() {
+    if let ControlMessageOwned::ScmRights(received) = cmsg {
+      fds.extend(received);
+    }
+  }
+  if msg.flags.contains(MsgFlags::MSG_CTRUNC) {
+    close_all(&fds);
+    return Err(nix::Error::Sys(Errno::EMSGSIZE));
+  }
+  if msg.bytes == 0 && fds.is_empty() {
+    return Ok(None);
+  }
+  Ok(Some(fds))
+}
+
+#[cfg(unix)]
+#[derive(Deserialize)]
+struct ReceiveResourcesArgs {
+  rid: i32,
+}
+
+#[cfg(unix)]
+fn op_receive_resources(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.receiveResources")?;
+  let args: ReceiveResourcesArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
+  let resource_table = isolate_state.resource_table.clone();
+  unix_stream_fd(&resource_table.borrow(), rid)?;
+
+  let op = async move {
+    let fds = loop {
+      let fd = unix_stream_fd(&resource_table.borrow(), rid)?;
+      match receive_fds(fd) {
+        Ok(Some(fds)) => break fds,
+        Ok(None) => return Ok(Value::Null),
+        Err(nix::Error::Sys(Errno::EAGAIN)) => {
+          wait_ready(&resource_table, rid, PollFlags::POLLIN).await?
+        }
+        Err(nix::Error::Sys(Errno::EMSGSIZE)) => {
+          return Err(OpError::other(format!(
+            "Received more than {} resources at once",
+            MAX_RESOURCES_PER_MESSAGE
+          )))
+        }
+        Err(err) => return Err(OpError::from(err)),
+      }
+    };
+
+    let mut resource_table = resource_table.borrow_mut();
+    let mut resources = vec![];
+    let mut error = None;
+    for fd in fds {
+      // The descriptors are owned by add_fd as soon as they're given to it,
+      // even when it fails.
+      let result = match fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)) {
+        Ok(_) => add_fd(&mut resource_table, fd),
+        Err(err) => {
+          let _ = nix::unistd::close(fd);
+          Err(OpError::from(err))
+        }
+      };
+      match result {
+        Ok((rid, kind)) => resources.push(json!({ "rid": rid, "kind": kind })),
+        Err(err) => {
+          error.get_or_insert(err);
+        }
+      }
+    }
+    if let Some(err) = error {
+      for resource in resources {
+        resource_table.close(resource["rid"].as_u64().unwrap() as u32);
+      }
+      return Err(err);
+    }
+    Ok(json!(resources))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
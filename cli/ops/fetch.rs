@@ -1,27 +1,64 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
-use super::io::{StreamResource, StreamResourceHolder};
-use crate::http_util::{create_http_client, HttpBody};
+use super::io::{FileMetadata, StreamResource, StreamResourceHolder};
+use crate::fetch_cache::{is_cacheable_request, CachedResponse};
+use crate::http_util::HttpBody;
+use crate::log_category;
 use crate::op_error::OpError;
 use crate::state::State;
+use bytes::Bytes;
 use deno_core::CoreIsolate;
 use deno_core::CoreIsolateState;
+use deno_core::ResourceTable;
 use deno_core::ZeroCopyBuf;
+use futures::future::poll_fn;
 use futures::future::FutureExt;
 use http::header::HeaderName;
 use http::header::HeaderValue;
+use http::header::CONTENT_LENGTH;
 use http::Method;
+use http::StatusCode;
+use std::cmp::min;
 use std::convert::From;
+use std::io;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_fetch", s.stateful_json_op2(op_fetch));
+  i.register_op(
+    "op_fetch_upload_progress_open",
+    s.stateful_json_op2(op_fetch_upload_progress_open),
+  );
+  i.register_op(
+    "op_fetch_upload_progress_poll",
+    s.stateful_json_op2(op_fetch_upload_progress_poll),
+  );
+}
+
+/// How many bytes of a request body are handed to the connection at once
+/// when its upload progress is reported.
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The number of bytes of a request body sent so far. `op_fetch` takes the
+/// sender, and `op_fetch_upload_progress_poll` reads the receiver until the
+/// body is sent.
+struct UploadProgressResource {
+  sender: Option<mpsc::UnboundedSender<u64>>,
+  receiver: mpsc::UnboundedReceiver<u64>,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct FetchArgs {
   method: Option<String>,
   url: String,
   headers: Vec<(String, String)>,
+  timeout: Option<u64>,
+  upload_progress_rid: Option<u32>,
+  /// Whether `onProgress` was given, which is unstable.
+  #[serde(default)]
+  progress: bool,
 }
 
 pub fn op_fetch(
@@ -33,8 +70,20 @@ pub fn op_fetch(
   let args: FetchArgs = serde_json::from_value(args)?;
   let url = args.url;
 
-  let client =
-    create_http_client(state.borrow().global_state.flags.ca_file.clone())?;
+  // Taken first, so that the progress is done as soon as this op is, even
+  // when it fails.
+  let upload_progress = match args.upload_progress_rid {
+    Some(rid) => {
+      let mut resource_table = isolate_state.resource_table.borrow_mut();
+      let resource = resource_table
+        .get_mut::<UploadProgressResource>(rid)
+        .ok_or_else(OpError::bad_resource_id)?;
+      resource.sender.take()
+    }
+    None => None,
+  };
+
+  let client = state.borrow().global_state.http_client.clone();
 
   let method = match args.method {
     Some(method_str) => Method::from_bytes(method_str.as_bytes())
@@ -55,31 +104,135 @@ pub fn op_fetch(
 
   state.check_net_url(&url_)?;
 
-  let mut request = client.request(method, url_);
+  let fetch_cache = match &state.borrow().global_state.fetch_cache {
+    Some(fetch_cache)
+      if is_cacheable_request(
+        method.as_str(),
+        !data.is_empty(),
+        &args.headers,
+      ) =>
+    {
+      Some(fetch_cache.clone())
+    }
+    _ => None,
+  };
+
+  let resource_table = isolate_state.resource_table.clone();
+  let mut request = client.request(method, url_.clone());
 
   match data.len() {
     0 => {}
-    1 => request = request.body(Vec::from(&*data[0])),
-    _ => panic!("Invalid number of arguments"),
+    1 => {
+      let body = Vec::from(&*data[0]);
+      request = match upload_progress {
+        Some(progress) => {
+          let has_content_length = args
+            .headers
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("content-length"));
+          if !has_content_length {
+            // A streamed body would be sent chunked otherwise.
+            request = request.header(CONTENT_LENGTH, body.len());
+          }
+          request.body(upload_body(body, progress))
+        }
+        None => request.body(body),
+      }
+    }
+    _ => {
+      return Err(OpError::type_error(
+        "Invalid number of arguments".to_string(),
+      ))
+    }
+  }
+
+  let request_headers = args.headers.clone();
+  if let Some(timeout) = args.timeout {
+    state.check_unstable("fetch({ timeout })")?;
+    request = request.timeout(Duration::from_millis(timeout));
+  }
+  if args.progress {
+    state.check_unstable("fetch({ onProgress })")?;
   }
 
   for (key, value) in args.headers {
-    let name = HeaderName::from_bytes(key.as_bytes()).unwrap();
-    let v = HeaderValue::from_str(&value).unwrap();
+    let name = HeaderName::from_bytes(key.as_bytes())
+      .map_err(|err| OpError::type_error(err.to_string()))?;
+    let v = HeaderValue::from_str(&value)
+      .map_err(|err| OpError::type_error(err.to_string()))?;
     request = request.header(name, v);
   }
-  debug!("Before fetch {}", url);
 
-  let resource_table = isolate_state.resource_table.clone();
   let future = async move {
+    let cached = match &fetch_cache {
+      Some(cache) => cache
+        .get_async(url_.clone(), request_headers.clone())
+        .await
+        .ok(),
+      None => None,
+    };
+    if let Some((cached, file)) = &cached {
+      if cached.is_fresh() {
+        debug!(target: log_category::CACHE, "Fetch cache hit {}", url);
+        let file = file.try_clone()?;
+        let mut resource_table = resource_table.borrow_mut();
+        return Ok(cached_response_json(&mut resource_table, cached, file));
+      }
+      for (key, value) in cached.revalidation_headers() {
+        if let Ok(v) = HeaderValue::from_str(&value) {
+          request = request.header(key.as_str(), v);
+        }
+      }
+    }
+
+    debug!(target: log_category::NET, "Before fetch {}", url);
     let res = request.send().await?;
-    debug!("Fetch response {}", url);
+    debug!(target: log_category::NET, "Fetch response {}", url);
     let status = res.status();
     let mut res_headers = Vec::new();
     for (key, val) in res.headers().iter() {
       res_headers.push((key.to_string(), val.to_str().unwrap().to_owned()));
     }
 
+    if let Some(fetch_cache) = fetch_cache {
+      if status == StatusCode::NOT_MODIFIED {
+        if let Some((mut cached, _)) = cached {
+          debug!(
+            target: log_category::CACHE,
+            "Fetch cache revalidated {}",
+            url
+          );
+          cached.revalidate(&res_headers);
+          let file = fetch_cache
+            .set_async(url_, request_headers, cached.clone(), None)
+            .await?;
+          let mut resource_table = resource_table.borrow_mut();
+          return Ok(cached_response_json(&mut resource_table, &cached, file));
+        }
+      }
+
+      let response = CachedResponse::new(
+        &url_,
+        status.as_u16(),
+        status.canonical_reason().unwrap_or(""),
+        res_headers.clone(),
+      );
+      if response.is_storable() {
+        debug!(target: log_category::CACHE, "Fetch cache store {}", url);
+        let body = res.bytes().await?;
+        let file = fetch_cache
+          .set_async(
+            url_,
+            request_headers,
+            response.clone(),
+            Some(body.to_vec()),
+          )
+          .await?;
+        let mut resource_table = resource_table.borrow_mut();
+        return Ok(cached_response_json(&mut resource_table, &response, file));
+      }
+    }
+
     let body = HttpBody::from(res);
     let mut resource_table = resource_table.borrow_mut();
     let rid = resource_table.add(
@@ -101,3 +254,101 @@ pub fn op_fetch(
 
   Ok(JsonOp::Async(future.boxed_local()))
 }
+
+/// Responds with a response of the fetch cache, whose body is read from
+/// `file`.
+fn cached_response_json(
+  resource_table: &mut ResourceTable,
+  cached: &CachedResponse,
+  file: std::fs::File,
+) -> Value {
+  let body = StreamResource::FsFile(Some((
+    tokio::fs::File::from_std(file),
+    FileMetadata::default(),
+  )));
+  let rid =
+    resource_table.add("fsFile", Box::new(StreamResourceHolder::new(body)));
+  json!({
+    "bodyRid": rid,
+    "status": cached.status,
+    "statusText": cached.status_text,
+    "headers": cached.headers
+  })
+}
+
+/// Streams `body` in chunks, and sends through `progress` how much of it was
+/// sent whenever the connection asks for more.
+fn upload_body(
+  body: Vec<u8>,
+  progress: mpsc::UnboundedSender<u64>,
+) -> reqwest::Body {
+  let total = body.len();
+  let body = Bytes::from(body);
+  let chunks = futures::stream::unfold(0, move |sent| {
+    if sent > 0 {
+      // The receiver is gone once the script stops polling, which is fine.
+      let _ = progress.send(sent as u64);
+    }
+    let chunk = if sent < total {
+      let end = min(sent + UPLOAD_CHUNK_SIZE, total);
+      Some((Ok::<_, io::Error>(body.slice(sent..end)), end))
+    } else {
+      None
+    };
+    futures::future::ready(chunk)
+  });
+  reqwest::Body::wrap_stream(chunks)
+}
+
+pub fn op_fetch_upload_progress_open(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  _args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("fetch({ onProgress })")?;
+  let (sender, receiver) = mpsc::unbounded_channel();
+  let resource = UploadProgressResource {
+    sender: Some(sender),
+    receiver,
+  };
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  let rid = resource_table.add("fetchUploadProgress", Box::new(resource));
+  Ok(JsonOp::Sync(json!(rid)))
+}
+
+/// Resolves with the latest upload progress once there is some, or with
+/// `done` once the body is sent or the fetch is over.
+pub fn op_fetch_upload_progress_poll(
+  isolate_state: &mut CoreIsolateState,
+  _state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  #[derive(Deserialize)]
+  struct PollArgs {
+    rid: u32,
+  }
+  let PollArgs { rid } = serde_json::from_value(args)?;
+  let resource_table = isolate_state.resource_table.clone();
+  let f = poll_fn(move |cx| {
+    let mut resource_table = resource_table.borrow_mut();
+    let resource = resource_table
+      .get_mut::<UploadProgressResource>(rid)
+      .ok_or_else(OpError::bad_resource_id)?;
+    resource
+      .receiver
+      .poll_recv(cx)
+      .map(|maybe_sent| match maybe_sent {
+        Some(mut sent) => {
+          // Only the latest progress matters.
+          while let Ok(latest) = resource.receiver.try_recv() {
+            sent = latest;
+          }
+          Ok(json!({ "loaded": sent, "done": false }))
+        }
+        None => Ok(json!({ "done": true })),
+      })
+  });
+  Ok(JsonOp::Async(f.boxed_local()))
+}
@@ -3,6 +3,7 @@
 use super::dispatch_json::{blocking_json, Deserialize, JsonOp, Value};
 use super::io::std_file_resource;
 use super::io::{FileMetadata, StreamResource, StreamResourceHolder};
+use crate::log_category;
 use crate::op_error::OpError;
 use crate::ops::dispatch_json::JsonResult;
 use crate::state::State;
@@ -214,7 +215,7 @@ fn op_umask(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.umask");
+  state.check_unstable("Deno.umask")?;
   let args: UmaskArgs = serde_json::from_value(args)?;
   // TODO implement umask for Windows
   // see https://github.com/nodejs/node/blob/master/src/node_process_methods.cc
@@ -281,7 +282,13 @@ fn op_mkdir(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_mkdir {} {:o} {}", path.display(), mode, args.recursive);
+    debug!(
+      target: log_category::OPS,
+      "op_mkdir {} {:o} {}",
+      path.display(),
+      mode,
+      args.recursive
+    );
     let mut builder = std::fs::DirBuilder::new();
     builder.recursive(args.recursive);
     #[cfg(unix)]
@@ -315,7 +322,7 @@ fn op_chmod(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_chmod {} {:o}", path.display(), mode);
+    debug!(target: log_category::OPS, "op_chmod {} {:o}", path.display(), mode);
     #[cfg(unix)]
     {
       use std::os::unix::fs::PermissionsExt;
@@ -354,7 +361,13 @@ fn op_chown(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_chown {} {} {}", path.display(), args.uid, args.gid);
+    debug!(
+      target: log_category::OPS,
+      "op_chown {} {} {}",
+      path.display(),
+      args.uid,
+      args.gid
+    );
     #[cfg(unix)]
     {
       use nix::unistd::{chown, Gid, Uid};
@@ -399,7 +412,12 @@ fn op_remove(
 
     let metadata = std::fs::symlink_metadata(&path)?;
 
-    debug!("op_remove {} {}", path.display(), recursive);
+    debug!(
+      target: log_category::OPS,
+      "op_remove {} {}",
+      path.display(),
+      recursive
+    );
     let file_type = metadata.file_type();
     if file_type.is_file() {
       std::fs::remove_file(&path)?;
@@ -447,7 +465,12 @@ fn op_copy_file(
   state.check_read(&from)?;
   state.check_write(&to)?;
 
-  debug!("op_copy_file {} {}", from.display(), to.display());
+  debug!(
+    target: log_category::OPS,
+    "op_copy_file {} {}",
+    from.display(),
+    to.display()
+  );
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
     // On *nix, Rust reports non-existent `from` as ErrorKind::InvalidInput
@@ -542,7 +565,7 @@ fn op_stat(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_stat {} {}", path.display(), lstat);
+    debug!(target: log_category::OPS, "op_stat {} {}", path.display(), lstat);
     let metadata = if lstat {
       std::fs::symlink_metadata(&path)?
     } else {
@@ -574,7 +597,7 @@ fn op_realpath(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_realpath {}", path.display());
+    debug!(target: log_category::OPS, "op_realpath {}", path.display());
     // corresponds to the realpath on Unix and
     // CreateFile and GetFinalPathNameByHandle on Windows
     let realpath = std::fs::canonicalize(&path)?;
@@ -606,7 +629,7 @@ fn op_read_dir(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_read_dir {}", path.display());
+    debug!(target: log_category::OPS, "op_read_dir {}", path.display());
     let entries: Vec<_> = std::fs::read_dir(path)?
       .filter_map(|entry| {
         let entry = entry.unwrap();
@@ -652,7 +675,12 @@ fn op_rename(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_rename {} {}", oldpath.display(), newpath.display());
+    debug!(
+      target: log_category::OPS,
+      "op_rename {} {}",
+      oldpath.display(),
+      newpath.display()
+    );
     std::fs::rename(&oldpath, &newpath)?;
     Ok(json!({}))
   })
@@ -671,7 +699,7 @@ fn op_link(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.link");
+  state.check_unstable("Deno.link")?;
   let args: LinkArgs = serde_json::from_value(args)?;
   let oldpath = PathBuf::from(&args.oldpath);
   let newpath = PathBuf::from(&args.newpath);
@@ -681,7 +709,12 @@ fn op_link(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_link {} {}", oldpath.display(), newpath.display());
+    debug!(
+      target: log_category::OPS,
+      "op_link {} {}",
+      oldpath.display(),
+      newpath.display()
+    );
     std::fs::hard_link(&oldpath, &newpath)?;
     Ok(json!({}))
   })
@@ -709,7 +742,7 @@ fn op_symlink(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.symlink");
+  state.check_unstable("Deno.symlink")?;
   let args: SymlinkArgs = serde_json::from_value(args)?;
   let oldpath = PathBuf::from(&args.oldpath);
   let newpath = PathBuf::from(&args.newpath);
@@ -718,7 +751,12 @@ fn op_symlink(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_symlink {} {}", oldpath.display(), newpath.display());
+    debug!(
+      target: log_category::OPS,
+      "op_symlink {} {}",
+      oldpath.display(),
+      newpath.display()
+    );
     #[cfg(unix)]
     {
       use std::os::unix::fs::symlink;
@@ -776,7 +814,7 @@ fn op_read_link(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_read_link {}", path.display());
+    debug!(target: log_category::OPS, "op_read_link {}", path.display());
     let target = std::fs::read_link(&path)?.into_os_string();
     let targetstr = into_string(target)?;
     Ok(json!(targetstr))
@@ -804,7 +842,7 @@ fn op_truncate(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_truncate {} {}", path.display(), len);
+    debug!(target: log_category::OPS, "op_truncate {} {}", path.display(), len);
     let f = std::fs::OpenOptions::new().write(true).open(&path)?;
     f.set_len(len)?;
     Ok(json!({}))
@@ -941,7 +979,7 @@ fn op_utime(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.utime");
+  state.check_unstable("Deno.utime")?;
 
   let args: UtimeArgs = serde_json::from_value(args)?;
   let path = PathBuf::from(&args.path);
@@ -950,7 +988,13 @@ fn op_utime(
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
-    debug!("op_utime {} {} {}", args.path, args.atime, args.mtime);
+    debug!(
+      target: log_category::OPS,
+      "op_utime {} {} {}",
+      args.path,
+      args.atime,
+      args.mtime
+    );
     utime::set_file_times(args.path, args.atime, args.mtime)?;
     Ok(json!({}))
   })
@@ -0,0 +1,48 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! The op of `Deno.writeHeapSnapshot()`. The snapshot is taken through the
+//! inspector, which the op can't reach, so the op only queues a request that
+//! the worker handles the next time it's polled.
+use super::dispatch_json::{Deserialize, JsonOp, Value};
+use crate::op_error::OpError;
+use crate::state::State;
+use deno_core::CoreIsolate;
+use deno_core::ZeroCopyBuf;
+use futures::channel::oneshot;
+use futures::future::FutureExt;
+use std::path::PathBuf;
+
+pub fn init(i: &mut CoreIsolate, s: &State) {
+  i.register_op("op_heap_snapshot", s.stateful_json_op(op_heap_snapshot));
+}
+
+#[derive(Deserialize)]
+struct HeapSnapshotArgs {
+  path: String,
+}
+
+fn op_heap_snapshot(
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.writeHeapSnapshot")?;
+  let args: HeapSnapshotArgs = serde_json::from_value(args)?;
+  let path = PathBuf::from(&args.path);
+  state.check_write(&path)?;
+
+  let (sender, receiver) = oneshot::channel();
+  state
+    .borrow_mut()
+    .heap_snapshot_requests
+    .push((path, sender));
+
+  let fut = async move {
+    // The sender is dropped if the worker stops before taking the snapshot.
+    let result = receiver.await.map_err(|_| {
+      OpError::other("The heap snapshot was canceled".to_string())
+    })?;
+    result?;
+    Ok(json!({}))
+  };
+  Ok(JsonOp::Async(fut.boxed_local()))
+}
@@ -1,5 +1,7 @@
+use super::dispatch_json::{Deserialize, JsonOp, Value};
 use super::dispatch_minimal::MinimalOp;
 use crate::http_util::HttpBody;
+use crate::log_category;
 use crate::op_error::OpError;
 use crate::state::State;
 use deno_core::CoreIsolate;
@@ -9,11 +11,17 @@ use deno_core::ZeroCopyBuf;
 use futures::future::poll_fn;
 use futures::future::FutureExt;
 use futures::ready;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::future::Future;
+use std::io::IoSlice;
+use std::io::IoSliceMut;
 use std::pin::Pin;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::Context;
 use std::task::Poll;
+use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio_rustls::client::TlsStream as ClientTlsStream;
@@ -64,6 +72,9 @@ lazy_static! {
 pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_read", s.stateful_minimal_op2(op_read));
   i.register_op("op_write", s.stateful_minimal_op2(op_write));
+  i.register_op("op_readv", s.stateful_minimal_op2(op_readv));
+  i.register_op("op_writev", s.stateful_minimal_op2(op_writev));
+  i.register_op("op_read_timeout", s.stateful_json_op2(op_read_timeout));
 }
 
 pub fn get_stdio() -> (
@@ -91,13 +102,20 @@ fn no_buffer_specified() -> OpError {
   OpError::type_error("no buffer specified".to_string())
 }
 
+fn too_many_buffers() -> OpError {
+  OpError::type_error("too many buffers specified".to_string())
+}
+
 #[cfg(unix)]
 use nix::sys::termios;
 
+/// The mode a terminal was in before it was put in raw mode, to restore it.
 #[derive(Default)]
 pub struct TTYMetadata {
   #[cfg(unix)]
   pub mode: Option<termios::Termios>,
+  #[cfg(windows)]
+  pub mode: Option<winapi::shared::minwindef::DWORD>,
 }
 
 #[derive(Default)]
@@ -105,8 +123,17 @@ pub struct FileMetadata {
   pub tty: TTYMetadata,
 }
 
+/// How long a read or a write of a resource may wait before it fails with
+/// a `TimedOut` error. `None` waits forever.
+#[derive(Clone, Copy, Default)]
+pub struct IdleTimeouts {
+  pub read: Option<Duration>,
+  pub write: Option<Duration>,
+}
+
 pub struct StreamResourceHolder {
   pub resource: StreamResource,
+  pub idle_timeouts: IdleTimeouts,
   waker: HashMap<usize, futures::task::AtomicWaker>,
   waker_counter: AtomicUsize,
 }
@@ -115,6 +142,7 @@ impl StreamResourceHolder {
   pub fn new(resource: StreamResource) -> StreamResourceHolder {
     StreamResourceHolder {
       resource,
+      idle_timeouts: IdleTimeouts::default(),
       // Atleast one task is expecter for the resource
       waker: HashMap::with_capacity(1),
       // Tracks wakers Ids
@@ -206,6 +234,95 @@ impl DenoAsyncRead for StreamResource {
   }
 }
 
+/// Returns the idle timeouts of `rid`. An unknown rid has none; the op
+/// fails on its own.
+fn idle_timeouts(resource_table: &ResourceTable, rid: i32) -> IdleTimeouts {
+  resource_table
+    .get::<StreamResourceHolder>(rid as u32)
+    .map(|resource_holder| resource_holder.idle_timeouts)
+    .unwrap_or_default()
+}
+
+/// Runs `f`, or gives up with `None` once it took longer than `timeout`.
+async fn within<T>(
+  timeout: Option<Duration>,
+  f: impl Future<Output = T>,
+) -> Option<T> {
+  match timeout {
+    None => Some(f.await),
+    Some(timeout) => tokio::time::timeout(timeout, f).await.ok(),
+  }
+}
+
+/// The waker a pending read registered on its resource. It's removed by the
+/// next poll, or when the read is dropped before it's done, like when it
+/// timed out.
+struct TrackedTask {
+  resource_table: Rc<RefCell<ResourceTable>>,
+  rid: u32,
+  id: Option<usize>,
+}
+
+impl TrackedTask {
+  fn new(resource_table: Rc<RefCell<ResourceTable>>, rid: u32) -> Self {
+    Self {
+      resource_table,
+      rid,
+      id: None,
+    }
+  }
+
+  fn track(
+    &mut self,
+    resource_holder: &mut StreamResourceHolder,
+    cx: &Context,
+  ) -> Result<(), OpError> {
+    self.id = Some(resource_holder.track_task(cx)?);
+    Ok(())
+  }
+
+  fn untrack(&mut self, resource_holder: &mut StreamResourceHolder) {
+    if let Some(id) = self.id.take() {
+      resource_holder.untrack_task(id);
+    }
+  }
+}
+
+impl Drop for TrackedTask {
+  fn drop(&mut self) {
+    let id = match self.id.take() {
+      Some(id) => id,
+      None => return,
+    };
+    if let Ok(mut resource_table) = self.resource_table.try_borrow_mut() {
+      if let Some(resource_holder) =
+        resource_table.get_mut::<StreamResourceHolder>(self.rid)
+      {
+        resource_holder.untrack_task(id);
+      }
+    }
+  }
+}
+
+/// Fails the read `op` with a `TimedOut` error when it takes longer than
+/// `timeout`. Writes are timed in steps instead, see op_write.
+fn with_idle_timeout(
+  timeout: Option<Duration>,
+  op: Pin<Box<dyn Future<Output = Result<i32, OpError>>>>,
+) -> MinimalOp {
+  match timeout {
+    None => MinimalOp::Async(op),
+    Some(timeout) => MinimalOp::Async(
+      async move {
+        tokio::time::timeout(timeout, op)
+          .await
+          .map_err(|_| OpError::timed_out())?
+      }
+      .boxed_local(),
+    ),
+  }
+}
+
 pub fn op_read(
   isolate_state: &mut CoreIsolateState,
   _state: &State,
@@ -213,11 +330,11 @@ pub fn op_read(
   rid: i32,
   zero_copy: &mut [ZeroCopyBuf],
 ) -> MinimalOp {
-  debug!("read rid={}", rid);
+  debug!(target: log_category::OPS, "read rid={}", rid);
   match zero_copy.len() {
     0 => return MinimalOp::Sync(Err(no_buffer_specified())),
     1 => {}
-    _ => panic!("Invalid number of arguments"),
+    _ => return MinimalOp::Sync(Err(too_many_buffers())),
   }
   let resource_table = isolate_state.resource_table.clone();
 
@@ -239,28 +356,30 @@ pub fn op_read(
       })
     })
   } else {
+    // The data is read straight into the buffer of the op, which the
+    // ZeroCopyBuf keeps alive for as long as the op is pending.
     let mut zero_copy = zero_copy[0].clone();
-    MinimalOp::Async(
+    // Kept across polls, so that the waker registered by the last poll is
+    // removed from the resource once the read is done.
+    let mut tracked_task = TrackedTask::new(resource_table.clone(), rid as u32);
+    let timeout = idle_timeouts(&resource_table.borrow(), rid).read;
+    with_idle_timeout(
+      timeout,
       poll_fn(move |cx| {
         let mut resource_table = resource_table.borrow_mut();
         let resource_holder = resource_table
           .get_mut::<StreamResourceHolder>(rid as u32)
           .ok_or_else(OpError::bad_resource_id)?;
 
-        let mut task_tracker_id: Option<usize> = None;
+        tracked_task.untrack(resource_holder);
         let nread = match resource_holder
           .resource
           .poll_read(cx, &mut zero_copy)
           .map_err(OpError::from)
         {
-          Poll::Ready(t) => {
-            if let Some(id) = task_tracker_id {
-              resource_holder.untrack_task(id);
-            }
-            t
-          }
+          Poll::Ready(t) => t,
           Poll::Pending => {
-            task_tracker_id.replace(resource_holder.track_task(cx)?);
+            tracked_task.track(resource_holder, cx)?;
             return Poll::Pending;
           }
         }?;
@@ -271,6 +390,69 @@ pub fn op_read(
   }
 }
 
+#[derive(Deserialize)]
+struct ReadTimeoutArgs {
+  rid: i32,
+  timeout: u64,
+}
+
+/// Reads from stdin like op_read, but gives up after `timeout` milliseconds
+/// and resolves to `null` then. With a timeout of 0 only data that's already
+/// there is read.
+///
+/// Giving up loses nothing: tokio reads stdin on the blocking pool, and what
+/// that read gets is kept by the resource for the next read.
+fn op_read_timeout(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  args: Value,
+  zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.readTimeout")?;
+  let args: ReadTimeoutArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
+  match zero_copy.len() {
+    0 => return Err(no_buffer_specified()),
+    1 => {}
+    _ => return Err(too_many_buffers()),
+  }
+  let resource_table = isolate_state.resource_table.clone();
+  match resource_table.borrow().get::<StreamResourceHolder>(rid) {
+    Some(StreamResourceHolder {
+      resource: StreamResource::Stdin(..),
+      ..
+    }) => {}
+    Some(_) => {
+      return Err(OpError::type_error(
+        "Only stdin can be read with a timeout".to_string(),
+      ))
+    }
+    None => return Err(OpError::bad_resource_id()),
+  }
+
+  let mut buf = zero_copy[0].clone();
+  let read = poll_fn(move |cx| {
+    let mut resource_table = resource_table.borrow_mut();
+    let resource_holder = resource_table
+      .get_mut::<StreamResourceHolder>(rid)
+      .ok_or_else(OpError::bad_resource_id)?;
+    resource_holder.resource.poll_read(cx, &mut buf)
+  });
+  let timeout = args.timeout;
+  let op = async move {
+    let nread = if timeout == 0 {
+      read.now_or_never().transpose()?
+    } else {
+      match tokio::time::timeout(Duration::from_millis(timeout), read).await {
+        Ok(result) => Some(result?),
+        Err(_) => None,
+      }
+    };
+    Ok(json!(nread))
+  };
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
 /// `DenoAsyncWrite` is the same as the `tokio_io::AsyncWrite` trait
 /// but uses an `OpError` error instead of `std::io:Error`
 pub trait DenoAsyncWrite {
@@ -326,8 +508,27 @@ impl DenoAsyncWrite for StreamResource {
     Ok(()).into()
   }
 
-  fn poll_close(&mut self, _cx: &mut Context) -> Poll<Result<(), OpError>> {
-    unimplemented!()
+  /// Flushes what was written and shuts the writing side down, which sends a
+  /// FIN on sockets (after the close_notify alert of TLS streams). A file
+  /// that is lent out to a sync op fails, since nothing would wake a pending
+  /// close when it's handed back.
+  fn poll_close(&mut self, cx: &mut Context) -> Poll<Result<(), OpError>> {
+    use StreamResource::*;
+    let f: &mut dyn UnpinAsyncWrite = match self {
+      FsFile(Some((f, _))) => f,
+      FsFile(None) => return Poll::Ready(Err(OpError::resource_unavailable())),
+      TcpStream(Some(f)) => f,
+      #[cfg(not(windows))]
+      UnixStream(f) => f,
+      ClientTlsStream(f) => f,
+      ServerTlsStream(f) => f,
+      ChildStdin(f) => f,
+      _ => return Err(OpError::bad_resource_id()).into(),
+    };
+
+    ready!(Pin::new(&mut *f).poll_flush(cx))?;
+    ready!(Pin::new(f).poll_shutdown(cx))?;
+    Ok(()).into()
   }
 }
 
@@ -338,11 +539,11 @@ pub fn op_write(
   rid: i32,
   zero_copy: &mut [ZeroCopyBuf],
 ) -> MinimalOp {
-  debug!("write rid={}", rid);
+  debug!(target: log_category::OPS, "write rid={}", rid);
   match zero_copy.len() {
     0 => return MinimalOp::Sync(Err(no_buffer_specified())),
     1 => {}
-    _ => panic!("Invalid number of arguments"),
+    _ => return MinimalOp::Sync(Err(too_many_buffers())),
   }
 
   if is_sync {
@@ -363,31 +564,243 @@ pub fn op_write(
       })
     })
   } else {
+    // Like for reads, the buffer of the op is written from directly.
     let zero_copy = zero_copy[0].clone();
     let resource_table = isolate_state.resource_table.clone();
+    let timeout = idle_timeouts(&resource_table.borrow(), rid).write;
     MinimalOp::Async(
       async move {
-        let nwritten = poll_fn(|cx| {
+        let write = poll_fn(|cx| {
           let mut resource_table = resource_table.borrow_mut();
           let resource_holder = resource_table
             .get_mut::<StreamResourceHolder>(rid as u32)
             .ok_or_else(OpError::bad_resource_id)?;
           resource_holder.resource.poll_write(cx, &zero_copy)
-        })
-        .await?;
+        });
+        let nwritten = within(timeout, write)
+          .await
+          .ok_or_else(OpError::timed_out)??;
 
         // TODO(bartlomieju): this step was added during upgrade to Tokio 0.2
         // and the reasons for the need to explicitly flush are not fully known.
         // Figure out why it's needed and preferably remove it.
         // https://github.com/denoland/deno/issues/3565
-        poll_fn(|cx| {
+        let flush = poll_fn(|cx| {
           let mut resource_table = resource_table.borrow_mut();
           let resource_holder = resource_table
             .get_mut::<StreamResourceHolder>(rid as u32)
             .ok_or_else(OpError::bad_resource_id)?;
           resource_holder.resource.poll_flush(cx)
-        })
-        .await?;
+        });
+        // The bytes were taken by the write, so they're reported even when
+        // the flush runs out of time, which it gets the whole timeout for.
+        if let Some(result) = within(timeout, flush).await {
+          result?;
+        }
+
+        Ok(nwritten as i32)
+      }
+      .boxed_local(),
+    )
+  }
+}
+
+/// Reads into several buffers with one op, filling them in order.
+///
+/// Like a single read, this only waits for data as long as nothing was read,
+/// so the buffers after the first may be left untouched.
+pub fn op_readv(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  is_sync: bool,
+  rid: i32,
+  zero_copy: &mut [ZeroCopyBuf],
+) -> MinimalOp {
+  debug!(target: log_category::OPS, "readv rid={}", rid);
+  if let Err(err) = state.check_unstable("Deno.readv") {
+    return MinimalOp::Sync(Err(err));
+  }
+  if zero_copy.is_empty() {
+    return MinimalOp::Sync(Err(no_buffer_specified()));
+  }
+  let resource_table = isolate_state.resource_table.clone();
+
+  if is_sync {
+    MinimalOp::Sync({
+      let mut resource_table = resource_table.borrow_mut();
+      std_file_resource(&mut resource_table, rid as u32, move |r| match r {
+        Ok(std_file) => {
+          use std::io::Read;
+          let mut bufs: Vec<IoSliceMut> = zero_copy
+            .iter_mut()
+            .map(|buf| IoSliceMut::new(&mut buf[..]))
+            .collect();
+          std_file
+            .read_vectored(&mut bufs)
+            .map(|n: usize| n as i32)
+            .map_err(OpError::from)
+        }
+        Err(_) => Err(OpError::type_error(
+          "sync read not allowed on this resource".to_string(),
+        )),
+      })
+    })
+  } else {
+    let mut bufs: Vec<ZeroCopyBuf> = zero_copy.to_vec();
+    let mut tracked_task = TrackedTask::new(resource_table.clone(), rid as u32);
+    let mut index = 0;
+    let mut offset = 0;
+    let mut nread = 0;
+    let timeout = idle_timeouts(&resource_table.borrow(), rid).read;
+    with_idle_timeout(
+      timeout,
+      poll_fn(move |cx| {
+        let mut resource_table = resource_table.borrow_mut();
+        let resource_holder = resource_table
+          .get_mut::<StreamResourceHolder>(rid as u32)
+          .ok_or_else(OpError::bad_resource_id)?;
+
+        tracked_task.untrack(resource_holder);
+        while index < bufs.len() {
+          let buf = &mut bufs[index][offset..];
+          if buf.is_empty() {
+            index += 1;
+            offset = 0;
+            continue;
+          }
+          match resource_holder.resource.poll_read(cx, buf) {
+            Poll::Ready(Ok(0)) => break,
+            Poll::Ready(Ok(n)) => {
+              nread += n;
+              offset += n;
+            }
+            // What was read so far is returned first; the error will come up
+            // again with the next read.
+            Poll::Ready(Err(err)) if nread == 0 => {
+              return Poll::Ready(Err(err))
+            }
+            Poll::Ready(Err(_)) => break,
+            Poll::Pending if nread == 0 => {
+              tracked_task.track(resource_holder, cx)?;
+              return Poll::Pending;
+            }
+            Poll::Pending => break,
+          }
+        }
+        Poll::Ready(Ok(nread as i32))
+      })
+      .boxed_local(),
+    )
+  }
+}
+
+/// Buffers of a vectored write that are this small in total are copied into
+/// one, so that a message made of a header and a body is sent at once.
+const WRITEV_COALESCE_SIZE: usize = 64 * 1024;
+
+/// Writes several buffers with one op, in order.
+///
+/// Like a single write, this only waits as long as nothing was written, so
+/// it may return before all the buffers were written.
+pub fn op_writev(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  is_sync: bool,
+  rid: i32,
+  zero_copy: &mut [ZeroCopyBuf],
+) -> MinimalOp {
+  debug!(target: log_category::OPS, "writev rid={}", rid);
+  if let Err(err) = state.check_unstable("Deno.writev") {
+    return MinimalOp::Sync(Err(err));
+  }
+  if zero_copy.is_empty() {
+    return MinimalOp::Sync(Err(no_buffer_specified()));
+  }
+
+  if is_sync {
+    MinimalOp::Sync({
+      let mut resource_table = isolate_state.resource_table.borrow_mut();
+      std_file_resource(&mut resource_table, rid as u32, move |r| match r {
+        Ok(std_file) => {
+          use std::io::Write;
+          let bufs: Vec<IoSlice> =
+            zero_copy.iter().map(|buf| IoSlice::new(&buf[..])).collect();
+          std_file
+            .write_vectored(&bufs)
+            .map(|nwritten: usize| nwritten as i32)
+            .map_err(OpError::from)
+        }
+        Err(_) => Err(OpError::type_error(
+          "sync write not allowed on this resource".to_string(),
+        )),
+      })
+    })
+  } else {
+    let bufs: Vec<ZeroCopyBuf> = zero_copy.to_vec();
+    let total: usize = bufs.iter().map(|buf| buf.len()).sum();
+    let coalesced = if bufs.len() > 1 && total <= WRITEV_COALESCE_SIZE {
+      let mut data = Vec::with_capacity(total);
+      for buf in &bufs {
+        data.extend_from_slice(buf);
+      }
+      Some(data)
+    } else {
+      None
+    };
+    let resource_table = isolate_state.resource_table.clone();
+    let mut index = 0;
+    let mut offset = 0;
+    let mut nwritten = 0;
+    let timeout = idle_timeouts(&resource_table.borrow(), rid).write;
+    MinimalOp::Async(
+      async move {
+        let write = poll_fn(|cx| {
+          let mut resource_table = resource_table.borrow_mut();
+          let resource_holder = resource_table
+            .get_mut::<StreamResourceHolder>(rid as u32)
+            .ok_or_else(OpError::bad_resource_id)?;
+          let slices: Vec<&[u8]> = match &coalesced {
+            Some(data) => vec![&data[..]],
+            None => bufs.iter().map(|buf| &buf[..]).collect(),
+          };
+          while index < slices.len() {
+            let buf = &slices[index][offset..];
+            if buf.is_empty() {
+              index += 1;
+              offset = 0;
+              continue;
+            }
+            match resource_holder.resource.poll_write(cx, buf) {
+              Poll::Ready(Ok(0)) => break,
+              Poll::Ready(Ok(n)) => {
+                nwritten += n;
+                offset += n;
+              }
+              Poll::Ready(Err(err)) if nwritten == 0 => {
+                return Poll::Ready(Err(err))
+              }
+              Poll::Ready(Err(_)) => break,
+              Poll::Pending if nwritten == 0 => return Poll::Pending,
+              Poll::Pending => break,
+            }
+          }
+          Poll::Ready(Ok(nwritten))
+        });
+        let nwritten = within(timeout, write)
+          .await
+          .ok_or_else(OpError::timed_out)??;
+
+        // See op_write.
+        let flush = poll_fn(|cx| {
+          let mut resource_table = resource_table.borrow_mut();
+          let resource_holder = resource_table
+            .get_mut::<StreamResourceHolder>(rid as u32)
+            .ok_or_else(OpError::bad_resource_id)?;
+          resource_holder.resource.poll_flush(cx)
+        });
+        if let Some(result) = within(timeout, flush).await {
+          result?;
+        }
 
         Ok(nwritten as i32)
       }
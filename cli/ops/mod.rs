@@ -1,18 +1,27 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 mod dispatch_json;
 mod dispatch_minimal;
+mod eager;
 
 pub use dispatch_json::json_op;
+#[cfg(any(fuzzing, test))]
+pub use dispatch_json::parse_control;
 pub use dispatch_json::JsonOp;
 pub use dispatch_json::JsonResult;
 pub use dispatch_minimal::minimal_op;
+#[cfg(any(fuzzing, test))]
+pub use dispatch_minimal::parse_min_record;
 pub use dispatch_minimal::MinimalOp;
 
+pub mod batch;
+pub mod bench;
 pub mod compiler;
 pub mod errors;
+pub mod fds;
 pub mod fetch;
 pub mod fs;
 pub mod fs_events;
+pub mod heap_snapshot;
 pub mod io;
 pub mod net;
 #[cfg(unix)]
@@ -1,8 +1,12 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
-use super::io::{StreamResource, StreamResourceHolder};
+use super::io::{DenoAsyncRead, DenoAsyncWrite};
+use super::io::{IdleTimeouts, StreamResource, StreamResourceHolder};
+use crate::log_category;
 use crate::op_error::OpError;
+use crate::resolve_addr::connect_tcp;
 use crate::resolve_addr::resolve_addr;
+use crate::resolve_addr::resolve_addrs;
 use crate::state::State;
 use deno_core::CoreIsolate;
 use deno_core::CoreIsolateState;
@@ -15,6 +19,7 @@ use std::net::Shutdown;
 use std::net::SocketAddr;
 use std::task::Context;
 use std::task::Poll;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::net::TcpStream;
 use tokio::net::UdpSocket;
@@ -29,6 +34,11 @@ pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_listen", s.stateful_json_op2(op_listen));
   i.register_op("op_receive", s.stateful_json_op2(op_receive));
   i.register_op("op_send", s.stateful_json_op2(op_send));
+  i.register_op("op_send_file", s.stateful_json_op2(op_send_file));
+  i.register_op(
+    "op_set_idle_timeouts",
+    s.stateful_json_op2(op_set_idle_timeouts),
+  );
 }
 
 #[derive(Deserialize)]
@@ -127,7 +137,11 @@ fn receive_udp(
   args: ReceiveArgs,
   zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  assert_eq!(zero_copy.len(), 1, "Invalid number of arguments");
+  if zero_copy.len() != 1 {
+    return Err(OpError::type_error(
+      "Invalid number of arguments".to_string(),
+    ));
+  }
   let mut zero_copy = zero_copy[0].clone();
 
   let rid = args.rid as u32;
@@ -167,7 +181,11 @@ fn op_receive(
   args: Value,
   zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  assert_eq!(zero_copy.len(), 1, "Invalid number of arguments");
+  if zero_copy.len() != 1 {
+    return Err(OpError::type_error(
+      "Invalid number of arguments".to_string(),
+    ));
+  }
 
   let args: ReceiveArgs = serde_json::from_value(args)?;
   match args.transport.as_str() {
@@ -197,7 +215,11 @@ fn op_send(
   args: Value,
   zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  assert_eq!(zero_copy.len(), 1, "Invalid number of arguments");
+  if zero_copy.len() != 1 {
+    return Err(OpError::type_error(
+      "Invalid number of arguments".to_string(),
+    ));
+  }
   let zero_copy = zero_copy[0].clone();
 
   let resource_table = isolate_state.resource_table.clone();
@@ -275,8 +297,8 @@ fn op_connect(
     } if transport == "tcp" => {
       state.check_net(&args.hostname, args.port)?;
       let op = async move {
-        let addr = resolve_addr(&args.hostname, args.port)?;
-        let tcp_stream = TcpStream::connect(&addr).await?;
+        let addrs = resolve_addrs(&args.hostname, args.port)?;
+        let tcp_stream = connect_tcp(addrs).await?;
         let local_addr = tcp_stream.local_addr()?;
         let remote_addr = tcp_stream.peer_addr()?;
         let mut resource_table = resource_table.borrow_mut();
@@ -308,7 +330,7 @@ fn op_connect(
       transport_args: ArgsEnum::Unix(args),
     } if transport == "unix" => {
       let address_path = net_unix::Path::new(&args.path);
-      state.check_unstable("Deno.connect");
+      state.check_unstable("Deno.connect")?;
       state.check_read(&address_path)?;
       let op = async move {
         let path = args.path;
@@ -353,7 +375,7 @@ fn op_shutdown(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.shutdown");
+  state.check_unstable("Deno.shutdown")?;
 
   let args: ShutdownArgs = serde_json::from_value(args)?;
 
@@ -385,6 +407,214 @@ fn op_shutdown(
   Ok(JsonOp::Sync(json!({})))
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendFileArgs {
+  file_rid: i32,
+  conn_rid: i32,
+  count: Option<u64>,
+}
+
+/// Size of the buffer `op_send_file` copies through.
+const SEND_FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Copies from the current position of a file to a connection, until EOF or
+/// `count` bytes were copied, without the data ever going through JS.
+///
+/// On Linux, files are sent to plain TCP and Unix sockets with sendfile(2),
+/// so the data never leaves the kernel. The tokio sockets don't let us wait
+/// for write readiness without writing, though, so when the socket is full
+/// one chunk is copied through a buffer owned by the op, which waits for it.
+/// TLS connections, and the other platforms, always go through the buffer.
+fn op_send_file(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.sendFile")?;
+
+  let args: SendFileArgs = serde_json::from_value(args)?;
+  let file_rid = args.file_rid as u32;
+  let conn_rid = args.conn_rid as u32;
+  let resource_table = isolate_state.resource_table.clone();
+
+  {
+    let resource_table = resource_table.borrow();
+    let resource = |rid| {
+      resource_table
+        .get::<StreamResourceHolder>(rid)
+        .map(|resource_holder| &resource_holder.resource)
+    };
+    match resource(file_rid) {
+      Some(StreamResource::FsFile(_)) => {}
+      _ => return Err(OpError::bad_resource_id()),
+    }
+    match resource(conn_rid) {
+      Some(StreamResource::TcpStream(_))
+      | Some(StreamResource::ClientTlsStream(_))
+      | Some(StreamResource::ServerTlsStream(_)) => {}
+      #[cfg(unix)]
+      Some(StreamResource::UnixStream(_)) => {}
+      _ => return Err(OpError::bad_resource_id()),
+    }
+  }
+
+  let op = async move {
+    let mut remaining = args.count.unwrap_or(u64::MAX);
+    let mut buf = vec![0u8; SEND_FILE_CHUNK_SIZE];
+    let mut total: u64 = 0;
+    while remaining > 0 {
+      #[cfg(target_os = "linux")]
+      {
+        let sent =
+          sendfile(&resource_table.borrow(), file_rid, conn_rid, remaining)?;
+        match sent {
+          Some(0) => break,
+          Some(n) => {
+            remaining -= n;
+            total += n;
+            continue;
+          }
+          None => {}
+        }
+      }
+
+      let len = std::cmp::min(remaining, buf.len() as u64) as usize;
+      let nread = poll_fn(|cx| {
+        let mut resource_table = resource_table.borrow_mut();
+        let resource_holder = resource_table
+          .get_mut::<StreamResourceHolder>(file_rid)
+          .ok_or_else(OpError::bad_resource_id)?;
+        resource_holder.resource.poll_read(cx, &mut buf[..len])
+      })
+      .await?;
+      if nread == 0 {
+        break;
+      }
+
+      let mut offset = 0;
+      while offset < nread {
+        let nwritten = poll_fn(|cx| {
+          let mut resource_table = resource_table.borrow_mut();
+          let resource_holder = resource_table
+            .get_mut::<StreamResourceHolder>(conn_rid)
+            .ok_or_else(OpError::bad_resource_id)?;
+          resource_holder.resource.poll_write(cx, &buf[offset..nread])
+        })
+        .await?;
+        if nwritten == 0 {
+          return Err(OpError::from(std::io::Error::from(
+            std::io::ErrorKind::WriteZero,
+          )));
+        }
+        offset += nwritten;
+      }
+      remaining -= nread as u64;
+      total += nread as u64;
+    }
+
+    poll_fn(|cx| {
+      let mut resource_table = resource_table.borrow_mut();
+      let resource_holder = resource_table
+        .get_mut::<StreamResourceHolder>(conn_rid)
+        .ok_or_else(OpError::bad_resource_id)?;
+      resource_holder.resource.poll_flush(cx)
+    })
+    .await?;
+
+    Ok(json!(total))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+/// Sends up to `count` bytes of the file to the connection with sendfile(2),
+/// which advances the position of the file. Returns the number of bytes sent,
+/// or `None` if they have to be copied through a buffer instead, because the
+/// socket is full or sendfile(2) can't handle one of the resources.
+#[cfg(target_os = "linux")]
+fn sendfile(
+  resource_table: &ResourceTable,
+  file_rid: u32,
+  conn_rid: u32,
+  count: u64,
+) -> Result<Option<u64>, OpError> {
+  use std::os::unix::io::AsRawFd;
+
+  let resource = |rid| {
+    resource_table
+      .get::<StreamResourceHolder>(rid)
+      .map(|resource_holder| &resource_holder.resource)
+      .ok_or_else(OpError::bad_resource_id)
+  };
+  let in_fd = match resource(file_rid)? {
+    StreamResource::FsFile(Some((file, _))) => file.as_raw_fd(),
+    _ => return Ok(None),
+  };
+  let out_fd = match resource(conn_rid)? {
+    StreamResource::TcpStream(Some(stream)) => stream.as_raw_fd(),
+    StreamResource::UnixStream(stream) => stream.as_raw_fd(),
+    _ => return Ok(None),
+  };
+  // The most sendfile(2) transfers at once.
+  let count = std::cmp::min(count, 0x7fff_f000) as usize;
+  let sent =
+    unsafe { libc::sendfile(out_fd, in_fd, std::ptr::null_mut(), count) };
+  if sent >= 0 {
+    return Ok(Some(sent as u64));
+  }
+  let err = std::io::Error::last_os_error();
+  match err.raw_os_error() {
+    // EINVAL is returned for the files that can't be mapped, like pipes.
+    Some(libc::EAGAIN) | Some(libc::EINVAL) | Some(libc::ENOSYS) => Ok(None),
+    _ => Err(OpError::from(err)),
+  }
+}
+
+#[derive(Deserialize)]
+struct SetIdleTimeoutsArgs {
+  rid: i32,
+  read: Option<u64>,
+  write: Option<u64>,
+}
+
+/// Sets how long reads and writes of a connection may wait, in milliseconds.
+/// Missing or zero timeouts are turned off. Ops that are already pending keep
+/// the timeouts they started with.
+fn op_set_idle_timeouts(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.setIdleTimeouts")?;
+  let args: SetIdleTimeoutsArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
+
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  let resource_holder = resource_table
+    .get_mut::<StreamResourceHolder>(rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+  match resource_holder.resource {
+    StreamResource::TcpStream(_)
+    | StreamResource::ClientTlsStream(_)
+    | StreamResource::ServerTlsStream(_) => {}
+    #[cfg(unix)]
+    StreamResource::UnixStream(_) => {}
+    _ => return Err(OpError::bad_resource_id()),
+  }
+  let duration = |ms: Option<u64>| match ms {
+    Some(ms) if ms > 0 => Some(Duration::from_millis(ms)),
+    _ => None,
+  };
+  resource_holder.idle_timeouts = IdleTimeouts {
+    read: duration(args.read),
+    write: duration(args.write),
+  };
+  Ok(JsonOp::Sync(json!({})))
+}
+
 #[allow(dead_code)]
 struct TcpListenerResource {
   listener: TcpListener,
@@ -464,6 +694,15 @@ fn listen_tcp(
   addr: SocketAddr,
 ) -> Result<(u32, SocketAddr), OpError> {
   let std_listener = std::net::TcpListener::bind(&addr)?;
+  add_tcp_listener(resource_table, std_listener)
+}
+
+/// Adds a listener to the resource table, like one that was received from
+/// another process.
+pub fn add_tcp_listener(
+  resource_table: &mut ResourceTable,
+  std_listener: std::net::TcpListener,
+) -> Result<(u32, SocketAddr), OpError> {
   let listener = TcpListener::from_std(std_listener)?;
   let local_addr = listener.local_addr()?;
   let listener_resource = TcpListenerResource {
@@ -476,6 +715,17 @@ fn listen_tcp(
   Ok((rid, local_addr))
 }
 
+#[cfg(unix)]
+pub fn tcp_listener_raw_fd(
+  resource_table: &ResourceTable,
+  rid: u32,
+) -> Option<std::os::unix::io::RawFd> {
+  use std::os::unix::io::AsRawFd;
+  resource_table
+    .get::<TcpListenerResource>(rid)
+    .map(|listener_resource| listener_resource.listener.as_raw_fd())
+}
+
 fn listen_udp(
   resource_table: &mut ResourceTable,
   addr: SocketAddr,
@@ -502,7 +752,7 @@ fn op_listen(
       transport_args: ArgsEnum::Ip(args),
     } => {
       if transport == "udp" {
-        state.check_unstable("Deno.listenDatagram");
+        state.check_unstable("Deno.listenDatagram")?;
       }
       state.check_net(&args.hostname, args.port)?;
       let addr = resolve_addr(&args.hostname, args.port)?;
@@ -512,6 +762,7 @@ fn op_listen(
         listen_udp(&mut resource_table, addr)?
       };
       debug!(
+        target: log_category::NET,
         "New listener {} {}:{}",
         rid,
         local_addr.ip().to_string(),
@@ -532,10 +783,10 @@ fn op_listen(
       transport_args: ArgsEnum::Unix(args),
     } if transport == "unix" || transport == "unixpacket" => {
       if transport == "unix" {
-        state.check_unstable("Deno.listen");
+        state.check_unstable("Deno.listen")?;
       }
       if transport == "unixpacket" {
-        state.check_unstable("Deno.listenDatagram");
+        state.check_unstable("Deno.listenDatagram")?;
       }
       let address_path = net_unix::Path::new(&args.path);
       state.check_read(&address_path)?;
@@ -546,6 +797,7 @@ fn op_listen(
         net_unix::listen_unix_packet(&mut resource_table, &address_path)?
       };
       debug!(
+        target: log_category::NET,
         "New listener {} {}",
         rid,
         local_addr.as_pathname().unwrap().display(),
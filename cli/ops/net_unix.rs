@@ -82,7 +82,11 @@ pub fn receive_unix_packet(
   rid: u32,
   zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  assert_eq!(zero_copy.len(), 1, "Invalid number of arguments");
+  if zero_copy.len() != 1 {
+    return Err(OpError::type_error(
+      "Invalid number of arguments".to_string(),
+    ));
+  }
   let mut zero_copy = zero_copy[0].clone();
   let resource_table = isolate_state.resource_table.clone();
 
@@ -31,7 +31,7 @@ fn op_get_dir(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.dir");
+  state.check_unstable("Deno.dir")?;
   state.check_env()?;
   let args: GetDirArgs = serde_json::from_value(args)?;
 
@@ -143,11 +143,15 @@ struct Exit {
 }
 
 fn op_exit(
-  _s: &State,
+  state: &State,
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
   let args: Exit = serde_json::from_value(args)?;
+  // The process exits without dropping the worker.
+  if let Some(tracer) = &state.borrow().op_tracer {
+    let _ = tracer.borrow_mut().finish();
+  }
   std::process::exit(args.code)
 }
 
@@ -156,7 +160,7 @@ fn op_loadavg(
   _args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.loadavg");
+  state.check_unstable("Deno.loadavg")?;
   state.check_env()?;
   match sys_info::loadavg() {
     Ok(loadavg) => Ok(JsonOp::Sync(json!([
@@ -173,7 +177,7 @@ fn op_hostname(
   _args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.hostname");
+  state.check_unstable("Deno.hostname")?;
   state.check_env()?;
   let hostname = sys_info::hostname().unwrap_or_else(|_| "".to_string());
   Ok(JsonOp::Sync(json!(hostname)))
@@ -184,7 +188,7 @@ fn op_os_release(
   _args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.osRelease");
+  state.check_unstable("Deno.osRelease")?;
   state.check_env()?;
   let release = sys_info::os_release().unwrap_or_else(|_| "".to_string());
   Ok(JsonOp::Sync(json!(release)))
@@ -1,4 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use crate::log_category;
 use crate::op_error::OpError;
 use crate::ops::dispatch_json::Deserialize;
 use crate::ops::dispatch_json::JsonOp;
@@ -39,29 +40,49 @@ pub fn op_open_plugin(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.openPlugin");
-  let args: OpenPluginArgs = serde_json::from_value(args).unwrap();
+  state.check_unstable("Deno.openPlugin")?;
+  let args: OpenPluginArgs = serde_json::from_value(args)?;
   let filename = PathBuf::from(&args.filename);
 
   state.check_plugin(&filename)?;
 
-  debug!("Loading Plugin: {:#?}", filename);
+  debug!(target: log_category::OPS, "Loading Plugin: {:#?}", filename);
   let plugin_lib = Library::open(filename)
     .map(Rc::new)
     .map_err(OpError::from)?;
-  let plugin_resource = PluginResource::new(&plugin_lib);
-
-  let mut resource_table = isolate_state.resource_table.borrow_mut();
-  let rid = resource_table.add("plugin", Box::new(plugin_resource));
-  let plugin_resource = resource_table.get::<PluginResource>(rid).unwrap();
-
-  let deno_plugin_init = *unsafe {
-    plugin_resource
-      .lib
-      .symbol::<plugin_api::InitFn>("deno_plugin_init")
+  // Any library can be opened, and only plugins can be initialized.
+  let deno_plugin_init =
+    *unsafe { plugin_lib.symbol::<plugin_api::InitFn>("deno_plugin_init") }
+      .map_err(|_| {
+        OpError::other(format!(
+          "{} is not a plugin, it doesn't export deno_plugin_init",
+          args.filename
+        ))
+      })?;
+
+  // The layout of the types shared with the plugin depends on the versions
+  // it was built with, so incompatible plugins would crash.
+  let descriptor = unsafe {
+    plugin_lib
+      .reference::<plugin_api::PluginDescriptor>("deno_plugin_descriptor")
   }
-  .unwrap();
-  drop(resource_table);
+  .map_err(|_| {
+    OpError::other(format!(
+      "{} can't be loaded, it doesn't export deno_plugin_descriptor",
+      args.filename
+    ))
+  })?;
+  unsafe { plugin_api::PluginDescriptor::check(&*descriptor) }.map_err(
+    |reason| {
+      OpError::other(format!("{} can't be loaded, {}", args.filename, reason))
+    },
+  )?;
+
+  let plugin_resource = PluginResource::new(&plugin_lib);
+  let rid = isolate_state
+    .resource_table
+    .borrow_mut()
+    .add("plugin", Box::new(plugin_resource));
 
   let mut interface = PluginInterface::new(isolate_state, &plugin_lib);
   deno_plugin_init(&mut interface);
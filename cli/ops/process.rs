@@ -1,6 +1,8 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
-use super::io::{std_file_resource, StreamResource, StreamResourceHolder};
+use super::io::{
+  std_file_resource, FileMetadata, StreamResource, StreamResourceHolder,
+};
 use crate::op_error::OpError;
 use crate::signal::kill;
 use crate::state::State;
@@ -14,6 +16,10 @@ use futures::TryFutureExt;
 use std::convert::From;
 use tokio::process::Command;
 
+#[cfg(unix)]
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, FromRawFd};
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt;
 
@@ -23,14 +29,68 @@ pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_kill", s.stateful_json_op(op_kill));
 }
 
-fn clone_file(
+/// Turns the resource `rid` into a file to use as stdio for a child.
+///
+/// Files are shared with the child. On Unix, sockets and the pipes of other
+/// children can be used too; those are moved to the child, so they are
+/// closed here, and made blocking like programs expect their stdio to be.
+fn resource_file(
+  rid: u32,
+  resource_table: &mut ResourceTable,
+) -> Result<std::fs::File, OpError> {
+  let file = std_file_resource(resource_table, rid, move |r| match r {
+    Ok(std_file) => std_file.try_clone().map(Some).map_err(OpError::from),
+    Err(_) => Ok(None),
+  })?;
+  match file {
+    Some(file) => Ok(file),
+    None => move_to_child(rid, resource_table),
+  }
+}
+
+#[cfg(unix)]
+fn move_to_child(
   rid: u32,
   resource_table: &mut ResourceTable,
 ) -> Result<std::fs::File, OpError> {
-  std_file_resource(resource_table, rid, move |r| match r {
-    Ok(std_file) => std_file.try_clone().map_err(OpError::from),
-    Err(_) => Err(OpError::bad_resource_id()),
-  })
+  let resource_holder = resource_table.get::<StreamResourceHolder>(rid);
+  let fd = match resource_holder.map(|holder| &holder.resource) {
+    Some(StreamResource::ChildStdin(f)) => f.as_raw_fd(),
+    Some(StreamResource::ChildStdout(f)) => f.as_raw_fd(),
+    Some(StreamResource::ChildStderr(f)) => f.as_raw_fd(),
+    _ => super::fds::raw_fd(resource_table, rid)?,
+  };
+  let fd = fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(0))?;
+  let file = unsafe { std::fs::File::from_raw_fd(fd) };
+  // The flag is shared with the closed resource, which nothing uses anymore.
+  let flags = OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL)?);
+  fcntl(fd, FcntlArg::F_SETFL(flags & !OFlag::O_NONBLOCK))?;
+  resource_table.close(rid);
+  Ok(file)
+}
+
+#[cfg(not(unix))]
+fn move_to_child(
+  _rid: u32,
+  _resource_table: &mut ResourceTable,
+) -> Result<std::fs::File, OpError> {
+  Err(OpError::type_error(
+    "Only files can be used as stdio on Windows".to_string(),
+  ))
+}
+
+/// Turns the reading end of a pipe into a file, to read from it like from
+/// the other files.
+#[cfg(unix)]
+fn pipe_file(reader: os_pipe::PipeReader) -> std::fs::File {
+  use std::os::unix::io::IntoRawFd;
+  unsafe { std::fs::File::from_raw_fd(reader.into_raw_fd()) }
+}
+
+#[cfg(windows)]
+fn pipe_file(reader: os_pipe::PipeReader) -> std::fs::File {
+  use std::os::windows::io::{FromRawHandle, IntoRawHandle};
+  unsafe { std::fs::File::from_raw_handle(reader.into_raw_handle()) }
 }
 
 fn subprocess_stdio_map(s: &str) -> std::process::Stdio {
@@ -75,7 +135,10 @@ fn op_run(
   let env = run_args.env;
   let cwd = run_args.cwd;
 
-  let mut c = Command::new(args.get(0).unwrap());
+  let cmd = args
+    .get(0)
+    .ok_or_else(|| OpError::type_error("The command is empty".to_string()))?;
+  let mut c = Command::new(cmd);
   (1..args.len()).for_each(|i| {
     let arg = args.get(i).unwrap();
     c.arg(arg);
@@ -85,28 +148,46 @@ fn op_run(
     c.env(key, value);
   }
 
-  // TODO: make this work with other resources, eg. sockets
   let stdin_rid = run_args.stdin_rid;
   if stdin_rid > 0 {
-    let file = clone_file(stdin_rid, &mut resource_table)?;
-    c.stdin(file);
+    c.stdin(resource_file(stdin_rid, &mut resource_table)?);
   } else {
     c.stdin(subprocess_stdio_map(run_args.stdin.as_ref()));
   }
 
+  // With "stdout", stderr goes wherever stdout goes. When stdout is piped,
+  // both are read from the stdout resource.
+  let merge_stderr = run_args.stderr == "stdout";
+  let mut output_reader = None;
+
   let stdout_rid = run_args.stdout_rid;
   if stdout_rid > 0 {
-    let file = clone_file(stdout_rid, &mut resource_table)?;
+    let file = resource_file(stdout_rid, &mut resource_table)?;
+    if merge_stderr {
+      c.stderr(file.try_clone()?);
+    }
     c.stdout(file);
+  } else if merge_stderr && run_args.stdout == "piped" {
+    // The child writes both to the same pipe, so that their output is read
+    // in the order it was written.
+    let (reader, writer) = os_pipe::pipe()?;
+    c.stderr(writer.try_clone()?);
+    c.stdout(writer);
+    output_reader = Some(reader);
   } else {
     c.stdout(subprocess_stdio_map(run_args.stdout.as_ref()));
+    if merge_stderr && run_args.stdout == "inherit" {
+      // The stdout of this process is the file behind rid 1.
+      c.stderr(resource_file(1, &mut resource_table)?);
+    } else if merge_stderr {
+      c.stderr(subprocess_stdio_map(run_args.stdout.as_ref()));
+    }
   }
 
   let stderr_rid = run_args.stderr_rid;
   if stderr_rid > 0 {
-    let file = clone_file(stderr_rid, &mut resource_table)?;
-    c.stderr(file);
-  } else {
+    c.stderr(resource_file(stderr_rid, &mut resource_table)?);
+  } else if !merge_stderr {
     c.stderr(subprocess_stdio_map(run_args.stderr.as_ref()));
   }
 
@@ -116,6 +197,9 @@ fn op_run(
   // Spawn the command.
   let mut child = c.spawn()?;
   let pid = child.id();
+  // The command keeps the writing ends of the pipe given to the child open,
+  // and its reader wouldn't see EOF until they're closed.
+  drop(c);
 
   let stdin_rid = match child.stdin.take() {
     Some(child_stdin) => {
@@ -130,8 +214,19 @@ fn op_run(
     None => None,
   };
 
-  let stdout_rid = match child.stdout.take() {
-    Some(child_stdout) => {
+  let stdout_rid = match (child.stdout.take(), output_reader) {
+    (_, Some(reader)) => {
+      let file = tokio::fs::File::from_std(pipe_file(reader));
+      let rid = resource_table.add(
+        "childStdout",
+        Box::new(StreamResourceHolder::new(StreamResource::FsFile(Some((
+          file,
+          FileMetadata::default(),
+        ))))),
+      );
+      Some(rid)
+    }
+    (Some(child_stdout), None) => {
       let rid = resource_table.add(
         "childStdout",
         Box::new(StreamResourceHolder::new(StreamResource::ChildStdout(
@@ -140,7 +235,7 @@ fn op_run(
       );
       Some(rid)
     }
-    None => None,
+    (None, None) => None,
   };
 
   let stderr_rid = match child.stderr.take() {
@@ -230,7 +325,7 @@ fn op_kill(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.kill");
+  state.check_unstable("Deno.kill")?;
   state.check_run()?;
 
   let args: KillArgs = serde_json::from_value(args)?;
@@ -19,7 +19,11 @@ fn op_get_random_values(
   _args: Value,
   zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  assert_eq!(zero_copy.len(), 1);
+  if zero_copy.len() != 1 {
+    return Err(OpError::type_error(
+      "Invalid number of arguments".to_string(),
+    ));
+  }
 
   if let Some(ref mut seeded_rng) = state.borrow_mut().seeded_rng {
     seeded_rng.fill(&mut *zero_copy[0]);
@@ -1,21 +1,43 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-use super::dispatch_json::{blocking_json, Deserialize, JsonOp, Value};
+use super::dispatch_json::{Deserialize, JsonOp, Value};
+use crate::flags::DenoSubcommand;
+use crate::log_category;
 use crate::op_error::OpError;
 use crate::repl;
 use crate::repl::Repl;
+use crate::repl::ReplEvent;
 use crate::state::State;
 use deno_core::CoreIsolate;
 use deno_core::CoreIsolateState;
 use deno_core::ZeroCopyBuf;
+use futures::channel::mpsc;
+use futures::future::FutureExt;
+use futures::StreamExt;
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
 
 pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_repl_start", s.stateful_json_op2(op_repl_start));
   i.register_op("op_repl_readline", s.stateful_json_op2(op_repl_readline));
+  i.register_op("op_repl_complete", s.stateful_json_op2(op_repl_complete));
+  i.register_op("op_repl_break", s.stateful_json_op(op_repl_break));
+  i.register_op(
+    "op_repl_is_incomplete",
+    s.stateful_json_op(op_repl_is_incomplete),
+  );
 }
 
-struct ReplResource(Arc<Mutex<Repl>>);
+struct ReplResource {
+  repl: Arc<Mutex<Repl>>,
+  /// Whether a line is being read on the editor thread.
+  reading: Rc<Cell<bool>>,
+  event_sender: mpsc::UnboundedSender<ReplEvent>,
+  events: Arc<futures::lock::Mutex<mpsc::UnboundedReceiver<ReplEvent>>>,
+  completions: std::sync::mpsc::Sender<Vec<String>>,
+}
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -30,14 +52,45 @@ fn op_repl_start(
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
   let args: ReplStartArgs = serde_json::from_value(args)?;
-  debug!("op_repl_start {}", args.history_file);
+  debug!(target: log_category::OPS, "op_repl_start {}", args.history_file);
+  let state = state.borrow();
   let history_path =
-    repl::history_path(&state.borrow().global_state.dir, &args.history_file);
-  let repl = repl::Repl::new(history_path);
-  let resource = ReplResource(Arc::new(Mutex::new(repl)));
+    repl::history_path(&state.global_state.dir, &args.history_file);
+  let (eval, eval_files) = match &state.global_state.flags.subcommand {
+    DenoSubcommand::Repl { eval, eval_files } => {
+      // Absolute, since they're used as script names.
+      let cwd = std::env::current_dir()?;
+      let eval_files: Vec<PathBuf> =
+        eval_files.iter().map(|file| cwd.join(file)).collect();
+      (eval.clone(), eval_files)
+    }
+    _ => (None, vec![]),
+  };
+  let (event_sender, events) = mpsc::unbounded();
+  let (completions, completions_receiver) = std::sync::mpsc::channel();
+  let repl =
+    repl::Repl::new(history_path, event_sender.clone(), completions_receiver);
+  let resource = ReplResource {
+    repl: Arc::new(Mutex::new(repl)),
+    reading: Rc::new(Cell::new(false)),
+    event_sender,
+    events: Arc::new(futures::lock::Mutex::new(events)),
+    completions,
+  };
+  // While execution is paused, the debugger reads its commands from the
+  // same line editor.
+  let debugger = state.repl_debugger.as_ref();
+  if let Some(debugger) = debugger {
+    debugger.borrow_mut().repl = Some(resource.repl.clone());
+  }
   let mut resource_table = isolate_state.resource_table.borrow_mut();
   let rid = resource_table.add("repl", Box::new(resource));
-  Ok(JsonOp::Sync(json!(rid)))
+  Ok(JsonOp::Sync(json!({
+    "rid": rid,
+    "eval": eval,
+    "evalFiles": eval_files,
+    "debugger": debugger.is_some(),
+  })))
 }
 
 #[derive(Deserialize)]
@@ -55,15 +108,91 @@ fn op_repl_readline(
   let args: ReplReadlineArgs = serde_json::from_value(args)?;
   let rid = args.rid as u32;
   let prompt = args.prompt;
-  debug!("op_repl_readline {} {}", rid, prompt);
+  debug!(target: log_category::OPS, "op_repl_readline {} {}", rid, prompt);
+  let resource_table = isolate_state.resource_table.borrow();
+  let resource = resource_table
+    .get::<ReplResource>(rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+
+  // A completion request returns before the line is read; the next call
+  // keeps waiting for the same line.
+  if !resource.reading.replace(true) {
+    let repl = resource.repl.clone();
+    let event_sender = resource.event_sender.clone();
+    tokio::task::spawn_blocking(move || {
+      let line = repl.lock().unwrap().readline(&prompt);
+      let _ = event_sender.unbounded_send(ReplEvent::Line(line));
+    });
+  }
+  let reading = resource.reading.clone();
+  let events = resource.events.clone();
+
+  let fut = async move {
+    // The resource holds a sender, so the channel stays open.
+    let event = events.lock().await.next().await.unwrap();
+    match event {
+      ReplEvent::Line(line) => {
+        reading.set(false);
+        Ok(json!({ "line": line? }))
+      }
+      ReplEvent::Complete(expr) => Ok(json!({ "complete": expr })),
+    }
+  };
+  Ok(JsonOp::Async(fut.boxed_local()))
+}
+
+#[derive(Deserialize)]
+struct ReplCompleteArgs {
+  rid: i32,
+  completions: Vec<String>,
+}
+
+fn op_repl_complete(
+  isolate_state: &mut CoreIsolateState,
+  _state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  let args: ReplCompleteArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
   let resource_table = isolate_state.resource_table.borrow();
   let resource = resource_table
     .get::<ReplResource>(rid)
     .ok_or_else(OpError::bad_resource_id)?;
-  let repl = resource.0.clone();
+  // The editor thread is gone if the line was read in the meantime.
+  let _ = resource.completions.send(args.completions);
+  Ok(JsonOp::Sync(json!({})))
+}
+
+#[derive(Deserialize)]
+struct ReplBreakArgs {
+  location: String,
+}
+
+fn op_repl_break(
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  let args: ReplBreakArgs = serde_json::from_value(args)?;
+  debug!(target: log_category::OPS, "op_repl_break {}", args.location);
+  let debugger = state.borrow().repl_debugger.clone().ok_or_else(|| {
+    OpError::other("The debugger of the REPL needs --inspect".to_string())
+  })?;
+  let message = debugger.borrow_mut().set_breakpoint(&args.location)?;
+  Ok(JsonOp::Sync(json!({ "message": message })))
+}
+
+#[derive(Deserialize)]
+struct ReplIsIncompleteArgs {
+  code: String,
+}
 
-  blocking_json(false, move || {
-    let line = repl.lock().unwrap().readline(&prompt)?;
-    Ok(json!(line))
-  })
+fn op_repl_is_incomplete(
+  _state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  let args: ReplIsIncompleteArgs = serde_json::from_value(args)?;
+  Ok(JsonOp::Sync(json!(repl::is_incomplete(&args.code))))
 }
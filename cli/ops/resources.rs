@@ -1,14 +1,23 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
+use super::io::{DenoAsyncWrite, StreamResource, StreamResourceHolder};
 use crate::op_error::OpError;
 use crate::state::State;
 use deno_core::CoreIsolate;
 use deno_core::CoreIsolateState;
 use deno_core::ZeroCopyBuf;
+use futures::future::poll_fn;
+use futures::future::FutureExt;
+use std::time::Duration;
 
 pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_resources", s.stateful_json_op2(op_resources));
   i.register_op("op_close", s.stateful_json_op2(op_close));
+  i.register_op("op_finish_close", s.stateful_json_op(op_finish_close));
+  i.register_op(
+    "op_set_resource_origin",
+    s.stateful_json_op(op_set_resource_origin),
+  );
 }
 
 fn op_resources(
@@ -21,21 +30,92 @@ fn op_resources(
   Ok(JsonOp::Sync(json!(serialized_resources)))
 }
 
+#[derive(Deserialize)]
+struct CloseArgs {
+  rid: i32,
+}
+
 /// op_close removes a resource from the resource table.
+///
+/// Sockets aren't dropped right away: they are kept aside, reported as
+/// `closing`, and JS then calls op_finish_close to shut them down in order.
 fn op_close(
   isolate_state: &mut CoreIsolateState,
-  _state: &State,
+  state: &State,
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  #[derive(Deserialize)]
-  struct CloseArgs {
-    rid: i32,
-  }
   let args: CloseArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
   let mut resource_table = isolate_state.resource_table.borrow_mut();
+  let is_socket = match resource_table.get::<StreamResourceHolder>(rid) {
+    Some(resource_holder) => match resource_holder.resource {
+      StreamResource::TcpStream(Some(_))
+      | StreamResource::ClientTlsStream(_)
+      | StreamResource::ServerTlsStream(_) => true,
+      #[cfg(not(windows))]
+      StreamResource::UnixStream(_) => true,
+      _ => false,
+    },
+    None => false,
+  };
+  if is_socket {
+    let mut resource_holder =
+      resource_table.remove::<StreamResourceHolder>(rid).unwrap();
+    // Pending reads and writes fail like for any closed resource.
+    resource_holder.wake_tasks();
+    let mut state = state.borrow_mut();
+    state.closing_sockets.insert(rid, *resource_holder);
+    return Ok(JsonOp::Sync(json!({ "closing": true })));
+  }
   resource_table
-    .close(args.rid as u32)
+    .close(rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+  Ok(JsonOp::Sync(json!({})))
+}
+
+/// How long a closed socket may take to flush and send its FIN before it is
+/// dropped anyway.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Flushes a socket closed by op_close, shuts its writing side down and only
+/// then drops it, so that the peer gets everything that was written. While
+/// this is pending, the event loop is kept alive.
+fn op_finish_close(
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  let args: CloseArgs = serde_json::from_value(args)?;
+  let mut resource_holder = state
+    .borrow_mut()
+    .closing_sockets
+    .remove(&(args.rid as u32))
     .ok_or_else(OpError::bad_resource_id)?;
+
+  let fut = async move {
+    let close = poll_fn(|cx| resource_holder.resource.poll_close(cx));
+    // The socket is gone for JS already, so errors (like a peer that reset
+    // the connection) and timeouts are nothing to report.
+    let _ = tokio::time::timeout(CLOSE_TIMEOUT, close).await;
+    Ok(json!({}))
+  };
+  Ok(JsonOp::Async(fut.boxed_local()))
+}
+
+#[derive(Deserialize)]
+struct SetResourceOriginArgs {
+  origin: String,
+}
+
+/// Sets the origin of the resources the next op adds. JS calls it before
+/// every op with `--strict-resources`.
+fn op_set_resource_origin(
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  let args: SetResourceOriginArgs = serde_json::from_value(args)?;
+  state.borrow_mut().next_op_origin = Some(args.origin.into());
   Ok(JsonOp::Sync(json!({})))
 }
@@ -1,5 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-use super::dispatch_json::{JsonOp, Value};
+use super::dispatch_json::{Deserialize, JsonOp, Value};
 use crate::colors;
 use crate::op_error::OpError;
 use crate::state::State;
@@ -12,6 +12,7 @@ use std::env;
 pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_start", s.stateful_json_op(op_start));
   i.register_op("op_metrics", s.stateful_json_op(op_metrics));
+  i.register_op("op_cancel", s.stateful_json_op(op_cancel));
 }
 
 fn op_start(
@@ -28,9 +29,12 @@ fn op_start(
     "cwd": &env::current_dir().unwrap(),
     "debugFlag": gs.flags.log_level.map_or(false, |l| l == log::Level::Debug),
     "denoVersion": version::DENO,
-    "noColor": !colors::use_color(),
+    "frozenTime": gs.flags.frozen_time,
+    "noColor": colors::no_color(),
+    "opStackTraces": gs.flags.op_stack_traces,
     "pid": std::process::id(),
-    "repl": gs.flags.subcommand == DenoSubcommand::Repl,
+    "repl": matches!(gs.flags.subcommand, DenoSubcommand::Repl { .. }),
+    "strictResources": gs.flags.strict_resources,
     "target": env!("TARGET"),
     "tsVersion": version::TYPESCRIPT,
     "unstableFlag": gs.flags.unstable,
@@ -61,3 +65,28 @@ fn op_metrics(
     "bytesReceived": m.bytes_received
   })))
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CancelArgs {
+  id: u64,
+}
+
+/// Aborts the pending async JSON op with the promise id `id`. Returns whether
+/// there was such an op; it may have completed in the meantime.
+fn op_cancel(
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  let args: CancelArgs = serde_json::from_value(args)?;
+  let maybe_handle = state.borrow_mut().cancel_handles.remove(&args.id);
+  let canceled = match maybe_handle {
+    Some(handle) => {
+      handle.abort();
+      true
+    }
+    None => false,
+  };
+  Ok(JsonOp::Sync(json!(canceled)))
+}
@@ -28,7 +28,7 @@ fn op_compile(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.compile");
+  state.check_unstable("Deno.compile")?;
   let args: CompileArgs = serde_json::from_value(args)?;
   let s = state.borrow();
   let global_state = s.global_state.clone();
@@ -59,7 +59,7 @@ fn op_transpile(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.transpile");
+  state.check_unstable("Deno.transpile")?;
   let args: TranspileArgs = serde_json::from_value(args)?;
   let s = state.borrow();
   let global_state = s.global_state.clone();
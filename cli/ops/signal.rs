@@ -4,6 +4,7 @@ use crate::op_error::OpError;
 use crate::state::State;
 use deno_core::CoreIsolate;
 use deno_core::CoreIsolateState;
+use deno_core::OpPriority;
 use deno_core::ZeroCopyBuf;
 
 #[cfg(unix)]
@@ -18,7 +19,11 @@ use tokio::signal::unix::{signal, Signal, SignalKind};
 pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_signal_bind", s.stateful_json_op2(op_signal_bind));
   i.register_op("op_signal_unbind", s.stateful_json_op2(op_signal_unbind));
-  i.register_op("op_signal_poll", s.stateful_json_op2(op_signal_poll));
+  i.register_op_with_priority(
+    "op_signal_poll",
+    OpPriority::High,
+    s.stateful_json_op2(op_signal_poll),
+  );
 }
 
 #[cfg(unix)]
@@ -45,16 +50,12 @@ fn op_signal_bind(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.signal");
+  state.check_unstable("Deno.signal")?;
   let args: BindSignalArgs = serde_json::from_value(args)?;
+  let stream = signal(SignalKind::from_raw(args.signo))?;
   let mut resource_table = isolate_state.resource_table.borrow_mut();
-  let rid = resource_table.add(
-    "signal",
-    Box::new(SignalStreamResource(
-      signal(SignalKind::from_raw(args.signo)).expect(""),
-      None,
-    )),
-  );
+  let rid =
+    resource_table.add("signal", Box::new(SignalStreamResource(stream, None)));
   Ok(JsonOp::Sync(json!({
     "rid": rid,
   })))
@@ -67,7 +68,7 @@ fn op_signal_poll(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.signal");
+  state.check_unstable("Deno.signal")?;
   let args: SignalArgs = serde_json::from_value(args)?;
   let rid = args.rid as u32;
   let resource_table = isolate_state.resource_table.clone();
@@ -94,7 +95,7 @@ pub fn op_signal_unbind(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.signal");
+  state.check_unstable("Deno.signal")?;
   let args: SignalArgs = serde_json::from_value(args)?;
   let rid = args.rid as u32;
   let mut resource_table = isolate_state.resource_table.borrow_mut();
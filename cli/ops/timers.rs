@@ -1,78 +1,143 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
+use super::dispatch_minimal::MinimalOp;
 use crate::op_error::OpError;
 use crate::state::State;
 use deno_core::CoreIsolate;
+use deno_core::CoreIsolateState;
+use deno_core::OpPriority;
 use deno_core::ZeroCopyBuf;
+use futures::future::poll_fn;
 use futures::future::FutureExt;
-use std::time::Duration;
-use std::time::Instant;
 
 pub fn init(i: &mut CoreIsolate, s: &State) {
-  i.register_op(
-    "op_global_timer_stop",
-    s.stateful_json_op(op_global_timer_stop),
+  i.register_op("op_timer_start", s.stateful_minimal_op2(op_timer_start));
+  i.register_op("op_timer_cancel", s.stateful_minimal_op2(op_timer_cancel));
+  i.register_op_with_priority(
+    "op_timer_poll",
+    OpPriority::High,
+    s.stateful_json_op(op_timer_poll),
   );
-  i.register_op("op_global_timer", s.stateful_json_op(op_global_timer));
-  i.register_op("op_now", s.stateful_json_op(op_now));
+  i.register_op("op_now", s.stateful_minimal_op2(op_now));
+  i.register_op("op_fake_time_step", s.stateful_json_op(op_fake_time_step));
 }
 
-fn op_global_timer_stop(
+// Schedules the timer whose id is given as the argument. The buffer holds the
+// delay in milliseconds and whether the timer repeats, as two u32.
+// Like op_now this is a minimal op, since timers are often set and cleared in
+// hot code.
+fn op_timer_start(
+  _isolate_state: &mut CoreIsolateState,
+  state: &State,
+  _is_sync: bool,
+  id: i32,
+  zero_copy: &mut [ZeroCopyBuf],
+) -> MinimalOp {
+  if zero_copy.len() != 1 || zero_copy[0].len() < 8 {
+    return MinimalOp::Sync(Err(OpError::type_error(
+      "op_timer_start needs an 8 byte buffer".to_string(),
+    )));
+  }
+  let buf = &zero_copy[0];
+  let delay = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]);
+  let repeat = u32::from_ne_bytes([buf[4], buf[5], buf[6], buf[7]]) != 0;
+
+  let mut state = state.borrow_mut();
+  state.timers.start(id as u32, delay as u64, repeat);
+  MinimalOp::Sync(Ok(0))
+}
+
+fn op_timer_cancel(
+  _isolate_state: &mut CoreIsolateState,
+  state: &State,
+  _is_sync: bool,
+  id: i32,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> MinimalOp {
+  let mut state = state.borrow_mut();
+  state.timers.cancel(id as u32);
+  MinimalOp::Sync(Ok(0))
+}
+
+// Resolves with the ids of the timers that are due. Only one of these is
+// pending at a time, whatever the number of timers.
+fn op_timer_poll(
   state: &State,
   _args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  let mut state = state.borrow_mut();
-  state.global_timer.cancel();
-  Ok(JsonOp::Sync(json!({})))
+  let state = state.clone();
+  let f = poll_fn(move |cx| state.borrow_mut().timers.poll_due(cx))
+    .map(|ids| Ok(json!(ids)));
+
+  Ok(JsonOp::Async(f.boxed_local()))
 }
 
 #[derive(Deserialize)]
-struct GlobalTimerArgs {
-  timeout: u64,
+struct FakeTimeStepArgs {
+  target: u64,
 }
 
-fn op_global_timer(
+// Moves the frozen clock towards `target`, in milliseconds since the start
+// time, and returns the ids of the timers that are due at the next deadline,
+// or null once the clock got there. `Deno.advanceTime()` fires those timers
+// before taking the next step, so that they see the time they were due at.
+fn op_fake_time_step(
   state: &State,
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  let args: GlobalTimerArgs = serde_json::from_value(args)?;
-  let val = args.timeout;
-
+  state.check_unstable("Deno.advanceTime")?;
+  let args: FakeTimeStepArgs = serde_json::from_value(args)?;
   let mut state = state.borrow_mut();
-  let deadline = Instant::now() + Duration::from_millis(val);
-  let f = state
-    .global_timer
-    .new_timeout(deadline)
-    .then(move |_| futures::future::ok(json!({})));
-
-  Ok(JsonOp::Async(f.boxed_local()))
+  if state.timers.frozen_now().is_none() {
+    return Err(OpError::other(
+      "The clock can only be advanced with --frozen-time".to_string(),
+    ));
+  }
+  let ids = state.timers.step(args.target);
+  Ok(JsonOp::Sync(json!(ids)))
 }
 
-// Returns a milliseconds and nanoseconds subsec
-// since the start time of the deno runtime.
-// If the High precision flag is not set, the
-// nanoseconds are rounded on 2ms.
+// Writes the seconds and the subsec nanoseconds since the start time of the
+// deno runtime into the buffer, as two u32. If the High precision flag is
+// not set, the nanoseconds are rounded on 2ms. With --frozen-time this is the
+// time of the virtual clock instead, as is.
+// Like op_read and op_write this is a minimal op, since performance.now() is
+// often called in hot loops.
 fn op_now(
+  _isolate_state: &mut CoreIsolateState,
   state: &State,
-  _args: Value,
-  _zero_copy: &mut [ZeroCopyBuf],
-) -> Result<JsonOp, OpError> {
+  _is_sync: bool,
+  _arg: i32,
+  zero_copy: &mut [ZeroCopyBuf],
+) -> MinimalOp {
+  if zero_copy.len() != 1 || zero_copy[0].len() < 8 {
+    return MinimalOp::Sync(Err(OpError::type_error(
+      "op_now needs an 8 byte buffer".to_string(),
+    )));
+  }
+
   let state = state.borrow();
-  let seconds = state.start_time.elapsed().as_secs();
-  let mut subsec_nanos = state.start_time.elapsed().subsec_nanos();
-  let reduced_time_precision = 2_000_000; // 2ms in nanoseconds
+  let (seconds, subsec_nanos) = if let Some(now) = state.timers.frozen_now() {
+    // The virtual clock doesn't tell anything about the real one.
+    ((now / 1000) as u32, (now % 1000) as u32 * 1_000_000)
+  } else {
+    let elapsed = state.start_time.elapsed();
+    let mut subsec_nanos = elapsed.subsec_nanos();
+    let reduced_time_precision = 2_000_000; // 2ms in nanoseconds
 
-  // If the permission is not enabled
-  // Round the nano result on 2 milliseconds
-  // see: https://developer.mozilla.org/en-US/docs/Web/API/DOMHighResTimeStamp#Reduced_time_precision
-  if !state.permissions.allow_hrtime.is_allow() {
-    subsec_nanos -= subsec_nanos % reduced_time_precision
-  }
+    // If the permission is not enabled
+    // Round the nano result on 2 milliseconds
+    // see: https://developer.mozilla.org/en-US/docs/Web/API/DOMHighResTimeStamp#Reduced_time_precision
+    if state.permissions.check_hrtime().is_err() {
+      subsec_nanos -= subsec_nanos % reduced_time_precision
+    }
+    (elapsed.as_secs() as u32, subsec_nanos)
+  };
 
-  Ok(JsonOp::Sync(json!({
-    "seconds": seconds,
-    "subsecNanos": subsec_nanos,
-  })))
+  let buf = &mut zero_copy[0];
+  buf[0..4].copy_from_slice(&seconds.to_ne_bytes());
+  buf[4..8].copy_from_slice(&subsec_nanos.to_ne_bytes());
+  MinimalOp::Sync(Ok(0))
 }
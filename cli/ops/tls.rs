@@ -2,7 +2,9 @@
 use super::dispatch_json::{Deserialize, JsonOp, Value};
 use super::io::{StreamResource, StreamResourceHolder};
 use crate::op_error::OpError;
+use crate::resolve_addr::connect_tcp;
 use crate::resolve_addr::resolve_addr;
+use crate::resolve_addr::resolve_addrs;
 use crate::state::State;
 use deno_core::CoreIsolate;
 use deno_core::CoreIsolateState;
@@ -18,7 +20,6 @@ use std::sync::Arc;
 use std::task::Context;
 use std::task::Poll;
 use tokio::net::TcpListener;
-use tokio::net::TcpStream;
 use tokio_rustls::{rustls::ClientConfig, TlsConnector};
 use tokio_rustls::{
   rustls::{
@@ -59,7 +60,7 @@ pub fn op_start_tls(
   args: Value,
   _zero_copy: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.startTls");
+  state.check_unstable("Deno.startTls")?;
   let args: StartTLSArgs = serde_json::from_value(args)?;
   let rid = args.rid as u32;
   let cert_file = args.cert_file.clone();
@@ -97,12 +98,16 @@ pub fn op_start_tls(
       if let Some(path) = cert_file {
         let key_file = File::open(path)?;
         let reader = &mut BufReader::new(key_file);
-        config.root_store.add_pem_file(reader).unwrap();
+        config.root_store.add_pem_file(reader).map_err(|_| {
+          OpError::other(
+            "Unable to add pem file to certificate store".to_string(),
+          )
+        })?;
       }
 
       let tls_connector = TlsConnector::from(Arc::new(config));
-      let dnsname =
-        DNSNameRef::try_from_ascii_str(&domain).expect("Invalid DNS lookup");
+      let dnsname = DNSNameRef::try_from_ascii_str(&domain)
+        .map_err(|_| OpError::type_error("Invalid DNS name".to_string()))?;
       let tls_stream = tls_connector.connect(dnsname, tcp_stream).await?;
 
       let mut resource_table_ = resource_table.borrow_mut();
@@ -152,8 +157,8 @@ pub fn op_connect_tls(
   }
 
   let op = async move {
-    let addr = resolve_addr(&args.hostname, args.port)?;
-    let tcp_stream = TcpStream::connect(&addr).await?;
+    let addrs = resolve_addrs(&args.hostname, args.port)?;
+    let tcp_stream = connect_tcp(addrs).await?;
     let local_addr = tcp_stream.local_addr()?;
     let remote_addr = tcp_stream.peer_addr()?;
     let mut config = ClientConfig::new();
@@ -163,11 +168,15 @@ pub fn op_connect_tls(
     if let Some(path) = cert_file {
       let key_file = File::open(path)?;
       let reader = &mut BufReader::new(key_file);
-      config.root_store.add_pem_file(reader).unwrap();
+      config.root_store.add_pem_file(reader).map_err(|_| {
+        OpError::other(
+          "Unable to add pem file to certificate store".to_string(),
+        )
+      })?;
     }
     let tls_connector = TlsConnector::from(Arc::new(config));
-    let dnsname =
-      DNSNameRef::try_from_ascii_str(&domain).expect("Invalid DNS lookup");
+    let dnsname = DNSNameRef::try_from_ascii_str(&domain)
+      .map_err(|_| OpError::type_error("Invalid DNS name".to_string()))?;
     let tls_stream = tls_connector.connect(dnsname, tcp_stream).await?;
     let mut resource_table_ = resource_table.borrow_mut();
     let rid = resource_table_.add(
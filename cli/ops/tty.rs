@@ -1,15 +1,19 @@
 use super::dispatch_json::JsonOp;
-use super::io::std_file_resource;
-use super::io::{StreamResource, StreamResourceHolder};
-use crate::op_error::OpError;
+use super::io::{StreamResource, StreamResourceHolder, TTYMetadata};
+use crate::op_error::{ErrorKind, OpError};
 use crate::state::State;
 use deno_core::CoreIsolate;
 use deno_core::CoreIsolateState;
+use deno_core::ResourceTable;
 use deno_core::ZeroCopyBuf;
 #[cfg(unix)]
 use nix::sys::termios;
 use serde_derive::Deserialize;
 use serde_json::Value;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+#[cfg(windows)]
+use std::os::windows::io::AsRawHandle;
 
 #[cfg(windows)]
 use winapi::shared::minwindef::DWORD;
@@ -19,147 +23,162 @@ use winapi::um::wincon;
 const RAW_MODE_MASK: DWORD = wincon::ENABLE_LINE_INPUT
   | wincon::ENABLE_ECHO_INPUT
   | wincon::ENABLE_PROCESSED_INPUT;
-#[cfg(windows)]
-fn get_windows_handle(
-  f: &std::fs::File,
-) -> Result<std::os::windows::io::RawHandle, OpError> {
-  use std::os::windows::io::AsRawHandle;
-  use winapi::um::handleapi;
-
-  let handle = f.as_raw_handle();
-  if handle == handleapi::INVALID_HANDLE_VALUE {
-    return Err(OpError::from(std::io::Error::last_os_error()));
-  } else if handle.is_null() {
-    return Err(OpError::other("null handle".to_owned()));
-  }
-  Ok(handle)
-}
-
 pub fn init(i: &mut CoreIsolate, s: &State) {
   i.register_op("op_set_raw", s.stateful_json_op2(op_set_raw));
   i.register_op("op_isatty", s.stateful_json_op2(op_isatty));
+  i.register_op("op_console_size", s.stateful_json_op2(op_console_size));
 }
 
-#[derive(Deserialize)]
-struct SetRawArgs {
-  rid: u32,
-  mode: bool,
-}
-
-pub fn op_set_raw(
-  isolate_state: &mut CoreIsolateState,
-  state: &State,
-  args: Value,
-  _zero_copy: &mut [ZeroCopyBuf],
-) -> Result<JsonOp, OpError> {
-  state.check_unstable("Deno.setRaw");
-  let args: SetRawArgs = serde_json::from_value(args)?;
-  let rid = args.rid;
-  let is_raw = args.mode;
+#[cfg(unix)]
+type RawTty = std::os::unix::io::RawFd;
+#[cfg(windows)]
+type RawTty = std::os::windows::io::RawHandle;
 
-  // From https://github.com/kkawakam/rustyline/blob/master/src/tty/windows.rs
-  // and https://github.com/kkawakam/rustyline/blob/master/src/tty/unix.rs
-  // and https://github.com/crossterm-rs/crossterm/blob/e35d4d2c1cc4c919e36d242e014af75f6127ab50/src/terminal/sys/windows.rs
-  // Copyright (c) 2015 Katsu Kawakami & Rustyline authors. MIT license.
-  // Copyright (c) 2019 Timon. MIT license.
-  #[cfg(windows)]
-  {
-    use std::os::windows::io::AsRawHandle;
-    use winapi::shared::minwindef::FALSE;
-    use winapi::um::{consoleapi, handleapi};
+/// A resource which may be a terminal: stdin, stdout, stderr, or a file
+/// opened on a terminal device such as /dev/tty or a PTY. Terminal ops look
+/// it up by rid, so they work the same on all of them, and it keeps the mode
+/// of the terminal to restore after raw mode in the metadata of the resource.
+struct Tty<'a> {
+  raw: RawTty,
+  metadata: &'a mut TTYMetadata,
+}
 
-    let mut resource_table = isolate_state.resource_table.borrow_mut();
-    let resource_holder = resource_table.get_mut::<StreamResourceHolder>(rid);
-    if resource_holder.is_none() {
-      return Err(OpError::bad_resource_id());
-    }
-    let resource_holder = resource_holder.unwrap();
-
-    // For now, only stdin.
-    let handle = match &mut resource_holder.resource {
-      StreamResource::Stdin(_, _) => std::io::stdin().as_raw_handle(),
-      StreamResource::FsFile(ref mut option_file_metadata) => {
-        if let Some((tokio_file, metadata)) = option_file_metadata.take() {
-          match tokio_file.try_into_std() {
-            Ok(std_file) => {
-              let raw_handle = std_file.as_raw_handle();
-              // Turn the std_file handle back into a tokio file, put it back
-              // in the resource table.
-              let tokio_file = tokio::fs::File::from_std(std_file);
-              resource_holder.resource =
-                StreamResource::FsFile(Some((tokio_file, metadata)));
-              // return the result.
-              raw_handle
-            }
-            Err(tokio_file) => {
-              // This function will return an error containing the file if
-              // some operation is in-flight.
-              resource_holder.resource =
-                StreamResource::FsFile(Some((tokio_file, metadata)));
-              return Err(OpError::resource_unavailable());
-            }
-          }
-        } else {
-          return Err(OpError::resource_unavailable());
-        }
+impl<'a> Tty<'a> {
+  fn get(
+    resource_table: &'a mut ResourceTable,
+    rid: u32,
+  ) -> Result<Self, OpError> {
+    let resource_holder = resource_table
+      .get_mut::<StreamResourceHolder>(rid)
+      .ok_or_else(OpError::bad_resource_id)?;
+    match &mut resource_holder.resource {
+      StreamResource::Stdin(_, metadata) => {
+        #[cfg(unix)]
+        let raw = std::io::stdin().as_raw_fd();
+        #[cfg(windows)]
+        let raw = std::io::stdin().as_raw_handle();
+        Ok(Self { raw, metadata })
       }
-      _ => {
-        return Err(OpError::bad_resource_id());
+      StreamResource::FsFile(Some((f, metadata))) => {
+        #[cfg(unix)]
+        let raw = f.as_raw_fd();
+        #[cfg(windows)]
+        let raw = f.as_raw_handle();
+        Ok(Self {
+          raw,
+          metadata: &mut metadata.tty,
+        })
       }
-    };
+      StreamResource::FsFile(None) => Err(OpError::resource_unavailable()),
+      _ => Err(OpError::other("Not supported".to_owned())),
+    }
+  }
+
+  #[cfg(windows)]
+  fn handle(&self) -> Result<RawTty, OpError> {
+    use winapi::um::handleapi;
 
+    let handle = self.raw;
     if handle == handleapi::INVALID_HANDLE_VALUE {
       return Err(OpError::from(std::io::Error::last_os_error()));
     } else if handle.is_null() {
       return Err(OpError::other("null handle".to_owned()));
     }
-    let mut original_mode: DWORD = 0;
-    if unsafe { consoleapi::GetConsoleMode(handle, &mut original_mode) }
-      == FALSE
+    Ok(handle)
+  }
+
+  #[cfg(windows)]
+  fn is_tty(&self) -> Result<bool, OpError> {
+    use winapi::um::consoleapi;
+
+    let handle = self.handle()?;
+    let mut test_mode: DWORD = 0;
+    // If I cannot get mode out of console, it is not a console.
+    Ok(unsafe { consoleapi::GetConsoleMode(handle, &mut test_mode) != 0 })
+  }
+
+  #[cfg(unix)]
+  fn is_tty(&self) -> Result<bool, OpError> {
+    Ok(unsafe { libc::isatty(self.raw as libc::c_int) == 1 })
+  }
+
+  /// Returns the columns and rows of the terminal.
+  #[cfg(windows)]
+  fn size(&self) -> Result<(i16, i16), OpError> {
+    use winapi::shared::minwindef::FALSE;
+
+    let handle = self.handle()?;
+    let mut info: wincon::CONSOLE_SCREEN_BUFFER_INFO =
+      unsafe { std::mem::zeroed() };
+    if unsafe { wincon::GetConsoleScreenBufferInfo(handle, &mut info) } == FALSE
     {
       return Err(OpError::from(std::io::Error::last_os_error()));
     }
-    let new_mode = if is_raw {
-      original_mode & !RAW_MODE_MASK
-    } else {
-      original_mode | RAW_MODE_MASK
-    };
-    if unsafe { consoleapi::SetConsoleMode(handle, new_mode) } == FALSE {
+    let window = info.srWindow;
+    Ok((
+      window.Right - window.Left + 1,
+      window.Bottom - window.Top + 1,
+    ))
+  }
+
+  /// Returns the columns and rows of the terminal.
+  #[cfg(unix)]
+  fn size(&self) -> Result<(u16, u16), OpError> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(self.raw, libc::TIOCGWINSZ, &mut size) } != 0 {
       return Err(OpError::from(std::io::Error::last_os_error()));
     }
-
-    Ok(JsonOp::Sync(json!({})))
+    Ok((size.ws_col, size.ws_row))
   }
-  #[cfg(unix)]
-  {
-    use std::os::unix::io::AsRawFd;
 
-    let mut resource_table = isolate_state.resource_table.borrow_mut();
-    let resource_holder = resource_table.get_mut::<StreamResourceHolder>(rid);
-    if resource_holder.is_none() {
-      return Err(OpError::bad_resource_id());
-    }
+  // From https://github.com/kkawakam/rustyline/blob/master/src/tty/windows.rs
+  // and https://github.com/kkawakam/rustyline/blob/master/src/tty/unix.rs
+  // and https://github.com/crossterm-rs/crossterm/blob/e35d4d2c1cc4c919e36d242e014af75f6127ab50/src/terminal/sys/windows.rs
+  // Copyright (c) 2015 Katsu Kawakami & Rustyline authors. MIT license.
+  // Copyright (c) 2019 Timon. MIT license.
+  #[cfg(windows)]
+  fn set_raw(&mut self, is_raw: bool) -> Result<(), OpError> {
+    use winapi::shared::minwindef::FALSE;
+    use winapi::um::consoleapi;
 
+    let handle = self.handle()?;
+    let maybe_console_mode = &mut self.metadata.mode;
     if is_raw {
-      let (raw_fd, maybe_tty_mode) =
-        match &mut resource_holder.unwrap().resource {
-          StreamResource::Stdin(_, ref mut metadata) => {
-            (std::io::stdin().as_raw_fd(), &mut metadata.mode)
-          }
-          StreamResource::FsFile(Some((f, ref mut metadata))) => {
-            (f.as_raw_fd(), &mut metadata.tty.mode)
-          }
-          StreamResource::FsFile(None) => {
-            return Err(OpError::resource_unavailable())
-          }
-          _ => {
-            return Err(OpError::other("Not supported".to_owned()));
-          }
-        };
+      if maybe_console_mode.is_some() {
+        // Already raw. Skip.
+        return Ok(());
+      }
 
+      let mut original_mode: DWORD = 0;
+      if unsafe { consoleapi::GetConsoleMode(handle, &mut original_mode) }
+        == FALSE
+      {
+        return Err(OpError::from(std::io::Error::last_os_error()));
+      }
+      let new_mode = original_mode & !RAW_MODE_MASK;
+      if unsafe { consoleapi::SetConsoleMode(handle, new_mode) } == FALSE {
+        return Err(OpError::from(std::io::Error::last_os_error()));
+      }
+      // Save original mode.
+      maybe_console_mode.replace(original_mode);
+    } else if let Some(mode) = maybe_console_mode.take() {
+      // Restore saved mode.
+      if unsafe { consoleapi::SetConsoleMode(handle, mode) } == FALSE {
+        return Err(OpError::from(std::io::Error::last_os_error()));
+      }
+    }
+    Ok(())
+  }
+
+  #[cfg(unix)]
+  fn set_raw(&mut self, is_raw: bool) -> Result<(), OpError> {
+    let raw_fd = self.raw;
+    let maybe_tty_mode = &mut self.metadata.mode;
+
+    if is_raw {
       if maybe_tty_mode.is_some() {
         // Already raw. Skip.
-        return Ok(JsonOp::Sync(json!({})));
+        return Ok(());
       }
 
       let original_mode = termios::tcgetattr(raw_fd)?;
@@ -182,34 +201,36 @@ pub fn op_set_raw(
       raw.control_chars[termios::SpecialCharacterIndices::VMIN as usize] = 1;
       raw.control_chars[termios::SpecialCharacterIndices::VTIME as usize] = 0;
       termios::tcsetattr(raw_fd, termios::SetArg::TCSADRAIN, &raw)?;
-      Ok(JsonOp::Sync(json!({})))
-    } else {
+    } else if let Some(mode) = maybe_tty_mode.take() {
       // Try restore saved mode.
-      let (raw_fd, maybe_tty_mode) =
-        match &mut resource_holder.unwrap().resource {
-          StreamResource::Stdin(_, ref mut metadata) => {
-            (std::io::stdin().as_raw_fd(), &mut metadata.mode)
-          }
-          StreamResource::FsFile(Some((f, ref mut metadata))) => {
-            (f.as_raw_fd(), &mut metadata.tty.mode)
-          }
-          StreamResource::FsFile(None) => {
-            return Err(OpError::resource_unavailable());
-          }
-          _ => {
-            return Err(OpError::bad_resource_id());
-          }
-        };
-
-      if let Some(mode) = maybe_tty_mode.take() {
-        termios::tcsetattr(raw_fd, termios::SetArg::TCSADRAIN, &mode)?;
-      }
-
-      Ok(JsonOp::Sync(json!({})))
+      termios::tcsetattr(raw_fd, termios::SetArg::TCSADRAIN, &mode)?;
     }
+    Ok(())
   }
 }
 
+#[derive(Deserialize)]
+struct SetRawArgs {
+  rid: u32,
+  mode: bool,
+}
+
+pub fn op_set_raw(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.setRaw")?;
+  let args: SetRawArgs = serde_json::from_value(args)?;
+  let rid = args.rid;
+  let is_raw = args.mode;
+
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  Tty::get(&mut resource_table, rid)?.set_raw(is_raw)?;
+  Ok(JsonOp::Sync(json!({})))
+}
+
 #[derive(Deserialize)]
 struct IsattyArgs {
   rid: u32,
@@ -225,28 +246,34 @@ pub fn op_isatty(
   let rid = args.rid;
 
   let mut resource_table = isolate_state.resource_table.borrow_mut();
-  let isatty: bool =
-    std_file_resource(&mut resource_table, rid as u32, move |r| match r {
-      Ok(std_file) => {
-        #[cfg(windows)]
-        {
-          use winapi::um::consoleapi;
-
-          let handle = get_windows_handle(&std_file)?;
-          let mut test_mode: DWORD = 0;
-          // If I cannot get mode out of console, it is not a console.
-          Ok(unsafe { consoleapi::GetConsoleMode(handle, &mut test_mode) != 0 })
-        }
-        #[cfg(unix)]
-        {
-          use std::os::unix::io::AsRawFd;
-          let raw_fd = std_file.as_raw_fd();
-          Ok(unsafe { libc::isatty(raw_fd as libc::c_int) == 1 })
-        }
-      }
-      Err(StreamResource::FsFile(_)) => unreachable!(),
-      Err(StreamResource::Stdin(_, _)) => Ok(atty::is(atty::Stream::Stdin)),
-      _ => Ok(false),
-    })?;
+  let isatty = match Tty::get(&mut resource_table, rid) {
+    Ok(tty) => tty.is_tty()?,
+    Err(err) if err.kind == ErrorKind::BadResource => return Err(err),
+    // Other kinds of resources are never terminals.
+    Err(_) => false,
+  };
   Ok(JsonOp::Sync(json!(isatty)))
 }
+
+#[derive(Deserialize)]
+struct ConsoleSizeArgs {
+  rid: u32,
+}
+
+pub fn op_console_size(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
+  args: Value,
+  _zero_copy: &mut [ZeroCopyBuf],
+) -> Result<JsonOp, OpError> {
+  state.check_unstable("Deno.consoleSize")?;
+  let args: ConsoleSizeArgs = serde_json::from_value(args)?;
+  let rid = args.rid;
+
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  let (columns, rows) = Tty::get(&mut resource_table, rid)?.size()?;
+  Ok(JsonOp::Sync(json!({
+    "columns": columns,
+    "rows": rows,
+  })))
+}
@@ -1,10 +1,12 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-use super::dispatch_json::{JsonOp, Value};
+use super::dispatch_json::{Deserialize, JsonOp, Value};
+use super::fds::{clone_resources, close_resources};
 use crate::op_error::OpError;
 use crate::ops::json_op;
 use crate::state::State;
 use crate::web_worker::WebWorkerHandle;
 use crate::worker::WorkerEvent;
+use crate::worker::WorkerMessage;
 use deno_core::CoreIsolate;
 use deno_core::CoreIsolateState;
 use deno_core::ZeroCopyBuf;
@@ -16,20 +18,26 @@ pub fn web_worker_op<D>(
   dispatcher: D,
 ) -> impl Fn(
   &mut CoreIsolateState,
+  &State,
   Value,
   &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError>
 where
   D: Fn(
+    &mut CoreIsolateState,
+    &State,
     &mpsc::Sender<WorkerEvent>,
     Value,
     &mut [ZeroCopyBuf],
   ) -> Result<JsonOp, OpError>,
 {
-  move |_isolate_state: &mut CoreIsolateState,
+  move |isolate_state: &mut CoreIsolateState,
+        state: &State,
         args: Value,
         zero_copy: &mut [ZeroCopyBuf]|
-        -> Result<JsonOp, OpError> { dispatcher(&sender, args, zero_copy) }
+        -> Result<JsonOp, OpError> {
+    dispatcher(isolate_state, state, &sender, args, zero_copy)
+  }
 }
 
 pub fn web_worker_op2<D>(
@@ -65,10 +73,7 @@ pub fn init(
 ) {
   i.register_op(
     "op_worker_post_message",
-    s.core_op(json_op(web_worker_op(
-      sender.clone(),
-      op_worker_post_message,
-    ))),
+    s.stateful_json_op2(web_worker_op(sender.clone(), op_worker_post_message)),
   );
   i.register_op(
     "op_worker_close",
@@ -80,18 +85,38 @@ pub fn init(
   );
 }
 
+#[derive(Deserialize)]
+struct PostMessageArgs {
+  #[serde(default)]
+  resources: Vec<u32>,
+}
+
 /// Post message to host as guest worker
 fn op_worker_post_message(
+  isolate_state: &mut CoreIsolateState,
+  state: &State,
   sender: &mpsc::Sender<WorkerEvent>,
-  _args: Value,
+  args: Value,
   data: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
   assert_eq!(data.len(), 1, "Invalid number of arguments");
+  let args: PostMessageArgs = serde_json::from_value(args)?;
+  let resources = if args.resources.is_empty() {
+    vec![]
+  } else {
+    state.check_unstable("postMessage({ resources })")?;
+    let mut resource_table = isolate_state.resource_table.borrow_mut();
+    clone_resources(&mut resource_table, &args.resources)?
+  };
   let d = Vec::from(&*data[0]).into_boxed_slice();
   let mut sender = sender.clone();
+  // The copies are dropped with the message if it can't be sent, and the
+  // originals are only closed once it is.
   sender
-    .try_send(WorkerEvent::Message(d))
-    .expect("Failed to post message to host");
+    .try_send(WorkerEvent::Message(WorkerMessage { data: d, resources }))
+    .map_err(|e| OpError::other(e.to_string()))?;
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  close_resources(&mut resource_table, &args.resources);
   Ok(JsonOp::Sync(json!({})))
 }
 
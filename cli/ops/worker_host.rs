@@ -1,7 +1,9 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
+use super::fds::{add_resources, clone_resources, close_resources};
 use crate::fmt_errors::JSError;
 use crate::global_state::GlobalState;
+use crate::log_category;
 use crate::op_error::OpError;
 use crate::ops::io::get_stdio;
 use crate::permissions::Permissions;
@@ -12,8 +14,10 @@ use crate::web_worker::WebWorker;
 use crate::web_worker::WebWorkerHandle;
 use crate::worker::WorkerEvent;
 use deno_core::CoreIsolate;
+use deno_core::CoreIsolateState;
 use deno_core::ErrBox;
 use deno_core::ModuleSpecifier;
+use deno_core::ResourceTable;
 use deno_core::ZeroCopyBuf;
 use futures::future::FutureExt;
 use std::convert::From;
@@ -27,11 +31,11 @@ pub fn init(i: &mut CoreIsolate, s: &State) {
   );
   i.register_op(
     "op_host_post_message",
-    s.stateful_json_op(op_host_post_message),
+    s.stateful_json_op2(op_host_post_message),
   );
   i.register_op(
     "op_host_get_message",
-    s.stateful_json_op(op_host_get_message),
+    s.stateful_json_op2(op_host_get_message),
   );
 }
 
@@ -153,7 +157,7 @@ fn run_worker_thread(
     // that means that we should store JoinHandle to thread to ensure
     // that it actually terminates.
     rt.block_on(worker).expect("Panic in event loop");
-    debug!("Worker thread shuts down {}", &name);
+    debug!(target: log_category::OPS, "Worker thread shuts down {}", &name);
   })?;
 
   let worker_handle = handle_receiver.recv().unwrap()?;
@@ -187,7 +191,7 @@ fn op_create_worker(
   let args_name = args.name;
   let use_deno_namespace = args.use_deno_namespace;
   if use_deno_namespace {
-    state.check_unstable("Worker.deno");
+    state.check_unstable("Worker.deno")?;
   }
   let parent_state = state.clone();
   let mut state = state.borrow_mut();
@@ -227,6 +231,10 @@ struct WorkerArgs {
   id: i32,
 }
 
+fn no_worker_found() -> OpError {
+  OpError::bad_resource("No worker handle found".to_string())
+}
+
 fn op_host_terminate_worker(
   state: &State,
   args: Value,
@@ -236,15 +244,21 @@ fn op_host_terminate_worker(
   let id = args.id as u32;
   let mut state = state.borrow_mut();
   let (join_handle, worker_handle) =
-    state.workers.remove(&id).expect("No worker handle found");
+    state.workers.remove(&id).ok_or_else(no_worker_found)?;
   worker_handle.terminate();
   join_handle.join().expect("Panic in worker thread");
   Ok(JsonOp::Sync(json!({})))
 }
 
-fn serialize_worker_event(event: WorkerEvent) -> Value {
-  match event {
-    WorkerEvent::Message(buf) => json!({ "type": "msg", "data": buf }),
+fn serialize_worker_event(
+  event: WorkerEvent,
+  resource_table: &mut ResourceTable,
+) -> Result<Value, OpError> {
+  let serialized_event = match event {
+    WorkerEvent::Message(msg) => {
+      let resources = add_resources(resource_table, msg.resources)?;
+      json!({ "type": "msg", "data": msg.data, "resources": resources })
+    }
     WorkerEvent::TerminalError(error) => {
       let mut serialized_error = json!({
         "type": "terminalError",
@@ -289,11 +303,13 @@ fn serialize_worker_event(event: WorkerEvent) -> Value {
 
       serialized_error
     }
-  }
+  };
+  Ok(serialized_event)
 }
 
 /// Get message from guest worker as host
 fn op_host_get_message(
+  isolate_state: &mut CoreIsolateState,
   state: &State,
   args: Value,
   _data: &mut [ZeroCopyBuf],
@@ -303,10 +319,11 @@ fn op_host_get_message(
   let worker_handle = {
     let state_ = state.borrow();
     let (_join_handle, worker_handle) =
-      state_.workers.get(&id).expect("No worker handle found");
+      state_.workers.get(&id).ok_or_else(no_worker_found)?;
     worker_handle.clone()
   };
   let state_ = state.clone();
+  let resource_table = isolate_state.resource_table.clone();
   let op = async move {
     let response = match worker_handle.get_event().await? {
       Some(event) => {
@@ -320,7 +337,7 @@ fn op_host_get_message(
             join_handle.join().expect("Worker thread panicked");
           }
         }
-        serialize_worker_event(event)
+        serialize_worker_event(event, &mut resource_table.borrow_mut())?
       }
       None => {
         // Worker shuts down
@@ -341,23 +358,43 @@ fn op_host_get_message(
   Ok(JsonOp::Async(op.boxed_local()))
 }
 
+#[derive(Deserialize)]
+struct HostPostMessageArgs {
+  id: i32,
+  #[serde(default)]
+  resources: Vec<u32>,
+}
+
 /// Post message to guest worker as host
 fn op_host_post_message(
+  isolate_state: &mut CoreIsolateState,
   state: &State,
   args: Value,
   data: &mut [ZeroCopyBuf],
 ) -> Result<JsonOp, OpError> {
   assert_eq!(data.len(), 1, "Invalid number of arguments");
-  let args: WorkerArgs = serde_json::from_value(args)?;
+  let args: HostPostMessageArgs = serde_json::from_value(args)?;
   let id = args.id as u32;
   let msg = Vec::from(&*data[0]).into_boxed_slice();
 
-  debug!("post message to worker {}", id);
+  let resources = if args.resources.is_empty() {
+    vec![]
+  } else {
+    state.check_unstable("postMessage({ resources })")?;
+    let mut resource_table = isolate_state.resource_table.borrow_mut();
+    clone_resources(&mut resource_table, &args.resources)?
+  };
+
+  debug!(target: log_category::OPS, "post message to worker {}", id);
   let state = state.borrow();
   let (_, worker_handle) =
-    state.workers.get(&id).expect("No worker handle found");
+    state.workers.get(&id).ok_or_else(no_worker_found)?;
+  // The copies are dropped with the message if it can't be sent, and the
+  // originals are only closed once it is.
   worker_handle
-    .post_message(msg)
+    .post_message_with_resources(msg, resources)
     .map_err(|e| OpError::other(e.to_string()))?;
+  let mut resource_table = isolate_state.resource_table.borrow_mut();
+  close_resources(&mut resource_table, &args.resources);
   Ok(JsonOp::Sync(json!({})))
 }
@@ -15,6 +15,7 @@ use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 #[cfg(test)]
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 #[cfg(test)]
 use std::sync::Mutex;
 use url::Url;
@@ -129,6 +130,52 @@ where
   d.deserialize_bool(BoolPermVisitor)
 }
 
+/// What an op asks to access. The paths are absolute.
+#[derive(Debug, PartialEq)]
+pub enum Permission<'a> {
+  /// Reading a path, or any path for queries without one, like
+  /// `Deno.permissions.query({ name: "read" })`.
+  Read(Option<&'a Path>),
+  /// Writing a path, or any path.
+  Write(Option<&'a Path>),
+  /// Connecting to a host, and a port if it's known, or to any host.
+  Net(Option<(&'a str, Option<u16>)>),
+  Env,
+  Run,
+  /// Opening the plugin at a path, or any plugin.
+  Plugin(Option<&'a Path>),
+  Hrtime,
+}
+
+/// Decides what the ops of an embedder's runtime may access, instead of the
+/// `--allow-*` flags: denying everything, reporting each access to a
+/// callback, allowing the modules signed with some key, etc.
+///
+/// Policies are shared by the workers, which run on their own threads. With a
+/// policy, `Deno.permissions.query()` and `request()` report what it allows,
+/// and `revoke()` has no effect. Paths Deno itself allowed reading with
+/// `Permissions::allow_read_path()` are never checked by the policy.
+pub trait PermissionPolicy: Send + Sync {
+  /// Returns `OpError::permission_denied()` if `permission` isn't granted.
+  fn check(&self, permission: &Permission) -> Result<(), OpError>;
+}
+
+/// The policy of `Permissions`, compared by identity.
+#[derive(Clone)]
+struct Policy(Arc<dyn PermissionPolicy>);
+
+impl fmt::Debug for Policy {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("Policy")
+  }
+}
+
+impl PartialEq for Policy {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.0, &other.0)
+  }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub struct Permissions {
   // Keep in sync with cli/js/permissions.ts
@@ -149,6 +196,9 @@ pub struct Permissions {
   pub allow_plugin: PermissionState,
   #[serde(deserialize_with = "deserialize_permission_state")]
   pub allow_hrtime: PermissionState,
+  /// Checks the permissions instead of the flags, if set.
+  #[serde(skip)]
+  policy: Option<Policy>,
 }
 
 fn resolve_fs_whitelist(whitelist: &[PathBuf]) -> HashSet<PathBuf> {
@@ -171,6 +221,40 @@ impl Permissions {
       allow_run: PermissionState::from(flags.allow_run),
       allow_plugin: PermissionState::from(flags.allow_plugin),
       allow_hrtime: PermissionState::from(flags.allow_hrtime),
+      policy: None,
+    }
+  }
+
+  /// Permissions which are all checked by `policy`.
+  pub fn with_policy(policy: Arc<dyn PermissionPolicy>) -> Self {
+    Self {
+      policy: Some(Policy(policy)),
+      ..Default::default()
+    }
+  }
+
+  /// Checks `permission` with the policy if there's one, and with
+  /// `check_flags` otherwise.
+  fn check(
+    &self,
+    permission: Permission,
+    check_flags: impl FnOnce() -> Result<(), OpError>,
+  ) -> Result<(), OpError> {
+    match &self.policy {
+      Some(_) if self.is_whitelisted(&permission) => Ok(()),
+      Some(policy) => policy.0.check(&permission),
+      None => check_flags(),
+    }
+  }
+
+  /// Whether `permission` is reading a path given to `allow_read_path()`.
+  /// With a policy, the whitelist holds nothing else.
+  fn is_whitelisted(&self, permission: &Permission) -> bool {
+    match permission {
+      Permission::Read(Some(path)) => {
+        check_path_white_list(path, &self.read_whitelist)
+      }
+      _ => false,
     }
   }
 
@@ -205,10 +289,18 @@ impl Permissions {
     }
   }
 
+  /// Allows reading `path` and the files under it, as `--allow-read=<path>`
+  /// does. This holds even if the permissions have a policy.
+  pub fn allow_read_path(&mut self, path: &Path) {
+    self.read_whitelist.insert(resolve_from_cwd(path).unwrap());
+  }
+
   pub fn check_run(&self) -> Result<(), OpError> {
-    self
-      .allow_run
-      .check("access to run a subprocess", "--allow-run")
+    self.check(Permission::Run, || {
+      self
+        .allow_run
+        .check("access to run a subprocess", "--allow-run")
+    })
   }
 
   fn get_state_read(&self, path: &Option<&Path>) -> PermissionState {
@@ -219,11 +311,14 @@ impl Permissions {
   }
 
   pub fn check_read(&self, path: &Path) -> Result<(), OpError> {
-    let (resolved_path, display_path) = self.resolved_and_display_path(path);
-    self.get_state_read(&Some(&resolved_path)).check(
-      &format!("read access to \"{}\"", display_path.display()),
-      "--allow-read",
-    )
+    let resolved_path = resolve_from_cwd(path).unwrap();
+    self.check(Permission::Read(Some(&resolved_path)), || {
+      let (resolved_path, display_path) = self.resolved_and_display_path(path);
+      self.get_state_read(&Some(&resolved_path)).check(
+        &format!("read access to \"{}\"", display_path.display()),
+        "--allow-read",
+      )
+    })
   }
 
   /// As `check_read()`, but permission error messages will anonymize the path
@@ -234,9 +329,16 @@ impl Permissions {
     display: &str,
   ) -> Result<(), OpError> {
     let resolved_path = resolve_from_cwd(path).unwrap();
+    let message = format!("read access to <{}>", display);
+    if self.policy.is_some() {
+      // The error of the policy may show the path.
+      return self
+        .check(Permission::Read(Some(&resolved_path)), || Ok(()))
+        .map_err(|_| OpError::permission_denied(message));
+    }
     self
       .get_state_read(&Some(&resolved_path))
-      .check(&format!("read access to <{}>", display), "--allow-read")
+      .check(&message, "--allow-read")
   }
 
   fn get_state_write(&self, path: &Option<&Path>) -> PermissionState {
@@ -247,11 +349,14 @@ impl Permissions {
   }
 
   pub fn check_write(&self, path: &Path) -> Result<(), OpError> {
-    let (resolved_path, display_path) = self.resolved_and_display_path(path);
-    self.get_state_write(&Some(&resolved_path)).check(
-      &format!("write access to \"{}\"", display_path.display()),
-      "--allow-write",
-    )
+    let resolved_path = resolve_from_cwd(path).unwrap();
+    self.check(Permission::Write(Some(&resolved_path)), || {
+      let (resolved_path, display_path) = self.resolved_and_display_path(path);
+      self.get_state_write(&Some(&resolved_path)).check(
+        &format!("write access to \"{}\"", display_path.display()),
+        "--allow-write",
+      )
+    })
   }
 
   fn get_state_net(&self, host: &str, port: Option<u16>) -> PermissionState {
@@ -277,42 +382,64 @@ impl Permissions {
   }
 
   pub fn check_net(&self, hostname: &str, port: u16) -> Result<(), OpError> {
-    self.get_state_net(hostname, Some(port)).check(
-      &format!("network access to \"{}:{}\"", hostname, port),
-      "--allow-net",
-    )
+    self.check(Permission::Net(Some((hostname, Some(port)))), || {
+      self.get_state_net(hostname, Some(port)).check(
+        &format!("network access to \"{}:{}\"", hostname, port),
+        "--allow-net",
+      )
+    })
   }
 
   pub fn check_net_url(&self, url: &url::Url) -> Result<(), OpError> {
     let host = url
       .host_str()
       .ok_or_else(|| OpError::uri_error("missing host".to_owned()))?;
-    self
-      .get_state_net(host, url.port())
-      .check(&format!("network access to \"{}\"", url), "--allow-net")
+    self.check(Permission::Net(Some((host, url.port()))), || {
+      self
+        .get_state_net(host, url.port())
+        .check(&format!("network access to \"{}\"", url), "--allow-net")
+    })
   }
 
   pub fn check_env(&self) -> Result<(), OpError> {
-    self
-      .allow_env
-      .check("access to environment variables", "--allow-env")
+    self.check(Permission::Env, || {
+      self
+        .allow_env
+        .check("access to environment variables", "--allow-env")
+    })
   }
 
   pub fn check_plugin(&self, path: &Path) -> Result<(), OpError> {
-    let (_, display_path) = self.resolved_and_display_path(path);
-    self.allow_plugin.check(
-      &format!("access to open a plugin: {}", display_path.display()),
-      "--allow-plugin",
-    )
+    let (resolved_path, display_path) = self.resolved_and_display_path(path);
+    self.check(Permission::Plugin(Some(&resolved_path)), || {
+      self.allow_plugin.check(
+        &format!("access to open a plugin: {}", display_path.display()),
+        "--allow-plugin",
+      )
+    })
+  }
+
+  pub fn check_hrtime(&self) -> Result<(), OpError> {
+    self.check(Permission::Hrtime, || {
+      self
+        .allow_hrtime
+        .check("access to high precision time", "--allow-hrtime")
+    })
   }
 
   pub fn request_run(&mut self) -> PermissionState {
+    if let Some(state) = self.policy_state("run", &None, &None) {
+      return state.unwrap_or(PermissionState::Deny);
+    }
     self
       .allow_run
       .request("Deno requests to access to run a subprocess")
   }
 
   pub fn request_read(&mut self, path: &Option<&Path>) -> PermissionState {
+    if let Some(state) = self.policy_state("read", &None, path) {
+      return state.unwrap_or(PermissionState::Deny);
+    }
     let paths = path.map(|p| self.resolved_and_display_path(p));
     if let Some((p, _)) = paths.as_ref() {
       if check_path_white_list(&p, &self.read_whitelist) {
@@ -329,6 +456,9 @@ impl Permissions {
   }
 
   pub fn request_write(&mut self, path: &Option<&Path>) -> PermissionState {
+    if let Some(state) = self.policy_state("write", &None, path) {
+      return state.unwrap_or(PermissionState::Deny);
+    }
     let paths = path.map(|p| self.resolved_and_display_path(p));
     if let Some((p, _)) = paths.as_ref() {
       if check_path_white_list(&p, &self.write_whitelist) {
@@ -348,6 +478,9 @@ impl Permissions {
     &mut self,
     url: &Option<&str>,
   ) -> Result<PermissionState, OpError> {
+    if let Some(state) = self.policy_state("net", url, &None) {
+      return state;
+    }
     if self.get_state_net_url(url)? == PermissionState::Ask {
       return Ok(self.allow_net.request(&match url {
         None => "Deno requests network access".to_string(),
@@ -358,18 +491,27 @@ impl Permissions {
   }
 
   pub fn request_env(&mut self) -> PermissionState {
+    if let Some(state) = self.policy_state("env", &None, &None) {
+      return state.unwrap_or(PermissionState::Deny);
+    }
     self
       .allow_env
       .request("Deno requests to access to environment variables")
   }
 
   pub fn request_hrtime(&mut self) -> PermissionState {
+    if let Some(state) = self.policy_state("hrtime", &None, &None) {
+      return state.unwrap_or(PermissionState::Deny);
+    }
     self
       .allow_hrtime
       .request("Deno requests to access to high precision time")
   }
 
   pub fn request_plugin(&mut self) -> PermissionState {
+    if let Some(state) = self.policy_state("plugin", &None, &None) {
+      return state.unwrap_or(PermissionState::Deny);
+    }
     self.allow_plugin.request("Deno requests to open plugins")
   }
 
@@ -379,6 +521,9 @@ impl Permissions {
     url: &Option<&str>,
     path: &Option<&Path>,
   ) -> Result<PermissionState, OpError> {
+    if let Some(state) = self.policy_state(name, url, path) {
+      return state;
+    }
     let path = path.map(|p| resolve_from_cwd(p).unwrap());
     let path = path.as_deref();
     match name {
@@ -392,6 +537,46 @@ impl Permissions {
       n => Err(OpError::other(format!("No such permission name: {}", n))),
     }
   }
+
+  /// What the policy allows, if there's one.
+  fn policy_state(
+    &self,
+    name: &str,
+    url: &Option<&str>,
+    path: &Option<&Path>,
+  ) -> Option<Result<PermissionState, OpError>> {
+    let policy = &self.policy.as_ref()?.0;
+    let path = path.map(|p| resolve_from_cwd(p).unwrap());
+    let path = path.as_deref();
+    let url = match url.map(Url::parse).transpose() {
+      Ok(url) => url,
+      Err(err) => return Some(Err(OpError::from(err))),
+    };
+    let host = url.as_ref().map(|url| {
+      let host = url.host_str().unwrap_or_default();
+      (host, url.port())
+    });
+    let permission = match name {
+      "run" => Permission::Run,
+      "read" => Permission::Read(path),
+      "write" => Permission::Write(path),
+      "net" => Permission::Net(host),
+      "env" => Permission::Env,
+      "plugin" => Permission::Plugin(None),
+      "hrtime" => Permission::Hrtime,
+      n => {
+        let message = format!("No such permission name: {}", n);
+        return Some(Err(OpError::other(message)));
+      }
+    };
+    if self.is_whitelisted(&permission) {
+      return Some(Ok(PermissionState::Allow));
+    }
+    Some(Ok(match policy.check(&permission) {
+      Ok(()) => PermissionState::Allow,
+      Err(_) => PermissionState::Deny,
+    }))
+  }
 }
 
 /// Shows the permission prompt and returns the answer according to the user input.
@@ -549,6 +734,15 @@ mod tests {
     assert!(perms.check_write(Path::new("/a/b")).is_err());
   }
 
+  #[test]
+  fn allow_read_path() {
+    let mut perms = Permissions::default();
+    perms.allow_read_path(Path::new("/a/cache"));
+    assert!(perms.check_read(Path::new("/a/cache/b")).is_ok());
+    assert!(perms.check_read(Path::new("/a/cache/../b")).is_err());
+    assert!(perms.check_write(Path::new("/a/cache/b")).is_err());
+  }
+
   #[test]
   fn test_check_net() {
     let perms = Permissions::from_flags(&Flags {
@@ -855,9 +1049,93 @@ mod tests {
       read_whitelist: HashSet::new(),
       write_whitelist: HashSet::new(),
       net_whitelist: HashSet::new(),
+      policy: None,
     };
     let deserialized_perms: Permissions =
       serde_json::from_str(json_perms).unwrap();
     assert_eq!(perms0, deserialized_perms);
   }
+
+  /// Allows reading `/allowed`, and records what's checked.
+  struct TestPolicy {
+    allowed: PathBuf,
+    checked: Mutex<Vec<String>>,
+  }
+
+  impl Default for TestPolicy {
+    fn default() -> Self {
+      Self {
+        allowed: resolve_from_cwd(Path::new("/allowed")).unwrap(),
+        checked: Mutex::new(vec![]),
+      }
+    }
+  }
+
+  impl PermissionPolicy for TestPolicy {
+    fn check(&self, permission: &Permission) -> Result<(), OpError> {
+      self
+        .checked
+        .lock()
+        .unwrap()
+        .push(format!("{:?}", permission));
+      match permission {
+        Permission::Read(Some(path)) if path.starts_with(&self.allowed) => {
+          Ok(())
+        }
+        _ => Err(OpError::permission_denied("denied".to_string())),
+      }
+    }
+  }
+
+  #[test]
+  fn check_with_policy() {
+    let policy = Arc::new(TestPolicy::default());
+    let mut perms = Permissions::with_policy(policy.clone());
+    assert!(perms.check_read(Path::new("/allowed/a.txt")).is_ok());
+    assert!(perms.check_read(Path::new("/a.txt")).is_err());
+    let err = perms
+      .check_read_blind(Path::new("/a.txt"), "exec_path")
+      .unwrap_err();
+    assert_eq!(err.msg, "read access to <exec_path>");
+    assert!(perms.check_write(Path::new("/allowed/a.txt")).is_err());
+    let url = Url::parse("https://deno.land:8000/x.ts").unwrap();
+    assert!(perms.check_net_url(&url).is_err());
+    assert!(perms.check_env().is_err());
+    // Paths are resolved before they're checked, which puts them on the
+    // current drive on Windows.
+    let allowed_file = resolve_from_cwd(Path::new("/allowed/a.txt")).unwrap();
+    let other_file = resolve_from_cwd(Path::new("/a.txt")).unwrap();
+    assert_eq!(
+      *policy.checked.lock().unwrap(),
+      vec![
+        format!("{:?}", Permission::Read(Some(&allowed_file))),
+        format!("{:?}", Permission::Read(Some(&other_file))),
+        format!("{:?}", Permission::Read(Some(&other_file))),
+        format!("{:?}", Permission::Write(Some(&allowed_file))),
+        "Net(Some((\"deno.land\", Some(8000))))".to_string(),
+        "Env".to_string(),
+      ]
+    );
+
+    // The flags aren't consulted, even once they're granted.
+    perms.allow_env = PermissionState::Allow;
+    assert!(perms.check_env().is_err());
+    assert_eq!(perms.request_env(), PermissionState::Deny);
+    let state =
+      perms.get_permission_state("read", &None, &Some(Path::new("/allowed")));
+    assert_eq!(state.unwrap(), PermissionState::Allow);
+    let state =
+      perms.get_permission_state("net", &Some("https://deno.land"), &None);
+    assert_eq!(state.unwrap(), PermissionState::Deny);
+
+    // Paths allowed by Deno itself aren't up to the policy.
+    perms.allow_read_path(Path::new("/cache"));
+    policy.checked.lock().unwrap().clear();
+    assert!(perms.check_read(Path::new("/cache/a.txt")).is_ok());
+    let state =
+      perms.get_permission_state("read", &None, &Some(Path::new("/cache")));
+    assert_eq!(state.unwrap(), PermissionState::Allow);
+    assert!(perms.check_read(Path::new("/cache/../a.txt")).is_err());
+    assert_eq!(policy.checked.lock().unwrap().len(), 1);
+  }
 }
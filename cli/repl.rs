@@ -1,20 +1,321 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use crate::colors;
 use crate::deno_dir::DenoDir;
 use crate::op_error::OpError;
+use crate::tokio_util;
 use deno_core::ErrBox;
+use deno_core::InterruptHandle;
+use futures::channel::mpsc::UnboundedSender;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::Config;
+use rustyline::Context;
 use rustyline::Editor;
+use std::borrow::Cow;
+use std::env;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Maximum number of entries kept in the history; older ones are dropped.
+const HISTORY_SIZE: usize = 1000;
+
+/// How long a tab completion waits for the isolate before giving up.
+const COMPLETION_TIMEOUT: Duration = Duration::from_millis(500);
+
+const KEYWORDS: &[&str] = &[
+  "async",
+  "await",
+  "break",
+  "case",
+  "catch",
+  "class",
+  "const",
+  "continue",
+  "debugger",
+  "default",
+  "delete",
+  "do",
+  "else",
+  "export",
+  "extends",
+  "false",
+  "finally",
+  "for",
+  "function",
+  "if",
+  "import",
+  "in",
+  "instanceof",
+  "let",
+  "new",
+  "null",
+  "of",
+  "return",
+  "static",
+  "super",
+  "switch",
+  "this",
+  "throw",
+  "true",
+  "try",
+  "typeof",
+  "undefined",
+  "var",
+  "void",
+  "while",
+  "with",
+  "yield",
+];
+
+/// Makes Ctrl-C terminate the input being evaluated on the isolate in
+/// `target`, rather than the whole process. The REPL loop survives, since
+/// `evalContext` reports the termination like an exception.
+///
+/// While a line is read the terminal is in raw mode, and the line editor
+/// handles Ctrl-C itself. A Ctrl-C while nothing is evaluated is ignored.
+pub fn interrupt_on_ctrl_c(target: Arc<Mutex<Option<InterruptHandle>>>) {
+  std::thread::spawn(move || {
+    let mut rt = tokio_util::create_basic_runtime();
+    rt.block_on(async move {
+      while tokio::signal::ctrl_c().await.is_ok() {
+        if let Some(interrupt_handle) = &*target.lock().unwrap() {
+          interrupt_handle.interrupt();
+        }
+      }
+    });
+  });
+}
+
+/// What the line editor, running on its own thread, reports to the isolate.
+pub enum ReplEvent {
+  /// The line that was read, or why none could be read.
+  Line(Result<String, OpError>),
+  /// Tab was pressed after the given expression, like `Deno.re`.
+  Complete(String),
+}
+
+/// Completes property names in the isolate.
+///
+/// The isolate is waiting for the line being read, so the expression before
+/// the cursor is sent to it as a `ReplEvent`, and it answers with the names
+/// of the matching properties.
+struct Helper {
+  events: UnboundedSender<ReplEvent>,
+  completions: Receiver<Vec<String>>,
+}
+
+impl Completer for Helper {
+  type Candidate = String;
+
+  fn complete(
+    &self,
+    line: &str,
+    pos: usize,
+    _ctx: &Context<'_>,
+  ) -> rustyline::Result<(usize, Vec<String>)> {
+    let start = match completion_start(line, pos) {
+      Some(start) => start,
+      None => return Ok((pos, vec![])),
+    };
+    let expr = &line[start..pos];
+    // Drop the answers to requests that timed out, so that they aren't taken
+    // for the answer to this one.
+    while self.completions.try_recv().is_ok() {}
+    let request = ReplEvent::Complete(expr.to_string());
+    if self.events.unbounded_send(request).is_err() {
+      return Ok((pos, vec![]));
+    }
+    // The isolate may be busy, e.g. with a timer that never yields, and the
+    // prompt mustn't hang on it.
+    let names = self
+      .completions
+      .recv_timeout(COMPLETION_TIMEOUT)
+      .unwrap_or_default();
+    let word_start = start + expr.rfind('.').map_or(0, |i| i + 1);
+    Ok((word_start, names))
+  }
+}
+
+impl Hinter for Helper {}
+
+impl Highlighter for Helper {
+  fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+    if !colors::use_color() {
+      return Cow::Borrowed(line);
+    }
+    Cow::Owned(highlight(line, matching_brackets(line, pos)))
+  }
+
+  fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+    // Redraw on every cursor move, so that the bracket pair follows it.
+    true
+  }
+}
+
+/// Colors keywords, strings, numbers and comments the way `console.log`
+/// colors values, and makes the brackets at `brackets` bold.
+fn highlight(line: &str, brackets: Option<(usize, usize)>) -> String {
+  let mut out = String::with_capacity(line.len());
+  let mut chars = line.char_indices().peekable();
+  while let Some((start, c)) = chars.next() {
+    // Consumes the token up to the first character not accepted by `f`.
+    let mut take_while = |f: &dyn Fn(char) -> bool| {
+      let mut end = start + c.len_utf8();
+      while let Some(&(i, c)) = chars.peek() {
+        if !f(c) {
+          break;
+        }
+        end = i + c.len_utf8();
+        chars.next();
+      }
+      end
+    };
+    match c {
+      _ if c.is_alphabetic() || c == '_' || c == '$' => {
+        let end = take_while(&|c| c.is_alphanumeric() || c == '_' || c == '$');
+        let word = &line[start..end];
+        if KEYWORDS.contains(&word) {
+          out.push_str(&colors::cyan(word.to_string()).to_string());
+        } else {
+          out.push_str(word);
+        }
+      }
+      _ if c.is_ascii_digit() => {
+        let end = take_while(&|c| c.is_ascii_alphanumeric() || c == '.');
+        out.push_str(&colors::yellow(line[start..end].to_string()).to_string());
+      }
+      '\'' | '"' | '`' => {
+        let mut end = line.len();
+        let mut escaped = false;
+        for (i, ch) in chars.by_ref() {
+          if escaped {
+            escaped = false;
+          } else if ch == '\\' {
+            escaped = true;
+          } else if ch == c {
+            end = i + 1;
+            break;
+          }
+        }
+        out.push_str(&colors::green(line[start..end].to_string()).to_string());
+      }
+      '/' if line[start..].starts_with("//") => {
+        out.push_str(&colors::gray(line[start..].to_string()).to_string());
+        break;
+      }
+      '/' if line[start..].starts_with("/*") => {
+        let end = line[start + 2..]
+          .find("*/")
+          .map_or(line.len(), |i| start + 2 + i + 2);
+        while chars.peek().map_or(false, |&(i, _)| i < end) {
+          chars.next();
+        }
+        out.push_str(&colors::gray(line[start..end].to_string()).to_string());
+      }
+      _ if brackets.map_or(false, |(a, b)| start == a || start == b) => {
+        out.push_str(&colors::bold(c.to_string()).to_string());
+      }
+      _ => out.push(c),
+    }
+  }
+  out
+}
+
+/// Returns the positions of the bracket at or just before the cursor and of
+/// the bracket matching it.
+fn matching_brackets(line: &str, pos: usize) -> Option<(usize, usize)> {
+  let bracket_at = |i: usize| {
+    line[i..]
+      .chars()
+      .next()
+      .filter(|c| "()[]{}".contains(*c))
+      .map(|c| (i, c))
+  };
+  let (at, bracket) = bracket_at(pos).or_else(|| {
+    line[..pos]
+      .chars()
+      .next_back()
+      .and_then(|c| bracket_at(pos - c.len_utf8()))
+  })?;
+  let (open, close, forward) = match bracket {
+    '(' => ('(', ')', true),
+    '[' => ('[', ']', true),
+    '{' => ('{', '}', true),
+    ')' => ('(', ')', false),
+    ']' => ('[', ']', false),
+    _ => ('{', '}', false),
+  };
+  let mut depth = 0;
+  let candidates: Box<dyn Iterator<Item = (usize, char)>> = if forward {
+    Box::new(line[at..].char_indices().map(|(i, c)| (at + i, c)))
+  } else {
+    Box::new(line[..=at].char_indices().rev())
+  };
+  for (i, c) in candidates {
+    if c == open {
+      depth += if forward { 1 } else { -1 };
+    } else if c == close {
+      depth += if forward { -1 } else { 1 };
+    }
+    if depth == 0 {
+      return Some((at, i));
+    }
+  }
+  None
+}
+
+impl Validator for Helper {}
+
+impl rustyline::Helper for Helper {}
+
+/// Returns where the member expression ending at `pos` starts, if there's one
+/// that can be completed.
+fn completion_start(line: &str, pos: usize) -> Option<usize> {
+  let before = &line[..pos];
+  let start = before
+    .char_indices()
+    .rev()
+    .find(|&(_, c)| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '.'))
+    .map_or(0, |(i, c)| i + c.len_utf8());
+  let expr = &before[start..];
+  if expr.starts_with(|c: char| c == '.' || c.is_ascii_digit())
+    || expr.contains("..")
+  {
+    return None;
+  }
+  Some(start)
+}
 
 pub struct Repl {
-  editor: Editor<()>,
-  history_file: PathBuf,
+  editor: Editor<Helper>,
+  history_file: Option<PathBuf>,
 }
 
 impl Repl {
-  pub fn new(history_file: PathBuf) -> Self {
+  /// Tab completions are requested through `events`, and expected back on
+  /// `completions`.
+  pub fn new(
+    history_file: Option<PathBuf>,
+    events: UnboundedSender<ReplEvent>,
+    completions: Receiver<Vec<String>>,
+  ) -> Self {
+    let config = Config::builder()
+      .max_history_size(HISTORY_SIZE)
+      .history_ignore_dups(true)
+      .build();
+    let mut editor = Editor::with_config(config);
+    editor.set_helper(Some(Helper {
+      events,
+      completions,
+    }));
     let mut repl = Self {
-      editor: Editor::<()>::new(),
+      editor,
       history_file,
     };
 
@@ -23,27 +324,32 @@ impl Repl {
   }
 
   fn load_history(&mut self) {
-    debug!("Loading REPL history: {:?}", self.history_file);
+    let history_file = match &self.history_file {
+      Some(history_file) => history_file,
+      None => return,
+    };
+    debug!("Loading REPL history: {:?}", history_file);
     self
       .editor
-      .load_history(&self.history_file.to_str().unwrap())
+      .load_history(history_file)
       .map_err(|e| {
-        debug!("Unable to load history file: {:?} {}", self.history_file, e)
+        debug!("Unable to load history file: {:?} {}", history_file, e)
       })
       // ignore this error (e.g. it occurs on first load)
       .unwrap_or(())
   }
 
   fn save_history(&mut self) -> Result<(), ErrBox> {
-    fs::create_dir_all(self.history_file.parent().unwrap())?;
-    self
-      .editor
-      .save_history(&self.history_file.to_str().unwrap())
-      .map(|_| debug!("Saved REPL history to: {:?}", self.history_file))
-      .map_err(|e| {
-        eprintln!("Unable to save REPL history: {:?} {}", self.history_file, e);
-        ErrBox::from(e)
-      })
+    let history_file = match &self.history_file {
+      Some(history_file) => history_file,
+      None => return Ok(()),
+    };
+    if let Some(parent) = history_file.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    self.editor.save_history(history_file)?;
+    debug!("Saved REPL history to: {:?}", history_file);
+    Ok(())
   }
 
   pub fn readline(&mut self, prompt: &str) -> Result<String, OpError> {
@@ -61,12 +367,207 @@ impl Repl {
 
 impl Drop for Repl {
   fn drop(&mut self) {
-    self.save_history().unwrap();
+    // Losing the history isn't worth a panic while the REPL shuts down.
+    if let Err(err) = self.save_history() {
+      // Saving can only fail if there's a file to save to.
+      let history_file = self.history_file.as_ref().unwrap();
+      warn!("Unable to save REPL history: {:?} {}", history_file, err);
+    }
+  }
+}
+
+/// Returns the file the REPL history is kept in, `history_file` in the
+/// `DENO_DIR` by default.
+///
+/// `DENO_REPL_HISTORY` overrides the location; when it's set to an empty
+/// string, the history isn't persisted at all.
+pub fn history_path(dir: &DenoDir, history_file: &str) -> Option<PathBuf> {
+  match env::var_os("DENO_REPL_HISTORY") {
+    Some(path) if path.is_empty() => None,
+    Some(path) => Some(PathBuf::from(path)),
+    None => Some(dir.root.join(history_file)),
+  }
+}
+
+/// Tells whether `code` looks like the beginning of a longer input, so that
+/// the REPL should read another line instead of reporting a syntax error.
+///
+/// This is a lexical check only: it looks for unclosed brackets, template
+/// literals and block comments, for a string continued with a backslash, and
+/// for a trailing operator.
+pub fn is_incomplete(code: &str) -> bool {
+  let chars: Vec<char> = code.chars().collect();
+  // Open brackets, with '`' for a template literal and '$' for a `${` in it.
+  let mut stack: Vec<char> = vec![];
+  // The last two characters outside of whitespace and comments.
+  let mut last: Option<char> = None;
+  let mut prev: Option<char> = None;
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    let next = chars.get(i + 1).copied();
+
+    if stack.last() == Some(&'`') {
+      match c {
+        '\\' => i += 1,
+        '`' => {
+          stack.pop();
+          prev = last;
+          last = Some('`');
+        }
+        '$' if next == Some('{') => {
+          stack.push('$');
+          i += 1;
+        }
+        _ => {}
+      }
+      i += 1;
+      continue;
+    }
+
+    match c {
+      '\'' | '"' => {
+        i += 1;
+        loop {
+          match chars.get(i) {
+            Some('\\') if i + 1 == chars.len() => return true,
+            Some('\\') => i += 2,
+            Some(&q) if q == c => break,
+            Some('\n') | None => return false,
+            Some(_) => i += 1,
+          }
+        }
+      }
+      '/' if next == Some('/') => {
+        while i < chars.len() && chars[i] != '\n' {
+          i += 1;
+        }
+        continue;
+      }
+      '/' if next == Some('*') => {
+        i += 2;
+        loop {
+          match chars.get(i) {
+            Some('*') if chars.get(i + 1) == Some(&'/') => break,
+            Some(_) => i += 1,
+            None => return true,
+          }
+        }
+        i += 2;
+        continue;
+      }
+      '/' if last.map_or(true, |l| "(,=:[!&|?{};+-*%<>~^".contains(l)) => {
+        // A regular expression literal.
+        let mut in_class = false;
+        i += 1;
+        loop {
+          match chars.get(i) {
+            Some('\\') => i += 1,
+            Some('[') => in_class = true,
+            Some(']') => in_class = false,
+            Some('/') if !in_class => break,
+            Some('\n') | None => return false,
+            Some(_) => {}
+          }
+          i += 1;
+        }
+      }
+      '(' | '[' | '{' | '`' => stack.push(c),
+      ')' | ']' | '}' => {
+        let open = match c {
+          ')' => '(',
+          ']' => '[',
+          _ => '{',
+        };
+        match stack.pop() {
+          Some(o) if o == open || (o == '$' && c == '}') => {}
+          _ => return false,
+        }
+      }
+      _ => {}
+    }
+
+    if !c.is_whitespace() {
+      prev = last;
+      last = Some(c);
+    }
+    i += 1;
+  }
+
+  if !stack.is_empty() {
+    return true;
+  }
+  match (prev, last) {
+    // Postfix operators.
+    (Some('+'), Some('+')) | (Some('-'), Some('-')) => false,
+    // A number like `1.`.
+    (Some(p), Some('.')) if p.is_ascii_digit() => false,
+    (_, Some(l)) => "+-*%=&|^<>!?:,.~".contains(l),
+    (_, None) => false,
   }
 }
 
-pub fn history_path(dir: &DenoDir, history_file: &str) -> PathBuf {
-  let mut p: PathBuf = dir.root.clone();
-  p.push(history_file);
-  p
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bracket_matching() {
+    assert_eq!(matching_brackets("f(a[0])", 1), Some((1, 6)));
+    assert_eq!(matching_brackets("f(a[0])", 7), Some((6, 1)));
+    assert_eq!(matching_brackets("f(a[0])", 6), Some((6, 1)));
+    assert_eq!(matching_brackets("f(a[0])", 4), Some((3, 5)));
+    assert_eq!(matching_brackets("f(a", 1), None);
+    assert_eq!(matching_brackets("abc", 1), None);
+    assert_eq!(matching_brackets("", 0), None);
+  }
+
+  #[test]
+  fn highlight_keeps_text() {
+    let line = "const a = \"s\\\"\" + 1.5; /* c */ f({ x: `t` }) // d";
+    let brackets = matching_brackets(line, line.find('(').unwrap());
+    assert!(brackets.is_some());
+    let highlighted = highlight(line, brackets);
+    assert_eq!(colors::strip_ansi_codes(&highlighted), line);
+  }
+
+  #[test]
+  fn completion_expression() {
+    assert_eq!(completion_start("Deno.re", 7), Some(0));
+    assert_eq!(completion_start("await Deno.re", 13), Some(6));
+    assert_eq!(completion_start("f(a.b", 5), Some(2));
+    assert_eq!(completion_start("Deno.readFile", 4), Some(0));
+    assert_eq!(completion_start("x ", 2), Some(2));
+    assert_eq!(completion_start("a..b", 4), None);
+    assert_eq!(completion_start("1.toFi", 6), None);
+    assert_eq!(completion_start("[].le", 5), None);
+  }
+
+  #[test]
+  fn incomplete_input() {
+    assert!(is_incomplete("function f() {"));
+    assert!(is_incomplete("const a = [1, (2"));
+    assert!(is_incomplete("`a ${b"));
+    assert!(is_incomplete("`a\n"));
+    assert!(is_incomplete("/* comment"));
+    assert!(is_incomplete("'abc\\"));
+    assert!(is_incomplete("1 +"));
+    assert!(is_incomplete("const f = (a) =>"));
+    assert!(is_incomplete("a.b. // comment"));
+    assert!(is_incomplete("if (x) { return '}'"));
+  }
+
+  #[test]
+  fn complete_or_invalid_input() {
+    assert!(!is_incomplete("1 + 2"));
+    assert!(!is_incomplete("a++"));
+    assert!(!is_incomplete("1."));
+    assert!(!is_incomplete("`a ${b} c`"));
+    assert!(!is_incomplete("/[/]/.test(a) // {"));
+    assert!(!is_incomplete("a / b / c"));
+    assert!(!is_incomplete("f(]"));
+    assert!(!is_incomplete("'abc"));
+    assert!(!is_incomplete("/* a */ x"));
+    assert!(!is_incomplete(""));
+  }
 }
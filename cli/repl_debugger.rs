@@ -0,0 +1,335 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! The debugger of `deno repl --inspect`. `.break file:line` sets a
+//! breakpoint, and while execution is paused the REPL reads debugger
+//! commands rather than inputs: `.step`, `.continue`, and expressions, which
+//! are evaluated in the paused frame.
+//!
+//! It drives an in-process inspector session. The pauses happen while V8
+//! runs JS, so they're handled from the pause loop of the inspector, which
+//! reads the commands from the line editor of the REPL.
+
+use crate::global_state::GlobalState;
+use crate::inspector::InProcessSession;
+use crate::op_error::OpError;
+use crate::repl::Repl;
+use crate::source_maps::SourceMapGetter;
+use crate::worker::Worker;
+use deno_core::ErrBox;
+use deno_core::ModuleSpecifier;
+use serde_json::Value;
+use sourcemap::SourceMap;
+use std::cell::RefCell;
+use std::io;
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use url::Url;
+
+const PROMPT: &str = "debug> ";
+
+const COMMANDS: &[(&str, &str)] = &[
+  (".break <file>:<line>", "Pause when the line is reached"),
+  (".continue", "Resume execution"),
+  (".help", "Print this list of commands"),
+  (".step", "Run to the next statement"),
+];
+
+pub struct ReplDebugger {
+  session: Box<InProcessSession>,
+  global_state: GlobalState,
+  /// The line editor of the REPL, once it's started.
+  pub repl: Option<Arc<Mutex<Repl>>>,
+  /// The frame expressions are evaluated in, while paused.
+  call_frame_id: Option<String>,
+}
+
+/// Enables the debugger of the REPL running in `worker`, if the worker has
+/// an inspector.
+pub fn attach(worker: &mut Worker) {
+  let inspector = match worker.inspector.as_mut() {
+    Some(inspector) => inspector,
+    None => return,
+  };
+  let mut session = InProcessSession::new(inspector);
+  session.keep_notifications("Debugger.paused");
+  if let Err(err) = session.post("Debugger.enable", json!({})) {
+    eprintln!("Couldn't enable the debugger: {}", err);
+    return;
+  }
+  let debugger = Rc::new(RefCell::new(ReplDebugger {
+    session,
+    global_state: worker.state.borrow().global_state.clone(),
+    repl: None,
+    call_frame_id: None,
+  }));
+  let debugger_ = debugger.clone();
+  inspector
+    .set_pause_handler(Box::new(move || debugger_.borrow_mut().on_pause()));
+  worker.state.borrow_mut().repl_debugger = Some(debugger);
+}
+
+impl ReplDebugger {
+  /// Sets a breakpoint at `location`, like `main.ts:12`, and describes it.
+  pub fn set_breakpoint(&mut self, location: &str) -> Result<String, ErrBox> {
+    let (file, line) = parse_location(location).ok_or_else(|| {
+      OpError::type_error("Usage: .break <file>:<line>".to_string())
+    })?;
+    let specifier = ModuleSpecifier::resolve_url_or_path(file)?;
+    let url = specifier.as_url();
+    // The breakpoints of TypeScript modules are set in the code they were
+    // compiled to.
+    let line_number = match self.source_map(url.as_str()) {
+      Some(source_map) => compiled_line(&source_map, line - 1)
+        .ok_or_else(|| OpError::other(format!("No code at {}", location)))?,
+      None => line - 1,
+    };
+    self.session.post(
+      "Debugger.setBreakpointByUrl",
+      json!({ "urlRegex": url_regex(url), "lineNumber": line_number }),
+    )?;
+    Ok(format!("Breakpoint set at {}:{}", url, line))
+  }
+
+  fn source_map(&self, url: &str) -> Option<SourceMap> {
+    let source_map = self.global_state.ts_compiler.get_source_map(url)?;
+    SourceMap::from_slice(&source_map).ok()
+  }
+
+  /// Handles one command while execution is paused. Called until one of
+  /// them resumes it.
+  fn on_pause(&mut self) {
+    if let Some(paused) = self.session.take_notifications().pop() {
+      let frame = &paused["params"]["callFrames"][0];
+      self.call_frame_id = frame["callFrameId"].as_str().map(String::from);
+      println!("Paused {}", self.describe_frame(frame));
+    }
+
+    let line = match self.read_line() {
+      Some(line) => line,
+      // Ctrl-C or Ctrl-D.
+      None => return self.post_command("Debugger.resume"),
+    };
+    let line = line.trim();
+    match line {
+      "" => {}
+      ".continue" => self.post_command("Debugger.resume"),
+      ".step" => self.post_command("Debugger.stepOver"),
+      ".help" => {
+        for (name, help) in COMMANDS {
+          println!("{:<22}{}", name, help);
+        }
+      }
+      _ if line.starts_with(".break") => {
+        match self.set_breakpoint(line[".break".len()..].trim()) {
+          Ok(message) => println!("{}", message),
+          Err(err) => eprintln!("{}", err),
+        }
+      }
+      _ if line.starts_with('.') => {
+        eprintln!("Invalid debugger command {}, type .help for a list", line)
+      }
+      expression => self.evaluate(expression),
+    }
+  }
+
+  fn post_command(&mut self, method: &str) {
+    if let Err(err) = self.session.post(method, json!({})) {
+      eprintln!("{}", err);
+    }
+  }
+
+  fn evaluate(&mut self, expression: &str) {
+    let call_frame_id = match &self.call_frame_id {
+      Some(call_frame_id) => call_frame_id.clone(),
+      None => return,
+    };
+    let result = self.session.post(
+      "Debugger.evaluateOnCallFrame",
+      json!({ "callFrameId": call_frame_id, "expression": expression }),
+    );
+    match result {
+      Ok(result) => match result.get("exceptionDetails") {
+        Some(details) => {
+          eprintln!("Thrown: {}", describe_object(&details["exception"]))
+        }
+        None => println!("{}", describe_object(&result["result"])),
+      },
+      Err(err) => eprintln!("{}", err),
+    }
+  }
+
+  /// Like `in f at file:///main.ts:3:5`, with the position in the original
+  /// source of TypeScript modules.
+  fn describe_frame(&self, frame: &Value) -> String {
+    let url = frame["url"].as_str().unwrap_or_default();
+    let mut line = frame["location"]["lineNumber"].as_u64().unwrap_or(0) as u32;
+    let mut column =
+      frame["location"]["columnNumber"].as_u64().unwrap_or(0) as u32;
+    if let Some(source_map) = self.source_map(url) {
+      if let Some(token) = source_map.lookup_token(line, column) {
+        line = token.get_src_line();
+        column = token.get_src_col();
+      }
+    }
+    let location = if url.is_empty() {
+      format!("<input>:{}:{}", line + 1, column + 1)
+    } else {
+      format!("{}:{}:{}", url, line + 1, column + 1)
+    };
+    match frame["functionName"].as_str() {
+      Some(name) if !name.is_empty() => format!("in {} at {}", name, location),
+      _ => format!("at {}", location),
+    }
+  }
+
+  fn read_line(&self) -> Option<String> {
+    let repl = match &self.repl {
+      Some(repl) => repl,
+      None => {
+        print!("{}", PROMPT);
+        io::stdout().flush().ok()?;
+        let mut line = String::new();
+        return match io::stdin().read_line(&mut line) {
+          Ok(0) | Err(_) => None,
+          Ok(_) => Some(line),
+        };
+      }
+    };
+    // Execution paused in a callback while the REPL was reading an input,
+    // which has to be finished first.
+    let mut repl = match repl.try_lock() {
+      Ok(repl) => repl,
+      Err(_) => {
+        println!("Paused; press Enter to debug");
+        repl.lock().ok()?
+      }
+    };
+    repl.readline(PROMPT).ok()
+  }
+}
+
+/// Splits `main.ts:12` into the file and the line, which starts at 1.
+fn parse_location(location: &str) -> Option<(&str, u32)> {
+  let colon = location.rfind(':')?;
+  let (file, line) = (&location[..colon], &location[colon + 1..]);
+  let line = line.parse().ok().filter(|line| *line > 0)?;
+  if file.is_empty() {
+    return None;
+  }
+  Some((file, line))
+}
+
+/// Matches the scripts of `url`. The scripts given with `--eval-file` are
+/// named by their path.
+fn url_regex(url: &Url) -> String {
+  match url.to_file_path() {
+    Ok(path) => format!(
+      "^(?:{}|{})$",
+      regex::escape(url.as_str()),
+      regex::escape(&path.to_string_lossy())
+    ),
+    Err(_) => format!("^{}$", regex::escape(url.as_str())),
+  }
+}
+
+/// The first line of the compiled code of the 0-based `line` of the source.
+fn compiled_line(source_map: &SourceMap, line: u32) -> Option<u32> {
+  source_map
+    .tokens()
+    .filter(|token| token.get_src_line() == line)
+    .map(|token| token.get_dst_line())
+    .min()
+}
+
+/// Formats a `RemoteObject` of the protocol.
+fn describe_object(object: &Value) -> String {
+  if let Some(value) = object["unserializableValue"].as_str() {
+    return value.to_string();
+  }
+  match object["type"].as_str() {
+    Some("undefined") => "undefined".to_string(),
+    Some("object") | Some("function") | Some("symbol") | Some("bigint") => {
+      object["description"].as_str().unwrap_or("null").to_string()
+    }
+    _ => object["value"].to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn locations() {
+    assert_eq!(parse_location("main.ts:12"), Some(("main.ts", 12)));
+    assert_eq!(
+      parse_location("file:///a/main.ts:3"),
+      Some(("file:///a/main.ts", 3))
+    );
+    assert_eq!(parse_location("main.ts"), None);
+    assert_eq!(parse_location("main.ts:0"), None);
+    assert_eq!(parse_location(":3"), None);
+  }
+
+  #[test]
+  fn url_regexes() {
+    let url = Url::parse("https://deno.land/std/a.ts").unwrap();
+    assert_eq!(url_regex(&url), r"^https://deno\.land/std/a\.ts$");
+    if cfg!(unix) {
+      let url = Url::parse("file:///a/main.js").unwrap();
+      assert_eq!(url_regex(&url), r"^(?:file:///a/main\.js|/a/main\.js)$");
+    }
+  }
+
+  #[test]
+  fn compiled_lines() {
+    // Maps line 1 of the compiled code to line 3 of the source.
+    let source_map = SourceMap::from_slice(
+      br#"{
+        "version": 3,
+        "sources": ["file:///a.ts"],
+        "names": [],
+        "mappings": ";AAEA,IAAI"
+      }"#,
+    )
+    .unwrap();
+    assert_eq!(compiled_line(&source_map, 2), Some(1));
+    assert_eq!(compiled_line(&source_map, 0), None);
+  }
+
+  #[test]
+  fn remote_objects() {
+    assert_eq!(
+      describe_object(&json!({ "type": "undefined" })),
+      "undefined"
+    );
+    assert_eq!(
+      describe_object(&json!({ "type": "number", "value": 3 })),
+      "3"
+    );
+    assert_eq!(
+      describe_object(&json!({ "type": "string", "value": "a" })),
+      "\"a\""
+    );
+    assert_eq!(
+      describe_object(&json!({
+        "type": "number",
+        "unserializableValue": "NaN"
+      })),
+      "NaN"
+    );
+    assert_eq!(
+      describe_object(&json!({
+        "type": "object",
+        "className": "Array",
+        "description": "Array(2)"
+      })),
+      "Array(2)"
+    );
+    assert_eq!(
+      describe_object(&json!({ "type": "object", "subtype": "null" })),
+      "null"
+    );
+  }
+}
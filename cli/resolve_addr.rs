@@ -1,10 +1,29 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use crate::op_error::OpError;
+use futures::future::Either;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use std::io;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
+use std::time::Duration;
+use tokio::net::TcpStream;
 
-/// Resolve network address. Returns a future.
+/// How long a connection attempt runs before the next address is tried in
+/// parallel, as recommended by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Resolve network address. Returns the first resolved address.
 pub fn resolve_addr(hostname: &str, port: u16) -> Result<SocketAddr, OpError> {
+  Ok(resolve_addrs(hostname, port)?.remove(0))
+}
+
+/// Resolve network address. Returns all resolved addresses, in the order
+/// the resolver gave them, which is never empty.
+pub fn resolve_addrs(
+  hostname: &str,
+  port: u16,
+) -> Result<Vec<SocketAddr>, OpError> {
   // Default to localhost if given just the port. Example: ":80"
   let addr: &str = if !hostname.is_empty() {
     &hostname
@@ -21,8 +40,80 @@ pub fn resolve_addr(hostname: &str, port: u16) -> Result<SocketAddr, OpError> {
     addr
   };
   let addr_port_pair = (addr, port);
-  let mut iter = addr_port_pair.to_socket_addrs().map_err(OpError::from)?;
-  Ok(iter.next().unwrap())
+  let addrs: Vec<SocketAddr> = addr_port_pair
+    .to_socket_addrs()
+    .map_err(OpError::from)?
+    .collect();
+  if addrs.is_empty() {
+    return Err(OpError::not_found(format!(
+      "No address found for {}",
+      hostname
+    )));
+  }
+  Ok(addrs)
+}
+
+/// Orders addresses for connection attempts as RFC 8305 does: IPv6 first,
+/// then alternating between the families, keeping the order of the resolver
+/// within each of them.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+  let (v6, v4): (Vec<_>, Vec<_>) =
+    addrs.into_iter().partition(SocketAddr::is_ipv6);
+  let mut v6 = v6.into_iter();
+  let mut v4 = v4.into_iter();
+  let mut ordered = Vec::new();
+  loop {
+    match (v6.next(), v4.next()) {
+      (None, None) => return ordered,
+      (a, b) => {
+        ordered.extend(a);
+        ordered.extend(b);
+      }
+    }
+  }
+}
+
+/// Connects to the first of `addrs` that accepts the connection, using
+/// Happy Eyeballs (RFC 8305): a new attempt is started every 250ms, or as
+/// soon as the previous one fails, while the earlier ones keep running. The
+/// first connection established wins and the other attempts are dropped.
+/// If all of them fail, the error of the last one is returned.
+pub async fn connect_tcp(addrs: Vec<SocketAddr>) -> io::Result<TcpStream> {
+  let mut addrs = interleave_families(addrs).into_iter();
+  let mut attempts = FuturesUnordered::new();
+  let mut last_err = None;
+  loop {
+    if attempts.is_empty() {
+      match addrs.next() {
+        Some(addr) => attempts.push(TcpStream::connect(addr)),
+        None => {
+          return Err(last_err.unwrap_or_else(|| {
+            io::Error::new(
+              io::ErrorKind::AddrNotAvailable,
+              "No address to connect to",
+            )
+          }))
+        }
+      }
+    }
+
+    let delay = tokio::time::delay_for(CONNECTION_ATTEMPT_DELAY);
+    let outcome = match futures::future::select(attempts.next(), delay).await {
+      Either::Left((outcome, _)) => outcome,
+      Either::Right(_) => None,
+    };
+    match outcome {
+      Some(Ok(tcp_stream)) => return Ok(tcp_stream),
+      Some(Err(err)) => {
+        debug!("Connection attempt failed: {}", err);
+        last_err = Some(err);
+      }
+      None => {}
+    }
+    if let Some(addr) = addrs.next() {
+      attempts.push(TcpStream::connect(addr));
+    }
+  }
 }
 
 #[cfg(test)]
@@ -57,6 +148,57 @@ mod tests {
     assert_eq!(actual, expected);
   }
 
+  #[test]
+  fn resolve_addrs_localhost() {
+    let addrs = resolve_addrs("127.0.0.1", 80).unwrap();
+    assert_eq!(addrs, vec!["127.0.0.1:80".parse::<SocketAddr>().unwrap()]);
+  }
+
+  #[test]
+  fn interleave_families_prefers_ipv6() {
+    let addrs: Vec<SocketAddr> = vec![
+      "192.0.2.1:80",
+      "192.0.2.2:80",
+      "192.0.2.3:80",
+      "[2001:db8::1]:80",
+      "[2001:db8::2]:80",
+    ]
+    .into_iter()
+    .map(|addr| addr.parse().unwrap())
+    .collect();
+    let ordered: Vec<String> = interleave_families(addrs)
+      .iter()
+      .map(SocketAddr::to_string)
+      .collect();
+    assert_eq!(
+      ordered,
+      vec![
+        "[2001:db8::1]:80",
+        "192.0.2.1:80",
+        "[2001:db8::2]:80",
+        "192.0.2.2:80",
+        "192.0.2.3:80",
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn connect_tcp_falls_back() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    // Nothing listens on the port of a listener that was just dropped, so
+    // connecting to it is refused right away.
+    let refused = std::net::TcpListener::bind("127.0.0.1:0")
+      .unwrap()
+      .local_addr()
+      .unwrap();
+    let tcp_stream = connect_tcp(vec![refused, addr]).await.unwrap();
+    assert_eq!(tcp_stream.peer_addr().unwrap(), addr);
+
+    let err = connect_tcp(vec![refused]).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+  }
+
   #[test]
   fn resolve_addr_ipv6() {
     let expected = SocketAddr::V6(SocketAddrV6::new(
@@ -3,6 +3,7 @@
 use sourcemap::SourceMap;
 use std::collections::HashMap;
 use std::str;
+use url::Url;
 
 pub trait SourceMapGetter {
   /// Returns the raw source map file.
@@ -28,6 +29,51 @@ fn builtin_source_map(file_name: &str) -> Option<Vec<u8>> {
   }
 }
 
+/// Returns the URL given by the last `//# sourceMappingURL=` comment of a
+/// JavaScript source, which is how bundlers and other tools point at the map
+/// of the code they generated.
+pub fn find_source_mapping_url(source_code: &[u8]) -> Option<String> {
+  let source_code = str::from_utf8(source_code).ok()?;
+  source_code.rsplit('\n').find_map(|line| {
+    let line = line.trim();
+    ["//# sourceMappingURL=", "//@ sourceMappingURL="]
+      .iter()
+      .find(|prefix| line.starts_with(*prefix))
+      .map(|prefix| line[prefix.len()..].trim().to_string())
+      .filter(|url| !url.is_empty())
+  })
+}
+
+/// Decodes an inline source map, given as a base64 `data:` URL.
+pub fn decode_data_url(url: &str) -> Option<Vec<u8>> {
+  if !url.starts_with("data:") {
+    return None;
+  }
+  let comma = url.find(',')?;
+  if !url[..comma].ends_with(";base64") {
+    return None;
+  }
+  base64::decode(&url[comma + 1..]).ok()
+}
+
+/// Makes the sources of a source map absolute, by resolving them against the
+/// URL the map was found at, so that they can be fetched like modules.
+pub fn resolve_sources(
+  raw_source_map: &[u8],
+  map_url: &Url,
+) -> Option<Vec<u8>> {
+  let mut source_map = SourceMap::from_slice(raw_source_map).ok()?;
+  for index in 0..source_map.get_source_count() {
+    let source = source_map.get_source(index)?.to_string();
+    if let Ok(url) = map_url.join(&source) {
+      source_map.set_source(index, url.as_str());
+    }
+  }
+  let mut output = vec![];
+  source_map.to_writer(&mut output).ok()?;
+  Some(output)
+}
+
 /// Apply a source map to a deno_core::JSError, returning a JSError where file
 /// names and line/column numbers point to the location in the original source,
 /// rather than the transpiled source code.
@@ -64,12 +110,12 @@ pub fn apply_source_map<G: SourceMapGetter>(
   };
   // if there is a source line that we might be different in the source file, we
   // will go fetch it from the getter
-  let source_line = match line_number {
-    Some(ln)
-      if js_error.source_line.is_some() && script_resource_name.is_some() =>
-    {
+  // The line is read from the file the position was mapped to, which is only
+  // the same as the generated one for modules compiled by Deno.
+  let source_line = match (&script_resource_name, line_number) {
+    (Some(file_name), Some(ln)) if js_error.source_line.is_some() => {
       getter.get_source_line(
-        &js_error.script_resource_name.clone().unwrap(),
+        file_name,
         // Getter expects 0-based line numbers, but ours are 1-based.
         ln as usize - 1,
       )
@@ -77,8 +123,9 @@ pub fn apply_source_map<G: SourceMapGetter>(
     _ => js_error.source_line.clone(),
   };
 
-  deno_core::JSError {
+  let js_error = deno_core::JSError {
     message: js_error.message.clone(),
+    name: js_error.name.clone(),
     source_line,
     script_resource_name,
     line_number,
@@ -86,7 +133,57 @@ pub fn apply_source_map<G: SourceMapGetter>(
     end_column,
     frames: js_error.frames.clone(),
     formatted_frames: js_error.formatted_frames.clone(),
+  };
+  locate_in_stack(js_error, getter)
+}
+
+/// Errors without a source line to show, like the ones thrown by ops from
+/// internal code, are located at the first stack frame in user code instead,
+/// so that the line which led to them can be printed.
+fn locate_in_stack<G: SourceMapGetter>(
+  mut js_error: deno_core::JSError,
+  getter: &G,
+) -> deno_core::JSError {
+  let has_source_line = match js_error.source_line {
+    Some(ref source_line) => {
+      !source_line.is_empty()
+        && js_error.start_column.is_some()
+        && js_error.end_column.is_some()
+    }
+    None => false,
+  };
+  if has_source_line {
+    return js_error;
+  }
+
+  // The frames have already been source mapped in prepareStackTrace().
+  let maybe_location = js_error.frames.iter().find_map(|frame| {
+    match (&frame.file_name, frame.line_number, frame.column_number) {
+      // Positions are 1-based, so frames without a real one are skipped.
+      (Some(file_name), Some(line_number), Some(column_number))
+        if !frame.is_native
+          && !file_name.starts_with("$deno$")
+          && line_number > 0
+          && column_number > 0 =>
+      {
+        Some((file_name.clone(), line_number, column_number))
+      }
+      _ => None,
+    }
+  });
+  if let Some((file_name, line_number, column_number)) = maybe_location {
+    // Getter expects 0-based line numbers, but ours are 1-based.
+    let maybe_source_line =
+      getter.get_source_line(&file_name, line_number as usize - 1);
+    if let Some(source_line) = maybe_source_line {
+      js_error.source_line = Some(source_line);
+      js_error.script_resource_name = Some(file_name);
+      js_error.line_number = Some(line_number);
+      js_error.start_column = Some(column_number - 1);
+      js_error.end_column = Some(column_number);
+    }
   }
+  js_error
 }
 
 fn get_maybe_orig_position<G: SourceMapGetter>(
@@ -162,6 +259,7 @@ fn parse_map_string<G: SourceMapGetter>(
 #[cfg(test)]
 mod tests {
   use super::*;
+  use deno_core::JSStackFrame;
 
   struct MockSourceMapGetter {}
 
@@ -202,10 +300,50 @@ mod tests {
     }
   }
 
+  #[test]
+  fn source_mapping_url() {
+    assert_eq!(
+      find_source_mapping_url(
+        b"a();\n//# sourceMappingURL=old.js.map\n//# sourceMappingURL=a.js.map\n"
+      ),
+      Some("a.js.map".to_string())
+    );
+    assert_eq!(
+      find_source_mapping_url(b"//@ sourceMappingURL=b.js.map"),
+      Some("b.js.map".to_string())
+    );
+    assert_eq!(find_source_mapping_url(b"a(); // sourceMappingURL=x"), None);
+  }
+
+  #[test]
+  fn data_url() {
+    let url = format!(
+      "data:application/json;charset=utf-8;base64,{}",
+      base64::encode("{}")
+    );
+    assert_eq!(decode_data_url(&url), Some(b"{}".to_vec()));
+    assert_eq!(decode_data_url("data:application/json,{}"), None);
+    assert_eq!(decode_data_url("bundle.js.map"), None);
+  }
+
+  #[test]
+  fn resolve_map_sources() {
+    let raw = br#"{"version":3,"sources":["../src/a.ts","https://x.com/b.ts"],"names":[],"mappings":"AAAA"}"#;
+    let map_url = Url::parse("https://example.com/dist/bundle.js.map").unwrap();
+    let resolved = resolve_sources(raw, &map_url).unwrap();
+    let source_map = SourceMap::from_slice(&resolved).unwrap();
+    assert_eq!(
+      source_map.get_source(0),
+      Some("https://example.com/src/a.ts")
+    );
+    assert_eq!(source_map.get_source(1), Some("https://x.com/b.ts"));
+  }
+
   #[test]
   fn apply_source_map_line() {
     let e = deno_core::JSError {
       message: "TypeError: baz".to_string(),
+      name: Some("TypeError".to_string()),
       source_line: Some("foo".to_string()),
       script_resource_name: Some("foo_bar.ts".to_string()),
       line_number: Some(4),
@@ -218,4 +356,67 @@ mod tests {
     let actual = apply_source_map(&e, &getter);
     assert_eq!(actual.source_line, Some("console.log('foo');".to_string()));
   }
+
+  fn stack_frame(file_name: &str, line_number: i64) -> JSStackFrame {
+    JSStackFrame {
+      type_name: None,
+      function_name: None,
+      method_name: None,
+      file_name: Some(file_name.to_string()),
+      line_number: Some(line_number),
+      column_number: Some(9),
+      eval_origin: None,
+      is_top_level: None,
+      is_eval: false,
+      is_native: false,
+      is_constructor: false,
+      is_async: false,
+      is_promise_all: false,
+      promise_index: None,
+    }
+  }
+
+  #[test]
+  fn apply_source_map_locates_in_stack() {
+    let e = deno_core::JSError {
+      message: "BadResource: Bad resource ID".to_string(),
+      name: Some("BadResource".to_string()),
+      source_line: None,
+      script_resource_name: Some("CLI_SNAPSHOT.js".to_string()),
+      line_number: None,
+      start_column: None,
+      end_column: None,
+      frames: vec![
+        stack_frame("$deno$/ops/dispatch_json.ts", 1),
+        stack_frame("foo_bar.ts", 2),
+      ],
+      formatted_frames: vec![],
+    };
+    let getter = MockSourceMapGetter {};
+    let actual = apply_source_map(&e, &getter);
+    assert_eq!(actual.script_resource_name, Some("foo_bar.ts".to_string()));
+    assert_eq!(actual.line_number, Some(2));
+    assert_eq!(actual.start_column, Some(8));
+    assert_eq!(actual.end_column, Some(9));
+    assert_eq!(actual.source_line, Some("console.log('foo');".to_string()));
+  }
+
+  #[test]
+  fn apply_source_map_skips_frames_without_position() {
+    let e = deno_core::JSError {
+      message: "BadResource: Bad resource ID".to_string(),
+      name: Some("BadResource".to_string()),
+      source_line: None,
+      script_resource_name: None,
+      line_number: None,
+      start_column: None,
+      end_column: None,
+      frames: vec![stack_frame("foo_bar.ts", 0), stack_frame("foo_bar.ts", 2)],
+      formatted_frames: vec![],
+    };
+    let getter = MockSourceMapGetter {};
+    let actual = apply_source_map(&e, &getter);
+    assert_eq!(actual.line_number, Some(2));
+    assert_eq!(actual.source_line, Some("console.log('foo');".to_string()));
+  }
 }
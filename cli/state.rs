@@ -1,13 +1,17 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use crate::file_fetcher::SourceFileFetcher;
 use crate::global_state::GlobalState;
-use crate::global_timer::GlobalTimer;
 use crate::import_map::ImportMap;
 use crate::metrics::Metrics;
 use crate::op_error::OpError;
+use crate::op_tracer::OpTracer;
+use crate::ops::io::StreamResourceHolder;
 use crate::ops::JsonOp;
+use crate::ops::JsonResult;
 use crate::ops::MinimalOp;
 use crate::permissions::Permissions;
+use crate::repl_debugger::ReplDebugger;
+use crate::timer_wheel::TimerWheel;
 use crate::tsc::TargetLib;
 use crate::web_worker::WebWorkerHandle;
 use deno_core::Buf;
@@ -16,7 +20,13 @@ use deno_core::ModuleLoadId;
 use deno_core::ModuleLoader;
 use deno_core::ModuleSpecifier;
 use deno_core::Op;
+use deno_core::OpAsyncFuture;
+use deno_core::ResourceTable;
 use deno_core::ZeroCopyBuf;
+use futures::channel::oneshot;
+use futures::future::poll_fn;
+use futures::future::AbortHandle;
+use futures::future::Abortable;
 use futures::future::FutureExt;
 use futures::Future;
 use rand::rngs::StdRng;
@@ -26,6 +36,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::path::Path;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::str;
@@ -51,7 +62,7 @@ pub struct StateInner {
   /// import map file will be resolved and set.
   pub import_map: Option<ImportMap>,
   pub metrics: Metrics,
-  pub global_timer: GlobalTimer,
+  pub timers: TimerWheel,
   pub workers: HashMap<u32, (JoinHandle<()>, WebWorkerHandle)>,
   pub next_worker_id: u32,
   pub start_time: Instant,
@@ -59,6 +70,39 @@ pub struct StateInner {
   pub target_lib: TargetLib,
   pub is_main: bool,
   pub is_internal: bool,
+  /// Set by the `--unstable` flag; ops of unstable APIs fail without it.
+  pub unstable: bool,
+  /// Handles to abort pending async JSON ops, by promise id.
+  pub cancel_handles: HashMap<u64, AbortHandle>,
+  /// Sockets that were closed and are being shut down, by their former rid.
+  pub closing_sockets: HashMap<u32, StreamResourceHolder>,
+  /// With `--strict-resources`, the stack of the code calling the next op,
+  /// which is recorded as the origin of the resources that op adds.
+  pub next_op_origin: Option<Rc<str>>,
+  /// The heap snapshots requested by `Deno.writeHeapSnapshot()`, which the
+  /// worker takes and reports through the senders.
+  pub heap_snapshot_requests:
+    Vec<(PathBuf, oneshot::Sender<Result<(), ErrBox>>)>,
+  /// Records the ops of the main worker with `--trace-ops`.
+  pub op_tracer: Option<Rc<RefCell<OpTracer>>>,
+  /// The debugger of `deno repl --inspect`.
+  pub repl_debugger: Option<Rc<RefCell<ReplDebugger>>>,
+}
+
+/// Makes `origin` the origin of the resources that `fut` adds while it's
+/// polled.
+fn with_origin(
+  resource_table: Rc<RefCell<ResourceTable>>,
+  origin: Rc<str>,
+  mut fut: OpAsyncFuture,
+) -> OpAsyncFuture {
+  poll_fn(move |cx| {
+    let previous = resource_table.borrow_mut().set_origin(Some(origin.clone()));
+    let poll = fut.poll_unpin(cx);
+    resource_table.borrow_mut().set_origin(previous);
+    poll
+  })
+  .boxed_local()
 }
 
 impl State {
@@ -109,7 +153,26 @@ impl State {
       let bytes_sent_zero_copy =
         zero_copy.iter().map(|b| b.len()).sum::<usize>() as u64;
 
-      let op = dispatcher(isolate_state, control, zero_copy);
+      let origin = state.borrow_mut().next_op_origin.take();
+      let op = match origin {
+        None => dispatcher(isolate_state, control, zero_copy),
+        Some(origin) => {
+          let resource_table = isolate_state.resource_table.clone();
+          let previous =
+            resource_table.borrow_mut().set_origin(Some(origin.clone()));
+          let op = dispatcher(isolate_state, control, zero_copy);
+          resource_table.borrow_mut().set_origin(previous);
+          match op {
+            Op::Sync(buf) => Op::Sync(buf),
+            Op::Async(fut) => {
+              Op::Async(with_origin(resource_table, origin, fut))
+            }
+            Op::AsyncUnref(fut) => {
+              Op::AsyncUnref(with_origin(resource_table, origin, fut))
+            }
+          }
+        }
+      };
 
       match op {
         Op::Sync(buf) => {
@@ -197,7 +260,11 @@ impl State {
     move |_isolate_state: &mut deno_core::CoreIsolateState,
           args: Value,
           zero_copy: &mut [ZeroCopyBuf]|
-          -> Result<JsonOp, OpError> { dispatcher(&state, args, zero_copy) }
+          -> Result<JsonOp, OpError> {
+      let promise_id = args.get("promiseId").and_then(Value::as_u64);
+      let result = dispatcher(&state, args, zero_copy);
+      state.cancelable(promise_id, result)
+    }
   }
 
   pub fn stateful_op2<D>(
@@ -221,29 +288,60 @@ impl State {
           args: Value,
           zero_copy: &mut [ZeroCopyBuf]|
           -> Result<JsonOp, OpError> {
-      dispatcher(isolate_state, &state, args, zero_copy)
+      let promise_id = args.get("promiseId").and_then(Value::as_u64);
+      let result = dispatcher(isolate_state, &state, args, zero_copy);
+      state.cancelable(promise_id, result)
     }
   }
 
-  /// Quits the process if the --unstable flag was not provided.
-  ///
-  /// This is intentionally a non-recoverable check so that people cannot probe
-  /// for unstable APIs from stable programs.
-  pub fn check_unstable(&self, api_name: &str) {
-    // TODO(ry) Maybe use IsolateHandle::terminate_execution here to provide a
-    // stack trace in JS.
-    let s = self.0.borrow();
-    if !s.global_state.flags.unstable {
-      exit_unstable(api_name);
+  /// Makes the future of an async op abortable through `op_cancel`. When it
+  /// is aborted the future is dropped, along with everything it owns, and
+  /// the op fails with an `Interrupted` error.
+  fn cancelable(
+    &self,
+    promise_id: Option<u64>,
+    result: Result<JsonOp, OpError>,
+  ) -> Result<JsonOp, OpError> {
+    // Promise ids handed out by JS start at 1; 0 is used by ops/batch.rs for
+    // the ops of a batch, which are canceled along with the batch itself.
+    let promise_id = match promise_id {
+      Some(promise_id) if promise_id != 0 => promise_id,
+      _ => return result,
+    };
+    let wrap = |fut: Pin<Box<dyn Future<Output = JsonResult>>>| {
+      let (handle, registration) = AbortHandle::new_pair();
+      self.borrow_mut().cancel_handles.insert(promise_id, handle);
+      let state = self.clone();
+      Abortable::new(fut, registration)
+        .map(move |result| {
+          state.borrow_mut().cancel_handles.remove(&promise_id);
+          result.unwrap_or_else(|_| Err(OpError::canceled()))
+        })
+        .boxed_local()
+    };
+    match result {
+      Ok(JsonOp::Async(fut)) => Ok(JsonOp::Async(wrap(fut))),
+      Ok(JsonOp::AsyncUnref(fut)) => Ok(JsonOp::AsyncUnref(wrap(fut))),
+      result => result,
+    }
+  }
+
+  /// Fails with a catchable error unless `--unstable` was passed. Ops of
+  /// unstable APIs call this before doing anything else.
+  #[inline]
+  pub fn check_unstable(&self, api_name: &str) -> Result<(), OpError> {
+    if self.borrow().unstable {
+      Ok(())
+    } else {
+      Err(OpError::unstable(api_name))
     }
   }
 }
 
+/// Used where an unstable feature is requested by a flag rather than by
+/// code, so there's nothing to throw an error in.
 pub fn exit_unstable(api_name: &str) {
-  eprintln!(
-    "Unstable API '{}'. The --unstable flag must be provided.",
-    api_name
-  );
+  eprintln!("{}", OpError::unstable(api_name).msg);
   std::process::exit(70);
 }
 
@@ -262,8 +360,12 @@ impl ModuleLoader for State {
         }
       }
     }
-    let module_specifier =
-      ModuleSpecifier::resolve_import(specifier, referrer)?;
+    let module_specifier = self
+      .borrow()
+      .global_state
+      .file_fetcher
+      .npm_resolver
+      .resolve_import(specifier, referrer)?;
 
     Ok(module_specifier)
   }
@@ -365,13 +467,19 @@ impl State {
       global_state.permissions.clone()
     };
 
+    let unstable = global_state.flags.unstable;
+    let timers = if global_state.flags.frozen_time.is_some() {
+      TimerWheel::new_frozen()
+    } else {
+      TimerWheel::new()
+    };
     let state = Rc::new(RefCell::new(StateInner {
       global_state,
       main_module,
       permissions,
       import_map: maybe_import_map,
       metrics: Metrics::default(),
-      global_timer: GlobalTimer::new(),
+      timers,
       workers: HashMap::new(),
       next_worker_id: 0,
       start_time: Instant::now(),
@@ -379,6 +487,13 @@ impl State {
       target_lib: TargetLib::Main,
       is_main: true,
       is_internal,
+      unstable,
+      cancel_handles: HashMap::new(),
+      closing_sockets: HashMap::new(),
+      next_op_origin: None,
+      heap_snapshot_requests: vec![],
+      op_tracer: None,
+      repl_debugger: None,
     }));
 
     Ok(Self(state))
@@ -401,13 +516,19 @@ impl State {
       global_state.permissions.clone()
     };
 
+    let unstable = global_state.flags.unstable;
+    let timers = if global_state.flags.frozen_time.is_some() {
+      TimerWheel::new_frozen()
+    } else {
+      TimerWheel::new()
+    };
     let state = Rc::new(RefCell::new(StateInner {
       global_state,
       main_module,
       permissions,
       import_map: None,
       metrics: Metrics::default(),
-      global_timer: GlobalTimer::new(),
+      timers,
       workers: HashMap::new(),
       next_worker_id: 0,
       start_time: Instant::now(),
@@ -415,6 +536,13 @@ impl State {
       target_lib: TargetLib::Worker,
       is_main: false,
       is_internal: false,
+      unstable,
+      cancel_handles: HashMap::new(),
+      closing_sockets: HashMap::new(),
+      next_op_origin: None,
+      heap_snapshot_requests: vec![],
+      op_tracer: None,
+      repl_debugger: None,
     }));
 
     Ok(Self(state))
@@ -461,6 +589,11 @@ impl State {
     self.borrow().permissions.check_run()
   }
 
+  #[inline]
+  pub fn check_hrtime(&self) -> Result<(), OpError> {
+    self.borrow().permissions.check_hrtime()
+  }
+
   #[inline]
   pub fn check_plugin(&self, filename: &Path) -> Result<(), OpError> {
     self.borrow().permissions.check_plugin(filename)
@@ -480,6 +613,17 @@ impl State {
         self.check_net_url(u)?;
         Ok(())
       }
+      "npm" => {
+        let registry_url = self
+          .borrow()
+          .global_state
+          .file_fetcher
+          .npm_resolver
+          .registry_url()
+          .clone();
+        self.check_net_url(&registry_url)?;
+        Ok(())
+      }
       "file" => {
         let path = u
           .to_file_path()
@@ -487,7 +631,11 @@ impl State {
           .into_os_string()
           .into_string()
           .unwrap();
-        self.check_read(Path::new(&path))?;
+        let state = self.borrow();
+        let npm_resolver = &state.global_state.file_fetcher.npm_resolver;
+        npm_resolver
+          .module_permissions(&state.permissions)
+          .check_read(Path::new(&path))?;
         Ok(())
       }
       _ => unreachable!(),
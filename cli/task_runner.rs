@@ -0,0 +1,688 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Implements `deno task`, which runs the commands listed in the `tasks`
+//! field of the project config file.
+//!
+//! Tasks are interpreted by a small shell of our own, so that they behave
+//! the same on every platform. It understands quoting, `$VAR` and `${VAR}`
+//! expansion, `NAME=value` assignments, pipes and the `&&`, `||` and `;`
+//! connectors. `echo` is built in, and `deno` always refers to the running
+//! executable.
+
+use crate::colors;
+use crate::config_file::ConfigFile;
+use crate::op_error::OpError;
+use deno_core::ErrBox;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::Child;
+use std::process::ChildStdout;
+use std::process::Command;
+use std::process::Stdio;
+
+#[derive(Clone, Debug, PartialEq)]
+enum WordPart {
+  Text(String),
+  Var(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Word {
+  /// Set for unquoted `NAME=value` words; `parts` then hold the value.
+  assign: Option<String>,
+  parts: Vec<WordPart>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+  Word(Word),
+  Pipe,
+  And,
+  Or,
+  Semicolon,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Connector {
+  Always,
+  And,
+  Or,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct SimpleCommand {
+  assignments: Vec<(String, Word)>,
+  words: Vec<Word>,
+}
+
+type Pipeline = Vec<SimpleCommand>;
+
+#[derive(Debug, Default, PartialEq)]
+struct Script {
+  /// Each pipeline runs depending on the exit code of the previous one.
+  pipelines: Vec<(Connector, Pipeline)>,
+}
+
+fn is_name_char(c: char, first: bool) -> bool {
+  c == '_' || c.is_ascii_alphabetic() || (!first && c.is_ascii_digit())
+}
+
+fn tokenize(script: &str) -> Result<Vec<Token>, String> {
+  let mut tokens = vec![];
+  let mut chars = script.chars().peekable();
+  let mut word: Option<Word> = None;
+  // Text of the current word that hasn't been pushed to `word.parts` yet.
+  let mut text = String::new();
+  // Whether the current word contained quotes or variables so far, in which
+  // case it can't be an assignment.
+  let mut plain = true;
+
+  macro_rules! flush_text {
+    () => {
+      if !text.is_empty() {
+        word
+          .get_or_insert_with(Word::default)
+          .parts
+          .push(WordPart::Text(std::mem::replace(&mut text, String::new())));
+      }
+    };
+  }
+  macro_rules! end_word {
+    () => {
+      flush_text!();
+      if let Some(w) = word.take() {
+        tokens.push(Token::Word(w));
+      }
+      plain = true;
+    };
+  }
+
+  while let Some(c) = chars.next() {
+    match c {
+      ' ' | '\t' | '\r' => {
+        end_word!();
+      }
+      '\n' | ';' => {
+        end_word!();
+        tokens.push(Token::Semicolon);
+      }
+      '#' if word.is_none() && text.is_empty() => {
+        while let Some(&c) = chars.peek() {
+          if c == '\n' {
+            break;
+          }
+          chars.next();
+        }
+      }
+      '|' => {
+        end_word!();
+        if chars.peek() == Some(&'|') {
+          chars.next();
+          tokens.push(Token::Or);
+        } else {
+          tokens.push(Token::Pipe);
+        }
+      }
+      '&' => {
+        end_word!();
+        if chars.peek() == Some(&'&') {
+          chars.next();
+          tokens.push(Token::And);
+        } else {
+          return Err("background commands (&) aren't supported".to_string());
+        }
+      }
+      '<' | '>' => {
+        return Err(format!("redirections ({}) aren't supported", c));
+      }
+      '=' if plain && word.is_none() && !text.is_empty() => {
+        let name = std::mem::replace(&mut text, String::new());
+        if name
+          .chars()
+          .enumerate()
+          .all(|(i, c)| is_name_char(c, i == 0))
+        {
+          word = Some(Word {
+            assign: Some(name),
+            parts: vec![],
+          });
+        } else {
+          text = name;
+          text.push('=');
+        }
+      }
+      '\\' => match chars.next() {
+        Some('\n') => {}
+        Some(c) => {
+          text.push(c);
+          plain = false;
+        }
+        None => text.push('\\'),
+      },
+      '\'' => {
+        plain = false;
+        word.get_or_insert_with(Word::default);
+        loop {
+          match chars.next() {
+            Some('\'') => break,
+            Some(c) => text.push(c),
+            None => return Err("unterminated single quote".to_string()),
+          }
+        }
+      }
+      '"' => {
+        plain = false;
+        word.get_or_insert_with(Word::default);
+        loop {
+          match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.peek() {
+              Some(&c) if c == '"' || c == '\\' || c == '$' => {
+                text.push(c);
+                chars.next();
+              }
+              _ => text.push('\\'),
+            },
+            Some('$') => match read_var(&mut chars)? {
+              Some(name) => {
+                flush_text!();
+                word.as_mut().unwrap().parts.push(WordPart::Var(name));
+              }
+              None => text.push('$'),
+            },
+            Some(c) => text.push(c),
+            None => return Err("unterminated double quote".to_string()),
+          }
+        }
+      }
+      '$' => match read_var(&mut chars)? {
+        Some(name) => {
+          plain = false;
+          flush_text!();
+          word
+            .get_or_insert_with(Word::default)
+            .parts
+            .push(WordPart::Var(name));
+        }
+        None => text.push('$'),
+      },
+      c => text.push(c),
+    }
+  }
+  flush_text!();
+  if let Some(w) = word {
+    tokens.push(Token::Word(w));
+  }
+  Ok(tokens)
+}
+
+/// Reads the name following a `$`, in either `NAME` or `{NAME}` form.
+fn read_var(
+  chars: &mut std::iter::Peekable<std::str::Chars>,
+) -> Result<Option<String>, String> {
+  let mut name = String::new();
+  if chars.peek() == Some(&'{') {
+    chars.next();
+    loop {
+      match chars.next() {
+        Some('}') if !name.is_empty() => return Ok(Some(name)),
+        Some(c) if is_name_char(c, name.is_empty()) => name.push(c),
+        _ => return Err("bad variable substitution".to_string()),
+      }
+    }
+  }
+  while let Some(&c) = chars.peek() {
+    if !is_name_char(c, name.is_empty()) {
+      break;
+    }
+    name.push(c);
+    chars.next();
+  }
+  Ok(if name.is_empty() { None } else { Some(name) })
+}
+
+fn parse(script: &str) -> Result<Script, String> {
+  let mut parsed = Script::default();
+  let mut connector = Connector::Always;
+  let mut pipeline: Pipeline = vec![];
+  let mut command = SimpleCommand::default();
+
+  let mut tokens = tokenize(script)?.into_iter().peekable();
+  // Skip empty statements at the start of the script.
+  while tokens.peek() == Some(&Token::Semicolon) {
+    tokens.next();
+  }
+  while let Some(token) = tokens.next() {
+    match token {
+      Token::Word(word) => match word.assign {
+        Some(name) if command.words.is_empty() => {
+          let value = Word {
+            assign: None,
+            parts: word.parts,
+          };
+          command.assignments.push((name, value));
+        }
+        // Assignments after the command name are plain arguments.
+        Some(name) => {
+          let mut parts = vec![WordPart::Text(format!("{}=", name))];
+          parts.extend(word.parts);
+          command.words.push(Word {
+            assign: None,
+            parts,
+          });
+        }
+        None => command.words.push(word),
+      },
+      operator => {
+        if command == SimpleCommand::default() {
+          return Err("syntax error: expected a command".to_string());
+        }
+        pipeline.push(std::mem::take(&mut command));
+        if operator == Token::Pipe {
+          continue;
+        }
+        parsed
+          .pipelines
+          .push((connector, std::mem::replace(&mut pipeline, vec![])));
+        connector = match operator {
+          Token::And => Connector::And,
+          Token::Or => Connector::Or,
+          _ => Connector::Always,
+        };
+        if operator == Token::Semicolon {
+          // Trailing and repeated separators are allowed.
+          while tokens.peek() == Some(&Token::Semicolon) {
+            tokens.next();
+          }
+        } else if tokens.peek().is_none() {
+          return Err("syntax error: expected a command".to_string());
+        }
+      }
+    }
+  }
+  if command != SimpleCommand::default() {
+    pipeline.push(command);
+  } else if !pipeline.is_empty() {
+    return Err("syntax error: expected a command".to_string());
+  }
+  if !pipeline.is_empty() {
+    parsed.pipelines.push((connector, pipeline));
+  }
+  Ok(parsed)
+}
+
+/// Wraps `arg` in single quotes if the shell would otherwise treat any of
+/// its characters specially.
+fn quote(arg: &str) -> String {
+  let is_plain = !arg.is_empty()
+    && arg.chars().all(|c| {
+      c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c) || !c.is_ascii()
+    });
+  if is_plain {
+    arg.to_string()
+  } else {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+  }
+}
+
+enum Input {
+  Inherit,
+  Bytes(Vec<u8>),
+  Child(ChildStdout),
+}
+
+struct Shell<'a> {
+  cwd: &'a Path,
+  /// Variables assigned by the script; they're exported to every command.
+  vars: HashMap<String, String>,
+}
+
+impl<'a> Shell<'a> {
+  fn expand(&self, word: &Word) -> String {
+    let mut out = String::new();
+    for part in &word.parts {
+      match part {
+        WordPart::Text(text) => out.push_str(text),
+        WordPart::Var(name) => {
+          if let Some(value) = self.vars.get(name) {
+            out.push_str(value);
+          } else if let Ok(value) = std::env::var(name) {
+            out.push_str(&value);
+          }
+        }
+      }
+    }
+    out
+  }
+
+  fn run(&mut self, script: &Script) -> i32 {
+    let mut status = 0;
+    for (connector, pipeline) in &script.pipelines {
+      let should_run = match connector {
+        Connector::Always => true,
+        Connector::And => status == 0,
+        Connector::Or => status != 0,
+      };
+      if should_run {
+        status = self.run_pipeline(pipeline);
+      }
+    }
+    status
+  }
+
+  fn run_pipeline(&mut self, pipeline: &[SimpleCommand]) -> i32 {
+    // A lone assignment sets a variable for the rest of the script.
+    if let [command] = pipeline {
+      if command.words.is_empty() {
+        for (name, value) in &command.assignments {
+          let value = self.expand(value);
+          self.vars.insert(name.clone(), value);
+        }
+        return 0;
+      }
+    }
+
+    let mut input = Input::Inherit;
+    let mut children: Vec<Child> = vec![];
+    let mut status = 0;
+    for (i, command) in pipeline.iter().enumerate() {
+      let is_last = i == pipeline.len() - 1;
+      let argv: Vec<String> =
+        command.words.iter().map(|w| self.expand(w)).collect();
+
+      if argv.is_empty() {
+        status = 0;
+        input = Input::Bytes(vec![]);
+        continue;
+      }
+
+      if argv[0] == "echo" {
+        let mut output = argv[1..].join(" ").into_bytes();
+        output.push(b'\n');
+        if is_last {
+          let stdout = std::io::stdout();
+          let mut stdout = stdout.lock();
+          let _ = stdout.write_all(&output).and_then(|_| stdout.flush());
+          // Drops the output of the previous command, if any, so that it
+          // doesn't block on a full pipe.
+          input = Input::Inherit;
+        } else {
+          input = Input::Bytes(output);
+        }
+        status = 0;
+        continue;
+      }
+
+      let program = if argv[0] == "deno" {
+        std::env::current_exe()
+          .map(|p| p.into_os_string())
+          .unwrap_or_else(|_| argv[0].clone().into())
+      } else {
+        argv[0].clone().into()
+      };
+      let mut cmd = Command::new(program);
+      cmd.args(&argv[1..]).current_dir(self.cwd).envs(&self.vars);
+      for (name, value) in &command.assignments {
+        cmd.env(name, self.expand(value));
+      }
+      let mut stdin_bytes = None;
+      match std::mem::replace(&mut input, Input::Inherit) {
+        Input::Inherit => {}
+        Input::Child(stdout) => {
+          cmd.stdin(Stdio::from(stdout));
+        }
+        Input::Bytes(bytes) => {
+          cmd.stdin(Stdio::piped());
+          stdin_bytes = Some(bytes);
+        }
+      }
+      if !is_last {
+        cmd.stdout(Stdio::piped());
+      }
+
+      let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+          eprintln!("{}: {}", argv[0], err);
+          // Like in other shells, the next command gets an empty stdin.
+          status = if err.kind() == std::io::ErrorKind::NotFound {
+            127
+          } else {
+            126
+          };
+          input = Input::Bytes(vec![]);
+          continue;
+        }
+      };
+      if let (Some(bytes), Some(mut stdin)) = (stdin_bytes, child.stdin.take())
+      {
+        std::thread::spawn(move || {
+          // The command may exit without reading its input.
+          let _ = stdin.write_all(&bytes);
+        });
+      }
+      if !is_last {
+        input = Input::Child(child.stdout.take().unwrap());
+      }
+      children.push(child);
+      if is_last {
+        status = -1;
+      }
+    }
+
+    let mut last_status = 1;
+    for child in children.iter_mut() {
+      last_status = match child.wait() {
+        Ok(exit_status) => exit_status.code().unwrap_or(1),
+        Err(_) => 1,
+      };
+    }
+    // The status of a pipeline is the one of its last command.
+    if status == -1 {
+      last_status
+    } else {
+      status
+    }
+  }
+}
+
+fn print_available_tasks(config_file: &ConfigFile) {
+  if config_file.tasks.is_empty() {
+    eprintln!(
+      "No tasks are defined in \"{}\".",
+      config_file.path.to_string_lossy()
+    );
+    return;
+  }
+  eprintln!("{}", colors::green("Available tasks:".to_string()));
+  for (name, command) in &config_file.tasks {
+    eprintln!("- {}", colors::bold(name.to_string()));
+    eprintln!("    {}", command);
+  }
+}
+
+/// Runs the task called `name` in the directory of the config file, with
+/// `args` appended to its command. Exits with the task's exit code if it
+/// fails.
+///
+/// Without a name the available tasks are listed.
+pub async fn run_task(
+  maybe_config_file: Option<ConfigFile>,
+  name: Option<String>,
+  args: Vec<String>,
+) -> Result<(), ErrBox> {
+  let config_file = maybe_config_file.ok_or_else(|| {
+    OpError::other(format!(
+      "No config file found. Tasks are defined in the \"tasks\" field of {}.",
+      crate::config_file::CONFIG_FILE_NAME
+    ))
+  })?;
+  let name = match name {
+    Some(name) => name,
+    None => {
+      print_available_tasks(&config_file);
+      return Ok(());
+    }
+  };
+  let command = match config_file.tasks.get(&name) {
+    Some(command) => command,
+    None => {
+      print_available_tasks(&config_file);
+      return Err(OpError::other(format!("Task not found: {}", name)).into());
+    }
+  };
+
+  let mut script_text = command.to_string();
+  for arg in &args {
+    script_text.push(' ');
+    script_text.push_str(&quote(arg));
+  }
+  let script = parse(&script_text).map_err(|msg| {
+    OpError::other(format!("Invalid task \"{}\": {}", name, msg))
+  })?;
+
+  info!(
+    "{} {} {}",
+    colors::green("Task".to_string()),
+    colors::bold(name),
+    script_text
+  );
+  let mut shell = Shell {
+    cwd: config_file.dir(),
+    vars: HashMap::new(),
+  };
+  let status = shell.run(&script);
+  if status != 0 {
+    std::process::exit(status);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn text(s: &str) -> Word {
+    Word {
+      assign: None,
+      parts: vec![WordPart::Text(s.to_string())],
+    }
+  }
+
+  fn command(words: &[&str]) -> SimpleCommand {
+    SimpleCommand {
+      assignments: vec![],
+      words: words.iter().map(|w| text(w)).collect(),
+    }
+  }
+
+  #[test]
+  fn parse_connectors_and_pipes() {
+    let script = parse("a 1 && b | c || d; e\n").unwrap();
+    assert_eq!(
+      script.pipelines,
+      vec![
+        (Connector::Always, vec![command(&["a", "1"])]),
+        (Connector::And, vec![command(&["b"]), command(&["c"])]),
+        (Connector::Or, vec![command(&["d"])]),
+        (Connector::Always, vec![command(&["e"])]),
+      ]
+    );
+  }
+
+  #[test]
+  fn parse_quotes_and_variables() {
+    let script = parse(r#"FOO=1 run 'a b' "c $HOME ${X}d" \$e x=y"#).unwrap();
+    let (_, pipeline) = &script.pipelines[0];
+    assert_eq!(
+      pipeline[0],
+      SimpleCommand {
+        assignments: vec![("FOO".to_string(), text("1"))],
+        words: vec![
+          text("run"),
+          text("a b"),
+          Word {
+            assign: None,
+            parts: vec![
+              WordPart::Text("c ".to_string()),
+              WordPart::Var("HOME".to_string()),
+              WordPart::Text(" ".to_string()),
+              WordPart::Var("X".to_string()),
+              WordPart::Text("d".to_string()),
+            ],
+          },
+          text("$e"),
+          Word {
+            assign: None,
+            parts: vec![
+              WordPart::Text("x=".to_string()),
+              WordPart::Text("y".to_string()),
+            ],
+          },
+        ],
+      }
+    );
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert!(parse("a &&").is_err());
+    assert!(parse("| a").is_err());
+    assert!(parse("a & b").is_err());
+    assert!(parse("a > out.txt").is_err());
+    assert!(parse("echo 'open").is_err());
+    assert!(parse("echo ${").is_err());
+    assert_eq!(parse("; # only a comment").unwrap(), Script::default());
+  }
+
+  #[test]
+  fn quote_args() {
+    assert_eq!(quote("--allow-net"), "--allow-net");
+    assert_eq!(quote("a b"), "'a b'");
+    assert_eq!(quote("it's"), "'it'\\''s'");
+    assert_eq!(quote(""), "''");
+    let script = parse(&format!("echo {}", quote("$HOME && it's"))).unwrap();
+    assert_eq!(
+      script.pipelines[0].1[0],
+      command(&["echo", "$HOME && it's"])
+    );
+  }
+
+  #[test]
+  fn expand_and_assign() {
+    let cwd = std::env::current_dir().unwrap();
+    let mut shell = Shell {
+      cwd: &cwd,
+      vars: HashMap::new(),
+    };
+    assert_eq!(shell.run(&parse("A=1; B=\"$A 2\"").unwrap()), 0);
+    assert_eq!(shell.vars.get("B"), Some(&"1 2".to_string()));
+    let word =
+      &parse("x${B}y$UNDEFINED_TASK_VAR").unwrap().pipelines[0].1[0].words[0];
+    assert_eq!(shell.expand(word), "x1 2y");
+  }
+
+  #[test]
+  fn exit_status() {
+    let cwd = std::env::current_dir().unwrap();
+    let mut shell = Shell {
+      cwd: &cwd,
+      vars: HashMap::new(),
+    };
+    let missing = "deno_task_missing_command";
+    assert_eq!(shell.run(&parse(missing).unwrap()), 127);
+    assert_eq!(
+      shell.run(&parse(&format!("{} || echo", missing)).unwrap()),
+      0
+    );
+    assert_eq!(
+      shell.run(&parse(&format!("echo && {}", missing)).unwrap()),
+      127
+    );
+    assert_eq!(
+      shell.run(&parse(&format!("{} | echo", missing)).unwrap()),
+      0
+    );
+  }
+}
@@ -0,0 +1,503 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! The formats `deno test` can report the results of the tests in. The tests
+//! are reported as the processes running them send their results, so that a
+//! single consistent stream comes out however many processes there are.
+use crate::colors;
+use crate::flags::TestReporter;
+use serde_json::Value;
+use std::io;
+use std::io::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TestStatus {
+  Passed,
+  Ignored,
+  Failed,
+}
+
+#[derive(Clone, Debug)]
+pub struct TestResult {
+  pub name: String,
+  pub status: TestStatus,
+  /// In milliseconds.
+  pub duration: u64,
+  pub error: Option<String>,
+  /// What the test printed.
+  pub output: String,
+}
+
+impl TestResult {
+  /// Reads a `testEnd` message of the JS test runner.
+  pub fn from_test_end(test_end: &Value, output: String) -> Self {
+    let status = match test_end["status"].as_str() {
+      Some("passed") => TestStatus::Passed,
+      Some("ignored") => TestStatus::Ignored,
+      _ => TestStatus::Failed,
+    };
+    Self {
+      name: test_end["name"].as_str().unwrap_or_default().to_string(),
+      status,
+      duration: test_end["duration"].as_u64().unwrap_or(0),
+      error: test_end["error"].as_str().map(String::from),
+      output,
+    }
+  }
+}
+
+#[derive(Default)]
+pub struct Summary {
+  pub filtered: u64,
+  pub ignored: u64,
+  pub measured: u64,
+  pub passed: u64,
+  pub failed: u64,
+  pub failures: Vec<(String, String)>,
+}
+
+impl Summary {
+  pub fn add(&mut self, result: &TestResult) {
+    match result.status {
+      TestStatus::Passed => self.passed += 1,
+      TestStatus::Ignored => self.ignored += 1,
+      TestStatus::Failed => self.failed += 1,
+    }
+    if let Some(error) = &result.error {
+      self.failures.push((result.name.clone(), error.clone()));
+    }
+  }
+
+  // The tests are counted as they end, since a process may be killed before
+  // its end message when failing fast.
+  pub fn add_end(&mut self, end: &Value) {
+    let count = |key: &str| end[key].as_u64().unwrap_or(0);
+    self.filtered += count("filtered");
+    self.measured += count("measured");
+  }
+}
+
+pub trait Reporter {
+  fn start(&mut self, module_count: usize, jobs: usize) -> io::Result<()>;
+  /// Reports what was printed outside of the tests.
+  fn output(&mut self, output: &str) -> io::Result<()>;
+  fn test_end(&mut self, result: &TestResult) -> io::Result<()>;
+  /// `duration` is in milliseconds.
+  fn end(&mut self, summary: &Summary, duration: u128) -> io::Result<()>;
+}
+
+pub fn create(kind: TestReporter, quiet: bool) -> Box<dyn Reporter> {
+  let out = io::stdout();
+  match kind {
+    TestReporter::Pretty => Box::new(PrettyReporter { out, quiet }),
+    TestReporter::Tap => Box::new(TapReporter { out, count: 0 }),
+    TestReporter::Junit => Box::new(JunitReporter::new(out)),
+    TestReporter::Json => Box::new(JsonReporter { out }),
+  }
+}
+
+/// The default, human readable, format.
+struct PrettyReporter<W: Write> {
+  out: W,
+  quiet: bool,
+}
+
+impl<W: Write> Reporter for PrettyReporter<W> {
+  fn start(&mut self, module_count: usize, jobs: usize) -> io::Result<()> {
+    if self.quiet {
+      return Ok(());
+    }
+    writeln!(
+      self.out,
+      "running {} test modules in {} jobs",
+      module_count, jobs
+    )
+  }
+
+  fn output(&mut self, output: &str) -> io::Result<()> {
+    if self.quiet {
+      return Ok(());
+    }
+    write!(self.out, "{}", output)
+  }
+
+  fn test_end(&mut self, result: &TestResult) -> io::Result<()> {
+    if self.quiet {
+      return Ok(());
+    }
+    let status = match result.status {
+      TestStatus::Passed => colors::green("ok".to_string()).to_string(),
+      TestStatus::Ignored => colors::yellow("ignored".to_string()).to_string(),
+      TestStatus::Failed => colors::red("FAILED".to_string()).to_string(),
+    };
+    let duration = colors::gray(format!("({}ms)", result.duration));
+    writeln!(self.out, "test {} ... {} {}", result.name, status, duration)?;
+    write!(self.out, "{}", result.output)
+  }
+
+  fn end(&mut self, summary: &Summary, duration: u128) -> io::Result<()> {
+    if self.quiet {
+      return Ok(());
+    }
+    let failed = colors::red("FAILED".to_string()).to_string();
+    let ok = colors::green("ok".to_string()).to_string();
+    if !summary.failures.is_empty() {
+      writeln!(self.out, "\nfailures:\n")?;
+      for (name, error) in &summary.failures {
+        writeln!(self.out, "{}\n{}\n", name, error)?;
+      }
+      writeln!(self.out, "failures:\n")?;
+      for (name, _) in &summary.failures {
+        writeln!(self.out, "\t{}", name)?;
+      }
+    }
+    writeln!(
+      self.out,
+      "\ntest result: {}. {} passed; {} failed; {} ignored; {} measured; \
+       {} filtered out {}\n",
+      if summary.failed > 0 { failed } else { ok },
+      summary.passed,
+      summary.failed,
+      summary.ignored,
+      summary.measured,
+      summary.filtered,
+      colors::gray(format!("({}ms)", duration)),
+    )
+  }
+}
+
+/// The Test Anything Protocol, version 13. The plan comes last, since the
+/// number of tests isn't known until they have all run.
+struct TapReporter<W: Write> {
+  out: W,
+  count: usize,
+}
+
+impl<W: Write> TapReporter<W> {
+  fn comment(&mut self, output: &str) -> io::Result<()> {
+    for line in colors::strip_ansi_codes(output).lines() {
+      writeln!(self.out, "# {}", line)?;
+    }
+    Ok(())
+  }
+}
+
+impl<W: Write> Reporter for TapReporter<W> {
+  fn start(&mut self, _module_count: usize, _jobs: usize) -> io::Result<()> {
+    writeln!(self.out, "TAP version 13")
+  }
+
+  fn output(&mut self, output: &str) -> io::Result<()> {
+    self.comment(output)
+  }
+
+  fn test_end(&mut self, result: &TestResult) -> io::Result<()> {
+    self.count += 1;
+    // A '#' in the name would start a directive.
+    let name = result.name.replace('#', "\\#");
+    match result.status {
+      TestStatus::Passed => writeln!(self.out, "ok {} - {}", self.count, name)?,
+      TestStatus::Ignored => {
+        writeln!(self.out, "ok {} - {} # SKIP", self.count, name)?
+      }
+      TestStatus::Failed => {
+        writeln!(self.out, "not ok {} - {}", self.count, name)?
+      }
+    }
+    if let Some(error) = &result.error {
+      writeln!(self.out, "  ---")?;
+      writeln!(self.out, "  message: |")?;
+      for line in colors::strip_ansi_codes(error).lines() {
+        writeln!(self.out, "    {}", line)?;
+      }
+      writeln!(self.out, "  ...")?;
+    }
+    self.comment(&result.output)
+  }
+
+  fn end(&mut self, summary: &Summary, duration: u128) -> io::Result<()> {
+    writeln!(self.out, "1..{}", self.count)?;
+    writeln!(self.out, "# pass {}", summary.passed)?;
+    writeln!(self.out, "# fail {}", summary.failed)?;
+    writeln!(self.out, "# skip {}", summary.ignored)?;
+    writeln!(self.out, "# duration {}ms", duration)
+  }
+}
+
+/// A JUnit XML report. It can only be written once all the tests have run,
+/// since the counts come first.
+struct JunitReporter<W: Write> {
+  out: W,
+  /// What was printed outside of the tests.
+  output: String,
+  results: Vec<TestResult>,
+}
+
+impl<W: Write> JunitReporter<W> {
+  fn new(out: W) -> Self {
+    Self {
+      out,
+      output: String::new(),
+      results: vec![],
+    }
+  }
+}
+
+fn escape_xml(s: &str) -> String {
+  let mut escaped = String::with_capacity(s.len());
+  for c in colors::strip_ansi_codes(s).chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      // Other control characters aren't allowed in XML 1.0.
+      '\t' | '\n' | '\r' => escaped.push(c),
+      c if c.is_control() => {}
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+fn seconds(ms: u128) -> String {
+  format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+impl<W: Write> Reporter for JunitReporter<W> {
+  fn start(&mut self, _module_count: usize, _jobs: usize) -> io::Result<()> {
+    Ok(())
+  }
+
+  fn output(&mut self, output: &str) -> io::Result<()> {
+    self.output.push_str(output);
+    Ok(())
+  }
+
+  fn test_end(&mut self, result: &TestResult) -> io::Result<()> {
+    self.results.push(result.clone());
+    Ok(())
+  }
+
+  fn end(&mut self, summary: &Summary, duration: u128) -> io::Result<()> {
+    let attrs = format!(
+      "name=\"deno test\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" \
+       time=\"{}\"",
+      self.results.len(),
+      summary.failed,
+      summary.ignored,
+      seconds(duration)
+    );
+    writeln!(self.out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(self.out, "<testsuites {}>", attrs)?;
+    writeln!(self.out, "  <testsuite {}>", attrs)?;
+    for result in &self.results {
+      writeln!(
+        self.out,
+        "    <testcase name=\"{}\" time=\"{}\">",
+        escape_xml(&result.name),
+        seconds(result.duration as u128)
+      )?;
+      match (&result.status, &result.error) {
+        (TestStatus::Ignored, _) => writeln!(self.out, "      <skipped/>")?,
+        (TestStatus::Failed, error) => {
+          let error = error.as_deref().unwrap_or_default();
+          let message = error.lines().next().unwrap_or_default();
+          writeln!(
+            self.out,
+            "      <failure message=\"{}\">{}</failure>",
+            escape_xml(message),
+            escape_xml(error)
+          )?;
+        }
+        _ => {}
+      }
+      if !result.output.is_empty() {
+        writeln!(
+          self.out,
+          "      <system-out>{}</system-out>",
+          escape_xml(&result.output)
+        )?;
+      }
+      writeln!(self.out, "    </testcase>")?;
+    }
+    if !self.output.is_empty() {
+      writeln!(
+        self.out,
+        "    <system-out>{}</system-out>",
+        escape_xml(&self.output)
+      )?;
+    }
+    writeln!(self.out, "  </testsuite>")?;
+    writeln!(self.out, "</testsuites>")
+  }
+}
+
+/// One JSON object per line, for each event.
+struct JsonReporter<W: Write> {
+  out: W,
+}
+
+impl<W: Write> Reporter for JsonReporter<W> {
+  fn start(&mut self, module_count: usize, jobs: usize) -> io::Result<()> {
+    let event =
+      json!({ "type": "start", "modules": module_count, "jobs": jobs });
+    writeln!(self.out, "{}", event)
+  }
+
+  fn output(&mut self, output: &str) -> io::Result<()> {
+    let event = json!({ "type": "output", "output": output });
+    writeln!(self.out, "{}", event)
+  }
+
+  fn test_end(&mut self, result: &TestResult) -> io::Result<()> {
+    let status = match result.status {
+      TestStatus::Passed => "passed",
+      TestStatus::Ignored => "ignored",
+      TestStatus::Failed => "failed",
+    };
+    let event = json!({
+      "type": "test",
+      "name": result.name,
+      "status": status,
+      "duration": result.duration,
+      "error": result.error,
+      "output": result.output,
+    });
+    writeln!(self.out, "{}", event)
+  }
+
+  fn end(&mut self, summary: &Summary, duration: u128) -> io::Result<()> {
+    let event = json!({
+      "type": "end",
+      "passed": summary.passed,
+      "failed": summary.failed,
+      "ignored": summary.ignored,
+      "measured": summary.measured,
+      "filtered": summary.filtered,
+      "duration": duration as u64,
+    });
+    writeln!(self.out, "{}", event)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn results() -> Vec<TestResult> {
+    vec![
+      TestResult {
+        name: "a".to_string(),
+        status: TestStatus::Passed,
+        duration: 3,
+        error: None,
+        output: "printed by a\n".to_string(),
+      },
+      TestResult {
+        name: "b <#1>".to_string(),
+        status: TestStatus::Failed,
+        duration: 1500,
+        error: Some("AssertionError: 1 != 2\n    at b".to_string()),
+        output: String::new(),
+      },
+      TestResult {
+        name: "c".to_string(),
+        status: TestStatus::Ignored,
+        duration: 0,
+        error: None,
+        output: String::new(),
+      },
+    ]
+  }
+
+  fn report<R: Reporter>(reporter: &mut R) {
+    let mut summary = Summary::default();
+    reporter.start(1, 1).unwrap();
+    reporter.output("before\n").unwrap();
+    for result in results() {
+      summary.add(&result);
+      reporter.test_end(&result).unwrap();
+    }
+    reporter.end(&summary, 1503).unwrap();
+  }
+
+  #[test]
+  fn tap() {
+    let mut reporter = TapReporter {
+      out: vec![],
+      count: 0,
+    };
+    report(&mut reporter);
+    assert_eq!(
+      String::from_utf8(reporter.out).unwrap(),
+      "TAP version 13\n\
+       # before\n\
+       ok 1 - a\n\
+       # printed by a\n\
+       not ok 2 - b <\\#1>\n  \
+         ---\n  \
+         message: |\n    \
+           AssertionError: 1 != 2\n        \
+           at b\n  \
+         ...\n\
+       ok 3 - c # SKIP\n\
+       1..3\n\
+       # pass 1\n\
+       # fail 1\n\
+       # skip 1\n\
+       # duration 1503ms\n"
+    );
+  }
+
+  #[test]
+  fn junit() {
+    let mut reporter = JunitReporter::new(vec![]);
+    report(&mut reporter);
+    let out = String::from_utf8(reporter.out).unwrap();
+    assert!(out.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    assert!(out.contains(
+      "<testsuite name=\"deno test\" tests=\"3\" failures=\"1\" \
+       skipped=\"1\" time=\"1.503\">"
+    ));
+    assert!(out.contains(
+      "<testcase name=\"a\" time=\"0.003\">\n      \
+       <system-out>printed by a\n</system-out>"
+    ));
+    assert!(out.contains(
+      "<testcase name=\"b &lt;#1&gt;\" time=\"1.500\">\n      \
+       <failure message=\"AssertionError: 1 != 2\">"
+    ));
+    assert!(
+      out.contains("<testcase name=\"c\" time=\"0.000\">\n      <skipped/>")
+    );
+    assert!(out.contains("<system-out>before\n</system-out>\n  </testsuite>"));
+  }
+
+  #[test]
+  fn json() {
+    let mut reporter = JsonReporter { out: vec![] };
+    report(&mut reporter);
+    let out = String::from_utf8(reporter.out).unwrap();
+    let events: Vec<Value> = out
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect();
+    assert_eq!(events.len(), 6);
+    assert_eq!(events[0]["type"], "start");
+    assert_eq!(events[1]["output"], "before\n");
+    assert_eq!(events[3]["status"], "failed");
+    assert_eq!(events[3]["duration"], 1500);
+    assert_eq!(events[5]["type"], "end");
+    assert_eq!(events[5]["passed"], 1);
+    assert_eq!(events[5]["ignored"], 1);
+  }
+
+  #[test]
+  fn escape() {
+    assert_eq!(
+      escape_xml("\u{1b}[31m<a & 'b'>\u{0}\"\u{1b}[39m"),
+      "&lt;a &amp; &apos;b&apos;&gt;&quot;"
+    );
+  }
+}
@@ -2,12 +2,30 @@
 
 use crate::fs as deno_fs;
 use crate::installer::is_remote_url;
+use crate::op_error::OpError;
+use crate::test_reporter::Reporter;
+use crate::test_reporter::Summary;
+use crate::test_reporter::TestResult;
+use crate::test_reporter::TestStatus;
 use deno_core::ErrBox;
+use serde_json::Value;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::time::Instant;
 use url::Url;
 
-fn is_supported(p: &Path) -> bool {
+/// Set on the processes spawned by `deno test --jobs`, as `<index>/<count>`.
+pub const SHARD_ENV: &str = "DENO_TEST_SHARD";
+/// Precedes the test messages written by the spawned processes on stdout.
+const RECORD_SEPARATOR: char = '\x1e';
+
+pub fn is_supported(p: &Path) -> bool {
   use std::path::Component;
   if let Some(Component::Normal(basename_os_str)) = p.components().next_back() {
     let basename = basename_os_str.to_string_lossy();
@@ -31,6 +49,16 @@ fn is_supported(p: &Path) -> bool {
 pub fn prepare_test_modules_urls(
   include: Vec<String>,
   root_path: &PathBuf,
+) -> Result<Vec<Url>, ErrBox> {
+  prepare_modules_urls(include, root_path, is_supported)
+}
+
+/// Resolves the given files and URLs. Directories are expanded to the files
+/// they contain for which `is_supported` is true.
+pub fn prepare_modules_urls(
+  include: Vec<String>,
+  root_path: &PathBuf,
+  is_supported: fn(&Path) -> bool,
 ) -> Result<Vec<Url>, ErrBox> {
   let (include_paths, include_urls): (Vec<String>, Vec<String>) =
     include.into_iter().partition(|n| !is_remote_url(n));
@@ -60,11 +88,14 @@ pub fn prepare_test_modules_urls(
   Ok(prepared)
 }
 
+/// Renders the module running the tests of `modules`. With `report_to_json`,
+/// the tests are reported to the parent process, as by a shard.
 pub fn render_test_file(
   modules: Vec<Url>,
   fail_fast: bool,
   quiet: bool,
   filter: Option<String>,
+  report_to_json: bool,
 ) -> String {
   let mut test_file = "".to_string();
 
@@ -72,11 +103,17 @@ pub fn render_test_file(
     test_file.push_str(&format!("import \"{}\";\n", module.to_string()));
   }
 
-  let options = if let Some(filter) = filter {
-    json!({ "failFast": fail_fast, "reportToConsole": !quiet, "disableLog": quiet, "filter": filter })
-  } else {
-    json!({ "failFast": fail_fast, "reportToConsole": !quiet, "disableLog": quiet })
-  };
+  let mut options = json!({
+    "failFast": fail_fast,
+    "reportToConsole": !quiet && !report_to_json,
+    "disableLog": quiet,
+  });
+  if let Some(filter) = filter {
+    options["filter"] = json!(filter);
+  }
+  if report_to_json {
+    options["reportToJson"] = json!(true);
+  }
 
   let run_tests_cmd = format!(
     "// @ts-ignore\nDeno[Deno.internal].runTests({});\n",
@@ -87,6 +124,213 @@ pub fn render_test_file(
   test_file
 }
 
+/// The part of the test modules run by one of the processes of
+/// `deno test --jobs`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shard {
+  index: usize,
+  count: usize,
+}
+
+impl Shard {
+  /// Returns the shard to run if this process was spawned by `deno test
+  /// --jobs`.
+  pub fn from_env() -> Option<Shard> {
+    Shard::parse(&std::env::var(SHARD_ENV).ok()?)
+  }
+
+  fn parse(s: &str) -> Option<Shard> {
+    let mut parts = s.splitn(2, '/');
+    let index = parts.next()?.parse().ok()?;
+    let count = parts.next()?.parse().ok()?;
+    if index < count {
+      Some(Shard { index, count })
+    } else {
+      None
+    }
+  }
+
+  /// Inserts the index of the shard before the extension of a file that
+  /// every process writes, like a CPU profile, so that the shards don't
+  /// overwrite each other's.
+  pub fn output_path(&self, path: &Path) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", self.index));
+    if let Some(extension) = path.extension() {
+      name.push(".");
+      name.push(extension);
+    }
+    path.with_file_name(name)
+  }
+
+  /// The name of the module generated to run the tests of the shard. Each
+  /// shard has its own, since the shards compile theirs at the same time.
+  pub fn test_file_name(&self) -> String {
+    format!(".deno.test.{}.ts", self.index)
+  }
+
+  /// Picks one module every `count`. They are sorted first, so that all the
+  /// processes agree on their order.
+  pub fn select(&self, mut modules: Vec<Url>) -> Vec<Url> {
+    modules.sort();
+    modules
+      .into_iter()
+      .enumerate()
+      .filter(|(i, _)| i % self.count == self.index)
+      .map(|(_, module)| module)
+      .collect()
+  }
+}
+
+/// Where a file given on the command line is written by this process: the
+/// path itself, or its variant for the shard when run by `deno test --jobs`.
+pub fn shard_output_path(path: &Path) -> PathBuf {
+  match Shard::from_env() {
+    Some(shard) => shard.output_path(path),
+    None => path.to_path_buf(),
+  }
+}
+
+enum ShardEvent {
+  Output(usize, String),
+  Message(usize, Value),
+  Closed(usize),
+}
+
+// Splits the stdout or the stderr of a shard into the test messages and the
+// output of the tests. Only stdout carries messages.
+fn read_shard(
+  index: usize,
+  stream: impl Read,
+  sender: mpsc::Sender<ShardEvent>,
+) {
+  let mut reader = BufReader::new(stream);
+  let mut buf = vec![];
+  while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+    if n == 0 {
+      break;
+    }
+    let line = String::from_utf8_lossy(&buf).into_owned();
+    buf.clear();
+    let event = match line.find(RECORD_SEPARATOR) {
+      Some(pos) => {
+        if pos > 0 {
+          let output = line[..pos].to_string();
+          let _ = sender.send(ShardEvent::Output(index, output));
+        }
+        match serde_json::from_str(&line[pos + 1..]) {
+          Ok(message) => ShardEvent::Message(index, message),
+          Err(_) => continue,
+        }
+      }
+      None => ShardEvent::Output(index, line),
+    };
+    let _ = sender.send(event);
+  }
+  let _ = sender.send(ShardEvent::Closed(index));
+}
+
+/// Runs the test modules in `jobs` processes, which run deno again with the
+/// same arguments and `SHARD_ENV` set. The results of their tests are
+/// reported here as they come, each followed by what the test printed on
+/// stdout or stderr, so that the output of tests running at the same time
+/// doesn't get mixed. Fails once the results are reported if a test failed.
+pub fn run_in_parallel(
+  module_count: usize,
+  jobs: usize,
+  fail_fast: bool,
+  reporter: &mut dyn Reporter,
+) -> Result<(), ErrBox> {
+  let exe = std::env::current_exe()?;
+  let (sender, receiver) = mpsc::channel();
+  let mut children = vec![];
+  for index in 0..jobs {
+    let mut child = Command::new(&exe)
+      .args(std::env::args_os().skip(1))
+      .env(SHARD_ENV, format!("{}/{}", index, jobs))
+      .stdin(Stdio::null())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()?;
+    let stdout = child.stdout.take().unwrap();
+    let stdout_sender = sender.clone();
+    std::thread::spawn(move || read_shard(index, stdout, stdout_sender));
+    let stderr = child.stderr.take().unwrap();
+    let stderr_sender = sender.clone();
+    std::thread::spawn(move || read_shard(index, stderr, stderr_sender));
+    children.push(child);
+  }
+  drop(sender);
+
+  reporter.start(module_count, jobs)?;
+  let start = Instant::now();
+  let mut summary = Summary::default();
+  // The output of the test each shard is running, if any.
+  let mut outputs: Vec<Option<String>> = vec![None; jobs];
+  let mut ended = vec![false; jobs];
+  // Both stdout and stderr have to be read till the end.
+  let mut open_streams = vec![2; jobs];
+  let mut killed = false;
+  for event in receiver {
+    match event {
+      ShardEvent::Output(index, output) => match outputs[index].as_mut() {
+        Some(buffered) => buffered.push_str(&output),
+        None => reporter.output(&output)?,
+      },
+      ShardEvent::Message(index, message) => {
+        if message.get("testStart").is_some() {
+          outputs[index] = Some(String::new());
+        } else if let Some(test_end) = message.get("testEnd") {
+          let output = outputs[index].take().unwrap_or_default();
+          let result = TestResult::from_test_end(test_end, output);
+          summary.add(&result);
+          reporter.test_end(&result)?;
+          if result.error.is_some() && fail_fast && !killed {
+            killed = true;
+            for child in &mut children {
+              let _ = child.kill();
+            }
+          }
+        } else if let Some(end) = message.get("end") {
+          summary.add_end(end);
+          ended[index] = true;
+        }
+      }
+      ShardEvent::Closed(index) => {
+        open_streams[index] -= 1;
+        if open_streams[index] > 0 {
+          continue;
+        }
+        let status = children[index].wait()?;
+        if !ended[index] && !killed {
+          let result = TestResult {
+            name: format!("test modules of job {}", index),
+            status: TestStatus::Failed,
+            duration: 0,
+            error: Some(format!("The test process exited with {}", status)),
+            output: outputs[index].take().unwrap_or_default(),
+          };
+          summary.add(&result);
+          reporter.test_end(&result)?;
+        }
+      }
+    }
+  }
+
+  reporter.end(&summary, start.elapsed().as_millis())?;
+  if summary.failed > 0 {
+    return Err(
+      OpError::other(format!(
+        "{} {} failed",
+        summary.failed,
+        if summary.failed == 1 { "test" } else { "tests" }
+      ))
+      .into(),
+    );
+  }
+  Ok(())
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -143,6 +387,52 @@ mod tests {
     assert!(!is_supported(Path::new("NotAtest.ts")));
   }
 
+  #[test]
+  fn shard_select() {
+    assert_eq!(Shard::parse("2/2"), None);
+    assert_eq!(Shard::parse("2"), None);
+    let urls: Vec<Url> = ["c", "a", "d", "b", "e"]
+      .iter()
+      .map(|name| Url::parse(&format!("file:///{}_test.ts", name)).unwrap())
+      .collect();
+    let first = Shard::parse("0/2").unwrap().select(urls.clone());
+    let second = Shard::parse("1/2").unwrap().select(urls);
+    let names = |urls: Vec<Url>| -> Vec<String> {
+      urls
+        .iter()
+        .map(|url| url.path()[1..2].to_string())
+        .collect()
+    };
+    assert_eq!(names(first), vec!["a", "c", "e"]);
+    assert_eq!(names(second), vec!["b", "d"]);
+  }
+
+  #[test]
+  fn shard_test_file_name() {
+    let first = Shard::parse("0/2").unwrap();
+    let second = Shard::parse("1/2").unwrap();
+    assert_eq!(first.test_file_name(), ".deno.test.0.ts");
+    assert_eq!(second.test_file_name(), ".deno.test.1.ts");
+  }
+
+  #[test]
+  fn shard_output_paths() {
+    let shard = Shard::parse("1/2").unwrap();
+    assert_eq!(
+      shard.output_path(Path::new("out/prof.cpuprofile")),
+      Path::new("out/prof.1.cpuprofile")
+    );
+    assert_eq!(shard.output_path(Path::new("trace")), Path::new("trace.1"));
+  }
+
+  #[test]
+  fn render_test_file_for_shard() {
+    let modules = vec![Url::parse("file:///a_test.ts").unwrap()];
+    let test_file = render_test_file(modules, false, false, None, true);
+    assert!(test_file.contains("\"reportToJson\":true"));
+    assert!(test_file.contains("\"reportToConsole\":false"));
+  }
+
   #[test]
   fn supports_dirs() {
     let root = test_util::root_path().join("std").join("http");
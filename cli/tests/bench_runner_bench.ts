@@ -0,0 +1,9 @@
+Deno.bench("parse", function (): void {
+  JSON.parse('{"hello":"world"}');
+});
+
+Deno.bench({
+  name: "ignored",
+  ignore: true,
+  fn(): void {},
+});
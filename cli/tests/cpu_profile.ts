@@ -0,0 +1,17 @@
+interface Point {
+  x: number;
+  y: number;
+}
+
+function busy(point: Point): number {
+  let sum = 0;
+  for (let i = 0; i < 1e6; i++) {
+    sum += (point.x * i) % (point.y + i);
+  }
+  return sum;
+}
+
+const start = Date.now();
+while (Date.now() - start < 200) {
+  busy({ x: 3, y: 7 });
+}
@@ -0,0 +1,7 @@
+/** Adds two numbers. */
+export function add(a: number, b: number): number {
+  return a + b;
+}
+
+/** Default greeting. */
+export const greeting = "hello";
@@ -0,0 +1,4 @@
+for (let i = 0; i < 2; i++) {
+  const res = await fetch("http://localhost:4545/etag_script.ts");
+  console.log(res.status, await res.text());
+}
@@ -0,0 +1,8 @@
+// The timeout option of fetch() is unstable.
+try {
+  await fetch("http://localhost:4545/cli/tests/fixture.json", {
+    timeout: 1000,
+  });
+} catch (err) {
+  console.log(err instanceof Error, err.message);
+}
@@ -0,0 +1,5 @@
+// Timers the frozen clock never reaches don't keep the program alive.
+setTimeout(() => console.log("not reached"), 1000);
+setInterval(() => console.log("not reached either"), 500);
+setTimeout(() => console.log("due"), 0);
+console.log("done");
@@ -0,0 +1,45 @@
+import {
+  assert,
+  assertEquals,
+  assertThrowsAsync,
+} from "../../std/testing/asserts.ts";
+
+Deno.test("dateFollowsFrozenClock", async function (): Promise<void> {
+  const start = Date.now();
+  assertEquals(new Date().getTime(), start);
+  assertEquals(new Date(0).getTime(), 0);
+  assert(new Date() instanceof Date);
+  await Deno.advanceTime(1500);
+  assertEquals(Date.now(), start + 1500);
+});
+
+Deno.test("timersFireInOrder", async function (): Promise<void> {
+  const start = performance.now();
+  const fired: Array<[string, number]> = [];
+  const at = (name: string) => (): void => {
+    fired.push([name, performance.now() - start]);
+  };
+  setTimeout(at("b"), 60000);
+  setTimeout(at("a"), 1000);
+  const id = setInterval(at("interval"), 25000);
+  await Deno.advanceTime(59999);
+  assertEquals(fired, [
+    ["a", 1000],
+    ["interval", 25000],
+    ["interval", 50000],
+  ]);
+  await Deno.advanceTime(1);
+  assertEquals(fired.length, 4);
+  assertEquals(fired[3], ["b", 60000]);
+  clearInterval(id);
+});
+
+Deno.test("zeroDelayTimersFire", async function (): Promise<void> {
+  const start = performance.now();
+  await new Promise((resolve) => setTimeout(resolve, 0));
+  assertEquals(performance.now(), start);
+});
+
+Deno.test("advanceTimeRejectsNegative", async function (): Promise<void> {
+  await assertThrowsAsync(() => Deno.advanceTime(-1), TypeError);
+});
@@ -0,0 +1,3 @@
+// Original source of inline_source_map.js.
+
+throw new Error("boom");
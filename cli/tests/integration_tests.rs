@@ -83,6 +83,74 @@ fn no_color() {
   assert_eq!("noColor false", util::strip_ansi_codes(stdout_str));
 }
 
+#[test]
+fn run_watch() {
+  let t = TempDir::new().expect("tempdir fail");
+  let main_file = t.path().join("main.js");
+  let dep_file = t.path().join("dep.js");
+  std::fs::write(
+    &main_file,
+    "import { value } from './dep.js';\nconsole.log(value);\n",
+  )
+  .expect("error writing file");
+  std::fs::write(&dep_file, "export const value = 'first';\n")
+    .expect("error writing file");
+
+  let mut child = util::deno_cmd()
+    .current_dir(t.path())
+    .arg("run")
+    .arg("--watch")
+    .arg("main.js")
+    .env("NO_COLOR", "1")
+    .stdout(std::process::Stdio::piped())
+    .stderr(std::process::Stdio::piped())
+    .spawn()
+    .expect("failed to spawn script");
+  let stdout = child.stdout.as_mut().unwrap();
+  let mut stdout_lines =
+    std::io::BufReader::new(stdout).lines().map(|r| r.unwrap());
+  assert_eq!(stdout_lines.next().unwrap(), "first");
+
+  // Changing a dependency restarts the program.
+  std::fs::write(&dep_file, "export const value = 'second';\n")
+    .expect("error writing file");
+  assert_eq!(stdout_lines.next().unwrap(), "second");
+
+  child.kill().unwrap();
+  child.wait().unwrap();
+}
+
+#[test]
+fn run_watch_busy_program() {
+  let t = TempDir::new().expect("tempdir fail");
+  let main_file = t.path().join("main.js");
+  std::fs::write(&main_file, "console.log('first');\nwhile (true) {}\n")
+    .expect("error writing file");
+
+  let mut child = util::deno_cmd()
+    .current_dir(t.path())
+    .arg("run")
+    .arg("--watch")
+    .arg("main.js")
+    .env("NO_COLOR", "1")
+    .stdout(std::process::Stdio::piped())
+    .stderr(std::process::Stdio::piped())
+    .spawn()
+    .expect("failed to spawn script");
+  let stdout = child.stdout.as_mut().unwrap();
+  let mut stdout_lines =
+    std::io::BufReader::new(stdout).lines().map(|r| r.unwrap());
+  assert_eq!(stdout_lines.next().unwrap(), "first");
+
+  // A program that never yields is terminated and restarted too.
+  std::fs::write(&main_file, "console.log('second');\n")
+    .expect("error writing file");
+  assert_eq!(stdout_lines.next().unwrap(), "second");
+
+  child.kill().unwrap();
+  child.wait().unwrap();
+}
+
 // TODO re-enable. This hangs on macOS
 // https://github.com/denoland/deno/issues/4262
 #[cfg(unix)]
@@ -206,6 +274,81 @@ fn cache_test() {
   drop(g);
 }
 
+#[test]
+fn npm_test() {
+  let g = util::http_server();
+  let deno_dir = TempDir::new().expect("tempdir fail");
+  let registry = "http://localhost:4545/cli/tests/npm/registry/";
+  let run = |args: &[&str]| {
+    util::deno_cmd()
+      .env("DENO_DIR", deno_dir.path())
+      .env("NPM_CONFIG_REGISTRY", registry)
+      .current_dir(util::tests_path())
+      .arg("run")
+      .args(args)
+      .output()
+      .expect("Failed to spawn script")
+  };
+
+  let output = run(&["--allow-net=localhost:4545", "npm_import.js"]);
+  assert!(output.status.success());
+  assert_eq!(
+    std::str::from_utf8(&output.stdout).unwrap(),
+    "Hello from npm\n"
+  );
+  let package_dir = deno_dir
+    .path()
+    .join("npm/localhost_PORT4545/deno-test-pkg@1.0.0");
+  assert!(package_dir.join("lib/greeting.js").is_file());
+
+  // The package is loaded from the cache, without the network.
+  let output = run(&["--cached-only", "npm_import.js"]);
+  assert!(output.status.success());
+  assert_eq!(
+    std::str::from_utf8(&output.stdout).unwrap(),
+    "Hello from npm\n"
+  );
+
+  let output = run(&["--allow-net=localhost:4545", "npm_missing_version.js"]);
+  assert!(!output.status.success());
+  let stderr = std::str::from_utf8(&output.stderr).unwrap();
+  assert!(stderr.contains("npm package \"deno-test-pkg\" has no version"));
+  drop(g);
+}
+
+#[test]
+fn fetch_cache_revalidate() {
+  let g = util::http_server();
+  let deno_dir = TempDir::new().expect("tempdir fail");
+  let output = util::deno_cmd()
+    .env("DENO_DIR", deno_dir.path())
+    .current_dir(util::tests_path())
+    .arg("run")
+    .arg("--unstable")
+    .arg("--fetch-cache")
+    .arg("--allow-net=localhost:4545")
+    .arg("--log=cache")
+    .arg("fetch_cache_revalidate.ts")
+    .output()
+    .expect("Failed to spawn script");
+  assert!(output.status.success());
+  assert_eq!(
+    std::str::from_utf8(&output.stdout).unwrap(),
+    "200 console.log('etag')\n200 console.log('etag')\n"
+  );
+  // The first response is stored, and the second one is revalidated with its
+  // ETag and answered from the cache.
+  let stderr = std::str::from_utf8(&output.stderr).unwrap();
+  let url = "http://localhost:4545/etag_script.ts";
+  let store = stderr.find(&format!("Fetch cache store {}", url)).unwrap();
+  let revalidated = stderr
+    .find(&format!("Fetch cache revalidated {}", url))
+    .unwrap();
+  assert!(store < revalidated);
+  assert!(deno_dir.path().join("fetch").is_dir());
+  drop(g);
+}
+
 #[test]
 fn fmt_test() {
   let t = TempDir::new().expect("tempdir fail");
@@ -240,6 +383,139 @@ fn fmt_test() {
   assert_eq!(expected, actual);
 }
 
+#[test]
+fn config_file_test() {
+  let t = TempDir::new().expect("tempdir fail");
+  std::fs::write(
+    t.path().join("deno.json"),
+    r#"{
+  "compilerOptions": { "strict": true },
+  "lint": { "exclude": ["gen/"], "rules": { "exclude": ["no-var"] } }
+}
+"#,
+  )
+  .unwrap();
+  std::fs::create_dir(t.path().join("gen")).unwrap();
+  std::fs::write(t.path().join("gen").join("out.js"), "debugger;\n").unwrap();
+  std::fs::write(
+    t.path().join("main.ts"),
+    "var f = function (a) {\n  return a;\n};\nconsole.log(f(1));\n",
+  )
+  .unwrap();
+
+  // `gen/` and `no-var` are excluded by the config file.
+  let status = util::deno_cmd()
+    .current_dir(t.path())
+    .arg("lint")
+    .spawn()
+    .expect("Failed to spawn script")
+    .wait()
+    .expect("Failed to wait for child process");
+  assert!(status.success());
+
+  // `strict` makes the implicit `any` of `a` an error.
+  let output = util::deno_cmd()
+    .current_dir(t.path())
+    .env("DENO_DIR", t.path().join("deno_dir"))
+    .arg("run")
+    .arg("main.ts")
+    .stderr(std::process::Stdio::piped())
+    .output()
+    .expect("Failed to spawn script");
+  assert!(!output.status.success());
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.contains("TS7006"));
+}
+
+#[test]
+fn task_test() {
+  let t = TempDir::new().expect("tempdir fail");
+  std::fs::write(
+    t.path().join("deno.json"),
+    r#"{
+  "tasks": {
+    "hello": "GREETING=hi; echo $GREETING | deno eval \"Deno.stdin.read(new Uint8Array(8)).then(() => console.log('piped'))\" && deno eval \"console.log(Deno.args)\" --",
+    "fail": "deno eval \"Deno.exit(3)\" || deno eval \"Deno.exit(4)\""
+  }
+}
+"#,
+  )
+  .unwrap();
+  let nested = t.path().join("nested");
+  std::fs::create_dir(&nested).unwrap();
+
+  let output = util::deno_cmd()
+    .current_dir(&nested)
+    .arg("task")
+    .arg("hello")
+    .arg("a b")
+    .arg("c")
+    .stdout(std::process::Stdio::piped())
+    .output()
+    .expect("Failed to spawn script");
+  assert!(output.status.success());
+  let stdout = String::from_utf8(output.stdout).unwrap();
+  assert_eq!(stdout, "piped\n[ \"a b\", \"c\" ]\n");
+
+  let status = util::deno_cmd()
+    .current_dir(&nested)
+    .arg("task")
+    .arg("fail")
+    .spawn()
+    .expect("Failed to spawn script")
+    .wait()
+    .expect("Failed to wait for child process");
+  assert_eq!(status.code(), Some(4));
+}
+
+#[test]
+fn fmt_json_and_markdown_test() {
+  let t = TempDir::new().expect("tempdir fail");
+  for ext in &["json", "md"] {
+    let original =
+      util::root_path().join(format!("cli/tests/badly_formatted.{}", ext));
+    std::fs::copy(&original, t.path().join(format!("badly_formatted.{}", ext)))
+      .expect("Failed to copy file");
+  }
+  let glob = t.path().join("badly_formatted.*");
+  let fmt = |args: &[&str]| {
+    util::deno_cmd()
+      .current_dir(util::root_path())
+      .arg("fmt")
+      .args(args)
+      .arg(glob.to_str().unwrap())
+      .spawn()
+      .expect("Failed to spawn script")
+      .wait()
+      .expect("Failed to wait for child process")
+  };
+
+  // Globs only match JavaScript and TypeScript files by default.
+  assert!(fmt(&[]).success());
+  for ext in &["json", "md"] {
+    let original =
+      util::root_path().join(format!("cli/tests/badly_formatted.{}", ext));
+    let expected = std::fs::read_to_string(original).unwrap();
+    let actual = std::fs::read_to_string(
+      t.path().join(format!("badly_formatted.{}", ext)),
+    )
+    .unwrap();
+    assert_eq!(expected, actual);
+  }
+
+  assert!(fmt(&["--ext=json,md"]).success());
+  for ext in &["json", "md"] {
+    let fixed = util::root_path()
+      .join(format!("cli/tests/badly_formatted_fixed.{}", ext));
+    let expected = std::fs::read_to_string(fixed).unwrap();
+    let actual = std::fs::read_to_string(
+      t.path().join(format!("badly_formatted.{}", ext)),
+    )
+    .unwrap();
+    assert_eq!(expected, actual);
+  }
+}
+
 #[test]
 fn fmt_stdin_error() {
   use std::io::Write;
@@ -277,6 +553,8 @@ fn upgrade_in_tmpdir() {
   let status = Command::new(&exe_path)
     .arg("upgrade")
     .arg("--force")
+    // The releases don't publish checksums of their archives.
+    .arg("--allow-unverified")
     .spawn()
     .unwrap()
     .wait()
@@ -301,6 +579,8 @@ fn upgrade_with_version_in_tmpdir() {
   let status = Command::new(&exe_path)
     .arg("upgrade")
     .arg("--force")
+    // The releases don't publish checksums of their archives.
+    .arg("--allow-unverified")
     .arg("--version")
     .arg("0.42.0")
     .spawn()
@@ -770,6 +1050,125 @@ fn repl_test_eof() {
   assert!(err.is_empty());
 }
 
+#[test]
+fn repl_test_history() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let history_file = temp_dir.path().join("history.txt");
+  let envs = Some(vec![
+    ("NO_COLOR".to_owned(), "1".to_owned()),
+    (
+      "DENO_REPL_HISTORY".to_owned(),
+      history_file.to_str().unwrap().to_owned(),
+    ),
+  ]);
+  util::run_and_collect_output(
+    true,
+    "repl",
+    Some(vec!["1 + 1", "1 + 1", "2 + 2"]),
+    envs.clone(),
+    false,
+  );
+  util::run_and_collect_output(true, "repl", Some(vec!["3 + 3"]), envs, false);
+
+  // Consecutive duplicates are only saved once, and the entries of the
+  // first session were loaded by the second one.
+  let history = std::fs::read_to_string(&history_file).unwrap();
+  let entries: Vec<&str> =
+    history.lines().filter(|l| !l.starts_with('#')).collect();
+  assert_eq!(entries, vec!["1 + 1", "2 + 2", "3 + 3"]);
+}
+
+#[test]
+fn repl_test_commands() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let session_file = temp_dir.path().join("session.js");
+  let session_file = session_file.to_str().unwrap();
+  let save = format!(".save {}", session_file);
+  let load = format!(".load {}", session_file);
+  let (out, err) = util::run_and_collect_output(
+    true,
+    "repl",
+    Some(vec![
+      ".help",
+      "var a = 20",
+      "a + 1",
+      &save,
+      ".clear",
+      "typeof a",
+      &load,
+      ".exit",
+      "'ignored'",
+    ]),
+    Some(vec![("NO_COLOR".to_owned(), "1".to_owned())]),
+    false,
+  );
+  assert!(out.contains(".load <file>"));
+  // `.clear` starts the REPL over.
+  assert!(out.contains(&format!("{}undefined\n", REPL_MSG)));
+  assert!(out.ends_with("21\n"));
+  assert!(err.is_empty());
+  assert_eq!(
+    std::fs::read_to_string(session_file).unwrap(),
+    "var a = 20\na + 1\n"
+  );
+}
+
+#[test]
+fn repl_test_debugger() {
+  let args = format!(
+    "repl {} --eval-file=repl_eval_file.js",
+    inspect_flag_with_unique_port("--inspect")
+  );
+  let (out, err) = util::run_and_collect_output(
+    true,
+    &args,
+    Some(vec![
+      ".break repl_eval_file.js:2",
+      "double(21)",
+      "x + 1",
+      ".continue",
+      "'done'",
+    ]),
+    Some(vec![("NO_COLOR".to_owned(), "1".to_owned())]),
+    false,
+  );
+  assert!(out.contains("Breakpoint set at file://"));
+  assert!(out.contains("Paused in double at "));
+  assert!(out.contains("repl_eval_file.js:2:3\n"));
+  // Evaluated in the paused frame.
+  assert!(out.contains("\n22\n"));
+  assert!(out.ends_with("42\ndone\n"));
+  assert!(err.contains("Debugger listening on"));
+}
+
+#[test]
+fn repl_test_eval() {
+  let (out, err) = util::run_and_collect_output(
+    true,
+    "repl --eval-file=repl_eval_file.js --eval=globalThis.x=21",
+    Some(vec!["double(x)"]),
+    Some(vec![("NO_COLOR".to_owned(), "1".to_owned())]),
+    false,
+  );
+  assert!(out.ends_with("42\n"));
+  assert!(err.is_empty());
+}
+
+#[test]
+fn repl_test_eval_error() {
+  let (out, err) = util::run_and_collect_output(
+    true,
+    "repl --eval=null.boom",
+    Some(vec!["1 + 1"]),
+    Some(vec![("NO_COLOR".to_owned(), "1".to_owned())]),
+    false,
+  );
+  assert!(out.ends_with("2\n"));
+  assert!(
+    err.contains("Uncaught TypeError: Cannot read property 'boom' of null")
+  );
+}
+
 #[test]
 fn repl_test_strict() {
   let (_, err) = util::run_and_collect_output(
@@ -830,6 +1229,19 @@ fn repl_test_multiline() {
   assert!(err.is_empty());
 }
 
+#[test]
+fn repl_test_multiline_comment_and_template() {
+  let (out, err) = util::run_and_collect_output(
+    true,
+    "repl",
+    Some(vec!["/* a\ncomment */ 1 + `${\n2\n}`"]),
+    Some(vec![("NO_COLOR".to_owned(), "1".to_owned())]),
+    false,
+  );
+  assert!(out.ends_with("12\n"));
+  assert!(err.is_empty());
+}
+
 #[test]
 fn repl_test_eval_unterminated() {
   let (out, err) = util::run_and_collect_output(
@@ -1097,7 +1509,7 @@ itest_ignore!(_019_media_types {
 itest!(_020_json_modules {
   args: "run --reload 020_json_modules.ts",
   output: "020_json_modules.ts.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(_021_mjs_modules {
@@ -1152,6 +1564,135 @@ itest!(deno_test {
   output: "deno_test.out",
 });
 
+itest!(test_sanitizers {
+  args: "test --allow-read test_sanitizers.ts",
+  exit_code: 1,
+  output: "test_sanitizers.out",
+});
+
+itest!(frozen_time {
+  args: "test --unstable --frozen-time=0 frozen_time_test.ts",
+  output: "frozen_time_test.out",
+});
+
+itest!(frozen_time_pending {
+  args: "run --unstable --frozen-time=0 frozen_time_pending.js",
+  output: "frozen_time_pending.out",
+});
+
+itest!(deno_test_jobs {
+  args: "test --jobs 2 test_jobs/",
+  output: "deno_test_jobs.out",
+});
+
+itest!(deno_test_jobs_fail {
+  args: "test --jobs 2 test_jobs_fail/",
+  exit_code: 1,
+  output: "deno_test_jobs_fail.out",
+});
+
+itest!(deno_test_tap {
+  args: "test --reporter=tap test_jobs/",
+  output: "deno_test_tap.out",
+});
+
+#[test]
+fn deno_test_coverage() {
+  let t = TempDir::new().expect("tempdir fail");
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("test")
+    .arg("--unstable")
+    .arg(format!("--coverage={}", t.path().display()))
+    .arg("test_coverage/")
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for the child process");
+  assert!(status.success());
+
+  let output = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("coverage")
+    .arg("--unstable")
+    .arg("--lcov")
+    .arg(t.path())
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let lcov = std::str::from_utf8(&output.stdout).unwrap();
+  let mod_path = util::tests_path().join("test_coverage/mod.ts");
+  assert!(lcov.contains(&format!("SF:{}\n", mod_path.display())));
+  assert!(!lcov.contains("mod_test.ts"));
+  // `return "negative"` is the only line that isn't run.
+  assert!(lcov.contains("DA:3,0\n"));
+  assert!(lcov.contains("DA:5,1\n"));
+  assert!(lcov.contains("FNDA:1,sign\n"));
+}
+
+#[test]
+fn cpu_profile() {
+  let t = TempDir::new().expect("tempdir fail");
+  let profile_path = t.path().join("out.cpuprofile");
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("run")
+    .arg("--unstable")
+    .arg(format!("--prof={}", profile_path.display()))
+    .arg("cpu_profile.ts")
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for the child process");
+  assert!(status.success());
+
+  let profile: serde_json::Value =
+    serde_json::from_slice(&std::fs::read(&profile_path).unwrap()).unwrap();
+  assert!(!profile["samples"].as_array().unwrap().is_empty());
+  let busy = profile["nodes"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|node| node["callFrame"]["functionName"] == "busy")
+    .expect("busy() wasn't sampled");
+  let url = busy["callFrame"]["url"].as_str().unwrap();
+  assert!(url.ends_with("cpu_profile.ts"));
+  // The line of the TypeScript source, which the interface shifts.
+  assert_eq!(busy["callFrame"]["lineNumber"], 5);
+}
+
+#[test]
+fn trace_ops() {
+  let t = TempDir::new().expect("tempdir fail");
+  let trace_path = t.path().join("trace.json");
+  let status = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("run")
+    .arg("--unstable")
+    .arg(format!("--trace-ops={}", trace_path.display()))
+    .arg("001_hello.js")
+    .spawn()
+    .expect("failed to spawn script")
+    .wait()
+    .expect("failed to wait for the child process");
+  assert!(status.success());
+
+  let trace: serde_json::Value =
+    serde_json::from_slice(&std::fs::read(&trace_path).unwrap()).unwrap();
+  let events = trace.as_array().unwrap();
+  // console.log() writes to stdout with op_write.
+  let write = events
+    .iter()
+    .find(|event| event["name"] == "op_write" && event["ph"] == "X")
+    .expect("op_write wasn't traced");
+  assert!(write["args"]["bytesSentZeroCopy"].as_u64().unwrap() > 0);
+}
+
+itest!(deno_bench {
+  args: "bench --unstable --allow-hrtime bench_runner_bench.ts",
+  output: "deno_bench.out",
+});
+
 #[test]
 fn workers() {
   let g = util::http_server();
@@ -1203,6 +1744,17 @@ itest!(_029_eval {
   output: "029_eval.out",
 });
 
+itest!(eval_script_args {
+  args: "eval console.log(Deno.args) -- --foo bar",
+  output_str: Some("[ \"--foo\", \"bar\" ]\n"),
+});
+
+itest!(eval_with_permissions {
+  args: "eval --allow-read Deno.env.toObject()",
+  output: "eval_with_permissions.out",
+  exit_code: 20,
+});
+
 // Ugly parentheses due to whitespace delimiting problem.
 itest!(_030_eval_ts {
   args: "eval --quiet -T console.log((123)as(number))", // 'as' is a TS keyword only
@@ -1251,7 +1803,7 @@ itest!(_037_fetch_multiple {
 itest!(_038_checkjs {
   // checking if JS file is run through TS compiler
   args: "run --reload --config 038_checkjs.tsconfig.json 038_checkjs.js",
-  exit_code: 1,
+  exit_code: 22,
   output: "038_checkjs.js.out",
 });
 
@@ -1273,7 +1825,7 @@ itest!(_042_dyn_import_evalcontext {
 itest!(_044_bad_resource {
   args: "run --quiet --reload --allow-read 044_bad_resource.ts",
   output: "044_bad_resource.ts.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest_ignore!(_045_proxy {
@@ -1341,7 +1893,7 @@ itest!(_058_tasks_microtasks_close {
 itest!(_059_fs_relative_path_perm {
   args: "run 059_fs_relative_path_perm.ts",
   output: "059_fs_relative_path_perm.ts.out",
-  exit_code: 1,
+  exit_code: 20,
 });
 
 itest!(js_import_detect {
@@ -1386,7 +1938,7 @@ itest_ignore!(lock_check_err2 {
 });
 
 itest!(async_error {
-  exit_code: 1,
+  exit_code: 23,
   args: "run --reload async_error.ts",
   output: "async_error.ts.out",
 });
@@ -1396,6 +1948,83 @@ itest!(bundle {
   output: "bundle.test.out",
 });
 
+itest!(doc_sample {
+  args: "doc doc_sample.ts",
+  output: "doc_sample.ts.out",
+});
+
+itest!(doc_sample_json {
+  args: "doc --json doc_sample.ts",
+  output: "doc_sample_json.out",
+});
+
+itest!(doc_sample_filter {
+  args: "doc doc_sample.ts add",
+  output: "doc_sample_filter.out",
+});
+
+itest!(lint {
+  args: "lint lint/*",
+  output: "lint/expected.out",
+  exit_code: 1,
+});
+
+itest!(lint_json {
+  args: "lint --json lint/file1.js lint/file2.ts",
+  output: "lint/expected_json.out",
+  exit_code: 1,
+});
+
+itest!(xeval {
+  args: "xeval console.log($.toUpperCase())",
+  input: Some("a\nb\n\nc"),
+  output_str: Some("A\nB\n\nC\n"),
+});
+
+itest!(xeval_replvar {
+  args: "xeval -I val console.log(val)",
+  input: Some("1\n2\n3\n"),
+  output_str: Some("1\n2\n3\n"),
+});
+
+itest!(xeval_delim {
+  args: "xeval -d DELIM console.log($)",
+  input: Some("!MADDADDELIMMADDAD!"),
+  output_str: Some("!MADDAD\nMADDAD!\n"),
+});
+
+itest!(xeval_await {
+  args: "xeval await(new(Promise)((r)=>setTimeout(r,1)));console.log($)",
+  input: Some("x\ny\n"),
+  output_str: Some("x\ny\n"),
+});
+
+itest!(env_file {
+  args: "run --quiet --reload --allow-env --env-file env_file.env env_file.ts",
+  output: "env_file.out",
+});
+
+itest!(env_file_invalid {
+  args: "run --quiet --reload --env-file env_file_invalid.env env_file.ts",
+  output: "env_file_invalid.out",
+  exit_code: 1,
+});
+
+itest!(run_stdin {
+  args: "run --quiet --reload -",
+  input: Some(
+    "import { printHello } from \"./subdir/print_hello.ts\";\nprintHello();\n"
+  ),
+  output_str: Some("Hello\n"),
+});
+
+itest!(run_stdin_error {
+  args: "run --quiet --reload -",
+  input: Some("throw new Error(\"boom\");\n"),
+  output: "run_stdin_error.out",
+  exit_code: 23,
+});
+
 itest!(fmt_stdin {
   args: "fmt -",
   input: Some("const a = 1\n"),
@@ -1421,25 +2050,36 @@ itest!(circular1 {
 
 itest!(config {
   args: "run --reload --config config.tsconfig.json config.ts",
-  exit_code: 1,
+  exit_code: 22,
   output: "config.ts.out",
 });
 
+itest!(no_check {
+  args: "run --quiet --reload --no-check no_check.ts",
+  output: "no_check.ts.out",
+});
+
+itest!(config_invalid {
+  args: "run --reload --config config_invalid.tsconfig.json 001_hello.js",
+  exit_code: 1,
+  output: "config_invalid.out",
+});
+
 itest!(error_001 {
   args: "run --reload error_001.ts",
-  exit_code: 1,
+  exit_code: 23,
   output: "error_001.ts.out",
 });
 
 itest!(error_002 {
   args: "run --reload error_002.ts",
-  exit_code: 1,
+  exit_code: 23,
   output: "error_002.ts.out",
 });
 
 itest!(error_003_typescript {
   args: "run --reload error_003_typescript.ts",
-  exit_code: 1,
+  exit_code: 22,
   output: "error_003_typescript.ts.out",
 });
 
@@ -1449,55 +2089,134 @@ itest!(error_003_typescript {
 // https://github.com/denoland/deno/issues/2436
 itest!(error_003_typescript2 {
   args: "run error_003_typescript.ts",
-  exit_code: 1,
+  exit_code: 22,
   output: "error_003_typescript.ts.out",
 });
 
+itest!(json_errors_001 {
+  args: "run --reload --json-errors error_001.ts",
+  exit_code: 23,
+  output: "json_errors_001.out",
+});
+
+itest!(json_errors_003_typescript {
+  args: "run --reload --json-errors error_003_typescript.ts",
+  exit_code: 22,
+  output: "json_errors_003_typescript.out",
+});
+
+#[cfg(unix)]
+#[test]
+fn stdout_broken_pipe() {
+  let mut child = util::deno_cmd()
+    .current_dir(util::tests_path())
+    .arg("run")
+    .arg("stdout_broken_pipe.ts")
+    .stdout(std::process::Stdio::piped())
+    .stderr(std::process::Stdio::piped())
+    .spawn()
+    .unwrap();
+  // Close the reading end, like `head` does once it has enough lines.
+  drop(child.stdout.take());
+  let output = child.wait_with_output().unwrap();
+  assert_eq!(output.status.code(), Some(141));
+  assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+#[test]
+fn unusable_deno_dir() {
+  // The DENO_DIR is a file, so no cache directory can be created in it.
+  let deno_dir = util::tests_path().join("001_hello.js");
+  let (_out, err) = util::run_and_collect_output(
+    false,
+    "run 001_hello.js",
+    None,
+    Some(vec![(
+      "DENO_DIR".to_owned(),
+      deno_dir.to_string_lossy().to_string(),
+    )]),
+    false,
+  );
+  assert!(err.contains("Could not create"));
+  assert!(!err.contains("panicked"));
+}
+
+#[test]
+fn no_color_flag() {
+  let (out, err) = util::run_and_collect_output(
+    true,
+    "--no-color eval console.log(Deno.noColor)",
+    None,
+    None,
+    false,
+  );
+  assert_eq!(out, "true\n");
+  assert!(err.is_empty());
+}
+
+itest!(inline_source_map {
+  args: "run --reload inline_source_map.js",
+  exit_code: 23,
+  output: "inline_source_map.js.out",
+});
+
+itest!(op_stack_traces {
+  args: "run --reload --allow-read --op-stack-traces op_stack_traces.ts",
+  exit_code: 23,
+  output: "op_stack_traces.ts.out",
+});
+
+itest!(strict_resources {
+  args: "run --reload --allow-read --strict-resources strict_resources.ts",
+  exit_code: 1,
+  output: "strict_resources.ts.out",
+});
+
 itest!(error_004_missing_module {
   args: "run --reload error_004_missing_module.ts",
-  exit_code: 1,
+  exit_code: 21,
   output: "error_004_missing_module.ts.out",
 });
 
 itest!(error_005_missing_dynamic_import {
   args: "run --reload --allow-read --quiet error_005_missing_dynamic_import.ts",
-  exit_code: 1,
+  exit_code: 23,
   output: "error_005_missing_dynamic_import.ts.out",
 });
 
 itest!(error_006_import_ext_failure {
   args: "run --reload error_006_import_ext_failure.ts",
-  exit_code: 1,
+  exit_code: 21,
   output: "error_006_import_ext_failure.ts.out",
 });
 
 itest!(error_007_any {
   args: "run --reload error_007_any.ts",
-  exit_code: 1,
+  exit_code: 23,
   output: "error_007_any.ts.out",
 });
 
 itest!(error_008_checkjs {
   args: "run --reload error_008_checkjs.js",
-  exit_code: 1,
+  exit_code: 23,
   output: "error_008_checkjs.js.out",
 });
 
 itest!(error_011_bad_module_specifier {
   args: "run --reload error_011_bad_module_specifier.ts",
-  exit_code: 1,
+  exit_code: 21,
   output: "error_011_bad_module_specifier.ts.out",
 });
 
 itest!(error_012_bad_dynamic_import_specifier {
   args: "run --reload error_012_bad_dynamic_import_specifier.ts",
-  exit_code: 1,
+  exit_code: 23,
   output: "error_012_bad_dynamic_import_specifier.ts.out",
 });
 
 itest!(error_013_missing_script {
   args: "run --reload missing_file_name",
-  exit_code: 1,
+  exit_code: 21,
   output: "error_013_missing_script.out",
 });
 
@@ -1509,7 +2228,7 @@ itest!(error_014_catch_dynamic_import_error {
 itest!(error_015_dynamic_import_permissions {
   args: "run --reload --quiet error_015_dynamic_import_permissions.js",
   output: "error_015_dynamic_import_permissions.out",
-  exit_code: 1,
+  exit_code: 23,
   http_server: true,
 });
 
@@ -1517,62 +2236,62 @@ itest!(error_015_dynamic_import_permissions {
 itest!(error_016_dynamic_import_permissions2 {
   args: "run --reload --allow-net error_016_dynamic_import_permissions2.js",
   output: "error_016_dynamic_import_permissions2.out",
-  exit_code: 1,
+  exit_code: 23,
   http_server: true,
 });
 
 itest!(error_017_hide_long_source_ts {
   args: "run --reload error_017_hide_long_source_ts.ts",
   output: "error_017_hide_long_source_ts.ts.out",
-  exit_code: 1,
+  exit_code: 22,
 });
 
 itest!(error_018_hide_long_source_js {
   args: "run error_018_hide_long_source_js.js",
   output: "error_018_hide_long_source_js.js.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(error_019_stack_function {
   args: "run error_019_stack_function.ts",
   output: "error_019_stack_function.ts.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(error_020_stack_constructor {
   args: "run error_020_stack_constructor.ts",
   output: "error_020_stack_constructor.ts.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(error_021_stack_method {
   args: "run error_021_stack_method.ts",
   output: "error_021_stack_method.ts.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(error_022_stack_custom_error {
   args: "run error_022_stack_custom_error.ts",
   output: "error_022_stack_custom_error.ts.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(error_023_stack_async {
   args: "run error_023_stack_async.ts",
   output: "error_023_stack_async.ts.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(error_024_stack_promise_all {
   args: "run error_024_stack_promise_all.ts",
   output: "error_024_stack_promise_all.ts.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(error_025_tab_indent {
   args: "run error_025_tab_indent",
   output: "error_025_tab_indent.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(error_syntax {
@@ -1589,20 +2308,20 @@ itest!(error_syntax_empty_trailing_line {
 
 itest!(error_type_definitions {
   args: "run --reload error_type_definitions.ts",
-  exit_code: 1,
+  exit_code: 21,
   output: "error_type_definitions.ts.out",
 });
 
 itest!(error_local_static_import_from_remote_ts {
   args: "run --reload http://localhost:4545/cli/tests/error_local_static_import_from_remote.ts",
-  exit_code: 1,
+  exit_code: 20,
   http_server: true,
   output: "error_local_static_import_from_remote.ts.out",
 });
 
 itest!(error_local_static_import_from_remote_js {
   args: "run --reload http://localhost:4545/cli/tests/error_local_static_import_from_remote.js",
-  exit_code: 1,
+  exit_code: 20,
   http_server: true,
   output: "error_local_static_import_from_remote.js.out",
 });
@@ -1675,7 +2394,7 @@ itest!(type_directives_redirect {
 itest!(ts_type_imports {
   args: "run --reload ts_type_imports.ts",
   output: "ts_type_imports.ts.out",
-  exit_code: 1,
+  exit_code: 22,
 });
 
 itest!(ts_decorators {
@@ -1694,6 +2413,11 @@ itest!(types {
   output: "types.out",
 });
 
+itest!(types_unstable {
+  args: "types --unstable",
+  output: "types_unstable.out",
+});
+
 itest!(unbuffered_stderr {
   args: "run --reload unbuffered_stderr.ts",
   output: "unbuffered_stderr.ts.out",
@@ -1724,7 +2448,7 @@ itest!(run_v8_help {
 itest!(unsupported_dynamic_import_scheme {
   args: "eval import('xxx:')",
   output: "unsupported_dynamic_import_scheme.out",
-  exit_code: 1,
+  exit_code: 23,
 });
 
 itest!(wasm {
@@ -1737,6 +2461,16 @@ itest!(wasm_async {
   output: "wasm_async.out",
 });
 
+itest!(wasm_import {
+  args: "run --quiet --reload wasm_import.ts",
+  output: "wasm_import.ts.out",
+});
+
+itest!(wasm_dynamic_import {
+  args: "run --quiet --reload --allow-read wasm_dynamic_import.js",
+  output: "wasm_dynamic_import.js.out",
+});
+
 itest!(top_level_await {
   args: "run --allow-read top_level_await.js",
   output: "top_level_await.out",
@@ -1757,9 +2491,20 @@ itest!(top_level_for_await_ts {
   output: "top_level_for_await.out",
 });
 
+itest!(unstable_op_error {
+  args: "run --quiet --reload unstable_op_error.js",
+  output: "unstable_op_error.js.out",
+});
+
+itest!(fetch_timeout_unstable {
+  args: "run --quiet --reload --allow-net fetch_timeout_unstable.js",
+  output: "fetch_timeout_unstable.js.out",
+  http_server: true,
+});
+
 itest!(unstable_disabled {
   args: "run --reload unstable.ts",
-  exit_code: 1,
+  exit_code: 22,
   output: "unstable_disabled.out",
 });
 
@@ -1814,14 +2559,25 @@ itest!(disallow_http_from_https_js {
   args: "run --quiet --reload --cert tls/RootCA.pem https://localhost:5545/cli/tests/disallow_http_from_https.js",
   output: "disallow_http_from_https_js.out",
   http_server: true,
-  exit_code: 1,
+  exit_code: 20,
 });
 
 itest!(disallow_http_from_https_ts {
   args: "run --quiet --reload --cert tls/RootCA.pem https://localhost:5545/cli/tests/disallow_http_from_https.ts",
   output: "disallow_http_from_https_ts.out",
   http_server: true,
-  exit_code: 1,
+  exit_code: 20,
+});
+
+itest!(jsx_factory {
+  args:
+    "run --quiet --reload --config jsx_factory.tsconfig.json jsx_factory.tsx",
+  output: "jsx_factory.tsx.out",
+});
+
+itest!(jsx_preserve_ignored {
+  args: "run --reload --config jsx_preserve.tsconfig.json jsx_factory.tsx",
+  output: "jsx_preserve_ignored.out",
 });
 
 itest!(tsx_imports {
@@ -2455,6 +3211,45 @@ async fn inspector_break_on_first_line() {
   child.wait().unwrap();
 }
 
+#[tokio::test]
+async fn inspector_console() {
+  let script = util::tests_path().join("inspector1.js");
+  let mut child = util::deno_cmd()
+    .arg("run")
+    .arg(inspect_flag_with_unique_port("--inspect"))
+    .arg(script)
+    .stdout(std::process::Stdio::null())
+    .stderr(std::process::Stdio::piped())
+    .spawn()
+    .unwrap();
+
+  let stderr = child.stderr.as_mut().unwrap();
+  let mut stderr_lines =
+    std::io::BufReader::new(stderr).lines().map(|r| r.unwrap());
+  let ws_url = extract_ws_url_from_stderr(&mut stderr_lines);
+
+  let (mut socket, _) = tokio_tungstenite::connect_async(ws_url)
+    .await
+    .expect("Can't connect");
+  socket
+    .send(r#"{"id":1,"method":"Runtime.enable"}"#.into())
+    .await
+    .unwrap();
+
+  // What the script prints with `console.log()` is sent to the inspector too.
+  loop {
+    let msg = socket.next().await.unwrap().unwrap().to_string();
+    if msg.starts_with(r#"{"method":"Runtime.consoleAPICalled","#) {
+      assert!(msg.contains(r#""type":"log""#));
+      assert!(msg.contains(r#"{"type":"string","value":"hello"}"#));
+      break;
+    }
+  }
+
+  child.kill().unwrap();
+  child.wait().unwrap();
+}
+
 #[tokio::test]
 async fn inspector_pause() {
   let script = util::tests_path().join("inspector1.js");
@@ -0,0 +1,9 @@
+declare namespace JSX {
+  interface IntrinsicElements {
+    [elemName: string]: any;
+  }
+}
+function h(tag: string, props: any, ...children: any[]) {
+  return { tag, props, children };
+}
+console.log(<div class="deno">land</div>);
@@ -0,0 +1,4 @@
+export function add(a: any, b: number): number {
+  debugger;
+  return a + b;
+}
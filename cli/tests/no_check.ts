@@ -0,0 +1,3 @@
+// Type checking would reject this assignment.
+const value: number = "not a number";
+console.log(value);
@@ -0,0 +1,4 @@
+// The entry of the package imports another of its files, which is read from
+// the npm cache without --allow-read.
+import { greeting } from "npm:deno-test-pkg";
+console.log(greeting);
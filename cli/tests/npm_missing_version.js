@@ -0,0 +1,2 @@
+// The "missing" dist-tag of the package points to a version it doesn't have.
+import "npm:deno-test-pkg@missing";
@@ -0,0 +1,5 @@
+function openMissing(): Promise<Deno.File> {
+  return Deno.open("op_stack_traces_missing.txt");
+}
+
+openMissing();
@@ -0,0 +1,7 @@
+function openLeaked(): Promise<Deno.File> {
+  return Deno.open("hello.txt");
+}
+
+const closed = Deno.openSync("hello.txt");
+closed.close();
+await openLeaked();
@@ -0,0 +1,5 @@
+onmessage = async function (e) {
+  const { rid } = e.resources[0];
+  await Deno.write(rid, new TextEncoder().encode("hello"));
+  postMessage(e.data, { resources: [rid] });
+};
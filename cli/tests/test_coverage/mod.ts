@@ -0,0 +1,6 @@
+export function sign(n: number): string {
+  if (n < 0) {
+    return "negative";
+  }
+  return "positive";
+}
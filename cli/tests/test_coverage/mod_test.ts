@@ -0,0 +1,7 @@
+import { sign } from "./mod.ts";
+
+Deno.test("sign", function (): void {
+  if (sign(1) !== "positive") {
+    throw new Error("sign(1) should be positive");
+  }
+});
@@ -0,0 +1,3 @@
+Deno.test("b", function (): void {
+  console.error("output of b");
+});
@@ -0,0 +1,3 @@
+Deno.test("a", function (): void {
+  console.log("output of a");
+});
@@ -0,0 +1,3 @@
+Deno.test("b", function (): void {
+  throw new Error("b failed");
+});
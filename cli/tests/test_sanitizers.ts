@@ -0,0 +1,7 @@
+Deno.test("leakingTimer", function (): void {
+  setTimeout(() => {}, 60000);
+});
+
+Deno.test("leakingFile", function (): void {
+  Deno.openSync("test_sanitizers.ts");
+});
@@ -0,0 +1,67 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { unitTest, assert, assertEquals } from "./test_util.ts";
+
+const {
+  runBenchmarks,
+  // @ts-expect-error TypeScript (as of 3.7) does not support indexing namespaces by symbol
+} = Deno[Deno.internal];
+
+Deno.bench("benchSum", function (): void {
+  let sum = 0;
+  for (let i = 0; i < 100; i++) {
+    sum += i;
+  }
+  assert(sum > 0);
+});
+
+Deno.bench("benchThrows", function (): void {
+  throw new Error("benchThrows");
+});
+
+unitTest({ perms: { hrtime: true } }, async function benchStats(): Promise<
+  void
+> {
+  const { measured, failed, results } = await runBenchmarks({
+    filter: "benchSum",
+    reportToConsole: false,
+    exitOnFail: false,
+  });
+  assertEquals(measured, 1);
+  assertEquals(failed, 0);
+  const { stats } = results[0];
+  assert(stats.samples >= 5);
+  assert(stats.samples <= 100);
+  assert(stats.iterations >= stats.samples);
+  assert(stats.min > 0);
+  assert(stats.min <= stats.p50);
+  assert(stats.p50 <= stats.p75);
+  assert(stats.p75 <= stats.p99);
+  assert(stats.p99 <= stats.max);
+  assert(stats.min <= stats.mean && stats.mean <= stats.max);
+  assert(stats.stddev >= 0);
+});
+
+unitTest({ perms: { hrtime: true } }, async function benchFails(): Promise<
+  void
+> {
+  const { measured, failed, results } = await runBenchmarks({
+    filter: "benchThrows",
+    reportToConsole: false,
+    exitOnFail: false,
+  });
+  assertEquals(measured, 0);
+  assertEquals(failed, 1);
+  assertEquals(results[0].error.message, "benchThrows");
+});
+
+unitTest({ perms: { hrtime: false } }, async function benchPerm(): Promise<
+  void
+> {
+  const { failed, results } = await runBenchmarks({
+    filter: "benchSum",
+    reportToConsole: false,
+    exitOnFail: false,
+  });
+  assertEquals(failed, 1);
+  assert(results[0].error instanceof Deno.errors.PermissionDenied);
+});
@@ -1,4 +1,10 @@
-import { assert, unitTest, assertMatch, unreachable } from "./test_util.ts";
+import {
+  assert,
+  assertEquals,
+  unitTest,
+  assertMatch,
+  unreachable,
+} from "./test_util.ts";
 
 const openErrorStackPattern = new RegExp(
   `^.*
@@ -19,6 +25,27 @@ unitTest(
   }
 );
 
+unitTest(
+  { perms: { read: true } },
+  async function sendAsyncErrorDetails(): Promise<void> {
+    await Deno.open("nonexistent.txt")
+      .then(unreachable)
+      .catch((error): void => {
+        assert(error instanceof Deno.errors.NotFound);
+        assertEquals(error.kind, "NotFound");
+        // ENOENT on Unix, ERROR_FILE_NOT_FOUND on Windows.
+        assertEquals(error.errno, 2);
+        assertEquals(error.cause, undefined);
+      });
+  }
+);
+      unreachable();
+    } catch (error) {
+      assertEquals(typeof error.kind, "string");
+    }
+  }
+);
+
 /* eslint-disable @typescript-eslint/no-namespace, @typescript-eslint/no-explicit-any,no-var */
 declare global {
   namespace Deno {
@@ -35,3 +62,43 @@ unitTest(function malformedJsonControlBuffer(): void {
   assert(!resJson.ok);
   assert(resJson.err);
 });
+
+const {
+  sendSyncBatch,
+  sendAsyncBatch,
+  // @ts-expect-error TypeScript (as of 3.7) does not support indexing namespaces by symbol
+} = Deno[Deno.internal];
+
+unitTest({ perms: { read: true } }, function sendSyncBatchStat(): void {
+  const results = sendSyncBatch([
+    ["op_stat", { path: "cli/tests/hello.txt", lstat: false }],
+    ["op_stat", { path: "nonexistent.txt", lstat: false }],
+    ["op_cwd"],
+  ]);
+  assertEquals(results.length, 3);
+  assert(results[0].ok.isFile);
+  assert(results[1].err instanceof Deno.errors.NotFound);
+  assertEquals(results[2].ok, Deno.cwd());
+});
+
+unitTest(
+  { perms: { read: true } },
+  async function sendAsyncBatchStat(): Promise<void> {
+    const results = await sendAsyncBatch([
+      ["op_stat", { path: "nonexistent.txt", lstat: false }],
+      ["op_stat", { path: "cli/tests/hello.txt", lstat: false }],
+    ]);
+    assertEquals(results.length, 2);
+    assert(results[0].err instanceof Deno.errors.NotFound);
+    assert(results[1].ok.isFile);
+  }
+);
+
+unitTest(function batchUnknownOp(): void {
+  try {
+    sendSyncBatch([["op_does_not_exist"]]);
+    unreachable();
+  } catch (error) {
+    assert(error instanceof TypeError);
+  }
+});
@@ -25,6 +25,18 @@ unitTest(async function sendAsyncStackTrace(): Promise<void> {
   }
 });
 
+unitTest(async function sendAsyncErrorDetails(): Promise<void> {
+  const buf = new Uint8Array(10);
+  try {
+    await Deno.read(10, buf);
+    unreachable();
+  } catch (error) {
+    assert(error instanceof Deno.errors.BadResource);
+    assertEquals(error.kind, "BadResource");
+    assertEquals(error.errno, undefined);
+  }
+});
+
 /* eslint-disable @typescript-eslint/no-namespace, @typescript-eslint/no-explicit-any,no-var */
 declare global {
   namespace Deno {
@@ -36,14 +48,16 @@ declare global {
 unitTest(function malformedMinimalControlBuffer(): void {
   const readOpId = Deno.core.ops()["op_read"];
   const res = Deno.core.send(readOpId, new Uint8Array([1, 2, 3, 4, 5]));
-  const header = res.slice(0, 12);
+  const header = res.slice(0, 16);
   const buf32 = new Int32Array(
     header.buffer,
     header.byteOffset,
     header.byteLength / 4
   );
   const arg = buf32[1];
-  const message = new TextDecoder().decode(res.slice(12)).trim();
+  const errno = buf32[3];
+  const message = new TextDecoder().decode(res.slice(16)).trim();
   assert(arg < 0);
+  assertEquals(errno, 0);
   assertEquals(message, "Unparsable control buffer");
 });
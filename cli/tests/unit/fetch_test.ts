@@ -32,6 +32,130 @@ unitTest(
     }
     assert(err instanceof Deno.errors.Http);
     assertStrContains(err.message, "error trying to connect");
+    assertEquals(err.kind, "Http");
+    assertEquals(typeof err.errno, "number");
+    assertEquals(typeof err.cause, "string");
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchAbortWhilePending(): Promise<void> {
+    const controller = new AbortController();
+    const promise = fetch("http://localhost:4545/cli/tests/fixture.json", {
+      signal: controller.signal,
+    });
+    controller.abort();
+    let err;
+    try {
+      await promise;
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof DOMException);
+    assertEquals(err.name, "AbortError");
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchAbortedSignal(): Promise<void> {
+    const controller = new AbortController();
+    controller.abort();
+    let err;
+    try {
+      await fetch("http://localhost:4545/cli/tests/fixture.json", {
+        signal: controller.signal,
+      });
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof DOMException);
+    assertEquals(err.name, "AbortError");
+  }
+);
+
+/** Serves a single connection: reads the request, answers with `head` and
+ * then stalls until closed. */
+function stallingServer(addr: string, head: string): { close(): void } {
+  const [hostname, port] = addr.split(":");
+  const listener = Deno.listen({ hostname, port: Number(port) });
+  let conn: Deno.Conn | undefined;
+  listener.accept().then(
+    async (c): Promise<void> => {
+      conn = c;
+      await c.read(new Uint8Array(1024));
+      await c.write(new TextEncoder().encode(head));
+    }
+  );
+  return {
+    close(): void {
+      conn?.close();
+      listener.close();
+    },
+  };
+}
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchAbortWhileReadingBody(): Promise<void> {
+    const addr = "127.0.0.1:4504";
+    const server = stallingServer(
+      addr,
+      "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello"
+    );
+    const controller = new AbortController();
+    const response = await fetch(`http://${addr}/`, {
+      signal: controller.signal,
+    });
+    assertEquals(response.status, 200);
+    controller.abort();
+    let err;
+    try {
+      await response.text();
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof DOMException);
+    assertEquals(err.name, "AbortError");
+    server.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchTimeout(): Promise<void> {
+    const addr = "127.0.0.1:4505";
+    const server = stallingServer(addr, "");
+    let err;
+    try {
+      await fetch(`http://${addr}/`, { timeout: 100 });
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof Deno.errors.TimedOut);
+    server.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchTimeoutWhileReadingBody(): Promise<void> {
+    const addr = "127.0.0.1:4506";
+    const server = stallingServer(
+      addr,
+      "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello"
+    );
+    const response = await fetch(`http://${addr}/`, { timeout: 200 });
+    assertEquals(response.status, 200);
+    let err;
+    try {
+      await response.text();
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof Deno.errors.TimedOut);
+    server.close();
   }
 );
 
@@ -78,6 +202,51 @@ unitTest({ perms: { net: true } }, async function fetchHeaders(): Promise<
   const _json = await response.json();
 });
 
+unitTest({ perms: { net: true } }, async function fetchGzip(): Promise<void> {
+  const response = await fetch(
+    "http://localhost:4545/cli/tests/053_import_compression/gziped"
+  );
+  assertEquals(response.headers.get("Content-Encoding"), null);
+  assertEquals(response.headers.get("Content-Length"), null);
+  assertEquals(await response.text(), "console.log('gzip')");
+});
+
+unitTest({ perms: { net: true } }, async function fetchBrotli(): Promise<
+  void
+> {
+  const response = await fetch(
+    "http://localhost:4545/cli/tests/053_import_compression/brotli"
+  );
+  assertEquals(response.headers.get("Content-Encoding"), null);
+  assertEquals(response.headers.get("Content-Length"), null);
+  assertEquals(await response.text(), "console.log('brotli');");
+});
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchDownloadProgress(): Promise<void> {
+    const progress: FetchProgress[] = [];
+    const response = await fetch(
+      "http://localhost:4545/cli/tests/fixture.json",
+      {
+        onProgress(p: FetchProgress): void {
+          progress.push(p);
+        },
+      }
+    );
+    const body = new Uint8Array(await response.arrayBuffer());
+    const total = Number(response.headers.get("Content-Length"));
+    assertEquals(body.byteLength, total);
+    assert(progress.length > 0);
+    assert(progress.every((p): boolean => p.direction === "download"));
+    assertEquals(progress[progress.length - 1], {
+      direction: "download",
+      loaded: total,
+      total,
+    });
+  }
+);
+
 unitTest({ perms: { net: true } }, async function fetchBlob(): Promise<void> {
   const response = await fetch("http://localhost:4545/cli/tests/fixture.json");
   const headers = response.headers;
@@ -517,6 +686,47 @@ unitTest(
   }
 );
 
+unitTest(
+  { perms: { net: true } },
+  async function fetchUserAgent(): Promise<void> {
+    const addr = "127.0.0.1:4508";
+    const buf = bufferServer(addr);
+    const response = await fetch(`http://${addr}/blah`, {
+      headers: [["User-Agent", "custom"]],
+    });
+    await response.arrayBuffer();
+    const actual = new TextDecoder().decode(buf.bytes());
+    assertStrContains(actual, "user-agent: custom\r\n");
+    assert(!actual.includes("Deno/"));
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchUploadProgress(): Promise<void> {
+    const addr = "127.0.0.1:4507";
+    const buf = bufferServer(addr);
+    const progress: FetchProgress[] = [];
+    const response = await fetch(`http://${addr}/blah`, {
+      method: "POST",
+      body: "hello world",
+      onProgress(p: FetchProgress): void {
+        progress.push(p);
+      },
+    });
+    await response.arrayBuffer();
+    assertEquals(progress, [
+      { direction: "upload", loaded: 11, total: 11 },
+      { direction: "download", loaded: 2, total: 2 },
+    ]);
+    // The body is streamed to report its progress, but still sent with its
+    // length rather than chunked.
+    const actual = new TextDecoder().decode(buf.bytes());
+    assertStrContains(actual, "content-length: 11\r\n");
+    assert(!actual.includes("transfer-encoding"));
+  }
+);
+
 unitTest(
   {
     perms: { net: true },
@@ -569,3 +569,63 @@ unitTest({ perms: { read: true } }, async function seekMode(): Promise<void> {
   assertEquals(new TextDecoder().decode(buf), "H");
   file.close();
 });
+
+unitTest(
+  { perms: { read: true, write: true } },
+  async function writevReadv(): Promise<void> {
+    const tempDir = await Deno.makeTempDir();
+    const filename = tempDir + "/test.txt";
+    const encoder = new TextEncoder();
+    const file = await Deno.open(filename, {
+      create: true,
+      read: true,
+      write: true,
+    });
+    const nwritten = await Deno.writev(file.rid, [
+      encoder.encode("Hello"),
+      new Uint8Array(0),
+      encoder.encode(" world!"),
+    ]);
+    assertEquals(nwritten, 12);
+    await file.seek(0, Deno.SeekMode.Start);
+    const header = new Uint8Array(5);
+    const body = new Uint8Array(10);
+    const nread = await Deno.readv(file.rid, [header, body]);
+    assertEquals(nread, 12);
+    const decoder = new TextDecoder();
+    assertEquals(decoder.decode(header), "Hello");
+    assertEquals(decoder.decode(body.subarray(0, 7)), " world!");
+    assertEquals(await Deno.readv(file.rid, [header, body]), null);
+    file.close();
+    await Deno.remove(tempDir, { recursive: true });
+  }
+);
+
+unitTest(
+  { perms: { read: true, write: true } },
+  function writevReadvSync(): void {
+    const tempDir = Deno.makeTempDirSync();
+    const filename = tempDir + "/test.txt";
+    const encoder = new TextEncoder();
+    const file = Deno.openSync(filename, {
+      create: true,
+      read: true,
+      write: true,
+    });
+    const nwritten = Deno.writevSync(file.rid, [
+      encoder.encode("Hello"),
+      encoder.encode(" world!"),
+    ]);
+    assertEquals(nwritten, 12);
+    file.seekSync(0, Deno.SeekMode.Start);
+    const header = new Uint8Array(5);
+    const body = new Uint8Array(10);
+    const nread = Deno.readvSync(file.rid, [header, body]);
+    assertEquals(nread, 12);
+    const decoder = new TextDecoder();
+    assertEquals(decoder.decode(header), "Hello");
+    assertEquals(decoder.decode(body.subarray(0, 7)), " world!");
+    file.close();
+    Deno.removeSync(tempDir, { recursive: true });
+  }
+);
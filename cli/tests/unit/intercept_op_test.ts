@@ -0,0 +1,98 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import {
+  unitTest,
+  assert,
+  assertEquals,
+  assertThrows,
+} from "./test_util.ts";
+
+unitTest(function interceptOpSync(): void {
+  const restore = Deno.interceptOp("op_open", (args): never => {
+    assertEquals(args.path, "stubbed.txt");
+    throw new Deno.errors.NotFound("stubbed");
+  });
+  try {
+    assertThrows(
+      (): void => {
+        Deno.openSync("stubbed.txt");
+      },
+      Deno.errors.NotFound,
+      "stubbed"
+    );
+  } finally {
+    restore();
+  }
+});
+
+unitTest(async function interceptOpAsync(): Promise<void> {
+  const restore = Deno.interceptOp(
+    "op_open",
+    async (): Promise<never> => {
+      await Promise.resolve();
+      throw new Deno.errors.NotFound("stubbed");
+    }
+  );
+  try {
+    let caughtError = false;
+    try {
+      await Deno.open("stubbed.txt");
+    } catch (e) {
+      caughtError = true;
+      assert(e instanceof Deno.errors.NotFound);
+      assertEquals(e.message, "stubbed");
+    }
+    assert(caughtError);
+  } finally {
+    restore();
+  }
+});
+
+unitTest(async function interceptOpMinimal(): Promise<void> {
+  const restore = Deno.interceptOp("op_read", (rid, buf): number => {
+    assertEquals(rid, 12345);
+    buf.set([1, 2, 3]);
+    return 3;
+  });
+  try {
+    const buf = new Uint8Array(8);
+    assertEquals(await Deno.read(12345, buf), 3);
+    assertEquals(Deno.readSync(12345, buf), 3);
+    assertEquals(Array.from(buf.subarray(0, 3)), [1, 2, 3]);
+  } finally {
+    restore();
+  }
+});
+
+unitTest({ perms: { read: true } }, function interceptOpRestore(): void {
+  const restore = Deno.interceptOp("op_open", (): never => {
+    throw new Error("intercepted");
+  });
+  restore();
+  const file = Deno.openSync("cli/tests/fixture.json");
+  assert(file.rid > 2);
+  file.close();
+});
+
+unitTest(function interceptOpErrors(): void {
+  assertThrows(
+    (): void => {
+      Deno.interceptOp("op_does_not_exist", (): void => {});
+    },
+    TypeError,
+    "Unknown op: op_does_not_exist"
+  );
+  const restore = Deno.interceptOp("op_open", (): Promise<void> =>
+    Promise.resolve()
+  );
+  try {
+    assertThrows(
+      (): void => {
+        Deno.openSync("stubbed.txt");
+      },
+      TypeError,
+      "can't return a promise"
+    );
+  } finally {
+    restore();
+  }
+});
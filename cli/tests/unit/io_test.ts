@@ -1,5 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-import { unitTest, assertEquals } from "./test_util.ts";
+import { unitTest, assertEquals, assertThrowsAsync } from "./test_util.ts";
 
 const DEFAULT_BUF_SIZE = 32 * 1024;
 
@@ -56,6 +56,24 @@ unitTest(async function copyWithCustomBufferSize() {
   assertEquals(readSpy.calls, DEFAULT_BUF_SIZE / bufSize + 1);
 });
 
+unitTest(async function copyDoesNotReuseChunksOfUserWriters() {
+  const chunks: Uint8Array[] = [];
+  const writer: Deno.Writer = {
+    write(p: Uint8Array): Promise<number> {
+      chunks.push(p);
+      return Promise.resolve(p.length);
+    },
+  };
+  const a = repeat("a", 16);
+  await Deno.copy(new Deno.Buffer(a.buffer as ArrayBuffer), writer);
+  const b = repeat("b", 16);
+  await Deno.copy(new Deno.Buffer(b.buffer as ArrayBuffer), writer);
+
+  assertEquals(chunks.length, 2);
+  assertEquals(chunks[0], a);
+  assertEquals(chunks[1], b);
+});
+
 unitTest({ perms: { write: true } }, async function copyBufferToFile() {
   const filePath = "test-file.txt";
   // bigger than max File possible buffer 16kb
@@ -71,3 +89,43 @@ unitTest({ perms: { write: true } }, async function copyBufferToFile() {
   write.close();
   await Deno.remove(filePath);
 });
+
+unitTest(
+  { perms: { run: true } },
+  async function readTimeoutStdin(): Promise<void> {
+    const src = `(async () => {
+      const buf = new Uint8Array(8);
+      const read = (t) => Deno.readTimeout(Deno.stdin.rid, buf, t);
+      console.log(await read(0), await read(50));
+      const n = await read(10000);
+      const text = new TextDecoder().decode(buf.subarray(0, n));
+      console.log(text, await read(10000));
+    })()`;
+    const p = Deno.run({
+      cmd: [Deno.execPath(), "eval", "--unstable", src],
+      stdin: "piped",
+      stdout: "piped",
+    });
+    const decoder = new TextDecoder();
+    const buf = new Uint8Array(64);
+    // Nothing is written until the reads with short timeouts are done.
+    const n = await p.stdout!.read(buf);
+    assertEquals(decoder.decode(buf.subarray(0, n!)), "0 0\n");
+    await p.stdin!.write(new TextEncoder().encode("hi"));
+    p.stdin!.close();
+    assertEquals(decoder.decode(await p.output()), "hi null\n");
+    assertEquals((await p.status()).code, 0);
+    p.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true } },
+  async function readTimeoutNotStdin(): Promise<void> {
+    const file = await Deno.open("cli/tests/hello.txt");
+    await assertThrowsAsync(async (): Promise<void> => {
+      await Deno.readTimeout(file.rid, new Uint8Array(8), 0);
+    }, TypeError);
+    file.close();
+  }
+);
@@ -15,6 +15,9 @@ unitTest(async function metrics(): Promise<void> {
   // control messages.
   const dataMsg = new Uint8Array([13, 13, 13]); // "\r\r\r",
   await Deno.stdout.write(dataMsg);
+  // The write may complete as soon as it's dispatched, which is counted as a
+  // sync op; a timer is sure to stay pending.
+  await new Promise((resolve): number => setTimeout(resolve, 10));
 
   const m2 = Deno.metrics();
   assert(m2.opsDispatched > m1.opsDispatched);
@@ -56,3 +59,24 @@ unitTest(
     assert(metrics.opsDispatchedAsync === metrics.opsCompletedAsync);
   }
 );
+
+unitTest(
+  { perms: { read: true, write: true } },
+  async function metricsBufferPool(): Promise<void> {
+    const filename = Deno.makeTempDirSync() + "/test.txt";
+    Deno.writeFileSync(filename, new Uint8Array([41, 42, 43]));
+
+    const copy = async (): Promise<void> => {
+      const src = await Deno.open(filename);
+      await Deno.copy(src, new Deno.Buffer());
+      src.close();
+    };
+    await copy();
+    const m1 = Deno.metrics();
+    assert(m1.bufferPoolFree > 0);
+    await copy();
+    const m2 = Deno.metrics();
+    assert(m2.bufferPoolHits > m1.bufferPoolHits);
+    assert(m2.bufferPoolFree === m1.bufferPoolFree);
+  }
+);
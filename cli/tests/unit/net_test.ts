@@ -3,6 +3,8 @@ import {
   unitTest,
   assert,
   assertEquals,
+  assertThrows,
+  assertThrowsAsync,
   createResolvable,
 } from "./test_util.ts";
 
@@ -525,3 +527,104 @@ unitTest(
     await resolvable;
   }
 );
+
+unitTest(
+  { perms: { net: true, read: true } },
+  async function netSendFile(): Promise<void> {
+    const addr = { hostname: "127.0.0.1", port: 3500 };
+    const listener = Deno.listen(addr);
+    listener.accept().then(
+      async (conn): Promise<void> => {
+        const file = await Deno.open("cli/tests/hello.txt");
+        assertEquals(await Deno.sendFile(file.rid, conn.rid, 5), 5);
+        assertEquals(await Deno.sendFile(file.rid, conn.rid), 7);
+        assertEquals(await Deno.sendFile(file.rid, conn.rid), 0);
+        file.close();
+        conn.close();
+      }
+    );
+
+    const conn = await Deno.connect(addr);
+    const data = await Deno.readAll(conn);
+    assertEquals(new TextDecoder().decode(data), "Hello world!");
+    listener.close();
+    conn.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function netCloseFlushesAndSendsFin(): Promise<void> {
+    const addr = { hostname: "127.0.0.1", port: 3500 };
+    const listener = Deno.listen(addr);
+    const data = new Uint8Array(1024 * 1024).fill(42);
+    listener.accept().then(
+      async (conn): Promise<void> => {
+        await Deno.writeAll(conn, data);
+        conn.close();
+        // The rid is gone right away, even though the socket is still being
+        // shut down.
+        assert(!(conn.rid in Deno.resources()));
+      }
+    );
+
+    const conn = await Deno.connect(addr);
+    const received = await Deno.readAll(conn);
+    assertEquals(received.byteLength, data.byteLength);
+    listener.close();
+    conn.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function netSetIdleTimeouts(): Promise<void> {
+    const addr = { hostname: "127.0.0.1", port: 3500 };
+    const listener = Deno.listen(addr);
+    const acceptPromise = listener.accept();
+    const conn = await Deno.connect(addr);
+    const serverConn = await acceptPromise;
+
+    Deno.setIdleTimeouts(serverConn.rid, { read: 50 });
+    const buf = new Uint8Array(8);
+    await assertThrowsAsync(async (): Promise<void> => {
+      await serverConn.read(buf);
+    }, Deno.errors.TimedOut);
+
+    // The connection is still usable after a timeout.
+    await conn.write(new Uint8Array([1, 2, 3]));
+    assertEquals(await serverConn.read(buf), 3);
+
+    Deno.setIdleTimeouts(serverConn.rid, {});
+    const readPromise = serverConn.read(buf);
+    await conn.write(new Uint8Array([4]));
+    assertEquals(await readPromise, 1);
+
+    // Writes time out once the peer stopped reading, but each write that
+    // went through resolved with what it wrote.
+    Deno.setIdleTimeouts(conn.rid, { write: 50 });
+    const chunk = new Uint8Array(64 * 1024);
+    let written = 0;
+    await assertThrowsAsync(async (): Promise<void> => {
+      for (;;) {
+        written += await conn.write(chunk);
+      }
+    }, Deno.errors.TimedOut);
+    assert(written > 0);
+
+    serverConn.close();
+    conn.close();
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true } },
+  function setIdleTimeoutsNotConnection(): void {
+    const file = Deno.openSync("cli/tests/hello.txt");
+    assertThrows((): void => {
+      Deno.setIdleTimeouts(file.rid, { read: 100 });
+    }, Deno.errors.BadResource);
+    file.close();
+  }
+);
@@ -286,6 +286,49 @@ unitTest(
   }
 );
 
+unitTest(
+  { perms: { run: true } },
+  async function runStderrMergedIntoStdout(): Promise<void> {
+    const p = run({
+      cmd: [
+        "python",
+        "-c",
+        "import sys; sys.stderr.write('error\\n'); sys.stderr.flush(); sys.stdout.write('output\\n');",
+      ],
+      stdout: "piped",
+      stderr: "stdout",
+    });
+    assert(!p.stderr);
+    const text = new TextDecoder().decode(await p.output());
+    assertEquals(text, "error\noutput\n");
+    assert((await p.status()).success);
+    p.close();
+  }
+);
+
+unitTest(
+  { ignore: Deno.build.os === "windows", perms: { run: true } },
+  async function runPipeBetweenProcesses(): Promise<void> {
+    const p1 = run({
+      cmd: ["python", "-c", "import sys; sys.stdout.write('hello')"],
+      stdout: "piped",
+    });
+    const p2 = run({
+      cmd: ["python", "-c", "import sys; sys.stdout.write(sys.stdin.read())"],
+      stdin: p1.stdout!.rid,
+      stdout: "piped",
+    });
+    // The pipe was handed over to the second process.
+    assert(!(p1.stdout!.rid in Deno.resources()));
+    const output = await p2.output();
+    assertEquals(new TextDecoder().decode(output), "hello");
+    await p1.status();
+    await p2.status();
+    p1.close();
+    p2.close();
+  }
+);
+
 unitTest({ perms: { run: true } }, async function runEnv(): Promise<void> {
   const p = run({
     cmd: [
@@ -0,0 +1,52 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { unitTest, assert, assertEquals } from "./test_util.ts";
+
+const {
+  readableStreamFromResource,
+  writableStreamFromResource,
+  // @ts-expect-error TypeScript (as of 3.7) does not support indexing namespaces by symbol
+} = Deno[Deno.internal];
+
+unitTest(
+  { perms: { read: true } },
+  async function readableStreamFromFile(): Promise<void> {
+    const filename = "cli/tests/fixture.json";
+    const file = await Deno.open(filename);
+    const stream = readableStreamFromResource(file.rid, { chunkSize: 16 });
+    const chunks: Uint8Array[] = [];
+    for await (const chunk of stream) {
+      assert(chunk.byteLength <= 16);
+      chunks.push(chunk);
+    }
+    const contents = new Uint8Array(
+      chunks.reduce((len, chunk): number => len + chunk.byteLength, 0)
+    );
+    let offset = 0;
+    for (const chunk of chunks) {
+      contents.set(chunk, offset);
+      offset += chunk.byteLength;
+    }
+    assertEquals(contents, await Deno.readFile(filename));
+    // The file was closed at the end of the stream.
+    assert(!(file.rid in Deno.resources()));
+  }
+);
+
+unitTest(
+  { perms: { read: true, write: true } },
+  async function writableStreamToFile(): Promise<void> {
+    const filename = Deno.makeTempDirSync() + "/test.txt";
+    const file = await Deno.open(filename, { write: true, create: true });
+    const stream = writableStreamFromResource(file.rid, { highWaterMark: 4 });
+    const writer = stream.getWriter();
+    const encoder = new TextEncoder();
+    await writer.ready;
+    writer.write(encoder.encode("hello "));
+    // The queue holds more than the high water mark now.
+    assert(writer.desiredSize! <= 0);
+    writer.write(encoder.encode("world"));
+    await writer.close();
+    assertEquals(await Deno.readTextFile(filename), "hello world");
+    assert(!(file.rid in Deno.resources()));
+  }
+);
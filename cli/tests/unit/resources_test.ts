@@ -59,3 +59,51 @@ unitTest({ perms: { read: true } }, async function resourcesFile(): Promise<
   })!;
   assertEquals(resourcesAfter[newRid], "fsFile");
 });
+
+unitTest({ perms: { read: true } }, function resourcesDup(): void {
+  const f = Deno.openSync("cli/tests/hello.txt");
+  const rid = Deno.dup(f.rid);
+  assert(rid !== f.rid);
+  assertEquals(Deno.resources()[rid], "fsFile");
+
+  // Both resources share the position in the file.
+  const buf = new Uint8Array(5);
+  assertEquals(Deno.readSync(f.rid, buf), 5);
+  f.close();
+  const rest = new Uint8Array(16);
+  const nread = Deno.readSync(rid, rest);
+  assertEquals(new TextDecoder().decode(rest.subarray(0, nread!)), " world!");
+  Deno.close(rid);
+});
+
+unitTest(
+  { ignore: Deno.build.os === "windows", perms: { read: true, write: true } },
+  async function resourcesSendReceive(): Promise<void> {
+    const filePath = Deno.makeTempDirSync() + "/resources.sock";
+    const listener = Deno.listen({ path: filePath, transport: "unix" });
+    const [sender, receiver] = await Promise.all([
+      listener.accept(),
+      Deno.connect({ path: filePath, transport: "unix" }),
+    ]);
+
+    const f = await Deno.open("cli/tests/hello.txt");
+    await Deno.sendResources(sender.rid, [f.rid]);
+    f.close();
+    const received = await Deno.receiveResources(receiver.rid);
+    assert(received !== null);
+    assertEquals(received.length, 1);
+    assertEquals(received[0].kind, "fsFile");
+    const buf = new Uint8Array(16);
+    const nread = await Deno.read(received[0].rid, buf);
+    assertEquals(
+      new TextDecoder().decode(buf.subarray(0, nread!)),
+      "Hello world!"
+    );
+    Deno.close(received[0].rid);
+
+    sender.close();
+    assertEquals(await Deno.receiveResources(receiver.rid), null);
+    receiver.close();
+    listener.close();
+  }
+);
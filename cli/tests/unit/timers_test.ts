@@ -366,3 +366,33 @@ unitTest(async function timerNestedMicrotaskOrdering(): Promise<void> {
 unitTest(function testQueueMicrotask() {
   assertEquals(typeof queueMicrotask, "function");
 });
+
+unitTest(async function timeoutsWithSameDelayFireInOrder(): Promise<void> {
+  const start = Date.now();
+  const fired: number[] = [];
+  const { promise, resolve } = deferred();
+  for (let i = 0; i < 20; i++) {
+    setTimeout((): void => {
+      fired.push(i);
+      if (fired.length === 20) {
+        resolve();
+      }
+    }, 1000);
+  }
+  await promise;
+  assert(Date.now() - start >= 1000);
+  assertEquals(fired, [...Array(20).keys()]);
+});
+
+unitTest(async function intervalStopsWhenCleared(): Promise<void> {
+  let count = 0;
+  const id = setInterval((): void => {
+    count++;
+  }, 10);
+  await waitForMs(55);
+  clearInterval(id);
+  const countAtClear = count;
+  assert(countAtClear >= 1);
+  await waitForMs(50);
+  assertEquals(count, countAtClear);
+});
@@ -1,5 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-import { unitTest, assert } from "./test_util.ts";
+import { unitTest, assert, assertThrows } from "./test_util.ts";
 
 // Note tests for Deno.setRaw is in integration tests.
 
@@ -21,3 +21,18 @@ unitTest(function isattyError(): void {
   }
   assert(caught);
 });
+
+unitTest({ perms: { read: true } }, function consoleSizeNotTty(): void {
+  const f = Deno.openSync("cli/tests/hello.txt");
+  assertThrows((): void => {
+    Deno.consoleSize(f.rid);
+  });
+  f.close();
+});
+
+unitTest(function consoleSizeError(): void {
+  assertThrows((): void => {
+    // Absurdly large rid.
+    Deno.consoleSize(0x7fffffff);
+  }, Deno.errors.BadResource);
+});
@@ -5,6 +5,7 @@
 // Test runner automatically spawns subprocesses for each required permissions combination.
 
 import "./abort_controller_test.ts";
+import "./bench_test.ts";
 import "./blob_test.ts";
 import "./body_test.ts";
 import "./buffer_test.ts";
@@ -30,6 +31,7 @@ import "./fs_events_test.ts";
 import "./get_random_values_test.ts";
 import "./globals_test.ts";
 import "./headers_test.ts";
+import "./intercept_op_test.ts";
 import "./internals_test.ts";
 import "./io_test.ts";
 import "./link_test.ts";
@@ -49,6 +51,7 @@ import "./read_link_test.ts";
 import "./remove_test.ts";
 import "./rename_test.ts";
 import "./request_test.ts";
+import "./resource_stream_test.ts";
 import "./resources_test.ts";
 import "./signal_test.ts";
 import "./stat_test.ts";
@@ -68,6 +71,7 @@ import "./url_test.ts";
 import "./url_search_params_test.ts";
 import "./utime_test.ts";
 import "./write_file_test.ts";
+import "./write_heap_snapshot_test.ts";
 import "./write_text_file_test.ts";
 import "./performance_test.ts";
 import "./version_test.ts";
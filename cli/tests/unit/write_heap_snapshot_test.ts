@@ -0,0 +1,31 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { unitTest, assert, assertEquals } from "./test_util.ts";
+
+unitTest(
+  { perms: { read: true, write: true } },
+  async function writeHeapSnapshotSuccess(): Promise<void> {
+    const filename = Deno.makeTempDirSync() + "/test.heapsnapshot";
+    // Something to find in the snapshot.
+    const marker = "writeHeapSnapshotSuccess marker";
+    await Deno.writeHeapSnapshot(filename);
+    const snapshot = JSON.parse(Deno.readTextFileSync(filename));
+    assert(snapshot.snapshot.node_count > 0);
+    assert(Array.isArray(snapshot.nodes));
+    assert(snapshot.strings.includes(marker));
+    Deno.removeSync(filename);
+  }
+);
+
+unitTest(
+  { perms: { write: false } },
+  async function writeHeapSnapshotPerm(): Promise<void> {
+    let err;
+    try {
+      await Deno.writeHeapSnapshot("/test.heapsnapshot");
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.PermissionDenied);
+    assertEquals(err.name, "PermissionDenied");
+  }
+);
@@ -0,0 +1,7 @@
+// UDP listeners are unstable; the op throws instead of exiting the process.
+try {
+  Deno.listen({ transport: "udp", port: 4509 });
+} catch (err) {
+  console.log(err instanceof Error, err.message);
+}
+console.log("still running");
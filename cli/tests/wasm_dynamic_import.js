@@ -0,0 +1,11 @@
+(async () => {
+  const { addImported } = await import("./add.wasm");
+  console.log(addImported(1, 2));
+
+  try {
+    await import("./subdir/malformed.wasm");
+  } catch (err) {
+    console.log("Caught malformed wasm import error.");
+    console.log(err);
+  }
+})();
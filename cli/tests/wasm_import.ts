@@ -0,0 +1,2 @@
+import { addImported } from "./add.wasm";
+console.log(addImported(40, 2));
@@ -307,3 +307,35 @@ Deno.test({
     w.terminate();
   },
 });
+
+Deno.test({
+  name: "worker transfers resources",
+  fn: async function (): Promise<void> {
+    const promise = createResolvable();
+    const worker = new Worker("../tests/subdir/resource_worker.js", {
+      type: "module",
+      deno: true,
+    });
+    const listener = Deno.listen({ port: 4510 });
+    const client = await Deno.connect({ port: 4510 });
+    const conn = await listener.accept();
+
+    worker.onmessage = (e): void => {
+      assertEquals(e.data, "conn");
+      assertEquals(e.resources.length, 1);
+      assertEquals(e.resources[0].kind, "tcpStream");
+      Deno.close(e.resources[0].rid);
+      promise.resolve();
+    };
+    worker.postMessage("conn", { resources: [conn.rid] });
+    assert(!(conn.rid in Deno.resources()));
+
+    const buf = new Uint8Array(5);
+    assertEquals(await client.read(buf), 5);
+    assertEquals(new TextDecoder().decode(buf), "hello");
+    await promise;
+    client.close();
+    listener.close();
+    worker.terminate();
+  },
+});
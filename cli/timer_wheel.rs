@@ -0,0 +1,358 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! This module implements the timers behind setTimeout and setInterval.
+//!
+//! All the timers of an isolate are kept here, by the slot of the wheel they
+//! are due in, and a single tokio Delay is armed for the earliest slot. JS
+//! keeps one `op_timer_poll` pending while it has timers, which resolves with
+//! the ids of the timers that are due; scheduling and canceling timers are
+//! synchronous ops, so they don't leave futures behind.
+//!
+//! Deadlines are rounded up to the end of their slot. Slots are 1ms wide for
+//! short timeouts and get wider with the timeout, up to 1/64th of it, so that
+//! long timeouts set around the same time are fired by the same wakeup.
+//!
+//! With `--frozen-time` the wheel runs on a virtual clock instead, which only
+//! moves when `step()` is called. Deadlines are kept exact then, and the
+//! timers that are due without the clock moving, like `setTimeout(f, 0)`, fire
+//! as usual.
+
+use futures::ready;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::time::Delay;
+
+struct Timer {
+  /// When the timer is due, in milliseconds since the wheel was created.
+  deadline: u64,
+  slot: u64,
+  delay: u64,
+  repeat: bool,
+}
+
+pub struct TimerWheel {
+  start: Instant,
+  timers: HashMap<u32, Timer>,
+  /// Ids of the scheduled timers, by slot, in the order they were scheduled.
+  slots: BTreeMap<u64, Vec<u32>>,
+  delay: Option<Delay>,
+  waker: Option<Waker>,
+  /// The virtual time in milliseconds, if the clock is frozen.
+  frozen: Option<u64>,
+}
+
+impl Default for TimerWheel {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Returns the end of the slot `deadline` falls in, which is also when it
+/// fires.
+fn slot_of(deadline: u64, delay: u64) -> u64 {
+  let width = (delay / 64).max(1);
+  // Round the width down to a power of two, so that the slots of close
+  // timeouts line up.
+  let width = 1 << (63 - width.leading_zeros());
+  (deadline + width - 1) / width * width
+}
+
+impl TimerWheel {
+  pub fn new() -> Self {
+    Self {
+      start: Instant::now(),
+      timers: HashMap::new(),
+      slots: BTreeMap::new(),
+      delay: None,
+      waker: None,
+      frozen: None,
+    }
+  }
+
+  /// Creates a wheel whose clock starts at 0 and only moves with `step()`.
+  pub fn new_frozen() -> Self {
+    Self {
+      frozen: Some(0),
+      ..Self::new()
+    }
+  }
+
+  /// The virtual time in milliseconds, or `None` if the clock isn't frozen.
+  pub fn frozen_now(&self) -> Option<u64> {
+    self.frozen
+  }
+
+  /// Milliseconds since the wheel was created, rounded up so that timers
+  /// never fire early.
+  fn now(&self) -> u64 {
+    if let Some(now) = self.frozen {
+      return now;
+    }
+    let elapsed = self.start.elapsed();
+    (elapsed.as_micros() as u64 + 999) / 1000
+  }
+
+  fn slot_of(&self, deadline: u64, delay: u64) -> u64 {
+    if self.frozen.is_some() {
+      deadline
+    } else {
+      slot_of(deadline, delay)
+    }
+  }
+
+  fn insert(&mut self, id: u32, timer: Timer) {
+    let is_earliest = match self.slots.keys().next() {
+      Some(&slot) => timer.slot < slot,
+      None => true,
+    };
+    self
+      .slots
+      .entry(timer.slot)
+      .or_insert_with(Vec::new)
+      .push(id);
+    self.timers.insert(id, timer);
+    // The pending poll has to arm its delay for the new slot.
+    if is_earliest {
+      self.wake();
+    }
+  }
+
+  fn wake(&mut self) {
+    if let Some(waker) = self.waker.take() {
+      waker.wake();
+    }
+  }
+
+  /// Schedules the timer `id` to fire in `delay` milliseconds, and then every
+  /// `delay` milliseconds if `repeat` is set. A timer that was already
+  /// scheduled with this id is replaced.
+  pub fn start(&mut self, id: u32, delay: u64, repeat: bool) {
+    self.cancel(id);
+    let deadline = self.now() + delay;
+    let timer = Timer {
+      deadline,
+      slot: self.slot_of(deadline, delay),
+      delay,
+      repeat,
+    };
+    self.insert(id, timer);
+  }
+
+  /// Cancels the timer `id`, if it is scheduled.
+  pub fn cancel(&mut self, id: u32) {
+    let timer = match self.timers.remove(&id) {
+      Some(timer) => timer,
+      None => return,
+    };
+    let ids = self.slots.get_mut(&timer.slot).unwrap();
+    ids.retain(|&other| other != id);
+    if ids.is_empty() {
+      self.slots.remove(&timer.slot);
+    }
+    // The pending poll resolves right away once there's nothing left to wait
+    // for.
+    if self.slots.is_empty() {
+      self.wake();
+    }
+  }
+
+  /// Takes the timers that are due at `now`, ordered by deadline, and
+  /// schedules the next run of the repeating ones.
+  fn take_due(&mut self, now: u64) -> Vec<u32> {
+    let mut due = vec![];
+    while let Some(&slot) = self.slots.keys().next() {
+      if slot > now {
+        break;
+      }
+      for id in self.slots.remove(&slot).unwrap() {
+        let timer = self.timers.remove(&id).unwrap();
+        due.push((id, timer));
+      }
+    }
+    // Slots are in order, but the deadlines within a slot are not. The sort is
+    // stable, so timers with the same deadline fire in the order they were
+    // scheduled in.
+    due.sort_by_key(|(_, timer)| timer.deadline);
+
+    let mut ids = Vec::with_capacity(due.len());
+    for (id, timer) in due {
+      ids.push(id);
+      if timer.repeat {
+        // Intervals don't drift, but they aren't scheduled in the past
+        // either when they fell behind.
+        let deadline = std::cmp::max(now, timer.deadline + timer.delay);
+        let timer = Timer {
+          deadline,
+          slot: self.slot_of(deadline, timer.delay),
+          ..timer
+        };
+        self.insert(id, timer);
+      }
+    }
+    ids
+  }
+
+  /// Moves the frozen clock to the earliest deadline up to `target` and
+  /// returns the ids of the timers that are due then, or moves it to `target`
+  /// and returns `None` once no timer is due before. The clock never goes
+  /// back.
+  pub fn step(&mut self, target: u64) -> Option<Vec<u32>> {
+    let now = self.frozen.expect("the clock isn't frozen");
+    let due = match self.slots.keys().next() {
+      Some(&slot) if slot <= target => {
+        let now = std::cmp::max(now, slot);
+        self.frozen = Some(now);
+        Some(self.take_due(now))
+      }
+      _ => {
+        self.frozen = Some(std::cmp::max(now, target));
+        None
+      }
+    };
+    // Like with `cancel()`, the pending poll resolves once there's nothing
+    // left to wait for.
+    if self.slots.is_empty() {
+      self.wake();
+    }
+    due
+  }
+
+  /// Resolves with the ids of the timers that are due, as soon as there are
+  /// some, or with no ids when no timer is scheduled anymore. On the frozen
+  /// clock it never waits: it resolves with no ids when no timer is due yet,
+  /// so that timers which only `step()` can make due don't keep the program
+  /// alive.
+  pub fn poll_due(&mut self, cx: &mut Context) -> Poll<Vec<u32>> {
+    self.waker = Some(cx.waker().clone());
+    loop {
+      let slot = match self.slots.keys().next() {
+        Some(&slot) => slot,
+        None => {
+          self.delay = None;
+          return Poll::Ready(vec![]);
+        }
+      };
+      if let Some(now) = self.frozen {
+        // Nothing but `step()` moves the clock, which takes the timers it
+        // finds due itself.
+        if slot > now {
+          self.waker = None;
+          return Poll::Ready(vec![]);
+        }
+        return Poll::Ready(self.take_due(now));
+      }
+      let deadline: tokio::time::Instant =
+        (self.start + Duration::from_millis(slot)).into();
+      match &mut self.delay {
+        Some(delay) if delay.deadline() == deadline => {}
+        Some(delay) => delay.reset(deadline),
+        None => self.delay = Some(tokio::time::delay_until(deadline)),
+      }
+      ready!(Pin::new(self.delay.as_mut().unwrap()).poll(cx));
+      self.delay = None;
+
+      let now = self.start.elapsed().as_millis() as u64;
+      let ids = self.take_due(std::cmp::max(slot, now));
+      if !ids.is_empty() {
+        return Poll::Ready(ids);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn timer(deadline: u64, delay: u64, repeat: bool) -> Timer {
+    Timer {
+      deadline,
+      slot: slot_of(deadline, delay),
+      delay,
+      repeat,
+    }
+  }
+
+  #[test]
+  fn slots() {
+    assert_eq!(slot_of(0, 0), 0);
+    assert_eq!(slot_of(7, 5), 7);
+    assert_eq!(slot_of(1003, 1000), 1008);
+    assert_eq!(slot_of(1008, 1000), 1008);
+    assert_eq!(slot_of(61_000, 60_000), 61_440);
+  }
+
+  #[test]
+  fn take_due() {
+    let mut wheel = TimerWheel::new();
+    wheel.insert(1, timer(1005, 1000, false));
+    wheel.insert(2, timer(1002, 1000, false));
+    wheel.insert(3, timer(1002, 1000, false));
+    wheel.insert(4, timer(1009, 1000, false));
+    wheel.insert(5, timer(2, 2, false));
+    assert_eq!(wheel.take_due(1), Vec::<u32>::new());
+    assert_eq!(wheel.take_due(1008), vec![5, 2, 3, 1]);
+    assert_eq!(wheel.take_due(1016), vec![4]);
+    assert!(wheel.timers.is_empty());
+    assert!(wheel.slots.is_empty());
+  }
+
+  #[test]
+  fn take_due_repeat() {
+    let mut wheel = TimerWheel::new();
+    wheel.insert(1, timer(10, 10, true));
+    assert_eq!(wheel.take_due(10), vec![1]);
+    assert_eq!(wheel.timers[&1].deadline, 20);
+    // An interval that fell behind is due right away, but only once.
+    assert_eq!(wheel.take_due(45), vec![1]);
+    assert_eq!(wheel.timers[&1].deadline, 45);
+  }
+
+  #[test]
+  fn frozen() {
+    let mut wheel = TimerWheel::new_frozen();
+    wheel.start(1, 1000, false);
+    wheel.start(2, 300, true);
+    wheel.start(3, 0, false);
+    assert_eq!(wheel.frozen_now(), Some(0));
+    // The clock stands still until it's stepped.
+    assert_eq!(wheel.take_due(wheel.now()), vec![3]);
+    assert_eq!(wheel.step(1000), Some(vec![2]));
+    assert_eq!(wheel.frozen_now(), Some(300));
+    assert_eq!(wheel.step(1000), Some(vec![2]));
+    assert_eq!(wheel.step(1000), Some(vec![2]));
+    assert_eq!(wheel.frozen_now(), Some(900));
+    // Deadlines are exact.
+    assert_eq!(wheel.step(1000), Some(vec![1]));
+    assert_eq!(wheel.step(1000), None);
+    assert_eq!(wheel.frozen_now(), Some(1000));
+    assert_eq!(wheel.step(1100), None);
+    assert_eq!(wheel.frozen_now(), Some(1100));
+    assert_eq!(wheel.timers[&2].deadline, 1200);
+    // Timers which aren't due don't keep the poll pending.
+    let waker = futures::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert_eq!(wheel.poll_due(&mut cx), Poll::Ready(vec![]));
+    wheel.start(4, 0, false);
+    assert_eq!(wheel.poll_due(&mut cx), Poll::Ready(vec![4]));
+  }
+
+  #[test]
+  fn cancel() {
+    let mut wheel = TimerWheel::new();
+    wheel.start(1, 10, false);
+    wheel.start(2, 10, true);
+    wheel.cancel(1);
+    wheel.cancel(2);
+    wheel.cancel(3);
+    assert!(wheel.timers.is_empty());
+    assert!(wheel.slots.is_empty());
+  }
+}
@@ -13,6 +13,10 @@ use crate::msg;
 use crate::op_error::OpError;
 use crate::ops;
 use crate::permissions::Permissions;
+use crate::source_maps::decode_data_url;
+use crate::source_maps::find_source_mapping_url;
+use crate::source_maps::resolve_sources;
+use crate::source_maps::CachedMaps;
 use crate::source_maps::SourceMapGetter;
 use crate::startup_data;
 use crate::state::State;
@@ -28,7 +32,6 @@ use futures::future::Either;
 use futures::future::Future;
 use futures::future::FutureExt;
 use log::info;
-use regex::Regex;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::json;
@@ -154,10 +157,123 @@ impl Future for CompilerWorker {
   }
 }
 
-// TODO(bartlomieju): use JSONC parser from dprint instead of Regex
-lazy_static! {
-  static ref CHECK_JS_RE: Regex =
-    Regex::new(r#""checkJs"\s*?:\s*?true"#).unwrap();
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CompilerOptionType {
+  Boolean,
+  String,
+  StringArray,
+}
+
+/// Compiler options that Deno forwards to the compiler, along with the type
+/// of value they take. Options that are ignored are listed in
+/// `IGNORED_COMPILER_OPTIONS` in `cli/js/compiler.ts`; anything else is left
+/// to the compiler to report.
+const SUPPORTED_COMPILER_OPTIONS: &[(&str, CompilerOptionType)] = &[
+  ("allowJs", CompilerOptionType::Boolean),
+  ("allowUmdGlobalAccess", CompilerOptionType::Boolean),
+  ("allowUnreachableCode", CompilerOptionType::Boolean),
+  ("allowUnusedLabels", CompilerOptionType::Boolean),
+  ("alwaysStrict", CompilerOptionType::Boolean),
+  (
+    "assumeChangesOnlyAffectDirectDependencies",
+    CompilerOptionType::Boolean,
+  ),
+  ("checkJs", CompilerOptionType::Boolean),
+  ("disableSizeLimit", CompilerOptionType::Boolean),
+  ("generateCpuProfile", CompilerOptionType::String),
+  ("jsx", CompilerOptionType::String),
+  ("jsxFactory", CompilerOptionType::String),
+  ("lib", CompilerOptionType::StringArray),
+  ("noFallthroughCasesInSwitch", CompilerOptionType::Boolean),
+  ("noImplicitAny", CompilerOptionType::Boolean),
+  ("noImplicitReturns", CompilerOptionType::Boolean),
+  ("noImplicitThis", CompilerOptionType::Boolean),
+  ("noImplicitUseStrict", CompilerOptionType::Boolean),
+  ("noStrictGenericChecks", CompilerOptionType::Boolean),
+  ("noUnusedLocals", CompilerOptionType::Boolean),
+  ("noUnusedParameters", CompilerOptionType::Boolean),
+  ("preserveConstEnums", CompilerOptionType::Boolean),
+  ("removeComments", CompilerOptionType::Boolean),
+  ("resolveJsonModule", CompilerOptionType::Boolean),
+  ("strict", CompilerOptionType::Boolean),
+  ("strictBindCallApply", CompilerOptionType::Boolean),
+  ("strictFunctionTypes", CompilerOptionType::Boolean),
+  ("strictNullChecks", CompilerOptionType::Boolean),
+  ("strictPropertyInitialization", CompilerOptionType::Boolean),
+  ("suppressExcessPropertyErrors", CompilerOptionType::Boolean),
+  (
+    "suppressImplicitAnyIndexErrors",
+    CompilerOptionType::Boolean,
+  ),
+  ("useDefineForClassFields", CompilerOptionType::Boolean),
+];
+
+const JSX_MODES: &[&str] = &["react", "preserve", "react-native"];
+
+/// Checks the values of the supported options in the `compilerOptions` of a
+/// config file, so that mistakes are reported before the compiler starts.
+///
+/// Returns the validated options, or `None` if the file has none.
+fn validate_compiler_options(
+  config_path: &str,
+  config_text: &str,
+) -> Result<Option<serde_json::Map<String, Value>>, ErrBox> {
+  let invalid = |msg: String| -> ErrBox {
+    OpError::other(format!("Invalid config file \"{}\": {}", config_path, msg))
+      .into()
+  };
+  let stripped =
+    crate::jsonc::strip_json_comments(config_text).map_err(invalid)?;
+  let config: Value =
+    serde_json::from_str(&stripped).map_err(|e| invalid(e.to_string()))?;
+  let options = match config.get("compilerOptions") {
+    None => return Ok(None),
+    Some(Value::Object(options)) => options.clone(),
+    Some(_) => {
+      return Err(invalid("\"compilerOptions\" must be an object".to_string()))
+    }
+  };
+
+  for (name, ty) in SUPPORTED_COMPILER_OPTIONS {
+    let value = match options.get(*name) {
+      Some(value) => value,
+      None => continue,
+    };
+    let valid = match ty {
+      CompilerOptionType::Boolean => value.is_boolean(),
+      CompilerOptionType::String => value.is_string(),
+      CompilerOptionType::StringArray => value
+        .as_array()
+        .map_or(false, |values| values.iter().all(Value::is_string)),
+    };
+    if !valid {
+      let expected = match ty {
+        CompilerOptionType::Boolean => "a boolean",
+        CompilerOptionType::String => "a string",
+        CompilerOptionType::StringArray => "an array of strings",
+      };
+      return Err(invalid(format!(
+        "compiler option \"{}\" must be {}, found {}",
+        name, expected, value
+      )));
+    }
+  }
+
+  if let Some(jsx) = options.get("jsx").and_then(Value::as_str) {
+    if !JSX_MODES.contains(&jsx.to_lowercase().as_str()) {
+      return Err(invalid(format!(
+        "compiler option \"jsx\" must be one of {}, found \"{}\"",
+        JSX_MODES
+          .iter()
+          .map(|mode| format!("\"{}\"", mode))
+          .collect::<Vec<_>>()
+          .join(", "),
+        jsx
+      )));
+    }
+  }
+
+  Ok(Some(options))
 }
 
 /// Create a new worker with snapshot of TS compiler and setup compiler's
@@ -216,7 +332,7 @@ impl CompilerConfig {
     let config_file = match &config_path {
       Some(config_file_name) => {
         debug!("Compiler config file: {}", config_file_name);
-        let cwd = std::env::current_dir().unwrap();
+        let cwd = std::env::current_dir()?;
         Some(cwd.join(config_file_name))
       }
       _ => None,
@@ -254,11 +370,14 @@ impl CompilerConfig {
 
     // If `checkJs` is set to true in `compilerOptions` then we're gonna be compiling
     // JavaScript files as well
-    let compile_js = if let Some(config_content) = config.clone() {
-      let config_str = std::str::from_utf8(&config_content)?;
-      CHECK_JS_RE.is_match(config_str)
-    } else {
-      false
+    let compile_js = match (&config_path, &config) {
+      (Some(Ok(path)), Some(config_content)) => {
+        let config_str = std::str::from_utf8(config_content)?;
+        validate_compiler_options(&path.to_string_lossy(), config_str)?
+          .and_then(|options| options.get("checkJs").and_then(Value::as_bool))
+          .unwrap_or(false)
+      }
+      _ => false,
     };
 
     let ts_config = Self {
@@ -316,7 +435,15 @@ impl GraphFileMetadata {
   }
 }
 
-/// Emit a SHA256 hash based on source code, deno version and TS config.
+/// Version string of the toolchain producing the compiled output.
+///
+/// Both a new deno release and a new bundled TypeScript may emit different
+/// JavaScript for the same source, so cached output is keyed on both.
+pub fn compiler_version() -> String {
+  format!("{}+ts.{}", version::DENO, version::TYPESCRIPT)
+}
+
+/// Emit a SHA256 hash based on source code, compiler version and TS config.
 /// Used to check if a recompilation for source code is needed.
 pub fn source_code_version_hash(
   source_code: &[u8],
@@ -338,6 +465,17 @@ pub struct TsCompilerInner {
   pub use_disk_cache: bool,
   /// This setting is controlled by `compilerOptions.checkJs`
   pub compile_js: bool,
+  /// This setting is controlled by `--no-check` flag. If it's provided
+  /// modules are only transpiled, without type checking.
+  pub no_check: bool,
+  /// Source maps parsed from the on-disk cache, populated lazily the first
+  /// time a stack frame from a given module is mapped.
+  pub source_maps: Mutex<CachedMaps>,
+  /// Modules whose compiled output is never written to the on-disk cache,
+  /// like a program read from stdin.
+  pub memory_only: Mutex<HashSet<Url>>,
+  /// Compiled output of `memory_only` modules, keyed by cache filename.
+  pub memory_cache: Mutex<HashMap<PathBuf, Vec<u8>>>,
 }
 
 #[derive(Clone)]
@@ -393,18 +531,67 @@ impl TsCompiler {
     disk_cache: DiskCache,
     use_disk_cache: bool,
     config_path: Option<String>,
+    no_check: bool,
   ) -> Result<Self, ErrBox> {
     let config = CompilerConfig::load(config_path)?;
     Ok(TsCompiler(Arc::new(TsCompilerInner {
       file_fetcher,
       disk_cache,
       compile_js: config.compile_js,
+      no_check,
       config,
       compiled: Mutex::new(HashSet::new()),
       use_disk_cache,
+      source_maps: Mutex::new(HashMap::new()),
+      memory_only: Mutex::new(HashSet::new()),
+      memory_cache: Mutex::new(HashMap::new()),
     })))
   }
 
+  /// Keeps the compiled output of given module in memory instead of the
+  /// on-disk cache, so that it's gone once the process exits.
+  pub fn keep_in_memory(&self, url: &Url) {
+    self.memory_only.lock().unwrap().insert(url.clone());
+  }
+
+  fn cache_get(&self, url: &Url, extension: &str) -> std::io::Result<Vec<u8>> {
+    let cache_key = self
+      .disk_cache
+      .get_cache_filename_with_extension(url, extension);
+    if !self.memory_only.lock().unwrap().contains(url) {
+      return self.disk_cache.get(&cache_key);
+    }
+    self
+      .memory_cache
+      .lock()
+      .unwrap()
+      .get(&cache_key)
+      .cloned()
+      .ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "Not in the memory cache")
+      })
+  }
+
+  fn cache_set(
+    &self,
+    url: &Url,
+    extension: &str,
+    data: &[u8],
+  ) -> std::io::Result<()> {
+    let cache_key = self
+      .disk_cache
+      .get_cache_filename_with_extension(url, extension);
+    if !self.memory_only.lock().unwrap().contains(url) {
+      return self.disk_cache.set(&cache_key, data);
+    }
+    self
+      .memory_cache
+      .lock()
+      .unwrap()
+      .insert(cache_key, data.to_vec());
+    Ok(())
+  }
+
   // TODO(bartlomieju): this method is no longer needed
   /// Mark given module URL as compiled to avoid multiple compilations of same
   /// module in single run.
@@ -430,7 +617,7 @@ impl TsCompiler {
         // TODO: it would probably be good idea to make it method implemented on SourceFile
         let version_hash_to_validate = source_code_version_hash(
           &source_file.source_code,
-          version::DENO,
+          &compiler_version(),
           &self.config.hash,
         );
 
@@ -466,12 +653,8 @@ impl TsCompiler {
       if let Some(metadata) = self.get_graph_metadata(&source_file.url) {
         has_cached_version = true;
 
-        let version_hash = crate::checksum::gen(vec![
-          version::DENO.as_bytes(),
-          &self.config.hash,
-        ]);
-
-        has_cached_version &= metadata.version_hash == version_hash;
+        has_cached_version &=
+          metadata.version_hash == self.graph_version_hash();
         has_cached_version &= self
           .has_compiled_source(&global_state.file_fetcher, &source_file.url);
 
@@ -507,6 +690,7 @@ impl TsCompiler {
         "rootNames": root_names,
         "bundle": bundle,
         "unstable": unstable,
+        "noCheck": self.no_check,
         "configPath": config_path,
         "config": str::from_utf8(&config_data).unwrap(),
         "cwd": cwd,
@@ -518,6 +702,7 @@ impl TsCompiler {
         "rootNames": root_names,
         "bundle": bundle,
         "unstable": unstable,
+        "noCheck": self.no_check,
         "cwd": cwd,
         "sourceFileMap": module_graph_json,
       }),
@@ -552,13 +737,24 @@ impl TsCompiler {
     Ok(())
   }
 
+  /// Hash stored in graph metadata; individual module sources are validated
+  /// separately by `has_compiled_source`.
+  ///
+  /// Output of `--no-check` runs gets a different hash, so that the graph is
+  /// type checked the next time it's compiled without the flag.
+  fn graph_version_hash(&self) -> String {
+    let version = compiler_version();
+    let mut parts = vec![version.as_bytes(), &self.config.hash[..]];
+    if self.no_check {
+      parts.push(b"no-check");
+    }
+    crate::checksum::gen(parts)
+  }
+
   fn get_graph_metadata(&self, url: &Url) -> Option<GraphFileMetadata> {
     // Try to load cached version:
     // 1. check if there's 'meta' file
-    let cache_key = self
-      .disk_cache
-      .get_cache_filename_with_extension(url, "graph");
-    if let Ok(metadata_bytes) = self.disk_cache.get(&cache_key) {
+    if let Ok(metadata_bytes) = self.cache_get(url, "graph") {
       if let Ok(metadata) = std::str::from_utf8(&metadata_bytes) {
         if let Ok(read_metadata) =
           GraphFileMetadata::from_json_string(metadata.to_string())
@@ -576,8 +772,7 @@ impl TsCompiler {
     url: Url,
     emit_map: &HashMap<String, EmittedSource>,
   ) -> std::io::Result<()> {
-    let version_hash =
-      crate::checksum::gen(vec![version::DENO.as_bytes(), &self.config.hash]);
+    let version_hash = self.graph_version_hash();
     let mut deps = vec![];
 
     for (_emitted_name, source) in emit_map.iter() {
@@ -601,22 +796,14 @@ impl TsCompiler {
     }
 
     let graph_metadata = GraphFileMetadata { deps, version_hash };
-    let meta_key = self
-      .disk_cache
-      .get_cache_filename_with_extension(&url, "graph");
-    self
-      .disk_cache
-      .set(&meta_key, graph_metadata.to_json_string()?.as_bytes())
+    self.cache_set(&url, "graph", graph_metadata.to_json_string()?.as_bytes())
   }
 
   /// Get associated `CompiledFileMetadata` for given module if it exists.
   pub fn get_metadata(&self, url: &Url) -> Option<CompiledFileMetadata> {
     // Try to load cached version:
     // 1. check if there's 'meta' file
-    let cache_key = self
-      .disk_cache
-      .get_cache_filename_with_extension(url, "meta");
-    if let Ok(metadata_bytes) = self.disk_cache.get(&cache_key) {
+    if let Ok(metadata_bytes) = self.cache_get(url, "meta") {
       if let Ok(metadata) = std::str::from_utf8(&metadata_bytes) {
         if let Ok(read_metadata) =
           CompiledFileMetadata::from_json_string(metadata.to_string())
@@ -688,7 +875,7 @@ impl TsCompiler {
     let cache_key = self
       .disk_cache
       .get_cache_filename_with_extension(&module_url, "js");
-    let compiled_code = self.disk_cache.get(&cache_key)?;
+    let compiled_code = self.cache_get(&module_url, "js")?;
     let compiled_code_filename = self.disk_cache.location.join(cache_key);
     debug!("compiled filename: {:?}", compiled_code_filename);
 
@@ -741,15 +928,12 @@ impl TsCompiler {
 
     let contents = content_lines.join("\n");
 
-    let js_key = self
-      .disk_cache
-      .get_cache_filename_with_extension(module_specifier.as_url(), "js");
-    self.disk_cache.set(&js_key, contents.as_bytes())?;
+    self.cache_set(module_specifier.as_url(), "js", contents.as_bytes())?;
     self.mark_compiled(module_specifier.as_url());
 
     let version_hash = source_code_version_hash(
       &source_file.source_code,
-      version::DENO,
+      &compiler_version(),
       &self.config.hash,
     );
 
@@ -757,11 +941,9 @@ impl TsCompiler {
       source_path: source_file.filename,
       version_hash,
     };
-    let meta_key = self
-      .disk_cache
-      .get_cache_filename_with_extension(module_specifier.as_url(), "meta");
-    self.disk_cache.set(
-      &meta_key,
+    self.cache_set(
+      module_specifier.as_url(),
+      "meta",
       compiled_file_metadata.to_json_string()?.as_bytes(),
     )
   }
@@ -776,7 +958,7 @@ impl TsCompiler {
     let cache_key = self
       .disk_cache
       .get_cache_filename_with_extension(module_specifier.as_url(), "js.map");
-    let source_code = self.disk_cache.get(&cache_key)?;
+    let source_code = self.cache_get(module_specifier.as_url(), "js.map")?;
     let source_map_filename = self.disk_cache.location.join(cache_key);
     debug!("source map filename: {:?}", source_map_filename);
 
@@ -805,10 +987,6 @@ impl TsCompiler {
     let js_file_url =
       Url::from_file_path(js_path).expect("Bad file URL for file");
 
-    let source_map_key = self
-      .disk_cache
-      .get_cache_filename_with_extension(module_specifier.as_url(), "js.map");
-
     let mut sm = SourceMap::from_slice(contents.as_bytes())
       .expect("Invalid source map content");
     sm.set_file(Some(&js_file_url.to_string()));
@@ -818,7 +996,15 @@ impl TsCompiler {
     sm.to_writer(&mut output)
       .expect("Failed to write source map");
 
-    self.disk_cache.set(&source_map_key, &output)
+    // Drop any previously parsed map (or failed lookup) for this module so
+    // that the next lookup picks up the freshly emitted one.
+    self
+      .source_maps
+      .lock()
+      .unwrap()
+      .remove(&module_specifier.to_string());
+
+    self.cache_set(module_specifier.as_url(), "js.map", &output)
   }
 }
 
@@ -827,6 +1013,7 @@ impl SourceMapGetter for TsCompiler {
     self
       .try_to_resolve_and_get_source_map(script_name)
       .map(|out| out.source_code)
+      .or_else(|| self.try_to_get_declared_source_map(script_name))
   }
 
   fn get_source_line(&self, script_name: &str, line: usize) -> Option<String> {
@@ -836,9 +1023,8 @@ impl SourceMapGetter for TsCompiler {
         str::from_utf8(&out.source_code).ok().and_then(|v| {
           // Do NOT use .lines(): it skips the terminating empty line.
           // (due to internally using .split_terminator() instead of .split())
-          let lines: Vec<&str> = v.split('\n').collect();
-          assert!(lines.len() > line);
-          Some(lines[line].to_string())
+          // The line may come from a source map that doesn't match the file.
+          v.split('\n').nth(line).map(|l| l.to_string())
         })
       })
   }
@@ -879,6 +1065,32 @@ impl TsCompiler {
 
     None
   }
+
+  /// Looks up the source map a module that wasn't compiled by Deno refers to
+  /// itself, like the map of a bundle, either inline or next to the module.
+  fn try_to_get_declared_source_map(
+    &self,
+    script_name: &str,
+  ) -> Option<Vec<u8>> {
+    let source_file = self.try_resolve_and_get_source_file(script_name)?;
+    let map_url = find_source_mapping_url(&source_file.source_code)?;
+    if let Some(raw_source_map) = decode_data_url(&map_url) {
+      return resolve_sources(&raw_source_map, &source_file.url);
+    }
+    let map_specifier =
+      ModuleSpecifier::resolve_import(&map_url, source_file.url.as_str())
+        .ok()?;
+    // Like their imports, remote modules can't refer to local files.
+    if source_file.url.scheme() != "file"
+      && map_specifier.as_url().scheme() == "file"
+    {
+      return None;
+    }
+    let map_file = self
+      .file_fetcher
+      .fetch_cached_source_file(&map_specifier, Permissions::allow_all())?;
+    resolve_sources(&map_file.source_code, map_specifier.as_url())
+  }
 }
 
 async fn execute_in_same_thread(
@@ -901,7 +1113,7 @@ async fn execute_in_same_thread(
           .expect("Empty message");
 
         let buf = match event {
-          WorkerEvent::Message(buf) => Ok(buf),
+          WorkerEvent::Message(msg) => Ok(msg.data),
           WorkerEvent::Error(error) => Err(error),
           WorkerEvent::TerminalError(error) => Err(error),
         }?;
@@ -1111,6 +1323,41 @@ mod tests {
   use std::path::PathBuf;
   use tempfile::TempDir;
 
+  #[test]
+  fn test_validate_compiler_options() {
+    let options = validate_compiler_options(
+      "tsconfig.json",
+      r#"{
+        // comments and trailing commas are allowed
+        "compilerOptions": {
+          "checkJs": true,
+          "jsx": "React",
+          "lib": ["dom"],
+          "target": 5,
+        },
+      }"#,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(options.get("checkJs"), Some(&json!(true)));
+    // Unsupported options are left for the compiler to deal with.
+    assert_eq!(options.get("target"), Some(&json!(5)));
+
+    assert!(validate_compiler_options("tsconfig.json", "{}")
+      .unwrap()
+      .is_none());
+
+    for invalid in &[
+      r#"{ "compilerOptions": [] }"#,
+      r#"{ "compilerOptions": { "strict": 1 } }"#,
+      r#"{ "compilerOptions": { "lib": ["dom", 1] } }"#,
+      r#"{ "compilerOptions": { "jsx": "vue" } }"#,
+      r#"{ "compilerOptions": { "jsxFactory": "h" "#,
+    ] {
+      assert!(validate_compiler_options("tsconfig.json", invalid).is_err());
+    }
+  }
+
   #[tokio::test]
   async fn test_compile() {
     let p = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -1191,6 +1438,28 @@ mod tests {
     assert_eq!(source, specifier.to_string());
   }
 
+  #[test]
+  fn test_keep_in_memory() {
+    let mock_state =
+      GlobalState::mock(vec![String::from("deno"), String::from("hello.ts")]);
+    let ts_compiler = &mock_state.ts_compiler;
+    let url = Url::parse("file:///$deno$stdin.ts").unwrap();
+    ts_compiler.keep_in_memory(&url);
+    ts_compiler
+      .cache_set(&url, "js", b"console.log(1);")
+      .unwrap();
+    assert_eq!(
+      ts_compiler.cache_get(&url, "js").unwrap(),
+      b"console.log(1);"
+    );
+    assert!(ts_compiler.cache_get(&url, "meta").is_err());
+
+    let js_key = ts_compiler
+      .disk_cache
+      .get_cache_filename_with_extension(&url, "js");
+    assert!(!ts_compiler.disk_cache.location.join(js_key).exists());
+  }
+
   #[tokio::test]
   async fn test_bundle() {
     let p = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -1241,6 +1510,13 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_compiler_version() {
+    let compiler_version = compiler_version();
+    assert!(compiler_version.starts_with(version::DENO));
+    assert!(compiler_version.ends_with(version::TYPESCRIPT));
+  }
+
   #[test]
   fn test_compile_js() {
     let temp_dir = TempDir::new().expect("tempdir fail");
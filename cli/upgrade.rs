@@ -7,21 +7,21 @@
 //! the same functions as ops available in JS runtime.
 
 extern crate semver_parser;
-use crate::futures::FutureExt;
-use crate::http_util::fetch_once;
-use crate::http_util::FetchOnceResult;
+use crate::checksum;
+use crate::http_util::create_http_client;
+use crate::http_util::format_progress;
+use crate::http_util::PoolOptions;
 use crate::op_error::OpError;
 use crate::ErrBox;
 use regex::Regex;
-use reqwest::{redirect::Policy, Client};
+use reqwest::header::LOCATION;
+use reqwest::{Client, Response, StatusCode};
 use semver_parser::version::parse as semver_parse;
 use semver_parser::version::Version;
 use std::fs;
-use std::future::Future;
 use std::io::prelude::*;
 use std::path::Path;
 use std::path::PathBuf;
-use std::pin::Pin;
 use std::process::Command;
 use std::process::Stdio;
 use std::string::String;
@@ -37,7 +37,7 @@ const ARCHIVE_NAME: &str = "deno-x86_64-apple-darwin.zip";
 const ARCHIVE_NAME: &str = "deno-x86_64-unknown-linux-gnu.zip";
 
 async fn get_latest_version(client: &Client) -> Result<Version, ErrBox> {
-  println!("Checking for latest version");
+  info!("Checking for latest version");
   let body = client
     .get(Url::parse(
       "https://github.com/denoland/deno/releases/latest",
@@ -56,15 +56,18 @@ pub async fn upgrade_command(
   dry_run: bool,
   force: bool,
   version: Option<String>,
+  allow_unverified: bool,
 ) -> Result<(), ErrBox> {
-  let client = Client::builder().redirect(Policy::none()).build()?;
+  let client = create_http_client(None, &PoolOptions::default())?;
   let current_version = semver_parse(crate::version::DENO).unwrap();
+  let old_exe_path = std::env::current_exe()?;
+  remove_replaced_exe(&old_exe_path);
 
   let install_version = match version {
     Some(passed_version) => match semver_parse(&passed_version) {
       Ok(ver) => {
         if !force && current_version == ver {
-          println!("Version {} is already installed", &ver);
+          info!("Version {} is already installed", &ver);
           std::process::exit(1)
         } else {
           ver
@@ -79,7 +82,7 @@ pub async fn upgrade_command(
       let latest_version = get_latest_version(&client).await?;
 
       if !force && current_version >= latest_version {
-        println!(
+        info!(
           "Local deno version {} is the most recent release",
           &crate::version::DENO
         );
@@ -90,15 +93,33 @@ pub async fn upgrade_command(
     }
   };
 
-  println!(
+  info!(
     "Version has been found\nDeno is upgrading to version {}",
     &install_version
   );
 
-  let archive_data =
-    download_package(&compose_url_to_exec(&install_version)?, client).await?;
+  let archive_url = compose_url_to_exec(&install_version)?;
+  let archive_data = download_package(&archive_url, &client).await?;
+  match fetch_checksum(&archive_url, &client).await? {
+    Some(expected) => {
+      verify_checksum(&archive_data, &expected)?;
+      info!("Checksum verified");
+    }
+    None if allow_unverified => {
+      info!("No checksum published for this release, installing it unverified")
+    }
+    None => {
+      return Err(
+        OpError::other(format!(
+          "No checksum is published for {}, pass --allow-unverified to \
+           install it anyway",
+          archive_url
+        ))
+        .into(),
+      )
+    }
+  }
 
-  let old_exe_path = std::env::current_exe()?;
   let new_exe_path = unpack(archive_data)?;
   let permissions = fs::metadata(&old_exe_path)?.permissions();
   fs::set_permissions(&new_exe_path, permissions)?;
@@ -108,27 +129,115 @@ pub async fn upgrade_command(
     replace_exe(&new_exe_path, &old_exe_path)?;
   }
 
-  println!("Upgrade done successfully");
+  info!("Upgrade done successfully");
 
   Ok(())
 }
 
-fn download_package(
+/// Sends a GET request, following redirects. Release assets are served from
+/// a different host than the one they're requested from.
+async fn get(url: &Url, client: &Client) -> Result<Response, ErrBox> {
+  let mut url = url.clone();
+  for _ in 0..10 {
+    let response = client.get(url.clone()).send().await?;
+    if !response.status().is_redirection() {
+      return Ok(response);
+    }
+    let location = response
+      .headers()
+      .get(LOCATION)
+      .and_then(|location| location.to_str().ok())
+      .ok_or_else(|| {
+        OpError::other(format!("Redirect without location from {}", url))
+      })?;
+    url = url.join(location)?;
+  }
+  Err(OpError::other(format!("Too many redirects for {}", url)).into())
+}
+
+async fn download_package(
   url: &Url,
-  client: Client,
-) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, ErrBox>>>> {
-  println!("downloading {}", url);
-  let url = url.clone();
-  let fut = async move {
-    match fetch_once(client.clone(), &url, None).await? {
-      FetchOnceResult::Code(source, _) => Ok(source),
-      FetchOnceResult::NotModified => unreachable!(),
-      FetchOnceResult::Redirect(_url, _) => {
-        download_package(&_url, client).await
-      }
+  client: &Client,
+) -> Result<Vec<u8>, ErrBox> {
+  info!("Downloading {}", url);
+  let mut response = get(url, client).await?;
+  if !response.status().is_success() {
+    return Err(
+      OpError::other(format!(
+        "Download of {} failed: {}",
+        url,
+        response.status()
+      ))
+      .into(),
+    );
+  }
+
+  let total = response.content_length();
+  let show_progress =
+    log_enabled!(log::Level::Info) && atty::is(atty::Stream::Stderr);
+  let mut data = Vec::with_capacity(total.unwrap_or(0) as usize);
+  while let Some(chunk) = response.chunk().await? {
+    data.extend_from_slice(&chunk);
+    if show_progress {
+      eprint!("\r{}", format_progress(data.len() as u64, total));
     }
-  };
-  fut.boxed_local()
+  }
+  if show_progress {
+    eprintln!();
+  }
+  Ok(data)
+}
+
+/// Fetches the SHA-256 checksum published next to a release archive, or
+/// `None` if the release has none.
+async fn fetch_checksum(
+  archive_url: &Url,
+  client: &Client,
+) -> Result<Option<String>, ErrBox> {
+  let url = Url::parse(&format!("{}.sha256sum", archive_url))?;
+  let response = get(&url, client).await?;
+  if response.status() == StatusCode::NOT_FOUND {
+    return Ok(None);
+  }
+  if !response.status().is_success() {
+    return Err(
+      OpError::other(format!(
+        "Download of {} failed: {}",
+        url,
+        response.status()
+      ))
+      .into(),
+    );
+  }
+  parse_checksum(&response.text().await?).map(Some)
+}
+
+/// Reads the digest from the output of `sha256sum`, which may be followed by
+/// the file name.
+fn parse_checksum(text: &str) -> Result<String, ErrBox> {
+  match text.split_whitespace().next() {
+    Some(digest)
+      if digest.len() == 64
+        && digest.chars().all(|c| c.is_ascii_hexdigit()) =>
+    {
+      Ok(digest.to_lowercase())
+    }
+    _ => Err(OpError::other("Malformed checksum file".to_string()).into()),
+  }
+}
+
+fn verify_checksum(data: &[u8], expected: &str) -> Result<(), ErrBox> {
+  let actual = checksum::gen(vec![data]);
+  if actual != expected {
+    return Err(
+      OpError::other(format!(
+        "Checksum mismatch for downloaded archive, expected {} but got {}",
+        expected, actual
+      ))
+      .into(),
+    );
+  }
+  Ok(())
 }
 
 fn compose_url_to_exec(version: &Version) -> Result<Url, ErrBox> {
@@ -202,17 +311,46 @@ fn unpack(archive_data: Vec<u8>) -> Result<PathBuf, ErrBox> {
   Ok(exe_path)
 }
 
-fn replace_exe(new: &Path, old: &Path) -> Result<(), ErrBox> {
+/// Path the running executable is moved to on Windows while it's replaced.
+fn replaced_exe_path(exe: &Path) -> PathBuf {
+  exe.with_extension("old.exe")
+}
+
+/// Deletes the executable left over by a previous upgrade on Windows, which
+/// couldn't be deleted while it was running.
+fn remove_replaced_exe(exe: &Path) {
   if cfg!(windows) {
-    // On windows you cannot replace the currently running executable.
-    // so first we rename it to deno.old.exe
-    fs::rename(old, old.with_extension("old.exe"))?;
+    let _ = fs::remove_file(replaced_exe_path(exe));
+  }
+}
+
+/// Replaces `old` with `new` such that `old` always points to a complete
+/// executable.
+fn replace_exe(new: &Path, old: &Path) -> Result<(), ErrBox> {
+  // A rename is only atomic within one file system, so the new executable
+  // is first copied next to the old one.
+  let staged = old.with_extension("new");
+  fs::copy(new, &staged)?;
+
+  let result = if cfg!(windows) {
+    // On Windows the running executable can't be replaced, but it can be
+    // renamed; it's deleted by the next upgrade.
+    let replaced = replaced_exe_path(old);
+    let _ = fs::remove_file(&replaced);
+    fs::rename(old, &replaced).and_then(|_| {
+      fs::rename(&staged, old).or_else(|err| {
+        // Put the old executable back.
+        fs::rename(&replaced, old)?;
+        Err(err)
+      })
+    })
   } else {
-    fs::remove_file(old)?;
+    fs::rename(&staged, old)
+  };
+  if let Err(err) = result {
+    let _ = fs::remove_file(&staged);
+    return Err(err.into());
   }
-  // Windows cannot rename files across device boundaries, so if rename fails,
-  // we try again with copy.
-  fs::rename(new, old).or_else(|_| fs::copy(new, old).map(|_| ()))?;
   Ok(())
 }
 
@@ -236,6 +374,29 @@ fn test_find_version() {
   assert_eq!(find_version(url).unwrap(), "0.36.0".to_string());
 }
 
+#[test]
+fn test_verify_checksum() {
+  let digest =
+    "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+  let text = format!("{}  deno-x86_64-unknown-linux-gnu.zip\n", digest);
+  assert_eq!(parse_checksum(&text).unwrap(), digest);
+  assert!(parse_checksum("not a checksum").is_err());
+  assert!(verify_checksum(b"hello", digest).is_ok());
+  assert!(verify_checksum(b"hello!", digest).is_err());
+}
+
+#[test]
+fn test_replace_exe() {
+  let temp_dir = TempDir::new().unwrap();
+  let old = temp_dir.path().join("deno");
+  let new = temp_dir.path().join("deno_downloaded");
+  fs::write(&old, "old").unwrap();
+  fs::write(&new, "new").unwrap();
+  replace_exe(&new, &old).unwrap();
+  assert_eq!(fs::read_to_string(&old).unwrap(), "new");
+  assert!(!old.with_extension("new").exists());
+}
+
 #[test]
 fn test_compose_url_to_exec() {
   let v = semver_parse("0.0.1").unwrap();
@@ -0,0 +1,319 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use crate::op_error::OpError;
+use deno_core::ErrBox;
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+const IMPORT_SECTION_ID: u8 = 2;
+const EXPORT_SECTION_ID: u8 = 7;
+
+/// Module names and export names found in a WebAssembly binary.
+#[derive(Debug, Default, PartialEq)]
+pub struct WasmModuleInfo {
+  pub imports: Vec<String>,
+  pub exports: Vec<String>,
+}
+
+struct Reader<'a> {
+  bytes: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn new(bytes: &'a [u8]) -> Self {
+    Self { bytes, pos: 0 }
+  }
+
+  fn is_empty(&self) -> bool {
+    self.pos >= self.bytes.len()
+  }
+
+  fn read_u8(&mut self) -> Result<u8, ErrBox> {
+    let byte = *self.bytes.get(self.pos).ok_or_else(malformed)?;
+    self.pos += 1;
+    Ok(byte)
+  }
+
+  fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ErrBox> {
+    let end = self.pos.checked_add(len).ok_or_else(malformed)?;
+    let slice = self.bytes.get(self.pos..end).ok_or_else(malformed)?;
+    self.pos = end;
+    Ok(slice)
+  }
+
+  /// Reads an unsigned LEB128 encoded 32-bit integer.
+  fn read_var_u32(&mut self) -> Result<u32, ErrBox> {
+    let mut result: u32 = 0;
+    let mut shift = 0;
+    loop {
+      let byte = self.read_u8()?;
+      if shift >= 32 {
+        return Err(malformed());
+      }
+      result |= u32::from(byte & 0x7f) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(result);
+      }
+      shift += 7;
+    }
+  }
+
+  fn read_name(&mut self) -> Result<String, ErrBox> {
+    let len = self.read_var_u32()? as usize;
+    let bytes = self.read_bytes(len)?;
+    Ok(String::from_utf8(bytes.to_vec())?)
+  }
+
+  fn skip_limits(&mut self) -> Result<(), ErrBox> {
+    let flags = self.read_var_u32()?;
+    self.read_var_u32()?;
+    if flags & 1 != 0 {
+      self.read_var_u32()?;
+    }
+    Ok(())
+  }
+}
+
+fn malformed() -> ErrBox {
+  OpError::other("Malformed WebAssembly module".to_string()).into()
+}
+
+/// Scans the import and export sections of a WebAssembly binary.
+///
+/// Only names are collected; function bodies and type information are
+/// left for V8 to validate when the module is instantiated.
+pub fn analyze(bytes: &[u8]) -> Result<WasmModuleInfo, ErrBox> {
+  let mut reader = Reader::new(bytes);
+  if reader.read_bytes(4)? != WASM_MAGIC {
+    return Err(malformed());
+  }
+  // Binary format version.
+  reader.read_bytes(4)?;
+
+  let mut info = WasmModuleInfo::default();
+  while !reader.is_empty() {
+    let id = reader.read_u8()?;
+    let size = reader.read_var_u32()? as usize;
+    let payload = reader.read_bytes(size)?;
+    let mut section = Reader::new(payload);
+    match id {
+      IMPORT_SECTION_ID => {
+        let count = section.read_var_u32()?;
+        for _ in 0..count {
+          let module = section.read_name()?;
+          section.read_name()?;
+          match section.read_u8()? {
+            // Function: type index.
+            0x00 => {
+              section.read_var_u32()?;
+            }
+            // Table: element type and limits.
+            0x01 => {
+              section.read_u8()?;
+              section.skip_limits()?;
+            }
+            // Memory: limits.
+            0x02 => section.skip_limits()?,
+            // Global: value type and mutability.
+            0x03 => {
+              section.read_u8()?;
+              section.read_u8()?;
+            }
+            _ => return Err(malformed()),
+          }
+          if !info.imports.contains(&module) {
+            info.imports.push(module);
+          }
+        }
+      }
+      EXPORT_SECTION_ID => {
+        let count = section.read_var_u32()?;
+        for _ in 0..count {
+          let name = section.read_name()?;
+          section.read_u8()?;
+          section.read_var_u32()?;
+          info.exports.push(name);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  Ok(info)
+}
+
+/// Words that can't name a binding in module code. They can still be the
+/// name of an export.
+const RESERVED_WORDS: &[&str] = &[
+  "arguments",
+  "await",
+  "break",
+  "case",
+  "catch",
+  "class",
+  "const",
+  "continue",
+  "debugger",
+  "default",
+  "delete",
+  "do",
+  "else",
+  "enum",
+  "eval",
+  "export",
+  "extends",
+  "false",
+  "finally",
+  "for",
+  "function",
+  "if",
+  "implements",
+  "import",
+  "in",
+  "instanceof",
+  "interface",
+  "let",
+  "new",
+  "null",
+  "package",
+  "private",
+  "protected",
+  "public",
+  "return",
+  "static",
+  "super",
+  "switch",
+  "this",
+  "throw",
+  "true",
+  "try",
+  "typeof",
+  "var",
+  "void",
+  "while",
+  "with",
+  "yield",
+];
+
+fn is_identifier_name(name: &str) -> bool {
+  let mut chars = name.chars();
+  match chars.next() {
+    Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
+    _ => return false,
+  }
+  chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+fn is_identifier(name: &str) -> bool {
+  is_identifier_name(name) && !RESERVED_WORDS.contains(&name)
+}
+
+/// Generates an ES module that instantiates the given WebAssembly binary.
+///
+/// Each module named in the import section becomes a static import whose
+/// namespace is handed to the instance, and each export becomes a named
+/// export of the wrapper. Exports named by a reserved word are bound to a
+/// local first and exported under their own name. Exports whose names aren't
+/// identifier names at all can't be exported and are left out.
+pub fn wrap(bytes: &[u8]) -> Result<String, ErrBox> {
+  let info = analyze(bytes)?;
+  let mut code = String::new();
+
+  for (i, module) in info.imports.iter().enumerate() {
+    let specifier = serde_json::to_string(module)?;
+    code.push_str(&format!("import * as m{} from {};\n", i, specifier));
+  }
+  code.push_str("const importObject = Object.create(null);\n");
+  for (i, module) in info.imports.iter().enumerate() {
+    let specifier = serde_json::to_string(module)?;
+    code.push_str(&format!("importObject[{}] = m{};\n", specifier, i));
+  }
+
+  code.push_str(&format!(
+    "const wasmCode = Uint8Array.from(atob(\"{}\"), (c) => c.charCodeAt(0));\n",
+    base64::encode(bytes)
+  ));
+  code.push_str(
+    "const wasmInstance = new WebAssembly.Instance(\n  new WebAssembly.Module(wasmCode),\n  importObject,\n);\n",
+  );
+
+  for (i, name) in info.exports.iter().enumerate() {
+    if name == "default" {
+      code.push_str("export default wasmInstance.exports[\"default\"];\n");
+    } else if is_identifier(name) {
+      code.push_str(&format!(
+        "export const {} = wasmInstance.exports[\"{}\"];\n",
+        name, name
+      ));
+    } else if is_identifier_name(name) {
+      code.push_str(&format!(
+        "const wasmExport{} = wasmInstance.exports[\"{}\"];\n",
+        i, name
+      ));
+      code.push_str(&format!("export {{ wasmExport{} as {} }};\n", i, name));
+    }
+  }
+
+  Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // (module
+  //   (import "./add.js" "add" (func $add (param i32 i32) (result i32)))
+  //   (func (export "addImported") (param i32 i32) (result i32)
+  //     local.get 0 local.get 1 call $add)
+  //   (memory (export "memory") 1))
+  const WASM_WITH_IMPORT: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x07, 0x01, 0x60,
+    0x02, 0x7f, 0x7f, 0x01, 0x7f, 0x02, 0x10, 0x01, 0x08, 0x2e, 0x2f, 0x61,
+    0x64, 0x64, 0x2e, 0x6a, 0x73, 0x03, 0x61, 0x64, 0x64, 0x00, 0x00, 0x03,
+    0x02, 0x01, 0x00, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x18, 0x02, 0x0b,
+    0x61, 0x64, 0x64, 0x49, 0x6d, 0x70, 0x6f, 0x72, 0x74, 0x65, 0x64, 0x00,
+    0x01, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, 0x0a, 0x0a,
+    0x01, 0x08, 0x00, 0x20, 0x00, 0x20, 0x01, 0x10, 0x00, 0x0b,
+  ];
+
+  #[test]
+  fn analyze_imports_and_exports() {
+    let info = analyze(WASM_WITH_IMPORT).unwrap();
+    assert_eq!(
+      info,
+      WasmModuleInfo {
+        imports: vec!["./add.js".to_string()],
+        exports: vec!["addImported".to_string(), "memory".to_string()],
+      }
+    );
+  }
+
+  #[test]
+  fn reserved_words_are_not_identifiers() {
+    assert!(is_identifier("addImported"));
+    assert!(is_identifier("$memory_1"));
+    assert!(!is_identifier("class"));
+    assert!(!is_identifier("delete"));
+    assert!(is_identifier_name("delete"));
+    assert!(!is_identifier_name("add-imported"));
+    assert!(!is_identifier_name("1st"));
+  }
+
+  #[test]
+  fn analyze_rejects_non_wasm() {
+    assert!(analyze(b"console.log('hello')").is_err());
+    assert!(analyze(&WASM_WITH_IMPORT[..20]).is_err());
+  }
+
+  #[test]
+  fn wrap_generates_bindings() {
+    let code = wrap(WASM_WITH_IMPORT).unwrap();
+    assert!(code.contains("import * as m0 from \"./add.js\";"));
+    assert!(code.contains("importObject[\"./add.js\"] = m0;"));
+    assert!(code.contains(
+      "export const addImported = wasmInstance.exports[\"addImported\"];"
+    ));
+    assert!(
+      code.contains("export const memory = wasmInstance.exports[\"memory\"];")
+    );
+  }
+}
@@ -5,6 +5,7 @@ use crate::worker::Worker;
 use crate::worker::WorkerEvent;
 use crate::worker::WorkerHandle;
 use deno_core::v8;
+use deno_core::CoreIsolate;
 use deno_core::ErrBox;
 use deno_core::StartupData;
 use futures::channel::mpsc;
@@ -114,6 +115,7 @@ impl WebWorker {
     {
       let isolate = &mut web_worker.worker.isolate;
       ops::runtime::init(isolate, &state);
+      ops::batch::init(isolate, &state);
       ops::web_worker::init(
         isolate,
         &state,
@@ -128,11 +130,13 @@ impl WebWorker {
       ops::fetch::init(isolate, &state);
 
       if has_deno_namespace {
+        ops::bench::init(isolate, &state);
         ops::runtime_compiler::init(isolate, &state);
         ops::fs::init(isolate, &state);
         ops::fs_events::init(isolate, &state);
         ops::plugin::init(isolate, &state);
         ops::net::init(isolate, &state);
+        ops::fds::init(isolate, &state);
         ops::tls::init(isolate, &state);
         ops::os::init(isolate, &state);
         ops::permissions::init(isolate, &state);
@@ -211,11 +215,24 @@ impl Future for WebWorker {
     {
       match r {
         Some(msg) => {
-          let msg = String::from_utf8(msg.to_vec()).unwrap();
-          debug!("received message from host: {}", msg);
-          let script = format!("workerMessageRecvCallback({})", msg);
-
-          if let Err(e) = worker.execute(&script) {
+          let data = String::from_utf8(msg.data.to_vec()).unwrap();
+          debug!("received message from host: {}", data);
+          let result = {
+            let state_rc = CoreIsolate::state(&worker.isolate);
+            let state = state_rc.borrow();
+            let mut resource_table = state.resource_table.borrow_mut();
+            ops::fds::add_resources(&mut resource_table, msg.resources)
+          };
+          let result = result.map_err(ErrBox::from).and_then(|resources| {
+            let script = format!(
+              "workerMessageRecvCallback({}, {})",
+              data,
+              json!(resources)
+            );
+            worker.execute(&script)
+          });
+
+          if let Err(e) = result {
             // If execution was terminated during message callback then
             // just ignore it
             if inner.handle.terminated.load(Ordering::Relaxed) {
@@ -304,8 +321,8 @@ mod tests {
       let maybe_msg = handle.get_event().await.unwrap();
       assert!(maybe_msg.is_some());
       match maybe_msg {
-        Some(WorkerEvent::Message(buf)) => {
-          assert_eq!(*buf, *b"[1,2,3]");
+        Some(WorkerEvent::Message(msg)) => {
+          assert_eq!(*msg.data, *b"[1,2,3]");
         }
         _ => unreachable!(),
       }
@@ -1,11 +1,20 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use crate::crash_report;
+use crate::flags::DenoSubcommand;
 use crate::fmt_errors::JSError;
 use crate::global_state::GlobalState;
+use crate::heap_snapshot;
 use crate::inspector::DenoInspector;
+use crate::op_error::OpError;
+use crate::op_tracer;
+use crate::op_tracer::OpTracer;
 use crate::ops;
+use crate::ops::fds::TransferredResource;
 use crate::ops::io::get_stdio;
+use crate::repl_debugger;
 use crate::startup_data;
 use crate::state::State;
+use crate::test_runner;
 use deno_core::Buf;
 use deno_core::CoreIsolate;
 use deno_core::ErrBox;
@@ -16,6 +25,7 @@ use futures::channel::mpsc;
 use futures::future::FutureExt;
 use futures::stream::StreamExt;
 use futures::task::AtomicWaker;
+use std::cell::RefCell;
 use std::env;
 use std::future::Future;
 use std::ops::Deref;
@@ -28,30 +38,50 @@ use std::task::Poll;
 use tokio::sync::Mutex as AsyncMutex;
 use url::Url;
 
+/// A message posted between a worker and its host, along with the
+/// resources transferred with it.
+pub struct WorkerMessage {
+  pub data: Buf,
+  pub resources: Vec<TransferredResource>,
+}
+
 /// Events that are sent to host from child
 /// worker.
 pub enum WorkerEvent {
-  Message(Buf),
+  Message(WorkerMessage),
   Error(ErrBox),
   TerminalError(ErrBox),
 }
 
 pub struct WorkerChannelsInternal {
   pub sender: mpsc::Sender<WorkerEvent>,
-  pub receiver: mpsc::Receiver<Buf>,
+  pub receiver: mpsc::Receiver<WorkerMessage>,
 }
 
 #[derive(Clone)]
 pub struct WorkerHandle {
-  pub sender: mpsc::Sender<Buf>,
+  pub sender: mpsc::Sender<WorkerMessage>,
   pub receiver: Arc<AsyncMutex<mpsc::Receiver<WorkerEvent>>>,
 }
 
 impl WorkerHandle {
   /// Post message to worker as a host.
   pub fn post_message(&self, buf: Buf) -> Result<(), ErrBox> {
+    self.post_message_with_resources(buf, vec![])
+  }
+
+  /// Post message to worker as a host, moving `resources` to it.
+  pub fn post_message_with_resources(
+    &self,
+    buf: Buf,
+    resources: Vec<TransferredResource>,
+  ) -> Result<(), ErrBox> {
     let mut sender = self.sender.clone();
-    sender.try_send(buf).map_err(ErrBox::from)
+    let msg = WorkerMessage {
+      data: buf,
+      resources,
+    };
+    sender.try_send(msg).map_err(ErrBox::from)
   }
 
   /// Get the event with lock.
@@ -63,7 +93,7 @@ impl WorkerHandle {
 }
 
 fn create_channels() -> (WorkerChannelsInternal, WorkerHandle) {
-  let (in_tx, in_rx) = mpsc::channel::<Buf>(1);
+  let (in_tx, in_rx) = mpsc::channel::<WorkerMessage>(1);
   let (out_tx, out_rx) = mpsc::channel::<WorkerEvent>(1);
   let internal_channels = WorkerChannelsInternal {
     sender: out_tx,
@@ -114,13 +144,17 @@ impl Worker {
 
     let inspector = {
       let state = state.borrow();
-      let global_state = &state.global_state;
-      global_state
-        .flags
-        .inspect
-        .or(global_state.flags.inspect_brk)
-        .filter(|_| !state.is_internal)
-        .map(|inspector_host| DenoInspector::new(&mut isolate, inspector_host))
+      let flags = &state.global_state.flags;
+      let inspector_host = flags.inspect.or(flags.inspect_brk);
+      // The coverage and the CPU profile of the main worker are collected
+      // through the inspector.
+      let local_session =
+        state.is_main && (flags.coverage_dir.is_some() || flags.prof.is_some());
+      if !state.is_internal && (inspector_host.is_some() || local_session) {
+        Some(DenoInspector::new(&mut isolate, inspector_host))
+      } else {
+        None
+      }
     };
 
     let (internal_channels, external_channels) = create_channels();
@@ -204,12 +238,31 @@ impl Worker {
         .wait_for_session_and_break_on_next_statement()
     }
   }
+
+  /// Takes the heap snapshots requested since the worker was last polled.
+  /// The inspector is created for them if there's none yet.
+  fn take_heap_snapshots(&mut self) {
+    let requests =
+      std::mem::take(&mut self.state.borrow_mut().heap_snapshot_requests);
+    if requests.is_empty() {
+      return;
+    }
+    let isolate = &mut self.isolate;
+    let inspector = self
+      .inspector
+      .get_or_insert_with(|| DenoInspector::new(isolate, None));
+    for (path, sender) in requests {
+      let _ = sender.send(heap_snapshot::write(inspector, &path));
+    }
+  }
 }
 
 impl Drop for Worker {
   fn drop(&mut self) {
     // The Isolate object must outlive the Inspector object, but this is
-    // currently not enforced by the type system.
+    // currently not enforced by the type system. The session of the REPL
+    // debugger must be dropped before the inspector.
+    self.state.borrow_mut().repl_debugger.take();
     self.inspector.take();
   }
 }
@@ -223,7 +276,10 @@ impl Future for Worker {
     // We always poll the inspector if it exists.
     let _ = inner.inspector.as_mut().map(|i| i.poll_unpin(cx));
     inner.waker.register(cx.waker());
-    inner.isolate.poll_unpin(cx)
+    let poll = inner.isolate.poll_unpin(cx);
+    // Sending the results of the snapshots wakes the ops waiting for them.
+    inner.take_heap_snapshots();
+    poll
   }
 }
 
@@ -256,14 +312,18 @@ impl MainWorker {
     {
       let isolate = &mut worker.isolate;
       ops::runtime::init(isolate, &state);
+      ops::batch::init(isolate, &state);
+      ops::bench::init(isolate, &state);
       ops::runtime_compiler::init(isolate, &state);
       ops::errors::init(isolate, &state);
       ops::fetch::init(isolate, &state);
       ops::fs::init(isolate, &state);
       ops::fs_events::init(isolate, &state);
+      ops::heap_snapshot::init(isolate, &state);
       ops::io::init(isolate, &state);
       ops::plugin::init(isolate, &state);
       ops::net::init(isolate, &state);
+      ops::fds::init(isolate, &state);
       ops::tls::init(isolate, &state);
       ops::os::init(isolate, &state);
       ops::permissions::init(isolate, &state);
@@ -304,9 +364,50 @@ impl MainWorker {
       t.add("stdout", Box::new(stdout));
       t.add("stderr", Box::new(stderr));
     }
+    if let Some(path) = &global_state.flags.trace_ops {
+      let path = test_runner::shard_output_path(path);
+      let tracer = Rc::new(RefCell::new(OpTracer::create(&path)?));
+      op_tracer::install(&mut worker.isolate, tracer.clone());
+      worker.state.borrow_mut().op_tracer = Some(tracer);
+    }
+    crash_report::set_main_worker_state(&worker.state);
+    // The REPL starts reading inputs during the bootstrap.
+    if let DenoSubcommand::Repl { .. } = global_state.flags.subcommand {
+      repl_debugger::attach(&mut worker);
+    }
     worker.execute("bootstrap.mainRuntime()")?;
     Ok(worker)
   }
+
+  /// Fails if resources other than stdio are still open, listing each one
+  /// with the stack it was created from. Used with `--strict-resources`.
+  pub fn check_leaked_resources(&self) -> Result<(), ErrBox> {
+    let state_rc = CoreIsolate::state(&self.isolate);
+    let state = state_rc.borrow();
+    let resource_table = state.resource_table.borrow();
+    let mut leaked = resource_table.entries();
+    // Stdin, stdout and stderr are the first three resources.
+    leaked.retain(|(rid, _name)| *rid > 2);
+    if leaked.is_empty() {
+      return Ok(());
+    }
+    leaked.sort();
+
+    let mut message = "These resources were never closed:".to_string();
+    for (rid, name) in leaked {
+      message.push_str(&format!("\n  {} (rid {})", name, rid));
+      match resource_table.origin(rid) {
+        Some(origin) => {
+          for line in origin.lines() {
+            message.push_str("\n  ");
+            message.push_str(line);
+          }
+        }
+        None => message.push_str(", created by the runtime"),
+      }
+    }
+    Err(OpError::other(message).into())
+  }
 }
 
 impl Deref for MainWorker {
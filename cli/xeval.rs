@@ -0,0 +1,134 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Implements `deno xeval`, which evaluates a snippet once for every chunk
+//! of stdin.
+//!
+//! Stdin is read and split on the Rust side; every chunk is handed to a
+//! function compiled once from the snippet, and the event loop is driven to
+//! completion before the next chunk is read.
+
+use crate::flags::Flags;
+use crate::global_state::GlobalState;
+use crate::worker::MainWorker;
+use deno_core::ErrBox;
+use deno_core::ModuleSpecifier;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+
+pub const DEFAULT_DELIMITER: &str = "\n";
+pub const DEFAULT_REPLVAR: &str = "$";
+
+const XEVAL_FN: &str = "__$deno$xeval";
+
+/// Splits a reader into chunks separated by `delimiter`.
+///
+/// The delimiter isn't included in the chunks. A trailing chunk that isn't
+/// terminated by the delimiter is still returned, unless it's empty.
+pub struct Chunks<R> {
+  reader: R,
+  delimiter: Vec<u8>,
+  done: bool,
+}
+
+impl<R: BufRead> Chunks<R> {
+  pub fn new(reader: R, delimiter: Vec<u8>) -> Self {
+    assert!(!delimiter.is_empty());
+    Self {
+      reader,
+      delimiter,
+      done: false,
+    }
+  }
+}
+
+impl<R: BufRead> Iterator for Chunks<R> {
+  type Item = io::Result<Vec<u8>>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+    let last_byte = *self.delimiter.last().unwrap();
+    let mut chunk = vec![];
+    loop {
+      match self.reader.read_until(last_byte, &mut chunk) {
+        Err(err) => return Some(Err(err)),
+        Ok(0) => {
+          self.done = true;
+          if chunk.is_empty() {
+            return None;
+          }
+          return Some(Ok(chunk));
+        }
+        Ok(_) => {
+          if chunk.ends_with(&self.delimiter) {
+            chunk.truncate(chunk.len() - self.delimiter.len());
+            return Some(Ok(chunk));
+          }
+          // Either only the last byte of a multi-byte delimiter matched, or
+          // the input ended; the next read tells them apart.
+        }
+      }
+    }
+  }
+}
+
+pub async fn xeval_command(
+  flags: Flags,
+  code: String,
+  delimiter: Option<String>,
+  replvar: Option<String>,
+) -> Result<(), ErrBox> {
+  let delimiter = delimiter.unwrap_or_else(|| DEFAULT_DELIMITER.to_string());
+  let replvar = replvar.unwrap_or_else(|| DEFAULT_REPLVAR.to_string());
+  let main_module =
+    ModuleSpecifier::resolve_url_or_path("./__$deno$xeval.js").unwrap();
+  let global_state = GlobalState::new(flags)?;
+  let mut worker = MainWorker::create(global_state, main_module)?;
+
+  // An async function, so that the snippet may use `await`.
+  worker.execute(&format!(
+    "globalThis[\"{}\"] = async function ({}) {{\n{}\n}};",
+    XEVAL_FN, replvar, code
+  ))?;
+  worker.execute("window.dispatchEvent(new Event('load'))")?;
+
+  let stdin = BufReader::new(io::stdin());
+  for chunk in Chunks::new(stdin, delimiter.into_bytes()) {
+    let chunk = String::from_utf8_lossy(&chunk?).into_owned();
+    worker.execute(&format!(
+      "globalThis[\"{}\"]({});",
+      XEVAL_FN,
+      serde_json::to_string(&chunk)?
+    ))?;
+    (&mut *worker).await?;
+  }
+
+  worker.execute("window.dispatchEvent(new Event('unload'))")?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn chunks(input: &str, delimiter: &str) -> Vec<String> {
+    Chunks::new(input.as_bytes(), delimiter.as_bytes().to_vec())
+      .map(|chunk| String::from_utf8(chunk.unwrap()).unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn split_lines() {
+    assert_eq!(chunks("a\nb\n", "\n"), vec!["a", "b"]);
+    assert_eq!(chunks("a\n\nb", "\n"), vec!["a", "", "b"]);
+    assert!(chunks("", "\n").is_empty());
+  }
+
+  #[test]
+  fn split_multi_byte_delimiter() {
+    assert_eq!(chunks("a--b-c--", "--"), vec!["a", "b-c"]);
+    assert_eq!(chunks("a-", "--"), vec!["a-"]);
+  }
+}
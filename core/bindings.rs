@@ -10,6 +10,7 @@ use v8::MapFnTo;
 
 use std::cell::Cell;
 use std::convert::TryFrom;
+use std::io::Write;
 use std::option::Option;
 use url::Url;
 
@@ -216,6 +217,14 @@ pub fn initialize_context<'s>(
     shared_getter,
   );
 
+  // The functions registered with `CoreIsolate::register_fn()`.
+  let fns_val = v8::Object::new(scope);
+  core_val.set(
+    context,
+    v8::String::new(scope, "fns").unwrap().into(),
+    fns_val.into(),
+  );
+
   // Direct bindings on `window`.
   let mut queue_microtask_tmpl =
     v8::FunctionTemplate::new(scope, queue_microtask);
@@ -394,19 +403,72 @@ fn print(
       .expect("Unable to convert to integer");
     is_err = int_val != 0;
   };
-  let mut try_catch = v8::TryCatch::new(scope);
-  let _tc = try_catch.enter();
-  let str_ = match obj.to_string(scope) {
-    Some(s) => s,
-    None => v8::String::new(scope, "").unwrap(),
+  let str_ = {
+    let mut try_catch = v8::TryCatch::new(scope);
+    let _tc = try_catch.enter();
+    match obj.to_string(scope) {
+      Some(s) => s,
+      None => v8::String::new(scope, "").unwrap(),
+    }
   };
-  if is_err {
-    eprint!("{}", str_.to_rust_string_lossy(scope));
+  let text = str_.to_rust_string_lossy(scope);
+  let result = if is_err {
+    std::io::stderr().write_all(text.as_bytes())
   } else {
-    print!("{}", str_.to_rust_string_lossy(scope));
+    std::io::stdout().write_all(text.as_bytes())
+  };
+  // A failed write, e.g. to a pipe that was closed, is thrown rather than
+  // panicking, named after the kind of the IO error.
+  if let Err(err) = result {
+    let context = scope.get_current_context().unwrap();
+    let msg = v8::String::new(scope, &err.to_string()).unwrap();
+    let exc = v8::Exception::error(scope, msg);
+    let name_key = v8::String::new(scope, "name").unwrap();
+    let name = v8::String::new(scope, &format!("{:?}", err.kind())).unwrap();
+    if let Some(exc_obj) = exc.to_object(scope) {
+      exc_obj.set(context, name_key.into(), name.into());
+    }
+    scope.isolate().throw_exception(exc);
   }
 }
 
+/// Sets `Deno.core.fns[name]` to a function running `callback`. Returns false
+/// if JS removed `Deno.core.fns`.
+pub fn set_fn<'s>(
+  scope: &mut impl v8::ToLocal<'s>,
+  context: v8::Local<v8::Context>,
+  name: &str,
+  callback: impl v8::MapFnTo<v8::FunctionCallback>,
+) -> bool {
+  let global = context.global(scope);
+  let fns = match get_object(scope, context, global, "Deno")
+    .and_then(|deno| get_object(scope, context, deno, "core"))
+    .and_then(|core| get_object(scope, context, core, "fns"))
+  {
+    Some(fns) => fns,
+    None => return false,
+  };
+  let mut tmpl = v8::FunctionTemplate::new(scope, callback);
+  let val = tmpl.get_function(scope, context).unwrap();
+  fns.set(
+    context,
+    v8::String::new(scope, name).unwrap().into(),
+    val.into(),
+  );
+  true
+}
+
+fn get_object<'s>(
+  scope: &mut impl v8::ToLocal<'s>,
+  context: v8::Local<v8::Context>,
+  object: v8::Local<v8::Object>,
+  key: &str,
+) -> Option<v8::Local<'s, v8::Object>> {
+  let key = v8::String::new(scope, key).unwrap();
+  let value = object.get(scope, context, key.into())?;
+  v8::Local::<v8::Object>::try_from(value).ok()
+}
+
 fn recv(
   scope: v8::FunctionCallbackScope,
   args: v8::FunctionCallbackArguments,
@@ -537,10 +599,13 @@ fn eval_context(
   mut rv: v8::ReturnValue,
 ) {
   let state_rc = CoreIsolate::state(scope.isolate());
-  let context = {
+  let (context, evaluating) = {
     let state = state_rc.borrow();
     assert!(!state.global_context.is_empty());
-    state.global_context.get(scope).unwrap()
+    (
+      state.global_context.get(scope).unwrap(),
+      state.evaluating.clone(),
+    )
   };
 
   let source = match v8::Local::<v8::String>::try_from(args.get(0)) {
@@ -555,6 +620,9 @@ fn eval_context(
 
   let url = v8::Local::<v8::String>::try_from(args.get(1))
     .map(|n| Url::from_file_path(n.to_rust_string_lossy(scope)).unwrap());
+  // Whether a `TerminateExecution()` stops only this script instead of all
+  // JavaScript running on the isolate.
+  let interruptible = args.get(2).is_true();
 
   let output = v8::Array::new(scope, 2);
   /*
@@ -564,6 +632,7 @@ fn eval_context(
        thrown: Error | any,
        isNativeError: boolean,
        isCompileError: boolean,
+       isInterrupted?: boolean,
      }
   */
   let mut try_catch = v8::TryCatch::new(scope);
@@ -613,7 +682,64 @@ fn eval_context(
     return;
   }
 
+  if interruptible {
+    *evaluating.lock().unwrap() = true;
+  }
   let result = maybe_script.unwrap().run(scope, context);
+  let is_interrupted = interruptible && {
+    // Holding the lock, so that `InterruptHandle::interrupt()` can't
+    // terminate anything once the script is done.
+    let mut evaluating = evaluating.lock().unwrap();
+    *evaluating = false;
+    // TODO(piscisaureus): in rusty_v8, `is_execution_terminating()` and
+    // `cancel_terminate_execution()` should also be implemented on
+    // `struct Isolate`.
+    let isolate_handle = scope.isolate().thread_safe_handle();
+    let is_terminating = isolate_handle.is_execution_terminating();
+    // Also cancels an interrupt which came in after the script was done, and
+    // would otherwise terminate whatever JavaScript runs next.
+    isolate_handle.cancel_terminate_execution();
+    is_terminating
+  };
+
+  if result.is_none() && is_interrupted {
+    output.set(
+      context,
+      v8::Integer::new(scope, 0).into(),
+      v8::null(scope).into(),
+    );
+
+    let errinfo_obj = v8::Object::new(scope);
+    errinfo_obj.set(
+      context,
+      v8::String::new(scope, "isCompileError").unwrap().into(),
+      v8::Boolean::new(scope, false).into(),
+    );
+    errinfo_obj.set(
+      context,
+      v8::String::new(scope, "isNativeError").unwrap().into(),
+      v8::Boolean::new(scope, false).into(),
+    );
+    errinfo_obj.set(
+      context,
+      v8::String::new(scope, "isInterrupted").unwrap().into(),
+      v8::Boolean::new(scope, true).into(),
+    );
+    errinfo_obj.set(
+      context,
+      v8::String::new(scope, "thrown").unwrap().into(),
+      v8::undefined(scope).into(),
+    );
+
+    output.set(
+      context,
+      v8::Integer::new(scope, 1).into(),
+      errinfo_obj.into(),
+    );
+
+    rv.set(output.into());
+    return;
+  }
 
   if result.is_none() {
     assert!(tc.has_caught());
@@ -0,0 +1,16 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use std::env;
+use std::process::Command;
+
+fn main() {
+  // Plugins share Rust types with Deno, whose layout is only stable for a
+  // given compiler, so the plugin descriptor records the one it was built
+  // with.
+  let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+  let output = Command::new(rustc)
+    .arg("--version")
+    .output()
+    .expect("Failed to run rustc --version");
+  let version = String::from_utf8(output.stdout).unwrap();
+  println!("cargo:rustc-env=DENO_CORE_RUSTC_VERSION={}", version.trim());
+}
@@ -11,6 +11,7 @@ use crate::bindings;
 use crate::ops::*;
 use crate::shared_queue::SharedQueue;
 use crate::shared_queue::RECOMMENDED_SIZE;
+use crate::CustomError;
 use crate::ErrBox;
 use crate::JSError;
 use crate::ResourceTable;
@@ -29,12 +30,18 @@ use std::ops::DerefMut;
 use std::option::Option;
 use std::pin::Pin;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::Once;
 use std::task::Context;
 use std::task::Poll;
 
 type PendingOpFuture = Pin<Box<dyn Future<Output = (OpId, Buf)>>>;
 
+/// How many completions of low priority ops are handed to JS per poll of the
+/// isolate, at most.
+const LOW_PRIORITY_OPS_PER_TURN: usize = 32;
+
 /// Stores a script used to initialize a Isolate
 pub struct Script<'a> {
   pub source: &'a str,
@@ -102,11 +109,14 @@ pub struct CoreIsolateState {
   pub(crate) pending_promise_exceptions: HashMap<i32, v8::Global<v8::Value>>,
   pub(crate) js_error_create_fn: Box<JSErrorCreateFn>,
   pub(crate) shared: SharedQueue,
-  pending_ops: FuturesUnordered<PendingOpFuture>,
+  /// Pending async ops, by priority.
+  pending_ops: [FuturesUnordered<PendingOpFuture>; 3],
   pending_unref_ops: FuturesUnordered<PendingOpFuture>,
   have_unpolled_ops: bool,
   pub op_registry: OpRegistry,
   waker: AtomicWaker,
+  /// Whether an interruptible `Deno.core.evalContext()` is running.
+  pub(crate) evaluating: Arc<Mutex<bool>>,
 }
 
 // TODO(ry) The trait v8::InIsolate is superfluous. HandleScope::new should just
@@ -132,6 +142,7 @@ impl DerefMut for CoreIsolate {
 
 impl Drop for CoreIsolate {
   fn drop(&mut self) {
+    self.release_resources();
     if let Some(creator) = self.snapshot_creator.take() {
       // TODO(ry): in rusty_v8, `SnapShotCreator::get_owned_isolate()` returns
       // a `struct OwnedIsolate` which is not actually owned, hence the need
@@ -242,11 +253,16 @@ impl CoreIsolate {
       js_macrotask_cb: v8::Global::<v8::Function>::new(),
       js_error_create_fn: Box::new(JSError::create),
       shared: SharedQueue::new(RECOMMENDED_SIZE),
-      pending_ops: FuturesUnordered::new(),
+      pending_ops: [
+        FuturesUnordered::new(),
+        FuturesUnordered::new(),
+        FuturesUnordered::new(),
+      ],
       pending_unref_ops: FuturesUnordered::new(),
       have_unpolled_ops: false,
       op_registry: OpRegistry::new(),
       waker: AtomicWaker::new(),
+      evaluating: Arc::new(Mutex::new(false)),
     })));
 
     Self {
@@ -269,6 +285,32 @@ impl CoreIsolate {
     s.clone()
   }
 
+  /// Drops the pending ops and then the resources of the isolate, which
+  /// closes its files and sockets and kills its child processes.
+  ///
+  /// This is done when the isolate is dropped, rather than whenever V8 frees
+  /// its slots, and even if something still holds on to the resource table.
+  fn release_resources(&mut self) {
+    let v8_isolate = match self.v8_isolate.as_ref() {
+      Some(v8_isolate) => v8_isolate,
+      None => return,
+    };
+    let state_rc = Self::state(v8_isolate);
+    let (pending_ops, pending_unref_ops, resource_table) = {
+      let mut state = state_rc.borrow_mut();
+      (
+        std::mem::take(&mut state.pending_ops),
+        std::mem::take(&mut state.pending_unref_ops),
+        state.resource_table.clone(),
+      )
+    };
+    // Ops go first, since they may use resources.
+    drop(pending_ops);
+    drop(pending_unref_ops);
+    let resources = std::mem::take(&mut *resource_table.borrow_mut());
+    drop(resources);
+  }
+
   /// Executes a bit of built-in JavaScript to provide Deno.sharedQueue.
   fn shared_init(&mut self) {
     if self.needs_init {
@@ -359,6 +401,21 @@ impl CoreIsolate {
     snapshot
   }
 
+  /// Creates the snapshot of an isolate which ran `scripts` in order, so that
+  /// embedders can start their isolates with JS runtime layers of their own,
+  /// like the CLI does. It's loaded with `Snapshot::Boxed`.
+  ///
+  /// The ops aren't part of the snapshot; they're registered by the isolates
+  /// started from it.
+  pub fn create_snapshot(scripts: &[Script]) -> Result<Box<[u8]>, ErrBox> {
+    let mut isolate = CoreIsolate::new(StartupData::None, true);
+    for script in scripts {
+      isolate.execute(script.filename, script.source)?;
+    }
+    let snapshot = isolate.snapshot();
+    Ok(snapshot.to_vec().into_boxed_slice())
+  }
+
   /// Defines the how Deno.core.dispatch() acts.
   /// Called whenever Deno.core.dispatch() is called in JavaScript. zero_copy_buf
   /// corresponds to the second argument of Deno.core.dispatch().
@@ -372,6 +429,98 @@ impl CoreIsolate {
     let mut state = state_rc.borrow_mut();
     state.op_registry.register(name, op)
   }
+
+  /// Like `register_op`, but the completions of `op` are handed to JS with
+  /// `priority`.
+  pub fn register_op_with_priority<F>(
+    &mut self,
+    name: &str,
+    priority: OpPriority,
+    op: F,
+  ) -> OpId
+  where
+    F: Fn(&mut CoreIsolateState, &[u8], &mut [ZeroCopyBuf]) -> Op + 'static,
+  {
+    let state_rc = Self::state(self);
+    let mut state = state_rc.borrow_mut();
+    state.op_registry.register_with_priority(name, priority, op)
+  }
+
+  /// Returns a handle that interrupts the scripts this isolate runs with
+  /// `Deno.core.evalContext()` as interruptible, from any thread.
+  pub fn interrupt_handle(&mut self) -> InterruptHandle {
+    let evaluating = Self::state(self).borrow().evaluating.clone();
+    InterruptHandle {
+      isolate_handle: self.v8_isolate.as_mut().unwrap().thread_safe_handle(),
+      evaluating,
+    }
+  }
+
+  /// Makes `callback` the function `Deno.core.fns[name]`. Unlike ops, these
+  /// are plain V8 callbacks: calling one doesn't encode its arguments in a
+  /// buffer nor go through the dispatch of ops and the shared queue, which
+  /// makes it much cheaper. It's meant for tiny functions called very often,
+  /// like checking that a resource id is valid; anything which may block or
+  /// takes more than a few numbers should be an op.
+  ///
+  /// Callbacks reach the state of the isolate with `CoreIsolate::state()`.
+  /// They're registered before running the JS which could remove `Deno.core`.
+  ///
+  /// Fails if JS removed `Deno.core.fns`. Panics if the isolate will be
+  /// snapshotted, since V8 can't serialize functions whose callbacks aren't
+  /// known in advance.
+  pub fn register_fn(
+    &mut self,
+    name: &str,
+    callback: impl v8::MapFnTo<v8::FunctionCallback>,
+  ) -> Result<(), ErrBox> {
+    assert!(
+      self.snapshot_creator.is_none(),
+      "Functions can't be registered in an isolate which will snapshot"
+    );
+    let state_rc = Self::state(self);
+    let state = state_rc.borrow();
+
+    let mut hs = v8::HandleScope::new(self.v8_isolate.as_mut().unwrap());
+    let scope = hs.enter();
+    let context = state.global_context.get(scope).unwrap();
+    let mut cs = v8::ContextScope::new(scope, context);
+    let scope = cs.enter();
+
+    drop(state);
+
+    if bindings::set_fn(scope, context, name, callback) {
+      Ok(())
+    } else {
+      Err(
+        CustomError::new("Error", "Deno.core.fns was removed".to_string())
+          .into(),
+      )
+    }
+  }
+}
+
+/// Interrupts the interruptible script running on an isolate, see
+/// `CoreIsolate::interrupt_handle()`.
+///
+/// Unlike `v8::IsolateHandle::terminate_execution()`, it never stops other
+/// JavaScript: the script is only terminated while it runs, and a termination
+/// which races with its end is cancelled when it returns.
+pub struct InterruptHandle {
+  isolate_handle: v8::IsolateHandle,
+  evaluating: Arc<Mutex<bool>>,
+}
+
+impl InterruptHandle {
+  /// Terminates the interruptible script being run, if any. Returns whether
+  /// there was one.
+  pub fn interrupt(&self) -> bool {
+    let evaluating = self.evaluating.lock().unwrap();
+    if *evaluating {
+      self.isolate_handle.terminate_execution();
+    }
+    *evaluating
+  }
 }
 
 impl Future for CoreIsolate {
@@ -398,46 +547,44 @@ impl Future for CoreIsolate {
 
     check_promise_exceptions(scope)?;
 
-    let mut overflow_response: Option<(OpId, Buf)> = None;
-
-    loop {
-      let mut state = state_rc.borrow_mut();
-      // Now handle actual ops.
-      state.have_unpolled_ops = false;
-
-      let pending_r = state.pending_ops.poll_next_unpin(cx);
-      match pending_r {
-        Poll::Ready(None) => break,
-        Poll::Pending => break,
-        Poll::Ready(Some((op_id, buf))) => {
-          let successful_push = state.shared.push(op_id, &buf);
-          if !successful_push {
-            // If we couldn't push the response to the shared queue, because
-            // there wasn't enough size, we will return the buffer via the
-            // legacy route, using the argument of deno_respond.
-            overflow_response = Some((op_id, buf));
-            break;
-          }
-        }
+    // Now handle actual ops.
+    state_rc.borrow_mut().have_unpolled_ops = false;
+    for &priority in &[OpPriority::High, OpPriority::Normal, OpPriority::Low] {
+      let mut budget = match priority {
+        OpPriority::Low => LOW_PRIORITY_OPS_PER_TURN,
+        _ => usize::MAX,
       };
+      loop {
+        if budget == 0 {
+          // The remaining completions are handled in the next turn, after
+          // the ops of higher priority that completed in the meantime.
+          state_rc.borrow().waker.wake();
+          break;
+        }
+        let mut state = state_rc.borrow_mut();
+        let pending_r =
+          state.pending_ops[priority as usize].poll_next_unpin(cx);
+        drop(state);
+        match pending_r {
+          Poll::Ready(None) => break,
+          Poll::Pending => break,
+          Poll::Ready(Some((op_id, buf))) => {
+            push_async_response(scope, &state_rc, op_id, buf)?;
+            budget -= 1;
+          }
+        };
+      }
     }
 
     loop {
       let mut state = state_rc.borrow_mut();
       let unref_r = state.pending_unref_ops.poll_next_unpin(cx);
-      #[allow(clippy::match_wild_err_arm)]
+      drop(state);
       match unref_r {
         Poll::Ready(None) => break,
         Poll::Pending => break,
         Poll::Ready(Some((op_id, buf))) => {
-          let successful_push = state.shared.push(op_id, &buf);
-          if !successful_push {
-            // If we couldn't push the response to the shared queue, because
-            // there wasn't enough size, we will return the buffer via the
-            // legacy route, using the argument of deno_respond.
-            overflow_response = Some((op_id, buf));
-            break;
-          }
+          push_async_response(scope, &state_rc, op_id, buf)?;
         }
       };
     }
@@ -453,19 +600,13 @@ impl Future for CoreIsolate {
       }
     }
 
-    {
-      if let Some((op_id, buf)) = overflow_response.take() {
-        async_op_response(scope, Some((op_id, buf)))?;
-      }
-
-      drain_macrotasks(scope)?;
+    drain_macrotasks(scope)?;
 
-      check_promise_exceptions(scope)?;
-    }
+    check_promise_exceptions(scope)?;
 
     let state = state_rc.borrow();
     // We're idle if pending_ops is empty.
-    if state.pending_ops.is_empty() {
+    if state.pending_ops.iter().all(|ops| ops.is_empty()) {
       Poll::Ready(Ok(()))
     } else {
       if state.have_unpolled_ops {
@@ -526,7 +667,8 @@ impl CoreIsolateState {
       }
       Op::Async(fut) => {
         let fut2 = fut.map(move |buf| (op_id, buf));
-        self.pending_ops.push(fut2.boxed_local());
+        let priority = self.op_registry.priority(op_id);
+        self.pending_ops[priority as usize].push(fut2.boxed_local());
         self.have_unpolled_ops = true;
         None
       }
@@ -540,6 +682,31 @@ impl CoreIsolateState {
   }
 }
 
+/// Queues the response of an async op in the shared queue so that JS can
+/// handle many completions per call of Deno.core.recv. When the queue is
+/// full, the responses already in it are handed to JS to make room. Only a
+/// response that doesn't fit into the empty queue is passed by itself, using
+/// the argument of Deno.core.recv.
+fn push_async_response<'s>(
+  scope: &mut impl v8::ToLocal<'s>,
+  state_rc: &Rc<RefCell<CoreIsolateState>>,
+  op_id: OpId,
+  buf: Buf,
+) -> Result<(), ErrBox> {
+  if state_rc.borrow_mut().shared.push(op_id, &buf) {
+    return Ok(());
+  }
+  if state_rc.borrow().shared.size() > 0 {
+    async_op_response(scope, None)?;
+    // The other side should have shifted off all the messages.
+    assert_eq!(state_rc.borrow().shared.size(), 0);
+    if state_rc.borrow_mut().shared.push(op_id, &buf) {
+      return Ok(());
+    }
+  }
+  async_op_response(scope, Some((op_id, buf)))
+}
+
 fn async_op_response<'s>(
   scope: &mut impl v8::ToLocal<'s>,
   maybe_buf: Option<(OpId, Box<[u8]>)>,
@@ -687,6 +854,7 @@ pub fn js_check<T>(r: Result<T, ErrBox>) -> T {
 pub mod tests {
   use super::*;
   use futures::future::lazy;
+  use std::convert::TryFrom;
   use std::ops::FnOnce;
   use std::sync::atomic::{AtomicUsize, Ordering};
   use std::sync::Arc;
@@ -871,6 +1039,101 @@ pub mod tests {
     assert_eq!(dispatch_count.load(Ordering::Relaxed), 1);
   }
 
+  #[test]
+  fn test_poll_async_many_ops() {
+    run_in_task(|cx| {
+      let (mut isolate, dispatch_count) = setup(Mode::Async);
+      // More responses than the shared queue has records for.
+      js_check(isolate.execute(
+        "many_ops.js",
+        r#"
+         let nrecv = 0;
+         Deno.core.setAsyncHandler(1, (buf) => {
+           assert(buf.byteLength === 1);
+           assert(buf[0] === 43);
+           nrecv++;
+         });
+         let control = new Uint8Array([42]);
+         for (let i = 0; i < 250; i++) {
+           Deno.core.dispatch(1, control);
+         }
+         "#,
+      ));
+      assert_eq!(dispatch_count.load(Ordering::Relaxed), 250);
+      assert!(match isolate.poll_unpin(cx) {
+        Poll::Ready(Ok(_)) => true,
+        _ => false,
+      });
+      js_check(isolate.execute("check.js", "assert(nrecv == 250);"));
+    });
+  }
+
+  #[test]
+  fn test_poll_async_ops_by_priority() {
+    run_in_task(|cx| {
+      let (mut isolate, _dispatch_count) = setup(Mode::Async);
+      let low_id =
+        isolate.register_op_with_priority("low", OpPriority::Low, |_, _, _| {
+          let buf = vec![1u8].into_boxed_slice();
+          Op::Async(futures::future::ready(buf).boxed())
+        });
+      assert_eq!(low_id, 2);
+      js_check(isolate.execute(
+        "priority.js",
+        r#"
+         let order = [];
+         Deno.core.setAsyncHandler(1, () => order.push("normal"));
+         Deno.core.setAsyncHandler(2, () => order.push("low"));
+         let control = new Uint8Array([42]);
+         for (let i = 0; i < 40; i++) {
+           Deno.core.dispatch(2, control);
+         }
+         Deno.core.dispatch(1, control);
+         "#,
+      ));
+      // The normal op is handled first, and only some of the low ones.
+      assert!(match isolate.poll_unpin(cx) {
+        Poll::Pending => true,
+        _ => false,
+      });
+      js_check(isolate.execute(
+        "check1.js",
+        r#"
+         assert(order[0] === "normal");
+         assert(order.length === 33);
+         "#,
+      ));
+      assert!(match isolate.poll_unpin(cx) {
+        Poll::Ready(Ok(_)) => true,
+        _ => false,
+      });
+      js_check(isolate.execute("check2.js", "assert(order.length === 41);"));
+    });
+  }
+
+  #[test]
+  fn test_drop_releases_resources() {
+    struct DropFlag(Arc<AtomicUsize>);
+    impl Drop for DropFlag {
+      fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+      }
+    }
+
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let (isolate, _dispatch_count) = setup(Mode::Async);
+    let state_rc = CoreIsolate::state(&isolate);
+    let resource_table = state_rc.borrow().resource_table.clone();
+    resource_table
+      .borrow_mut()
+      .add("flag", Box::new(DropFlag(dropped.clone())));
+    drop(state_rc);
+    drop(isolate);
+    // The table outlived the isolate, but its resources didn't.
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+    assert!(resource_table.borrow().entries().is_empty());
+  }
+
   #[test]
   fn test_poll_async_delayed_ops() {
     run_in_task(|cx| {
@@ -994,6 +1257,52 @@ pub mod tests {
     terminator_thread.join().unwrap();
   }
 
+  #[test]
+  fn terminate_interruptible_eval_context() {
+    let (mut isolate, _dispatch_count) = setup(Mode::Async);
+    let interrupt_handle = isolate.interrupt_handle();
+
+    let terminator_thread = std::thread::spawn(move || {
+      // Retried until the script runs.
+      while !interrupt_handle.interrupt() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+      }
+    });
+
+    // Only the evaluated script is terminated; the calling script goes on.
+    js_check(isolate.execute(
+      "interruptible.js",
+      r#"
+        const [result, errInfo] = Deno.core.evalContext(
+          "for(;;) {}",
+          undefined,
+          true,
+        );
+        if (result !== null || !errInfo.isInterrupted) {
+          throw Error("expected the script to be interrupted");
+        }
+      "#,
+    ));
+
+    terminator_thread.join().unwrap();
+  }
+
+  #[test]
+  fn interrupt_without_eval_context() {
+    let (mut isolate, _dispatch_count) = setup(Mode::Async);
+    let interrupt_handle = isolate.interrupt_handle();
+    assert!(!interrupt_handle.interrupt());
+    js_check(isolate.execute(
+      "after_interrupt.js",
+      r#"
+        const [result, errInfo] = Deno.core.evalContext("1 + 1", undefined, true);
+        if (result !== 2 || errInfo !== null) {
+          throw Error("expected the script to run");
+        }
+      "#,
+    ));
+  }
+
   #[test]
   fn dangling_shared_isolate() {
     let v8_isolate_handle = {
@@ -1196,6 +1505,81 @@ pub mod tests {
     });
   }
 
+  #[test]
+  fn create_snapshot() {
+    let snapshot = CoreIsolate::create_snapshot(&[
+      Script {
+        source: "globalThis.a = 1;",
+        filename: "a.js",
+      },
+      Script {
+        source: "globalThis.b = a + 1;",
+        filename: "b.js",
+      },
+    ])
+    .unwrap();
+    let startup_data = StartupData::Snapshot(Snapshot::Boxed(snapshot));
+    let mut isolate = CoreIsolate::new(startup_data, false);
+    js_check(isolate.execute("check.js", "if (b != 2) throw Error('x')"));
+
+    let result = CoreIsolate::create_snapshot(&[Script {
+      source: "throw Error('setup failed')",
+      filename: "setup.js",
+    }]);
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn register_fn() {
+    fn has_resource(
+      scope: v8::FunctionCallbackScope,
+      args: v8::FunctionCallbackArguments,
+      mut rv: v8::ReturnValue,
+    ) {
+      let state_rc = CoreIsolate::state(scope.isolate());
+      let state = state_rc.borrow();
+      let has_resource = match v8::Local::<v8::Uint32>::try_from(args.get(0)) {
+        Ok(rid) => state.resource_table.borrow().has(rid.value()),
+        Err(_) => false,
+      };
+      rv.set(v8::Boolean::new(scope, has_resource).into());
+    }
+
+    let mut isolate = CoreIsolate::new(StartupData::None, false);
+    isolate.register_fn("hasResource", has_resource).unwrap();
+    let state_rc = CoreIsolate::state(&isolate);
+    let resource_table = state_rc.borrow().resource_table.clone();
+    let rid = resource_table.borrow_mut().add("test", Box::new(()));
+    js_check(isolate.execute(
+      "has_resource.js",
+      &format!(
+        r#"
+          const {{ hasResource }} = Deno.core.fns;
+          if (!hasResource({}) || hasResource({}) || hasResource("a")) {{
+            throw Error("assert");
+          }}
+        "#,
+        rid,
+        rid + 1
+      ),
+    ));
+  }
+
+  #[test]
+  fn register_fn_without_fns() {
+    fn noop(
+      _scope: v8::FunctionCallbackScope,
+      _args: v8::FunctionCallbackArguments,
+      _rv: v8::ReturnValue,
+    ) {
+    }
+
+    let mut isolate = CoreIsolate::new(StartupData::None, false);
+    js_check(isolate.execute("delete_fns.js", "delete Deno.core.fns;"));
+    let err = isolate.register_fn("noop", noop).unwrap_err();
+    assert_eq!(err.to_string(), "Deno.core.fns was removed");
+  }
+
   #[test]
   fn will_snapshot() {
     let snapshot = {
@@ -78,6 +78,8 @@ impl fmt::Display for ErrBox {
 #[derive(Debug, PartialEq, Clone)]
 pub struct JSError {
   pub message: String,
+  /// `name` of the thrown error, `None` if what was thrown isn't an Error.
+  pub name: Option<String>,
   pub source_line: Option<String>,
   pub script_resource_name: Option<String>,
   pub line_number: Option<i64>,
@@ -132,7 +134,9 @@ impl JSError {
 
     let msg = v8::Exception::create_message(scope, exception);
 
-    let (message, frames, formatted_frames) = if exception.is_native_error() {
+    let (message, name, frames, formatted_frames) = if exception
+      .is_native_error()
+    {
       // The exception is a JS Error object.
       let exception: v8::Local<v8::Object> =
         exception.clone().try_into().unwrap();
@@ -147,6 +151,7 @@ impl JSError {
         .map(|s| s.to_rust_string_lossy(scope))
         .unwrap_or_else(|| "undefined".to_string());
       let message = format!("Uncaught {}: {}", name, message_prop);
+      let name = Some(name);
 
       // Access error.stack to ensure that prepareStackTrace() has been called.
       // This should populate error.__callSiteEvals and error.__formattedFrames.
@@ -286,16 +291,22 @@ impl JSError {
           formatted_frames.push(formatted_frame)
         }
       }
-      (message, frames, formatted_frames)
+      (message, name, frames, formatted_frames)
     } else {
       // The exception is not a JS Error object.
       // Get the message given by V8::Exception::create_message(), and provide
       // empty frames.
-      (msg.get(scope).to_rust_string_lossy(scope), vec![], vec![])
+      (
+        msg.get(scope).to_rust_string_lossy(scope),
+        None,
+        vec![],
+        vec![],
+      )
     };
 
     Self {
       message,
+      name,
       script_resource_name: msg
         .get_script_resource_name(scope)
         .and_then(|v| v8::Local::<v8::String>::try_from(v).ok())
@@ -364,6 +375,33 @@ impl fmt::Display for JSError {
   }
 }
 
+/// An error that isn't one of the kinds known to the runtime, such as one
+/// defined by an embedder for its own ops. It only carries the name of the
+/// class that the embedder throws for it in JavaScript, so that new kinds of
+/// errors can be added without changing the list of built-in ones.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CustomError {
+  pub class_name: String,
+  pub message: String,
+}
+
+impl CustomError {
+  pub fn new(class_name: &str, message: String) -> Self {
+    Self {
+      class_name: class_name.to_string(),
+      message,
+    }
+  }
+}
+
+impl Error for CustomError {}
+
+impl fmt::Display for CustomError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.pad(&self.message)
+  }
+}
+
 pub(crate) fn attach_handle_to_error(
   scope: &mut impl v8::InIsolate,
   err: ErrBox,
@@ -0,0 +1,23 @@
+// Calls a no-op through `Deno.core.dispatch()` and through `Deno.core.fns`,
+// and prints how long a call takes on average for both.
+const ITERATIONS = 1e6;
+
+function bench(name, f) {
+  // Let V8 optimize `f` before measuring it.
+  for (let i = 0; i < ITERATIONS / 10; i++) {
+    f();
+  }
+  const start = Date.now();
+  for (let i = 0; i < ITERATIONS; i++) {
+    f();
+  }
+  const nsPerCall = ((Date.now() - start) * 1e6) / ITERATIONS;
+  Deno.core.print(`${name}: ${nsPerCall.toFixed(0)} ns/call\n`);
+}
+
+const opId = Deno.core.ops()["noop"];
+const control = new Uint8Array(0);
+const { noop } = Deno.core.fns;
+
+bench("op", () => Deno.core.dispatch(opId, control));
+bench("fn", () => noop());
@@ -0,0 +1,41 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! Compares the cost of calling a function registered with
+//! `CoreIsolate::register_fn()` to the cost of dispatching a sync op which
+//! does the same nothing.
+
+use deno_core::js_check;
+use deno_core::v8;
+use deno_core::CoreIsolate;
+use deno_core::CoreIsolateState;
+use deno_core::Op;
+use deno_core::StartupData;
+use deno_core::ZeroCopyBuf;
+use std::env;
+
+fn op_noop(
+  _isolate_state: &mut CoreIsolateState,
+  _control_buf: &[u8],
+  _zero_copy_bufs: &mut [ZeroCopyBuf],
+) -> Op {
+  Op::Sync(vec![].into_boxed_slice())
+}
+
+fn fn_noop(
+  _scope: v8::FunctionCallbackScope,
+  _args: v8::FunctionCallbackArguments,
+  _rv: v8::ReturnValue,
+) {
+}
+
+fn main() {
+  // NOTE: `--help` arg will display V8 help and exit
+  deno_core::v8_set_flags(env::args().collect());
+
+  let mut isolate = CoreIsolate::new(StartupData::None, false);
+  isolate.register_op("noop", op_noop);
+  isolate
+    .register_fn("noop", fn_noop)
+    .expect("failed to register fn");
+  js_check(isolate.execute("fns_bench.js", include_str!("fns_bench.js")));
+}
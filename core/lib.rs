@@ -15,6 +15,7 @@ mod es_isolate;
 mod flags;
 mod module_specifier;
 mod modules;
+pub mod op_api;
 mod ops;
 pub mod plugin_api;
 mod resources;
@@ -26,11 +27,14 @@ pub use rusty_v8 as v8;
 pub use crate::core_isolate::js_check;
 pub use crate::core_isolate::CoreIsolate;
 pub use crate::core_isolate::CoreIsolateState;
+pub use crate::core_isolate::InterruptHandle;
 pub use crate::core_isolate::Script;
 pub use crate::core_isolate::Snapshot;
 pub use crate::core_isolate::StartupData;
+pub use crate::errors::CustomError;
 pub use crate::errors::ErrBox;
 pub use crate::errors::JSError;
+pub use crate::errors::JSStackFrame;
 pub use crate::es_isolate::EsIsolate;
 pub use crate::es_isolate::EsIsolateState;
 pub use crate::flags::v8_set_flags;
@@ -46,6 +50,7 @@ pub use crate::ops::Buf;
 pub use crate::ops::Op;
 pub use crate::ops::OpAsyncFuture;
 pub use crate::ops::OpId;
+pub use crate::ops::OpPriority;
 pub use crate::resources::ResourceTable;
 pub use crate::zero_copy_buf::ZeroCopyBuf;
 
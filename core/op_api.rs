@@ -0,0 +1,180 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+
+//! The interface for embedders to add their own ops to an isolate, such as the
+//! domain-specific ops of an application built on this crate.
+//!
+//! Unlike the rest of the crate, which changes along with the CLI, this module
+//! follows semver: it only changes incompatibly in a new major version. That's
+//! why it only exposes what an op needs, which is its name, whether it's sync
+//! or async, the buffers sent with it and the resources of the isolate, and
+//! not the internals of the isolate it's dispatched in. Even the resources are
+//! only reached through the methods of `OpContext`, so that `ResourceTable`
+//! can keep changing.
+
+pub use crate::resources::Resource;
+pub use crate::resources::ResourceId;
+pub use crate::Buf;
+pub use crate::OpId;
+pub use crate::ZeroCopyBuf;
+
+use crate::CoreIsolate;
+use crate::CoreIsolateState;
+use crate::Op;
+use futures::future::FutureExt;
+use std::future::Future;
+
+/// What an op can reach of the isolate dispatching it.
+pub struct OpContext<'a> {
+  state: &'a mut CoreIsolateState,
+}
+
+impl OpContext<'_> {
+  /// Adds a resource to the isolate, returning the id JavaScript refers to it
+  /// by. The name shows up in `Deno.resources()`.
+  pub fn add_resource<T: Resource>(
+    &self,
+    name: &str,
+    resource: T,
+  ) -> ResourceId {
+    let mut resource_table = self.state.resource_table.borrow_mut();
+    resource_table.add(name, Box::new(resource))
+  }
+
+  /// Calls `f` with the resource `rid`, or returns `None` if there's no such
+  /// resource or it isn't a `T`.
+  pub fn with_resource<T: Resource, R>(
+    &self,
+    rid: ResourceId,
+    f: impl FnOnce(&mut T) -> R,
+  ) -> Option<R> {
+    let mut resource_table = self.state.resource_table.borrow_mut();
+    resource_table.get_mut::<T>(rid).map(f)
+  }
+
+  /// Removes the resource `rid` from the isolate, returning it if it's a `T`.
+  pub fn take_resource<T: Resource>(&self, rid: ResourceId) -> Option<Box<T>> {
+    self.state.resource_table.borrow_mut().remove::<T>(rid)
+  }
+
+  /// Closes the resource `rid`, returning `None` if there's no such resource.
+  pub fn close_resource(&self, rid: ResourceId) -> Option<()> {
+    self.state.resource_table.borrow_mut().close(rid)
+  }
+
+  /// The ids and names of the resources of the isolate.
+  pub fn resources(&self) -> Vec<(ResourceId, String)> {
+    self.state.resource_table.borrow().entries()
+  }
+}
+
+/// Registers ops, which JavaScript looks up by name with `Deno.core.ops()`
+/// and calls with `Deno.core.dispatch(opId, control, ...zeroCopy)`. The names
+/// must be unique in the isolate.
+pub trait RegisterOps {
+  /// Registers an op whose response is returned by `Deno.core.dispatch()`.
+  fn register_sync_op<F>(&mut self, name: &str, op: F) -> OpId
+  where
+    F: Fn(&mut OpContext, &[u8], &mut [ZeroCopyBuf]) -> Buf + 'static;
+
+  /// Registers an op whose response is handed to the handler set with
+  /// `Deno.core.setAsyncHandler()` once the future it returns completes. The
+  /// zero-copy buffers can be cloned into the future.
+  fn register_async_op<F, R>(&mut self, name: &str, op: F) -> OpId
+  where
+    F: Fn(&mut OpContext, &[u8], &mut [ZeroCopyBuf]) -> R + 'static,
+    R: Future<Output = Buf> + 'static;
+}
+
+impl RegisterOps for CoreIsolate {
+  fn register_sync_op<F>(&mut self, name: &str, op: F) -> OpId
+  where
+    F: Fn(&mut OpContext, &[u8], &mut [ZeroCopyBuf]) -> Buf + 'static,
+  {
+    self.register_op(name, move |state, control, zero_copy| {
+      Op::Sync(op(&mut OpContext { state }, control, zero_copy))
+    })
+  }
+
+  fn register_async_op<F, R>(&mut self, name: &str, op: F) -> OpId
+  where
+    F: Fn(&mut OpContext, &[u8], &mut [ZeroCopyBuf]) -> R + 'static,
+    R: Future<Output = Buf> + 'static,
+  {
+    self.register_op(name, move |state, control, zero_copy| {
+      let fut = op(&mut OpContext { state }, control, zero_copy);
+      Op::Async(fut.boxed_local())
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::core_isolate::tests::run_in_task;
+  use crate::js_check;
+  use crate::StartupData;
+  use std::task::Poll;
+
+  #[test]
+  fn sync_and_async_ops() {
+    run_in_task(|cx| {
+      let mut isolate = CoreIsolate::new(StartupData::None, false);
+      let sum_id =
+        isolate.register_sync_op("sum", |context, control, zero_copy| {
+          let rid = context.add_resource("counter", 0u8);
+          assert_eq!(context.resources(), vec![(rid, "counter".to_string())]);
+          for n in control.iter().chain(zero_copy[0].iter()) {
+            context
+              .with_resource(rid, |sum: &mut u8| *sum += n)
+              .unwrap();
+          }
+          let sum = *context.take_resource::<u8>(rid).unwrap();
+          assert_eq!(context.close_resource(rid), None);
+          vec![sum].into_boxed_slice()
+        });
+      let echo_id =
+        isolate.register_async_op("echo", |context, _, zero_copy| {
+          assert_eq!(context.resources().len(), 0);
+          let buf = zero_copy[0].clone();
+          async move { buf.to_vec().into_boxed_slice() }
+        });
+      assert_eq!((sum_id, echo_id), (1, 2));
+
+      js_check(isolate.execute(
+        "ops.js",
+        r#"
+          function assert(cond) {
+            if (!cond) {
+              throw Error("assert");
+            }
+          }
+          const ops = Deno.core.ops();
+          const sum = Deno.core.dispatch(
+            ops.sum,
+            new Uint8Array([1, 2]),
+            new Uint8Array([3])
+          );
+          assert(sum[0] === 6);
+          var echoed = null;
+          Deno.core.setAsyncHandler(ops.echo, (buf) => {
+            echoed = buf;
+          });
+          const pending = Deno.core.dispatch(
+            ops.echo,
+            new Uint8Array([]),
+            new Uint8Array([4, 5])
+          );
+          assert(pending === undefined);
+        "#,
+      ));
+      assert!(match isolate.poll_unpin(cx) {
+        Poll::Ready(Ok(_)) => true,
+        _ => false,
+      });
+      js_check(isolate.execute(
+        "check.js",
+        "assert(echoed.length === 2 && echoed[1] === 5);",
+      ));
+    });
+  }
+}
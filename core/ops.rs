@@ -20,13 +20,36 @@ pub enum Op {
   AsyncUnref(OpAsyncFuture),
 }
 
+/// How urgently the completions of an async op are handed to JS. The isolate
+/// drains completed ops from the highest priority down, and hands only so
+/// many low priority completions to JS per turn of the event loop, so that a
+/// flood of background work can't hold back time sensitive I/O.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpPriority {
+  /// For ops like timers, which are late as soon as they wait.
+  High = 0,
+  /// For I/O; the priority ops are registered with by default.
+  Normal = 1,
+  /// For background work, like writing to caches.
+  Low = 2,
+}
+
+impl Default for OpPriority {
+  fn default() -> Self {
+    OpPriority::Normal
+  }
+}
+
 /// Main type describing op
 pub type OpDispatcher =
   dyn Fn(&mut CoreIsolateState, &[u8], &mut [ZeroCopyBuf]) -> Op + 'static;
 
+/// Maps op names to the ids that JavaScript dispatches with, and ids to their
+/// dispatchers. Op 0 is built in and returns the name to id map.
 #[derive(Default)]
 pub struct OpRegistry {
   dispatchers: Vec<Rc<OpDispatcher>>,
+  priorities: Vec<OpPriority>,
   name_to_id: HashMap<String, OpId>,
 }
 
@@ -41,7 +64,23 @@ impl OpRegistry {
     registry
   }
 
+  /// Registers `op` under `name`, which must not be taken yet, and returns
+  /// its id.
   pub fn register<F>(&mut self, name: &str, op: F) -> OpId
+  where
+    F: Fn(&mut CoreIsolateState, &[u8], &mut [ZeroCopyBuf]) -> Op + 'static,
+  {
+    self.register_with_priority(name, OpPriority::default(), op)
+  }
+
+  /// Like `register`, but the completions of `op` are handed to JS with
+  /// `priority`.
+  pub fn register_with_priority<F>(
+    &mut self,
+    name: &str,
+    priority: OpPriority,
+    op: F,
+  ) -> OpId
   where
     F: Fn(&mut CoreIsolateState, &[u8], &mut [ZeroCopyBuf]) -> Op + 'static,
   {
@@ -53,9 +92,23 @@ impl OpRegistry {
       format!("Op already registered: {}", name)
     );
     self.dispatchers.push(Rc::new(op));
+    self.priorities.push(priority);
     op_id
   }
 
+  /// Replaces the dispatcher of each op with the one `wrap` returns for it,
+  /// so that all the ops can be instrumented at once. The ops registered
+  /// afterwards aren't wrapped.
+  pub fn wrap_dispatchers(
+    &mut self,
+    wrap: impl Fn(&str, Rc<OpDispatcher>) -> Rc<OpDispatcher>,
+  ) {
+    for (name, op_id) in &self.name_to_id {
+      let dispatcher = &mut self.dispatchers[*op_id as usize];
+      *dispatcher = wrap(name, dispatcher.clone());
+    }
+  }
+
   fn json_map(&self) -> Buf {
     let op_map_json = serde_json::to_string(&self.name_to_id).unwrap();
     op_map_json.as_bytes().to_owned().into_boxed_slice()
@@ -64,6 +117,14 @@ impl OpRegistry {
   pub fn get(&self, op_id: OpId) -> Option<Rc<OpDispatcher>> {
     self.dispatchers.get(op_id as usize).map(Rc::clone)
   }
+
+  pub fn priority(&self, op_id: OpId) -> OpPriority {
+    self
+      .priorities
+      .get(op_id as usize)
+      .copied()
+      .unwrap_or_default()
+  }
 }
 
 #[test]
@@ -103,6 +164,41 @@ fn test_op_registry() {
   assert!(op_registry.get(100).is_none());
 }
 
+#[test]
+fn wrap_dispatchers() {
+  use crate::CoreIsolate;
+  use std::cell::RefCell;
+  let mut op_registry = OpRegistry::new();
+  let test_id = op_registry.register("test", |_, _, _| Op::Sync(Box::new([1])));
+
+  let names = Rc::new(RefCell::new(vec![]));
+  let names_ = names.clone();
+  op_registry.wrap_dispatchers(|name, dispatcher| {
+    names_.borrow_mut().push(name.to_string());
+    Rc::new(
+      move |state: &mut CoreIsolateState,
+            control: &[u8],
+            zero_copy: &mut [ZeroCopyBuf]| {
+        match dispatcher(state, control, zero_copy) {
+          Op::Sync(buf) => Op::Sync(buf.iter().map(|b| b + 1).collect()),
+          op => op,
+        }
+      },
+    )
+  });
+  names.borrow_mut().sort();
+  assert_eq!(*names.borrow(), vec!["ops", "test"]);
+
+  let isolate = CoreIsolate::new(crate::StartupData::None, false);
+  let dispatch = op_registry.get(test_id).unwrap();
+  let state_rc = CoreIsolate::state(&isolate);
+  let mut state = state_rc.borrow_mut();
+  match dispatch(&mut state, &[], &mut []) {
+    Op::Sync(buf) => assert_eq!(&*buf, &[2]),
+    _ => unreachable!(),
+  }
+}
+
 #[test]
 fn register_op_during_call() {
   use crate::CoreIsolate;
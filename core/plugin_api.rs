@@ -12,11 +12,193 @@ pub use crate::Buf;
 pub use crate::Op;
 pub use crate::OpId;
 pub use crate::ZeroCopyBuf;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::c_char;
 
 pub type InitFn = fn(&mut dyn Interface);
 
+/// The version of this interface, which is bumped whenever it changes
+/// incompatibly.
+pub const ABI_VERSION: u32 = 2;
+
+/// What a plugin was built against. Plugins export it as
+/// `deno_plugin_descriptor`, and are only initialized if it's compatible:
+///
+/// ```ignore
+/// #[no_mangle]
+/// #[allow(non_upper_case_globals)]
+/// pub static deno_plugin_descriptor: PluginDescriptor =
+///   PluginDescriptor::CURRENT;
+/// ```
+///
+/// Its fields only have C types, so that it can be read whatever compiler
+/// the plugin was built with. The ops of plugins hand buffers to Deno, which
+/// frees them, so plugins mustn't set a global allocator of their own.
+#[repr(C)]
+pub struct PluginDescriptor {
+  /// `ABI_VERSION` of the plugin. The fields after it are only read if it's
+  /// the same as Deno's, since their layout may differ otherwise.
+  pub abi_version: u32,
+  /// The size of this struct in the plugin, which tells that its layout is
+  /// the one Deno expects.
+  pub size: u32,
+  /// The version of deno_core, whose `Op` and `ZeroCopyBuf` types are shared
+  /// with the plugin, as a nul-terminated string.
+  pub core_version: *const c_char,
+  /// The output of `rustc --version` for the compiler the plugin was built
+  /// with, as a nul-terminated string. The layout of the Rust types shared
+  /// with the plugin is only the same with the same compiler.
+  pub rustc_version: *const c_char,
+}
+
+// The strings of descriptors are static and never written to.
+unsafe impl Sync for PluginDescriptor {}
+
+impl PluginDescriptor {
+  pub const CURRENT: PluginDescriptor = PluginDescriptor {
+    abi_version: ABI_VERSION,
+    size: mem::size_of::<PluginDescriptor>() as u32,
+    core_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr()
+      as *const c_char,
+    rustc_version: concat!(env!("DENO_CORE_RUSTC_VERSION"), "\0").as_ptr()
+      as *const c_char,
+  };
+
+  /// Why a plugin with this descriptor can't be loaded, if it can't.
+  ///
+  /// # Safety
+  /// `descriptor` must point to a `PluginDescriptor`, possibly of another
+  /// version of this interface.
+  pub unsafe fn check(descriptor: *const Self) -> Result<(), String> {
+    let abi_version = *(descriptor as *const u32);
+    if abi_version != ABI_VERSION {
+      return Err(format!(
+        "it's built for plugin interface {}, Deno supports {}",
+        abi_version, ABI_VERSION
+      ));
+    }
+    let descriptor = &*descriptor;
+    if descriptor.size != Self::CURRENT.size {
+      return Err(format!(
+        "its descriptor has {} bytes, Deno expects {}",
+        descriptor.size,
+        Self::CURRENT.size
+      ));
+    }
+    let current_core_version = to_string(Self::CURRENT.core_version);
+    let core_version = to_string(descriptor.core_version);
+    if !is_compatible(&core_version, &current_core_version) {
+      return Err(format!(
+        "it's built with deno_core {}, Deno has {}",
+        core_version, current_core_version
+      ));
+    }
+    let current_rustc_version = to_string(Self::CURRENT.rustc_version);
+    let rustc_version = to_string(descriptor.rustc_version);
+    if rustc_version != current_rustc_version {
+      return Err(format!(
+        "it's built with {}, Deno with {}",
+        rustc_version, current_rustc_version
+      ));
+    }
+    Ok(())
+  }
+}
+
+/// # Safety
+/// `s` must point to a nul-terminated string.
+unsafe fn to_string(s: *const c_char) -> String {
+  CStr::from_ptr(s).to_string_lossy().into_owned()
+}
+
+/// Whether a plugin built with deno_core `version` can run in a Deno that has
+/// deno_core `host`. Their major versions must match, and so do their minor
+/// versions before 1.0. The next part of the version of the plugin mustn't be
+/// newer, since it may use what the host doesn't have yet.
+fn is_compatible(version: &str, host: &str) -> bool {
+  let parse = |version: &str| -> Option<Vec<u64>> {
+    let release = version.split(|c: char| c == '-' || c == '+').next()?;
+    let parts = release
+      .split('.')
+      .map(|part| part.parse().ok())
+      .collect::<Option<Vec<u64>>>()?;
+    if parts.len() == 3 {
+      Some(parts)
+    } else {
+      None
+    }
+  };
+  match (parse(version), parse(host)) {
+    (Some(version), Some(host)) => {
+      let fixed = if host[0] == 0 { 2 } else { 1 };
+      version[..fixed] == host[..fixed] && version[fixed] <= host[fixed]
+    }
+    _ => false,
+  }
+}
+
 pub type DispatchOpFn = fn(&mut dyn Interface, &[u8], &mut [ZeroCopyBuf]) -> Op;
 
 pub trait Interface {
   fn register_op(&mut self, name: &str, dispatcher: DispatchOpFn) -> OpId;
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn compatible_versions() {
+    assert!(is_compatible("0.47.0", "0.47.1"));
+    assert!(is_compatible("0.47.1", "0.47.1"));
+    assert!(!is_compatible("0.47.2", "0.47.1"));
+    assert!(!is_compatible("0.46.0", "0.47.0"));
+    assert!(is_compatible("1.0.3", "1.2.0"));
+    assert!(is_compatible("1.2.5", "1.2.0"));
+    assert!(!is_compatible("1.3.0", "1.2.0"));
+    assert!(!is_compatible("2.0.0", "1.0.3"));
+    assert!(is_compatible("1.0.0-rc.1", "1.0.0"));
+    assert!(!is_compatible("1.0", "1.0.0"));
+  }
+
+  #[test]
+  fn descriptor_layout() {
+    // The fields are read by their offsets in plugins built with other
+    // compilers, so they mustn't move.
+    let pointer = mem::size_of::<*const c_char>();
+    assert_eq!(mem::size_of::<PluginDescriptor>(), 8 + 2 * pointer);
+    assert_eq!(PluginDescriptor::CURRENT.size as usize, 8 + 2 * pointer);
+  }
+
+  #[test]
+  fn check_descriptors() {
+    unsafe {
+      assert!(PluginDescriptor::check(&PluginDescriptor::CURRENT).is_ok());
+      let future_abi: u32 = ABI_VERSION + 1;
+      let err = PluginDescriptor::check(
+        &future_abi as *const u32 as *const PluginDescriptor,
+      )
+      .unwrap_err();
+      assert!(err.contains("plugin interface 3"));
+      let bigger = PluginDescriptor {
+        size: PluginDescriptor::CURRENT.size + 8,
+        ..PluginDescriptor::CURRENT
+      };
+      let err = PluginDescriptor::check(&bigger).unwrap_err();
+      assert!(err.contains("descriptor has"));
+      let old_core = PluginDescriptor {
+        core_version: "0.1.0\0".as_ptr() as *const c_char,
+        ..PluginDescriptor::CURRENT
+      };
+      let err = PluginDescriptor::check(&old_core).unwrap_err();
+      assert!(err.contains("deno_core 0.1.0"));
+      let other_rustc = PluginDescriptor {
+        rustc_version: "rustc 1.0.0\0".as_ptr() as *const c_char,
+        ..PluginDescriptor::CURRENT
+      };
+      let err = PluginDescriptor::check(&other_rustc).unwrap_err();
+      assert!(err.contains("built with rustc 1.0.0"));
+    }
+  }
+}
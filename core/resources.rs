@@ -9,6 +9,7 @@
 use downcast_rs::Downcast;
 use std::any::Any;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 /// ResourceId is Deno's version of a file descriptor. ResourceId is also referred
 /// to as rid in the code base.
@@ -22,6 +23,9 @@ type ResourceMap = HashMap<ResourceId, (String, Box<dyn Resource>)>;
 pub struct ResourceTable {
   map: ResourceMap,
   next_id: u32,
+  /// Where the resources added now are created from, if it's tracked.
+  origin: Option<Rc<str>>,
+  origins: HashMap<ResourceId, Rc<str>>,
 }
 
 impl ResourceTable {
@@ -56,9 +60,23 @@ impl ResourceTable {
     let rid = self.next_rid();
     let r = self.map.insert(rid, (name.to_string(), resource));
     assert!(r.is_none());
+    if let Some(origin) = &self.origin {
+      self.origins.insert(rid, origin.clone());
+    }
     rid
   }
 
+  /// Sets the origin of the resources added from now on, like the stack of
+  /// the code that started the op adding them, and returns the previous one.
+  pub fn set_origin(&mut self, origin: Option<Rc<str>>) -> Option<Rc<str>> {
+    std::mem::replace(&mut self.origin, origin)
+  }
+
+  /// Returns the origin of a resource, if one was set when it was added.
+  pub fn origin(&self, rid: ResourceId) -> Option<&str> {
+    self.origins.get(&rid).map(|origin| &**origin)
+  }
+
   pub fn entries(&self) -> Vec<(ResourceId, String)> {
     self
       .map
@@ -70,11 +88,13 @@ impl ResourceTable {
   // close(2) is done by dropping the value. Therefore we just need to remove
   // the resource from the resource table.
   pub fn close(&mut self, rid: ResourceId) -> Option<()> {
+    self.origins.remove(&rid);
     self.map.remove(&rid).map(|(_name, _resource)| ())
   }
 
   pub fn remove<T: Resource>(&mut self, rid: ResourceId) -> Option<Box<T>> {
     if let Some((_name, resource)) = self.map.remove(&rid) {
+      self.origins.remove(&rid);
       let res = match resource.downcast::<T>() {
         Ok(res) => Some(res),
         Err(_e) => None,
@@ -163,4 +183,17 @@ mod tests {
     assert_eq!(table.map.len(), 0);
     assert!(res2.is_some());
   }
+
+  #[test]
+  fn test_resource_origins() {
+    let mut table = ResourceTable::default();
+    let rid1 = table.add("fake1", Box::new(FakeResource::new(1)));
+    assert_eq!(table.set_origin(Some(Rc::from("here"))), None);
+    let rid2 = table.add("fake2", Box::new(FakeResource::new(2)));
+    assert_eq!(table.set_origin(None).as_deref(), Some("here"));
+    assert_eq!(table.origin(rid1), None);
+    assert_eq!(table.origin(rid2), Some("here"));
+    table.close(rid2);
+    assert_eq!(table.origin(rid2), None);
+  }
 }
@@ -6,8 +6,10 @@ use std::ops::DerefMut;
 /// A ZeroCopyBuf encapsulates a slice that's been borrowed from a JavaScript
 /// ArrayBuffer object. JavaScript objects can normally be garbage collected,
 /// but the existence of a ZeroCopyBuf inhibits this until it is dropped. It
-/// behaves much like an Arc<[u8]>, although a ZeroCopyBuf currently can't be
-/// cloned.
+/// behaves much like an Arc<[u8]>: clones point to the same memory, and the
+/// backing store stays alive until the last clone is dropped. This is what
+/// lets async ops read into and write from the buffers passed by JavaScript
+/// without copying them.
 #[derive(Clone)]
 pub struct ZeroCopyBuf {
   backing_store: v8::SharedRef<v8::BackingStore>,
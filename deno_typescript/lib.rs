@@ -195,12 +195,39 @@ fn print_source_code(code: &str) {
   }
 }
 
+/// JS that runs in a snapshot after its main bundle, which embedders add
+/// their own runtime layers with.
+pub enum Extension {
+  /// A bundle written by `compile_bundle()`, and the name of its main module.
+  Bundle(PathBuf, String),
+  /// A script, which isn't compiled.
+  Script(PathBuf),
+}
+
 /// Create a V8 snapshot.
 pub fn mksnapshot_bundle(
   isolate: &mut CoreIsolate,
   snapshot_filename: &Path,
   bundle_filename: &Path,
   main_module_name: &str,
+) -> Result<(), ErrBox> {
+  mksnapshot_bundle_with_extensions(
+    isolate,
+    snapshot_filename,
+    bundle_filename,
+    main_module_name,
+    &[],
+  )
+}
+
+/// Create a V8 snapshot, in which `extensions` run in order after the main
+/// bundle.
+pub fn mksnapshot_bundle_with_extensions(
+  isolate: &mut CoreIsolate,
+  snapshot_filename: &Path,
+  bundle_filename: &Path,
+  main_module_name: &str,
+  extensions: &[Extension],
 ) -> Result<(), ErrBox> {
   js_check(isolate.execute("system_loader.js", SYSTEM_LOADER));
   let source_code_vec = std::fs::read(bundle_filename).unwrap();
@@ -210,6 +237,26 @@ pub fn mksnapshot_bundle(
   );
   let script = &format!("__instantiate(\"{}\");", main_module_name);
   js_check(isolate.execute("anon", script));
+
+  for extension in extensions {
+    match extension {
+      // The loader is gone once its bundle is instantiated, so each bundle of
+      // an extension has its own, in a block. They can't import the modules
+      // of the other bundles.
+      Extension::Bundle(bundle_filename, main_module_name) => {
+        let bundle_source_code = fs::read_to_string(bundle_filename)?;
+        let script = format!(
+          "{{\n{}\n{}\n__instantiate(\"{}\");\n}}",
+          SYSTEM_LOADER, bundle_source_code, main_module_name
+        );
+        js_check(isolate.execute(&bundle_filename.to_string_lossy(), &script));
+      }
+      Extension::Script(filename) => {
+        let source_code = fs::read_to_string(filename)?;
+        js_check(isolate.execute(&filename.to_string_lossy(), &source_code));
+      }
+    }
+  }
   write_snapshot(isolate, snapshot_filename)?;
   Ok(())
 }
@@ -358,3 +405,61 @@ pub fn op_fetch_asset<S: ::std::hash::BuildHasher>(
     deno_core::Op::Sync(vec.into_boxed_slice())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use deno_core::Snapshot;
+
+  #[test]
+  fn snapshot_with_extensions() {
+    let dir = std::env::temp_dir()
+      .join(format!("deno_typescript_extensions_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let bundle_path = dir.join("bundle.js");
+    fs::write(
+      &bundle_path,
+      r#"System.register("main", [], function (exports) {
+        return { setters: [], execute() { globalThis.order = ["main"]; } };
+      });"#,
+    )
+    .unwrap();
+    let extension_bundle_path = dir.join("extension_bundle.js");
+    fs::write(
+      &extension_bundle_path,
+      r#"System.register("extension", [], function (exports) {
+        return { setters: [], execute() { globalThis.order.push("bundle"); } };
+      });"#,
+    )
+    .unwrap();
+    let script_path = dir.join("extension.js");
+    fs::write(&script_path, "globalThis.order.push('script');").unwrap();
+    let snapshot_path = dir.join("snapshot.bin");
+
+    let mut isolate = CoreIsolate::new(StartupData::None, true);
+    mksnapshot_bundle_with_extensions(
+      &mut isolate,
+      &snapshot_path,
+      &bundle_path,
+      "main",
+      &[
+        Extension::Bundle(extension_bundle_path, "extension".to_string()),
+        Extension::Script(script_path),
+      ],
+    )
+    .unwrap();
+    drop(isolate);
+
+    // The globals the extensions set are in the snapshot, in their order.
+    let snapshot = fs::read(&snapshot_path).unwrap().into_boxed_slice();
+    let mut isolate =
+      CoreIsolate::new(StartupData::Snapshot(Snapshot::Boxed(snapshot)), false);
+    js_check(isolate.execute(
+      "check.js",
+      r#"if (globalThis.order.join() !== "main,bundle,script") {
+        throw new Error(`Unexpected order: ${globalThis.order}`);
+      }"#,
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -1,9 +1,14 @@
 use deno_core::plugin_api::Buf;
 use deno_core::plugin_api::Interface;
 use deno_core::plugin_api::Op;
+use deno_core::plugin_api::PluginDescriptor;
 use deno_core::plugin_api::ZeroCopyBuf;
 use futures::future::FutureExt;
 
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static deno_plugin_descriptor: PluginDescriptor = PluginDescriptor::CURRENT;
+
 #[no_mangle]
 pub fn deno_plugin_init(interface: &mut dyn Interface) {
   interface.register_op("testSync", op_test_sync);
@@ -63,7 +63,8 @@ def eslint():
     source_files = get_sources(root_path, [
         "*.js", "*.ts", ":!:cli/tests/swc_syntax_error.ts",
         ":!:std/**/testdata/*", ":!:std/**/node_modules/*",
-        ":!:cli/compilers/wasm_wrap.js", ":!:cli/tests/error_syntax.js"
+        ":!:cli/compilers/wasm_wrap.js", ":!:cli/tests/error_syntax.js",
+        ":!:cli/tests/lint/*"
     ])
     if source_files:
         print_command("eslint", source_files)